@@ -11,4 +11,6 @@ NERModel = perceptron.NERModel
 NERTrainer = perceptron.NERTrainer
 POSModel = perceptron.POSModel
 POSTrainer = perceptron.POSTrainer
+Pipeline = perceptron.Pipeline
+Sentence = perceptron.Sentence
 Trainer = perceptron.Trainer
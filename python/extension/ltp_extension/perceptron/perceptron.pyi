@@ -2,9 +2,10 @@
 class Algorithm:
     """
     The perceptron algorithm.
-    algorithm support "AP", "Pa", "PaI", "PaII"
+    algorithm support "AP", "Pa", "PaI", "PaII", "Hogwild"
     AP: average perceptron, param is the threads
     PA: parallel average perceptron, param is c(margin)
+    Hogwild: lock-free parallel perceptron, param is the threads, results are not reproducible
     """
 
     def __init__(self, algorithm, param=None):
@@ -204,6 +205,19 @@ class NERModel:
         Predict batched sentences
         """
         pass
+    def batch_predict_indices(self, batch_words, batch_pos, parallelism=True):
+        """
+        Predict batched sentences, return the label ids as int64 arrays `(labels, offsets)`,
+        the tags of the i-th sentence are `labels[offsets[i]:offsets[i + 1]]`.
+        Both arrays support the buffer protocol, e.g. `numpy.asarray(labels)` does not copy.
+        """
+        pass
+    @property
+    def labels(self):
+        """
+        The labels of the model, indexed by the ids from `batch_predict_indices`
+        """
+        pass
     @staticmethod
     def load(path):
         """
@@ -301,6 +315,19 @@ class POSModel:
         Predict batched sentences
         """
         pass
+    def batch_predict_indices(self, batch_words, parallelism=True):
+        """
+        Predict batched sentences, return the label ids as int64 arrays `(labels, offsets)`,
+        the tags of the i-th sentence are `labels[offsets[i]:offsets[i + 1]]`.
+        Both arrays support the buffer protocol, e.g. `numpy.asarray(labels)` does not copy.
+        """
+        pass
+    @property
+    def labels(self):
+        """
+        The labels of the model, indexed by the ids from `batch_predict_indices`
+        """
+        pass
     @staticmethod
     def load(path):
         """
@@ -390,6 +417,60 @@ class POSTrainer:
         """
         pass
 
+class Pipeline:
+    """
+    cws -> pos -> ner pipeline, the models are copied into the pipeline.
+    The GIL is released while processing.
+    """
+
+    def __init__(self, cws, pos=None, ner=None):
+        pass
+    def batch_process(self, batch_text):
+        """
+        Process batched sentences in parallel
+        """
+        pass
+    def process(self, text):
+        """
+        Process a sentence
+        """
+        pass
+    def process_document(self, text):
+        """
+        Split a document into sentences and process them
+        """
+        pass
+
+class Sentence:
+    """
+    The result of a pipeline, `pos` and `ner` are None when the stage is absent.
+    """
+
+    @property
+    def ner(self):
+        """
+        The NER tags, one per word
+        """
+        pass
+    @property
+    def pos(self):
+        """
+        The POS tags, one per word
+        """
+        pass
+    @property
+    def text(self):
+        """
+        The input text
+        """
+        pass
+    @property
+    def words(self):
+        """
+        The words of the sentence
+        """
+        pass
+
 class Trainer:
     def __init__(self, model_type=ModelType.Auto, labels=None):
         pass
@@ -422,6 +503,12 @@ class Trainer:
         Get the value of the epoch parameter.
         """
         pass
+    @staticmethod
+    def from_config(path):
+        """
+        Create a Trainer from a TOML config file, with corpus already loaded
+        """
+        pass
     def load_eval_data(self, path):
         """
         Load Eval Data from a path
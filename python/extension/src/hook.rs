@@ -1,3 +1,4 @@
+use ltp::dict::Dictionary;
 use ltp::hook::Hook;
 use pyo3::prelude::*;
 
@@ -25,6 +26,13 @@ impl PyHook {
         self.hook.add_word(word, freq)
     }
 
+    /// load a jieba/ICTCLAS style dictionary, one `word [freq] [pos]` per line
+    #[pyo3(text_signature = "(self, path)")]
+    pub fn load_dict(&mut self, path: &str) -> PyResult<()> {
+        Dictionary::from_file(path)?.extend_hook(&mut self.hook);
+        Ok(())
+    }
+
     /// hook to the new words
     #[pyo3(text_signature = "(self, sentence, words)")]
     pub fn hook<'a>(&self, sentence: &'a str, words: Vec<&str>) -> PyResult<Vec<&'a str>> {
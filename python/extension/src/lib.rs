@@ -16,7 +16,7 @@ pub use algorithms::{py_eisner, py_get_entities, py_viterbi_decode_postprocess};
 use hook::PyHook;
 pub use perceptron::{
     CharacterType, PyAlgorithm, PyCWSModel, PyCWSTrainer, PyNERModel, PyNERTrainer, PyPOSModel,
-    PyPOSTrainer,
+    PyPOSTrainer, PyPipeline, PySentence,
 };
 use pyo3::prelude::*;
 use stnsplit::StnSplit;
@@ -84,6 +84,9 @@ fn ltp_extension(py: Python, m: &PyModule) -> PyResult<()> {
     perceptron.add_class::<PyNERModel>()?;
     perceptron.add_class::<PyNERTrainer>()?;
 
+    perceptron.add_class::<PyPipeline>()?;
+    perceptron.add_class::<PySentence>()?;
+
     m.add_submodule(algorithms)?;
     m.add_submodule(perceptron)?;
     Ok(())
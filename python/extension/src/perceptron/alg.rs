@@ -5,9 +5,10 @@ use serde::{Deserialize, Serialize};
 use std::fmt::{Display, Formatter};
 
 /// The perceptron algorithm.
-/// algorithm support "AP", "Pa", "PaI", "PaII"
+/// algorithm support "AP", "Pa", "PaI", "PaII", "Hogwild"
 /// AP: average perceptron, param is the threads
 /// PA: parallel average perceptron, param is c(margin)
+/// Hogwild: lock-free parallel perceptron, param is the threads, results are not reproducible
 #[pyclass(module = "ltp_extension.perceptron", name = "Algorithm", subclass)]
 #[derive(Clone, Serialize, Deserialize, Default, Debug, PartialEq)]
 pub struct PyAlgorithm {
@@ -34,6 +35,14 @@ impl PyAlgorithm {
                     Ok(Algorithm::AP(1usize))
                 }
             }
+            "Hogwild" => {
+                if let Some(param) = param {
+                    let param = param.extract::<usize>(py)?;
+                    Ok(Algorithm::Hogwild(param))
+                } else {
+                    Ok(Algorithm::Hogwild(1usize))
+                }
+            }
             "Pa" => Ok(Algorithm::PA(PaMode::Pa)),
             "PaI" => {
                 if let Some(c) = param {
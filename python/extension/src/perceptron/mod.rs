@@ -1,12 +1,14 @@
 mod alg;
 mod com;
 mod model;
+mod pipeline;
 mod specialization;
 mod trainer;
 
 pub type Perceptron<T> = ltp::perceptron::SerdeModel<T, f64>;
 pub use alg::PyAlgorithm;
 pub use model::{EnumModel, ModelType, PyModel};
+pub use pipeline::{PyPipeline, PySentence};
 pub use specialization::{
     CharacterType, PyCWSModel, PyCWSTrainer, PyNERModel, PyNERTrainer, PyPOSModel, PyPOSTrainer,
 };
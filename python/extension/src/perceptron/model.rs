@@ -87,13 +87,23 @@ impl PyModel {
     #[staticmethod]
     #[pyo3(text_signature = "(path, model_type=ModelType.Auto)")]
     pub fn load(path: &str, model_type: ModelType) -> PyResult<Self> {
-        let file = std::fs::File::open(path)?;
+        let mut file = std::io::BufReader::new(std::fs::File::open(path)?);
         let format = if path.ends_with(".json") {
             ltp::perceptron::Format::JSON
         } else {
             ltp::perceptron::Format::AVRO(ltp::perceptron::Codec::Deflate)
         };
 
+        // 带文件头的模型可以直接得到任务类型
+        let model_type = match (model_type, ltp::perceptron::ModelHeader::peek(&mut file)?) {
+            (ModelType::Auto, Some(header)) => match header.task {
+                ltp::perceptron::ModelTask::CWS => ModelType::CWS,
+                ltp::perceptron::ModelTask::POS => ModelType::POS,
+                ltp::perceptron::ModelTask::NER => ModelType::NER,
+            },
+            (model_type, _) => model_type,
+        };
+
         let model = match (model_type, format) {
             (ModelType::CWS, format) => ModelSerde::load(file, format).map(EnumModel::CWS)?,
             (ModelType::POS, format) => ModelSerde::load(file, format).map(EnumModel::POS)?,
@@ -0,0 +1,133 @@
+use crate::perceptron::{PyCWSModel, PyNERModel, PyPOSModel};
+use ltp::pipeline::Sentence;
+use ltp::LtpPipeline;
+use pyo3::prelude::*;
+use pyo3::types::{PyList, PyTuple};
+use std::sync::Arc;
+
+/// The result of a pipeline, `pos` and `ner` are None when the stage is absent.
+#[pyclass(module = "ltp_extension.perceptron", name = "Sentence")]
+#[derive(Clone, Debug)]
+pub struct PySentence {
+    /// The input text
+    #[pyo3(get)]
+    pub text: String,
+    /// The words of the sentence
+    #[pyo3(get)]
+    pub words: Vec<String>,
+    /// The POS tags, one per word
+    #[pyo3(get)]
+    pub pos: Option<Vec<String>>,
+    /// The NER tags, one per word
+    #[pyo3(get)]
+    pub ner: Option<Vec<String>>,
+}
+
+impl From<Sentence> for PySentence {
+    fn from(sentence: Sentence) -> Self {
+        let labels = |labels: Option<ltp::InternedLabels>| {
+            labels.map(|labels| labels.iter().map(String::from).collect())
+        };
+        Self {
+            pos: labels(sentence.pos),
+            ner: labels(sentence.ner),
+            text: sentence.text,
+            words: sentence.words,
+        }
+    }
+}
+
+#[pymethods]
+impl PySentence {
+    fn __repr__(&self) -> String {
+        let labels = |labels: &Option<Vec<String>>| match labels {
+            Some(labels) => format!("{:?}", labels),
+            None => "None".to_string(),
+        };
+        format!(
+            "Sentence(words={:?}, pos={}, ner={})",
+            self.words,
+            labels(&self.pos),
+            labels(&self.ner)
+        )
+    }
+}
+
+/// cws -> pos -> ner pipeline, the models are copied into the pipeline.
+/// The GIL is released while processing.
+#[pyclass(module = "ltp_extension.perceptron", name = "Pipeline")]
+#[derive(Clone, Debug)]
+pub struct PyPipeline {
+    pipeline: Arc<LtpPipeline>,
+}
+
+#[pymethods]
+impl PyPipeline {
+    #[new]
+    #[pyo3(text_signature = "(self, cws, pos=None, ner=None)", signature = (cws, pos = None, ner = None))]
+    pub fn new(
+        cws: &PyCWSModel,
+        pos: Option<&PyPOSModel>,
+        ner: Option<&PyNERModel>,
+    ) -> PyResult<Self> {
+        let mut pipeline = LtpPipeline::new().with_cws(cws.model.clone());
+        if let Some(pos) = pos {
+            pipeline = pipeline.with_pos(pos.model.clone());
+        }
+        if let Some(ner) = ner {
+            pipeline = pipeline.with_ner(ner.model.clone());
+        }
+        pipeline.validate()?;
+        Ok(Self {
+            pipeline: Arc::new(pipeline),
+        })
+    }
+
+    #[pyo3(signature = ( * args))]
+    pub fn __call__(&self, py: Python, args: &PyTuple) -> PyResult<PyObject> {
+        let first = args.get_item(0)?;
+        match first.get_type().name()? {
+            "str" => Ok(self.process(py, first.extract()?)?.into_py(py)),
+            "list" => self.batch_process(py, first.extract()?),
+            name => Err(pyo3::exceptions::PyValueError::new_err(format!(
+                "type \"{}\" has not been supported",
+                name
+            ))),
+        }
+    }
+
+    /// Process a sentence
+    #[pyo3(text_signature = "(self, text)")]
+    pub fn process(&self, py: Python, text: &str) -> PyResult<PySentence> {
+        let sentence = py.allow_threads(|| self.pipeline.process(text))?;
+        Ok(sentence.into())
+    }
+
+    /// Process batched sentences in parallel
+    #[pyo3(text_signature = "(self, batch_text)")]
+    pub fn batch_process(&self, py: Python, batch_text: Vec<&str>) -> PyResult<PyObject> {
+        let sentences = py.allow_threads(|| self.pipeline.process_batch(&batch_text))?;
+        Ok(sentences_to_list(py, sentences))
+    }
+
+    /// Split a document into sentences and process them
+    #[pyo3(text_signature = "(self, text)")]
+    pub fn process_document(&self, py: Python, text: &str) -> PyResult<PyObject> {
+        let sentences = py.allow_threads(|| self.pipeline.process_document(text))?;
+        Ok(sentences_to_list(py, sentences))
+    }
+
+    fn __repr__(&self) -> String {
+        format!("Pipeline({:?})", self.pipeline)
+    }
+}
+
+fn sentences_to_list(py: Python, sentences: Vec<Sentence>) -> PyObject {
+    PyList::new(
+        py,
+        sentences
+            .into_iter()
+            .map(|sentence| PySentence::from(sentence).into_py(py)),
+    )
+    .into()
+}
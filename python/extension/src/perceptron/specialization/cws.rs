@@ -146,14 +146,8 @@ impl PyCWSModel {
     /// Predict a sentence
     #[pyo3(text_signature = "(self, text)")]
     pub fn predict(&self, py: Python, text: &str) -> PyResult<PyObject> {
-        Ok(PyList::new(
-            py,
-            self.model
-                .predict(text)?
-                .into_iter()
-                .map(|s| PyString::new(py, s)),
-        )
-            .into())
+        let result = py.allow_threads(|| self.model.predict(text))?;
+        Ok(PyList::new(py, result.into_iter().map(|s| PyString::new(py, s))).into())
     }
 
     /// Predict batched sentences
@@ -164,10 +158,12 @@ impl PyCWSModel {
         batch_text: Vec<&str>,
         parallelism: bool,
     ) -> PyResult<PyObject> {
-        let result: Result<Vec<Vec<_>>, _> = batch_text
-            .into_maybe_par_iter_cond(parallelism)
-            .map(|text| self.model.predict(text))
-            .collect();
+        let result: Result<Vec<Vec<_>>, _> = py.allow_threads(|| {
+            batch_text
+                .into_maybe_par_iter_cond(parallelism)
+                .map(|text| self.model.predict(text))
+                .collect()
+        });
         let result = result?;
         let res = PyList::new(py, Vec::<&PyList>::with_capacity(result.len()));
         for snt in result {
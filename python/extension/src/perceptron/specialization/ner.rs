@@ -1,7 +1,8 @@
 use crate::impl_model;
 use crate::perceptron::{Perceptron, PyAlgorithm};
+use crate::utils::array::to_flat_arrays;
 use crate::utils::parallelism::MaybeParallelIterator;
-use ltp::perceptron::{NERDefinition as Definition, Trainer};
+use ltp::perceptron::{Definition as _, NERDefinition as Definition, Trainer};
 use pyo3::prelude::*;
 use pyo3::types::{PyList, PyString, PyTuple};
 use serde::{Deserialize, Serialize};
@@ -64,14 +65,8 @@ impl PyNERModel {
     /// Predict a sentence
     #[pyo3(text_signature = "(self, words, pos)")]
     pub fn predict(&self, py: Python, words: Vec<&str>, pos: Vec<&str>) -> PyResult<PyObject> {
-        Ok(PyList::new(
-            py,
-            self.model
-                .predict((&words, &pos))?
-                .into_iter()
-                .map(|s| PyString::new(py, s)),
-        )
-            .into())
+        let result = py.allow_threads(|| self.model.predict((&words, &pos)))?;
+        Ok(PyList::new(py, result.into_iter().map(|s| PyString::new(py, s))).into())
     }
 
     /// Predict batched sentences
@@ -83,11 +78,13 @@ impl PyNERModel {
         batch_pos: Vec<Vec<&str>>,
         parallelism: bool,
     ) -> PyResult<PyObject> {
-        let result: Result<Vec<Vec<_>>, _> = batch_words
-            .into_maybe_par_iter_cond(parallelism)
-            .zip(batch_pos)
-            .map(|(words, pos)| self.model.predict((&words, &pos)))
-            .collect();
+        let result: Result<Vec<Vec<_>>, _> = py.allow_threads(|| {
+            batch_words
+                .into_maybe_par_iter_cond(parallelism)
+                .zip(batch_pos)
+                .map(|(words, pos)| self.model.predict((&words, &pos)))
+                .collect()
+        });
         let result = result?;
         let res = PyList::new(py, Vec::<&PyList>::with_capacity(result.len()));
         for snt in result {
@@ -100,6 +97,39 @@ impl PyNERModel {
         Ok(res.into())
     }
 
+    /// Predict batched sentences, return the label ids as int64 arrays `(labels, offsets)`,
+    /// the tags of the i-th sentence are `labels[offsets[i]:offsets[i + 1]]`.
+    /// Both arrays support the buffer protocol, e.g. `numpy.asarray(labels)` does not copy.
+    #[pyo3(text_signature = "(self, batch_words, batch_pos, parallelism=True)", signature = (batch_words, batch_pos, parallelism = true))]
+    pub fn batch_predict_indices(
+        &self,
+        py: Python,
+        batch_words: Vec<Vec<&str>>,
+        batch_pos: Vec<Vec<&str>>,
+        parallelism: bool,
+    ) -> PyResult<(PyObject, PyObject)> {
+        let result: Result<Vec<Vec<usize>>, _> = py.allow_threads(|| {
+            batch_words
+                .into_maybe_par_iter_cond(parallelism)
+                .zip(batch_pos)
+                .map(|(words, pos)| {
+                    let mut buffer = Vec::new();
+                    self.model.predict_indices_with_buffer(
+                        &(words.as_slice(), pos.as_slice()),
+                        &mut buffer,
+                    )
+                })
+                .collect()
+        });
+        to_flat_arrays(py, &result?)
+    }
+
+    /// The labels of the model, indexed by the ids from `batch_predict_indices`
+    #[getter]
+    pub fn labels(&self) -> Vec<String> {
+        self.model.definition.labels()
+    }
+
     /// Load Model from a path
     #[staticmethod]
     #[pyo3(text_signature = "(path)")]
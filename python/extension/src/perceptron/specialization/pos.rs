@@ -1,7 +1,8 @@
 use crate::impl_model;
 use crate::perceptron::{Perceptron, PyAlgorithm};
+use crate::utils::array::to_flat_arrays;
 use crate::utils::parallelism::MaybeParallelIterator;
-use ltp::perceptron::{POSDefinition as Definition, Trainer};
+use ltp::perceptron::{Definition as _, POSDefinition as Definition, Trainer};
 use pyo3::prelude::*;
 use pyo3::types::{PyList, PyString, PyTuple};
 use serde::{Deserialize, Serialize};
@@ -55,14 +56,8 @@ impl PyPOSModel {
     /// Predict a sentence
     #[pyo3(text_signature = "(self, words)")]
     pub fn predict(&self, py: Python, words: Vec<&str>) -> PyResult<PyObject> {
-        Ok(PyList::new(
-            py,
-            self.model
-                .predict(&words)?
-                .into_iter()
-                .map(|s| PyString::new(py, s)),
-        )
-            .into())
+        let result = py.allow_threads(|| self.model.predict(&words))?;
+        Ok(PyList::new(py, result.into_iter().map(|s| PyString::new(py, s))).into())
     }
 
     /// Predict batched sentences
@@ -73,10 +68,12 @@ impl PyPOSModel {
         batch_words: Vec<Vec<&str>>,
         parallelism: bool,
     ) -> PyResult<PyObject> {
-        let result: Result<Vec<Vec<_>>, _> = batch_words
-            .into_maybe_par_iter_cond(parallelism)
-            .map(|text| self.model.predict(&text))
-            .collect();
+        let result: Result<Vec<Vec<_>>, _> = py.allow_threads(|| {
+            batch_words
+                .into_maybe_par_iter_cond(parallelism)
+                .map(|text| self.model.predict(&text))
+                .collect()
+        });
         let result = result?;
         let res = PyList::new(py, Vec::<&PyList>::with_capacity(result.len()));
         for snt in result {
@@ -89,6 +86,35 @@ impl PyPOSModel {
         Ok(res.into())
     }
 
+    /// Predict batched sentences, return the label ids as int64 arrays `(labels, offsets)`,
+    /// the tags of the i-th sentence are `labels[offsets[i]:offsets[i + 1]]`.
+    /// Both arrays support the buffer protocol, e.g. `numpy.asarray(labels)` does not copy.
+    #[pyo3(text_signature = "(self, batch_words, parallelism=True)", signature = (batch_words, parallelism = true))]
+    pub fn batch_predict_indices(
+        &self,
+        py: Python,
+        batch_words: Vec<Vec<&str>>,
+        parallelism: bool,
+    ) -> PyResult<(PyObject, PyObject)> {
+        let result: Result<Vec<Vec<usize>>, _> = py.allow_threads(|| {
+            batch_words
+                .into_maybe_par_iter_cond(parallelism)
+                .map(|words| {
+                    let mut buffer = Vec::new();
+                    self.model
+                        .predict_indices_with_buffer(&words.as_slice(), &mut buffer)
+                })
+                .collect()
+        });
+        to_flat_arrays(py, &result?)
+    }
+
+    /// The labels of the model, indexed by the ids from `batch_predict_indices`
+    #[getter]
+    pub fn labels(&self) -> Vec<String> {
+        self.model.definition.labels()
+    }
+
     /// Load Model from a path
     #[staticmethod]
     #[pyo3(text_signature = "(path)")]
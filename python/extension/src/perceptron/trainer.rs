@@ -1,5 +1,6 @@
 use crate::perceptron::model::{EnumModel, ModelType, PyModel};
 use crate::perceptron::PyAlgorithm;
+use ltp::perceptron::{ModelTask, TrainConfig};
 use ltp::{CWSDefinition, NERDefinition, POSDefinition, Trainer};
 use pyo3::prelude::*;
 use serde::{Deserialize, Serialize};
@@ -58,6 +59,19 @@ impl PyTrainer {
         Ok(Self { trainer })
     }
 
+    /// Create a Trainer from a TOML config file, with corpus already loaded
+    #[staticmethod]
+    #[pyo3(text_signature = "(path)")]
+    pub fn from_config(path: &str) -> PyResult<Self> {
+        let config = TrainConfig::from_file(path)?;
+        let trainer = match config.task {
+            ModelTask::CWS => EnumTrainer::CWS(config.trainer()?),
+            ModelTask::POS => EnumTrainer::POS(config.trainer()?),
+            ModelTask::NER => EnumTrainer::NER(config.trainer()?),
+        };
+        Ok(Self { trainer })
+    }
+
     /// Get the value of the epoch parameter.
     #[getter]
     pub fn get_epoch(&self) -> PyResult<usize> {
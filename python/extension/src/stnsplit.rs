@@ -22,6 +22,7 @@ impl StnSplit {
                 bracket_as_entity: true,
                 zh_quote_as_entity: true,
                 en_quote_as_entity: true,
+                ..Default::default()
             },
         })
     }
@@ -0,0 +1,24 @@
+use pyo3::prelude::*;
+use pyo3::types::PyBytes;
+
+/// 转为 `array.array("q")`，支持 buffer protocol，`numpy.asarray` 可以直接零拷贝使用
+pub fn to_int_array(py: Python, values: &[i64]) -> PyResult<PyObject> {
+    let bytes: Vec<u8> = values.iter().flat_map(|v| v.to_ne_bytes()).collect();
+    let array = py
+        .import("array")?
+        .getattr("array")?
+        .call1(("q", PyBytes::new(py, &bytes)))?;
+    Ok(array.into())
+}
+
+/// 将一批变长序列平铺为 `(values, offsets)`，第 i 个序列为 `values[offsets[i]:offsets[i + 1]]`
+pub fn to_flat_arrays(py: Python, batch: &[Vec<usize>]) -> PyResult<(PyObject, PyObject)> {
+    let mut offsets = Vec::with_capacity(batch.len() + 1);
+    offsets.push(0);
+    let mut values = Vec::with_capacity(batch.iter().map(|s| s.len()).sum());
+    for sequence in batch {
+        values.extend(sequence.iter().map(|&v| v as i64));
+        offsets.push(values.len() as i64);
+    }
+    Ok((to_int_array(py, &values)?, to_int_array(py, &offsets)?))
+}
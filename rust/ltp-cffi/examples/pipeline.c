@@ -0,0 +1,58 @@
+#include <string.h>
+#include <stdio.h>
+#include <assert.h>
+#include "ltp.h"
+
+static void print_strings(const char *name, LtpStrings strings) {
+  if (strings.items == NULL) {
+    return;
+  }
+  printf("%s:", name);
+  for (size_t i = 0; i < strings.len; i++) {
+    printf(" %.*s", (int) strings.items[i].len, (const char *) strings.items[i].ptr);
+  }
+  printf("\n");
+}
+
+int main() {
+  const char *cws_model_path = "data/legacy-models/cws_model.bin";
+  const char *pos_model_path = "data/legacy-models/pos_model.bin";
+  const char *ner_model_path = "data/legacy-models/ner_model.bin";
+
+  Pipeline *pipeline = pipeline_load((const uint8_t *) cws_model_path,
+                                     (const uint8_t *) pos_model_path,
+                                     (const uint8_t *) ner_model_path);
+  if (pipeline == NULL) {
+    fprintf(stderr, "failed to load pipeline: %s\n", last_error());
+    return 1;
+  }
+
+  // 单句
+  const char *sentence = "他叫汤姆去拿外衣。";
+  LtpSentence *result = pipeline_process(pipeline, (const uint8_t *) sentence, strlen(sentence));
+  assert(result != NULL);
+  print_strings("words", result->words);
+  print_strings("pos", result->pos);
+  print_strings("ner", result->ner);
+  sentence_release(&result);
+  assert(result == NULL);
+
+  // 整篇文档，先分句
+  const char *document = "他叫汤姆去拿外衣。我们去拿外衣！";
+  LtpDocument *sentences =
+      pipeline_process_document(pipeline, (const uint8_t *) document, strlen(document));
+  assert(sentences != NULL);
+  for (size_t i = 0; i < sentences->len; i++) {
+    print_strings("words", sentences->sentences[i].words);
+  }
+  document_release(&sentences);
+
+  // 非法的 UTF-8 输入
+  const uint8_t invalid[] = {0xff, 0xfe};
+  assert(pipeline_process(pipeline, invalid, sizeof(invalid)) == NULL);
+  printf("error: %s\n", last_error());
+
+  pipeline_release(&pipeline);
+  assert(pipeline == NULL);
+  return 0;
+}
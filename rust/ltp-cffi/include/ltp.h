@@ -0,0 +1,252 @@
+#ifndef LTP_BINDINGS_DEFINE
+#define LTP_BINDINGS_DEFINE
+
+#pragma once
+
+/* Warning, this file is autogenerated by cbindgen. Don't modify this manually. */
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+
+/**
+ * An opaque model handle. A loaded model is read-only, so one handle can be shared by any
+ * number of threads, as long as it is not released while a call is still running.
+ */
+typedef struct Model Model;
+
+/**
+ * An opaque pipeline handle (cws -> pos -> ner). Like `Model` it is read-only after loading
+ * and can be shared by any number of threads.
+ */
+typedef struct Pipeline Pipeline;
+
+/**
+ * A UTF-8 string view, not NUL terminated.
+ */
+typedef struct LtpStr {
+  const uint8_t *ptr;
+  size_t len;
+} LtpStr;
+
+/**
+ * A list of strings, `items` is NULL when the annotation is not available.
+ */
+typedef struct LtpStrings {
+  const struct LtpStr *items;
+  size_t len;
+} LtpStrings;
+
+/**
+ * The annotations of one sentence, `pos` and `ner` hold one tag per word.
+ */
+typedef struct LtpSentence {
+  struct LtpStr text;
+  struct LtpStrings words;
+  struct LtpStrings pos;
+  struct LtpStrings ner;
+} LtpSentence;
+
+/**
+ * The sentences of a document or a batch.
+ */
+typedef struct LtpDocument {
+  const struct LtpSentence *sentences;
+  size_t len;
+} LtpDocument;
+
+/**
+ * Receive the predicted tags of a batch, one call per tag.
+ * the call args:
+ *    state: your design
+ *    tag: the predicted tag
+ *    tag_len: the length of tag
+ *    tag_index: the index of current predict
+ *    tag_total: the length of current predict
+ *    batch_index: the predict index of current batch
+ *    batch_total: the batch size of current batch
+ */
+typedef struct BatchCallback {
+  void *state;
+  void (*call)(void*, const uint8_t*, size_t, size_t, size_t, size_t, size_t);
+} BatchCallback;
+
+/**
+ * Receive the predicted tags of one sentence, one call per tag.
+ * the call args:
+ *    state: your design
+ *    tag: the predicted tag
+ *    tag_len: the length of tag
+ *    tag_index: the index of current predict
+ *    tag_total: the length of current predict
+ */
+typedef struct Callback {
+  void *state;
+  void (*call)(void*, const uint8_t*, size_t, size_t, size_t);
+} Callback;
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+/**
+ * Free a result returned by `pipeline_process_document`/`pipeline_process_batch`
+ * and set `*document` to NULL.
+ */
+void document_release(struct LtpDocument **document);
+
+/**
+ * Return the message of the last failed call on the current thread, or NULL if it succeeded.
+ * The string is owned by the library and valid until the next LTP call on the same thread.
+ */
+const char *last_error(void);
+
+size_t model_cws_batch_predict(const struct Model *model,
+                               const uint8_t *const *sentences,
+                               const size_t *sentences_len,
+                               size_t sentences_len_len,
+                               struct BatchCallback callback,
+                               size_t threads);
+
+size_t model_cws_predict(const struct Model *model,
+                         const uint8_t *sentence,
+                         size_t sentence_len,
+                         struct Callback callback);
+
+/**
+ * Segment a UTF-8 sentence. Return NULL on failure, see `last_error`.
+ * The result is owned by the caller and must be freed with `strings_release`.
+ */
+struct LtpStrings *model_cws_split(const struct Model *model,
+                                   const uint8_t *sentence,
+                                   size_t sentence_len);
+
+struct Model *model_load(const uint8_t *model_path);
+
+struct Model *model_load_s(const uint8_t *model_path, size_t model_path_len);
+
+size_t model_ner_batch_predict(const struct Model *model,
+                               const uint8_t *const *const *batch_words,
+                               const size_t *const *batch_words_len,
+                               const uint8_t *const *const *batch_pos,
+                               const size_t *const *batch_pos_len,
+                               const size_t *batch_words_len_len,
+                               size_t batch_words_len_len_len,
+                               struct BatchCallback callback,
+                               size_t threads);
+
+size_t model_ner_predict(const struct Model *model,
+                         const uint8_t *const *words,
+                         const size_t *words_len,
+                         const uint8_t *const *pos,
+                         const size_t *pos_len,
+                         size_t words_len_len,
+                         struct Callback callback);
+
+/**
+ * Recognize the named entities of one sentence, `words` and `pos` have `words_len` items.
+ * Return NULL on failure, see `last_error`.
+ * The result is owned by the caller and must be freed with `strings_release`.
+ */
+struct LtpStrings *model_ner_tag(const struct Model *model,
+                                 const struct LtpStr *words,
+                                 const struct LtpStr *pos,
+                                 size_t words_len);
+
+size_t model_pos_batch_predict(const struct Model *model,
+                               const uint8_t *const *const *batch_words,
+                               const size_t *const *batch_words_len,
+                               const size_t *batch_words_len_len,
+                               size_t batch_words_len_len_len,
+                               struct BatchCallback callback,
+                               size_t threads);
+
+size_t model_pos_predict(const struct Model *model,
+                         const uint8_t *const *words,
+                         const size_t *words_len,
+                         size_t words_len_len,
+                         struct Callback callback);
+
+/**
+ * Tag the words of one sentence. Return NULL on failure, see `last_error`.
+ * The result is owned by the caller and must be freed with `strings_release`.
+ */
+struct LtpStrings *model_pos_tag(const struct Model *model,
+                                 const struct LtpStr *words,
+                                 size_t words_len);
+
+void model_release(struct Model **model);
+
+bool model_save(const struct Model *model, const uint8_t *model_path);
+
+bool model_save_s(const struct Model *model, const uint8_t *model_path, size_t model_path_len);
+
+/**
+ * Load a pipeline from NUL terminated model paths. `pos_path` and `ner_path` may be NULL
+ * to skip the stage, a stage requires all the stages before it.
+ * Return NULL on failure, see `last_error`.
+ */
+struct Pipeline *pipeline_load(const uint8_t *cws_path,
+                               const uint8_t *pos_path,
+                               const uint8_t *ner_path);
+
+/**
+ * Run the pipeline on one UTF-8 sentence. Return NULL on failure, see `last_error`.
+ * The result is owned by the caller and must be freed with `sentence_release`.
+ */
+struct LtpSentence *pipeline_process(const struct Pipeline *pipeline,
+                                     const uint8_t *text,
+                                     size_t text_len);
+
+/**
+ * Run the pipeline on `texts_len` UTF-8 sentences in parallel.
+ * Return NULL on failure, see `last_error`.
+ * The result is owned by the caller and must be freed with `document_release`.
+ */
+struct LtpDocument *pipeline_process_batch(const struct Pipeline *pipeline,
+                                           const struct LtpStr *texts,
+                                           size_t texts_len);
+
+/**
+ * Split a UTF-8 document into sentences and run the pipeline on each of them.
+ * Return NULL on failure, see `last_error`.
+ * The result is owned by the caller and must be freed with `document_release`.
+ */
+struct LtpDocument *pipeline_process_document(const struct Pipeline *pipeline,
+                                              const uint8_t *text,
+                                              size_t text_len);
+
+/**
+ * Free a pipeline and set `*pipeline` to NULL.
+ */
+void pipeline_release(struct Pipeline **pipeline);
+
+/**
+ * Free a result returned by `pipeline_process` and set `*sentence` to NULL.
+ */
+void sentence_release(struct LtpSentence **sentence);
+
+size_t stn_split(const uint8_t *text, size_t text_len, struct Callback callback);
+
+size_t stn_split_with_options(const uint8_t *text,
+                              size_t text_len,
+                              struct Callback callback,
+                              bool use_zh,
+                              bool use_en,
+                              bool bracket_as_entity,
+                              bool zh_quote_as_entity,
+                              bool en_quote_as_entity);
+
+/**
+ * Free a result returned by the `*_split`/`*_tag` functions and set `*strings` to NULL.
+ */
+void strings_release(struct LtpStrings **strings);
+
+#ifdef __cplusplus
+}  // extern "C"
+#endif  // __cplusplus
+
+#endif  /* LTP_BINDINGS_DEFINE */
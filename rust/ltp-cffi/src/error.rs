@@ -0,0 +1,39 @@
+use std::cell::RefCell;
+use std::ffi::{c_char, CString};
+use std::fmt::Display;
+use std::ptr;
+
+thread_local! {
+    static LAST_ERROR: RefCell<Option<CString>> = const { RefCell::new(None) };
+}
+
+pub(crate) fn set_last_error(err: impl Display) {
+    let message = CString::new(err.to_string().replace('\0', " ")).unwrap_or_default();
+    LAST_ERROR.with(|e| *e.borrow_mut() = Some(message));
+}
+
+pub(crate) fn clear_last_error() {
+    LAST_ERROR.with(|e| *e.borrow_mut() = None);
+}
+
+/// # Safety
+/// `ptr` 为 NULL 或指向一个有效的 `T`，`name` 只用于错误信息
+pub(crate) unsafe fn handle<'a, T>(ptr: *const T, name: &str) -> Option<&'a T> {
+    if ptr.is_null() {
+        set_last_error(format!("unexpected NULL {}", name));
+        return None;
+    }
+    Some(&*ptr)
+}
+
+/// Return the message of the last failed call on the current thread, or NULL if it succeeded.
+/// The string is owned by the library and valid until the next LTP call on the same thread.
+#[no_mangle]
+pub extern "C" fn last_error() -> *const c_char {
+    LAST_ERROR.with(|e| {
+        e.borrow()
+            .as_ref()
+            .map(|message| message.as_ptr())
+            .unwrap_or(ptr::null())
+    })
+}
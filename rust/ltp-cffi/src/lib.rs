@@ -7,10 +7,13 @@ static GLOBAL: MiMalloc = MiMalloc;
 
 use std::ffi::c_void;
 
+pub mod error;
 pub mod model;
+pub mod pipeline;
+pub mod result;
 pub mod stnsplit;
 
-/// The LTP CFFI API.
+/// Receive the predicted tags of one sentence, one call per tag.
 /// the call args:
 ///    state: your design
 ///    tag: the predicted tag
@@ -24,7 +27,7 @@ pub struct Callback {
     pub call: extern "C" fn(*mut c_void, *const u8, usize, usize, usize),
 }
 
-/// The LTP CFFI API.
+/// Receive the predicted tags of a batch, one call per tag.
 /// the call args:
 ///    state: your design
 ///    tag: the predicted tag
@@ -1,3 +1,5 @@
+use crate::error::{clear_last_error, handle, set_last_error};
+use crate::result::{into_strings, utf8, utf8_list, LtpStr, LtpStrings};
 use crate::{BatchCallback, Callback};
 use ltp::{CWSDefinition, NERDefinition, POSDefinition};
 use rayon::prelude::*;
@@ -13,54 +15,57 @@ pub enum EnumModel {
     NER(Perceptron<NERDefinition>),
 }
 
+/// An opaque model handle. A loaded model is read-only, so one handle can be shared by any
+/// number of threads, as long as it is not released while a call is still running.
 pub struct Model {
     model: EnumModel,
 }
 
-fn rs_model_load(model_path: &str) -> *mut Model {
-    use ltp::perceptron::{ModelSerde, Reader, Schema};
-    let file = match std::fs::File::open(model_path) {
-        Ok(file) => file,
-        Err(err) => {
-            println!("{}", err);
-            return std::ptr::null_mut();
-        }
-    };
+pub(crate) fn load_model(model_path: &str) -> Result<EnumModel, String> {
+    use ltp::perceptron::{Format, ModelHeader, ModelSerde, ModelTask, Reader, Schema};
+    use std::io::BufRead;
+    let file = std::fs::File::open(model_path).map_err(|err| err.to_string())?;
+    let mut file = std::io::BufReader::new(file);
+
+    if let Some(header) = ModelHeader::peek(&mut file).map_err(|err| err.to_string())? {
+        let format = Format::from(header.payload);
+        let model = match header.task {
+            ModelTask::CWS => ModelSerde::load(file, format).map(EnumModel::CWS),
+            ModelTask::POS => ModelSerde::load(file, format).map(EnumModel::POS),
+            ModelTask::NER => ModelSerde::load(file, format).map(EnumModel::NER),
+        };
+        return model.map_err(|err| err.to_string());
+    }
 
-    let reader = match Reader::new(file) {
-        Ok(reader) => reader,
-        Err(_) => {
-            println!("Not Correct Bin Format!");
-            return std::ptr::null_mut();
-        }
+    // 没有文件头时只能是旧的 avro 模型，无法确定格式的文件直接报错
+    let magic = file.fill_buf().map_err(|err| err.to_string())?;
+    if !magic.starts_with(b"Obj\x01") {
+        return Err(
+            "unknown model format, only models with a header or legacy avro models are supported"
+                .into(),
+        );
+    }
+    let reader = Reader::new(file).map_err(|err| err.to_string())?;
+    let model = match reader.writer_schema() {
+        Schema::Record { name, .. } => match name.name.as_str() {
+            "cws" => ModelSerde::load_avro(reader).map(EnumModel::CWS),
+            "pos" => ModelSerde::load_avro(reader).map(EnumModel::POS),
+            "ner" => ModelSerde::load_avro(reader).map(EnumModel::NER),
+            name => return Err(format!("unknown model schema `{}`", name)),
+        },
+        _ => return Err("unknown model schema".to_string()),
     };
+    model.map_err(|err| err.to_string())
+}
 
-    let model: Result<Box<Model>, _> = {
-        match reader.writer_schema() {
-            Schema::Record { name, .. } => match name.name.as_str() {
-                "cws" => ModelSerde::load_avro(reader)
-                    .map(EnumModel::CWS)
-                    .map(|model| Box::new(Model { model })),
-                "pos" => ModelSerde::load_avro(reader)
-                    .map(EnumModel::POS)
-                    .map(|model| Box::new(Model { model })),
-                "ner" => ModelSerde::load_avro(reader)
-                    .map(EnumModel::NER)
-                    .map(|model| Box::new(Model { model })),
-                _ => {
-                    return std::ptr::null_mut();
-                }
-            },
-            _ => {
-                return std::ptr::null_mut();
-            }
+fn rs_model_load(model_path: &str) -> *mut Model {
+    match load_model(model_path) {
+        Ok(model) => {
+            clear_last_error();
+            Box::into_raw(Box::new(Model { model }))
         }
-    };
-
-    match model {
-        Ok(model) => Box::into_raw(model),
         Err(err) => {
-            println!("{}", err);
+            set_last_error(err);
             std::ptr::null_mut()
         }
     }
@@ -84,40 +89,39 @@ pub extern "C" fn model_load_s(model_path: *const u8, model_path_len: usize) ->
 #[no_mangle]
 #[allow(clippy::not_unsafe_ptr_arg_deref)]
 pub extern "C" fn model_release(model: *mut *mut Model) {
+    if model.is_null() || unsafe { (*model).is_null() } {
+        return;
+    }
     let _ = unsafe { Box::from_raw(*model) };
     unsafe { *model = std::ptr::null_mut() };
 }
 
-pub extern "C" fn rs_model_save(
-    model: *const Model,
-    model_path: &str,
-) -> bool {
+fn rs_model_save(model: &Model, model_path: &str) -> bool {
     use ltp::perceptron::ModelSerde;
 
-    let file = match std::fs::File::open(model_path) {
-        Ok(file) => file,
-        Err(err) => {
-            println!("{}", err);
-            return false;
-        }
-    };
-
     let model_format = if model_path.ends_with(".json") {
         ltp::perceptron::Format::JSON
     } else {
         ltp::perceptron::Format::AVRO(ltp::perceptron::Codec::Deflate)
     };
 
-    match {
-        match unsafe { &(*model).model } {
-            EnumModel::CWS(ref model) => ModelSerde::save(model, file, model_format),
-            EnumModel::POS(ref model) => ModelSerde::save(model, file, model_format),
-            EnumModel::NER(ref model) => ModelSerde::save(model, file, model_format),
+    let result = std::fs::File::create(model_path)
+        .map_err(|err| err.to_string())
+        .and_then(|file| {
+            match &model.model {
+                EnumModel::CWS(ref model) => ModelSerde::save(model, file, model_format),
+                EnumModel::POS(ref model) => ModelSerde::save(model, file, model_format),
+                EnumModel::NER(ref model) => ModelSerde::save(model, file, model_format),
+            }
+            .map_err(|err| err.to_string())
+        });
+    match result {
+        Ok(_) => {
+            clear_last_error();
+            true
         }
-    } {
-        Ok(_) => true,
         Err(err) => {
-            println!("{}", err);
+            set_last_error(err);
             false
         }
     }
@@ -125,10 +129,10 @@ pub extern "C" fn rs_model_save(
 
 #[no_mangle]
 #[allow(clippy::not_unsafe_ptr_arg_deref)]
-pub extern "C" fn model_save(
-    model: *const Model,
-    model_path: *const u8,
-) -> bool {
+pub extern "C" fn model_save(model: *const Model, model_path: *const u8) -> bool {
+    let Some(model) = (unsafe { handle(model, "model") }) else {
+        return false;
+    };
     let model_path = unsafe { std::ffi::CStr::from_ptr(model_path as *const _) };
     rs_model_save(model, &model_path.to_string_lossy())
 }
@@ -140,12 +144,14 @@ pub extern "C" fn model_save_s(
     model_path: *const u8,
     model_path_len: usize,
 ) -> bool {
+    let Some(model) = (unsafe { handle(model, "model") }) else {
+        return false;
+    };
     let model_path =
         unsafe { str::from_utf8_unchecked(slice::from_raw_parts(model_path, model_path_len)) };
     rs_model_save(model, model_path)
 }
 
-
 #[no_mangle]
 #[allow(clippy::not_unsafe_ptr_arg_deref)]
 pub extern "C" fn model_cws_predict(
@@ -506,3 +512,88 @@ pub extern "C" fn model_ner_batch_predict(
         0
     }
 }
+
+fn wrong_task(expected: &str) -> *mut LtpStrings {
+    set_last_error(format!("the model is not a {} model", expected));
+    std::ptr::null_mut()
+}
+
+fn rs_strings<E: std::fmt::Display>(results: Result<Vec<&str>, E>) -> *mut LtpStrings {
+    match results {
+        Ok(results) => {
+            clear_last_error();
+            into_strings(results.into_iter().map(String::from).collect())
+        }
+        Err(err) => {
+            set_last_error(err);
+            std::ptr::null_mut()
+        }
+    }
+}
+
+/// Segment a UTF-8 sentence. Return NULL on failure, see `last_error`.
+/// The result is owned by the caller and must be freed with `strings_release`.
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn model_cws_split(
+    model: *const Model,
+    sentence: *const u8,
+    sentence_len: usize,
+) -> *mut LtpStrings {
+    let Some(model) = (unsafe { handle(model, "model") }) else {
+        return std::ptr::null_mut();
+    };
+    let Some(sentence) = (unsafe { utf8(sentence, sentence_len) }) else {
+        return std::ptr::null_mut();
+    };
+    match &model.model {
+        EnumModel::CWS(ref model) => rs_strings(model.predict(sentence)),
+        _ => wrong_task("cws"),
+    }
+}
+
+/// Tag the words of one sentence. Return NULL on failure, see `last_error`.
+/// The result is owned by the caller and must be freed with `strings_release`.
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn model_pos_tag(
+    model: *const Model,
+    words: *const LtpStr,
+    words_len: usize,
+) -> *mut LtpStrings {
+    let Some(model) = (unsafe { handle(model, "model") }) else {
+        return std::ptr::null_mut();
+    };
+    let Some(words) = (unsafe { utf8_list(words, words_len) }) else {
+        return std::ptr::null_mut();
+    };
+    match &model.model {
+        EnumModel::POS(ref model) => rs_strings(model.predict(&words)),
+        _ => wrong_task("pos"),
+    }
+}
+
+/// Recognize the named entities of one sentence, `words` and `pos` have `words_len` items.
+/// Return NULL on failure, see `last_error`.
+/// The result is owned by the caller and must be freed with `strings_release`.
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn model_ner_tag(
+    model: *const Model,
+    words: *const LtpStr,
+    pos: *const LtpStr,
+    words_len: usize,
+) -> *mut LtpStrings {
+    let Some(model) = (unsafe { handle(model, "model") }) else {
+        return std::ptr::null_mut();
+    };
+    let (Some(words), Some(pos)) = (unsafe { utf8_list(words, words_len) }, unsafe {
+        utf8_list(pos, words_len)
+    }) else {
+        return std::ptr::null_mut();
+    };
+    match &model.model {
+        EnumModel::NER(ref model) => rs_strings(model.predict((&words, &pos))),
+        _ => wrong_task("ner"),
+    }
+}
@@ -0,0 +1,141 @@
+use crate::error::{clear_last_error, handle, set_last_error};
+use crate::model::{load_model, EnumModel};
+use crate::result::{
+    into_document, into_sentence, utf8, utf8_list, LtpDocument, LtpSentence, LtpStr,
+};
+use ltp::LtpPipeline;
+use std::ffi::CStr;
+use std::ptr;
+
+/// An opaque pipeline handle (cws -> pos -> ner). Like `Model` it is read-only after loading
+/// and can be shared by any number of threads.
+pub struct Pipeline {
+    pipeline: LtpPipeline,
+}
+
+fn rs_pipeline_load(paths: [Option<&str>; 3]) -> Result<LtpPipeline, String> {
+    let mut pipeline = LtpPipeline::new();
+    for (expected, path) in ["cws", "pos", "ner"].into_iter().zip(paths) {
+        let Some(path) = path else { continue };
+        pipeline = match (expected, load_model(path)?) {
+            ("cws", EnumModel::CWS(model)) => pipeline.with_cws(model),
+            ("pos", EnumModel::POS(model)) => pipeline.with_pos(model),
+            ("ner", EnumModel::NER(model)) => pipeline.with_ner(model),
+            _ => return Err(format!("`{}` is not a {} model", path, expected)),
+        };
+    }
+    pipeline.validate().map_err(|err| err.to_string())?;
+    Ok(pipeline)
+}
+
+fn c_path(path: *const u8) -> Option<String> {
+    if path.is_null() {
+        None
+    } else {
+        let path = unsafe { CStr::from_ptr(path as *const _) };
+        Some(path.to_string_lossy().into_owned())
+    }
+}
+
+/// Load a pipeline from NUL terminated model paths. `pos_path` and `ner_path` may be NULL
+/// to skip the stage, a stage requires all the stages before it.
+/// Return NULL on failure, see `last_error`.
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn pipeline_load(
+    cws_path: *const u8,
+    pos_path: *const u8,
+    ner_path: *const u8,
+) -> *mut Pipeline {
+    let paths = [c_path(cws_path), c_path(pos_path), c_path(ner_path)];
+    match rs_pipeline_load(paths.each_ref().map(|p| p.as_deref())) {
+        Ok(pipeline) => {
+            clear_last_error();
+            Box::into_raw(Box::new(Pipeline { pipeline }))
+        }
+        Err(err) => {
+            set_last_error(err);
+            ptr::null_mut()
+        }
+    }
+}
+
+/// Free a pipeline and set `*pipeline` to NULL.
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn pipeline_release(pipeline: *mut *mut Pipeline) {
+    if pipeline.is_null() || unsafe { (*pipeline).is_null() } {
+        return;
+    }
+    let _ = unsafe { Box::from_raw(*pipeline) };
+    unsafe { *pipeline = ptr::null_mut() };
+}
+
+fn finish<T, R, E: std::fmt::Display>(result: Result<T, E>, into: fn(T) -> *mut R) -> *mut R {
+    match result {
+        Ok(result) => {
+            clear_last_error();
+            into(result)
+        }
+        Err(err) => {
+            set_last_error(err);
+            ptr::null_mut()
+        }
+    }
+}
+
+/// Run the pipeline on one UTF-8 sentence. Return NULL on failure, see `last_error`.
+/// The result is owned by the caller and must be freed with `sentence_release`.
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn pipeline_process(
+    pipeline: *const Pipeline,
+    text: *const u8,
+    text_len: usize,
+) -> *mut LtpSentence {
+    let Some(pipeline) = (unsafe { handle(pipeline, "pipeline") }) else {
+        return ptr::null_mut();
+    };
+    let Some(text) = (unsafe { utf8(text, text_len) }) else {
+        return ptr::null_mut();
+    };
+    finish(pipeline.pipeline.process(text), into_sentence)
+}
+
+/// Split a UTF-8 document into sentences and run the pipeline on each of them.
+/// Return NULL on failure, see `last_error`.
+/// The result is owned by the caller and must be freed with `document_release`.
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn pipeline_process_document(
+    pipeline: *const Pipeline,
+    text: *const u8,
+    text_len: usize,
+) -> *mut LtpDocument {
+    let Some(pipeline) = (unsafe { handle(pipeline, "pipeline") }) else {
+        return ptr::null_mut();
+    };
+    let Some(text) = (unsafe { utf8(text, text_len) }) else {
+        return ptr::null_mut();
+    };
+    finish(pipeline.pipeline.process_document(text), into_document)
+}
+
+/// Run the pipeline on `texts_len` UTF-8 sentences in parallel.
+/// Return NULL on failure, see `last_error`.
+/// The result is owned by the caller and must be freed with `document_release`.
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn pipeline_process_batch(
+    pipeline: *const Pipeline,
+    texts: *const LtpStr,
+    texts_len: usize,
+) -> *mut LtpDocument {
+    let Some(pipeline) = (unsafe { handle(pipeline, "pipeline") }) else {
+        return ptr::null_mut();
+    };
+    let Some(texts) = (unsafe { utf8_list(texts, texts_len) }) else {
+        return ptr::null_mut();
+    };
+    finish(pipeline.pipeline.process_batch(&texts), into_document)
+}
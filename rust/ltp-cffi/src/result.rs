@@ -0,0 +1,196 @@
+use crate::error::set_last_error;
+use ltp::Sentence;
+use std::{ptr, slice, str};
+
+/// A UTF-8 string view, not NUL terminated.
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct LtpStr {
+    pub ptr: *const u8,
+    pub len: usize,
+}
+
+/// A list of strings, `items` is NULL when the annotation is not available.
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct LtpStrings {
+    pub items: *const LtpStr,
+    pub len: usize,
+}
+
+/// The annotations of one sentence, `pos` and `ner` hold one tag per word.
+#[repr(C)]
+pub struct LtpSentence {
+    pub text: LtpStr,
+    pub words: LtpStrings,
+    pub pos: LtpStrings,
+    pub ner: LtpStrings,
+}
+
+/// The sentences of a document or a batch.
+#[repr(C)]
+pub struct LtpDocument {
+    pub sentences: *const LtpSentence,
+    pub len: usize,
+}
+
+impl LtpStrings {
+    const NONE: LtpStrings = LtpStrings {
+        items: ptr::null(),
+        len: 0,
+    };
+}
+
+/// 结果中所有字符串与视图的实际存储，随结果一起释放
+#[derive(Default)]
+struct Storage {
+    strings: Vec<String>,
+    views: Vec<Box<[LtpStr]>>,
+}
+
+impl Storage {
+    fn str(&mut self, text: String) -> LtpStr {
+        // String 移动时堆上的内容不会移动，视图保持有效
+        let view = LtpStr {
+            ptr: text.as_ptr(),
+            len: text.len(),
+        };
+        self.strings.push(text);
+        view
+    }
+
+    fn strings<I: IntoIterator<Item = String>>(&mut self, items: I) -> LtpStrings {
+        let view: Box<[LtpStr]> = items.into_iter().map(|s| self.str(s)).collect();
+        let strings = LtpStrings {
+            items: view.as_ptr(),
+            len: view.len(),
+        };
+        self.views.push(view);
+        strings
+    }
+
+    fn sentence(&mut self, sentence: Sentence) -> LtpSentence {
+        let labels = |storage: &mut Storage, labels: Option<ltp::InternedLabels>| match labels {
+            Some(labels) => storage.strings(labels.iter().map(String::from)),
+            None => LtpStrings::NONE,
+        };
+        LtpSentence {
+            text: self.str(sentence.text),
+            words: self.strings(sentence.words),
+            pos: labels(self, sentence.pos),
+            ner: labels(self, sentence.ner),
+        }
+    }
+}
+
+/// 返回给调用方的结果，`head` 必须是第一个字段，这样指向它的指针也就指向整个结果
+#[repr(C)]
+struct Owned<T> {
+    head: T,
+    _storage: Storage,
+    _sentences: Box<[LtpSentence]>,
+}
+
+impl<T> Owned<T> {
+    fn into_raw(head: T, storage: Storage, sentences: Box<[LtpSentence]>) -> *mut T {
+        Box::into_raw(Box::new(Owned {
+            head,
+            _storage: storage,
+            _sentences: sentences,
+        })) as *mut T
+    }
+
+    /// # Safety
+    /// `head` 必须来自 [`Owned::into_raw`]，且只释放一次
+    unsafe fn release(head: *mut *mut T) {
+        if head.is_null() || (*head).is_null() {
+            return;
+        }
+        let _ = Box::from_raw(*head as *mut Owned<T>);
+        *head = ptr::null_mut();
+    }
+}
+
+pub(crate) fn into_strings(items: Vec<String>) -> *mut LtpStrings {
+    let mut storage = Storage::default();
+    let strings = storage.strings(items);
+    Owned::into_raw(strings, storage, Box::new([]))
+}
+
+pub(crate) fn into_sentence(sentence: Sentence) -> *mut LtpSentence {
+    let mut storage = Storage::default();
+    let sentence = storage.sentence(sentence);
+    Owned::into_raw(sentence, storage, Box::new([]))
+}
+
+pub(crate) fn into_document(sentences: Vec<Sentence>) -> *mut LtpDocument {
+    let mut storage = Storage::default();
+    let sentences: Box<[LtpSentence]> = sentences
+        .into_iter()
+        .map(|sentence| storage.sentence(sentence))
+        .collect();
+    let document = LtpDocument {
+        sentences: sentences.as_ptr(),
+        len: sentences.len(),
+    };
+    Owned::into_raw(document, storage, sentences)
+}
+
+/// 检查输入是否为合法的 UTF-8，失败时记录错误
+///
+/// # Safety
+/// `ptr` 指向至少 `len` 个可读字节，或 `len` 为 0
+pub(crate) unsafe fn utf8<'a>(ptr: *const u8, len: usize) -> Option<&'a str> {
+    if len == 0 {
+        return Some("");
+    }
+    if ptr.is_null() {
+        set_last_error("unexpected NULL string");
+        return None;
+    }
+    match str::from_utf8(slice::from_raw_parts(ptr, len)) {
+        Ok(text) => Some(text),
+        Err(err) => {
+            set_last_error(err);
+            None
+        }
+    }
+}
+
+/// # Safety
+/// `items` 指向 `len` 个 [`LtpStr`]，或 `len` 为 0
+pub(crate) unsafe fn utf8_list<'a>(items: *const LtpStr, len: usize) -> Option<Vec<&'a str>> {
+    if len == 0 {
+        return Some(vec![]);
+    }
+    if items.is_null() {
+        set_last_error("unexpected NULL string list");
+        return None;
+    }
+    slice::from_raw_parts(items, len)
+        .iter()
+        .map(|item| utf8(item.ptr, item.len))
+        .collect()
+}
+
+/// Free a result returned by the `*_split`/`*_tag` functions and set `*strings` to NULL.
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn strings_release(strings: *mut *mut LtpStrings) {
+    unsafe { Owned::release(strings) }
+}
+
+/// Free a result returned by `pipeline_process` and set `*sentence` to NULL.
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn sentence_release(sentence: *mut *mut LtpSentence) {
+    unsafe { Owned::release(sentence) }
+}
+
+/// Free a result returned by `pipeline_process_document`/`pipeline_process_batch`
+/// and set `*document` to NULL.
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn document_release(document: *mut *mut LtpDocument) {
+    unsafe { Owned::release(document) }
+}
@@ -40,6 +40,7 @@ pub extern "C" fn stn_split_with_options(
         bracket_as_entity,
         zh_quote_as_entity,
         en_quote_as_entity,
+        ..Default::default()
     };
 
     let sentences = r_stn_split_with_options(text, &options);
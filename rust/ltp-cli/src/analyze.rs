@@ -0,0 +1,49 @@
+use crate::model::AnyModel;
+use crate::train::{CorpusFormat, Task};
+use anyhow::{anyhow, Result};
+use clap::Parser;
+use ltp::corpus::analyze;
+use ltp::perceptron::TraitFeature;
+
+/// 统计并校验语料：规模、标签分布、句长分布、未登录词、标注体系错误与重复句子
+#[derive(Parser, Debug)]
+pub struct Analyze {
+    /// 行格式语料的任务类型，决定每个词带几个标签
+    #[clap(value_enum)]
+    task: Task,
+    #[clap(long, value_enum, default_value_t = CorpusFormat::Ltp)]
+    format: CorpusFormat,
+    /// 统计相对于该模型的未登录词，须为词性标注或命名实体识别模型
+    #[clap(short, long)]
+    model: Option<String>,
+    #[clap(required = true)]
+    inputs: Vec<String>,
+}
+
+impl Analyze {
+    pub fn execute(self) -> Result<()> {
+        let model = self.model.as_ref().map(AnyModel::load_file).transpose()?;
+        if let Some(AnyModel::Cws(_)) = &model {
+            return Err(anyhow!(
+                "oov needs a POS or NER model, CWS models have no word features"
+            ));
+        }
+        // 词性标注与命名实体识别以 `2` 为当前词特征的前缀
+        let known = |word: &str| {
+            let key = format!("2{word}");
+            match &model {
+                Some(AnyModel::Pos(model)) => model.features.get_with_key(&key).is_some(),
+                Some(AnyModel::Ner(model)) => model.features.get_with_key(&key).is_some(),
+                _ => false,
+            }
+        };
+
+        let mut sentences = Vec::new();
+        for input in &self.inputs {
+            sentences.extend(self.format.read(input, self.task)?);
+        }
+        let known: Option<&dyn Fn(&str) -> bool> = model.as_ref().map(|_| &known as _);
+        print!("{}", analyze(&sentences, known));
+        Ok(())
+    }
+}
@@ -0,0 +1,83 @@
+use crate::model::AnyModel;
+use anyhow::{anyhow, Result};
+use clap::Parser;
+use ltp::corpus::{align_brat, parse_ann, write_ltp};
+use ltp::{CWSModel, POSModel};
+use std::fs::{read_to_string, File};
+use std::io::{stdout, BufWriter, Write};
+use std::path::Path;
+
+/// 把 brat 的 standoff 标注（`.txt` 与同名 `.ann`）按分词模型的结果对齐，转换为 `词/词性/实体`，
+/// 无法对齐的实体输出到标准错误，覆盖的词标为不确定（`_`）
+#[derive(Parser, Debug)]
+pub struct Brat {
+    /// 分词模型，实体边界须与其分词结果对齐
+    #[clap(long)]
+    cws: String,
+    /// 词性标注模型，实体识别的特征依赖词性
+    #[clap(long)]
+    pos: String,
+    /// 存在无法对齐的实体时报错退出，不写出结果
+    #[clap(long)]
+    strict: bool,
+    /// 输出文件，缺省时写到标准输出
+    #[clap(short, long)]
+    output: Option<String>,
+    /// `.txt` 或 `.ann` 文件，另一个按同名查找
+    #[clap(required = true)]
+    inputs: Vec<String>,
+}
+
+fn load<T>(path: &str, select: impl FnOnce(AnyModel) -> Option<T>) -> Result<T> {
+    let model = AnyModel::load_file(path)?;
+    let task = model.task();
+    select(model).ok_or_else(|| anyhow!("{}: unexpected {} model", path, task))
+}
+
+impl Brat {
+    pub fn execute(self) -> Result<()> {
+        let cws: CWSModel = load(&self.cws, |model| match model {
+            AnyModel::Cws(model) => Some(model),
+            _ => None,
+        })?;
+        let pos: POSModel = load(&self.pos, |model| match model {
+            AnyModel::Pos(model) => Some(model),
+            _ => None,
+        })?;
+
+        let mut sentences = Vec::new();
+        let mut conflicts = 0;
+        for input in &self.inputs {
+            let path = Path::new(input);
+            let text = read_to_string(path.with_extension("txt"))?;
+            let entities = parse_ann(&read_to_string(path.with_extension("ann"))?)?;
+            let segment = |line: &str| -> Result<Vec<String>> {
+                Ok(cws.predict(line)?.into_iter().map(String::from).collect())
+            };
+            let (aligned, found) = align_brat(&text, &entities, segment)?;
+            for conflict in &found {
+                eprintln!("{}: {}", input, conflict);
+            }
+            conflicts += found.len();
+            sentences.extend(aligned);
+        }
+        if conflicts > 0 {
+            eprintln!("{} entities could not be aligned", conflicts);
+            if self.strict {
+                return Err(anyhow!("alignment conflicts in strict mode"));
+            }
+        }
+        for sentence in &mut sentences {
+            let tags = pos.predict(&sentence.words())?;
+            sentence.pos = Some(tags.into_iter().map(String::from).collect());
+        }
+
+        let mut writer: Box<dyn Write> = match &self.output {
+            Some(path) => Box::new(BufWriter::new(File::create(path)?)),
+            None => Box::new(BufWriter::new(stdout().lock())),
+        };
+        write_ltp(&mut writer, &sentences)?;
+        writer.flush()?;
+        Ok(())
+    }
+}
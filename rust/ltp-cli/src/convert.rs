@@ -0,0 +1,106 @@
+use crate::model::{output_format, AnyModel};
+use crate::with_model;
+use anyhow::{anyhow, Result};
+use clap::{Parser, ValueEnum};
+use ltp::perceptron::{Compression, Format};
+use ltp::{Codec, Quantization};
+use std::fs::File;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, ValueEnum)]
+pub enum CodecArg {
+    Null,
+    Deflate,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, ValueEnum)]
+pub enum QuantizationArg {
+    F16,
+    I8,
+}
+
+/// 转换模型格式，可选裁剪特征或量化参数
+#[derive(Parser, Debug)]
+pub struct Convert {
+    #[clap(short, long)]
+    input: String,
+    /// `.json` 保存为 JSON，其余为 avro；`.gz`、`.zst` 结尾时压缩
+    #[clap(short, long)]
+    output: String,
+    /// avro 的块压缩方式
+    #[clap(long, value_enum, default_value_t = CodecArg::Deflate)]
+    codec: CodecArg,
+
+    /// 按参数绝对值之和裁剪特征，保留的比例为 `1 - prune`
+    #[clap(long, value_parser)]
+    prune: Option<f64>,
+    /// 绝对值之和小于该阈值的特征总是被裁剪
+    #[clap(long, value_parser, default_value_t = 1e-3)]
+    threshold: f64,
+
+    /// 量化参数，量化后的模型只能保存为 JSON，通过 serde 加载为 `QuantizedModel`
+    #[clap(long, value_enum)]
+    quantize: Option<QuantizationArg>,
+
+    /// 导出为可移植的线性模型格式（JSON），供其它语言的运行时直接推理
+    #[clap(long, conflicts_with = "quantize")]
+    portable: bool,
+
+    /// 导出为 ltp-core 的二进制格式，供 no_std 目标从 flash 中直接读取，只支持分词模型
+    #[clap(long, conflicts_with_all = ["quantize", "portable"])]
+    embedded: bool,
+}
+
+impl Convert {
+    pub fn execute(self) -> Result<()> {
+        let mut model = AnyModel::load_file(&self.input)?;
+        let codec = match self.codec {
+            CodecArg::Null => Codec::Null,
+            CodecArg::Deflate => Codec::Deflate,
+        };
+
+        if let Some(prune) = self.prune {
+            if !(0.0..1.0).contains(&prune) {
+                return Err(anyhow!("--prune must be in [0, 1)"));
+            }
+            let (ratio, threshold) = (1.0 - prune, self.threshold);
+            model = match model {
+                AnyModel::Cws(model) => AnyModel::Cws(model.compress(ratio, threshold)),
+                AnyModel::Pos(model) => AnyModel::Pos(model.compress(ratio, threshold)),
+                AnyModel::Ner(model) => AnyModel::Ner(model.compress(ratio, threshold)),
+            };
+        }
+
+        if self.portable {
+            let mut writer =
+                Compression::from_path(&self.output).encoder(File::create(&self.output)?)?;
+            with_model!(model, model => model.to_portable().write(&mut writer)?);
+            writer.finish()?;
+            return Ok(());
+        }
+
+        if self.embedded {
+            let AnyModel::Cws(model) = &model else {
+                return Err(anyhow!("--embedded only supports cws models"));
+            };
+            std::fs::write(&self.output, model.to_portable().to_embedded()?)?;
+            return Ok(());
+        }
+
+        let format = output_format(&self.output, codec);
+        let Some(mode) = self.quantize else {
+            return model.save_file(&self.output, format);
+        };
+        if !matches!(format, Format::JSON) {
+            return Err(anyhow!("quantized models can only be saved as json"));
+        }
+        let mode = match mode {
+            QuantizationArg::F16 => Quantization::F16,
+            QuantizationArg::I8 => Quantization::I8,
+        };
+        let mut writer =
+            Compression::from_path(&self.output).encoder(File::create(&self.output)?)?;
+        with_model!(model, model => serde_json::to_writer(&mut writer, &model.quantize(mode))?);
+        writer.finish()?;
+        Ok(())
+    }
+}
@@ -0,0 +1,47 @@
+use anyhow::Result;
+use clap::{Parser, ValueEnum};
+use ltp::corpus::{read_ctb, write_ltp};
+use ltp::perceptron::CompressedReader;
+use std::fs::File;
+use std::io::{stdout, BufWriter, Write};
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, ValueEnum)]
+pub enum Target {
+    /// 只输出词，用于训练分词
+    Cws,
+    /// 输出 `词/词性`，用于训练词性标注
+    Pos,
+}
+
+/// 把宾州中文树库（CTB）的括号树转换为训练使用的行格式，去掉空语类
+#[derive(Parser, Debug)]
+pub struct Ctb {
+    #[clap(long, value_enum, default_value_t = Target::Pos)]
+    task: Target,
+    /// 输出文件，缺省时写到标准输出
+    #[clap(short, long)]
+    output: Option<String>,
+    /// 树库文件，如 `chtb_0001.fid`，须为 UTF-8 或带 BOM 的 UTF-16，可以是 gzip、zstd 压缩的
+    #[clap(required = true)]
+    inputs: Vec<String>,
+}
+
+impl Ctb {
+    pub fn execute(self) -> Result<()> {
+        let mut writer: Box<dyn Write> = match &self.output {
+            Some(path) => Box::new(BufWriter::new(File::create(path)?)),
+            None => Box::new(BufWriter::new(stdout().lock())),
+        };
+        for input in &self.inputs {
+            let mut sentences = read_ctb(CompressedReader::open(input)?)?;
+            if self.task == Target::Cws {
+                sentences
+                    .iter_mut()
+                    .for_each(|sentence| sentence.pos = None);
+            }
+            write_ltp(&mut writer, &sentences)?;
+        }
+        writer.flush()?;
+        Ok(())
+    }
+}
@@ -0,0 +1,26 @@
+use crate::predict::pipeline;
+use anyhow::Result;
+use clap::Parser;
+use ltp::LtpDaemon;
+
+/// 常驻进程，加载模型后通过 Unix domain socket 提供服务，配合 `ltp predict --socket` 使用
+#[derive(Parser, Debug)]
+pub struct Daemon {
+    #[clap(long)]
+    socket: String,
+    #[clap(long)]
+    cws: String,
+    #[clap(long)]
+    pos: Option<String>,
+    /// 依赖词性标注模型
+    #[clap(long)]
+    ner: Option<String>,
+}
+
+impl Daemon {
+    pub fn execute(self) -> Result<()> {
+        let pipeline = pipeline(&self.cws, self.pos.as_deref(), self.ner.as_deref())?;
+        eprintln!("ltp daemon listening on {}", self.socket);
+        LtpDaemon::new(pipeline).serve(&self.socket)
+    }
+}
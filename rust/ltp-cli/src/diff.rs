@@ -0,0 +1,130 @@
+use crate::model::AnyModel;
+use crate::predict::pipeline;
+use anyhow::{anyhow, Result};
+use clap::Parser;
+use ltp::evaluate::DiffSummary;
+use ltp::perceptron::{char_tags, CompressedReader, ModelTask, SentenceErrors};
+use ltp::LtpPipeline;
+use std::fs::File;
+use std::io::{self, BufRead, BufWriter, Write};
+
+/// 用两个同任务的模型标注同一份文本，只输出预测不同的句子（逐词对照旧、新模型的标签，分词按字对照 B/M/E/S），
+/// 最后在标准错误输出差异的汇总，用于替换线上模型前检查行为变化
+#[derive(Parser, Debug)]
+pub struct Diff {
+    /// 旧模型
+    old: String,
+    /// 新模型，须与旧模型为同一任务
+    new: String,
+    /// 分词模型，比较词性标注或实体识别模型时用于分词
+    #[clap(long)]
+    cws: Option<String>,
+    /// 词性标注模型，比较实体识别模型时提供词性
+    #[clap(long)]
+    pos: Option<String>,
+    /// 输出文件，缺省时写到标准输出
+    #[clap(short, long)]
+    output: Option<String>,
+    /// 输入文件，每行一句，省略时读取标准输入
+    inputs: Vec<String>,
+}
+
+impl Diff {
+    /// 比较分词以外的模型时，用上游模型得到词与词性
+    fn upstream(&self, task: ModelTask) -> Result<Option<LtpPipeline>> {
+        let pos = match task {
+            ModelTask::CWS => return Ok(None),
+            ModelTask::POS => None,
+            ModelTask::NER => Some(
+                self.pos
+                    .as_deref()
+                    .ok_or_else(|| anyhow!("comparing NER models requires --pos"))?,
+            ),
+        };
+        let cws = self
+            .cws
+            .as_deref()
+            .ok_or_else(|| anyhow!("comparing {} models requires --cws", task))?;
+        Ok(Some(pipeline(cws, pos, None)?))
+    }
+
+    /// 返回逐词对齐的词与两个模型的标签
+    fn compare(
+        old: &AnyModel,
+        new: &AnyModel,
+        upstream: Option<&LtpPipeline>,
+        line: &str,
+    ) -> Result<(Vec<String>, Vec<String>, Vec<String>)> {
+        let to_vec = |v: Vec<&str>| v.into_iter().map(String::from).collect::<Vec<_>>();
+        let sentence = upstream.map(|p| p.process(line)).transpose()?;
+        let words = sentence.as_ref().map(|s| s.words()).unwrap_or_default();
+        let pos = sentence
+            .as_ref()
+            .and_then(|s| s.pos.as_ref())
+            .map(|pos| pos.to_vec())
+            .unwrap_or_default();
+        Ok(match (old, new) {
+            (AnyModel::Cws(a), AnyModel::Cws(b)) => {
+                let chars = line.chars().map(String::from).collect();
+                let (a, b) = (a.predict(line)?, b.predict(line)?);
+                (chars, char_tags(&a), char_tags(&b))
+            }
+            (AnyModel::Pos(a), AnyModel::Pos(b)) => (
+                to_vec(words.clone()),
+                to_vec(a.predict(&words)?),
+                to_vec(b.predict(&words)?),
+            ),
+            (AnyModel::Ner(a), AnyModel::Ner(b)) => (
+                to_vec(words.clone()),
+                to_vec(a.predict((&words, &pos))?),
+                to_vec(b.predict((&words, &pos))?),
+            ),
+            _ => unreachable!("tasks are checked before comparing"),
+        })
+    }
+
+    pub fn execute(self) -> Result<()> {
+        let old = AnyModel::load_file(&self.old)?;
+        let new = AnyModel::load_file(&self.new)?;
+        if old.task() != new.task() {
+            return Err(anyhow!(
+                "cannot compare a {} model with a {} model",
+                old.task(),
+                new.task()
+            ));
+        }
+        let upstream = self.upstream(old.task())?;
+        let mut writer: Box<dyn Write> = match &self.output {
+            Some(path) => Box::new(BufWriter::new(File::create(path)?)),
+            None => Box::new(BufWriter::new(io::stdout().lock())),
+        };
+        let readers: Vec<Box<dyn BufRead>> = match self.inputs.is_empty() {
+            true => vec![Box::new(io::stdin().lock())],
+            false => self
+                .inputs
+                .iter()
+                .map(|input| Ok(Box::new(CompressedReader::open(input)?) as Box<dyn BufRead>))
+                .collect::<Result<_>>()?,
+        };
+
+        let mut summary = DiffSummary::default();
+        for reader in readers {
+            for line in reader.lines() {
+                let line = line?;
+                let line = line.trim();
+                if line.is_empty() {
+                    continue;
+                }
+                let index = summary.sentences;
+                let (tokens, a, b) = Self::compare(&old, &new, upstream.as_ref(), line)?;
+                summary.add(&a, &b);
+                if let Some(diff) = SentenceErrors::new(index, &tokens, &a, &b) {
+                    writeln!(writer, "{}", diff)?;
+                }
+            }
+        }
+        writer.flush()?;
+        eprintln!("{}", summary);
+        Ok(())
+    }
+}
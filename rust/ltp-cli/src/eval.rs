@@ -0,0 +1,297 @@
+use crate::model::AnyModel;
+use crate::with_model;
+use anyhow::{anyhow, Result};
+use clap::{ArgAction, Parser};
+use ltp::corpus::{read_ltp, AnnotatedSentence};
+use ltp::evaluate::Bootstrap;
+use ltp::perceptron::{
+    write_errors, CompressedReader, LengthReport, ModelTask, OovReport, Predictions,
+    SentenceErrors, TraitFeature,
+};
+use ltp::Trainer;
+use std::collections::HashSet;
+use std::fs::File;
+use std::io::BufWriter;
+
+/// 在标注语料上评测模型，任务类型由模型文件识别
+#[derive(Parser, Debug)]
+pub struct Eval {
+    #[clap(short, long)]
+    model: String,
+    /// 标注语料，格式与训练语料相同
+    #[clap(short, long)]
+    gold: String,
+    #[clap(short, long, value_parser, default_value_t = 8)]
+    threads: usize,
+    #[clap(long, action = ArgAction::Set, default_value_t = true)]
+    verbose: bool,
+    /// 输出每个标签的精确率、召回率、F1 与 support，以及宏平均、微平均与准确率
+    #[clap(long)]
+    report: bool,
+    /// 把逐词的混淆矩阵写到文件，扩展名为 `.json` 时输出 JSON，其余输出 CSV
+    #[clap(long)]
+    confusion: Option<String>,
+    /// 按实体做片段级评测（精确、左边界、右边界、部分重叠、仅类型），只适用于实体识别模型
+    #[clap(long)]
+    spans: bool,
+    /// 按句长分桶输出指标与逐句解码耗时（平均、p50、p95、最大值与每词耗时）
+    #[clap(long)]
+    lengths: bool,
+    /// 句长分桶的上界，以逗号分隔
+    #[clap(
+        long,
+        requires = "lengths",
+        value_delimiter = ',',
+        default_value = "10,20,40,80,160"
+    )]
+    buckets: Vec<usize>,
+    /// 按预测标签的后验概率分箱输出逐词准确率与期望校准误差（ECE），箱数由此给出
+    #[clap(long, num_args = 0..=1, default_missing_value = "10")]
+    calibration: Option<usize>,
+    /// 把含错误的句子按错误数从多到少写到文件，逐词对照标注与预测，错误的片段用 `[[ ]]` 标出
+    #[clap(long)]
+    errors: Option<String>,
+    /// 分别统计登录词与未登录词的指标
+    #[clap(long)]
+    oov: bool,
+    /// 训练语料，其中的词为登录词；缺省时以模型的当前词特征为准，分词模型必须提供
+    #[clap(long, requires = "oov")]
+    vocab: Option<String>,
+    /// `ltp predict` 对标注语料原文的标注结果，给出时直接在其上评测，不再用模型解码，模型只提供标签表；
+    /// 不能与需要模型打分或计时的 `--lengths`、`--calibration`、`--compare` 同时使用
+    #[clap(long, conflicts_with_all = ["lengths", "calibration", "compare"])]
+    predictions: Option<String>,
+    /// 与另一个同任务的模型比较，用配对自助法检验两者 F1 的差异是否显著
+    #[clap(long)]
+    compare: Option<String>,
+    /// 自助法的重采样次数
+    #[clap(long, requires = "compare", default_value_t = 1000)]
+    bootstrap: usize,
+    #[clap(long, requires = "compare", default_value_t = 0)]
+    seed: u64,
+}
+
+/// 用模型重新标注语料，分词为预测的词，其余为预测的标签
+fn relabel(model: &AnyModel, gold: &str) -> Result<Vec<(AnnotatedSentence, Vec<String>)>> {
+    let sentences = read_ltp(CompressedReader::open(gold)?, model.task())?;
+    let mut relabelled = Vec::with_capacity(sentences.len());
+    for sentence in sentences {
+        let words = sentence.words();
+        let predicted = match model {
+            AnyModel::Cws(model) => model.predict(&sentence.words.concat())?.join(" "),
+            AnyModel::Pos(model) => model.predict(&words)?.join(" "),
+            AnyModel::Ner(model) => model.predict((&words, &sentence.pos()?))?.join(" "),
+        };
+        let predicted = predicted.split(' ').map(String::from).collect();
+        relabelled.push((sentence, predicted));
+    }
+    Ok(relabelled)
+}
+
+/// 读取保存的标注结果，与标注语料逐句对应
+fn relabel_saved(
+    task: ModelTask,
+    gold: &str,
+    predictions: &str,
+) -> Result<Vec<(AnnotatedSentence, Vec<String>)>> {
+    let sentences = read_ltp(CompressedReader::open(gold)?, task)?;
+    let predicted = read_ltp(CompressedReader::open(predictions)?, task)?;
+    if sentences.len() != predicted.len() {
+        return Err(anyhow!(
+            "{} predicted sentences for {} gold sentences",
+            predicted.len(),
+            sentences.len()
+        ));
+    }
+    let mut relabelled = Vec::with_capacity(sentences.len());
+    for (sentence, predicted) in sentences.into_iter().zip(predicted) {
+        let labels = gold_column(task, &predicted)?;
+        let labels = labels.into_iter().map(String::from).collect();
+        relabelled.push((sentence, labels));
+    }
+    Ok(relabelled)
+}
+
+/// 与预测结果对应的标注：分词为词，词性标注为词性，实体识别为实体标签
+fn gold_column(task: ModelTask, sentence: &AnnotatedSentence) -> Result<Vec<&str>> {
+    match task {
+        ModelTask::CWS => Ok(sentence.words()),
+        ModelTask::POS => sentence.pos(),
+        ModelTask::NER => sentence.labels(),
+    }
+}
+
+impl Eval {
+    fn errors(
+        &self,
+        task: ModelTask,
+        relabelled: &[(AnnotatedSentence, Vec<String>)],
+    ) -> Result<()> {
+        let Some(path) = &self.errors else {
+            return Ok(());
+        };
+        let mut errors = Vec::new();
+        for (index, (sentence, predicted)) in relabelled.iter().enumerate() {
+            let gold = gold_column(task, sentence)?;
+            let predicted: Vec<&str> = predicted.iter().map(String::as_str).collect();
+            errors.extend(match task {
+                ModelTask::CWS => SentenceErrors::from_words(index, &gold, &predicted),
+                _ => SentenceErrors::new(index, &sentence.words(), &gold, &predicted),
+            });
+        }
+        write_errors(BufWriter::new(File::create(path)?), &mut errors)?;
+        println!("{} sentences with errors written to {}", errors.len(), path);
+        Ok(())
+    }
+
+    /// 登录词取自 `--vocab` 给出的训练语料，缺省时取词性标注与实体识别模型的当前词特征
+    fn oov(&self, model: &AnyModel, relabelled: &[(AnnotatedSentence, Vec<String>)]) -> Result<()> {
+        if !self.oov {
+            return Ok(());
+        }
+        let task = model.task();
+        let vocab: Option<HashSet<String>> = match &self.vocab {
+            Some(path) => {
+                let sentences = read_ltp(CompressedReader::open(path)?, task)?;
+                Some(sentences.into_iter().flat_map(|s| s.words).collect())
+            }
+            None if task == ModelTask::CWS => {
+                return Err(anyhow!("CWS models have no word features, provide --vocab"))
+            }
+            None => None,
+        };
+        // 词性标注与命名实体识别以 `2` 为当前词特征的前缀
+        let known = |word: &str| match (&vocab, model) {
+            (Some(vocab), _) => vocab.contains(word),
+            (None, AnyModel::Pos(model)) => {
+                model.features.get_with_key(&format!("2{word}")).is_some()
+            }
+            (None, AnyModel::Ner(model)) => {
+                model.features.get_with_key(&format!("2{word}")).is_some()
+            }
+            (None, AnyModel::Cws(_)) => false,
+        };
+        let mut report = OovReport::default();
+        for (sentence, predicted) in relabelled {
+            let gold = gold_column(task, sentence)?;
+            let predicted: Vec<&str> = predicted.iter().map(String::as_str).collect();
+            match task {
+                ModelTask::CWS => report.add_words(&gold, &predicted, &known),
+                _ => report.add_tokens(&sentence.words(), &gold, &predicted, &known),
+            }
+        }
+        println!("{report}");
+        Ok(())
+    }
+
+    /// 两个模型须为同一任务，以 `model` 的定义读取语料
+    #[allow(clippy::clone_on_copy)]
+    fn compare(&self, model: &AnyModel) -> Result<()> {
+        let Some(path) = &self.compare else {
+            return Ok(());
+        };
+        let other = AnyModel::load_file(path)?;
+        let bootstrap = Bootstrap::new().samples(self.bootstrap).seed(self.seed);
+        macro_rules! significance {
+            ($a:expr, $b:expr) => {{
+                let trainer: Trainer<_> = Trainer::new().definition($a.definition.clone());
+                let test = trainer.load_dataset(&self.gold)?;
+                bootstrap.significance($a, $b, &test)
+            }};
+        }
+        let result = match (model, &other) {
+            (AnyModel::Cws(a), AnyModel::Cws(b)) => significance!(a, b),
+            (AnyModel::Pos(a), AnyModel::Pos(b)) => significance!(a, b),
+            (AnyModel::Ner(a), AnyModel::Ner(b)) => significance!(a, b),
+            _ => {
+                return Err(anyhow!(
+                    "cannot compare a {} model with a {} model",
+                    model.task(),
+                    other.task()
+                ))
+            }
+        };
+        println!("{result}");
+        Ok(())
+    }
+
+    // CWSDefinition 实现了 Copy，其余定义只实现了 Clone
+    #[allow(clippy::clone_on_copy)]
+    pub fn execute(self) -> Result<()> {
+        let model = AnyModel::load_file(&self.model)?;
+        let start = std::time::Instant::now();
+        if self.spans && model.task() != ModelTask::NER {
+            return Err(anyhow!("span evaluation requires a NER model"));
+        }
+        let (report, confusion, spans, lengths, calibration) = with_model!(&model, model => {
+            let trainer = Trainer::new()
+                .definition(model.definition.clone())
+                .verbose(self.verbose)
+                .eval_threads(self.threads)
+                .eval_file(&self.gold)?;
+            // 解码一遍，各项评测共用预测结果
+            let predictions = match &self.predictions {
+                Some(path) => Predictions::from_file(&model.definition, path)?,
+                None => trainer.predictions(model)?,
+            };
+            let samples = trainer.eval_set.as_deref().unwrap_or_default();
+            let confusion = match self.confusion {
+                Some(_) => Some(predictions.confusion_matrix(&model.definition, samples)?),
+                None => None,
+            };
+            let spans = match self.spans {
+                true => Some(predictions.span_report(&model.definition, samples)?),
+                false => None,
+            };
+            let lengths = match self.lengths {
+                true => {
+                    let buckets = LengthReport::new(self.buckets.clone());
+                    Some(trainer.evaluate_lengths(model, buckets)?)
+                }
+                false => None,
+            };
+            let calibration = match self.calibration {
+                Some(bins) => Some(trainer.calibration(model, bins)?),
+                None => None,
+            };
+            let report = trainer.evaluate_predictions(&predictions)?;
+            (report, confusion, spans, lengths, calibration)
+        });
+        let duration = start.elapsed().as_millis();
+        if self.report {
+            println!("{report}");
+        }
+        if let Some(spans) = spans {
+            println!("{spans}");
+        }
+        if let Some(lengths) = lengths {
+            println!("{lengths}");
+        }
+        if let Some(calibration) = calibration {
+            println!("{calibration}");
+        }
+        if let (Some(path), Some(matrix)) = (&self.confusion, confusion) {
+            let writer = BufWriter::new(File::create(path)?);
+            if path.ends_with(".json") {
+                serde_json::to_writer(writer, &matrix)?;
+            } else {
+                matrix.write_csv(writer)?;
+            }
+            for (gold, predicted, count) in matrix.confusions().into_iter().take(10) {
+                println!("{gold} -> {predicted}: {count}");
+            }
+        }
+        if self.errors.is_some() || self.oov {
+            let relabelled = match &self.predictions {
+                Some(path) => relabel_saved(model.task(), &self.gold, path)?,
+                None => relabel(&model, &self.gold)?,
+            };
+            self.errors(model.task(), &relabelled)?;
+            self.oov(&model, &relabelled)?;
+        }
+        self.compare(&model)?;
+        let (p, r, f1) = (report.precision(), report.recall(), report.f1());
+        println!("[{duration}ms] precision: {p}, recall: {r}, f1: {f1}");
+        Ok(())
+    }
+}
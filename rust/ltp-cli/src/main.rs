@@ -0,0 +1,54 @@
+//! LTP 命令行工具：特征预处理、训练、评测、模型比较、语料统计与划分、标注、模型转换、语料转换、流式处理与常驻服务
+mod analyze;
+mod brat;
+mod convert;
+mod ctb;
+#[cfg(unix)]
+mod daemon;
+mod diff;
+mod eval;
+mod model;
+mod predict;
+mod preprocess;
+mod split;
+mod stream;
+mod train;
+
+use anyhow::Result;
+use clap::Parser;
+
+#[derive(Parser, Debug)]
+#[clap(name = "ltp", author, version, about, long_about = None)]
+enum Args {
+    Preprocess(preprocess::Preprocess),
+    Train(train::Train),
+    Eval(eval::Eval),
+    Diff(diff::Diff),
+    Analyze(analyze::Analyze),
+    Split(split::Split),
+    Predict(predict::Predict),
+    Convert(convert::Convert),
+    Ctb(ctb::Ctb),
+    Brat(brat::Brat),
+    Stream(stream::Stream),
+    #[cfg(unix)]
+    Daemon(daemon::Daemon),
+}
+
+fn main() -> Result<()> {
+    match Args::parse() {
+        Args::Preprocess(args) => args.execute(),
+        Args::Train(args) => args.execute(),
+        Args::Eval(args) => args.execute(),
+        Args::Diff(args) => args.execute(),
+        Args::Analyze(args) => args.execute(),
+        Args::Split(args) => args.execute(),
+        Args::Predict(args) => args.execute(),
+        Args::Convert(args) => args.execute(),
+        Args::Ctb(args) => args.execute(),
+        Args::Brat(args) => args.execute(),
+        Args::Stream(args) => args.execute(),
+        #[cfg(unix)]
+        Args::Daemon(args) => args.execute(),
+    }
+}
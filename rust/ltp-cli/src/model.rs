@@ -0,0 +1,30 @@
+use ltp::perceptron::Compression;
+use ltp::{Codec, Format};
+use std::path::Path;
+
+pub use ltp::DynModel as AnyModel;
+
+/// 对三种模型执行同一段代码
+#[macro_export]
+macro_rules! with_model {
+    ($model:expr, $name:ident => $body:expr) => {
+        match $model {
+            $crate::model::AnyModel::Cws($name) => $body,
+            $crate::model::AnyModel::Pos($name) => $body,
+            $crate::model::AnyModel::Ner($name) => $body,
+        }
+    };
+}
+
+/// `.json`（可带压缩扩展名）保存为 JSON，其余保存为 avro
+pub fn output_format<P: AsRef<Path>>(path: P, codec: Codec) -> Format {
+    let path = path.as_ref();
+    let stem = match Compression::from_path(path) {
+        Compression::None => path.to_path_buf(),
+        _ => path.with_extension(""),
+    };
+    match stem.extension().and_then(|ext| ext.to_str()) {
+        Some("json") => Format::JSON,
+        _ => Format::AVRO(codec),
+    }
+}
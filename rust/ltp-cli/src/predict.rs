@@ -0,0 +1,169 @@
+use crate::model::AnyModel;
+use anyhow::{anyhow, Result};
+use clap::Parser;
+use ltp::perceptron::{CompressedReader, ModelTask};
+#[cfg(unix)]
+use ltp::LtpClient;
+use ltp::{LtpPipeline, Sentence};
+use std::fs::File;
+use std::io::{self, BufRead, BufWriter, Write};
+
+/// 逐行标注文本，输出空格分隔的 `词[/词性[/实体]]`
+#[derive(Parser, Debug)]
+pub struct Predict {
+    #[clap(long, required_unless_present_any = ["socket", "config"])]
+    cws: Option<String>,
+    #[clap(long)]
+    pos: Option<String>,
+    /// 依赖词性标注模型
+    #[clap(long)]
+    ner: Option<String>,
+    /// 从 TOML 配置文件加载模型、词典与各项参数，格式见 `ltp::pipeline::PipelineConfig`
+    #[clap(long, conflicts_with_all = ["cws", "pos", "ner"])]
+    config: Option<String>,
+    /// 交给 `ltp daemon` 处理，不再加载模型
+    #[cfg(unix)]
+    #[clap(long, conflicts_with_all = ["cws", "pos", "ner", "config"])]
+    socket: Option<String>,
+
+    /// 每批并行处理的行数，每批处理完后写出
+    #[clap(long, value_parser, default_value_t = 1024)]
+    batch_size: usize,
+
+    /// 输出文件，省略时写到标准输出
+    #[clap(short, long)]
+    output: Option<String>,
+    /// 输入文件，省略或为 `-` 时读取标准输入
+    inputs: Vec<String>,
+}
+
+pub fn pipeline(cws: &str, pos: Option<&str>, ner: Option<&str>) -> Result<LtpPipeline> {
+    let mut pipeline = LtpPipeline::new();
+    let models = [
+        (Some(cws), ModelTask::CWS),
+        (pos, ModelTask::POS),
+        (ner, ModelTask::NER),
+    ];
+    for (path, task) in models {
+        let Some(path) = path else { continue };
+        let model = AnyModel::load_file(path)?;
+        if model.task() != task {
+            return Err(anyhow!(
+                "{}: expected a {} model, found a {} model",
+                path,
+                task,
+                model.task()
+            ));
+        }
+        pipeline = match model {
+            AnyModel::Cws(model) => pipeline.with_cws(model),
+            AnyModel::Pos(model) => pipeline.with_pos(model),
+            AnyModel::Ner(model) => pipeline.with_ner(model),
+        };
+    }
+    pipeline.validate()?;
+    Ok(pipeline)
+}
+
+pub fn format_sentence(sentence: &Sentence) -> String {
+    let pos: Option<Vec<&str>> = sentence.pos.as_ref().map(|pos| pos.iter().collect());
+    let ner: Option<Vec<&str>> = sentence.ner.as_ref().map(|ner| ner.iter().collect());
+    sentence
+        .words
+        .iter()
+        .enumerate()
+        .map(|(i, word)| {
+            let mut token = word.clone();
+            for labels in [&pos, &ner].into_iter().flatten() {
+                token.push('/');
+                token.push_str(labels[i]);
+            }
+            token
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+enum Backend {
+    Local(LtpPipeline),
+    #[cfg(unix)]
+    Daemon(LtpClient),
+}
+
+impl Backend {
+    fn process_batch(&mut self, texts: &[&str]) -> Result<Vec<Sentence>> {
+        match self {
+            Backend::Local(pipeline) => pipeline.process_batch(texts),
+            #[cfg(unix)]
+            Backend::Daemon(client) => client.process_batch(texts),
+        }
+    }
+}
+
+impl Predict {
+    fn backend(&self) -> Result<Backend> {
+        #[cfg(unix)]
+        if let Some(socket) = &self.socket {
+            return Ok(Backend::Daemon(LtpClient::connect(socket)?));
+        }
+        if let Some(config) = &self.config {
+            return Ok(Backend::Local(LtpPipeline::from_config(config)?));
+        }
+        let cws = self.cws.as_deref().expect("required by clap");
+        let pipeline = pipeline(cws, self.pos.as_deref(), self.ner.as_deref())?;
+        Ok(Backend::Local(pipeline))
+    }
+
+    fn process<R: BufRead, W: Write>(
+        &self,
+        backend: &mut Backend,
+        reader: R,
+        writer: &mut W,
+    ) -> Result<()> {
+        let mut lines = reader.lines();
+        loop {
+            let batch: Vec<String> = lines
+                .by_ref()
+                .take(self.batch_size.max(1))
+                .collect::<io::Result<_>>()?;
+            if batch.is_empty() {
+                return Ok(());
+            }
+            // 空行原样输出，保持行号对齐
+            let texts: Vec<&str> = batch
+                .iter()
+                .map(|line| line.trim())
+                .filter(|line| !line.is_empty())
+                .collect();
+            let mut sentences = backend.process_batch(&texts)?.into_iter();
+            for line in &batch {
+                if line.trim().is_empty() {
+                    writeln!(writer)?;
+                } else if let Some(sentence) = sentences.next() {
+                    writeln!(writer, "{}", format_sentence(&sentence))?;
+                }
+            }
+            writer.flush()?;
+        }
+    }
+
+    pub fn execute(self) -> Result<()> {
+        let mut backend = self.backend()?;
+        let mut writer: Box<dyn Write> = match &self.output {
+            Some(path) => Box::new(BufWriter::new(File::create(path)?)),
+            None => Box::new(BufWriter::new(io::stdout().lock())),
+        };
+
+        if self.inputs.is_empty() {
+            return self.process(&mut backend, io::stdin().lock(), &mut writer);
+        }
+        for input in &self.inputs {
+            if input == "-" {
+                self.process(&mut backend, io::stdin().lock(), &mut writer)?;
+            } else {
+                self.process(&mut backend, CompressedReader::open(input)?, &mut writer)?;
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,41 @@
+use crate::train::{discover_labels, CorpusFormat, Task};
+use anyhow::Result;
+use clap::Parser;
+use ltp::corpus::LabelOrder;
+use ltp::perceptron::preprocess;
+use ltp::{CWSDefinition, NERDefinition, POSDefinition};
+
+/// 抽取行格式语料的特征写入二进制缓存，`ltp train` 的 `--train` 与 `--eval` 可以直接读取，
+/// 以不同的超参数多次训练时省去特征抽取；语料按分片并行处理
+#[derive(Parser, Debug)]
+pub struct Preprocess {
+    #[clap(value_enum)]
+    task: Task,
+    #[clap(short, long)]
+    input: String,
+    /// 缓存路径，`.gz`、`.zst` 结尾时压缩
+    #[clap(short, long)]
+    output: String,
+    /// 标签表，每行一个，缺省时从语料中按字典序发现；训练集与验证集的缓存须使用同一个标签表
+    #[clap(long)]
+    vocab: Option<String>,
+}
+
+impl Preprocess {
+    fn labels(&self, task: Task) -> Result<Vec<String>> {
+        let sentences = CorpusFormat::Ltp.read(&self.input, task)?;
+        let order = LabelOrder::Lexicographic;
+        discover_labels(task, &sentences, self.vocab.as_deref(), order)
+    }
+
+    pub fn execute(self) -> Result<()> {
+        let (input, output) = (&self.input, &self.output);
+        let stats = match self.task {
+            Task::Cws => preprocess(&CWSDefinition::default(), input, output)?,
+            task @ Task::Pos => preprocess(&POSDefinition::new(self.labels(task)?), input, output)?,
+            task @ Task::Ner => preprocess(&NERDefinition::new(self.labels(task)?), input, output)?,
+        };
+        println!("{stats}");
+        Ok(())
+    }
+}
@@ -0,0 +1,95 @@
+use crate::train::{CorpusFormat, Task};
+use anyhow::{anyhow, Result};
+use clap::{Parser, ValueEnum};
+use ltp::corpus::{write_ltp, SplitManifest, SplitSize, Splitter, Stratify};
+use serde::Serialize;
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::PathBuf;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, ValueEnum)]
+pub enum StratifyArg {
+    None,
+    /// 按句中最稀有的标签分层
+    Label,
+    /// 按句长分层，桶宽为 `--length-width`
+    Length,
+}
+
+/// 划分训练、验证、测试集，写出 `train.txt`、`dev.txt`、`test.txt` 与记录划分的 `split.json`
+#[derive(Parser, Debug)]
+pub struct Split {
+    #[clap(value_enum)]
+    task: Task,
+    #[clap(long, value_enum, default_value_t = CorpusFormat::Ltp)]
+    format: CorpusFormat,
+    /// 小于 1 的小数为比例，否则为句子数
+    #[clap(long, value_parser = parse_size, default_value = "0.1")]
+    dev: SplitSize,
+    #[clap(long, value_parser = parse_size, default_value = "0.1")]
+    test: SplitSize,
+    #[clap(long, default_value_t = 0)]
+    seed: u64,
+    #[clap(long, value_enum, default_value_t = StratifyArg::None)]
+    stratify: StratifyArg,
+    #[clap(long, default_value_t = 10)]
+    length_width: usize,
+    /// 输出目录，按行格式写出
+    #[clap(short, long, default_value = ".")]
+    output: PathBuf,
+    #[clap(required = true)]
+    inputs: Vec<String>,
+}
+
+fn parse_size(size: &str) -> Result<SplitSize> {
+    if size.contains('.') {
+        let ratio: f64 = size.parse()?;
+        if !(0.0..=1.0).contains(&ratio) {
+            return Err(anyhow!("ratio must be in [0, 1]"));
+        }
+        return Ok(SplitSize::Ratio(ratio));
+    }
+    Ok(SplitSize::Count(size.parse()?))
+}
+
+#[derive(Serialize)]
+struct Manifest<'a> {
+    inputs: &'a [String],
+    #[serde(flatten)]
+    split: &'a SplitManifest,
+}
+
+impl Split {
+    pub fn execute(self) -> Result<()> {
+        let mut sentences = Vec::new();
+        for input in &self.inputs {
+            sentences.extend(self.format.read(input, self.task)?);
+        }
+        let stratify = match self.stratify {
+            StratifyArg::None => Stratify::None,
+            StratifyArg::Label => Stratify::Label,
+            StratifyArg::Length => Stratify::Length(self.length_width),
+        };
+        let manifest = Splitter::default()
+            .dev(self.dev)
+            .test(self.test)
+            .seed(self.seed)
+            .stratify(stratify)
+            .split(&sentences)?;
+
+        std::fs::create_dir_all(&self.output)?;
+        let splits = manifest.apply(&sentences)?;
+        for (name, split) in ["train", "dev", "test"].into_iter().zip(splits) {
+            let file = File::create(self.output.join(format!("{name}.txt")))?;
+            println!("{name}: {} sentences", split.len());
+            write_ltp(BufWriter::new(file), split)?;
+        }
+        let file = File::create(self.output.join("split.json"))?;
+        let manifest = Manifest {
+            inputs: &self.inputs,
+            split: &manifest,
+        };
+        serde_json::to_writer_pretty(BufWriter::new(file), &manifest)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,177 @@
+use crate::predict::pipeline;
+use anyhow::Result;
+use clap::Parser;
+use ltp::{LtpPipeline, Sentence};
+use serde::Serialize;
+use std::io::{self, BufRead, BufWriter, Write};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// 常驻的过滤模式：从标准输入逐行读取文档，向标准输出逐行写出 JSON
+#[derive(Parser, Debug)]
+pub struct Stream {
+    #[clap(long)]
+    cws: String,
+    #[clap(long)]
+    pos: Option<String>,
+    /// 依赖词性标注模型
+    #[clap(long)]
+    ner: Option<String>,
+
+    /// 每写出多少行刷新一次输出
+    #[clap(long, value_parser, default_value_t = 1)]
+    flush_lines: usize,
+    /// 距上次刷新超过该毫秒数时刷新输出，等待输入时也会按时刷新
+    #[clap(long, value_parser)]
+    flush_ms: Option<u64>,
+}
+
+/// 未加载的模型对应的字段不输出
+#[derive(Serialize)]
+struct SentenceRecord {
+    text: String,
+    words: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pos: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    ner: Option<Vec<String>>,
+}
+
+impl From<Sentence> for SentenceRecord {
+    fn from(sentence: Sentence) -> Self {
+        let labels = |labels: Option<ltp::InternedLabels>| {
+            labels.map(|labels| labels.iter().map(String::from).collect())
+        };
+        Self {
+            pos: labels(sentence.pos),
+            ner: labels(sentence.ner),
+            text: sentence.text,
+            words: sentence.words,
+        }
+    }
+}
+
+/// 处理失败的行输出 `{"text": ..., "error": ...}`，不中断后续输入
+#[derive(Serialize)]
+struct DocumentRecord<'a> {
+    text: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    sentences: Option<Vec<SentenceRecord>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+impl<'a> DocumentRecord<'a> {
+    fn process(pipeline: &LtpPipeline, text: &'a str) -> Self {
+        let (sentences, error) = match pipeline.process_document(text) {
+            Ok(sentences) => (Some(sentences.into_iter().map(Into::into).collect()), None),
+            Err(err) => (None, Some(err.to_string())),
+        };
+        Self {
+            text,
+            sentences,
+            error,
+        }
+    }
+}
+
+impl Stream {
+    pub fn execute(self) -> Result<()> {
+        let pipeline = pipeline(&self.cws, self.pos.as_deref(), self.ner.as_deref())?;
+        let flush_lines = self.flush_lines.max(1);
+        let flush_interval = self.flush_ms.map(Duration::from_millis);
+
+        // 由单独的线程读取标准输入，等待输入时仍能按 `flush_ms` 刷新已写出的行
+        let (tx, rx) = mpsc::channel();
+        thread::spawn(move || {
+            for line in io::stdin().lock().lines() {
+                if tx.send(line).is_err() {
+                    break;
+                }
+            }
+        });
+
+        let mut writer = BufWriter::new(io::stdout().lock());
+        let mut pending = 0;
+        let mut last_flush = Instant::now();
+        loop {
+            let line = match flush_interval.filter(|_| pending > 0) {
+                Some(interval) => {
+                    match rx.recv_timeout(interval.saturating_sub(last_flush.elapsed())) {
+                        Ok(line) => line,
+                        Err(RecvTimeoutError::Timeout) => {
+                            writer.flush()?;
+                            pending = 0;
+                            last_flush = Instant::now();
+                            continue;
+                        }
+                        Err(RecvTimeoutError::Disconnected) => break,
+                    }
+                }
+                None => match rx.recv() {
+                    Ok(line) => line,
+                    Err(_) => break,
+                },
+            };
+            let line = line?;
+            serde_json::to_writer(
+                &mut writer,
+                &DocumentRecord::process(&pipeline, line.trim()),
+            )?;
+            writer.write_all(b"\n")?;
+
+            pending += 1;
+            let expired = flush_interval.is_some_and(|interval| last_flush.elapsed() >= interval);
+            if pending >= flush_lines || expired {
+                writer.flush()?;
+                pending = 0;
+                last_flush = Instant::now();
+            }
+        }
+        writer.flush()?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ltp::pipeline::{Annotation, PipelineStage, StageMetadata};
+
+    struct Whitespace;
+
+    impl PipelineStage for Whitespace {
+        fn metadata(&self) -> StageMetadata {
+            StageMetadata::new("whitespace")
+                .inputs(&[Annotation::Text])
+                .outputs(&[Annotation::Words])
+        }
+
+        fn process(&self, sentence: &mut Sentence) -> Result<()> {
+            if sentence.text.contains('!') {
+                return Err(anyhow::anyhow!("unexpected `!`"));
+            }
+            sentence.words = sentence.text.split_whitespace().map(String::from).collect();
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_document_record() -> Result<()> {
+        let pipeline = LtpPipeline::new().add_stage(Whitespace);
+
+        let record = DocumentRecord::process(&pipeline, "他 叫 汤姆。");
+        assert_eq!(
+            serde_json::to_string(&record)?,
+            r#"{"text":"他 叫 汤姆。","sentences":[{"text":"他 叫 汤姆。","words":["他","叫","汤姆。"]}]}"#
+        );
+
+        let record = DocumentRecord::process(&pipeline, "你好！再见!");
+        assert_eq!(
+            serde_json::to_string(&record)?,
+            r#"{"text":"你好！再见!","error":"unexpected `!`"}"#
+        );
+        Ok(())
+    }
+}
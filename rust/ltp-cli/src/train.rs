@@ -0,0 +1,363 @@
+use crate::model::output_format;
+use anyhow::{anyhow, Result};
+use clap::{ArgAction, Parser, ValueEnum};
+use ltp::corpus::{
+    read_conll, read_ltp, AnnotatedSentence, Augmenter, ConllFormat, DigitRandomization,
+    EntitySubstitution, LabelDiscovery, LabelOrder, PunctuationVariation, SampleDefinition,
+    SynonymSubstitution,
+};
+use ltp::perceptron::{
+    write_curve, CacheHeader, CompressedReader, ConfigDefinition, Definition, ModelTask,
+    SerdeModel, TrainConfig,
+};
+use ltp::{
+    Algorithm, CWSDefinition, Codec, ModelSerde, NERDefinition, POSDefinition, PaMode, Trainer,
+};
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufRead, BufWriter};
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, ValueEnum)]
+pub enum Task {
+    Cws,
+    Pos,
+    Ner,
+}
+
+impl From<Task> for ModelTask {
+    fn from(task: Task) -> Self {
+        match task {
+            Task::Cws => ModelTask::CWS,
+            Task::Pos => ModelTask::POS,
+            Task::Ner => ModelTask::NER,
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, ValueEnum)]
+pub enum CorpusFormat {
+    /// 空格分隔的 `词/词性/实体`，与 `examples` 相同
+    Ltp,
+    /// 词、词性、组块标签
+    Conll2000,
+    /// 词、词性、组块标签、实体标签
+    Conll2003,
+}
+
+impl CorpusFormat {
+    /// 读取语料，行格式下按任务决定每个词带几个标签
+    pub fn read(self, path: &str, task: Task) -> Result<Vec<AnnotatedSentence>> {
+        let reader = CompressedReader::open(path)?;
+        match self {
+            CorpusFormat::Ltp => read_ltp(reader, task.into()),
+            CorpusFormat::Conll2000 => read_conll(reader, ConllFormat::conll2000()),
+            CorpusFormat::Conll2003 => read_conll(reader, ConllFormat::conll2003()),
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, ValueEnum)]
+pub enum LabelOrderArg {
+    Lexicographic,
+    FirstSeen,
+    Frequency,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, ValueEnum)]
+pub enum AlgorithmArg {
+    Ap,
+    Pa,
+    PaI,
+    PaII,
+    Hogwild,
+}
+
+/// 训练模型
+#[derive(Parser, Debug)]
+pub struct Train {
+    #[clap(value_enum, required_unless_present = "config")]
+    task: Option<Task>,
+
+    /// 从 TOML 配置文件读取任务、语料与训练参数，此时忽略其余训练参数
+    #[clap(long, conflicts_with = "task")]
+    config: Option<String>,
+
+    // 训练参数
+    #[clap(long, value_parser, default_value_t = 10)]
+    epoch: usize,
+    #[clap(long, action = ArgAction::Set, default_value_t = true)]
+    shuffle: bool,
+    #[clap(long, value_enum, default_value_t = AlgorithmArg::Ap)]
+    algorithm: AlgorithmArg,
+    /// AP 与 Hogwild 的训练线程数
+    #[clap(long, value_parser, default_value_t = 8)]
+    threads: usize,
+    #[clap(long, value_parser, default_value_t = 0.5)]
+    pa_margin: f64,
+    #[clap(long, value_parser, default_value_t = 8)]
+    eval_threads: usize,
+    #[clap(long, action = ArgAction::Set, default_value_t = true)]
+    verbose: bool,
+
+    // 模型压缩参数
+    #[clap(long, action = ArgAction::Set, default_value_t = true)]
+    compress: bool,
+    #[clap(long, value_parser, default_value_t = 0.3)]
+    ratio: f64,
+    #[clap(long, value_parser, default_value_t = 1e-3)]
+    threshold: f64,
+
+    // 数据集
+    /// 训练语料，也可以是 `ltp preprocess` 写出的特征缓存
+    #[clap(short, long, required_unless_present = "config")]
+    train: Option<String>,
+    /// 额外的训练语料及其采样权重，形如 `social.txt=3.0`，可以重复；`--train` 的权重为 1
+    #[clap(long = "domain", value_parser = parse_domain)]
+    domains: Vec<(String, f64)>,
+    /// 验证语料，也可以是与训练集使用同一个标签表的特征缓存
+    #[clap(short, long)]
+    eval: Option<String>,
+    /// 语料格式，CoNLL 格式下组块分析按命名实体识别训练
+    #[clap(long, value_enum, default_value_t = CorpusFormat::Ltp)]
+    format: CorpusFormat,
+    /// 标签表，每行一个，缺省时从训练集中发现；给出时保持其中标签的下标，新标签追加在后面
+    #[clap(long)]
+    vocab: Option<String>,
+    /// 新发现标签的排列顺序
+    #[clap(long, value_enum, default_value_t = LabelOrderArg::Lexicographic)]
+    label_order: LabelOrderArg,
+
+    // 数据增强
+    /// 混入增强样本，数值为增强样本数与训练集句子数之比；总是随机化数字与变换全半角标点
+    #[clap(long, value_parser)]
+    augment: Option<f64>,
+    #[clap(long, value_parser, default_value_t = 0)]
+    augment_seed: u64,
+    /// 实体替换词典，每行为 `类型 实体`
+    #[clap(long, requires = "augment")]
+    entity_dict: Option<String>,
+    /// 同义词替换词典，每行一组以空白分隔的同义词
+    #[clap(long, requires = "augment")]
+    synonym_dict: Option<String>,
+
+    /// 写入模型元数据的自定义标签，形如 `key=value`，可以重复
+    #[clap(long = "tag", value_parser = parse_tag)]
+    tags: Vec<(String, String)>,
+
+    /// 不保存模型，改为依次用训练集的 10%、20%…100% 训练并在验证集上评测，把学习曲线以 CSV 写到文件
+    #[clap(long, requires = "eval")]
+    curve: Option<String>,
+    /// 学习曲线的点数，训练集按此等分
+    #[clap(long, requires = "curve", default_value_t = 10)]
+    curve_steps: usize,
+
+    /// 模型保存路径，`.json` 保存为 JSON，其余为 avro；`.gz`、`.zst` 结尾时压缩
+    #[clap(short, long, default_value = "model.bin")]
+    model: String,
+}
+
+/// `vocab` 中的标签保持原有下标，语料中新出现的标签追加在后面
+pub fn discover_labels(
+    task: Task,
+    sentences: &[AnnotatedSentence],
+    vocab: Option<&str>,
+    order: LabelOrder,
+) -> Result<Vec<String>> {
+    let mut pinned = Vec::new();
+    if let Some(path) = vocab {
+        let lines = CompressedReader::open(path)?.lines();
+        pinned.extend(lines.map_while(Result::ok).filter(|s| !s.is_empty()));
+    }
+    let known = pinned.len();
+    let labels = LabelDiscovery::new()
+        .pin(pinned)
+        .order(order)
+        .discover_corpus(sentences, task.into())?;
+    if vocab.is_some() && labels.len() > known {
+        eprintln!("labels not in vocab: {:?}", &labels[known..]);
+    }
+    Ok(labels)
+}
+
+fn is_cache(path: &str) -> Result<bool> {
+    Ok(CacheHeader::from_file(path)?.is_some())
+}
+
+fn parse_domain(domain: &str) -> Result<(String, f64)> {
+    let (path, weight) = domain
+        .rsplit_once('=')
+        .ok_or_else(|| anyhow!("domain must be in the form of path=weight"))?;
+    Ok((path.to_string(), weight.parse()?))
+}
+
+fn parse_tag(tag: &str) -> Result<(String, String)> {
+    let (key, value) = tag
+        .split_once('=')
+        .ok_or_else(|| anyhow!("tag must be in the form of key=value"))?;
+    Ok((key.to_string(), value.to_string()))
+}
+
+impl Train {
+    /// `--vocab` 中的标签保持原有下标，训练集中新出现的标签追加在后面；训练集为特征缓存时使用缓存的标签表
+    fn vocab(&self, task: Task) -> Result<Vec<String>> {
+        let train = self.train.as_ref().expect("required by clap");
+        if let Some(header) = CacheHeader::from_file(train)? {
+            return Ok(header.labels);
+        }
+        let mut sentences = self.format.read(train, task)?;
+        for (path, _) in &self.domains {
+            sentences.extend(self.format.read(path, task)?);
+        }
+        let order = match self.label_order {
+            LabelOrderArg::Lexicographic => LabelOrder::Lexicographic,
+            LabelOrderArg::FirstSeen => LabelOrder::FirstSeen,
+            LabelOrderArg::Frequency => LabelOrder::Frequency,
+        };
+        discover_labels(task, &sentences, self.vocab.as_deref(), order)
+    }
+
+    fn algorithm(&self) -> Algorithm<f64> {
+        match self.algorithm {
+            AlgorithmArg::Ap => Algorithm::AP(self.threads),
+            AlgorithmArg::Pa => Algorithm::PA(PaMode::Pa),
+            AlgorithmArg::PaI => Algorithm::PA(PaMode::PaI(self.pa_margin)),
+            AlgorithmArg::PaII => Algorithm::PA(PaMode::PaII(self.pa_margin)),
+            AlgorithmArg::Hogwild => Algorithm::Hogwild(self.threads),
+        }
+    }
+
+    fn conll(&self) -> Option<ConllFormat> {
+        match self.format {
+            CorpusFormat::Ltp => None,
+            CorpusFormat::Conll2000 => Some(ConllFormat::conll2000()),
+            CorpusFormat::Conll2003 => Some(ConllFormat::conll2003()),
+        }
+    }
+
+    fn augmenter(&self, ratio: f64) -> Result<Augmenter> {
+        let mut augmenter = Augmenter::new()
+            .ratio(ratio)
+            .seed(self.augment_seed)
+            .transform(DigitRandomization)
+            .transform(PunctuationVariation::default());
+        if let Some(path) = &self.entity_dict {
+            let entities = EntitySubstitution::from_reader(CompressedReader::open(path)?)?;
+            augmenter = augmenter.transform(entities);
+        }
+        if let Some(path) = &self.synonym_dict {
+            let synonyms = SynonymSubstitution::from_reader(CompressedReader::open(path)?)?;
+            augmenter = augmenter.transform(synonyms);
+        }
+        Ok(augmenter)
+    }
+
+    fn run<Define>(&self, task: Task, definition: Define) -> Result<()>
+    where
+        Define: SampleDefinition + Send + Sync + 'static,
+        SerdeModel<Define, f64>: for<'de> ModelSerde<'de>,
+    {
+        let train = self.train.as_ref().expect("required by clap");
+        let mut trainer = Trainer::new()
+            .definition(definition)
+            .epoch(self.epoch)
+            .shuffle(self.shuffle)
+            .algorithm(self.algorithm())
+            .eval_threads(self.eval_threads)
+            .verbose(self.verbose)
+            .compress(self.compress)
+            .ratio(self.ratio)
+            .threshold(self.threshold);
+        trainer = match (self.augment, self.conll()) {
+            _ if is_cache(train)? => {
+                if self.augment.is_some() || self.conll().is_some() {
+                    return Err(anyhow!(
+                        "a feature cache can not be augmented or read as CoNLL"
+                    ));
+                }
+                trainer.train_cache(train)?
+            }
+            (Some(ratio), _) => {
+                let sentences = self.format.read(train, task)?;
+                trainer.train_sentences(&self.augmenter(ratio)?.mix(&sentences))?
+            }
+            (None, Some(format)) => trainer.train_conll(train, format)?,
+            (None, None) => trainer.train_file(train)?,
+        };
+        for (path, weight) in &self.domains {
+            trainer = match self.conll() {
+                Some(format) => {
+                    let samples = trainer.load_conll(path, format)?;
+                    trainer.train_domain(path, samples, *weight)?
+                }
+                None => trainer.train_file_weighted(path, *weight)?,
+            };
+        }
+        if let Some(eval) = &self.eval {
+            trainer = match self.conll() {
+                _ if is_cache(eval)? => trainer.eval_cache(eval)?,
+                Some(format) => trainer.eval_conll(eval, format)?,
+                None => trainer.eval_file(eval)?,
+            };
+        }
+        for (key, value) in &self.tags {
+            trainer = trainer.tag(key, value);
+        }
+        if let Some(path) = &self.curve {
+            return self.learning_curve(trainer, path);
+        }
+        build_and_save(trainer, &self.model)
+    }
+
+    fn learning_curve<Define>(&self, mut trainer: Trainer<Define, f64>, path: &str) -> Result<()>
+    where
+        Define: Definition + Send + Sync + 'static,
+    {
+        let steps = self.curve_steps.max(1);
+        let fractions: Vec<f64> = (1..=steps).map(|i| i as f64 / steps as f64).collect();
+        let points = trainer.learning_curve::<HashMap<String, usize>, Vec<f64>>(&fractions, 0)?;
+        write_curve(BufWriter::new(File::create(path)?), &points)?;
+        for point in &points {
+            println!("{point}");
+        }
+        Ok(())
+    }
+
+    fn run_config<Define>(&self, config: &TrainConfig) -> Result<()>
+    where
+        Define: ConfigDefinition + Send + Sync + 'static,
+        SerdeModel<Define, f64>: for<'de> ModelSerde<'de>,
+    {
+        let trainer = config.trainer::<Define, f64>()?;
+        let model = match config.model_path() {
+            Some(path) => path.to_string_lossy().into_owned(),
+            None => self.model.clone(),
+        };
+        build_and_save(trainer, &model)
+    }
+
+    pub fn execute(self) -> Result<()> {
+        if let Some(path) = &self.config {
+            let config = TrainConfig::from_file(path)?;
+            return match config.task {
+                ModelTask::CWS => self.run_config::<CWSDefinition>(&config),
+                ModelTask::POS => self.run_config::<POSDefinition>(&config),
+                ModelTask::NER => self.run_config::<NERDefinition>(&config),
+            };
+        }
+        match self.task.expect("required by clap") {
+            task @ Task::Cws => self.run(task, CWSDefinition::default()),
+            task @ Task::Pos => self.run(task, POSDefinition::new(self.vocab(task)?)),
+            task @ Task::Ner => self.run(task, NERDefinition::new(self.vocab(task)?)),
+        }
+    }
+}
+
+fn build_and_save<Define>(trainer: Trainer<Define, f64>, path: &str) -> Result<()>
+where
+    Define: Definition + Send + Sync + 'static,
+    SerdeModel<Define, f64>: for<'de> ModelSerde<'de>,
+{
+    let trainer = trainer.display();
+    let model: SerdeModel<Define, f64> = trainer.build::<HashMap<String, usize>, Vec<f64>>()?;
+    model.save_file(path, output_format(path, Codec::Deflate))
+}
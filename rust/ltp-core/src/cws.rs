@@ -0,0 +1,86 @@
+//! 分词：与 `ltp` 的 `CWSDefinition` 使用相同的特征模板（不含 `char-type`、`cross-char`、
+//! `near-char-type` 编译选项），空白与控制字符不产生特征，也不会出现在词的首尾
+use crate::decode::decode;
+use crate::model::StaticModel;
+use alloc::string::String;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::fmt::{Arguments, Write};
+
+/// 与 `ltp::perceptron::is_skipped_char` 相同
+pub fn is_skipped_char(ch: char) -> bool {
+    ch.is_whitespace() || ch.is_control()
+}
+
+/// 第 `i` 个字的特征，按 `ltp` 的抽取顺序逐个交给 `emit`
+pub fn char_features(chars: &[char], i: usize, buffer: &mut String, mut emit: impl FnMut(&str)) {
+    let mut feature = |args: Arguments| {
+        buffer.clear();
+        let _ = buffer.write_fmt(args);
+        emit(buffer);
+    };
+    let cur = chars[i];
+    feature(format_args!("2{}", cur));
+    if i >= 1 {
+        let pre = chars[i - 1];
+        feature(format_args!("1{}", pre));
+        feature(format_args!("6{}{}", pre, cur));
+        if i >= 2 {
+            let pre2 = chars[i - 2];
+            feature(format_args!("0{}", pre2));
+            feature(format_args!("5{}{}", pre2, pre));
+            if pre2 == cur {
+                feature(format_args!("c"));
+            }
+        }
+    }
+    if let Some(&next) = chars.get(i + 1) {
+        feature(format_args!("3{}", next));
+        feature(format_args!("7{}{}", cur, next));
+        if let Some(&next2) = chars.get(i + 2) {
+            feature(format_args!("4{}", next2));
+            feature(format_args!("8{}{}", next, next2));
+        }
+    }
+}
+
+impl<'a> StaticModel<'a> {
+    /// 每个字（跳过空白与控制字符）的字节位置与预测的标签下标
+    pub fn tag_chars(&self, text: &str) -> (Vec<usize>, Vec<usize>) {
+        let (index, chars): (Vec<usize>, Vec<char>) = text
+            .char_indices()
+            .filter(|&(_, ch)| !is_skipped_char(ch))
+            .unzip();
+        let label_num = self.label_num();
+        let mut emission = vec![0.0f32; chars.len() * label_num];
+        let mut buffer = String::new();
+        for (i, scores) in emission.chunks_exact_mut(label_num).enumerate() {
+            char_features(&chars, i, &mut buffer, |feature| {
+                if let Some(row) = self.feature_row(feature) {
+                    self.add_row(row, scores);
+                }
+            });
+        }
+        (index, decode(self, &emission))
+    }
+
+    /// 按 `B`/`M`/`E`/`S` 标签切分，词从 `B`、`S` 或上一个字为 `E`、`S` 的位置开始
+    pub fn segment<'t>(&self, text: &'t str) -> Vec<&'t str> {
+        let (index, labels) = self.tag_chars(text);
+        let tag = |i: usize| self.label(labels[i]).as_bytes().first().copied();
+        let mut words = Vec::new();
+        let mut start = 0;
+        for i in 0..labels.len() {
+            let is_end = i + 1 == labels.len()
+                || matches!(tag(i), Some(b'E' | b'S'))
+                || matches!(tag(i + 1), Some(b'B' | b'S'));
+            if is_end {
+                let last = index[i];
+                let width = text[last..].chars().next().map_or(0, char::len_utf8);
+                words.push(&text[index[start]..last + width]);
+                start = i + 1;
+            }
+        }
+        words
+    }
+}
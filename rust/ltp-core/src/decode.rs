@@ -0,0 +1,48 @@
+use crate::model::{Decoder, StaticModel};
+use alloc::vec;
+use alloc::vec::Vec;
+
+/// `emission` 按 `[position * label_num + label]` 排列，与 `ltp` 中的解码器一致：
+/// viterbi 回溯起点取最后一个最大值，其余取第一个最大值
+pub fn decode(model: &StaticModel, emission: &[f32]) -> Vec<usize> {
+    let label_num = model.label_num();
+    let length = emission.len() / label_num;
+    if length == 0 {
+        return Vec::new();
+    }
+    if model.decoder() == Decoder::Argmax {
+        return emission
+            .chunks_exact(label_num)
+            .map(|scores| {
+                (0..label_num).fold(0, |best, l| if scores[l] > scores[best] { l } else { best })
+            })
+            .collect();
+    }
+
+    let mut backptr = vec![0usize; length * label_num];
+    let mut last = emission[..label_num].to_vec();
+    let mut now = vec![0.0f32; label_num];
+    for i in 1..length {
+        let base = i * label_num;
+        for cur in 0..label_num {
+            let mut max_score = f32::MIN;
+            for (prev, &score) in last.iter().enumerate() {
+                let score = score + emission[base + cur] + model.weight(prev * label_num + cur);
+                if score > max_score {
+                    max_score = score;
+                    backptr[base + cur] = prev;
+                    now[cur] = score;
+                }
+            }
+        }
+        core::mem::swap(&mut last, &mut now);
+    }
+
+    let mut label = (0..label_num).fold(0, |best, l| if last[l] >= last[best] { l } else { best });
+    let mut path = vec![0; length];
+    for i in (0..length).rev() {
+        path[i] = label;
+        label = backptr[i * label_num + label];
+    }
+    path
+}
@@ -0,0 +1,63 @@
+//! LTP 分词推理的 `no_std` 核心：只依赖 `core` 与 `alloc`，没有文件读写，
+//! 模型以 [`StaticModel`] 格式直接从 `&[u8]`（例如烧录在 flash 中的 `include_bytes!`）读取
+//!
+//! 模型由 `ltp` 的 `PortableModel::to_embedded` 或 `ltp convert --embedded` 导出。
+//!
+//! ```ignore
+//! static MODEL: &[u8] = include_bytes!("cws.ltpc");
+//!
+//! let model = ltp_core::StaticModel::from_bytes(MODEL)?;
+//! let words = model.segment("他叫汤姆去拿外衣。");
+//! ```
+#![no_std]
+
+extern crate alloc;
+
+mod cws;
+mod decode;
+mod model;
+
+pub use cws::{char_features, is_skipped_char};
+pub use decode::decode;
+pub use model::{Decoder, Error, StaticModel, MAGIC, VERSION};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::vec;
+
+    #[test]
+    fn test_static_model() -> Result<(), Error> {
+        let labels = ["S", "B", "M", "E"];
+        // 前 4 行为转移矩阵，第 4 行：ch[0]ch[1] = 汤姆 时倾向 B，第 5 行：ch[-1]ch[0] = 汤姆 时倾向 E
+        let mut weights = vec![0.0; 6 * 4];
+        weights[4 * 4 + 1] = 2.0;
+        weights[5 * 4 + 3] = 2.0;
+        let bytes = StaticModel::encode(
+            Decoder::Viterbi,
+            &labels,
+            &[("7汤姆", 4), ("6汤姆", 5)],
+            &weights,
+        );
+        let model = StaticModel::from_bytes(&bytes)?;
+        assert_eq!(model.label(3), "E");
+        assert_eq!(model.feature_row("6汤姆"), Some(5));
+        assert_eq!(model.feature_row("2汤"), None);
+        assert_eq!(model.segment("他叫 汤姆\n"), ["他", "叫", "汤姆"]);
+        assert!(model.segment(" ").is_empty());
+
+        assert_eq!(
+            StaticModel::from_bytes(&bytes[..bytes.len() - 1]).unwrap_err(),
+            Error::Truncated
+        );
+        assert_eq!(
+            StaticModel::from_bytes(b"LTP").unwrap_err(),
+            Error::Truncated
+        );
+        assert_eq!(
+            StaticModel::from_bytes(b"abcd").unwrap_err(),
+            Error::BadMagic
+        );
+        Ok(())
+    }
+}
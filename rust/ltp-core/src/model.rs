@@ -0,0 +1,250 @@
+//! 只读、零拷贝的二进制模型，可以直接放在 flash 中
+//!
+//! 所有整数为小端 `u32`，不要求对齐：
+//!
+//! ```text
+//! "LTPC" | version | decoder | label_num | row_num
+//! labels:   count | offsets[count + 1] | bytes
+//! features: count | offsets[count + 1] | bytes      按字节序排列
+//! rows:     row[count]                               与 features 一一对应
+//! weights:  f32[row_num * label_num]                 第 r 行第 l 列为 weights[r * label_num + l]
+//! ```
+//!
+//! 使用 viterbi 解码时前 `label_num` 行为转移矩阵。
+use alloc::vec::Vec;
+use core::fmt::{Display, Formatter};
+
+pub const MAGIC: &[u8; 4] = b"LTPC";
+pub const VERSION: u32 = 1;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Decoder {
+    Argmax = 0,
+    Viterbi = 1,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Error {
+    BadMagic,
+    UnsupportedVersion(u32),
+    Truncated,
+    Malformed(&'static str),
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Error::BadMagic => write!(f, "not an ltp-core model"),
+            Error::UnsupportedVersion(version) => write!(f, "unsupported version {}", version),
+            Error::Truncated => write!(f, "model is truncated"),
+            Error::Malformed(reason) => write!(f, "malformed model: {}", reason),
+        }
+    }
+}
+
+fn read_u32(data: &[u8], index: usize) -> u32 {
+    let bytes = &data[index * 4..index * 4 + 4];
+    u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]])
+}
+
+fn split(data: &[u8], len: usize) -> Result<(&[u8], &[u8]), Error> {
+    if data.len() < len {
+        return Err(Error::Truncated);
+    }
+    Ok(data.split_at(len))
+}
+
+fn split_u32s(data: &[u8], count: usize) -> Result<(&[u8], &[u8]), Error> {
+    let len = count.checked_mul(4).ok_or(Error::Truncated)?;
+    split(data, len)
+}
+
+/// 字符串表：`count | offsets[count + 1] | bytes`
+#[derive(Debug, Clone, Copy)]
+struct Table<'a> {
+    offsets: &'a [u8],
+    bytes: &'a [u8],
+    len: usize,
+}
+
+impl<'a> Table<'a> {
+    fn parse(data: &'a [u8]) -> Result<(Self, &'a [u8]), Error> {
+        let (count, data) = split_u32s(data, 1)?;
+        let len = read_u32(count, 0) as usize;
+        let (offsets, data) = split_u32s(data, len + 1)?;
+        let mut last = 0;
+        for index in 0..=len {
+            let offset = read_u32(offsets, index);
+            if offset < last {
+                return Err(Error::Malformed("string offsets are not sorted"));
+            }
+            last = offset;
+        }
+        let (bytes, data) = split(data, last as usize)?;
+        Ok((
+            Self {
+                offsets,
+                bytes,
+                len,
+            },
+            data,
+        ))
+    }
+
+    fn get(&self, index: usize) -> &'a [u8] {
+        let start = read_u32(self.offsets, index) as usize;
+        let end = read_u32(self.offsets, index + 1) as usize;
+        &self.bytes[start..end]
+    }
+
+    fn write(out: &mut Vec<u8>, strings: &[&[u8]]) {
+        out.extend_from_slice(&(strings.len() as u32).to_le_bytes());
+        let mut offset = 0u32;
+        out.extend_from_slice(&offset.to_le_bytes());
+        for string in strings {
+            offset += string.len() as u32;
+            out.extend_from_slice(&offset.to_le_bytes());
+        }
+        for string in strings {
+            out.extend_from_slice(string);
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct StaticModel<'a> {
+    decoder: Decoder,
+    label_num: usize,
+    labels: Table<'a>,
+    features: Table<'a>,
+    rows: &'a [u8],
+    weights: &'a [u8],
+}
+
+impl<'a> StaticModel<'a> {
+    /// 只检查结构，不复制数据
+    pub fn from_bytes(data: &'a [u8]) -> Result<Self, Error> {
+        let (magic, data) = split(data, 4)?;
+        if magic != MAGIC {
+            return Err(Error::BadMagic);
+        }
+        let (header, data) = split_u32s(data, 4)?;
+        let version = read_u32(header, 0);
+        if version != VERSION {
+            return Err(Error::UnsupportedVersion(version));
+        }
+        let decoder = match read_u32(header, 1) {
+            0 => Decoder::Argmax,
+            1 => Decoder::Viterbi,
+            _ => return Err(Error::Malformed("unknown decoder")),
+        };
+        let label_num = read_u32(header, 2) as usize;
+        let row_num = read_u32(header, 3) as usize;
+
+        let (labels, data) = Table::parse(data)?;
+        if labels.len != label_num || label_num == 0 {
+            return Err(Error::Malformed("label count mismatch"));
+        }
+        if (0..label_num).any(|i| core::str::from_utf8(labels.get(i)).is_err()) {
+            return Err(Error::Malformed("labels are not utf-8"));
+        }
+        let (features, data) = Table::parse(data)?;
+        let (rows, data) = split_u32s(data, features.len)?;
+        if (0..features.len).any(|i| read_u32(rows, i) as usize >= row_num) {
+            return Err(Error::Malformed("feature row out of range"));
+        }
+        if decoder == Decoder::Viterbi && row_num < label_num {
+            return Err(Error::Malformed("missing transition matrix"));
+        }
+        let weight_num = row_num
+            .checked_mul(label_num)
+            .ok_or(Error::Malformed("too many weights"))?;
+        let (weights, _) = split_u32s(data, weight_num)?;
+
+        Ok(Self {
+            decoder,
+            label_num,
+            labels,
+            features,
+            rows,
+            weights,
+        })
+    }
+
+    /// 写出模型，`features` 为特征字符串与参数行号，顺序任意；参数转为 `f32` 保存
+    pub fn encode(
+        decoder: Decoder,
+        labels: &[&str],
+        features: &[(&str, usize)],
+        weights: &[f64],
+    ) -> Vec<u8> {
+        let mut features = features.to_vec();
+        features.sort_unstable_by(|a, b| a.0.as_bytes().cmp(b.0.as_bytes()));
+        let label_num = labels.len().max(1);
+
+        let mut out = Vec::with_capacity(weights.len() * 4 + features.len() * 16);
+        out.extend_from_slice(MAGIC);
+        for value in [
+            VERSION,
+            decoder as u32,
+            labels.len() as u32,
+            (weights.len() / label_num) as u32,
+        ] {
+            out.extend_from_slice(&value.to_le_bytes());
+        }
+        let labels: Vec<&[u8]> = labels.iter().map(|label| label.as_bytes()).collect();
+        Table::write(&mut out, &labels);
+        let keys: Vec<&[u8]> = features.iter().map(|(key, _)| key.as_bytes()).collect();
+        Table::write(&mut out, &keys);
+        for (_, row) in &features {
+            out.extend_from_slice(&(*row as u32).to_le_bytes());
+        }
+        for &weight in weights {
+            out.extend_from_slice(&(weight as f32).to_le_bytes());
+        }
+        out
+    }
+
+    pub fn decoder(&self) -> Decoder {
+        self.decoder
+    }
+
+    pub fn label_num(&self) -> usize {
+        self.label_num
+    }
+
+    pub fn label(&self, index: usize) -> &'a str {
+        // 加载时已经检查过
+        core::str::from_utf8(self.labels.get(index)).unwrap_or_default()
+    }
+
+    pub fn feature_num(&self) -> usize {
+        self.features.len
+    }
+
+    /// 二分查找特征所在的参数行
+    pub fn feature_row(&self, feature: &str) -> Option<usize> {
+        let (mut low, mut high) = (0, self.features.len);
+        while low < high {
+            let mid = low + (high - low) / 2;
+            match self.features.get(mid).cmp(feature.as_bytes()) {
+                core::cmp::Ordering::Less => low = mid + 1,
+                core::cmp::Ordering::Greater => high = mid,
+                core::cmp::Ordering::Equal => return Some(read_u32(self.rows, mid) as usize),
+            }
+        }
+        None
+    }
+
+    pub fn weight(&self, index: usize) -> f32 {
+        f32::from_bits(read_u32(self.weights, index))
+    }
+
+    /// 把一行参数累加到 `scores` 上
+    pub fn add_row(&self, row: usize, scores: &mut [f32]) {
+        let base = row * self.label_num;
+        for (label, score) in scores.iter_mut().enumerate() {
+            *score += self.weight(base + label);
+        }
+    }
+}
@@ -0,0 +1,89 @@
+package cn.edu.hit.ltp;
+
+import java.util.concurrent.locks.ReadWriteLock;
+import java.util.concurrent.locks.ReentrantReadWriteLock;
+
+/**
+ * cws -> pos -> ner 流水线。
+ *
+ * <p>加载后只读，同一个实例可以被多个线程并发调用；{@link #close()} 会等待正在进行的调用返回，
+ * 之后的调用抛出 {@link LtpException}。
+ * 在 Spark/Flink 中建议每个 executor 只加载一次，例如放在静态的懒加载字段中。
+ *
+ * <p>默认通过 {@code System.loadLibrary("ltp_jni")} 加载动态库，也可以通过系统属性
+ * {@code ltp.library.path} 指定动态库的完整路径。
+ */
+public final class Ltp implements AutoCloseable {
+  static {
+    String path = System.getProperty("ltp.library.path");
+    if (path != null) {
+      System.load(path);
+    } else {
+      System.loadLibrary("ltp_jni");
+    }
+  }
+
+  private final ReadWriteLock lock = new ReentrantReadWriteLock();
+  private long handle;
+
+  /** 仅加载分词模型 */
+  public Ltp(String cws) {
+    this(cws, null, null);
+  }
+
+  /** 由模型文件路径创建，{@code pos} 与 {@code ner} 可以为 {@code null}，ner 依赖 pos */
+  public Ltp(String cws, String pos, String ner) {
+    this.handle = load(cws, pos, ner);
+  }
+
+  /** 处理一句话 */
+  public Sentence process(String text) {
+    lock.readLock().lock();
+    try {
+      return process(handle, text);
+    } finally {
+      lock.readLock().unlock();
+    }
+  }
+
+  /** 先分句再逐句处理 */
+  public Sentence[] processDocument(String text) {
+    lock.readLock().lock();
+    try {
+      return processDocument(handle, text);
+    } finally {
+      lock.readLock().unlock();
+    }
+  }
+
+  /** 并行处理一批句子，按输入顺序返回 */
+  public Sentence[] processBatch(String[] texts) {
+    lock.readLock().lock();
+    try {
+      return processBatch(handle, texts);
+    } finally {
+      lock.readLock().unlock();
+    }
+  }
+
+  @Override
+  public void close() {
+    lock.writeLock().lock();
+    try {
+      release(handle);
+      handle = 0;
+    } finally {
+      lock.writeLock().unlock();
+    }
+  }
+
+  private static native long load(String cws, String pos, String ner);
+
+  private static native void release(long handle);
+
+  private static native Sentence process(long handle, String text);
+
+  private static native Sentence[] processDocument(long handle, String text);
+
+  private static native Sentence[] processBatch(long handle, String[] texts);
+}
@@ -0,0 +1,8 @@
+package cn.edu.hit.ltp;
+
+/** 模型加载或推理失败时由 native 层抛出 */
+public class LtpException extends RuntimeException {
+  public LtpException(String message) {
+    super(message);
+  }
+}
@@ -0,0 +1,32 @@
+package cn.edu.hit.ltp;
+
+import java.io.Serializable;
+import java.util.Arrays;
+
+/** 一句话的分析结果，未加载的模型对应的字段为 {@code null} */
+public final class Sentence implements Serializable {
+  private static final long serialVersionUID = 1L;
+
+  public final String text;
+  public final String[] words;
+  public final String[] pos;
+  public final String[] ner;
+
+  public Sentence(String text, String[] words, String[] pos, String[] ner) {
+    this.text = text;
+    this.words = words;
+    this.pos = pos;
+    this.ner = ner;
+  }
+
+  @Override
+  public String toString() {
+    return "Sentence(words="
+        + Arrays.toString(words)
+        + ", pos="
+        + Arrays.toString(pos)
+        + ", ner="
+        + Arrays.toString(ner)
+        + ")";
+  }
+}
@@ -0,0 +1,208 @@
+//! LTP 的 JNI 绑定，对应的 Java 封装见 `java/src/main/java/cn/edu/hit/ltp`
+use anyhow::{anyhow, Result};
+use jni::objects::{JClass, JObject, JObjectArray, JString, JValue};
+use jni::sys::{jlong, jobject, jobjectArray};
+use jni::JNIEnv;
+use ltp::{CWSModel, InternedLabels, LtpPipeline, ModelSerde, NERModel, POSModel, Sentence};
+
+const EXCEPTION: &str = "cn/edu/hit/ltp/LtpException";
+const SENTENCE: &str = "cn/edu/hit/ltp/Sentence";
+const SENTENCE_CTOR: &str =
+    "(Ljava/lang/String;[Ljava/lang/String;[Ljava/lang/String;[Ljava/lang/String;)V";
+
+/// 出错时抛出 `LtpException` 并返回默认值，Java 侧会在 native 方法返回后收到异常
+fn throw_on_err<T: Default>(env: &mut JNIEnv, result: Result<T>) -> T {
+    result.unwrap_or_else(|err| {
+        // 已有未处理的 Java 异常时保留原异常
+        if !env.exception_check().unwrap_or(false) {
+            let _ = env.throw_new(EXCEPTION, err.to_string());
+        }
+        T::default()
+    })
+}
+
+fn get_string(env: &mut JNIEnv, string: &JString) -> Result<Option<String>> {
+    if string.is_null() {
+        return Ok(None);
+    }
+    Ok(Some(env.get_string(string)?.into()))
+}
+
+fn load<M: for<'de> ModelSerde<'de>>(path: &str) -> Result<M> {
+    let bytes = std::fs::read(path).map_err(|err| anyhow!("{}: {}", path, err))?;
+    M::from_bytes(&bytes).map_err(|err| anyhow!("{}: {}", path, err))
+}
+
+fn pipeline<'a>(handle: jlong) -> Result<&'a LtpPipeline> {
+    if handle == 0 {
+        return Err(anyhow!("the pipeline has been closed"));
+    }
+    Ok(unsafe { &*(handle as *const LtpPipeline) })
+}
+
+fn string_array<'local, S: AsRef<str>>(
+    env: &mut JNIEnv<'local>,
+    items: &[S],
+) -> Result<JObjectArray<'local>> {
+    let array = env.new_object_array(items.len() as i32, "java/lang/String", JObject::null())?;
+    for (i, item) in items.iter().enumerate() {
+        let item = env.new_string(item.as_ref())?;
+        env.set_object_array_element(&array, i as i32, &item)?;
+        env.delete_local_ref(item)?;
+    }
+    Ok(array)
+}
+
+/// 未运行的阶段为 `None`，对应 Java 侧的 `null`
+fn labels(labels: &Option<InternedLabels>) -> Option<Vec<&str>> {
+    labels.as_ref().map(InternedLabels::to_vec)
+}
+
+fn optional_string_array<'local>(
+    env: &mut JNIEnv<'local>,
+    items: Option<Vec<&str>>,
+) -> Result<JObjectArray<'local>> {
+    match items {
+        Some(items) => string_array(env, &items),
+        None => Ok(JObjectArray::default()),
+    }
+}
+
+fn sentence<'local>(env: &mut JNIEnv<'local>, sentence: &Sentence) -> Result<JObject<'local>> {
+    let text = env.new_string(&sentence.text)?;
+    let words = string_array(env, &sentence.words)?;
+    let pos = optional_string_array(env, labels(&sentence.pos))?;
+    let ner = optional_string_array(env, labels(&sentence.ner))?;
+    let object = env.new_object(
+        SENTENCE,
+        SENTENCE_CTOR,
+        &[
+            JValue::Object(&text),
+            JValue::Object(&words),
+            JValue::Object(&pos),
+            JValue::Object(&ner),
+        ],
+    )?;
+    Ok(object)
+}
+
+fn sentences<'local>(env: &mut JNIEnv<'local>, items: &[Sentence]) -> Result<JObjectArray<'local>> {
+    let array = env.new_object_array(items.len() as i32, SENTENCE, JObject::null())?;
+    for (i, item) in items.iter().enumerate() {
+        let item = sentence(env, item)?;
+        env.set_object_array_element(&array, i as i32, &item)?;
+        env.delete_local_ref(item)?;
+    }
+    Ok(array)
+}
+
+#[no_mangle]
+pub extern "system" fn Java_cn_edu_hit_ltp_Ltp_load<'local>(
+    mut env: JNIEnv<'local>,
+    _class: JClass<'local>,
+    cws: JString<'local>,
+    pos: JString<'local>,
+    ner: JString<'local>,
+) -> jlong {
+    let result = (|| {
+        let cws = get_string(&mut env, &cws)?.ok_or_else(|| anyhow!("cws model is required"))?;
+        let mut pipeline = LtpPipeline::new().with_cws(load::<CWSModel>(&cws)?);
+        if let Some(pos) = get_string(&mut env, &pos)? {
+            pipeline = pipeline.with_pos(load::<POSModel>(&pos)?);
+        }
+        if let Some(ner) = get_string(&mut env, &ner)? {
+            pipeline = pipeline.with_ner(load::<NERModel>(&ner)?);
+        }
+        pipeline.validate()?;
+        Ok(Box::into_raw(Box::new(pipeline)) as jlong)
+    })();
+    throw_on_err(&mut env, result)
+}
+
+#[no_mangle]
+pub extern "system" fn Java_cn_edu_hit_ltp_Ltp_release<'local>(
+    _env: JNIEnv<'local>,
+    _class: JClass<'local>,
+    handle: jlong,
+) {
+    if handle != 0 {
+        drop(unsafe { Box::from_raw(handle as *mut LtpPipeline) });
+    }
+}
+
+#[no_mangle]
+pub extern "system" fn Java_cn_edu_hit_ltp_Ltp_process<'local>(
+    mut env: JNIEnv<'local>,
+    _class: JClass<'local>,
+    handle: jlong,
+    text: JString<'local>,
+) -> jobject {
+    let result = (|| {
+        let text = get_string(&mut env, &text)?.ok_or_else(|| anyhow!("text is null"))?;
+        let result = pipeline(handle)?.process(&text)?;
+        Ok(sentence(&mut env, &result)?.into_raw())
+    })();
+    throw_on_err(&mut env, result.map(Some)).unwrap_or(std::ptr::null_mut())
+}
+
+#[no_mangle]
+pub extern "system" fn Java_cn_edu_hit_ltp_Ltp_processDocument<'local>(
+    mut env: JNIEnv<'local>,
+    _class: JClass<'local>,
+    handle: jlong,
+    text: JString<'local>,
+) -> jobjectArray {
+    let result = (|| {
+        let text = get_string(&mut env, &text)?.ok_or_else(|| anyhow!("text is null"))?;
+        let result = pipeline(handle)?.process_document(&text)?;
+        Ok(sentences(&mut env, &result)?.into_raw())
+    })();
+    throw_on_err(&mut env, result.map(Some)).unwrap_or(std::ptr::null_mut())
+}
+
+#[no_mangle]
+pub extern "system" fn Java_cn_edu_hit_ltp_Ltp_processBatch<'local>(
+    mut env: JNIEnv<'local>,
+    _class: JClass<'local>,
+    handle: jlong,
+    texts: JObjectArray<'local>,
+) -> jobjectArray {
+    let result = (|| {
+        let len = env.get_array_length(&texts)?;
+        let mut batch = Vec::with_capacity(len as usize);
+        for i in 0..len {
+            let text = JString::from(env.get_object_array_element(&texts, i)?);
+            let value =
+                get_string(&mut env, &text)?.ok_or_else(|| anyhow!("texts[{}] is null", i))?;
+            env.delete_local_ref(text)?;
+            batch.push(value);
+        }
+        let batch: Vec<&str> = batch.iter().map(|text| text.as_str()).collect();
+        let result = pipeline(handle)?.process_batch(&batch)?;
+        Ok(sentences(&mut env, &result)?.into_raw())
+    })();
+    throw_on_err(&mut env, result.map(Some)).unwrap_or(std::ptr::null_mut())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ltp::LabelSet;
+
+    #[test]
+    fn test_closed_pipeline() {
+        let error = pipeline(0).unwrap_err();
+        assert_eq!(error.to_string(), "the pipeline has been closed");
+    }
+
+    #[test]
+    fn test_labels() -> Result<()> {
+        let mut sentence = Sentence::new("他叫汤姆");
+        assert_eq!(labels(&sentence.pos), None);
+
+        let tags = LabelSet::new(vec!["r".into(), "v".into(), "nh".into()]);
+        sentence.pos = Some(InternedLabels::from_indices(tags, &[0, 1, 2])?);
+        assert_eq!(labels(&sentence.pos), Some(vec!["r", "v", "nh"]));
+        Ok(())
+    }
+}
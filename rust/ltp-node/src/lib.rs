@@ -0,0 +1,127 @@
+//! LTP 的 Node.js 绑定，批量接口在 libuv 线程池中运行，不阻塞事件循环
+use ltp::{CWSModel, LtpPipeline, ModelSerde, NERModel, POSModel};
+use napi::bindgen_prelude::*;
+use napi_derive::napi;
+use std::sync::Arc;
+
+fn to_napi(err: anyhow::Error) -> Error {
+    Error::from_reason(err.to_string())
+}
+
+fn load<M: for<'de> ModelSerde<'de>>(path: &str) -> Result<M> {
+    let bytes =
+        std::fs::read(path).map_err(|err| Error::from_reason(format!("{}: {}", path, err)))?;
+    M::from_bytes(&bytes).map_err(to_napi)
+}
+
+/// 一句话的分析结果，未加载的模型对应的字段为 `undefined`
+#[napi(object)]
+pub struct Sentence {
+    pub text: String,
+    pub words: Vec<String>,
+    pub pos: Option<Vec<String>>,
+    pub ner: Option<Vec<String>>,
+}
+
+impl From<ltp::Sentence> for Sentence {
+    fn from(sentence: ltp::Sentence) -> Self {
+        let labels = |labels: Option<ltp::InternedLabels>| {
+            labels.map(|labels| labels.iter().map(String::from).collect())
+        };
+        Sentence {
+            pos: labels(sentence.pos),
+            ner: labels(sentence.ner),
+            text: sentence.text,
+            words: sentence.words,
+        }
+    }
+}
+
+pub struct ProcessDocument {
+    pipeline: Arc<LtpPipeline>,
+    text: String,
+}
+
+impl Task for ProcessDocument {
+    type Output = Vec<ltp::Sentence>;
+    type JsValue = Vec<Sentence>;
+
+    fn compute(&mut self) -> Result<Self::Output> {
+        self.pipeline.process_document(&self.text).map_err(to_napi)
+    }
+
+    fn resolve(&mut self, _env: Env, output: Self::Output) -> Result<Self::JsValue> {
+        Ok(output.into_iter().map(Sentence::from).collect())
+    }
+}
+
+pub struct PredictBatch {
+    pipeline: Arc<LtpPipeline>,
+    texts: Vec<String>,
+}
+
+impl Task for PredictBatch {
+    type Output = Vec<ltp::Sentence>;
+    type JsValue = Vec<Sentence>;
+
+    fn compute(&mut self) -> Result<Self::Output> {
+        let texts: Vec<&str> = self.texts.iter().map(|t| t.as_str()).collect();
+        self.pipeline.process_batch(&texts).map_err(to_napi)
+    }
+
+    fn resolve(&mut self, _env: Env, output: Self::Output) -> Result<Self::JsValue> {
+        Ok(output.into_iter().map(Sentence::from).collect())
+    }
+}
+
+/// cws -> pos -> ner 流水线，加载后只读，可以被并发的多个请求共享
+#[napi]
+pub struct Ltp {
+    pipeline: Arc<LtpPipeline>,
+}
+
+#[napi]
+impl Ltp {
+    /// 由模型文件路径创建，`pos` 与 `ner` 可以省略
+    #[napi(constructor)]
+    pub fn new(cws: String, pos: Option<String>, ner: Option<String>) -> Result<Self> {
+        let mut pipeline = LtpPipeline::new().with_cws(load::<CWSModel>(&cws)?);
+        if let Some(pos) = pos {
+            pipeline = pipeline.with_pos(load::<POSModel>(&pos)?);
+        }
+        if let Some(ner) = ner {
+            pipeline = pipeline.with_ner(load::<NERModel>(&ner)?);
+        }
+        pipeline.validate().map_err(to_napi)?;
+        Ok(Ltp {
+            pipeline: Arc::new(pipeline),
+        })
+    }
+
+    /// 同步处理一句话，耗时的文本请使用异步接口
+    #[napi]
+    pub fn process(&self, text: String) -> Result<Sentence> {
+        self.pipeline
+            .process(&text)
+            .map(Sentence::from)
+            .map_err(to_napi)
+    }
+
+    /// 先分句再逐句处理，返回 `Promise<Sentence[]>`
+    #[napi(ts_return_type = "Promise<Array<Sentence>>")]
+    pub fn process_document(&self, text: String) -> AsyncTask<ProcessDocument> {
+        AsyncTask::new(ProcessDocument {
+            pipeline: self.pipeline.clone(),
+            text,
+        })
+    }
+
+    /// 并行处理一批句子，返回 `Promise<Sentence[]>`
+    #[napi(ts_return_type = "Promise<Array<Sentence>>")]
+    pub fn predict_batch(&self, texts: Vec<String>) -> AsyncTask<PredictBatch> {
+        AsyncTask::new(PredictBatch {
+            pipeline: self.pipeline.clone(),
+            texts,
+        })
+    }
+}
@@ -0,0 +1,236 @@
+use crate::pipeline::{annotate, labels, tokens, Error};
+use ltp::pipeline::Annotation;
+use ltp::LtpPipeline;
+use std::pin::Pin;
+use std::sync::Arc;
+use tokio::sync::mpsc;
+use tokio_stream::wrappers::ReceiverStream;
+use tokio_stream::{Stream, StreamExt};
+use tonic::{Request, Response, Status, Streaming};
+
+pub mod proto {
+    tonic::include_proto!("ltp.v1");
+}
+
+use proto::{
+    NerRequest, NerResponse, PipelineBatchRequest, PipelineRequest, SegmentRequest,
+    SegmentResponse, Sentence, TagRequest, TagResponse,
+};
+
+impl From<Error> for Status {
+    fn from(err: Error) -> Self {
+        match err {
+            Error::Unavailable(_) => Status::failed_precondition(err.to_string()),
+            Error::Invalid(message) => Status::invalid_argument(message),
+            Error::Internal(message) => Status::internal(message),
+        }
+    }
+}
+
+impl From<ltp::Sentence> for Sentence {
+    fn from(sentence: ltp::Sentence) -> Self {
+        Sentence {
+            text: sentence.text,
+            words: sentence.words,
+            pos: labels(sentence.pos).unwrap_or_default(),
+            ner: labels(sentence.ner).unwrap_or_default(),
+        }
+    }
+}
+
+type SentenceStream = Pin<Box<dyn Stream<Item = Result<Sentence, Status>> + Send>>;
+
+fn stream(sentences: Vec<ltp::Sentence>) -> SentenceStream {
+    Box::pin(tokio_stream::iter(
+        sentences.into_iter().map(Sentence::from).map(Ok),
+    ))
+}
+
+/// 推理在阻塞线程池中运行，不占用 tokio 的工作线程
+#[derive(Debug, Clone)]
+pub struct LtpService {
+    pipeline: Arc<LtpPipeline>,
+}
+
+impl LtpService {
+    pub fn new(pipeline: Arc<LtpPipeline>) -> Self {
+        Self { pipeline }
+    }
+
+    async fn blocking<T, F>(&self, f: F) -> Result<T, Status>
+    where
+        T: Send + 'static,
+        F: FnOnce(&LtpPipeline) -> Result<T, Error> + Send + 'static,
+    {
+        let pipeline = self.pipeline.clone();
+        tokio::task::spawn_blocking(move || f(&pipeline))
+            .await
+            .map_err(|err| Error::Internal(err.to_string()))?
+            .map_err(Status::from)
+    }
+}
+
+#[tonic::async_trait]
+impl proto::ltp_server::Ltp for LtpService {
+    async fn segment(
+        &self,
+        request: Request<SegmentRequest>,
+    ) -> Result<Response<SegmentResponse>, Status> {
+        let text = request.into_inner().text;
+        let sentence = self
+            .blocking(move |pipeline| {
+                annotate(pipeline, ltp::Sentence::new(text), Annotation::Words)
+            })
+            .await?;
+        Ok(Response::new(SegmentResponse {
+            words: sentence.words,
+        }))
+    }
+
+    async fn tag(&self, request: Request<TagRequest>) -> Result<Response<TagResponse>, Status> {
+        let words = request.into_inner().words;
+        let sentence = self
+            .blocking(move |pipeline| annotate(pipeline, tokens(words, None)?, Annotation::Pos))
+            .await?;
+        let tags = labels(sentence.pos).unwrap_or_default();
+        Ok(Response::new(TagResponse { tags }))
+    }
+
+    async fn ner(&self, request: Request<NerRequest>) -> Result<Response<NerResponse>, Status> {
+        let NerRequest { words, pos } = request.into_inner();
+        let sentence = self
+            .blocking(move |pipeline| {
+                annotate(pipeline, tokens(words, Some(pos))?, Annotation::Ner)
+            })
+            .await?;
+        let tags = labels(sentence.ner).unwrap_or_default();
+        Ok(Response::new(NerResponse { tags }))
+    }
+
+    async fn pipeline(
+        &self,
+        request: Request<PipelineRequest>,
+    ) -> Result<Response<Sentence>, Status> {
+        let text = request.into_inner().text;
+        let sentence = self
+            .blocking(move |pipeline| Ok(pipeline.process(&text)?))
+            .await?;
+        Ok(Response::new(sentence.into()))
+    }
+
+    type ProcessDocumentStream = SentenceStream;
+
+    async fn process_document(
+        &self,
+        request: Request<PipelineRequest>,
+    ) -> Result<Response<Self::ProcessDocumentStream>, Status> {
+        let text = request.into_inner().text;
+        let sentences = self
+            .blocking(move |pipeline| Ok(pipeline.process_document(&text)?))
+            .await?;
+        Ok(Response::new(stream(sentences)))
+    }
+
+    type PipelineBatchStream = SentenceStream;
+
+    async fn pipeline_batch(
+        &self,
+        request: Request<PipelineBatchRequest>,
+    ) -> Result<Response<Self::PipelineBatchStream>, Status> {
+        let texts = request.into_inner().texts;
+        let sentences = self
+            .blocking(move |pipeline| {
+                let texts: Vec<&str> = texts.iter().map(String::as_str).collect();
+                Ok(pipeline.process_batch(&texts)?)
+            })
+            .await?;
+        Ok(Response::new(stream(sentences)))
+    }
+
+    type PipelineStreamStream = SentenceStream;
+
+    async fn pipeline_stream(
+        &self,
+        request: Request<Streaming<PipelineRequest>>,
+    ) -> Result<Response<Self::PipelineStreamStream>, Status> {
+        let mut requests = request.into_inner();
+        let service = self.clone();
+        let (tx, rx) = mpsc::channel(16);
+        tokio::spawn(async move {
+            while let Some(request) = requests.next().await {
+                let result = match request {
+                    Ok(PipelineRequest { text }) => service
+                        .blocking(move |pipeline| Ok(pipeline.process(&text)?))
+                        .await
+                        .map(Sentence::from),
+                    Err(status) => Err(status),
+                };
+                if tx.send(result).await.is_err() {
+                    break;
+                }
+            }
+        });
+        Ok(Response::new(Box::pin(ReceiverStream::new(rx))))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::proto::ltp_server::Ltp;
+    use super::*;
+    use ltp::{CWSDefinition, CWSModel, POSDefinition, POSModel};
+    use std::collections::HashMap;
+    use tonic::Code;
+
+    fn service(pos: bool) -> LtpService {
+        let cws =
+            CWSModel::new_with_parameters(CWSDefinition::new(), HashMap::new(), vec![0.0; 16]);
+        let mut pipeline = LtpPipeline::new().with_cws(cws);
+        if pos {
+            let define = POSDefinition::new(vec!["n".into(), "v".into()]);
+            let pos = POSModel::new_with_parameters(define, HashMap::new(), Vec::new());
+            pipeline = pipeline.with_pos(pos);
+        }
+        LtpService::new(Arc::new(pipeline))
+    }
+
+    #[tokio::test]
+    async fn test_segment() {
+        let request = Request::new(SegmentRequest {
+            text: "他叫汤姆去拿外衣。".to_string(),
+        });
+        let response = service(false).segment(request).await.unwrap();
+        let words = response.into_inner().words;
+        assert!(!words.is_empty());
+        assert_eq!(words.concat(), "他叫汤姆去拿外衣。");
+    }
+
+    #[tokio::test]
+    async fn test_tag_without_pos() {
+        let request = Request::new(TagRequest {
+            words: vec!["他".to_string(), "叫".to_string()],
+        });
+        let status = service(false).tag(request).await.unwrap_err();
+        assert_eq!(status.code(), Code::FailedPrecondition);
+    }
+
+    #[tokio::test]
+    async fn test_pipeline_batch() {
+        let texts = vec!["他叫汤姆。", "去拿外衣", "", "好"];
+        let request = Request::new(PipelineBatchRequest {
+            texts: texts.iter().map(|text| text.to_string()).collect(),
+        });
+        let response = service(true).pipeline_batch(request).await.unwrap();
+        let sentences: Vec<Sentence> = response
+            .into_inner()
+            .collect::<Result<_, _>>()
+            .await
+            .unwrap();
+        let outputs: Vec<&str> = sentences.iter().map(|s| s.text.as_str()).collect();
+        assert_eq!(outputs, texts);
+        for sentence in &sentences {
+            assert_eq!(sentence.words.concat(), sentence.text);
+            assert_eq!(sentence.pos.len(), sentence.words.len());
+        }
+    }
+}
@@ -0,0 +1,206 @@
+use crate::pipeline::{annotate, labels, Error};
+use axum::extract::State;
+use axum::http::{header, StatusCode};
+use axum::response::{IntoResponse, Response};
+use axum::routing::{get, post};
+use axum::{Json, Router};
+use ltp::pipeline::Annotation;
+use ltp::{get_entities, LtpPipeline, Sentence};
+use rayon::prelude::*;
+use serde::Serialize;
+use std::sync::Arc;
+
+impl IntoResponse for Error {
+    fn into_response(self) -> Response {
+        let status = match self {
+            Error::Internal(_) => StatusCode::INTERNAL_SERVER_ERROR,
+            _ => StatusCode::BAD_REQUEST,
+        };
+        let body = Json(serde_json::json!({ "error": self.to_string() }));
+        (status, body).into_response()
+    }
+}
+
+/// 一个词及其在原文中的字符偏移 `[start, end)`
+#[derive(Debug, Serialize)]
+pub struct Token {
+    pub text: String,
+    pub start: usize,
+    pub end: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pos: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ner: Option<String>,
+}
+
+/// 一个命名实体及其在原文中的字符偏移 `[start, end)`
+#[derive(Debug, Serialize)]
+pub struct Entity {
+    pub label: String,
+    pub text: String,
+    pub start: usize,
+    pub end: usize,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Document {
+    pub text: String,
+    pub tokens: Vec<Token>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub entities: Option<Vec<Entity>>,
+}
+
+/// 依次在原文中查找每个词，返回字符偏移；找不到的词记为空区间
+fn char_offsets(text: &str, words: &[String]) -> Vec<(usize, usize)> {
+    let mut byte = 0;
+    let mut chars = 0;
+    words
+        .iter()
+        .map(|word| {
+            let (begin, end) = match text[byte..].find(word.as_str()) {
+                Some(i) => (byte + i, byte + i + word.len()),
+                None => (byte, byte),
+            };
+            chars += text[byte..begin].chars().count();
+            let start = chars;
+            chars += text[begin..end].chars().count();
+            byte = end;
+            (start, chars)
+        })
+        .collect()
+}
+
+impl From<Sentence> for Document {
+    fn from(sentence: Sentence) -> Self {
+        let offsets = char_offsets(&sentence.text, &sentence.words);
+        let pos = labels(sentence.pos);
+        let ner = labels(sentence.ner);
+        let entities = ner.as_ref().map(|ner| {
+            get_entities(ner)
+                .into_iter()
+                .map(|(label, begin, end)| Entity {
+                    label: label.to_string(),
+                    text: sentence.words[begin..=end].concat(),
+                    start: offsets[begin].0,
+                    end: offsets[end].1,
+                })
+                .collect()
+        });
+        let mut pos = pos.map(Vec::into_iter);
+        let mut ner = ner.map(Vec::into_iter);
+        let tokens = sentence
+            .words
+            .into_iter()
+            .zip(offsets)
+            .map(|(text, (start, end))| Token {
+                text,
+                start,
+                end,
+                pos: pos.as_mut().and_then(Iterator::next),
+                ner: ner.as_mut().and_then(Iterator::next),
+            })
+            .collect();
+        Document {
+            text: sentence.text,
+            tokens,
+            entities,
+        }
+    }
+}
+
+async fn batch<F>(
+    pipeline: Arc<LtpPipeline>,
+    texts: Vec<String>,
+    f: F,
+) -> Result<Json<Vec<Document>>, Error>
+where
+    F: Fn(&LtpPipeline, String) -> Result<Sentence, Error> + Send + Sync + 'static,
+{
+    tokio::task::spawn_blocking(move || {
+        texts
+            .into_par_iter()
+            .map(|text| f(&pipeline, text).map(Document::from))
+            .collect::<Result<Vec<_>, _>>()
+    })
+    .await
+    .map_err(|err| Error::Internal(err.to_string()))?
+    .map(Json)
+}
+
+async fn segment(
+    State(pipeline): State<Arc<LtpPipeline>>,
+    Json(texts): Json<Vec<String>>,
+) -> Result<Json<Vec<Document>>, Error> {
+    batch(pipeline, texts, |pipeline, text| {
+        annotate(pipeline, Sentence::new(text), Annotation::Words)
+    })
+    .await
+}
+
+async fn pos(
+    State(pipeline): State<Arc<LtpPipeline>>,
+    Json(texts): Json<Vec<String>>,
+) -> Result<Json<Vec<Document>>, Error> {
+    batch(pipeline, texts, |pipeline, text| {
+        annotate(pipeline, Sentence::new(text), Annotation::Pos)
+    })
+    .await
+}
+
+async fn ner(
+    State(pipeline): State<Arc<LtpPipeline>>,
+    Json(texts): Json<Vec<String>>,
+) -> Result<Json<Vec<Document>>, Error> {
+    batch(pipeline, texts, |pipeline, text| {
+        annotate(pipeline, Sentence::new(text), Annotation::Ner)
+    })
+    .await
+}
+
+async fn process(
+    State(pipeline): State<Arc<LtpPipeline>>,
+    Json(texts): Json<Vec<String>>,
+) -> Result<Json<Vec<Document>>, Error> {
+    batch(pipeline, texts, |pipeline, text| {
+        Ok(pipeline.process(&text)?)
+    })
+    .await
+}
+
+async fn metrics(State(pipeline): State<Arc<LtpPipeline>>) -> impl IntoResponse {
+    (
+        [(header::CONTENT_TYPE, "text/plain; version=0.0.4")],
+        crate::metrics::render(&pipeline.metrics()),
+    )
+}
+
+/// 请求体为文本的 JSON 数组，按输入顺序返回带字符偏移的结果
+pub fn router(pipeline: Arc<LtpPipeline>) -> Router {
+    Router::new()
+        .route("/segment", post(segment))
+        .route("/pos", post(pos))
+        .route("/ner", post(ner))
+        .route("/pipeline", post(process))
+        .route("/metrics", get(metrics))
+        .with_state(pipeline)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_char_offsets() {
+        let words: Vec<String> = ["他", "叫", "Tom", "去"]
+            .iter()
+            .map(|w| w.to_string())
+            .collect();
+        assert_eq!(
+            char_offsets("他叫 Tom去", &words),
+            vec![(0, 1), (1, 2), (3, 6), (6, 7)]
+        );
+
+        let words = vec!["他".to_string(), "她".to_string(), "叫".to_string()];
+        assert_eq!(char_offsets("他叫", &words), vec![(0, 1), (1, 1), (1, 2)]);
+    }
+}
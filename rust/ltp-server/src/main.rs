@@ -0,0 +1,82 @@
+//! LTP 的推理服务，默认提供 gRPC 接口，可选同时提供 HTTP/JSON 接口
+//! 模型加载后只读，由所有连接共享
+mod grpc;
+mod http;
+mod metrics;
+mod pipeline;
+
+use anyhow::{Context, Result};
+use clap::Parser;
+use grpc::proto::ltp_server::LtpServer;
+use grpc::LtpService;
+use ltp::{CWSModel, LtpPipeline, ModelSerde, NERModel, POSModel};
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+#[derive(Parser, Debug)]
+#[clap(author, version, about, long_about = None)]
+struct Args {
+    /// gRPC 监听地址
+    #[clap(long, value_parser, default_value = "0.0.0.0:50051")]
+    addr: SocketAddr,
+    /// HTTP/JSON 监听地址，省略时不启动
+    #[clap(long, value_parser)]
+    http: Option<SocketAddr>,
+    /// 分词模型
+    #[clap(long, value_parser)]
+    cws: PathBuf,
+    /// 词性标注模型
+    #[clap(long, value_parser)]
+    pos: Option<PathBuf>,
+    /// 命名实体识别模型，依赖词性标注模型
+    #[clap(long, value_parser)]
+    ner: Option<PathBuf>,
+}
+
+fn load<M: for<'de> ModelSerde<'de>>(path: &PathBuf) -> Result<M> {
+    let bytes = std::fs::read(path).with_context(|| format!("{}", path.display()))?;
+    M::from_bytes(&bytes).with_context(|| format!("{}", path.display()))
+}
+
+async fn shutdown() {
+    let _ = tokio::signal::ctrl_c().await;
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    let args = Args::parse();
+
+    let mut pipeline = LtpPipeline::new().with_cws(load::<CWSModel>(&args.cws)?);
+    if let Some(pos) = &args.pos {
+        pipeline = pipeline.with_pos(load::<POSModel>(pos)?);
+    }
+    if let Some(ner) = &args.ner {
+        pipeline = pipeline.with_ner(load::<NERModel>(ner)?);
+    }
+    pipeline.validate()?;
+    let pipeline = Arc::new(pipeline.enable_metrics(true));
+
+    let (mut reporter, health) = tonic_health::server::health_reporter();
+    reporter.set_serving::<LtpServer<LtpService>>().await;
+
+    eprintln!("ltp-server listening on {} (grpc)", args.addr);
+    let grpc = tonic::transport::Server::builder()
+        .add_service(health)
+        .add_service(LtpServer::new(LtpService::new(pipeline.clone())))
+        .serve_with_shutdown(args.addr, shutdown());
+
+    match args.http {
+        Some(addr) => {
+            let listener = tokio::net::TcpListener::bind(addr).await?;
+            eprintln!("ltp-server listening on {} (http)", addr);
+            let http =
+                axum::serve(listener, http::router(pipeline)).with_graceful_shutdown(shutdown());
+            tokio::try_join!(async { grpc.await.map_err(anyhow::Error::from) }, async {
+                http.await.map_err(anyhow::Error::from)
+            },)?;
+        }
+        None => grpc.await?,
+    }
+    Ok(())
+}
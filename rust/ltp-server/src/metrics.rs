@@ -0,0 +1,103 @@
+use ltp::pipeline::{PipelineMetrics, StageMetrics};
+use std::fmt::Write;
+
+fn counter(
+    out: &mut String,
+    metrics: &PipelineMetrics,
+    name: &str,
+    help: &str,
+    get: fn(&StageMetrics) -> u64,
+) {
+    let _ = writeln!(out, "# HELP {} {}", name, help);
+    let _ = writeln!(out, "# TYPE {} counter", name);
+    for stage in &metrics.stages {
+        let _ = writeln!(out, "{}{{stage=\"{}\"}} {}", name, stage.name, get(stage));
+    }
+}
+
+/// 以 Prometheus 文本格式输出流水线各阶段的调用次数、错误数、词数与延迟直方图
+pub fn render(metrics: &PipelineMetrics) -> String {
+    let mut out = String::new();
+    counter(
+        &mut out,
+        metrics,
+        "ltp_stage_calls_total",
+        "Number of stage calls.",
+        |s| s.calls,
+    );
+    counter(
+        &mut out,
+        metrics,
+        "ltp_stage_errors_total",
+        "Number of failed stage calls.",
+        |s| s.errors,
+    );
+    counter(
+        &mut out,
+        metrics,
+        "ltp_stage_tokens_total",
+        "Number of tokens produced.",
+        |s| s.tokens,
+    );
+
+    let name = "ltp_stage_latency_seconds";
+    let _ = writeln!(out, "# HELP {} Stage latency.", name);
+    let _ = writeln!(out, "# TYPE {} histogram", name);
+    for stage in &metrics.stages {
+        for (bound, count) in stage.cumulative_histogram() {
+            let le = bound.map_or_else(|| "+Inf".to_string(), |bound| bound.to_string());
+            let _ = writeln!(
+                out,
+                "{}_bucket{{stage=\"{}\",le=\"{}\"}} {}",
+                name, stage.name, le, count
+            );
+        }
+        let seconds = stage.total_time.as_secs_f64();
+        let _ = writeln!(out, "{}_sum{{stage=\"{}\"}} {}", name, stage.name, seconds);
+        let _ = writeln!(
+            out,
+            "{}_count{{stage=\"{}\"}} {}",
+            name, stage.name, stage.calls
+        );
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ltp::pipeline::LATENCY_BUCKETS_US;
+    use std::time::Duration;
+
+    #[test]
+    fn test_render() {
+        let mut latency_histogram = vec![0; LATENCY_BUCKETS_US.len() + 1];
+        latency_histogram[0] = 1;
+        latency_histogram[LATENCY_BUCKETS_US.len()] = 1;
+        let metrics = PipelineMetrics {
+            stages: vec![StageMetrics {
+                name: "cws".to_string(),
+                calls: 2,
+                errors: 1,
+                tokens: 5,
+                total_time: Duration::from_millis(3),
+                latency_histogram,
+            }],
+        };
+
+        let text = render(&metrics);
+        for line in [
+            "# TYPE ltp_stage_calls_total counter",
+            "ltp_stage_calls_total{stage=\"cws\"} 2",
+            "ltp_stage_errors_total{stage=\"cws\"} 1",
+            "ltp_stage_tokens_total{stage=\"cws\"} 5",
+            "ltp_stage_latency_seconds_bucket{stage=\"cws\",le=\"0.00001\"} 1",
+            "ltp_stage_latency_seconds_bucket{stage=\"cws\",le=\"1\"} 1",
+            "ltp_stage_latency_seconds_bucket{stage=\"cws\",le=\"+Inf\"} 2",
+            "ltp_stage_latency_seconds_sum{stage=\"cws\"} 0.003",
+            "ltp_stage_latency_seconds_count{stage=\"cws\"} 2",
+        ] {
+            assert!(text.lines().any(|l| l == line), "missing `{}`", line);
+        }
+    }
+}
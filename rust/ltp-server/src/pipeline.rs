@@ -0,0 +1,77 @@
+use ltp::pipeline::Annotation;
+use ltp::{InternedLabels, LabelSet, LtpPipeline, Sentence};
+use std::fmt::{Display, Formatter};
+
+/// 推理请求的错误，由 gRPC 与 HTTP 接口分别转换为各自的状态码
+#[derive(Debug)]
+pub enum Error {
+    /// 流水线中没有产出该标注层的模型
+    Unavailable(Annotation),
+    /// 非法输入
+    Invalid(String),
+    /// 推理线程异常退出
+    Internal(String),
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Error::Unavailable(annotation) => write!(f, "{} model is not loaded", annotation),
+            Error::Invalid(message) | Error::Internal(message) => f.write_str(message),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl From<anyhow::Error> for Error {
+    fn from(err: anyhow::Error) -> Self {
+        Error::Invalid(err.to_string())
+    }
+}
+
+/// 补全 `sentence` 直到产出 `target`，流水线中没有产出 `target` 的阶段时返回 [`Error::Unavailable`]
+pub fn annotate(
+    pipeline: &LtpPipeline,
+    sentence: Sentence,
+    target: Annotation,
+) -> Result<Sentence, Error> {
+    if !pipeline
+        .stages()
+        .iter()
+        .any(|stage| stage.outputs.contains(&target))
+    {
+        return Err(Error::Unavailable(target));
+    }
+    Ok(pipeline.annotate(sentence, target)?)
+}
+
+/// 由调用方分好的词与词性构造句子，词性的标签表只包含其中出现的标签
+pub fn tokens(words: Vec<String>, pos: Option<Vec<String>>) -> Result<Sentence, Error> {
+    let mut sentence = Sentence::new(words.concat());
+    if let Some(pos) = pos {
+        if words.len() != pos.len() {
+            return Err(Error::Invalid(format!(
+                "words and pos have different lengths: {} != {}",
+                words.len(),
+                pos.len()
+            )));
+        }
+        let mut tags = pos.clone();
+        tags.sort();
+        tags.dedup();
+        let tags = LabelSet::new(tags);
+        let ids = pos
+            .iter()
+            .map(|tag| tags.id(tag))
+            .collect::<Option<_>>()
+            .ok_or_else(|| Error::Invalid("too many distinct pos tags".to_string()))?;
+        sentence.pos = Some(InternedLabels::new(tags, ids));
+    }
+    sentence.words = words;
+    Ok(sentence)
+}
+
+pub fn labels(labels: Option<InternedLabels>) -> Option<Vec<String>> {
+    labels.map(|labels| labels.iter().map(String::from).collect())
+}
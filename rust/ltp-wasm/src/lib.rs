@@ -0,0 +1,99 @@
+//! LTP 的 WebAssembly 绑定，模型以字节数组传入，分词、词性标注与命名实体识别都在浏览器中完成
+use ltp::{CWSModel, ModelSerde, NERModel, POSModel};
+use wasm_bindgen::prelude::*;
+
+fn js_error(err: anyhow::Error) -> JsError {
+    JsError::new(&err.to_string())
+}
+
+/// 一句话的分析结果，未加载的模型对应的字段为 `undefined`
+#[wasm_bindgen(getter_with_clone)]
+#[derive(Debug, Clone, Default)]
+pub struct Sentence {
+    pub words: Vec<String>,
+    pub pos: Option<Vec<String>>,
+    pub ner: Option<Vec<String>>,
+}
+
+/// ```js
+/// const ltp = new Ltp(new Uint8Array(await (await fetch("cws_model.bin")).arrayBuffer()));
+/// ltp.loadPos(posBytes);
+/// ltp.analyze("他叫汤姆去拿外衣。").words;
+/// ```
+#[wasm_bindgen]
+#[derive(Debug)]
+pub struct Ltp {
+    cws: CWSModel,
+    pos: Option<POSModel>,
+    ner: Option<NERModel>,
+}
+
+#[wasm_bindgen]
+impl Ltp {
+    /// 由分词模型文件的内容创建
+    #[wasm_bindgen(constructor)]
+    pub fn new(cws: &[u8]) -> Result<Ltp, JsError> {
+        Ok(Ltp {
+            cws: CWSModel::from_bytes(cws).map_err(js_error)?,
+            pos: None,
+            ner: None,
+        })
+    }
+
+    #[wasm_bindgen(js_name = loadPos)]
+    pub fn load_pos(&mut self, pos: &[u8]) -> Result<(), JsError> {
+        self.pos = Some(POSModel::from_bytes(pos).map_err(js_error)?);
+        Ok(())
+    }
+
+    /// 命名实体识别依赖词性标注结果，需要先加载词性标注模型
+    #[wasm_bindgen(js_name = loadNer)]
+    pub fn load_ner(&mut self, ner: &[u8]) -> Result<(), JsError> {
+        self.ner = Some(NERModel::from_bytes(ner).map_err(js_error)?);
+        Ok(())
+    }
+
+    pub fn cut(&self, text: &str) -> Result<Vec<String>, JsError> {
+        let words = self.cws.predict(text).map_err(js_error)?;
+        Ok(words.into_iter().map(String::from).collect())
+    }
+
+    pub fn pos(&self, words: Vec<String>) -> Result<Vec<String>, JsError> {
+        let model = self
+            .pos
+            .as_ref()
+            .ok_or_else(|| JsError::new("pos model is not loaded"))?;
+        let words: Vec<&str> = words.iter().map(|w| w.as_str()).collect();
+        let tags = model.predict(&words).map_err(js_error)?;
+        Ok(tags.into_iter().map(String::from).collect())
+    }
+
+    pub fn ner(&self, words: Vec<String>, pos: Vec<String>) -> Result<Vec<String>, JsError> {
+        let model = self
+            .ner
+            .as_ref()
+            .ok_or_else(|| JsError::new("ner model is not loaded"))?;
+        let words: Vec<&str> = words.iter().map(|w| w.as_str()).collect();
+        let pos: Vec<&str> = pos.iter().map(|p| p.as_str()).collect();
+        let tags = model.predict((&words, &pos)).map_err(js_error)?;
+        Ok(tags.into_iter().map(String::from).collect())
+    }
+
+    /// 依次运行已加载的模型
+    pub fn analyze(&self, text: &str) -> Result<Sentence, JsError> {
+        let words = self.cws.predict(text).map_err(js_error)?;
+        let mut sentence = Sentence {
+            words: words.iter().map(|w| w.to_string()).collect(),
+            ..Default::default()
+        };
+        if let Some(model) = &self.pos {
+            let pos = model.predict(&words).map_err(js_error)?;
+            if let Some(ner) = &self.ner {
+                let tags = ner.predict((&words, &pos)).map_err(js_error)?;
+                sentence.ner = Some(tags.into_iter().map(String::from).collect());
+            }
+            sentence.pos = Some(pos.into_iter().map(String::from).collect());
+        }
+        Ok(sentence)
+    }
+}
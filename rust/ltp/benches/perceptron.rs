@@ -0,0 +1,241 @@
+use anyhow::Result;
+use criterion::{black_box, criterion_group, criterion_main, Criterion, Throughput};
+use itertools::Itertools;
+use ltp::perceptron::{Definition, Perceptron, TraitFeature};
+use ltp::{CWSDefinition, NERDefinition, POSDefinition, Trainer};
+use std::collections::{BTreeSet, HashMap};
+use std::fs::read_to_string;
+use std::path::PathBuf;
+
+type Model<D> = Perceptron<D, HashMap<String, usize>, Vec<f64>, f64>;
+
+fn data_path(name: &str) -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("benches")
+        .join("data")
+        .join(name)
+}
+
+/// 读取 `word/pos/ner` 格式的语料，按列拆分
+fn load_corpus() -> Result<Vec<Vec<Vec<String>>>> {
+    let text = read_to_string(data_path("ner.txt"))?;
+    Ok(text
+        .lines()
+        .filter(|line| !line.is_empty())
+        .map(|line| {
+            line.split_whitespace()
+                .map(|item| item.split('/').map(String::from).collect())
+                .collect()
+        })
+        .collect())
+}
+
+fn column(corpus: &[Vec<Vec<String>>], idx: usize) -> Vec<Vec<&str>> {
+    corpus
+        .iter()
+        .map(|sentence| sentence.iter().map(|item| item[idx].as_str()).collect())
+        .collect()
+}
+
+fn vocab(corpus: &[Vec<Vec<String>>], idx: usize) -> Vec<String> {
+    let labels: BTreeSet<&String> = corpus
+        .iter()
+        .flat_map(|sentence| sentence.iter().map(|item| &item[idx]))
+        .collect();
+    labels.into_iter().cloned().collect()
+}
+
+fn train<D>(definition: D, file: &str) -> Result<Model<D>>
+where
+    D: Definition + Sync + Send + 'static,
+{
+    Trainer::new_with_define(definition)
+        .epoch(3)
+        .verbose(false)
+        .compress(false)
+        .train_file(data_path(file))?
+        .build::<HashMap<String, usize>, Vec<f64>>()
+}
+
+struct Fixture {
+    sentences: Vec<String>,
+    words: Vec<Vec<&'static str>>,
+    poses: Vec<Vec<&'static str>>,
+    chars: usize,
+    cws: Model<CWSDefinition>,
+    pos: Model<POSDefinition>,
+    ner: Model<NERDefinition>,
+}
+
+impl Fixture {
+    fn new() -> Result<Self> {
+        // 语料很小，泄漏后可以直接借用 `&'static str`
+        let corpus: &'static [Vec<Vec<String>>] = Box::leak(load_corpus()?.into_boxed_slice());
+        let words = column(corpus, 0);
+        let poses = column(corpus, 1);
+        let sentences = words.iter().map(|w| w.concat()).collect_vec();
+        let chars = sentences.iter().map(|s| s.chars().count()).sum();
+
+        Ok(Fixture {
+            cws: train(CWSDefinition::default(), "cws.txt")?,
+            pos: train(POSDefinition::new(vocab(corpus, 1)), "pos.txt")?,
+            ner: train(NERDefinition::new(vocab(corpus, 2)), "ner.txt")?,
+            sentences,
+            words,
+            poses,
+            chars,
+        })
+    }
+}
+
+fn bench_features(c: &mut Criterion, fixture: &Fixture) {
+    let mut group = c.benchmark_group("features");
+    group.throughput(Throughput::Elements(fixture.chars as u64));
+
+    let mut buffer = Vec::new();
+    group.bench_function("cws", |b| {
+        b.iter(|| {
+            for sentence in &fixture.sentences {
+                let sentence = sentence.as_str();
+                black_box(
+                    fixture
+                        .cws
+                        .definition
+                        .parse_features_with_buffer(&sentence, &mut buffer)
+                        .unwrap(),
+                );
+            }
+        })
+    });
+    group.bench_function("pos", |b| {
+        b.iter(|| {
+            for words in &fixture.words {
+                black_box(
+                    fixture
+                        .pos
+                        .definition
+                        .parse_features_with_buffer(&words.as_slice(), &mut buffer)
+                        .unwrap(),
+                );
+            }
+        })
+    });
+    group.bench_function("ner", |b| {
+        b.iter(|| {
+            for (words, poses) in fixture.words.iter().zip(&fixture.poses) {
+                black_box(
+                    fixture
+                        .ner
+                        .definition
+                        .parse_features_with_buffer(
+                            &(words.as_slice(), poses.as_slice()),
+                            &mut buffer,
+                        )
+                        .unwrap(),
+                );
+            }
+        })
+    });
+    group.finish();
+}
+
+fn bench_lookup(c: &mut Criterion, fixture: &Fixture) {
+    let features = fixture
+        .sentences
+        .iter()
+        .flat_map(|sentence| {
+            let (_, features) = fixture
+                .cws
+                .definition
+                .parse_features(&sentence.as_str())
+                .unwrap();
+            features.as_flat().to_vec()
+        })
+        .collect_vec();
+
+    let mut group = c.benchmark_group("lookup");
+    group.throughput(Throughput::Elements(features.len() as u64));
+    group.bench_function("hash_map", |b| {
+        b.iter(|| {
+            for feature in &features {
+                black_box(fixture.cws.features.get_with_key(feature));
+            }
+        })
+    });
+    group.finish();
+}
+
+fn bench_decode(c: &mut Criterion, fixture: &Fixture) {
+    let mut buffer = Vec::new();
+    let inputs = fixture
+        .sentences
+        .iter()
+        .map(|sentence| {
+            let (_, features) = fixture
+                .cws
+                .definition
+                .parse_features_with_buffer(&sentence.as_str(), &mut buffer)
+                .unwrap();
+            fixture.cws.features.get_arena(&features)
+        })
+        .collect_vec();
+
+    let mut group = c.benchmark_group("decode");
+    group.throughput(Throughput::Elements(fixture.chars as u64));
+    group.bench_function("viterbi", |b| {
+        b.iter(|| {
+            for features in &inputs {
+                black_box(fixture.cws.decode(features));
+            }
+        })
+    });
+    group.finish();
+}
+
+fn bench_end_to_end(c: &mut Criterion, fixture: &Fixture) {
+    let mut group = c.benchmark_group("end_to_end");
+    group.throughput(Throughput::Elements(fixture.chars as u64));
+
+    group.bench_function("cws", |b| {
+        b.iter(|| {
+            for sentence in &fixture.sentences {
+                black_box(fixture.cws.predict(sentence).unwrap());
+            }
+        })
+    });
+    group.bench_function("pos", |b| {
+        b.iter(|| {
+            for words in &fixture.words {
+                black_box(fixture.pos.predict(words).unwrap());
+            }
+        })
+    });
+    group.bench_function("ner", |b| {
+        b.iter(|| {
+            for (words, poses) in fixture.words.iter().zip(&fixture.poses) {
+                black_box(fixture.ner.predict((words, poses)).unwrap());
+            }
+        })
+    });
+    group.bench_function("pipeline", |b| {
+        b.iter(|| {
+            for sentence in &fixture.sentences {
+                let words = fixture.cws.predict(sentence).unwrap();
+                let poses = fixture.pos.predict(&words).unwrap();
+                black_box(fixture.ner.predict((&words, &poses)).unwrap());
+            }
+        })
+    });
+    group.finish();
+}
+
+fn benches(c: &mut Criterion) {
+    let fixture = Fixture::new().expect("failed to train benchmark models");
+    bench_features(c, &fixture);
+    bench_lookup(c, &fixture);
+    bench_decode(c, &fixture);
+    bench_end_to_end(c, &fixture);
+}
+
+criterion_group!(perceptron, benches);
+criterion_main!(perceptron);
@@ -21,6 +21,7 @@ enum AlgorithmArg {
     Pa,
     PaI,
     PaII,
+    Hogwild,
 }
 
 #[derive(Parser, Debug)]
@@ -103,6 +104,7 @@ fn main() -> Result<()> {
                 AlgorithmArg::Pa => Algorithm::PA(PaMode::Pa),
                 AlgorithmArg::PaI => Algorithm::PA(PaMode::PaI(mode.pa_margin)),
                 AlgorithmArg::PaII => Algorithm::PA(PaMode::PaII(mode.pa_margin)),
+                AlgorithmArg::Hogwild => Algorithm::Hogwild(mode.ap_threads),
             };
 
             let trainer = Trainer::new()
@@ -1,5 +1,6 @@
 use itertools::multizip;
-use ltp::{CWSModel, Codec, Format, ModelSerde, NERModel, POSModel};
+use ltp::prelude::*;
+use ltp::Codec;
 use std::fs::File;
 
 fn main() -> Result<(), Box<dyn std::error::Error>> {
@@ -0,0 +1,250 @@
+//! 原文、规范化后的文本与分词结果之间的位置对齐：字节、字符与词的下标可以双向换算，
+//! 文本经过清洗或简繁转换时，词的区间可以投影回原文档中的字节
+use crate::clean::OffsetMap;
+use crate::drop_get_entities;
+use crate::pipeline::Sentence;
+use anyhow::{anyhow, Result};
+use std::ops::Range;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Alignment {
+    /// 文本中各字符的字节起点，末尾为文本长度
+    chars: Vec<usize>,
+    /// 各词在文本中的字节区间
+    tokens: Vec<Range<usize>>,
+    /// 文本被改写时到最初文本的映射
+    map: Option<OffsetMap>,
+    /// 最初文本在文档中的字节位置
+    offset: usize,
+}
+
+fn char_starts(text: &str) -> Vec<usize> {
+    text.char_indices()
+        .map(|(i, _)| i)
+        .chain(std::iter::once(text.len()))
+        .collect()
+}
+
+impl Alignment {
+    /// 词须依次出现在 `text` 中，词之间可以有空白等未被分词的字符
+    pub fn new<S: AsRef<str>>(text: &str, tokens: &[S]) -> Result<Self> {
+        let mut cursor = 0;
+        let mut spans = Vec::with_capacity(tokens.len());
+        for token in tokens.iter().map(AsRef::as_ref) {
+            let start = text[cursor..]
+                .find(token)
+                .ok_or_else(|| anyhow!("token `{}` not found after byte {}", token, cursor))?;
+            spans.push(cursor + start..cursor + start + token.len());
+            cursor += start + token.len();
+        }
+        Self::from_spans(text, spans)
+    }
+
+    /// 由各词的字节区间构造，区间须有序、互不重叠且落在字符边界上
+    pub fn from_spans(text: &str, tokens: Vec<Range<usize>>) -> Result<Self> {
+        let mut last = 0;
+        for span in &tokens {
+            let valid = last <= span.start
+                && span.start <= span.end
+                && text.is_char_boundary(span.start)
+                && text.is_char_boundary(span.end)
+                && span.end <= text.len();
+            if !valid {
+                return Err(anyhow!("invalid token span {}..{}", span.start, span.end));
+            }
+            last = span.end;
+        }
+        Ok(Self {
+            chars: char_starts(text),
+            tokens,
+            map: None,
+            offset: 0,
+        })
+    }
+
+    /// 句子的词，文本被改写时映射到改写前的文本，位置加上句子在文档中的偏移
+    pub fn from_sentence(sentence: &Sentence) -> Result<Self> {
+        let alignment = Self::new(&sentence.text, &sentence.words)?.offset(sentence.offset);
+        Ok(match &sentence.original {
+            Some((_, map)) => alignment.original(map.clone()),
+            None => alignment,
+        })
+    }
+
+    /// 文本由最初的文本改写而来，如 [`TextCleaner::clean`](crate::TextCleaner::clean) 的输出
+    pub fn original(mut self, map: OffsetMap) -> Self {
+        self.map = Some(map);
+        self
+    }
+
+    /// 最初文本在文档中的字节位置，如 [`Sentence::offset`]
+    pub fn offset(mut self, offset: usize) -> Self {
+        self.offset = offset;
+        self
+    }
+
+    pub fn len(&self) -> usize {
+        self.tokens.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tokens.is_empty()
+    }
+
+    /// 字节所在的字符的下标
+    pub fn byte_to_char(&self, byte: usize) -> Option<usize> {
+        let chars = self.chars.len() - 1;
+        (byte < self.chars[chars]).then(|| self.chars.partition_point(|&start| start <= byte) - 1)
+    }
+
+    /// 字符的字节起点，`char` 为字符数时返回文本长度
+    pub fn char_to_byte(&self, char: usize) -> Option<usize> {
+        self.chars.get(char).copied()
+    }
+
+    /// 字节所在的词，落在词之间时返回 `None`
+    pub fn byte_to_token(&self, byte: usize) -> Option<usize> {
+        let token = self.tokens.partition_point(|span| span.end <= byte);
+        self.tokens
+            .get(token)
+            .filter(|span| span.start <= byte)
+            .map(|_| token)
+    }
+
+    pub fn char_to_token(&self, char: usize) -> Option<usize> {
+        if char + 1 >= self.chars.len() {
+            return None;
+        }
+        self.byte_to_token(self.chars[char])
+    }
+
+    /// 词在文本中的字节区间
+    pub fn token_to_bytes(&self, token: usize) -> Option<Range<usize>> {
+        self.tokens.get(token).cloned()
+    }
+
+    /// 词在文本中的字符区间
+    pub fn token_to_chars(&self, token: usize) -> Option<Range<usize>> {
+        let span = self.tokens.get(token)?;
+        let char = |byte| self.chars.partition_point(|&start| start < byte);
+        Some(char(span.start)..char(span.end))
+    }
+
+    /// 与字节区间有重叠的词，空区间视为其所在的位置
+    pub fn bytes_to_tokens(&self, span: Range<usize>) -> Range<usize> {
+        let start = self.tokens.partition_point(|token| token.end <= span.start);
+        let end = self.tokens.partition_point(|token| token.start < span.end);
+        start..end.max(start)
+    }
+
+    /// 第 `tokens.start` 到 `tokens.end`（不含）个词在文档中的字节区间，如 [`get_entities`] 给出的实体，
+    /// 词的区间为空或越界时返回 `None`
+    ///
+    /// [`get_entities`]: crate::get_entities
+    pub fn tokens_to_original(&self, tokens: Range<usize>) -> Option<Range<usize>> {
+        if tokens.is_empty() {
+            return None;
+        }
+        let start = self.tokens.get(tokens.start)?.start;
+        let end = self.tokens.get(tokens.end.checked_sub(1)?)?.end;
+        let span = match &self.map {
+            Some(map) => map.to_original(start..end),
+            None => start..end,
+        };
+        Some(span.start + self.offset..span.end + self.offset)
+    }
+
+    /// 与文档中的字节区间有重叠的词
+    pub fn original_to_tokens(&self, span: Range<usize>) -> Range<usize> {
+        let span = span.start.saturating_sub(self.offset)..span.end.saturating_sub(self.offset);
+        match &self.map {
+            Some(map) => {
+                let chars = map.normalized_chars(span);
+                let byte = |char: usize| self.chars[char.min(self.chars.len() - 1)];
+                self.bytes_to_tokens(byte(chars.start)..byte(chars.end))
+            }
+            None => self.bytes_to_tokens(span),
+        }
+    }
+}
+
+impl Sentence {
+    /// 见 [`Alignment::from_sentence`]
+    pub fn alignment(&self) -> Result<Alignment> {
+        Alignment::from_sentence(self)
+    }
+
+    /// 实体的类型与其在文档中的字节区间；由 [`LtpPipeline::process_tokens`] 处理的句子
+    /// 为调用方给出的各词的位置所覆盖的区间
+    ///
+    /// [`LtpPipeline::process_tokens`]: crate::LtpPipeline::process_tokens
+    pub fn entity_spans(&self) -> Result<Vec<(&str, Range<usize>)>> {
+        let Some(ner) = &self.ner else {
+            return Ok(vec![]);
+        };
+        let entities = drop_get_entities(ner.to_vec());
+        if let Some(spans) = &self.token_spans {
+            return Ok(entities
+                .into_iter()
+                .map(|(kind, start, end)| (kind, spans[start].start..spans[end].end))
+                .collect());
+        }
+        let alignment = self.alignment()?;
+        entities
+            .into_iter()
+            .map(|(kind, start, end)| {
+                let span = alignment
+                    .tokens_to_original(start..end + 1)
+                    .ok_or_else(|| anyhow!("entity {}..{} is out of range", start, end + 1))?;
+                Ok((kind, span))
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clean::TextCleaner;
+
+    #[test]
+    fn test_alignment() -> Result<()> {
+        let text = "他去 北京";
+        let alignment = Alignment::new(text, &["他", "去", "北京"])?;
+        assert_eq!(alignment.byte_to_char(4), Some(1));
+        assert_eq!(alignment.byte_to_char(text.len()), None);
+        assert_eq!(alignment.char_to_byte(3), Some(7));
+        assert_eq!(alignment.byte_to_token(6), None);
+        assert_eq!(alignment.char_to_token(4), Some(2));
+        assert_eq!(alignment.token_to_chars(2), Some(3..5));
+        assert_eq!(alignment.bytes_to_tokens(4..8), 1..3);
+        assert!(Alignment::new(text, &["北京", "他"]).is_err());
+        assert!(Alignment::from_spans(text, vec![0..1, 3..6]).is_err());
+
+        // 清洗后的文本投影回文档
+        let document = "前文。苏&amp;杭 很美";
+        let original = &document[9..];
+        let cleaner = TextCleaner {
+            html_entities: true,
+            ..Default::default()
+        };
+        let (cleaned, map) = cleaner.clean(original);
+        let mut sentence = Sentence::new(cleaned);
+        sentence.words = ["苏", "&", "杭", "很", "美"].map(String::from).into();
+        sentence.original = Some((original.to_string(), map));
+        sentence.offset = 9;
+        let alignment = sentence.alignment()?;
+        assert_eq!(
+            &document[alignment.tokens_to_original(0..3).unwrap()],
+            "苏&amp;杭"
+        );
+        assert_eq!(
+            &document[alignment.tokens_to_original(1..2).unwrap()],
+            "&amp;"
+        );
+        assert_eq!(alignment.original_to_tokens(14..16), 1..2);
+        assert_eq!(alignment.original_to_tokens(20..23), 3..4);
+        assert_eq!(alignment.tokens_to_original(2..2), None);
+        Ok(())
+    }
+}
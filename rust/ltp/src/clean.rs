@@ -0,0 +1,207 @@
+//! 文本清洗：去掉控制字符与零宽字符、合并重复标点、解码 HTML 实体，
+//! 同时记录清洗后的文本到原文的位置映射，标注结果可以映射回原文
+#[cfg(feature = "serialization")]
+use serde::{Deserialize, Serialize};
+use std::ops::Range;
+
+/// 各项默认关闭
+#[cfg_attr(feature = "serialization", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serialization", serde(default, deny_unknown_fields))]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TextCleaner {
+    /// 去掉换行与制表符以外的控制字符
+    pub control_chars: bool,
+    /// 去掉零宽空格、零宽连接符等不可见字符，注意这会拆开由零宽连接符组成的 emoji
+    pub zero_width: bool,
+    /// 连续重复的同一标点只保留一个，省略号、破折号、`.` 与 `-` 除外
+    pub collapse_punctuation: bool,
+    /// 解码 `&amp;`、`&#39;`、`&#x4E2D;` 等 HTML 实体
+    pub html_entities: bool,
+}
+
+/// 清洗后文本中每个字符对应的原文字节区间
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct OffsetMap {
+    starts: Vec<usize>,
+    original: Vec<Range<usize>>,
+    original_len: usize,
+}
+
+impl OffsetMap {
+    pub(crate) fn new(original_len: usize) -> Self {
+        Self {
+            original_len,
+            ..Default::default()
+        }
+    }
+
+    pub(crate) fn push(&mut self, start: usize, original: Range<usize>) {
+        self.starts.push(start);
+        self.original.push(original);
+    }
+
+    /// 把清洗后文本中的字节区间映射为原文中的字节区间，被合并的标点与被删去的字符归入相邻的字符
+    pub fn to_original(&self, span: Range<usize>) -> Range<usize> {
+        let index = self.starts.partition_point(|&start| start < span.start);
+        let start = self
+            .original
+            .get(index)
+            .map_or(self.original_len, |original| original.start);
+        let index = self.starts.partition_point(|&start| start < span.end);
+        let end = match index {
+            0 => start,
+            index => self.original[index - 1].end.max(start),
+        };
+        start..end
+    }
+
+    /// 原文中的字节区间与清洗后文本中哪些字符（按下标）有重叠，空区间视为其所在的位置
+    pub(crate) fn normalized_chars(&self, span: Range<usize>) -> Range<usize> {
+        let start = self
+            .original
+            .partition_point(|original| original.end <= span.start);
+        let end = self
+            .original
+            .partition_point(|original| original.start < span.end);
+        start..end.max(start)
+    }
+
+    /// 文本先经 `self` 对应的变换、再经 `next` 对应的变换后，最终文本到最初文本的映射
+    pub fn compose(&self, next: &OffsetMap) -> OffsetMap {
+        OffsetMap {
+            starts: next.starts.clone(),
+            original: next
+                .original
+                .iter()
+                .map(|span| self.to_original(span.clone()))
+                .collect(),
+            original_len: self.original_len,
+        }
+    }
+}
+
+impl TextCleaner {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 启用全部清洗规则
+    pub fn all() -> Self {
+        Self {
+            control_chars: true,
+            zero_width: true,
+            collapse_punctuation: true,
+            html_entities: true,
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.control_chars || self.zero_width || self.collapse_punctuation || self.html_entities
+    }
+
+    pub fn clean(&self, text: &str) -> (String, OffsetMap) {
+        let mut cleaned = String::with_capacity(text.len());
+        let mut map = OffsetMap::new(text.len());
+        let mut last: Option<char> = None;
+        let mut index = 0;
+        while let Some(ch) = text[index..].chars().next() {
+            let (ch, end) = match ch {
+                '&' if self.html_entities => match decode_entity(&text[index..]) {
+                    Some((decoded, len)) => (Some(decoded), index + len),
+                    None => (Some(ch), index + 1),
+                },
+                ch if self.control_chars
+                    && ch.is_control()
+                    && !matches!(ch, '\t' | '\n' | '\r') =>
+                {
+                    (None, index + ch.len_utf8())
+                }
+                ch if self.zero_width && is_zero_width(ch) => (None, index + ch.len_utf8()),
+                ch => (Some(ch), index + ch.len_utf8()),
+            };
+            match ch {
+                Some(ch) if self.collapse_punctuation && last == Some(ch) && is_collapsible(ch) => {
+                    if let Some(original) = map.original.last_mut() {
+                        original.end = end;
+                    }
+                }
+                Some(ch) => {
+                    map.push(cleaned.len(), index..end);
+                    cleaned.push(ch);
+                    last = Some(ch);
+                }
+                None => {}
+            }
+            index = end;
+        }
+        (cleaned, map)
+    }
+}
+
+fn is_zero_width(ch: char) -> bool {
+    matches!(
+        ch,
+        '\u{200B}' | '\u{200C}' | '\u{200D}' | '\u{2060}' | '\u{FEFF}'
+    )
+}
+
+fn is_collapsible(ch: char) -> bool {
+    let punctuation = ch.is_ascii_punctuation()
+        || matches!(
+            ch,
+            '\u{2010}'..='\u{2027}'
+                | '\u{3001}'..='\u{303F}'
+                | '\u{FF01}'..='\u{FF0F}'
+                | '\u{FF1A}'..='\u{FF20}'
+                | '\u{FF5B}'..='\u{FF65}'
+        );
+    punctuation && !matches!(ch, '…' | '⋯' | '—' | '.' | '-')
+}
+
+/// 解码开头的 HTML 实体，返回字符与实体的字节长度
+fn decode_entity(text: &str) -> Option<(char, usize)> {
+    // 最长的实体如 `&#x10FFFF;`
+    let end = text.bytes().take(12).position(|b| b == b';')?;
+    let name = &text[1..end];
+    let ch = match name {
+        "amp" => '&',
+        "lt" => '<',
+        "gt" => '>',
+        "quot" => '"',
+        "apos" => '\'',
+        "nbsp" => '\u{A0}',
+        _ => {
+            let code = name.strip_prefix('#')?;
+            let code = match code.strip_prefix(['x', 'X']) {
+                Some(hex) => u32::from_str_radix(hex, 16).ok()?,
+                None => code.parse().ok()?,
+            };
+            char::from_u32(code)?
+        }
+    };
+    Some((ch, end + 1))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_clean() {
+        let text = "他\u{200B}说：&quot;好！！！&quot;\u{7}&#x4E2D;&#25991;……&bad;";
+        let (cleaned, map) = TextCleaner::all().clean(text);
+        assert_eq!(cleaned, "他说：\"好！\"中文……&bad;");
+
+        // 合并的标点对应原文中的全部重复
+        let bang = cleaned.find('！').unwrap();
+        let span = map.to_original(bang..bang + '！'.len_utf8());
+        assert_eq!(&text[span], "！！！");
+        let zh = cleaned.find("中文").unwrap();
+        assert_eq!(&text[map.to_original(zh..zh + 6)], "&#x4E2D;&#25991;");
+        assert_eq!(map.to_original(0..cleaned.len()), 0..text.len());
+
+        let (cleaned, _) = TextCleaner::new().clean(text);
+        assert_eq!(cleaned, text);
+        assert!(!TextCleaner::new().is_enabled());
+    }
+}
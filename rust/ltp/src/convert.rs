@@ -0,0 +1,202 @@
+//! 简繁转换：OpenCC 格式的字表与词表，词优先于逐字转换，
+//! 同时记录转换后的文本到原文的位置映射，分词等结果可以投影回原来的字形
+//!
+//! 表文件每行为 `原文\t候选 候选…`，取第一个候选，与 OpenCC 的 `STCharacters.txt`、
+//! `STPhrases.txt` 等文件格式相同；空行与 `#` 开头的注释忽略。
+use crate::clean::OffsetMap;
+#[cfg(feature = "fs")]
+use crate::perceptron::CompressedReader;
+use anyhow::{anyhow, Result};
+use std::collections::HashMap;
+use std::io::{BufRead, BufReader, Read};
+#[cfg(feature = "fs")]
+use std::path::Path;
+
+static ST_CHARACTERS: &str = include_str!(concat!(
+    env!("CARGO_MANIFEST_DIR"),
+    "/vendor/convert/st_characters.txt"
+));
+static ST_PHRASES: &str = include_str!(concat!(
+    env!("CARGO_MANIFEST_DIR"),
+    "/vendor/convert/st_phrases.txt"
+));
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Converter {
+    chars: HashMap<char, String>,
+    phrases: HashMap<String, String>,
+    /// 最长的词的字数
+    longest: usize,
+}
+
+impl Converter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 内置的简体到繁体的常用字与词，只收录没有歧义的常用字
+    pub fn simplified_to_traditional() -> Self {
+        let mut converter = Self::new();
+        converter.extend_lines(ST_CHARACTERS).unwrap();
+        converter.extend_lines(ST_PHRASES).unwrap();
+        converter
+    }
+
+    /// 内置表的反向转换
+    pub fn traditional_to_simplified() -> Self {
+        Self::simplified_to_traditional().inverse()
+    }
+
+    pub fn from_reader<R: Read>(reader: R) -> Result<Self> {
+        let mut converter = Self::new();
+        converter.extend_reader(reader)?;
+        Ok(converter)
+    }
+
+    /// 加入一个表中的字与词，已有的条目被覆盖
+    pub fn extend_reader<R: Read>(&mut self, reader: R) -> Result<()> {
+        for line in BufReader::new(reader).lines() {
+            self.extend_lines(&line?)?;
+        }
+        Ok(())
+    }
+
+    /// 文件可以是 gzip、zstd 压缩的
+    #[cfg(feature = "fs")]
+    pub fn extend_file<P: AsRef<Path>>(&mut self, path: P) -> Result<()> {
+        self.extend_reader(CompressedReader::open(path)?)
+    }
+
+    fn extend_lines(&mut self, text: &str) -> Result<()> {
+        for line in text.lines() {
+            let line = line.trim_start_matches('\u{feff}').trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let (from, to) = line
+                .split_once('\t')
+                .and_then(|(from, to)| Some((from, to.split_whitespace().next()?)))
+                .ok_or_else(|| anyhow!("invalid conversion entry `{}`", line))?;
+            self.insert(from, to);
+        }
+        Ok(())
+    }
+
+    /// 单字进入字表，多字进入词表
+    pub fn insert(&mut self, from: &str, to: &str) {
+        let mut chars = from.chars();
+        match (chars.next(), chars.next()) {
+            (Some(ch), None) => {
+                self.chars.insert(ch, to.to_string());
+            }
+            (Some(_), Some(_)) => {
+                self.longest = self.longest.max(from.chars().count());
+                self.phrases.insert(from.to_string(), to.to_string());
+            }
+            _ => {}
+        }
+    }
+
+    /// 交换各条目的原文与译文，多个原文对应同一译文时任取其一
+    pub fn inverse(&self) -> Self {
+        let mut inverse = Self::new();
+        let mut chars: Vec<_> = self.chars.iter().collect();
+        chars.sort_unstable();
+        for (from, to) in chars {
+            inverse.insert(to, &from.to_string());
+        }
+        let mut phrases: Vec<_> = self.phrases.iter().collect();
+        phrases.sort_unstable();
+        for (from, to) in phrases {
+            inverse.insert(to, from);
+        }
+        inverse
+    }
+
+    pub fn len(&self) -> usize {
+        self.chars.len() + self.phrases.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.chars.is_empty() && self.phrases.is_empty()
+    }
+
+    pub fn convert(&self, text: &str) -> String {
+        self.convert_with_offsets(text).0
+    }
+
+    /// 从左到右取最长的词，没有词时逐字转换；字数不变的词逐字对应原文，否则整个词对应原文中的词
+    pub fn convert_with_offsets(&self, text: &str) -> (String, OffsetMap) {
+        let mut converted = String::with_capacity(text.len());
+        let mut map = OffsetMap::new(text.len());
+        let chars: Vec<(usize, char)> = text.char_indices().collect();
+        let end_of = |i: usize| chars.get(i).map_or(text.len(), |&(index, _)| index);
+        let mut i = 0;
+        while i < chars.len() {
+            let start = chars[i].0;
+            let phrase = (2..=self.longest.min(chars.len() - i))
+                .rev()
+                .find_map(|len| Some((len, self.phrases.get(&text[start..end_of(i + len)])?)));
+            let (len, to) = match phrase {
+                Some((len, to)) => (len, to.as_str()),
+                None => match self.chars.get(&chars[i].1) {
+                    Some(to) => (1, to.as_str()),
+                    None => (1, &text[start..end_of(i + 1)]),
+                },
+            };
+            let aligned = to.chars().count() == len;
+            for (k, ch) in to.chars().enumerate() {
+                let original = match aligned {
+                    true => chars[i + k].0..end_of(i + k + 1),
+                    false => start..end_of(i + len),
+                };
+                map.push(converted.len(), original);
+                converted.push(ch);
+            }
+            i += len;
+        }
+        (converted, map)
+    }
+
+    /// 逐词转换，各词独立地取最长匹配
+    pub fn convert_words<S: AsRef<str>>(&self, words: &[S]) -> Vec<String> {
+        words
+            .iter()
+            .map(|word| self.convert(word.as_ref()))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::LtpPipeline;
+
+    #[test]
+    fn test_convert() -> Result<()> {
+        let converter = Converter::simplified_to_traditional();
+        assert_eq!(converter.convert("这个发型很好看"), "這個髮型很好看");
+        assert_eq!(converter.convert("他出发以后理发了"), "他出發以後理髮了");
+        let back = Converter::traditional_to_simplified();
+        assert_eq!(back.convert("這個髮型"), "这个发型");
+
+        let mut custom = Converter::from_reader("# 用户词表\n计算机\t電腦 計算機\n".as_bytes())?;
+        custom.insert("网", "網");
+        assert_eq!(custom.convert("计算机网络"), "電腦網络");
+        assert!(Converter::from_reader("错误".as_bytes()).is_err());
+
+        // 字数不同的词整体对应原文
+        let text = "用计算机";
+        let (converted, map) = custom.convert_with_offsets(text);
+        let start = converted.find("電腦").unwrap();
+        assert_eq!(&text[map.to_original(start..start + 3)], "计算机");
+
+        let pipeline = LtpPipeline::new().add_stage(converter.clone());
+        let mut sentence = pipeline.process("头发很干净")?;
+        assert_eq!(sentence.text, "頭髮很乾淨");
+        sentence.words = ["頭髮", "很", "乾淨"].map(String::from).into();
+        assert_eq!(sentence.original_words(), ["头发", "很", "干净"]);
+        assert_eq!(converter.convert_words(&["头发", "干净"]), ["頭髮", "乾淨"]);
+        Ok(())
+    }
+}
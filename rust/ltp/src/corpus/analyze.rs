@@ -0,0 +1,218 @@
+//! 语料统计与校验：规模、标签分布、句长分布、未登录词、标注体系错误与重复句子
+use crate::corpus::AnnotatedSentence;
+#[cfg(feature = "serialization")]
+use serde::{Deserialize, Serialize};
+use std::collections::hash_map::Entry;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::{Display, Formatter};
+
+/// 标签序列中不合法的位置，如 `I-Nh` 前没有 `B-Nh`
+#[cfg_attr(feature = "serialization", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Violation {
+    pub sentence: usize,
+    pub token: usize,
+    pub label: String,
+    /// 前一个标签，句首为 `None`
+    pub previous: Option<String>,
+}
+
+#[cfg_attr(feature = "serialization", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CorpusReport {
+    pub sentences: usize,
+    pub tokens: usize,
+    pub chars: usize,
+    pub pos: BTreeMap<String, usize>,
+    pub labels: BTreeMap<String, usize>,
+    /// 句长（词数） -> 句子数
+    pub lengths: BTreeMap<usize, usize>,
+    pub violations: Vec<Violation>,
+    /// 重复句子及其首次出现的位置
+    pub duplicates: Vec<(usize, usize)>,
+    /// 未登录词数，只在提供词表时统计
+    pub oov: Option<usize>,
+}
+
+/// 标签拆分为前缀与类型，`O` 等无类型标签的类型为空
+fn split_label(label: &str) -> (&str, &str) {
+    match label.split_once('-') {
+        Some((prefix, kind)) if matches!(prefix, "B" | "I" | "M" | "E" | "S") => (prefix, kind),
+        _ => (label, ""),
+    }
+}
+
+/// 检查 BIO 与 BIESO 标注体系，出现 `E-`、`S-` 时按 BIESO 检查实体是否闭合
+fn check_scheme(sentence: usize, labels: &[String], bieso: bool, out: &mut Vec<Violation>) {
+    let mut previous: Option<(&str, &str)> = None;
+    for (token, label) in labels.iter().enumerate() {
+        let (prefix, kind) = split_label(label);
+        // 前一个标签是否为同类实体的开头或中间
+        let open = match previous {
+            Some((p, k)) => k == kind && matches!(p, "B" | "I" | "M"),
+            None => false,
+        };
+        let ok = match prefix {
+            "I" | "M" | "E" => open,
+            // BIESO 下实体未闭合就开始新的标签
+            _ => !bieso || !matches!(previous, Some(("B" | "I" | "M", _))),
+        };
+        if !ok {
+            out.push(Violation {
+                sentence,
+                token,
+                label: label.clone(),
+                previous: token.checked_sub(1).map(|i| labels[i].clone()),
+            });
+        }
+        previous = Some((prefix, kind));
+    }
+    if bieso && matches!(previous, Some(("B" | "I" | "M", _))) {
+        out.push(Violation {
+            sentence,
+            token: labels.len(),
+            label: String::new(),
+            previous: labels.last().cloned(),
+        });
+    }
+}
+
+/// 统计语料，`known` 判断一个词是否在词表（如模型或训练集）中
+pub fn analyze(
+    sentences: &[AnnotatedSentence],
+    known: Option<&dyn Fn(&str) -> bool>,
+) -> CorpusReport {
+    let bieso = sentences
+        .iter()
+        .flat_map(|sentence| sentence.labels.iter().flatten())
+        .any(|label| matches!(split_label(label).0, "E" | "S"));
+
+    let mut report = CorpusReport {
+        sentences: sentences.len(),
+        oov: known.map(|_| 0),
+        ..Default::default()
+    };
+    let mut seen = HashMap::new();
+    for (index, sentence) in sentences.iter().enumerate() {
+        report.tokens += sentence.words.len();
+        report.chars += sentence
+            .words
+            .iter()
+            .map(|w| w.chars().count())
+            .sum::<usize>();
+        *report.lengths.entry(sentence.words.len()).or_default() += 1;
+        for tag in sentence.pos.iter().flatten() {
+            *report.pos.entry(tag.clone()).or_default() += 1;
+        }
+        if let Some(labels) = &sentence.labels {
+            for label in labels {
+                *report.labels.entry(label.clone()).or_default() += 1;
+            }
+            check_scheme(index, labels, bieso, &mut report.violations);
+        }
+        if let (Some(known), Some(oov)) = (known, report.oov.as_mut()) {
+            *oov += sentence.words.iter().filter(|w| !known(w)).count();
+        }
+        match seen.entry(&sentence.words) {
+            Entry::Occupied(first) => report.duplicates.push((index, *first.get())),
+            Entry::Vacant(entry) => {
+                entry.insert(index);
+            }
+        }
+    }
+    report
+}
+
+impl CorpusReport {
+    pub fn oov_rate(&self) -> Option<f64> {
+        self.oov.map(|oov| oov as f64 / (self.tokens.max(1)) as f64)
+    }
+
+    /// 按 `width` 个词分桶的句长分布
+    pub fn histogram(&self, width: usize) -> BTreeMap<usize, usize> {
+        let width = width.max(1);
+        let mut buckets = BTreeMap::new();
+        for (length, count) in &self.lengths {
+            *buckets.entry(length / width * width).or_default() += count;
+        }
+        buckets
+    }
+}
+
+impl Display for CorpusReport {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        writeln!(
+            f,
+            "sentences: {}, tokens: {}, chars: {}",
+            self.sentences, self.tokens, self.chars
+        )?;
+        if let Some(rate) = self.oov_rate() {
+            writeln!(f, "oov: {} ({:.2}%)", self.oov.unwrap_or(0), rate * 100.0)?;
+        }
+        for (name, column) in [("pos", &self.pos), ("labels", &self.labels)] {
+            if column.is_empty() {
+                continue;
+            }
+            writeln!(f, "{}:", name)?;
+            for (label, count) in column {
+                writeln!(f, "  {}\t{}", label, count)?;
+            }
+        }
+        writeln!(f, "lengths:")?;
+        for (start, count) in self.histogram(10) {
+            writeln!(f, "  {}-{}\t{}", start, start + 9, count)?;
+        }
+        writeln!(f, "violations: {}", self.violations.len())?;
+        for v in &self.violations {
+            let previous = v.previous.as_deref().unwrap_or("<s>");
+            let label = if v.label.is_empty() { "</s>" } else { &v.label };
+            writeln!(
+                f,
+                "  sentence {} token {}: {} -> {}",
+                v.sentence, v.token, previous, label
+            )?;
+        }
+        writeln!(f, "duplicates: {}", self.duplicates.len())?;
+        for (index, first) in &self.duplicates {
+            writeln!(f, "  sentence {} duplicates sentence {}", index, first)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sentence(words: &[&str], labels: &[&str]) -> AnnotatedSentence {
+        AnnotatedSentence {
+            words: words.iter().map(|w| w.to_string()).collect(),
+            pos: None,
+            labels: Some(labels.iter().map(|w| w.to_string()).collect()),
+        }
+    }
+
+    #[test]
+    fn test_analyze() {
+        let sentences = vec![
+            sentence(&["他", "叫", "汤姆"], &["O", "O", "S-Nh"]),
+            sentence(&["汤", "姆", "去"], &["I-Nh", "E-Nh", "B-Ns"]),
+            sentence(&["他", "叫", "汤姆"], &["O", "O", "S-Nh"]),
+        ];
+        let known = |word: &str| word != "汤姆";
+        let report = analyze(&sentences, Some(&known));
+        assert_eq!(report.tokens, 9);
+        assert_eq!(report.labels["O"], 4);
+        assert_eq!(report.oov, Some(2));
+        assert_eq!(report.duplicates, vec![(2, 0)]);
+        // 句首的 I-Nh 与句末未闭合的 B-Ns
+        assert_eq!(report.violations.len(), 2);
+        assert_eq!(report.violations[0].token, 0);
+        assert_eq!(report.violations[1].token, 3);
+        assert_eq!(report.histogram(10)[&0], 3);
+
+        // BIO 中 B 后直接为 O 是合法的
+        let bio = analyze(&[sentence(&["汤姆", "去"], &["B-Nh", "O"])], None);
+        assert!(bio.violations.is_empty());
+    }
+}
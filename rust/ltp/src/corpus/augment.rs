@@ -0,0 +1,369 @@
+//! 序列标注的数据增强：同义词与实体替换、数字随机化、全半角标点变换
+use crate::corpus::AnnotatedSentence;
+use anyhow::{anyhow, Result};
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::{Rng, RngCore, SeedableRng};
+use std::collections::HashMap;
+use std::io::{BufRead, BufReader, Read};
+
+/// 对一个已标注句子做变换，词、词性与标签须保持一一对应
+pub trait Augment: Send + Sync {
+    fn augment(&self, sentence: &mut AnnotatedSentence, rng: &mut dyn RngCore);
+}
+
+/// 同义词替换，词性与标签保持不变
+#[derive(Debug, Clone, Default)]
+pub struct SynonymSubstitution {
+    groups: Vec<Vec<String>>,
+    index: HashMap<String, usize>,
+    /// 每个可替换的词被替换的概率
+    pub probability: f64,
+}
+
+impl SynonymSubstitution {
+    pub fn new(groups: Vec<Vec<String>>) -> Self {
+        let mut index = HashMap::new();
+        for (i, group) in groups.iter().enumerate() {
+            for word in group {
+                index.insert(word.clone(), i);
+            }
+        }
+        SynonymSubstitution {
+            groups,
+            index,
+            probability: 0.5,
+        }
+    }
+
+    /// 每行一组以空白分隔的同义词
+    pub fn from_reader<R: Read>(reader: R) -> Result<Self> {
+        let mut groups = Vec::new();
+        for line in BufReader::new(reader).lines() {
+            let group: Vec<String> = line?.split_whitespace().map(String::from).collect();
+            if group.len() > 1 {
+                groups.push(group);
+            }
+        }
+        Ok(Self::new(groups))
+    }
+
+    pub fn probability(mut self, probability: f64) -> Self {
+        self.probability = probability;
+        self
+    }
+}
+
+impl Augment for SynonymSubstitution {
+    fn augment(&self, sentence: &mut AnnotatedSentence, rng: &mut dyn RngCore) {
+        for word in sentence.words.iter_mut() {
+            if let Some(&group) = self.index.get(word.as_str()) {
+                if rng.gen_bool(self.probability) {
+                    *word = self.groups[group].choose(rng).cloned().unwrap_or_default();
+                }
+            }
+        }
+    }
+}
+
+/// 实体替换：把标注为某类型的实体换成词典中同类型的另一个实体，替换后的实体为一个词
+#[derive(Debug, Clone, Default)]
+pub struct EntitySubstitution {
+    entities: HashMap<String, Vec<String>>,
+    pub probability: f64,
+}
+
+impl EntitySubstitution {
+    pub fn new(entities: HashMap<String, Vec<String>>) -> Self {
+        EntitySubstitution {
+            entities,
+            probability: 0.5,
+        }
+    }
+
+    /// 每行为 `类型 实体`，如 `Nh 汤姆`
+    pub fn from_reader<R: Read>(reader: R) -> Result<Self> {
+        let mut entities: HashMap<String, Vec<String>> = HashMap::new();
+        for (index, line) in BufReader::new(reader).lines().enumerate() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            let (kind, entity) = line
+                .trim()
+                .split_once(char::is_whitespace)
+                .ok_or_else(|| anyhow!("line {}: expected `type entity`", index + 1))?;
+            entities
+                .entry(kind.to_string())
+                .or_default()
+                .push(entity.trim().to_string());
+        }
+        Ok(Self::new(entities))
+    }
+
+    pub fn probability(mut self, probability: f64) -> Self {
+        self.probability = probability;
+        self
+    }
+
+    /// 实体的范围与类型，兼容 BIO 与 BIESO
+    fn spans(labels: &[String]) -> Vec<(usize, usize, &str)> {
+        let mut spans = Vec::new();
+        let mut start = None;
+        for (i, label) in labels.iter().enumerate() {
+            let (tag, kind) = label.split_once('-').unwrap_or((label, ""));
+            match tag {
+                "S" => spans.push((i, i + 1, kind)),
+                "B" => start = Some((i, kind)),
+                _ => {}
+            }
+            if let Some((begin, kind)) = start {
+                let next = labels
+                    .get(i + 1)
+                    .and_then(|label| label.split_once('-'))
+                    .map(|(prefix, _)| prefix);
+                if tag == "E" || !matches!(next, Some("I" | "M" | "E")) {
+                    spans.push((begin, i + 1, kind));
+                    start = None;
+                }
+            }
+        }
+        spans
+    }
+}
+
+impl Augment for EntitySubstitution {
+    fn augment(&self, sentence: &mut AnnotatedSentence, rng: &mut dyn RngCore) {
+        let Some(labels) = &sentence.labels else {
+            return;
+        };
+        let bieso = labels
+            .iter()
+            .any(|l| l.starts_with("S-") || l.starts_with("E-"));
+        let mut replaced = AnnotatedSentence::default();
+        let mut last = 0;
+        for (start, end, kind) in Self::spans(labels) {
+            let Some(candidates) = self.entities.get(kind) else {
+                continue;
+            };
+            if !rng.gen_bool(self.probability) {
+                continue;
+            }
+            let Some(entity) = candidates.choose(rng) else {
+                continue;
+            };
+            replaced.push_range(sentence, last, start);
+            replaced.words.push(entity.clone());
+            if let Some(pos) = &sentence.pos {
+                replaced
+                    .pos
+                    .get_or_insert_with(Vec::new)
+                    .push(pos[start].clone());
+            }
+            let prefix = if bieso { "S" } else { "B" };
+            replaced
+                .labels
+                .get_or_insert_with(Vec::new)
+                .push(format!("{}-{}", prefix, kind));
+            last = end;
+        }
+        if last > 0 {
+            replaced.push_range(sentence, last, sentence.words.len());
+            *sentence = replaced;
+        }
+    }
+}
+
+impl AnnotatedSentence {
+    fn push_range(&mut self, other: &AnnotatedSentence, start: usize, end: usize) {
+        self.words.extend_from_slice(&other.words[start..end]);
+        if let Some(pos) = &other.pos {
+            let column = self.pos.get_or_insert_with(Vec::new);
+            column.extend_from_slice(&pos[start..end]);
+        }
+        if let Some(labels) = &other.labels {
+            let column = self.labels.get_or_insert_with(Vec::new);
+            column.extend_from_slice(&labels[start..end]);
+        }
+    }
+}
+
+/// 把数字随机替换为同宽度的其它数字
+#[derive(Debug, Clone, Copy, Default)]
+pub struct DigitRandomization;
+
+impl Augment for DigitRandomization {
+    fn augment(&self, sentence: &mut AnnotatedSentence, rng: &mut dyn RngCore) {
+        for word in sentence.words.iter_mut() {
+            if !word
+                .chars()
+                .any(|c| c.is_ascii_digit() || ('０'..='９').contains(&c))
+            {
+                continue;
+            }
+            *word = word
+                .chars()
+                .map(|c| match c {
+                    '0'..='9' => char::from(b'0' + rng.gen_range(0..10u8)),
+                    '０'..='９' => {
+                        char::from_u32('０' as u32 + rng.gen_range(0..10)).unwrap_or(c)
+                    }
+                    c => c,
+                })
+                .collect();
+        }
+    }
+}
+
+const PUNCTUATION: [(&str, &str); 8] = [
+    ("，", ","),
+    ("。", "."),
+    ("！", "!"),
+    ("？", "?"),
+    ("：", ":"),
+    ("；", ";"),
+    ("（", "("),
+    ("）", ")"),
+];
+
+/// 全角与半角标点互换
+#[derive(Debug, Clone, Copy)]
+pub struct PunctuationVariation {
+    pub probability: f64,
+}
+
+impl Default for PunctuationVariation {
+    fn default() -> Self {
+        PunctuationVariation { probability: 0.5 }
+    }
+}
+
+impl Augment for PunctuationVariation {
+    fn augment(&self, sentence: &mut AnnotatedSentence, rng: &mut dyn RngCore) {
+        for word in sentence.words.iter_mut() {
+            let variant = PUNCTUATION
+                .iter()
+                .find_map(|&(full, half)| match word.as_str() {
+                    w if w == full => Some(half),
+                    w if w == half => Some(full),
+                    _ => None,
+                });
+            if let Some(variant) = variant {
+                if rng.gen_bool(self.probability) {
+                    *word = variant.to_string();
+                }
+            }
+        }
+    }
+}
+
+/// 按比例生成增强样本并与原语料混合，随机种子固定时结果可复现
+pub struct Augmenter {
+    transforms: Vec<Box<dyn Augment>>,
+    /// 增强样本数与原语料句子数之比
+    pub ratio: f64,
+    pub seed: u64,
+}
+
+impl Default for Augmenter {
+    fn default() -> Self {
+        Augmenter {
+            transforms: Vec::new(),
+            ratio: 0.2,
+            seed: 0,
+        }
+    }
+}
+
+impl Augmenter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn transform<A: Augment + 'static>(mut self, transform: A) -> Self {
+        self.transforms.push(Box::new(transform));
+        self
+    }
+
+    pub fn ratio(mut self, ratio: f64) -> Self {
+        self.ratio = ratio;
+        self
+    }
+
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+        self
+    }
+
+    /// 随机抽取句子依次应用所有变换，没有变化的句子丢弃，因此增强样本数不超过 `ratio * n`
+    pub fn augment(&self, sentences: &[AnnotatedSentence]) -> Vec<AnnotatedSentence> {
+        let mut rng = StdRng::seed_from_u64(self.seed);
+        let count = (self.ratio.max(0.0) * sentences.len() as f64).round() as usize;
+        let mut augmented = Vec::with_capacity(count);
+        for _ in 0..count {
+            let Some(original) = sentences.choose(&mut rng) else {
+                break;
+            };
+            let mut sentence = original.clone();
+            for transform in &self.transforms {
+                transform.augment(&mut sentence, &mut rng);
+            }
+            if &sentence != original {
+                augmented.push(sentence);
+            }
+        }
+        augmented
+    }
+
+    /// 原语料后接增强样本
+    pub fn mix(&self, sentences: &[AnnotatedSentence]) -> Vec<AnnotatedSentence> {
+        let mut mixed = sentences.to_vec();
+        mixed.extend(self.augment(sentences));
+        mixed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sentence(words: &[&str], labels: &[&str]) -> AnnotatedSentence {
+        AnnotatedSentence {
+            words: words.iter().map(|w| w.to_string()).collect(),
+            pos: None,
+            labels: Some(labels.iter().map(|w| w.to_string()).collect()),
+        }
+    }
+
+    #[test]
+    fn test_augment() -> Result<()> {
+        let mut rng = StdRng::seed_from_u64(0);
+        let entities = EntitySubstitution::new(HashMap::from([(
+            "Nh".to_string(),
+            vec!["汤姆".to_string()],
+        )]))
+        .probability(1.0);
+        let mut s = sentence(
+            &["张", "三", "在", "2023", "年", "，"],
+            &["B-Nh", "E-Nh", "O", "O", "O", "O"],
+        );
+        entities.augment(&mut s, &mut rng);
+        assert_eq!(s.words, vec!["汤姆", "在", "2023", "年", "，"]);
+        assert_eq!(s.labels()?.len(), 5);
+        assert_eq!(s.labels()?[0], "S-Nh");
+
+        DigitRandomization.augment(&mut s, &mut rng);
+        assert_eq!(s.words[2].len(), 4);
+        assert!(s.words[2].chars().all(|c| c.is_ascii_digit()));
+        PunctuationVariation { probability: 1.0 }.augment(&mut s, &mut rng);
+        assert_eq!(s.words[4], ",");
+
+        let augmenter = Augmenter::new()
+            .transform(PunctuationVariation { probability: 1.0 })
+            .ratio(1.0);
+        let mixed = augmenter.mix(&[sentence(&["好", "。"], &["O", "O"])]);
+        assert_eq!(mixed.len(), 2);
+        assert_eq!(mixed[1].words, vec!["好", "."]);
+        Ok(())
+    }
+}
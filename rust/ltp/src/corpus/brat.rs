@@ -0,0 +1,279 @@
+//! brat 标注工具的 standoff 格式（`.txt` 原文与 `.ann` 标注），按分词结果对齐为实体识别语料
+use crate::corpus::AnnotatedSentence;
+use crate::perceptron::UNCERTAIN_TAG;
+use anyhow::{anyhow, Result};
+use std::fmt::{Display, Formatter};
+
+/// `.ann` 中的一个文本标注（`T` 开头的行），偏移为字符下标，左闭右开
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BratEntity {
+    pub id: String,
+    pub kind: String,
+    pub spans: Vec<(usize, usize)>,
+    pub text: String,
+}
+
+/// 只读取 `T` 行，关系、事件、属性与注释等其它标注忽略
+pub fn parse_ann(ann: &str) -> Result<Vec<BratEntity>> {
+    let mut entities = Vec::new();
+    for (index, line) in ann.lines().enumerate() {
+        if !line.starts_with('T') {
+            continue;
+        }
+        let error = || anyhow!("line {}: malformed text-bound annotation", index + 1);
+        let mut fields = line.splitn(3, '\t');
+        let (Some(id), Some(annotation)) = (fields.next(), fields.next()) else {
+            return Err(error());
+        };
+        let (kind, offsets) = annotation.split_once(' ').ok_or_else(error)?;
+        let mut spans = Vec::new();
+        // 不连续的实体形如 `0 5;8 10`
+        for span in offsets.split(';') {
+            let (start, end) = span.trim().split_once(' ').ok_or_else(error)?;
+            let (start, end) = (start.parse()?, end.parse()?);
+            if start >= end {
+                return Err(error());
+            }
+            spans.push((start, end));
+        }
+        entities.push(BratEntity {
+            id: id.to_string(),
+            kind: kind.to_string(),
+            spans,
+            text: fields.next().unwrap_or_default().to_string(),
+        });
+    }
+    Ok(entities)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConflictReason {
+    /// 实体边界落在词的中间
+    Boundary,
+    /// 与已对齐的实体重叠，brat 允许嵌套实体而序列标注不允许
+    Overlap,
+    /// 不连续的实体
+    Discontinuous,
+    /// 实体跨行
+    CrossLine,
+}
+
+/// 无法对齐的实体，覆盖的词标为不确定（`_`），训练时不产生更新
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BratConflict {
+    pub entity: BratEntity,
+    pub reason: ConflictReason,
+    /// 所在行的分词结果，跨行与不连续的实体为空
+    pub words: Vec<String>,
+}
+
+impl Display for BratConflict {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let reason = match self.reason {
+            ConflictReason::Boundary => "boundary inside a word",
+            ConflictReason::Overlap => "overlaps another entity",
+            ConflictReason::Discontinuous => "discontinuous entity",
+            ConflictReason::CrossLine => "entity spans lines",
+        };
+        let spans: Vec<String> = self
+            .entity
+            .spans
+            .iter()
+            .map(|(start, end)| format!("{}-{}", start, end))
+            .collect();
+        write!(
+            f,
+            "{} {} `{}` [{}]: {}",
+            self.entity.id,
+            self.entity.kind,
+            self.entity.text,
+            spans.join(";"),
+            reason
+        )?;
+        if !self.words.is_empty() {
+            write!(f, " ({})", self.words.join(" "))?;
+        }
+        Ok(())
+    }
+}
+
+/// 一行原文，`start` 为其首字符在全文中的下标
+struct Line<'a> {
+    start: usize,
+    text: &'a str,
+}
+
+fn lines(text: &str) -> Vec<Line<'_>> {
+    let mut result = Vec::new();
+    let mut start = 0;
+    for line in text.split('\n') {
+        let length = line.chars().count();
+        result.push(Line {
+            start,
+            text: line.strip_suffix('\r').unwrap_or(line),
+        });
+        start += length + 1;
+    }
+    result
+}
+
+/// 词在行内的字符范围，分词结果须依次出现在原文中，可以跳过空白
+fn offsets(line: &str, words: &[String]) -> Result<Vec<(usize, usize)>> {
+    let mut result = Vec::with_capacity(words.len());
+    let (mut byte, mut char) = (0, 0);
+    for word in words {
+        let found = line[byte..]
+            .find(word.as_str())
+            .ok_or_else(|| anyhow!("word `{}` is not in line `{}`", word, line))?;
+        char += line[byte..byte + found].chars().count();
+        byte += found + word.len();
+        let length = word.chars().count();
+        result.push((char, char + length));
+        char += length;
+    }
+    Ok(result)
+}
+
+/// 按 `segment` 的分词结果逐行对齐实体，输出 BIES 标签（实体外为 `O`），空行跳过。
+/// 无法对齐的实体不会被丢弃，而是作为冲突返回
+pub fn align_brat<F>(
+    text: &str,
+    entities: &[BratEntity],
+    mut segment: F,
+) -> Result<(Vec<AnnotatedSentence>, Vec<BratConflict>)>
+where
+    F: FnMut(&str) -> Result<Vec<String>>,
+{
+    let lines = lines(text);
+    let line_of = |offset: usize| lines.partition_point(|line| line.start <= offset) - 1;
+
+    let mut conflicts = Vec::new();
+    let mut by_line = vec![Vec::new(); lines.len()];
+    // 跨行与不连续的实体在各行内覆盖的范围
+    let mut uncertain = vec![Vec::new(); lines.len()];
+    for entity in entities {
+        let (start, end) = (entity.spans[0].0, entity.spans[entity.spans.len() - 1].1);
+        let reason = if entity.spans.len() > 1 {
+            ConflictReason::Discontinuous
+        } else if line_of(start) != line_of(end - 1) {
+            ConflictReason::CrossLine
+        } else {
+            let line = line_of(start);
+            by_line[line].push((start - lines[line].start, end - lines[line].start, entity));
+            continue;
+        };
+        for &(start, end) in &entity.spans {
+            for line in line_of(start)..=line_of(end - 1) {
+                let offset = lines[line].start;
+                let range = (start.max(offset) - offset, end - offset);
+                uncertain[line].push(range);
+            }
+        }
+        conflicts.push(BratConflict {
+            entity: entity.clone(),
+            reason,
+            words: Vec::new(),
+        });
+    }
+
+    let mut sentences = Vec::new();
+    for ((line, mut entities), uncertain) in lines.iter().zip(by_line).zip(uncertain) {
+        if line.text.trim().is_empty() {
+            continue;
+        }
+        let words = segment(line.text)?;
+        let offsets = offsets(line.text, &words)?;
+        let mut labels = vec!["O".to_string(); words.len()];
+        let mut labelled = vec![false; words.len()];
+        entities.sort_by_key(|&(start, end, _)| (start, std::cmp::Reverse(end)));
+        for (start, end, entity) in entities {
+            // 与实体有交集的词
+            let first = offsets.partition_point(|&(_, e)| e <= start);
+            let last = offsets.partition_point(|&(s, _)| s < end);
+            if first >= last {
+                continue;
+            }
+            let reason = if labelled[first..last].iter().any(|&l| l) {
+                ConflictReason::Overlap
+            } else if offsets[first].0 != start || offsets[last - 1].1 != end {
+                ConflictReason::Boundary
+            } else {
+                let kind = &entity.kind;
+                for (i, label) in labels[first..last].iter_mut().enumerate() {
+                    *label = match (i, last - first) {
+                        (_, 1) => format!("S-{}", kind),
+                        (0, _) => format!("B-{}", kind),
+                        (i, n) if i == n - 1 => format!("E-{}", kind),
+                        _ => format!("I-{}", kind),
+                    };
+                }
+                labelled[first..last].fill(true);
+                continue;
+            };
+            for i in first..last {
+                if !labelled[i] {
+                    labels[i] = UNCERTAIN_TAG.to_string();
+                }
+            }
+            conflicts.push(BratConflict {
+                entity: entity.clone(),
+                reason,
+                words: words[first..last].to_vec(),
+            });
+        }
+        for (start, end) in uncertain {
+            for (i, &(s, e)) in offsets.iter().enumerate() {
+                if s < end && e > start && !labelled[i] {
+                    labels[i] = UNCERTAIN_TAG.to_string();
+                }
+            }
+        }
+        sentences.push(AnnotatedSentence {
+            words,
+            pos: None,
+            labels: Some(labels),
+        });
+    }
+    Ok((sentences, conflicts))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_align_brat() -> Result<()> {
+        let text = "张三在北京大学读书\n\n李四去上海\n";
+        let ann = "T1\tPerson 0 2\t张三\nT2\tOrg 3 7\t北京大学\nT3\tLoc 3 5\t北京\n\
+                   T4\tPerson 11 12\t李\nR1\tWork Arg1:T1 Arg2:T2\nT5\tLoc 14 16\t上海\n\
+                   T6\tLoc 13 14;15 16\t去海\n";
+        let entities = parse_ann(ann)?;
+        assert_eq!(entities.len(), 6);
+        assert_eq!(entities[1].spans, vec![(3, 7)]);
+
+        let segment = |line: &str| -> Result<Vec<String>> {
+            let words: &[&str] = match line {
+                "张三在北京大学读书" => &["张三", "在", "北京大学", "读书"],
+                _ => &["李四", "去", "上海"],
+            };
+            Ok(words.iter().map(|w| w.to_string()).collect())
+        };
+        let (sentences, conflicts) = align_brat(text, &entities, segment)?;
+        assert_eq!(sentences.len(), 2);
+        assert_eq!(sentences[0].labels()?, vec!["S-Person", "O", "S-Org", "O"]);
+        assert_eq!(sentences[1].labels()?, vec!["_", "_", "S-Loc"]);
+        let reasons: Vec<_> = conflicts
+            .iter()
+            .map(|c| (c.entity.id.as_str(), c.reason))
+            .collect();
+        assert_eq!(
+            reasons,
+            vec![
+                ("T6", ConflictReason::Discontinuous),
+                ("T3", ConflictReason::Overlap),
+                ("T4", ConflictReason::Boundary)
+            ]
+        );
+        Ok(())
+    }
+}
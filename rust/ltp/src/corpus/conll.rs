@@ -0,0 +1,185 @@
+//! CoNLL 列格式：每行一个词，列之间以空白或指定分隔符分开，空行分隔句子
+use crate::corpus::AnnotatedSentence;
+use anyhow::{anyhow, Result};
+use std::io::{BufRead, BufReader, Lines, Read};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConllFormat {
+    pub word: usize,
+    pub pos: Option<usize>,
+    pub label: Option<usize>,
+    /// 以该前缀开头的行视为注释
+    pub comment: Option<String>,
+    /// 列分隔符，`None` 时按任意空白切分
+    pub delimiter: Option<char>,
+    /// 跳过 CoNLL-2003 的 `-DOCSTART-` 行
+    pub skip_docstart: bool,
+}
+
+impl Default for ConllFormat {
+    fn default() -> Self {
+        ConllFormat {
+            word: 0,
+            pos: None,
+            label: None,
+            comment: Some("#".to_string()),
+            delimiter: None,
+            skip_docstart: true,
+        }
+    }
+}
+
+macro_rules! impl_set_param {
+    ($name:ident, $type:ty) => {
+        pub fn $name(mut self, $name: $type) -> Self {
+            self.$name = $name;
+            self
+        }
+    };
+}
+
+impl ConllFormat {
+    pub fn new(word: usize) -> Self {
+        ConllFormat {
+            word,
+            ..Default::default()
+        }
+    }
+
+    /// CoNLL-2000 组块分析：词、词性、组块标签
+    pub fn conll2000() -> Self {
+        Self::new(0).pos(1).label(2)
+    }
+
+    /// CoNLL-2003 命名实体识别：词、词性、组块标签、实体标签
+    pub fn conll2003() -> Self {
+        Self::new(0).pos(1).label(3)
+    }
+
+    pub fn pos(mut self, column: usize) -> Self {
+        self.pos = Some(column);
+        self
+    }
+
+    pub fn label(mut self, column: usize) -> Self {
+        self.label = Some(column);
+        self
+    }
+
+    impl_set_param!(comment, Option<String>);
+    impl_set_param!(delimiter, Option<char>);
+    impl_set_param!(skip_docstart, bool);
+
+    fn is_skipped(&self, line: &str) -> bool {
+        if let Some(comment) = &self.comment {
+            if line.starts_with(comment.as_str()) {
+                return true;
+            }
+        }
+        self.skip_docstart && line.starts_with("-DOCSTART-")
+    }
+
+    fn split<'a>(&self, line: &'a str) -> Vec<&'a str> {
+        match self.delimiter {
+            Some(delimiter) => line.split(delimiter).map(str::trim).collect(),
+            None => line.split_whitespace().collect(),
+        }
+    }
+}
+
+/// 逐句读取 CoNLL 语料
+pub struct ConllReader<R: Read> {
+    lines: Lines<BufReader<R>>,
+    format: ConllFormat,
+    line: usize,
+}
+
+impl<R: Read> ConllReader<R> {
+    pub fn new(reader: R, format: ConllFormat) -> Self {
+        ConllReader {
+            lines: BufReader::new(reader).lines(),
+            format,
+            line: 0,
+        }
+    }
+
+    fn column<'a>(&self, columns: &[&'a str], index: usize) -> Result<&'a str> {
+        columns.get(index).copied().ok_or_else(|| {
+            anyhow!(
+                "line {}: expected at least {} columns, found {}",
+                self.line,
+                index + 1,
+                columns.len()
+            )
+        })
+    }
+
+    fn push(&self, sentence: &mut AnnotatedSentence, line: &str) -> Result<()> {
+        let columns = self.format.split(line);
+        sentence
+            .words
+            .push(self.column(&columns, self.format.word)?.to_string());
+        if let Some(pos) = self.format.pos {
+            let tag = self.column(&columns, pos)?.to_string();
+            sentence.pos.get_or_insert_with(Vec::new).push(tag);
+        }
+        if let Some(label) = self.format.label {
+            let tag = self.column(&columns, label)?.to_string();
+            sentence.labels.get_or_insert_with(Vec::new).push(tag);
+        }
+        Ok(())
+    }
+}
+
+impl<R: Read> Iterator for ConllReader<R> {
+    type Item = Result<AnnotatedSentence>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let mut sentence = AnnotatedSentence::default();
+        while let Some(line) = self.lines.next() {
+            self.line += 1;
+            let line = match line {
+                Ok(line) => line,
+                Err(err) => return Some(Err(err.into())),
+            };
+            let line = line.trim();
+            if line.is_empty() {
+                if sentence.words.is_empty() {
+                    continue;
+                }
+                return Some(Ok(sentence));
+            }
+            if self.format.is_skipped(line) {
+                continue;
+            }
+            if let Err(err) = self.push(&mut sentence, line) {
+                return Some(Err(err));
+            }
+        }
+        (!sentence.words.is_empty()).then_some(Ok(sentence))
+    }
+}
+
+pub fn read_conll<R: Read>(reader: R, format: ConllFormat) -> Result<Vec<AnnotatedSentence>> {
+    ConllReader::new(reader, format).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_conll2003() -> Result<()> {
+        let text = "-DOCSTART- -X- -X- O\n\nEU NNP B-NP B-ORG\nrejects VBZ B-VP O\n# comment\n\n\nPeter NNP B-NP B-PER\n";
+        let sentences = read_conll(text.as_bytes(), ConllFormat::conll2003())?;
+        assert_eq!(sentences.len(), 2);
+        assert_eq!(sentences[0].words, vec!["EU", "rejects"]);
+        assert_eq!(sentences[0].pos, Some(vec!["NNP".into(), "VBZ".into()]));
+        assert_eq!(sentences[0].labels, Some(vec!["B-ORG".into(), "O".into()]));
+        assert_eq!(sentences[1].words, vec!["Peter"]);
+
+        let err = read_conll("EU NNP\n".as_bytes(), ConllFormat::conll2003()).unwrap_err();
+        assert!(err.to_string().starts_with("line 1"));
+        Ok(())
+    }
+}
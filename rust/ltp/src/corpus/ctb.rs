@@ -0,0 +1,165 @@
+//! 宾州中文树库（CTB）括号树：抽取词与词性，去掉空语类
+use crate::corpus::AnnotatedSentence;
+use anyhow::{anyhow, Result};
+use std::io::Read;
+
+/// 识别 BOM，CTB 5 及更早版本为 GB2312 编码，需要先用 iconv 等工具转为 UTF-8
+fn decode(bytes: &[u8]) -> Result<String> {
+    if let Some(bytes) = bytes.strip_prefix(b"\xEF\xBB\xBF") {
+        return Ok(String::from_utf8(bytes.to_vec())?);
+    }
+    let utf16 = |bytes: &[u8], le: bool| -> Result<String> {
+        let units: Vec<u16> = bytes
+            .chunks_exact(2)
+            .map(|pair| match le {
+                true => u16::from_le_bytes([pair[0], pair[1]]),
+                false => u16::from_be_bytes([pair[0], pair[1]]),
+            })
+            .collect();
+        Ok(String::from_utf16(&units)?)
+    };
+    if let Some(bytes) = bytes.strip_prefix(b"\xFF\xFE") {
+        return utf16(bytes, true);
+    }
+    if let Some(bytes) = bytes.strip_prefix(b"\xFE\xFF") {
+        return utf16(bytes, false);
+    }
+    String::from_utf8(bytes.to_vec())
+        .map_err(|_| anyhow!("treebank is not UTF-8, convert it first (e.g. iconv -f GB18030)"))
+}
+
+/// 树库中转义的括号
+fn unescape(word: &str) -> &str {
+    match word {
+        "-LRB-" => "(",
+        "-RRB-" => ")",
+        "-LSB-" => "[",
+        "-RSB-" => "]",
+        "-LCB-" => "{",
+        "-RCB-" => "}",
+        word => word,
+    }
+}
+
+/// 去掉功能标签与同指下标，如 `NN-SHORT` 与 `NP-SBJ=2`
+fn strip_tag(tag: &str) -> &str {
+    if tag.starts_with('-') {
+        return tag;
+    }
+    tag.split(['-', '=']).next().unwrap_or(tag)
+}
+
+enum Token<'a> {
+    Open,
+    Close,
+    Atom(&'a str),
+}
+
+fn tokenize(text: &str) -> Vec<Token<'_>> {
+    let mut tokens = Vec::new();
+    let mut depth = 0usize;
+    for line in text.lines() {
+        // 顶层的 SGML 标记，如 `<S ID=1>`、`</DOC>`
+        if depth == 0 && line.trim_start().starts_with('<') {
+            continue;
+        }
+        let mut rest = line;
+        while let Some(start) = rest.find(|c: char| !c.is_whitespace()) {
+            rest = &rest[start..];
+            if let Some(tail) = rest.strip_prefix('(') {
+                tokens.push(Token::Open);
+                depth += 1;
+                rest = tail;
+            } else if let Some(tail) = rest.strip_prefix(')') {
+                tokens.push(Token::Close);
+                depth = depth.saturating_sub(1);
+                rest = tail;
+            } else {
+                let end = rest
+                    .find(|c: char| c.is_whitespace() || c == '(' || c == ')')
+                    .unwrap_or(rest.len());
+                tokens.push(Token::Atom(&rest[..end]));
+                rest = &rest[end..];
+            }
+        }
+    }
+    tokens
+}
+
+/// 解析一个括号表达式，把叶子 `(词性 词)` 依次加入句子，返回下一个位置
+fn parse_node(tokens: &[Token], mut pos: usize, sentence: &mut AnnotatedSentence) -> Result<usize> {
+    // 调用时 tokens[pos] 为 Open
+    pos += 1;
+    let label = match tokens.get(pos) {
+        Some(Token::Atom(label)) => {
+            pos += 1;
+            Some(*label)
+        }
+        _ => None,
+    };
+    if let (Some(tag), Some(Token::Atom(word)), Some(Token::Close)) =
+        (label, tokens.get(pos), tokens.get(pos + 1))
+    {
+        if tag != "-NONE-" {
+            sentence.words.push(unescape(word).to_string());
+            let pos = sentence.pos.get_or_insert_with(Vec::new);
+            pos.push(strip_tag(tag).to_string());
+        }
+        return Ok(pos + 2);
+    }
+    loop {
+        match tokens.get(pos) {
+            Some(Token::Open) => pos = parse_node(tokens, pos, sentence)?,
+            Some(Token::Close) => return Ok(pos + 1),
+            Some(Token::Atom(atom)) => return Err(anyhow!("unexpected atom {} in tree", atom)),
+            None => return Err(anyhow!("unbalanced brackets in tree")),
+        }
+    }
+}
+
+pub fn parse_ctb(text: &str) -> Result<Vec<AnnotatedSentence>> {
+    let tokens = tokenize(text);
+    let mut sentences = Vec::new();
+    let mut pos = 0;
+    while pos < tokens.len() {
+        match tokens[pos] {
+            Token::Open => {
+                let mut sentence = AnnotatedSentence::default();
+                pos = parse_node(&tokens, pos, &mut sentence)?;
+                if !sentence.words.is_empty() {
+                    sentences.push(sentence);
+                }
+            }
+            Token::Close => return Err(anyhow!("unbalanced brackets in tree")),
+            // 树之外的文本，如部分版本中的句子编号
+            Token::Atom(_) => pos += 1,
+        }
+    }
+    Ok(sentences)
+}
+
+/// 读取一个树库文件，处理 BOM 与 UTF-16
+pub fn read_ctb<R: Read>(mut reader: R) -> Result<Vec<AnnotatedSentence>> {
+    let mut bytes = Vec::new();
+    reader.read_to_end(&mut bytes)?;
+    parse_ctb(&decode(&bytes)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_ctb() -> Result<()> {
+        let text = "\u{feff}<S ID=1>\n( (IP (NP-SBJ (-NONE- *pro*))\n     (VP (VV 欢迎) (NP-OBJ (NR-SHORT 上海)))\n     (PU -LRB-)))\n</S>\n((FRAG (NN 完)))";
+        let sentences = read_ctb(text.as_bytes())?;
+        assert_eq!(sentences.len(), 2);
+        assert_eq!(sentences[0].words, vec!["欢迎", "上海", "("]);
+        assert_eq!(sentences[0].pos()?, vec!["VV", "NR", "PU"]);
+        assert_eq!(sentences[1].words, vec!["完"]);
+
+        assert!(parse_ctb("( (IP (NN 完)").is_err());
+        assert!(read_ctb(&b"\xc9\xcf\xba\xa3"[..]).is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,110 @@
+//! 从语料中发现标签表，固定已有标签的下标，新标签追加在后面
+use crate::corpus::AnnotatedSentence;
+use crate::perceptron::{ModelTask, UNCERTAIN_TAG};
+use anyhow::{anyhow, Result};
+#[cfg(feature = "serialization")]
+use serde::{Deserialize, Serialize};
+use std::cmp::Reverse;
+use std::collections::{HashMap, HashSet};
+
+/// 新发现标签的排列顺序
+#[cfg_attr(feature = "serialization", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serialization", serde(rename_all = "kebab-case"))]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum LabelOrder {
+    /// 按字典序，与语料顺序无关
+    #[default]
+    Lexicographic,
+    /// 按在语料中首次出现的顺序
+    FirstSeen,
+    /// 按出现次数从多到少，次数相同时按字典序
+    Frequency,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LabelDiscovery {
+    /// 固定在最前面的标签，如旧模型的标签表，重新训练后下标保持不变
+    pub pinned: Vec<String>,
+    pub order: LabelOrder,
+}
+
+impl LabelDiscovery {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn pin(mut self, labels: Vec<String>) -> Self {
+        self.pinned = labels;
+        self
+    }
+
+    pub fn order(mut self, order: LabelOrder) -> Self {
+        self.order = order;
+        self
+    }
+
+    /// 不确定标记 `_` 不计入标签表
+    pub fn discover<'a, I: IntoIterator<Item = &'a str>>(&self, labels: I) -> Vec<String> {
+        let mut result = self.pinned.clone();
+        let mut seen: HashSet<&str> = self.pinned.iter().map(String::as_str).collect();
+        let mut counts: HashMap<&str, usize> = HashMap::new();
+        let mut found = Vec::new();
+        for label in labels.into_iter().filter(|&label| label != UNCERTAIN_TAG) {
+            *counts.entry(label).or_default() += 1;
+            if seen.insert(label) {
+                found.push(label);
+            }
+        }
+        match self.order {
+            LabelOrder::Lexicographic => found.sort_unstable(),
+            LabelOrder::FirstSeen => {}
+            LabelOrder::Frequency => found.sort_by_key(|label| (Reverse(counts[label]), *label)),
+        }
+        result.extend(found.into_iter().map(String::from));
+        result
+    }
+
+    /// 词性标注取 `pos` 列，命名实体识别取 `labels` 列，分词的标签固定为 B/M/E/S
+    pub fn discover_corpus(
+        &self,
+        sentences: &[AnnotatedSentence],
+        task: ModelTask,
+    ) -> Result<Vec<String>> {
+        let mut columns = Vec::with_capacity(sentences.len());
+        for sentence in sentences {
+            columns.push(match task {
+                ModelTask::CWS => return Err(anyhow!("CWS labels are fixed")),
+                ModelTask::POS => sentence.pos()?,
+                ModelTask::NER => sentence.labels()?,
+            });
+        }
+        Ok(self.discover(columns.into_iter().flatten()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_discover() {
+        let labels = ["O", "S-Nh", "O", "B-Ns", "E-Ns", "S-Nh", "O"];
+        let discovery = LabelDiscovery::new();
+        assert_eq!(
+            discovery.discover(labels),
+            vec!["B-Ns", "E-Ns", "O", "S-Nh"]
+        );
+        assert_eq!(
+            discovery
+                .clone()
+                .order(LabelOrder::Frequency)
+                .discover(labels),
+            vec!["O", "S-Nh", "B-Ns", "E-Ns"]
+        );
+        let pinned = discovery
+            .pin(vec!["S-Nh".into(), "S-Ni".into()])
+            .order(LabelOrder::FirstSeen)
+            .discover(labels);
+        assert_eq!(pinned, vec!["S-Nh", "S-Ni", "O", "B-Ns", "E-Ns"]);
+    }
+}
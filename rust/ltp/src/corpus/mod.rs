@@ -0,0 +1,369 @@
+//! 语料读取，分词、词性标注、命名实体识别与组块分析共用
+mod analyze;
+mod augment;
+mod brat;
+mod conll;
+mod ctb;
+mod labels;
+mod split;
+
+pub use analyze::{analyze, CorpusReport, Violation};
+pub use augment::{
+    Augment, Augmenter, DigitRandomization, EntitySubstitution, PunctuationVariation,
+    SynonymSubstitution,
+};
+pub use brat::{align_brat, parse_ann, BratConflict, BratEntity, ConflictReason};
+pub use conll::{read_conll, ConllFormat, ConllReader};
+pub use ctb::{parse_ctb, read_ctb};
+pub use labels::{LabelDiscovery, LabelOrder};
+pub use split::{SplitManifest, SplitSize, Splitter, Stratify};
+
+#[cfg(any(feature = "cws", feature = "pos", feature = "ner"))]
+use crate::error::LtpError;
+#[cfg(feature = "fs")]
+use crate::perceptron::CompressedReader;
+use crate::perceptron::{Definition, ModelTask, Sample, Trainer, TraitParameter};
+#[cfg(any(feature = "pos", feature = "ner"))]
+use crate::perceptron::{UNCERTAIN, UNCERTAIN_TAG};
+#[cfg(feature = "cws")]
+use crate::CWSDefinition;
+#[cfg(feature = "ner")]
+use crate::NERDefinition;
+#[cfg(feature = "pos")]
+use crate::POSDefinition;
+use anyhow::{anyhow, Result};
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
+use std::borrow::Borrow;
+use std::fmt::Display;
+use std::io::{BufRead, BufReader, Read, Write};
+#[cfg(feature = "fs")]
+use std::path::Path;
+
+/// 一个已标注的句子，`pos` 与 `labels` 与 `words` 一一对应
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct AnnotatedSentence {
+    pub words: Vec<String>,
+    pub pos: Option<Vec<String>>,
+    /// 实体、组块等序列标签
+    pub labels: Option<Vec<String>>,
+}
+
+impl AnnotatedSentence {
+    pub fn words(&self) -> Vec<&str> {
+        self.words.iter().map(|w| w.as_str()).collect()
+    }
+
+    fn column<'a>(column: &'a Option<Vec<String>>, name: &str) -> Result<Vec<&'a str>> {
+        column
+            .as_ref()
+            .map(|column| column.iter().map(|w| w.as_str()).collect())
+            .ok_or_else(|| anyhow!("sentence has no {} column", name))
+    }
+
+    pub fn pos(&self) -> Result<Vec<&str>> {
+        Self::column(&self.pos, "pos")
+    }
+
+    pub fn labels(&self) -> Result<Vec<&str>> {
+        Self::column(&self.labels, "label")
+    }
+}
+
+/// 读取训练使用的行格式，按任务决定每个词带几个标签：分词无标签，
+/// 词性标注为 `词/词性`，命名实体识别为 `词/词性/实体`
+pub fn read_ltp<R: Read>(reader: R, task: ModelTask) -> Result<Vec<AnnotatedSentence>> {
+    let columns = match task {
+        ModelTask::CWS => 0,
+        ModelTask::POS => 1,
+        ModelTask::NER => 2,
+    };
+    let mut sentences = Vec::new();
+    for (index, line) in BufReader::new(reader).lines().enumerate() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let mut sentence = AnnotatedSentence::default();
+        for token in line.split_whitespace() {
+            let mut parts = token.rsplitn(columns + 1, '/');
+            let mut tags: Vec<_> = parts.by_ref().take(columns).collect();
+            let word = parts
+                .next()
+                .ok_or_else(|| anyhow!("line {}: token {} has too few tags", index + 1, token))?;
+            sentence.words.push(word.to_string());
+            if let Some(label) = (columns == 2).then(|| tags.remove(0)) {
+                sentence
+                    .labels
+                    .get_or_insert_with(Vec::new)
+                    .push(label.to_string());
+            }
+            if let Some(pos) = tags.pop() {
+                sentence
+                    .pos
+                    .get_or_insert_with(Vec::new)
+                    .push(pos.to_string());
+            }
+        }
+        sentences.push(sentence);
+    }
+    Ok(sentences)
+}
+
+/// 写出为训练使用的行格式，每句一行、空格分隔的 `词[/词性][/标签]`，与 `examples` 相同
+pub fn write_ltp<W, I>(mut writer: W, sentences: I) -> Result<()>
+where
+    W: Write,
+    I: IntoIterator,
+    I::Item: Borrow<AnnotatedSentence>,
+{
+    for sentence in sentences {
+        let sentence = sentence.borrow();
+        let mut line = String::new();
+        for (i, word) in sentence.words.iter().enumerate() {
+            if i > 0 {
+                line.push(' ');
+            }
+            line.push_str(word);
+            for column in [&sentence.pos, &sentence.labels].into_iter().flatten() {
+                let tag = column
+                    .get(i)
+                    .ok_or_else(|| anyhow!("column is shorter than words"))?;
+                line.push('/');
+                line.push_str(tag);
+            }
+        }
+        writeln!(writer, "{}", line)?;
+    }
+    Ok(())
+}
+
+/// 由已标注句子构造训练样本：分词使用词边界，词性标注以 `pos` 为目标，
+/// 命名实体识别与组块分析以 `labels` 为目标、`pos` 为特征
+pub trait SampleDefinition: Definition {
+    fn sample(&self, sentence: &AnnotatedSentence) -> Result<Sample>;
+
+    /// 由词与逐词的标签构造样本，标签与训练文件中 `/` 之后的部分相同：
+    /// 分词没有标签，词性标注为词性，命名实体识别为 `词性/实体`
+    fn tagged_sample(&self, tokens: &[&str], labels: &[&str]) -> Result<Sample>;
+
+    fn samples(&self, sentences: &[AnnotatedSentence]) -> Result<Vec<Sample>>
+    where
+        Self: Sync,
+    {
+        #[cfg(feature = "parallel")]
+        let iter = sentences.par_iter();
+        #[cfg(not(feature = "parallel"))]
+        let iter = sentences.iter();
+        iter.map(|sentence| self.sample(sentence)).collect()
+    }
+}
+
+/// `_` 标记不确定的词，训练时不产生更新
+#[cfg(any(feature = "pos", feature = "ner"))]
+fn label_ids<D: Definition>(define: &D, labels: &[&str]) -> Result<Vec<usize>> {
+    labels
+        .iter()
+        .map(|&label| match label {
+            UNCERTAIN_TAG => Ok(UNCERTAIN),
+            label => define.label_to(label),
+        })
+        .collect()
+}
+
+#[cfg(feature = "cws")]
+impl SampleDefinition for CWSDefinition {
+    fn sample(&self, sentence: &AnnotatedSentence) -> Result<Sample> {
+        let text = sentence.words.concat();
+        let boundaries: Vec<usize> = sentence
+            .words
+            .iter()
+            .scan(0, |end, word| {
+                *end += word.len();
+                Some(*end)
+            })
+            .collect();
+        let labels = self.boundary_labels(&text, &boundaries)?;
+        let (_, features) = self.parse_char_features(&text)?;
+        Ok(Sample::new(features, labels))
+    }
+
+    fn tagged_sample(&self, tokens: &[&str], labels: &[&str]) -> Result<Sample> {
+        if !labels.is_empty() {
+            return Err(LtpError::LengthMismatch(0, labels.len()).into());
+        }
+        self.sample(&AnnotatedSentence {
+            words: tokens.iter().map(|&word| word.to_string()).collect(),
+            ..Default::default()
+        })
+    }
+}
+
+#[cfg(feature = "pos")]
+impl SampleDefinition for POSDefinition {
+    fn sample(&self, sentence: &AnnotatedSentence) -> Result<Sample> {
+        let labels = label_ids(self, &sentence.pos()?)?;
+        let features = self.parse_words_features(&sentence.words())?;
+        Ok(Sample::new(features, labels))
+    }
+
+    fn tagged_sample(&self, tokens: &[&str], labels: &[&str]) -> Result<Sample> {
+        if tokens.len() != labels.len() {
+            return Err(LtpError::LengthMismatch(tokens.len(), labels.len()).into());
+        }
+        let labels = label_ids(self, labels)?;
+        Ok(Sample::new(self.parse_words_features(tokens)?, labels))
+    }
+}
+
+#[cfg(feature = "ner")]
+impl SampleDefinition for NERDefinition {
+    fn sample(&self, sentence: &AnnotatedSentence) -> Result<Sample> {
+        let labels = label_ids(self, &sentence.labels()?)?;
+        let features = self.parse_words_features(&sentence.words(), &sentence.pos()?)?;
+        Ok(Sample::new(features, labels))
+    }
+
+    fn tagged_sample(&self, tokens: &[&str], labels: &[&str]) -> Result<Sample> {
+        if tokens.len() != labels.len() {
+            return Err(LtpError::LengthMismatch(tokens.len(), labels.len()).into());
+        }
+        let (poses, labels): (Vec<&str>, Vec<&str>) = labels
+            .iter()
+            .map(|&tag| {
+                tag.rsplit_once('/')
+                    .ok_or_else(|| LtpError::MalformedToken(tag.to_string(), "pos/label"))
+            })
+            .collect::<Result<Vec<_>, _>>()?
+            .into_iter()
+            .unzip();
+        let labels = label_ids(self, &labels)?;
+        Ok(Sample::new(
+            self.parse_words_features(tokens, &poses)?,
+            labels,
+        ))
+    }
+}
+
+impl<Define, Param> Trainer<Define, Param>
+where
+    Define: SampleDefinition + Sync + Send + 'static,
+    Param: TraitParameter + Display + Sync + Send + 'static,
+{
+    /// 由内存中的已标注句子构造训练集，如混合了增强样本的语料
+    pub fn train_sentences(mut self, sentences: &[AnnotatedSentence]) -> Result<Self> {
+        self.train_set = Some(self.definition.samples(sentences)?);
+        self.domains.clear();
+        Ok(self)
+    }
+
+    /// 由内存中的词与标签逐句抽取特征，不必先写成训练文件，标签见 [`SampleDefinition::tagged_sample`]
+    pub fn load_tagged<I, T, L>(&self, samples: I) -> Result<Vec<Sample>>
+    where
+        I: IntoIterator<Item = (T, L)>,
+        T: IntoIterator,
+        T::Item: AsRef<str>,
+        L: IntoIterator,
+        L::Item: AsRef<str>,
+    {
+        samples
+            .into_iter()
+            .map(|(tokens, labels)| {
+                let tokens: Vec<T::Item> = tokens.into_iter().collect();
+                let labels: Vec<L::Item> = labels.into_iter().collect();
+                let tokens: Vec<&str> = tokens.iter().map(AsRef::as_ref).collect();
+                let labels: Vec<&str> = labels.iter().map(AsRef::as_ref).collect();
+                self.definition.tagged_sample(&tokens, &labels)
+            })
+            .collect()
+    }
+
+    pub fn train_tagged<I, T, L>(mut self, samples: I) -> Result<Self>
+    where
+        I: IntoIterator<Item = (T, L)>,
+        T: IntoIterator,
+        T::Item: AsRef<str>,
+        L: IntoIterator,
+        L::Item: AsRef<str>,
+    {
+        self.train_set = Some(self.load_tagged(samples)?);
+        self.domains.clear();
+        Ok(self)
+    }
+
+    pub fn eval_tagged<I, T, L>(mut self, samples: I) -> Result<Self>
+    where
+        I: IntoIterator<Item = (T, L)>,
+        T: IntoIterator,
+        T::Item: AsRef<str>,
+        L: IntoIterator,
+        L::Item: AsRef<str>,
+    {
+        self.eval_set = Some(self.load_tagged(samples)?);
+        Ok(self)
+    }
+
+    #[cfg(feature = "fs")]
+    pub fn load_conll<P: AsRef<Path>>(&self, path: P, format: ConllFormat) -> Result<Vec<Sample>> {
+        let sentences = read_conll(CompressedReader::open(path)?, format)?;
+        self.definition.samples(&sentences)
+    }
+
+    #[cfg(feature = "fs")]
+    pub fn train_conll<P: AsRef<Path>>(mut self, path: P, format: ConllFormat) -> Result<Self> {
+        self.train_set = Some(self.load_conll(path, format)?);
+        self.domains.clear();
+        Ok(self)
+    }
+
+    #[cfg(feature = "fs")]
+    pub fn eval_conll<P: AsRef<Path>>(mut self, path: P, format: ConllFormat) -> Result<Self> {
+        self.eval_set = Some(self.load_conll(path, format)?);
+        Ok(self)
+    }
+}
+
+#[cfg(all(test, feature = "cws", feature = "pos", feature = "ner"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_samples() -> Result<()> {
+        let text = "他 r O\n叫 v O\n汤姆 nh S-Nh\n";
+        let sentences = read_conll(text.as_bytes(), ConllFormat::new(0).pos(1).label(2))?;
+
+        let cws = CWSDefinition::new();
+        let expected = &cws.parse_gold_features("他 叫 汤姆".as_bytes())?[0].labels;
+        assert_eq!(&cws.sample(&sentences[0])?.labels, expected);
+
+        let ner = NERDefinition::new(vec!["O".into(), "S-Nh".into()]);
+        assert_eq!(ner.samples(&sentences)?[0].labels, vec![0, 0, 1]);
+        let pos = POSDefinition::new(vec!["r".into(), "v".into()]);
+        assert!(pos.sample(&sentences[0]).is_err());
+
+        let mut buffer = Vec::new();
+        write_ltp(&mut buffer, &sentences)?;
+        assert_eq!(read_ltp(buffer.as_slice(), ModelTask::NER)?, sentences);
+        assert!(read_ltp("他/r".as_bytes(), ModelTask::NER).is_err());
+
+        // 不确定的词
+        let sentences = read_ltp("他/_ 叫/v".as_bytes(), ModelTask::POS)?;
+        assert_eq!(pos.sample(&sentences[0])?.labels, vec![UNCERTAIN, 1]);
+        let gold = &pos.parse_gold_features("他/_ 叫/v".as_bytes())?[0].labels;
+        assert_eq!(gold, &vec![UNCERTAIN, 1]);
+
+        // 内存中的词与标签
+        let trainer: Trainer<NERDefinition> = Trainer::new_with_define(ner);
+        let tagged = [(vec!["他", "叫", "汤姆"], vec!["r/O", "v/O", "nh/S-Nh"])];
+        let samples = trainer.load_tagged(tagged.clone())?;
+        assert_eq!(samples[0].labels, vec![0, 0, 1]);
+        assert!(trainer.load_tagged([(vec!["他"], vec!["O"])]).is_err());
+        let trainer = trainer.train_tagged(tagged)?;
+        assert_eq!(trainer.train_set.map(|set| set.len()), Some(1));
+        let trainer: Trainer<CWSDefinition> = Trainer::new_with_define(cws);
+        let words = vec![vec!["他".to_string(), "叫".into(), "汤姆".into()]];
+        let samples = trainer.load_tagged(words.into_iter().map(|w| (w, Vec::<&str>::new())))?;
+        assert_eq!(&samples[0].labels, expected);
+        Ok(())
+    }
+}
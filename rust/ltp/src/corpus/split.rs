@@ -0,0 +1,226 @@
+//! 训练、验证、测试集划分，固定随机种子，可按标签分布或句长分层
+use crate::corpus::AnnotatedSentence;
+use anyhow::{anyhow, Result};
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::SeedableRng;
+#[cfg(feature = "serialization")]
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+
+/// 划分的大小，比例或句子数
+#[cfg_attr(feature = "serialization", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serialization", serde(rename_all = "lowercase"))]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SplitSize {
+    Ratio(f64),
+    Count(usize),
+}
+
+impl SplitSize {
+    fn count(&self, total: usize) -> Result<usize> {
+        match *self {
+            SplitSize::Ratio(ratio) if (0.0..=1.0).contains(&ratio) => {
+                Ok((ratio * total as f64).round() as usize)
+            }
+            SplitSize::Ratio(ratio) => Err(anyhow!("split ratio {} is not in [0, 1]", ratio)),
+            SplitSize::Count(count) => Ok(count),
+        }
+    }
+}
+
+#[cfg_attr(feature = "serialization", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serialization", serde(rename_all = "lowercase"))]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Stratify {
+    #[default]
+    None,
+    /// 按句中最稀有的标签（`labels`，没有时用 `pos`）分层
+    Label,
+    /// 按句长分层，参数为分桶宽度
+    Length(usize),
+}
+
+#[cfg_attr(feature = "serialization", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, PartialEq)]
+pub struct Splitter {
+    pub dev: SplitSize,
+    pub test: SplitSize,
+    pub seed: u64,
+    pub stratify: Stratify,
+}
+
+impl Default for Splitter {
+    fn default() -> Self {
+        Splitter {
+            dev: SplitSize::Ratio(0.1),
+            test: SplitSize::Ratio(0.1),
+            seed: 0,
+            stratify: Stratify::None,
+        }
+    }
+}
+
+/// 划分结果，记录每个集合中句子在原语料中的下标，可以保存下来复现实验
+#[cfg_attr(feature = "serialization", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SplitManifest {
+    pub splitter: Splitter,
+    pub total: usize,
+    pub train: Vec<usize>,
+    pub dev: Vec<usize>,
+    pub test: Vec<usize>,
+}
+
+impl SplitManifest {
+    /// 按下标取出各集合的句子，依次为训练、验证、测试集
+    pub fn apply<'a>(
+        &self,
+        sentences: &'a [AnnotatedSentence],
+    ) -> Result<[Vec<&'a AnnotatedSentence>; 3]> {
+        if sentences.len() != self.total {
+            return Err(anyhow!(
+                "manifest is for {} sentences, got {}",
+                self.total,
+                sentences.len()
+            ));
+        }
+        let pick = |indices: &[usize]| indices.iter().map(|&i| &sentences[i]).collect();
+        Ok([pick(&self.train), pick(&self.dev), pick(&self.test)])
+    }
+}
+
+macro_rules! impl_set_param {
+    ($name:ident, $type:ty) => {
+        pub fn $name(mut self, $name: $type) -> Self {
+            self.$name = $name;
+            self
+        }
+    };
+}
+
+impl Splitter {
+    impl_set_param!(dev, SplitSize);
+    impl_set_param!(test, SplitSize);
+    impl_set_param!(seed, u64);
+    impl_set_param!(stratify, Stratify);
+
+    fn keys(&self, sentences: &[AnnotatedSentence]) -> Vec<String> {
+        match self.stratify {
+            Stratify::None => vec![String::new(); sentences.len()],
+            Stratify::Length(width) => sentences
+                .iter()
+                .map(|s| (s.words.len() / width.max(1)).to_string())
+                .collect(),
+            Stratify::Label => {
+                let columns: Vec<_> = sentences
+                    .iter()
+                    .map(|s| s.labels.as_ref().or(s.pos.as_ref()))
+                    .collect();
+                let mut freq: HashMap<&str, usize> = HashMap::new();
+                for label in columns.iter().flatten().copied().flatten() {
+                    *freq.entry(label).or_default() += 1;
+                }
+                columns
+                    .iter()
+                    .map(|labels| {
+                        labels
+                            .iter()
+                            .copied()
+                            .flatten()
+                            .filter(|label| label.as_str() != "O")
+                            .min_by_key(|label| (freq[label.as_str()], label.as_str()))
+                            .cloned()
+                            .unwrap_or_default()
+                    })
+                    .collect()
+            }
+        }
+    }
+
+    /// 组内打乱后按组内相对位置交错排列，依次取验证集、测试集，其余为训练集，
+    /// 这样每个集合中各组的比例与全体一致
+    pub fn split(&self, sentences: &[AnnotatedSentence]) -> Result<SplitManifest> {
+        let total = sentences.len();
+        let dev = self.dev.count(total)?;
+        let test = self.test.count(total)?;
+        if dev + test > total {
+            return Err(anyhow!(
+                "dev ({}) and test ({}) exceed the corpus size {}",
+                dev,
+                test,
+                total
+            ));
+        }
+
+        let mut rng = StdRng::seed_from_u64(self.seed);
+        let mut groups: BTreeMap<String, Vec<usize>> = BTreeMap::new();
+        for (index, key) in self.keys(sentences).into_iter().enumerate() {
+            groups.entry(key).or_default().push(index);
+        }
+        let mut order = Vec::with_capacity(total);
+        for group in groups.values_mut() {
+            group.shuffle(&mut rng);
+            let len = group.len() as f64;
+            let position = |(i, &index): (usize, &usize)| ((i as f64 + 0.5) / len, index);
+            order.extend(group.iter().enumerate().map(position));
+        }
+        // 稳定排序，位置相同时按组的顺序
+        order.sort_by(|a, b| a.0.total_cmp(&b.0));
+        let order: Vec<usize> = order.into_iter().map(|(_, index)| index).collect();
+
+        let mut manifest = SplitManifest {
+            splitter: self.clone(),
+            total,
+            dev: order[..dev].to_vec(),
+            test: order[dev..dev + test].to_vec(),
+            train: order[dev + test..].to_vec(),
+        };
+        manifest.train.sort_unstable();
+        manifest.dev.sort_unstable();
+        manifest.test.sort_unstable();
+        Ok(manifest)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split() -> Result<()> {
+        let sentences: Vec<_> = (0..100)
+            .map(|i| AnnotatedSentence {
+                words: vec![i.to_string()],
+                pos: None,
+                labels: Some(vec![if i % 10 == 0 { "S-Nh" } else { "O" }.to_string()]),
+            })
+            .collect();
+        let splitter = Splitter::default()
+            .test(SplitSize::Count(20))
+            .seed(42)
+            .stratify(Stratify::Label);
+        let manifest = splitter.split(&sentences)?;
+        assert_eq!(manifest, splitter.split(&sentences)?);
+        assert_eq!(
+            (
+                manifest.train.len(),
+                manifest.dev.len(),
+                manifest.test.len()
+            ),
+            (70, 10, 20)
+        );
+        // 10 个含实体的句子按比例分到三个集合
+        let entities = |indices: &[usize]| indices.iter().filter(|&&i| i % 10 == 0).count();
+        assert_eq!(entities(&manifest.dev), 1);
+        assert_eq!(entities(&manifest.test), 2);
+
+        let [train, ..] = manifest.apply(&sentences)?;
+        assert_eq!(train.len(), 70);
+        assert!(Splitter::default()
+            .dev(SplitSize::Ratio(0.95))
+            .split(&sentences)
+            .is_err());
+        Ok(())
+    }
+}
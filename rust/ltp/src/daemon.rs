@@ -0,0 +1,393 @@
+//! 常驻进程：一个进程持有模型，通过 Unix domain socket 为其它进程提供服务，
+//! 避免短命的命令行进程反复加载模型
+//!
+//! 协议均为小端，每条消息为 `u32` 长度加消息体，字符串为 `u32` 长度加 UTF-8：
+//!
+//! - 请求：`u8` 操作（1 为逐句处理，2 为先分句再处理），`u32` 文本数，各文本
+//! - 响应：`u8` 状态，0 为成功，1 为失败并跟一个错误信息字符串；成功时依次为
+//!   词性标签表与实体标签表（`u16` 个数加各标签），`u32` 句子数，以及每句的原文、
+//!   `u32` 词数与各词、`u8` 标志（第 0 位有词性，第 1 位有实体）、按标志给出的 `u16` 标签下标
+//!
+//! 一个连接上可以依次发送多个请求，自定义阶段的输出不会传输。
+use crate::label::{InternedLabels, LabelId, LabelSet};
+use crate::pipeline::{LtpPipeline, Sentence};
+use anyhow::{anyhow, Result};
+use std::collections::HashMap;
+use std::fs;
+use std::io::{self, BufReader, BufWriter, ErrorKind, Read, Write};
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::Path;
+use std::sync::Arc;
+use std::thread;
+
+const OP_PROCESS: u8 = 1;
+const OP_DOCUMENT: u8 = 2;
+const STATUS_OK: u8 = 0;
+const STATUS_ERROR: u8 = 1;
+const HAS_POS: u8 = 1;
+const HAS_NER: u8 = 2;
+/// 单条消息的上限，防止错误的长度前缀导致巨量内存申请
+const MAX_MESSAGE: usize = 1 << 30;
+
+#[derive(Default)]
+struct Encoder {
+    buf: Vec<u8>,
+}
+
+impl Encoder {
+    fn u8(&mut self, value: u8) {
+        self.buf.push(value);
+    }
+
+    fn u16(&mut self, value: u16) {
+        self.buf.extend_from_slice(&value.to_le_bytes());
+    }
+
+    fn u32(&mut self, value: usize) -> Result<()> {
+        let value = u32::try_from(value).map_err(|_| anyhow!("{} exceeds u32", value))?;
+        self.buf.extend_from_slice(&value.to_le_bytes());
+        Ok(())
+    }
+
+    fn str(&mut self, value: &str) -> Result<()> {
+        self.u32(value.len())?;
+        self.buf.extend_from_slice(value.as_bytes());
+        Ok(())
+    }
+
+    fn send<W: Write>(self, writer: &mut W) -> Result<()> {
+        writer.write_all(&(self.buf.len() as u32).to_le_bytes())?;
+        writer.write_all(&self.buf)?;
+        writer.flush()?;
+        Ok(())
+    }
+}
+
+struct Decoder<'a> {
+    buf: &'a [u8],
+}
+
+impl<'a> Decoder<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8]> {
+        if self.buf.len() < len {
+            return Err(anyhow!("truncated message"));
+        }
+        let (head, tail) = self.buf.split_at(len);
+        self.buf = tail;
+        Ok(head)
+    }
+
+    fn u8(&mut self) -> Result<u8> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u16(&mut self) -> Result<u16> {
+        Ok(u16::from_le_bytes(self.take(2)?.try_into()?))
+    }
+
+    fn u32(&mut self) -> Result<usize> {
+        Ok(u32::from_le_bytes(self.take(4)?.try_into()?) as usize)
+    }
+
+    fn str(&mut self) -> Result<&'a str> {
+        let len = self.u32()?;
+        Ok(std::str::from_utf8(self.take(len)?)?)
+    }
+}
+
+/// 读取一条消息，连接在消息边界关闭时返回 `None`
+fn receive<R: Read>(reader: &mut R) -> Result<Option<Vec<u8>>> {
+    let mut len = [0u8; 4];
+    match reader.read_exact(&mut len) {
+        Ok(()) => {}
+        Err(err) if err.kind() == ErrorKind::UnexpectedEof => return Ok(None),
+        Err(err) => return Err(err.into()),
+    }
+    let len = u32::from_le_bytes(len) as usize;
+    if len > MAX_MESSAGE {
+        return Err(anyhow!("message of {} bytes is too large", len));
+    }
+    let mut buf = vec![0u8; len];
+    reader.read_exact(&mut buf)?;
+    Ok(Some(buf))
+}
+
+/// 响应中的标签表，按首次出现的顺序编号
+#[derive(Default)]
+struct LabelTable {
+    labels: Vec<String>,
+    index: HashMap<String, u16>,
+}
+
+impl LabelTable {
+    fn ids(&mut self, labels: &InternedLabels) -> Result<Vec<u16>> {
+        labels
+            .iter()
+            .map(|label| {
+                if let Some(&id) = self.index.get(label) {
+                    return Ok(id);
+                }
+                let id =
+                    u16::try_from(self.labels.len()).map_err(|_| anyhow!("too many labels"))?;
+                self.labels.push(label.to_string());
+                self.index.insert(label.to_string(), id);
+                Ok(id)
+            })
+            .collect()
+    }
+
+    fn encode(&self, encoder: &mut Encoder) -> Result<()> {
+        encoder.u16(self.labels.len() as u16);
+        for label in &self.labels {
+            encoder.str(label)?;
+        }
+        Ok(())
+    }
+}
+
+fn encode_sentences(sentences: &[Sentence]) -> Result<Encoder> {
+    let (mut pos_table, mut ner_table) = (LabelTable::default(), LabelTable::default());
+    let mut body = Encoder::default();
+    body.u32(sentences.len())?;
+    for sentence in sentences {
+        body.str(&sentence.text)?;
+        body.u32(sentence.words.len())?;
+        for word in &sentence.words {
+            body.str(word)?;
+        }
+        let pos = sentence
+            .pos
+            .as_ref()
+            .map(|pos| pos_table.ids(pos))
+            .transpose()?;
+        let ner = sentence
+            .ner
+            .as_ref()
+            .map(|ner| ner_table.ids(ner))
+            .transpose()?;
+        let flags = pos.as_ref().map_or(0, |_| HAS_POS) | ner.as_ref().map_or(0, |_| HAS_NER);
+        body.u8(flags);
+        for ids in [pos, ner].into_iter().flatten() {
+            body.u32(ids.len())?;
+            ids.into_iter().for_each(|id| body.u16(id));
+        }
+    }
+
+    let mut encoder = Encoder::default();
+    encoder.u8(STATUS_OK);
+    pos_table.encode(&mut encoder)?;
+    ner_table.encode(&mut encoder)?;
+    encoder.buf.extend_from_slice(&body.buf);
+    Ok(encoder)
+}
+
+fn decode_labels(decoder: &mut Decoder) -> Result<LabelSet> {
+    let len = decoder.u16()?;
+    let labels = (0..len)
+        .map(|_| decoder.str().map(String::from))
+        .collect::<Result<_>>()?;
+    Ok(LabelSet::new(labels))
+}
+
+fn decode_ids(decoder: &mut Decoder, labels: &LabelSet) -> Result<InternedLabels> {
+    let len = decoder.u32()?;
+    let ids = (0..len)
+        .map(|_| {
+            let id = decoder.u16()? as usize;
+            if id >= labels.len() {
+                return Err(anyhow!("label id {} out of range", id));
+            }
+            Ok(LabelId::try_from(id)?)
+        })
+        .collect::<Result<_>>()?;
+    Ok(InternedLabels::new(labels.clone(), ids))
+}
+
+fn decode_sentences(buf: &[u8]) -> Result<Vec<Sentence>> {
+    let mut decoder = Decoder { buf };
+    match decoder.u8()? {
+        STATUS_OK => {}
+        STATUS_ERROR => return Err(anyhow!("daemon: {}", decoder.str()?)),
+        status => return Err(anyhow!("unknown response status {}", status)),
+    }
+    let pos_labels = decode_labels(&mut decoder)?;
+    let ner_labels = decode_labels(&mut decoder)?;
+    let len = decoder.u32()?;
+    let mut sentences = Vec::with_capacity(len.min(buf.len()));
+    for _ in 0..len {
+        let mut sentence = Sentence::new(decoder.str()?);
+        let words = decoder.u32()?;
+        sentence.words = (0..words)
+            .map(|_| decoder.str().map(String::from))
+            .collect::<Result<_>>()?;
+        let flags = decoder.u8()?;
+        if flags & HAS_POS != 0 {
+            sentence.pos = Some(decode_ids(&mut decoder, &pos_labels)?);
+        }
+        if flags & HAS_NER != 0 {
+            sentence.ner = Some(decode_ids(&mut decoder, &ner_labels)?);
+        }
+        sentences.push(sentence);
+    }
+    Ok(sentences)
+}
+
+/// 持有流水线的服务端，每个连接一个线程
+pub struct LtpDaemon {
+    pipeline: Arc<LtpPipeline>,
+}
+
+impl LtpDaemon {
+    pub fn new(pipeline: impl Into<Arc<LtpPipeline>>) -> Self {
+        Self {
+            pipeline: pipeline.into(),
+        }
+    }
+
+    /// 绑定到 `path` 并阻塞地处理连接，已存在的 socket 文件会被替换
+    pub fn serve<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        let path = path.as_ref();
+        if path.exists() {
+            fs::remove_file(path)?;
+        }
+        self.serve_listener(UnixListener::bind(path)?)
+    }
+
+    pub fn serve_listener(&self, listener: UnixListener) -> Result<()> {
+        for stream in listener.incoming() {
+            let stream = stream?;
+            let pipeline = self.pipeline.clone();
+            thread::spawn(move || {
+                if let Err(err) = handle(&pipeline, stream) {
+                    eprintln!("ltp daemon: {}", err);
+                }
+            });
+        }
+        Ok(())
+    }
+}
+
+fn respond(pipeline: &LtpPipeline, request: &[u8]) -> Result<Encoder> {
+    let mut decoder = Decoder { buf: request };
+    let op = decoder.u8()?;
+    let len = decoder.u32()?;
+    let texts = (0..len)
+        .map(|_| decoder.str())
+        .collect::<Result<Vec<_>>>()?;
+    let sentences = match op {
+        OP_PROCESS => pipeline.process_batch(&texts)?,
+        OP_DOCUMENT => {
+            let mut sentences = Vec::new();
+            for text in texts {
+                sentences.extend(pipeline.process_document(text)?);
+            }
+            sentences
+        }
+        op => return Err(anyhow!("unknown operation {}", op)),
+    };
+    encode_sentences(&sentences)
+}
+
+fn handle(pipeline: &LtpPipeline, stream: UnixStream) -> Result<()> {
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let mut writer = BufWriter::new(stream);
+    while let Some(request) = receive(&mut reader)? {
+        let response = respond(pipeline, &request).or_else(|err| -> Result<_> {
+            let mut encoder = Encoder::default();
+            encoder.u8(STATUS_ERROR);
+            encoder.str(&err.to_string())?;
+            Ok(encoder)
+        })?;
+        response.send(&mut writer)?;
+    }
+    Ok(())
+}
+
+/// [`LtpDaemon`] 的客户端，一个连接上的请求依次处理
+pub struct LtpClient {
+    reader: BufReader<UnixStream>,
+    writer: BufWriter<UnixStream>,
+}
+
+impl LtpClient {
+    pub fn connect<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let stream = UnixStream::connect(path)?;
+        Ok(Self {
+            reader: BufReader::new(stream.try_clone()?),
+            writer: BufWriter::new(stream),
+        })
+    }
+
+    fn request<S: AsRef<str>>(&mut self, op: u8, texts: &[S]) -> Result<Vec<Sentence>> {
+        let mut encoder = Encoder::default();
+        encoder.u8(op);
+        encoder.u32(texts.len())?;
+        for text in texts {
+            encoder.str(text.as_ref())?;
+        }
+        encoder.send(&mut self.writer)?;
+        let response =
+            receive(&mut self.reader)?.ok_or_else(|| io::Error::from(ErrorKind::UnexpectedEof))?;
+        decode_sentences(&response)
+    }
+
+    pub fn process(&mut self, text: &str) -> Result<Sentence> {
+        self.request(OP_PROCESS, &[text])?
+            .pop()
+            .ok_or_else(|| anyhow!("empty response"))
+    }
+
+    /// 与 [`LtpPipeline::process_batch`] 相同，结果与输入一一对应
+    pub fn process_batch<S: AsRef<str>>(&mut self, texts: &[S]) -> Result<Vec<Sentence>> {
+        self.request(OP_PROCESS, texts)
+    }
+
+    /// 与 [`LtpPipeline::process_document`] 相同
+    pub fn process_document(&mut self, text: &str) -> Result<Vec<Sentence>> {
+        self.request(OP_DOCUMENT, &[text])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pipeline::{Annotation, PipelineStage, StageMetadata};
+
+    struct Whitespace;
+
+    impl PipelineStage for Whitespace {
+        fn metadata(&self) -> StageMetadata {
+            StageMetadata::new("whitespace")
+                .inputs(&[Annotation::Text])
+                .outputs(&[Annotation::Words])
+        }
+
+        fn process(&self, sentence: &mut Sentence) -> Result<()> {
+            if sentence.text.is_empty() {
+                return Err(anyhow!("empty text"));
+            }
+            sentence.words = sentence.text.split_whitespace().map(String::from).collect();
+            let labels = LabelSet::new(vec!["n".to_string(), "v".to_string()]);
+            let ids = (0..sentence.words.len()).map(|i| i % 2).collect::<Vec<_>>();
+            sentence.pos = Some(InternedLabels::from_indices(labels, &ids)?);
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_daemon_roundtrip() -> Result<()> {
+        let path = std::env::temp_dir().join(format!("ltp-daemon-{}.sock", std::process::id()));
+        let _ = fs::remove_file(&path);
+        let listener = UnixListener::bind(&path)?;
+        let pipeline = LtpPipeline::new().add_stage(Whitespace);
+        let expected = pipeline.process_batch(&["他 叫 汤姆", "去 拿"])?;
+        thread::spawn(move || LtpDaemon::new(pipeline).serve_listener(listener));
+
+        let mut client = LtpClient::connect(&path)?;
+        assert_eq!(client.process_batch(&["他 叫 汤姆", "去 拿"])?, expected);
+        assert!(client.process("").is_err());
+        assert_eq!(client.process("外衣")?.words, vec!["外衣"]);
+        fs::remove_file(&path)?;
+        Ok(())
+    }
+}
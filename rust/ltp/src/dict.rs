@@ -0,0 +1,316 @@
+//! 常见中文词典格式的导入：每行 `词[ 词频][ 词性]`，分隔符为空格或制表符
+//!
+//! jieba 的格式为 `词 词频 词性`，ICTCLAS（NLPIR）的用户词典为 `词 词性`，
+//! 也有 `词\t词性\t词频` 的变体，第二、三列中能解析为整数的一列视为词频。
+//!
+//! 流水线中的词典可以用 [`Reloadable`] 在运行中替换，不需要重新加载模型或重启服务。
+use crate::hook::Hook;
+#[cfg(feature = "fs")]
+use crate::perceptron::CompressedReader;
+use anyhow::{anyhow, Result};
+use std::cmp::Reverse;
+use std::collections::HashMap;
+use std::fmt::{Debug, Formatter};
+use std::io::{BufRead, BufReader, Read};
+use std::ops::Range;
+#[cfg(feature = "fs")]
+use std::path::Path;
+use std::sync::{Arc, RwLock};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DictEntry {
+    pub word: String,
+    pub freq: Option<usize>,
+    pub pos: Option<String>,
+}
+
+impl DictEntry {
+    /// 空行与 `#` 开头的注释返回 `None`
+    pub fn parse(line: &str) -> Result<Option<Self>> {
+        let line = line.trim_start_matches('\u{feff}').trim();
+        if line.is_empty() || line.starts_with('#') {
+            return Ok(None);
+        }
+        let mut fields = line.split_whitespace();
+        let word = fields.next().unwrap_or_default().to_string();
+        let mut entry = DictEntry {
+            word,
+            freq: None,
+            pos: None,
+        };
+        for field in fields {
+            match field.parse::<usize>() {
+                Ok(freq) if entry.freq.is_none() => entry.freq = Some(freq),
+                _ if entry.pos.is_none() => entry.pos = Some(field.to_string()),
+                _ => return Err(anyhow!("too many fields in `{}`", line)),
+            }
+        }
+        Ok(Some(entry))
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Dictionary {
+    pub entries: Vec<DictEntry>,
+}
+
+impl Dictionary {
+    pub fn from_reader<R: Read>(reader: R) -> Result<Self> {
+        let mut entries = Vec::new();
+        for (index, line) in BufReader::new(reader).lines().enumerate() {
+            let entry = DictEntry::parse(&line?)
+                .map_err(|error| anyhow!("line {}: {}", index + 1, error))?;
+            entries.extend(entry);
+        }
+        Ok(Dictionary { entries })
+    }
+
+    /// 可以是 gzip、zstd 压缩的
+    #[cfg(feature = "fs")]
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self> {
+        Self::from_reader(CompressedReader::open(path)?)
+    }
+
+    /// 加入用户词典，没有词频的词按 1 计
+    pub fn extend_hook(&self, hook: &mut Hook) {
+        for entry in &self.entries {
+            hook.add_word(&entry.word, entry.freq);
+        }
+    }
+
+    pub fn hook(&self) -> Hook {
+        let mut hook = Hook::new();
+        self.extend_hook(&mut hook);
+        hook
+    }
+
+    /// 带词性的词条构成词性词典，没有词性的词条忽略
+    pub fn lexicon(&self) -> PosLexicon {
+        let mut lexicon = PosLexicon::default();
+        for entry in &self.entries {
+            if let Some(pos) = &entry.pos {
+                lexicon.insert(&entry.word, pos, entry.freq.unwrap_or(1));
+            }
+        }
+        lexicon
+    }
+
+    /// 带类型的词条构成实体词典，第二列为实体类型，如 `北京大学 Ni`
+    pub fn gazetteer(&self) -> Gazetteer {
+        let mut gazetteer = Gazetteer::new();
+        for entry in &self.entries {
+            if let Some(kind) = &entry.pos {
+                gazetteer.insert(&entry.word, kind);
+            }
+        }
+        gazetteer
+    }
+}
+
+/// 词 -> 可能的词性，按词频从高到低排列
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PosLexicon {
+    tags: HashMap<String, Vec<(String, usize)>>,
+}
+
+impl PosLexicon {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.tags.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tags.is_empty()
+    }
+
+    /// 同一个词的同一个词性重复出现时词频累加
+    pub fn insert(&mut self, word: &str, pos: &str, freq: usize) {
+        let tags = self.tags.entry(word.to_string()).or_default();
+        match tags.iter_mut().find(|(tag, _)| tag == pos) {
+            Some((_, count)) => *count += freq,
+            None => tags.push((pos.to_string(), freq)),
+        }
+        tags.sort_by_key(|&(_, freq)| Reverse(freq));
+    }
+
+    pub fn tags(&self, word: &str) -> Option<impl Iterator<Item = &str>> {
+        self.tags
+            .get(word)
+            .map(|tags| tags.iter().map(|(tag, _)| tag.as_str()))
+    }
+
+    /// 词典中的词若预测的词性不在词典给出的词性中，改为词典中词频最高的词性
+    pub fn apply<'a>(&'a self, words: &[&str], pos: &mut [&'a str]) {
+        for (word, tag) in words.iter().zip(pos.iter_mut()) {
+            if let Some(tags) = self.tags.get(*word) {
+                if !tags.iter().any(|(t, _)| t == tag) {
+                    if let Some((best, _)) = tags.first() {
+                        *tag = best;
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// 实体文本 -> 实体类型，在分词结果上从左到右取最长的相邻词匹配
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Gazetteer {
+    entities: HashMap<String, String>,
+    /// 一个实体最多包含的词数
+    pub max_words: usize,
+}
+
+impl Default for Gazetteer {
+    fn default() -> Self {
+        Self {
+            entities: HashMap::new(),
+            max_words: 8,
+        }
+    }
+}
+
+impl Gazetteer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn max_words(mut self, max_words: usize) -> Self {
+        self.max_words = max_words.max(1);
+        self
+    }
+
+    pub fn len(&self) -> usize {
+        self.entities.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entities.is_empty()
+    }
+
+    /// 同一个实体重复出现时以最后一次的类型为准
+    pub fn insert(&mut self, entity: &str, kind: &str) {
+        self.entities.insert(entity.to_string(), kind.to_string());
+    }
+
+    pub fn get(&self, entity: &str) -> Option<&str> {
+        self.entities.get(entity).map(String::as_str)
+    }
+
+    /// 词典中的实体在 `words` 中的下标区间与类型，互不重叠
+    pub fn find<S: AsRef<str>>(&self, words: &[S]) -> Vec<(Range<usize>, &str)> {
+        let mut found = Vec::new();
+        let mut start = 0;
+        while start < words.len() {
+            let longest = (start + self.max_words).min(words.len());
+            let entity = (start + 1..=longest).rev().find_map(|end| {
+                let text: String = words[start..end].iter().map(AsRef::as_ref).collect();
+                Some((end, self.get(&text)?))
+            });
+            match entity {
+                Some((end, kind)) => {
+                    found.push((start..end, kind));
+                    start = end;
+                }
+                None => start += 1,
+            }
+        }
+        found
+    }
+}
+
+/// 可在运行中整体替换的词典等数据，克隆得到的句柄共享同一份数据。
+///
+/// 读取方拿到的是替换前或替换后的完整数据，不会看到替换了一半的结果；
+/// 替换时正在处理的句子继续使用旧的数据。
+pub struct Reloadable<T>(Arc<RwLock<Arc<T>>>);
+
+impl<T> Reloadable<T> {
+    pub fn new(value: T) -> Self {
+        Self(Arc::new(RwLock::new(Arc::new(value))))
+    }
+
+    pub fn get(&self) -> Arc<T> {
+        match self.0.read() {
+            Ok(value) => value.clone(),
+            Err(poisoned) => poisoned.into_inner().clone(),
+        }
+    }
+
+    /// 替换为新的数据，返回旧的数据
+    pub fn replace(&self, value: T) -> Arc<T> {
+        let mut slot = match self.0.write() {
+            Ok(slot) => slot,
+            Err(poisoned) => poisoned.into_inner(),
+        };
+        std::mem::replace(&mut *slot, Arc::new(value))
+    }
+}
+
+impl<T> Clone for Reloadable<T> {
+    fn clone(&self) -> Self {
+        Self(self.0.clone())
+    }
+}
+
+impl<T: Default> Default for Reloadable<T> {
+    fn default() -> Self {
+        Self::new(T::default())
+    }
+}
+
+impl<T: Debug> Debug for Reloadable<T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("Reloadable").field(&self.get()).finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_dictionary() -> Result<()> {
+        let text = "\u{feff}# jieba\n汤姆 5 nh\n外衣 n 3\n姆去拿\n\n汤姆 2 v\n";
+        let dict = Dictionary::from_reader(text.as_bytes())?;
+        assert_eq!(dict.entries.len(), 4);
+        assert_eq!(
+            dict.entries[1],
+            DictEntry {
+                word: "外衣".into(),
+                freq: Some(3),
+                pos: Some("n".into())
+            }
+        );
+
+        // 重复的词以最后一次的词频为准
+        assert_eq!(dict.hook().total(), 6);
+
+        let lexicon = dict.lexicon();
+        assert_eq!(lexicon.len(), 2);
+        assert_eq!(
+            lexicon.tags("汤姆").unwrap().collect::<Vec<_>>(),
+            ["nh", "v"]
+        );
+        let mut pos = vec!["r", "v", "v"];
+        lexicon.apply(&["他", "外衣", "汤姆"], &mut pos);
+        assert_eq!(pos, ["r", "n", "v"]);
+
+        assert!(Dictionary::from_reader("汤姆 5 nh x".as_bytes()).is_err());
+
+        let gazetteer = Dictionary::from_reader("北京大学 Ni\n北京 Ns\n".as_bytes())?.gazetteer();
+        let words = ["他", "在", "北京", "大学", "和", "北京"];
+        let found = gazetteer.find(&words);
+        assert_eq!(found, [(2..4, "Ni"), (5..6, "Ns")]);
+
+        let reloadable = Reloadable::new(lexicon);
+        let handle = reloadable.clone();
+        let old = handle.replace(PosLexicon::new());
+        assert_eq!(old.len(), 2);
+        assert!(reloadable.get().is_empty());
+        Ok(())
+    }
+}
@@ -0,0 +1,86 @@
+//! 输入有误时返回的错误，公开接口返回 `anyhow::Result`，可以用 `downcast_ref::<LtpError>()` 区分
+use thiserror::Error;
+
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum LtpError {
+    /// 标签不在模型的标签表中
+    #[error("unknown label `{0}`")]
+    UnknownLabel(String),
+    /// 第几个词为空
+    #[error("empty token at position {0}")]
+    EmptyToken(usize),
+    /// 标注语料中的词缺少 `/` 分隔的标签
+    #[error("malformed token `{0}`, expected {1}")]
+    MalformedToken(String, &'static str),
+    /// 逐词对齐的输入长度不同，如实体识别的词与词性
+    #[error("length mismatch: {0} vs {1}")]
+    LengthMismatch(usize, usize),
+    /// 训练样本中的标签下标超出模型的标签数
+    #[error("label id {0} out of range, model has {1} labels")]
+    LabelOutOfRange(usize, usize),
+    /// 标签下标超出 [`LabelId`](crate::LabelId) 的范围，最多支持 65536 个标签
+    #[error("label id {0} does not fit in a LabelId, at most 65536 labels are supported")]
+    TooManyLabels(usize),
+    /// 传入的标签表与模型的标签表不同
+    #[error("the label set does not match the labels of this model")]
+    LabelSetMismatch,
+}
+
+#[cfg(all(test, feature = "cws", feature = "pos", feature = "ner"))]
+mod tests {
+    use super::*;
+    use crate::perceptron::Definition;
+    use crate::{NERDefinition, POSDefinition};
+
+    fn error<T: std::fmt::Debug>(result: anyhow::Result<T>) -> LtpError {
+        let error = result.unwrap_err();
+        error.downcast_ref::<LtpError>().cloned().unwrap()
+    }
+
+    #[test]
+    fn test_errors() {
+        let pos = POSDefinition::new(vec!["r".into(), "v".into()]);
+        assert_eq!(error(pos.label_to("n")), LtpError::UnknownLabel("n".into()));
+        assert_eq!(
+            error(pos.parse_words_features(&["他", ""])),
+            LtpError::EmptyToken(1)
+        );
+        assert_eq!(
+            error(pos.parse_gold_features("他/r 叫/n".as_bytes())),
+            LtpError::UnknownLabel("n".into())
+        );
+        assert_eq!(
+            error(pos.parse_gold_features("他/r 叫".as_bytes())),
+            LtpError::MalformedToken("叫".into(), "word/pos")
+        );
+
+        let ner = NERDefinition::new(vec!["O".into()]);
+        assert_eq!(
+            error(ner.parse_words_features(&["他", "叫"], &["r"])),
+            LtpError::LengthMismatch(2, 1)
+        );
+    }
+
+    #[test]
+    fn test_blank_tokens() {
+        use crate::CWSDefinition;
+
+        let pos = POSDefinition::new(vec!["r".into()]);
+        assert_eq!(
+            error(pos.parse_words_features(&["他", " ", ""])),
+            LtpError::EmptyToken(2)
+        );
+        let blank = pos.parse_words_features(&["他", "\u{3000}"]).unwrap();
+        let tab = pos.parse_words_features(&["他", "\t"]).unwrap();
+        assert_eq!(blank, tab);
+
+        let cws = CWSDefinition::default();
+        let (_, features) = cws.parse_char_features("").unwrap();
+        assert!(features.is_empty());
+        let sentence = "他 叫\t汤姆\n";
+        let (index, features) = cws.parse_char_features(sentence).unwrap();
+        assert_eq!(features.len(), 4);
+        assert_eq!(cws.word(sentence, &index, 0, 0), "他");
+        assert_eq!(cws.word(sentence, &index, 1, 3), "叫\t汤姆");
+    }
+}
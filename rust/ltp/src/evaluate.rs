@@ -0,0 +1,264 @@
+//! 两个模型之间的比较：配对自助法（paired bootstrap）显著性检验与预测差异统计
+use crate::perceptron::{
+    Definition, Perceptron, Sample, TraitFeature, TraitParameter, TraitParameterStorage,
+};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
+#[cfg(feature = "serialization")]
+use serde::{Deserialize, Serialize};
+use std::cmp::Reverse;
+use std::collections::BTreeMap;
+use std::fmt::{Display, Formatter};
+use std::iter::zip;
+
+/// 每个句子的 `(正确, 预测, 标注)` 数
+type Counts = (usize, usize, usize);
+
+fn f1((correct, predicted, gold): Counts) -> f64 {
+    if correct == 0 {
+        return 0.0;
+    }
+    let precision = correct as f64 / predicted as f64;
+    let recall = correct as f64 / gold as f64;
+    2.0 * precision * recall / (precision + recall)
+}
+
+fn sum<'a, I: IntoIterator<Item = &'a Counts>>(counts: I) -> Counts {
+    counts
+        .into_iter()
+        .fold((0, 0, 0), |(a, b, c), &(x, y, z)| (a + x, b + y, c + z))
+}
+
+#[cfg_attr(feature = "serialization", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Significance {
+    pub f1_a: f64,
+    pub f1_b: f64,
+    /// 重采样次数
+    pub samples: usize,
+    /// 重采样中，在全体上较好的模型没有胜出的比例
+    pub p_value: f64,
+}
+
+impl Display for Significance {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "f1: {:.4} vs {:.4} (delta {:+.4}), p-value: {:.4} ({} samples)",
+            self.f1_a,
+            self.f1_b,
+            self.f1_b - self.f1_a,
+            self.p_value,
+            self.samples
+        )
+    }
+}
+
+/// 按句子有放回地重采样，比较两个模型在每个样本上的 F1（Koehn, 2004）
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Bootstrap {
+    pub samples: usize,
+    pub seed: u64,
+}
+
+impl Default for Bootstrap {
+    fn default() -> Self {
+        Bootstrap {
+            samples: 1000,
+            seed: 0,
+        }
+    }
+}
+
+macro_rules! impl_set_param {
+    ($name:ident, $type:ty) => {
+        pub fn $name(mut self, $name: $type) -> Self {
+            self.$name = $name;
+            self
+        }
+    };
+}
+
+impl Bootstrap {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    impl_set_param!(samples, usize);
+    impl_set_param!(seed, u64);
+
+    /// 两个模型在同一批句子上的计数，下标一一对应
+    pub fn compare(&self, a: &[Counts], b: &[Counts]) -> Significance {
+        assert_eq!(a.len(), b.len(), "counts must be paired");
+        let (f1_a, f1_b) = (f1(sum(a)), f1(sum(b)));
+        let better_is_b = f1_b >= f1_a;
+        let mut rng = StdRng::seed_from_u64(self.seed);
+        let mut losses = 0;
+        for _ in 0..self.samples {
+            let (mut sample_a, mut sample_b) = ((0, 0, 0), (0, 0, 0));
+            for _ in 0..a.len() {
+                let i = rng.gen_range(0..a.len());
+                sample_a = sum([&sample_a, &a[i]]);
+                sample_b = sum([&sample_b, &b[i]]);
+            }
+            let (sa, sb) = (f1(sample_a), f1(sample_b));
+            let wins = if better_is_b { sb > sa } else { sa > sb };
+            if !wins {
+                losses += 1;
+            }
+        }
+        Significance {
+            f1_a,
+            f1_b,
+            samples: self.samples,
+            p_value: losses as f64 / self.samples.max(1) as f64,
+        }
+    }
+
+    pub fn significance<Define, FA, SA, FB, SB, Param>(
+        &self,
+        a: &Perceptron<Define, FA, SA, Param>,
+        b: &Perceptron<Define, FB, SB, Param>,
+        test: &[Sample],
+    ) -> Significance
+    where
+        Define: Definition + Sync,
+        FA: TraitFeature + Sync,
+        FB: TraitFeature + Sync,
+        SA: TraitParameterStorage<Param> + Sync,
+        SB: TraitParameterStorage<Param> + Sync,
+        Param: TraitParameter,
+    {
+        #[cfg(feature = "parallel")]
+        let iter = test.par_iter();
+        #[cfg(not(feature = "parallel"))]
+        let iter = test.iter();
+        let (counts_a, counts_b): (Vec<_>, Vec<_>) = iter
+            .map(|Sample { features, labels }| {
+                let counts_a = a.evaluate(features, labels);
+                (counts_a, b.evaluate(features, labels))
+            })
+            .unzip();
+        self.compare(&counts_a, &counts_b)
+    }
+}
+
+/// 以默认参数（1000 次重采样）检验 `b` 与 `a` 在 `test` 上的差异是否显著
+pub fn significance<Define, FA, SA, FB, SB, Param>(
+    a: &Perceptron<Define, FA, SA, Param>,
+    b: &Perceptron<Define, FB, SB, Param>,
+    test: &[Sample],
+) -> Significance
+where
+    Define: Definition + Sync,
+    FA: TraitFeature + Sync,
+    FB: TraitFeature + Sync,
+    SA: TraitParameterStorage<Param> + Sync,
+    SB: TraitParameterStorage<Param> + Sync,
+    Param: TraitParameter,
+{
+    Bootstrap::default().significance(a, b, test)
+}
+
+/// 两个模型在同一批句子上的预测差异，用于替换线上模型前检查行为变化
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DiffSummary {
+    pub sentences: usize,
+    /// 预测不同的句子数
+    pub changed: usize,
+    pub tokens: usize,
+    pub changed_tokens: usize,
+    /// `(旧标签, 新标签)` 出现的次数
+    pub transitions: BTreeMap<(String, String), usize>,
+}
+
+impl DiffSummary {
+    /// 累加一个句子逐词对齐的两组预测，返回不同的词数
+    pub fn add<S: AsRef<str>>(&mut self, a: &[S], b: &[S]) -> usize {
+        let mut changed = 0;
+        for (a, b) in zip(a, b) {
+            let (a, b) = (a.as_ref(), b.as_ref());
+            if a != b {
+                changed += 1;
+                let key = (a.to_string(), b.to_string());
+                *self.transitions.entry(key).or_default() += 1;
+            }
+        }
+        self.sentences += 1;
+        self.tokens += a.len();
+        self.changed_tokens += changed;
+        if changed > 0 {
+            self.changed += 1;
+        }
+        changed
+    }
+
+    /// 按次数从多到少排列的标签变化
+    pub fn top(&self, n: usize) -> Vec<(&str, &str, usize)> {
+        let mut transitions: Vec<_> = self
+            .transitions
+            .iter()
+            .map(|((a, b), &count)| (a.as_str(), b.as_str(), count))
+            .collect();
+        transitions.sort_by_key(|&(_, _, count)| Reverse(count));
+        transitions.truncate(n);
+        transitions
+    }
+}
+
+impl Display for DiffSummary {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let ratio = |a: usize, b: usize| a as f64 * 100.0 / b.max(1) as f64;
+        writeln!(
+            f,
+            "changed sentences: {}/{} ({:.2}%)",
+            self.changed,
+            self.sentences,
+            ratio(self.changed, self.sentences)
+        )?;
+        write!(
+            f,
+            "changed tokens: {}/{} ({:.2}%)",
+            self.changed_tokens,
+            self.tokens,
+            ratio(self.changed_tokens, self.tokens)
+        )?;
+        for (a, b, count) in self.top(10) {
+            write!(f, "\n{a} -> {b}: {count}")?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bootstrap() {
+        let a: Vec<Counts> = (0..50).map(|_| (8, 10, 10)).collect();
+        let b: Vec<Counts> = (0..50).map(|i| (9 + i % 2, 10, 10)).collect();
+        let bootstrap = Bootstrap::new().samples(200).seed(1);
+        let result = bootstrap.compare(&a, &b);
+        assert!((result.f1_a - 0.8).abs() < 1e-9);
+        assert!(result.p_value < 0.01);
+        assert_eq!(result, bootstrap.compare(&a, &b));
+
+        // 相同的模型不显著
+        assert_eq!(bootstrap.compare(&a, &a).p_value, 1.0);
+    }
+
+    #[test]
+    fn test_diff() {
+        let mut diff = DiffSummary::default();
+        assert_eq!(diff.add(&["n", "v", "n"], &["n", "v", "n"]), 0);
+        assert_eq!(diff.add(&["n", "v", "n"], &["nh", "v", "nh"]), 2);
+        assert_eq!(diff.add(&["v", "u"], &["p", "u"]), 1);
+        assert_eq!((diff.changed, diff.sentences), (2, 3));
+        assert_eq!((diff.changed_tokens, diff.tokens), (3, 8));
+        assert_eq!(diff.top(1), [("n", "nh", 2)]);
+        assert!(diff.to_string().contains("changed sentences: 2/3"));
+    }
+}
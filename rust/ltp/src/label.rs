@@ -0,0 +1,207 @@
+use crate::error::LtpError;
+#[cfg(feature = "serialization")]
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt::{Debug, Formatter};
+use std::ops::Index;
+use std::sync::Arc;
+
+/// 标签在 [`LabelSet`] 中的下标，只占两个字节
+#[cfg_attr(feature = "serialization", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct LabelId(u16);
+
+impl TryFrom<usize> for LabelId {
+    type Error = LtpError;
+
+    /// 超过 65536 个标签时返回 [`LtpError::TooManyLabels`]
+    fn try_from(index: usize) -> Result<Self, Self::Error> {
+        u16::try_from(index)
+            .map(LabelId)
+            .map_err(|_| LtpError::TooManyLabels(index))
+    }
+}
+
+impl LabelId {
+    pub fn index(&self) -> usize {
+        self.0 as usize
+    }
+}
+
+/// 模型的标签表，由引用它的所有输出共享，克隆只增加引用计数
+#[derive(Clone, Default)]
+pub struct LabelSet {
+    labels: Arc<[String]>,
+    index: Arc<HashMap<String, LabelId>>,
+}
+
+impl LabelSet {
+    /// 超出 [`LabelId`] 范围的标签没有下标，[`id`](Self::id) 返回 `None`
+    pub fn new(labels: Vec<String>) -> Self {
+        let index = labels
+            .iter()
+            .enumerate()
+            .map_while(|(i, label)| Some((label.clone(), LabelId::try_from(i).ok()?)))
+            .collect();
+        LabelSet {
+            labels: labels.into(),
+            index: Arc::new(index),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.labels.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.labels.is_empty()
+    }
+
+    pub fn get(&self, id: LabelId) -> Option<&str> {
+        self.labels.get(id.index()).map(|s| s.as_str())
+    }
+
+    pub fn id(&self, label: &str) -> Option<LabelId> {
+        self.index.get(label).copied()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &str> {
+        self.labels.iter().map(|s| s.as_str())
+    }
+
+    /// 标签相同且顺序相同的两个标签表可以互换
+    pub fn same_as(&self, other: &LabelSet) -> bool {
+        Arc::ptr_eq(&self.labels, &other.labels) || self.labels == other.labels
+    }
+}
+
+impl Index<LabelId> for LabelSet {
+    type Output = str;
+
+    fn index(&self, id: LabelId) -> &Self::Output {
+        &self.labels[id.index()]
+    }
+}
+
+impl Debug for LabelSet {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_list().entries(self.labels.iter()).finish()
+    }
+}
+
+impl PartialEq for LabelSet {
+    fn eq(&self, other: &Self) -> bool {
+        self.same_as(other)
+    }
+}
+
+impl Eq for LabelSet {}
+
+impl From<Vec<String>> for LabelSet {
+    fn from(labels: Vec<String>) -> Self {
+        LabelSet::new(labels)
+    }
+}
+
+/// 不依赖模型生命周期的一串标签，如一个句子的词性
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct InternedLabels {
+    labels: LabelSet,
+    ids: Vec<LabelId>,
+}
+
+impl InternedLabels {
+    pub fn new(labels: LabelSet, ids: Vec<LabelId>) -> Self {
+        InternedLabels { labels, ids }
+    }
+
+    /// 下标超出标签表时返回 [`LtpError::LabelOutOfRange`]
+    pub fn from_indices(labels: LabelSet, indices: &[usize]) -> Result<Self, LtpError> {
+        let ids = indices
+            .iter()
+            .map(|&i| match i < labels.len() {
+                true => LabelId::try_from(i),
+                false => Err(LtpError::LabelOutOfRange(i, labels.len())),
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(InternedLabels { labels, ids })
+    }
+
+    pub fn label_set(&self) -> &LabelSet {
+        &self.labels
+    }
+
+    pub fn ids(&self) -> &[LabelId] {
+        &self.ids
+    }
+
+    pub fn len(&self) -> usize {
+        self.ids.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.ids.is_empty()
+    }
+
+    pub fn get(&self, index: usize) -> Option<&str> {
+        self.ids.get(index).and_then(|&id| self.labels.get(id))
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &str> {
+        self.ids.iter().map(move |&id| &self.labels[id])
+    }
+
+    pub fn to_vec(&self) -> Vec<&str> {
+        self.iter().collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_send_static<T: Send + Sync + 'static>(_: &T) {}
+
+    #[test]
+    fn test_label_set() {
+        let labels = LabelSet::new(vec!["n".to_string(), "v".to_string(), "wp".to_string()]);
+        assert_eq!(labels.len(), 3);
+        assert_eq!(labels.id("v"), Some(LabelId::try_from(1).unwrap()));
+        assert_eq!(labels.id("x"), None);
+        assert_eq!(&labels[LabelId::try_from(2).unwrap()], "wp");
+        let error = LabelId::try_from(70000);
+        assert_eq!(error, Err(LtpError::TooManyLabels(70000)));
+
+        let tags = InternedLabels::from_indices(labels.clone(), &[0, 1, 2, 1]).unwrap();
+        assert_send_static(&tags);
+        assert_eq!(tags.to_vec(), vec!["n", "v", "wp", "v"]);
+        assert_eq!(tags.get(3), Some("v"));
+        assert!(tags.label_set().same_as(&labels));
+        assert_eq!(
+            InternedLabels::from_indices(labels, &[0, 3]),
+            Err(LtpError::LabelOutOfRange(3, 3))
+        );
+    }
+
+    #[cfg(feature = "pos")]
+    #[test]
+    fn test_predict_interned() -> anyhow::Result<()> {
+        use crate::perceptron::Perceptron;
+        use crate::POSDefinition;
+        use std::collections::HashMap;
+
+        let define = POSDefinition::new(vec!["n".into(), "v".into()]);
+        let model: Perceptron<_, HashMap<String, usize>, Vec<f64>, f64> =
+            Perceptron::new_with_parameters(define, HashMap::new(), Vec::new());
+        let tags = model.predict_interned(&["他"], &model.label_set())?;
+        assert_eq!(tags.to_vec(), ["n"]);
+
+        // 其它模型的标签表，即使标签数相同也返回错误
+        for labels in [vec!["v", "n"], vec!["n"]] {
+            let labels = LabelSet::new(labels.into_iter().map(String::from).collect());
+            let error = model.predict_interned(&["他"], &labels).unwrap_err();
+            assert_eq!(error.downcast_ref(), Some(&LtpError::LabelSetMismatch));
+        }
+        Ok(())
+    }
+}
@@ -1,24 +1,80 @@
+pub mod align;
+pub mod clean;
+pub mod convert;
+pub mod corpus;
+#[cfg(all(unix, feature = "daemon"))]
+pub mod daemon;
+pub mod dict;
 pub mod eisner;
 pub mod entities;
+pub mod error;
+pub mod evaluate;
 pub mod hook;
+pub mod label;
 pub mod perceptron;
+pub mod pipeline;
+pub mod prelude;
+pub mod quantity;
+#[cfg(feature = "remote")]
+pub mod resolver;
+pub mod script;
 pub mod stnsplit;
+pub mod stopwords;
+pub mod tfidf;
+#[cfg(feature = "tokenizers")]
+pub mod tokenizer;
 pub mod viterbi;
 
-pub use perceptron::{
-    Algorithm, CWSDefinition, NERDefinition, POSDefinition, PaMode, Perceptron, Trainer,
-};
+#[cfg(feature = "cws")]
+pub use perceptron::CWSDefinition;
+#[cfg(feature = "ner")]
+pub use perceptron::NERDefinition;
+#[cfg(feature = "pos")]
+pub use perceptron::POSDefinition;
+#[cfg(all(feature = "serialization", feature = "cws"))]
+pub use perceptron::SerdeCWSModel;
+#[cfg(all(feature = "serialization", feature = "ner"))]
+pub use perceptron::SerdeNERModel;
+#[cfg(all(feature = "serialization", feature = "pos"))]
+pub use perceptron::SerdePOSModel;
+#[cfg(feature = "zero-copy")]
+pub use perceptron::{load_zero_copy, ZeroCopyDefinition, ZeroCopyModel, ZeroCopyPerceptron};
+pub use perceptron::{Algorithm, Compression, PaMode, Perceptron, SequenceDefinition, Trainer};
 #[cfg(feature = "serialization")]
-pub use perceptron::{Codec, Format, ModelSerde, Reader, SerdeModel, SerdeCWSModel, SerdePOSModel, SerdeNERModel};
+pub use perceptron::{Codec, Format, ModelSerde, Reader, SerdeModel};
+#[cfg(all(
+    feature = "serialization",
+    any(feature = "cws", feature = "pos", feature = "ner")
+))]
+pub use perceptron::{DynInput, DynModel, DynOutput};
+#[cfg(feature = "quantization")]
+pub use perceptron::{Quantization, QuantizedModel, QuantizedParameters};
 
-#[cfg(feature = "serialization")]
+#[cfg(all(feature = "serialization", feature = "cws"))]
 pub type CWSModel = SerdeCWSModel;
-#[cfg(feature = "serialization")]
+#[cfg(all(feature = "serialization", feature = "pos"))]
 pub type POSModel = SerdePOSModel;
-#[cfg(feature = "serialization")]
+#[cfg(all(feature = "serialization", feature = "ner"))]
 pub type NERModel = SerdeNERModel;
 
+pub use align::Alignment;
+pub use clean::{OffsetMap, TextCleaner};
+pub use convert::Converter;
+#[cfg(all(unix, feature = "daemon"))]
+pub use daemon::{LtpClient, LtpDaemon};
 pub use eisner::eisner;
 pub use entities::{drop_get_entities, get_entities};
-pub use stnsplit::{stn_split, stn_split_with_options, SplitOptions};
+pub use error::LtpError;
+pub use label::{InternedLabels, LabelId, LabelSet};
+pub use pipeline::{LtpPipeline, Paragraph, PipelineStage, Sentence, StageMetadata};
+pub use quantity::{parse_number, parse_quantity, Quantity, QuantityRecognizer, QuantityValue};
+#[cfg(feature = "remote")]
+pub use resolver::ModelResolver;
+pub use script::{NgramModel, NonChinese, Script, ScriptGuard, ScriptStats};
+pub use stnsplit::{
+    paragraph_spans, stn_split, stn_split_spans, stn_split_with_options, ParagraphBreak,
+    SplitOptions,
+};
+pub use stopwords::Stopwords;
+pub use tfidf::{CountMinSketch, TermCounts, TermStats};
 pub use viterbi::viterbi_decode_postprocessing;
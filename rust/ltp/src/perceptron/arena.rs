@@ -0,0 +1,210 @@
+#[cfg(feature = "serialization")]
+use serde::{Deserialize, Serialize};
+use std::ops::{Index, Range};
+
+/// 一句话的逐词（字）特征，所有特征平铺在同一块内存中，每个词用 `(start, len)` 记录自己的区间
+#[cfg_attr(feature = "serialization", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FeatureArena<T> {
+    data: Vec<T>,
+    spans: Vec<(usize, usize)>,
+    // 当前未结束的词从哪里开始
+    open: usize,
+}
+
+impl<T> Default for FeatureArena<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> FeatureArena<T> {
+    pub fn new() -> Self {
+        Self {
+            data: Vec::new(),
+            spans: Vec::new(),
+            open: 0,
+        }
+    }
+
+    pub fn with_capacity(tokens: usize, features: usize) -> Self {
+        Self {
+            data: Vec::with_capacity(features),
+            spans: Vec::with_capacity(tokens),
+            open: 0,
+        }
+    }
+
+    /// 向当前词追加一个特征
+    pub fn push(&mut self, feature: T) {
+        self.data.push(feature);
+    }
+
+    /// 结束当前词，此前追加的特征归属于它
+    pub fn end_token(&mut self) {
+        let len = self.data.len() - self.open;
+        self.spans.push((self.open, len));
+        self.open = self.data.len();
+    }
+
+    pub fn push_token<I: IntoIterator<Item = T>>(&mut self, features: I) {
+        self.data.extend(features);
+        self.end_token();
+    }
+
+    pub fn clear(&mut self) {
+        self.data.clear();
+        self.spans.clear();
+        self.open = 0;
+    }
+
+    /// 词数
+    pub fn len(&self) -> usize {
+        self.spans.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.spans.is_empty()
+    }
+
+    /// 所有词的特征总数
+    pub fn feature_len(&self) -> usize {
+        self.data.len()
+    }
+
+    pub fn span(&self, index: usize) -> Range<usize> {
+        let (start, len) = self.spans[index];
+        start..start + len
+    }
+
+    pub fn get(&self, index: usize) -> Option<&[T]> {
+        self.spans
+            .get(index)
+            .map(|&(start, len)| &self.data[start..start + len])
+    }
+
+    pub fn as_flat(&self) -> &[T] {
+        &self.data
+    }
+
+    pub fn iter(&self) -> Tokens<'_, T> {
+        Tokens {
+            data: &self.data,
+            spans: self.spans.iter(),
+        }
+    }
+
+    /// 逐个特征转换，词的划分保持不变
+    pub fn map<U, F: FnMut(&T) -> U>(&self, f: F) -> FeatureArena<U> {
+        FeatureArena {
+            data: self.data.iter().map(f).collect(),
+            spans: self.spans.clone(),
+            open: self.open,
+        }
+    }
+
+    /// 逐个特征转换并丢弃 `None`，常用于过滤掉不在特征字典中的特征
+    pub fn filter_map<U, F: FnMut(&T) -> Option<U>>(&self, mut f: F) -> FeatureArena<U> {
+        let mut arena = FeatureArena::with_capacity(self.len(), self.feature_len());
+        for token in self.iter() {
+            arena.data.extend(token.iter().filter_map(&mut f));
+            arena.end_token();
+        }
+        arena
+    }
+
+    pub fn to_nested(&self) -> Vec<Vec<T>>
+    where
+        T: Clone,
+    {
+        self.iter().map(|token| token.to_vec()).collect()
+    }
+}
+
+impl<T> Index<usize> for FeatureArena<T> {
+    type Output = [T];
+
+    fn index(&self, index: usize) -> &Self::Output {
+        &self.data[self.span(index)]
+    }
+}
+
+impl<'a, T> IntoIterator for &'a FeatureArena<T> {
+    type Item = &'a [T];
+    type IntoIter = Tokens<'a, T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+/// 逐词遍历 [`FeatureArena`]
+#[derive(Debug, Clone)]
+pub struct Tokens<'a, T> {
+    data: &'a [T],
+    spans: std::slice::Iter<'a, (usize, usize)>,
+}
+
+impl<'a, T> Iterator for Tokens<'a, T> {
+    type Item = &'a [T];
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let &(start, len) = self.spans.next()?;
+        Some(&self.data[start..start + len])
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.spans.size_hint()
+    }
+}
+
+impl<T> DoubleEndedIterator for Tokens<'_, T> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        let &(start, len) = self.spans.next_back()?;
+        Some(&self.data[start..start + len])
+    }
+}
+
+impl<T> ExactSizeIterator for Tokens<'_, T> {}
+
+impl<T> From<Vec<Vec<T>>> for FeatureArena<T> {
+    fn from(nested: Vec<Vec<T>>) -> Self {
+        nested.into_iter().collect()
+    }
+}
+
+impl<T, I: IntoIterator<Item = T>> FromIterator<I> for FeatureArena<T> {
+    fn from_iter<It: IntoIterator<Item = I>>(iter: It) -> Self {
+        let mut arena = FeatureArena::new();
+        for token in iter {
+            arena.push_token(token);
+        }
+        arena
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::FeatureArena;
+
+    #[test]
+    fn test_arena() {
+        let mut arena = FeatureArena::new();
+        arena.push("a");
+        arena.push("b");
+        arena.end_token();
+        arena.end_token();
+        arena.push_token(["c"]);
+
+        assert_eq!(arena.len(), 3);
+        assert_eq!(arena.feature_len(), 3);
+        assert_eq!(&arena[0], &["a", "b"]);
+        assert!(arena[1].is_empty());
+        assert_eq!(arena.get(2), Some(&["c"][..]));
+        assert_eq!(arena.get(3), None);
+
+        let lookup = arena.filter_map(|f| (*f != "b").then_some(f.len()));
+        assert_eq!(lookup.to_nested(), vec![vec![1], vec![], vec![1]]);
+        assert_eq!(FeatureArena::from(arena.to_nested()), arena);
+    }
+}
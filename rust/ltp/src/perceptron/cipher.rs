@@ -0,0 +1,136 @@
+//! 保存与加载模型时对序列化后的字节做变换，使随应用分发的模型文件不是明文
+//!
+//! 加密的模型文件为魔数 `LTPE` 加 [`ModelCipher::encrypt`] 的输出，解密后与普通的模型文件相同
+//! （可以是压缩的）。
+use anyhow::{anyhow, Result};
+use std::fmt::Debug;
+
+pub const ENCRYPTED_MAGIC: [u8; 4] = *b"LTPE";
+
+pub trait ModelCipher: Debug + Send + Sync {
+    fn encrypt(&self, plain: &[u8]) -> Result<Vec<u8>>;
+
+    /// 密钥不对或数据损坏时应返回错误，而不是返回错误的明文
+    fn decrypt(&self, encrypted: &[u8]) -> Result<Vec<u8>>;
+}
+
+/// 加上魔数
+pub fn encrypt_model(cipher: &dyn ModelCipher, plain: &[u8]) -> Result<Vec<u8>> {
+    let encrypted = cipher.encrypt(plain)?;
+    let mut bytes = Vec::with_capacity(ENCRYPTED_MAGIC.len() + encrypted.len());
+    bytes.extend_from_slice(&ENCRYPTED_MAGIC);
+    bytes.extend(encrypted);
+    Ok(bytes)
+}
+
+pub fn decrypt_model(cipher: &dyn ModelCipher, bytes: &[u8]) -> Result<Vec<u8>> {
+    let encrypted = bytes
+        .strip_prefix(&ENCRYPTED_MAGIC)
+        .ok_or_else(|| anyhow!("not an encrypted ltp model"))?;
+    cipher.decrypt(encrypted)
+}
+
+const FNV_OFFSET: u64 = 0xcbf29ce484222325;
+const FNV_PRIME: u64 = 0x100000001b3;
+
+fn fnv(seed: u64, bytes: &[u8]) -> u64 {
+    bytes.iter().fold(seed, |hash, &byte| {
+        (hash ^ byte as u64).wrapping_mul(FNV_PRIME)
+    })
+}
+
+/// SplitMix64
+fn next(state: &mut u64) -> u64 {
+    *state = state.wrapping_add(0x9e3779b97f4a7c15);
+    let mut z = *state;
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+    z ^ (z >> 31)
+}
+
+/// 内置的带密钥的混淆：以密钥与每次随机生成的 nonce 产生的伪随机序列异或，并附带密钥校验。
+///
+/// 只能防止直接读取模型文件，不是密码学意义上的加密；需要抵御有针对性的破解时，
+/// 用 AES-GCM 等算法实现 [`ModelCipher`]。
+#[derive(Clone, PartialEq, Eq)]
+pub struct XorCipher {
+    key: Vec<u8>,
+}
+
+/// 不输出密钥
+impl Debug for XorCipher {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("XorCipher").finish_non_exhaustive()
+    }
+}
+
+impl XorCipher {
+    pub fn new<K: AsRef<[u8]>>(key: K) -> Self {
+        Self {
+            key: key.as_ref().to_vec(),
+        }
+    }
+
+    fn check(&self, nonce: u64) -> u64 {
+        fnv(fnv(FNV_OFFSET, &self.key), &nonce.to_le_bytes())
+    }
+
+    fn apply(&self, nonce: u64, data: &mut [u8]) {
+        let mut state = fnv(FNV_OFFSET, &self.key) ^ nonce;
+        for chunk in data.chunks_mut(8) {
+            let stream = next(&mut state).to_le_bytes();
+            for (byte, key) in chunk.iter_mut().zip(stream) {
+                *byte ^= key;
+            }
+        }
+    }
+}
+
+/// 输出为 nonce（8 字节）、密钥校验（8 字节）与异或后的数据
+impl ModelCipher for XorCipher {
+    fn encrypt(&self, plain: &[u8]) -> Result<Vec<u8>> {
+        let nonce: u64 = rand::random();
+        let mut bytes = Vec::with_capacity(16 + plain.len());
+        bytes.extend_from_slice(&nonce.to_le_bytes());
+        bytes.extend_from_slice(&self.check(nonce).to_le_bytes());
+        bytes.extend_from_slice(plain);
+        self.apply(nonce, &mut bytes[16..]);
+        Ok(bytes)
+    }
+
+    fn decrypt(&self, encrypted: &[u8]) -> Result<Vec<u8>> {
+        if encrypted.len() < 16 {
+            return Err(anyhow!("encrypted model is truncated"));
+        }
+        let word = |bytes: &[u8]| u64::from_le_bytes(bytes.try_into().unwrap());
+        let nonce = word(&encrypted[..8]);
+        if word(&encrypted[8..16]) != self.check(nonce) {
+            return Err(anyhow!("wrong key for the encrypted model"));
+        }
+        let mut plain = encrypted[16..].to_vec();
+        self.apply(nonce, &mut plain);
+        Ok(plain)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_xor_cipher() -> Result<()> {
+        let cipher = XorCipher::new("secret");
+        let plain = b"{\"definition\":{}}";
+        let bytes = encrypt_model(&cipher, plain)?;
+        assert!(bytes.starts_with(&ENCRYPTED_MAGIC));
+        assert!(!bytes.windows(plain.len()).any(|window| window == plain));
+        // 每次加密的 nonce 不同
+        assert_ne!(encrypt_model(&cipher, plain)?, bytes);
+
+        assert_eq!(decrypt_model(&cipher, &bytes)?, plain);
+        assert!(decrypt_model(&XorCipher::new("wrong"), &bytes).is_err());
+        assert!(decrypt_model(&cipher, plain).is_err());
+        assert_eq!(format!("{:?}", cipher), "XorCipher { .. }");
+        Ok(())
+    }
+}
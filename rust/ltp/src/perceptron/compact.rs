@@ -0,0 +1,175 @@
+//! 内联短字符串的特征表
+//!
+//! 特征字符串大多不超过 16 字节，[`CompactFeatures`] 以 `CompactString` 为键，
+//! 不超过 24 字节的键直接存放在哈希表的槽位中，不单独申请内存，查找时也少一次间接访问；
+//! 可以直接作为训练时的特征字典，如 `trainer.build::<CompactFeatures, Vec<f64>>()`。
+use crate::perceptron::{
+    Definition, Perceptron, TraitFeature, TraitFeaturesTrainUtils, TraitParameter,
+    TraitParameterStorage,
+};
+use compact_str::CompactString;
+use std::collections::hash_map::RandomState;
+use std::collections::HashMap;
+use std::hash::BuildHasher;
+
+#[derive(Debug, Clone, Default)]
+pub struct CompactFeatures<S = RandomState> {
+    map: HashMap<CompactString, usize, S>,
+}
+
+impl<S: BuildHasher> PartialEq for CompactFeatures<S> {
+    fn eq(&self, other: &Self) -> bool {
+        self.map == other.map
+    }
+}
+
+impl<S: BuildHasher> Eq for CompactFeatures<S> {}
+
+fn into_owned((key, value): (CompactString, usize)) -> (String, usize) {
+    (key.into_string(), value)
+}
+
+impl<S: BuildHasher> CompactFeatures<S> {
+    pub fn len(&self) -> usize {
+        self.map.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.map.is_empty()
+    }
+
+    /// 超过内联长度、仍在堆上的键的个数
+    pub fn heap_keys(&self) -> usize {
+        self.map
+            .keys()
+            .filter(|key| key.is_heap_allocated())
+            .count()
+    }
+
+    pub fn to_hash_map(&self) -> HashMap<String, usize> {
+        self.map
+            .iter()
+            .map(|(key, &value)| (key.to_string(), value))
+            .collect()
+    }
+}
+
+impl<K: AsRef<str>, S: BuildHasher + Default> FromIterator<(K, usize)> for CompactFeatures<S> {
+    fn from_iter<I: IntoIterator<Item = (K, usize)>>(features: I) -> Self {
+        let map = features
+            .into_iter()
+            .map(|(key, value)| (CompactString::new(key), value))
+            .collect();
+        CompactFeatures { map }
+    }
+}
+
+impl<S> IntoIterator for CompactFeatures<S> {
+    type Item = (String, usize);
+    type IntoIter = std::iter::Map<
+        std::collections::hash_map::IntoIter<CompactString, usize>,
+        fn((CompactString, usize)) -> (String, usize),
+    >;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.map.into_iter().map(into_owned as _)
+    }
+}
+
+impl<S: BuildHasher> TraitFeature for CompactFeatures<S> {
+    #[inline]
+    fn get_with_key(&self, key: &str) -> Option<usize> {
+        self.map.get(key).copied()
+    }
+}
+
+impl<S: BuildHasher + Clone> TraitFeaturesTrainUtils for CompactFeatures<S> {
+    fn feature_num(&self) -> usize {
+        self.map.len()
+    }
+
+    /// 短的键复制到内联存储后释放原来的 `String`
+    fn insert_feature(&mut self, key: String, value: usize) {
+        self.map.insert(CompactString::from(key), value);
+    }
+
+    fn remove_feature(&mut self, key: &str) -> Option<usize> {
+        self.map.remove(key)
+    }
+
+    fn put_feature(&mut self, key: String, value: usize) {
+        self.map.insert(CompactString::from(key), value);
+    }
+
+    fn del_feature(&mut self, key: &str) -> Option<usize> {
+        self.map.remove(key)
+    }
+}
+
+pub type CompactModel<Define> = Perceptron<Define, CompactFeatures, Vec<f64>, f64>;
+
+impl<Define, S, ParamStorage, Param>
+    Perceptron<Define, HashMap<String, usize, S>, ParamStorage, Param>
+where
+    S: BuildHasher,
+    Param: TraitParameter,
+    ParamStorage: TraitParameterStorage<Param>,
+    Define: Definition,
+{
+    /// 将特征表转换为内联短键的哈希表，参数保持不变
+    pub fn into_compact(self) -> Perceptron<Define, CompactFeatures, ParamStorage, Param> {
+        let features = self.features.into_iter().collect();
+        Perceptron::new_with_parameters(self.definition, features, self.parameters)
+            .with_metadata(self.metadata)
+    }
+}
+
+impl<Define, S, ParamStorage, Param> Perceptron<Define, CompactFeatures<S>, ParamStorage, Param>
+where
+    S: BuildHasher,
+    Param: TraitParameter,
+    ParamStorage: TraitParameterStorage<Param>,
+    Define: Definition,
+{
+    /// 转回 `HashMap<String, usize>`，如保存模型前
+    pub fn into_hash_map(self) -> Perceptron<Define, HashMap<String, usize>, ParamStorage, Param> {
+        let features = self.features.to_hash_map();
+        Perceptron::new_with_parameters(self.definition, features, self.parameters)
+            .with_metadata(self.metadata)
+    }
+}
+
+#[cfg(all(test, feature = "cws"))]
+mod tests {
+    use super::*;
+    use crate::perceptron::Trainer;
+    use crate::CWSDefinition;
+    use anyhow::Result;
+
+    #[test]
+    fn test_compact_features() -> Result<()> {
+        let define = CWSDefinition::default();
+        let dataset =
+            define.parse_gold_features("他 叫 汤姆 去 拿 外衣 。\n我们 去 拿 外衣\n".as_bytes())?;
+        let mut trainer: Trainer<CWSDefinition> =
+            Trainer::new().epoch(5).verbose(false).compress(false);
+        trainer.train_set = Some(dataset);
+
+        let model = trainer.build::<CompactFeatures, Vec<f64>>()?;
+        assert_eq!(model.features.heap_keys(), 0);
+        assert_eq!(
+            model.predict("我们去拿外衣")?,
+            vec!["我们", "去", "拿", "外衣"]
+        );
+
+        let hash_model = model.clone().into_hash_map();
+        assert_eq!(hash_model.features.len(), model.features.len());
+        assert_eq!(hash_model.into_compact().features, model.features);
+
+        let long = "7".repeat(30);
+        let features: CompactFeatures = [("2我", 1), (long.as_str(), 2)].into_iter().collect();
+        assert_eq!(features.get_with_key(&long), Some(2));
+        assert_eq!(features.heap_keys(), 1);
+        Ok(())
+    }
+}
@@ -0,0 +1,243 @@
+//! 模型与语料文件的压缩层，读写均为流式，加载时不需要先解压到内存
+use anyhow::{anyhow, Result};
+#[cfg(feature = "fs")]
+use std::fs::File;
+use std::io::{BufRead, BufReader, Read, Write};
+#[cfg(feature = "fs")]
+use std::io::{Seek, SeekFrom};
+use std::path::Path;
+
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum Compression {
+    #[default]
+    None,
+    /// 压缩等级 0-9
+    Gzip(u32),
+    /// 压缩等级 1-22，0 为默认等级
+    Zstd(i32),
+}
+
+impl Compression {
+    /// 根据扩展名选择压缩方式：`.gz` 与 `.zst`/`.zstd`，其余不压缩
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Self {
+        match path.as_ref().extension().and_then(|ext| ext.to_str()) {
+            Some("gz") | Some("gzip") => Compression::Gzip(6),
+            Some("zst") | Some("zstd") => Compression::Zstd(0),
+            _ => Compression::None,
+        }
+    }
+
+    /// 根据文件头识别压缩方式，gzip 以 `1f 8b` 开头，zstd 以 `28 b5 2f fd` 开头
+    pub fn from_magic(bytes: &[u8]) -> Self {
+        if bytes.starts_with(&[0x1f, 0x8b]) {
+            Compression::Gzip(6)
+        } else if bytes.starts_with(&[0x28, 0xb5, 0x2f, 0xfd]) {
+            Compression::Zstd(0)
+        } else {
+            Compression::None
+        }
+    }
+
+    pub fn decoder<R: Read>(self, reader: R) -> Result<CompressedReader<R>> {
+        let reader = BufReader::new(reader);
+        Ok(match self {
+            Compression::None => CompressedReader::Plain(reader),
+            #[cfg(feature = "gzip")]
+            Compression::Gzip(_) => {
+                CompressedReader::Gzip(BufReader::new(flate2::bufread::MultiGzDecoder::new(reader)))
+            }
+            #[cfg(feature = "zstd")]
+            Compression::Zstd(_) => CompressedReader::Zstd(BufReader::new(
+                zstd::stream::read::Decoder::with_buffer(reader)?,
+            )),
+            #[allow(unreachable_patterns)]
+            other => return Err(unsupported(other)),
+        })
+    }
+
+    pub fn encoder<W: Write>(self, writer: W) -> Result<CompressedWriter<W>> {
+        Ok(match self {
+            Compression::None => CompressedWriter::Plain(writer),
+            #[cfg(feature = "gzip")]
+            Compression::Gzip(level) => CompressedWriter::Gzip(flate2::write::GzEncoder::new(
+                writer,
+                flate2::Compression::new(level),
+            )),
+            #[cfg(feature = "zstd")]
+            Compression::Zstd(level) => {
+                CompressedWriter::Zstd(zstd::stream::write::Encoder::new(writer, level)?)
+            }
+            #[allow(unreachable_patterns)]
+            other => return Err(unsupported(other)),
+        })
+    }
+}
+
+#[allow(dead_code)]
+fn unsupported(compression: Compression) -> anyhow::Error {
+    let feature = match compression {
+        Compression::None => "",
+        Compression::Gzip(_) => "gzip",
+        Compression::Zstd(_) => "zstd",
+    };
+    anyhow!(
+        "{:?} compression requires the `{}` feature of ltp",
+        compression,
+        feature
+    )
+}
+
+pub enum CompressedReader<R: Read> {
+    Plain(BufReader<R>),
+    #[cfg(feature = "gzip")]
+    Gzip(BufReader<flate2::bufread::MultiGzDecoder<BufReader<R>>>),
+    #[cfg(feature = "zstd")]
+    Zstd(BufReader<zstd::stream::read::Decoder<'static, BufReader<R>>>),
+}
+
+#[cfg(feature = "fs")]
+impl CompressedReader<File> {
+    /// 打开文件并透明解压，优先按扩展名识别，没有压缩扩展名时检查文件头
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        let mut file = File::open(path)?;
+        let compression = match Compression::from_path(path) {
+            Compression::None => {
+                let mut magic = Vec::with_capacity(4);
+                (&mut file).take(4).read_to_end(&mut magic)?;
+                file.seek(SeekFrom::Start(0))?;
+                Compression::from_magic(&magic)
+            }
+            compression => compression,
+        };
+        compression.decoder(file)
+    }
+}
+
+impl<R: Read> Read for CompressedReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        match self {
+            CompressedReader::Plain(r) => r.read(buf),
+            #[cfg(feature = "gzip")]
+            CompressedReader::Gzip(r) => r.read(buf),
+            #[cfg(feature = "zstd")]
+            CompressedReader::Zstd(r) => r.read(buf),
+        }
+    }
+}
+
+impl<R: Read> BufRead for CompressedReader<R> {
+    fn fill_buf(&mut self) -> std::io::Result<&[u8]> {
+        match self {
+            CompressedReader::Plain(r) => r.fill_buf(),
+            #[cfg(feature = "gzip")]
+            CompressedReader::Gzip(r) => r.fill_buf(),
+            #[cfg(feature = "zstd")]
+            CompressedReader::Zstd(r) => r.fill_buf(),
+        }
+    }
+
+    fn consume(&mut self, amt: usize) {
+        match self {
+            CompressedReader::Plain(r) => r.consume(amt),
+            #[cfg(feature = "gzip")]
+            CompressedReader::Gzip(r) => r.consume(amt),
+            #[cfg(feature = "zstd")]
+            CompressedReader::Zstd(r) => r.consume(amt),
+        }
+    }
+}
+
+/// 写完后需要调用 [`CompressedWriter::finish`] 写入压缩流的结尾
+pub enum CompressedWriter<W: Write> {
+    Plain(W),
+    #[cfg(feature = "gzip")]
+    Gzip(flate2::write::GzEncoder<W>),
+    #[cfg(feature = "zstd")]
+    Zstd(zstd::stream::write::Encoder<'static, W>),
+}
+
+impl<W: Write> CompressedWriter<W> {
+    pub fn finish(self) -> Result<W> {
+        Ok(match self {
+            CompressedWriter::Plain(mut w) => {
+                w.flush()?;
+                w
+            }
+            #[cfg(feature = "gzip")]
+            CompressedWriter::Gzip(w) => w.finish()?,
+            #[cfg(feature = "zstd")]
+            CompressedWriter::Zstd(w) => w.finish()?,
+        })
+    }
+}
+
+impl<W: Write> Write for CompressedWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        match self {
+            CompressedWriter::Plain(w) => w.write(buf),
+            #[cfg(feature = "gzip")]
+            CompressedWriter::Gzip(w) => w.write(buf),
+            #[cfg(feature = "zstd")]
+            CompressedWriter::Zstd(w) => w.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        match self {
+            CompressedWriter::Plain(w) => w.flush(),
+            #[cfg(feature = "gzip")]
+            CompressedWriter::Gzip(w) => w.flush(),
+            #[cfg(feature = "zstd")]
+            CompressedWriter::Zstd(w) => w.flush(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn round_trip(compression: Compression) -> Result<()> {
+        let data = "我们都有一个家，名字叫中国。".repeat(64);
+        let mut writer = compression.encoder(Vec::new())?;
+        writer.write_all(data.as_bytes())?;
+        let bytes = writer.finish()?;
+        if compression != Compression::None {
+            assert!(bytes.len() < data.len());
+        }
+
+        let mut restored = String::new();
+        compression
+            .decoder(bytes.as_slice())?
+            .read_to_string(&mut restored)?;
+        assert_eq!(restored, data);
+        Ok(())
+    }
+
+    #[test]
+    fn test_compression() -> Result<()> {
+        assert_eq!(Compression::from_path("cws.json.gz"), Compression::Gzip(6));
+        assert_eq!(Compression::from_path("cws.avro.zst"), Compression::Zstd(0));
+        assert_eq!(Compression::from_path("cws.avro"), Compression::None);
+        assert_eq!(
+            Compression::from_magic(b"\x1f\x8b\x08"),
+            Compression::Gzip(6)
+        );
+        assert_eq!(
+            Compression::from_magic(b"\x28\xb5\x2f\xfd"),
+            Compression::Zstd(0)
+        );
+        assert_eq!(
+            Compression::from_magic("他 叫".as_bytes()),
+            Compression::None
+        );
+
+        round_trip(Compression::None)?;
+        #[cfg(feature = "gzip")]
+        round_trip(Compression::Gzip(6))?;
+        #[cfg(feature = "zstd")]
+        round_trip(Compression::Zstd(3))?;
+        Ok(())
+    }
+}
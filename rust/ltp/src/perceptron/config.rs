@@ -0,0 +1,320 @@
+//! 配置文件驱动的训练，一份 TOML 即可复现一次实验
+//!
+//! ```toml
+//! task = "pos"
+//! train = "data/pos/train.txt"
+//! domains = { "data/pos/weibo.txt" = 3.0 }
+//! eval = "data/pos/dev.txt"
+//! vocab = "data/pos/vocab.txt"
+//! model = "pos_model.bin"
+//! features = ["near-char-type"]
+//!
+//! epoch = 10
+//! algorithm = "pa-i"
+//! margin = 0.5
+//! ratio = 0.3
+//!
+//! [tags]
+//! corpus = "ctb8"
+//! ```
+//!
+//! 相对路径均相对于配置文件所在目录。
+use crate::corpus::{read_ltp, LabelDiscovery, LabelOrder};
+#[cfg(feature = "cws")]
+use crate::perceptron::CWSDefinition;
+#[cfg(feature = "ner")]
+use crate::perceptron::NERDefinition;
+#[cfg(feature = "pos")]
+use crate::perceptron::POSDefinition;
+use crate::perceptron::{
+    Algorithm, CompressedReader, ModelMetadata, ModelTask, Normalizers, PaMode, TaskDefinition,
+    TokenJoin, Trainer, TraitParameter,
+};
+use anyhow::{anyhow, Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt::Display;
+use std::io::BufRead;
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum AlgorithmName {
+    Ap,
+    Pa,
+    PaI,
+    PaIi,
+    Hogwild,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TrainConfig {
+    pub task: ModelTask,
+    pub train: PathBuf,
+    /// 额外的训练语料及其采样权重，`train` 的权重为 1
+    #[serde(default)]
+    pub domains: BTreeMap<PathBuf, f64>,
+    #[serde(default)]
+    pub eval: Option<PathBuf>,
+    /// 标签表，也可以由 `vocab`（每行一个）给出，缺省时从训练集中发现
+    #[serde(default)]
+    pub labels: Vec<String>,
+    #[serde(default)]
+    pub vocab: Option<PathBuf>,
+    #[serde(default)]
+    pub label_order: Option<LabelOrder>,
+    /// 模型保存路径，由调用方使用
+    #[serde(default)]
+    pub model: Option<PathBuf>,
+    /// 训练时期望的特征模板选项（char-type、cross-char、near-char-type），须与编译选项一致
+    #[serde(default)]
+    pub features: Option<Vec<String>>,
+    /// 分词时连续的拉丁字母与数字的切分方式，如 `{ mode = "run", keep_hyphens = true }`
+    #[serde(default)]
+    pub token_join: Option<TokenJoin>,
+    /// 特征抽取前的内置规范化方式，如 `["width-fold", "lowercase-latin"]`，记录在模型中
+    #[serde(default)]
+    pub normalizers: Vec<String>,
+
+    #[serde(default)]
+    pub epoch: Option<usize>,
+    #[serde(default)]
+    pub shuffle: Option<bool>,
+    #[serde(default)]
+    pub verbose: Option<bool>,
+    #[serde(default)]
+    pub eval_threads: Option<usize>,
+    #[serde(default)]
+    pub algorithm: Option<AlgorithmName>,
+    /// AP 与 Hogwild 的训练线程数
+    #[serde(default)]
+    pub threads: Option<usize>,
+    /// PaI、PaII 的 c
+    #[serde(default)]
+    pub margin: Option<f64>,
+    #[serde(default)]
+    pub compress: Option<bool>,
+    #[serde(default)]
+    pub ratio: Option<f64>,
+    #[serde(default)]
+    pub threshold: Option<f64>,
+
+    #[serde(default)]
+    pub tags: BTreeMap<String, String>,
+
+    #[serde(skip)]
+    base: PathBuf,
+}
+
+impl TrainConfig {
+    pub fn from_toml(content: &str) -> Result<Self> {
+        Ok(toml::from_str(content)?)
+    }
+
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        let content = std::fs::read_to_string(path)
+            .with_context(|| format!("failed to read config {}", path.display()))?;
+        let mut config = Self::from_toml(&content)?;
+        config.base = path.parent().map(Path::to_path_buf).unwrap_or_default();
+        Ok(config)
+    }
+
+    /// 相对于配置文件所在目录解析路径
+    pub fn resolve<P: AsRef<Path>>(&self, path: P) -> PathBuf {
+        self.base.join(path)
+    }
+
+    pub fn model_path(&self) -> Option<PathBuf> {
+        self.model.as_ref().map(|path| self.resolve(path))
+    }
+
+    /// `labels` 或 `vocab` 中的标签保持原有下标，训练集中新出现的标签按 `label_order` 追加在后面
+    pub fn labels(&self) -> Result<Vec<String>> {
+        let mut pinned = self.labels.clone();
+        if let Some(path) = &self.vocab {
+            for line in CompressedReader::open(self.resolve(path))?.lines() {
+                let line = line?;
+                if !line.is_empty() {
+                    pinned.push(line);
+                }
+            }
+        }
+        let mut sentences = Vec::new();
+        for path in std::iter::once(&self.train).chain(self.domains.keys()) {
+            let reader = CompressedReader::open(self.resolve(path))?;
+            sentences.extend(read_ltp(reader, self.task)?);
+        }
+        LabelDiscovery::new()
+            .pin(pinned)
+            .order(self.label_order.unwrap_or_default())
+            .discover_corpus(&sentences, self.task)
+    }
+
+    pub fn algorithm<Param: TraitParameter>(&self) -> Result<Option<Algorithm<Param>>> {
+        let threads = self.threads.unwrap_or(1);
+        let margin =
+            || Param::from(self.margin.unwrap_or(0.5)).ok_or_else(|| anyhow!("invalid margin"));
+        self.algorithm
+            .map(|name| -> Result<_> {
+                Ok(match name {
+                    AlgorithmName::Ap => Algorithm::AP(threads),
+                    AlgorithmName::Pa => Algorithm::PA(PaMode::Pa),
+                    AlgorithmName::PaI => Algorithm::PA(PaMode::PaI(margin()?)),
+                    AlgorithmName::PaIi => Algorithm::PA(PaMode::PaII(margin()?)),
+                    AlgorithmName::Hogwild => Algorithm::Hogwild(threads),
+                })
+            })
+            .transpose()
+    }
+
+    fn check_features(&self) -> Result<()> {
+        let Some(expected) = &self.features else {
+            return Ok(());
+        };
+        let mut expected = expected.clone();
+        expected.sort();
+        let mut actual = ModelMetadata::normalization_features();
+        actual.sort();
+        if expected != actual {
+            return Err(anyhow!(
+                "config expects features {:?}, but ltp is built with {:?}",
+                expected,
+                actual
+            ));
+        }
+        Ok(())
+    }
+
+    /// 按配置构造训练器并加载训练、验证集
+    pub fn trainer<Define, Param>(&self) -> Result<Trainer<Define, Param>>
+    where
+        Define: ConfigDefinition + Sync + Send + 'static,
+        Param: TraitParameter + Display + Sync + Send + 'static,
+    {
+        if self.task != Define::TASK {
+            return Err(anyhow!(
+                "config is for {}, but a {} trainer is requested",
+                self.task,
+                Define::TASK
+            ));
+        }
+        self.check_features()?;
+
+        let mut definition = match Define::TASK {
+            ModelTask::CWS => Define::from_labels(Vec::new()),
+            _ => Define::from_labels(self.labels()?),
+        };
+        if let Some(join) = self.token_join {
+            definition.set_token_join(join);
+        }
+        let normalizers = Normalizers::from_names(&self.normalizers);
+        if let Some(name) = normalizers.unresolved().first() {
+            return Err(anyhow!("unknown normalizer `{}`", name));
+        }
+        definition.set_normalizers(normalizers);
+        let mut trainer = Trainer::new_with_define(definition);
+        if let Some(epoch) = self.epoch {
+            trainer = trainer.epoch(epoch);
+        }
+        if let Some(shuffle) = self.shuffle {
+            trainer = trainer.shuffle(shuffle);
+        }
+        if let Some(verbose) = self.verbose {
+            trainer = trainer.verbose(verbose);
+        }
+        if let Some(eval_threads) = self.eval_threads {
+            trainer = trainer.eval_threads(eval_threads);
+        }
+        if let Some(algorithm) = self.algorithm()? {
+            trainer = trainer.algorithm(algorithm);
+        }
+        if let Some(compress) = self.compress {
+            trainer = trainer.compress(compress);
+        }
+        if let Some(ratio) = self.ratio {
+            trainer = trainer.ratio(ratio);
+        }
+        if let Some(threshold) = self.threshold {
+            let threshold = Param::from(threshold).ok_or_else(|| anyhow!("invalid threshold"))?;
+            trainer = trainer.threshold(threshold);
+        }
+        for (key, value) in &self.tags {
+            trainer = trainer.tag(key, value);
+        }
+
+        let mut trainer = trainer.train_file(self.resolve(&self.train))?;
+        for (path, weight) in &self.domains {
+            trainer = trainer.train_file_weighted(self.resolve(path), *weight)?;
+        }
+        if let Some(eval) = &self.eval {
+            trainer = trainer.eval_file(self.resolve(eval))?;
+        }
+        Ok(trainer)
+    }
+}
+
+/// 可由标签表构造的任务定义，分词忽略标签表
+pub trait ConfigDefinition: TaskDefinition {
+    fn from_labels(labels: Vec<String>) -> Self;
+}
+
+#[cfg(feature = "cws")]
+impl ConfigDefinition for CWSDefinition {
+    fn from_labels(_labels: Vec<String>) -> Self {
+        CWSDefinition::new()
+    }
+}
+
+#[cfg(feature = "pos")]
+impl ConfigDefinition for POSDefinition {
+    fn from_labels(labels: Vec<String>) -> Self {
+        POSDefinition::new(labels)
+    }
+}
+
+#[cfg(feature = "ner")]
+impl ConfigDefinition for NERDefinition {
+    fn from_labels(labels: Vec<String>) -> Self {
+        NERDefinition::new(labels)
+    }
+}
+
+impl<Define, Param> Trainer<Define, Param>
+where
+    Define: ConfigDefinition + Sync + Send + 'static,
+    Param: TraitParameter + Display + Sync + Send + 'static,
+{
+    /// 从 TOML 配置文件构造训练器，格式见 [`TrainConfig`]
+    pub fn from_config<P: AsRef<Path>>(path: P) -> Result<Self> {
+        TrainConfig::from_file(path)?.trainer()
+    }
+}
+
+#[cfg(all(test, feature = "cws", feature = "pos"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_config() -> Result<()> {
+        let dir = std::env::temp_dir().join("ltp-train-config");
+        std::fs::create_dir_all(&dir)?;
+        std::fs::write(dir.join("train.txt"), "他 叫 汤姆\n去 拿 外衣 。\n")?;
+        std::fs::write(
+            dir.join("cws.toml"),
+            "task = \"cws\"\ntrain = \"train.txt\"\nepoch = 3\nalgorithm = \"pa-ii\"\nmargin = 0.1\n\n[tags]\ncorpus = \"toy\"\n",
+        )?;
+
+        let trainer: Trainer<CWSDefinition> = Trainer::from_config(dir.join("cws.toml"))?;
+        assert_eq!(trainer.epoch, 3);
+        assert_eq!(trainer.algorithm, Algorithm::PA(PaMode::PaII(0.1)));
+        assert_eq!(trainer.train_set.as_ref().map(Vec::len), Some(2));
+        assert_eq!(trainer.tags["corpus"], "toy");
+
+        let err = Trainer::<POSDefinition>::from_config(dir.join("cws.toml")).unwrap_err();
+        assert!(err.to_string().contains("CWS"));
+        assert!(TrainConfig::from_toml("task = \"cws\"\ntrain = \"a\"\nepochs = 1").is_err());
+        Ok(())
+    }
+}
@@ -1,6 +1,10 @@
 use crate::buf_feature;
-use crate::perceptron::definition::GenericItem;
+use crate::error::LtpError;
+use crate::perceptron::definition::{
+    is_skipped_char, split_buffer, to_owned_features, GenericItem,
+};
 use crate::perceptron::{Definition, Sample};
+use crate::perceptron::{EmbeddingFeatures, FeatureArena, Normalizers, TokenJoin};
 use anyhow::Result;
 use itertools::Itertools;
 #[cfg(feature = "parallel")]
@@ -8,6 +12,7 @@ use rayon::prelude::*;
 #[cfg(feature = "serialization")]
 use serde::{Deserialize, Serialize};
 use std::io::{BufRead, BufReader, Read, Write};
+use std::sync::Arc;
 
 /// Character type.
 #[cfg(any(
@@ -61,15 +66,57 @@ impl CharacterType {
             _ => Self::Other,
         }
     }
+
+    /// 单元的类型取其首字的类型
+    pub fn get_unit_type(unit: &str) -> Self {
+        unit.chars().next().map_or(Self::Other, Self::get_type)
+    }
 }
 
 #[cfg_attr(feature = "serialization", derive(Serialize, Deserialize))]
-#[derive(Default, Debug, Clone, Copy, PartialEq, Eq, Hash)]
-pub struct CWSDefinition {}
+#[derive(Default, Debug, Clone, PartialEq, Eq)]
+pub struct CWSDefinition {
+    /// 离散化的预训练词向量特征，不随模型保存
+    #[cfg_attr(feature = "serialization", serde(skip))]
+    embeddings: Option<Arc<EmbeddingFeatures>>,
+    /// 单元切分方式，不随定义保存，由模型的元信息恢复
+    #[cfg_attr(feature = "serialization", serde(skip))]
+    join: TokenJoin,
+    /// 特征抽取前的规范化，不随定义保存，由模型的元信息恢复
+    #[cfg_attr(feature = "serialization", serde(skip))]
+    normalizers: Normalizers,
+}
 
 impl CWSDefinition {
     pub fn new() -> Self {
-        CWSDefinition {}
+        CWSDefinition {
+            embeddings: None,
+            join: TokenJoin::default(),
+            normalizers: Normalizers::default(),
+        }
+    }
+
+    /// 连续的拉丁字母与数字的切分方式，训练与预测须一致
+    pub fn join(mut self, join: TokenJoin) -> Self {
+        self.join = join;
+        self
+    }
+
+    /// 抽取特征前规范化各字，训练与预测一致
+    pub fn with_normalizers(mut self, normalizers: Normalizers) -> Self {
+        self.normalizers = normalizers;
+        self
+    }
+
+    /// 抽取特征时加入离散化的预训练字向量特征
+    pub fn embeddings(mut self, embeddings: Arc<EmbeddingFeatures>) -> Self {
+        self.embeddings = Some(embeddings);
+        self
+    }
+
+    /// 加载模型后重新设置训练时使用的字向量特征
+    pub fn set_embeddings(&mut self, embeddings: Option<Arc<EmbeddingFeatures>>) {
+        self.embeddings = embeddings;
     }
 
     /// +--------------+-----------------------------------------------------------------------+
@@ -87,147 +134,171 @@ impl CWSDefinition {
         &self,
         sentence: &str,
         buffer: &'a mut Vec<u8>,
-    ) -> Result<(Vec<usize>, Vec<Vec<usize>>)> {
-        let char_null = '\u{0000}';
+    ) -> Result<(Vec<usize>, FeatureArena<usize>)> {
+        buffer.clear();
         let chars_len = sentence.len();
+        let (starts, units): (Vec<usize>, Vec<&str>) =
+            self.join.units(sentence).into_iter().unzip();
+        // 规范化只影响特征，词仍按原文切分
+        let normalized = self.normalizers.apply_all(&units)?;
+        let chars: Vec<&str> = normalized.iter().map(AsRef::as_ref).collect();
 
-        let mut index = Vec::with_capacity(chars_len);
-        let mut features = Vec::with_capacity(chars_len);
+        let mut index = Vec::with_capacity(chars.len() + 1);
+        let mut features = FeatureArena::with_capacity(chars.len(), chars.len() * 13);
 
-        let mut pre_char = char_null;
-        let mut pre2_char = char_null;
-        let mut chars = sentence
-            .char_indices()
-            .filter(|(_, ch)| !ch.is_whitespace())
-            .multipeek();
-        while let Some((char_idx, cur_char)) = chars.next() {
-            let mut feature = Vec::with_capacity(13);
+        for (i, (&char_idx, &cur_char)) in starts.iter().zip(&chars).enumerate() {
             // ch[0]
-            buf_feature!(buffer, feature, "2{}", cur_char);
+            buf_feature!(buffer, features, "2{}", cur_char);
             // TYPE(ch[0])
             #[cfg(feature = "char-type")]
             buf_feature!(
                 buffer,
-                feature,
+                features,
                 "b{}",
-                CharacterType::get_type(cur_char) as u8
+                CharacterType::get_unit_type(cur_char) as u8
             );
-            if pre_char != char_null {
+            if let Some(pre_char) = i.checked_sub(1).map(|i| chars[i]) {
                 // ch[-1]
-                buf_feature!(buffer, feature, "1{}", pre_char);
+                buf_feature!(buffer, features, "1{}", pre_char);
                 // ch[-1]ch[0]
-                buf_feature!(buffer, feature, "6{}{}", pre_char, cur_char);
+                buf_feature!(buffer, features, "6{}{}", pre_char, cur_char);
                 // TYPE(ch[-1])
                 #[cfg(feature = "char-type")]
                 buf_feature!(
                     buffer,
-                    feature,
+                    features,
                     "c{}",
-                    CharacterType::get_type(pre_char) as u8
+                    CharacterType::get_unit_type(pre_char) as u8
                 );
 
                 // TYPE(ch[-1]) TYPE(ch[0])
                 #[cfg(feature = "near-char-type")]
                 buf_feature!(
                     buffer,
-                    feature,
+                    features,
                     "d{}{}",
-                    CharacterType::get_type(pre_char) as u8,
-                    CharacterType::get_type(cur_char) as u8
+                    CharacterType::get_unit_type(pre_char) as u8,
+                    CharacterType::get_unit_type(cur_char) as u8
                 );
 
-                if pre2_char != char_null {
+                let pre2_char = i.checked_sub(2).map(|i| chars[i]);
+                if let Some(pre2_char) = pre2_char {
                     // ch[-2]
-                    buf_feature!(buffer, feature, "0{}", pre2_char);
+                    buf_feature!(buffer, features, "0{}", pre2_char);
                     // ch[-2]ch[-1]
-                    buf_feature!(buffer, feature, "5{}{}", pre2_char, pre_char);
+                    buf_feature!(buffer, features, "5{}{}", pre2_char, pre_char);
                     // ch[-2]ch[0]
                     #[cfg(feature = "cross-char")]
-                    buf_feature!(buffer, feature, "9{}{}", pre2_char, cur_char);
+                    buf_feature!(buffer, features, "9{}{}", pre2_char, cur_char);
                 }
 
-                if pre2_char == cur_char {
-                    buf_feature!(buffer, feature, "c"); // ch[-2]=ch[0]?
+                if pre2_char == Some(cur_char) {
+                    buf_feature!(buffer, features, "c"); // ch[-2]=ch[0]?
                 }
             }
 
-            let next_char = if let Some((_, next_char)) = chars.peek() {
+            if let Some(&next_char) = chars.get(i + 1) {
                 // ch[+1]
-                buf_feature!(buffer, feature, "3{}", next_char);
+                buf_feature!(buffer, features, "3{}", next_char);
                 // ch[0]ch[+1]
-                buf_feature!(buffer, feature, "7{}{}", cur_char, next_char);
+                buf_feature!(buffer, features, "7{}{}", cur_char, next_char);
                 // TYPE(ch[1])
                 #[cfg(feature = "char-type")]
                 buf_feature!(
                     buffer,
-                    feature,
+                    features,
                     "d{}",
-                    CharacterType::get_type(*next_char) as u8
+                    CharacterType::get_unit_type(next_char) as u8
                 );
-                *next_char
-            } else {
-                ' '
-            };
-
-            if let Some((_, next2_char)) = chars.peek() {
-                // ch[+2]
-                buf_feature!(buffer, feature, "4{}", next2_char);
-                // ch[+1]ch[+2]
-                buf_feature!(buffer, feature, "8{}{}", next_char, next2_char);
-                // ch[0]ch[+2]
-                #[cfg(feature = "cross-char")]
-                buf_feature!(buffer, feature, "a{}{}", cur_char, next2_char);
+
+                if let Some(&next2_char) = chars.get(i + 2) {
+                    // ch[+2]
+                    buf_feature!(buffer, features, "4{}", next2_char);
+                    // ch[+1]ch[+2]
+                    buf_feature!(buffer, features, "8{}{}", next_char, next2_char);
+                    // ch[0]ch[+2]
+                    #[cfg(feature = "cross-char")]
+                    buf_feature!(buffer, features, "a{}{}", cur_char, next2_char);
+                }
             }
 
-            pre2_char = pre_char;
-            pre_char = cur_char;
+            if let Some(embeddings) = &self.embeddings {
+                embeddings.write(buffer, &mut features, &chars, i)?;
+            }
 
             index.push(char_idx);
-            features.push(feature);
+            features.end_token();
         }
         index.push(chars_len);
         Ok((index, features))
     }
 
-    pub fn parse_char_features(&self, sentence: &str) -> Result<(Vec<usize>, Vec<Vec<String>>)> {
-        let mut buffer = Vec::with_capacity(sentence.len() * 20);
-        let (index, features) = self.parse_char_features_with_buffer(sentence, &mut buffer)?;
+    pub fn parse_char_features(
+        &self,
+        sentence: &str,
+    ) -> Result<(Vec<usize>, FeatureArena<String>)> {
+        let mut buffer = Vec::with_capacity(self.buffer_capacity(&sentence));
+        let (index, features) = self.parse_char_features_with_buffer_str(sentence, &mut buffer)?;
+        Ok((index, to_owned_features(features)))
+    }
 
-        let mut start = 0usize;
-        let mut result = Vec::with_capacity(features.len());
-        for feature_end in features {
-            let mut feature = Vec::with_capacity(feature_end.len());
-            for end in feature_end {
-                // Safety : all write are valid utf8
-                feature.push(String::from_utf8_lossy(&buffer[start..end]).to_string());
-                start = end;
-            }
-            result.push(feature);
-        }
+    /// 第 `start` 到第 `end`（含）个单元组成的词，`index` 为解析特征时返回的各单元的字节位置，
+    /// 词尾之后被跳过的空白与控制字符不属于该词
+    pub fn word<'a>(
+        &self,
+        sentence: &'a str,
+        index: &[usize],
+        start: usize,
+        end: usize,
+    ) -> &'a str {
+        let last = index[end];
+        let width = self.join.unit_len(&sentence[last..]);
+        &sentence[index[start]..last + width]
+    }
+
+    /// 各单元的 BMES 标签，`boundaries` 为词的分隔处的字节位置（升序），
+    /// 两个单元之间有分隔时前者为词尾、后者为词首，跨越分隔的单元整体属于其开头所在的词
+    pub(crate) fn boundary_labels(&self, text: &str, boundaries: &[usize]) -> Result<Vec<usize>> {
+        let units = self.join.units(text);
+        let end = |i: usize| units[i].0 + units[i].1.len();
+        // `from..=to` 中是否有分隔
+        let split = |from: usize, to: usize| {
+            boundaries.partition_point(|&b| b < from) < boundaries.partition_point(|&b| b <= to)
+        };
+        (0..units.len())
+            .map(|i| {
+                let begin = i == 0 || split(end(i - 1), units[i].0);
+                let finish = i + 1 == units.len() || split(end(i), units[i + 1].0);
+                self.label_to(match (begin, finish) {
+                    (true, true) => "S",
+                    (true, false) => "B",
+                    (false, true) => "E",
+                    (false, false) => "M",
+                })
+            })
+            .collect()
+    }
 
-        Ok((index, result))
+    /// 以空白分隔词的一行标注语料
+    fn gold_sample(&self, sentence: &str) -> Result<Sample> {
+        let (_, features) = self.parse_char_features(sentence)?;
+        // 空白与控制字符都视为词的分隔
+        let boundaries: Vec<usize> = sentence
+            .char_indices()
+            .filter(|&(_, ch)| is_skipped_char(ch))
+            .map(|(i, _)| i)
+            .collect();
+        let labels = self.boundary_labels(sentence, &boundaries)?;
+        Ok(Sample::new(features, labels))
     }
 
     pub fn parse_char_features_with_buffer_str<'a>(
         &self,
         sentence: &str,
         buffer: &'a mut Vec<u8>,
-    ) -> Result<(Vec<usize>, Vec<Vec<&'a str>>)> {
+    ) -> Result<(Vec<usize>, FeatureArena<&'a str>)> {
         let (index, features) = self.parse_char_features_with_buffer(sentence, buffer)?;
-
-        let mut start = 0usize;
-        let mut result = Vec::with_capacity(features.len());
-        for feature_end in features {
-            let mut feature = Vec::with_capacity(feature_end.len());
-            for end in feature_end {
-                // Safety : all write are valid utf8
-                feature.push(unsafe { std::str::from_utf8_unchecked(&buffer[start..end]) });
-                start = end;
-            }
-            result.push(feature);
-        }
-
-        Ok((index, result))
+        Ok((index, split_buffer(buffer, &features)))
     }
 }
 
@@ -253,13 +324,13 @@ impl Definition for CWSDefinition {
         4
     }
 
-    fn label_to(&self, label: &str) -> usize {
+    fn label_to(&self, label: &str) -> Result<usize> {
         match label {
-            "S" => 0,
-            "B" => 1,
-            "M" => 2,
-            "E" => 3,
-            _ => panic!("unknown label"),
+            "S" => Ok(0),
+            "B" => Ok(1),
+            "M" => Ok(2),
+            "E" => Ok(3),
+            _ => Err(LtpError::UnknownLabel(label.to_string()).into()),
         }
     }
 
@@ -273,16 +344,31 @@ impl Definition for CWSDefinition {
         }
     }
 
-    fn parse_features(&self, sentence: &&str) -> Result<(Vec<usize>, Vec<Vec<String>>)> {
-        let (index, features) = self.parse_char_features(sentence)?;
-        Ok((index, features))
+    fn token_join(&self) -> TokenJoin {
+        self.join
+    }
+
+    fn set_token_join(&mut self, join: TokenJoin) {
+        self.join = join;
+    }
+
+    fn normalizers(&self) -> Normalizers {
+        self.normalizers.clone()
+    }
+
+    fn set_normalizers(&mut self, normalizers: Normalizers) {
+        self.normalizers = normalizers;
+    }
+
+    fn buffer_capacity(&self, sentence: &&str) -> usize {
+        sentence.len() * 20
     }
 
     fn parse_features_with_buffer<'a>(
         &self,
         sentence: &&str,
         buf: &'a mut Vec<u8>,
-    ) -> Result<(Vec<usize>, Vec<Vec<&'a str>>)> {
+    ) -> Result<(Vec<usize>, FeatureArena<&'a str>)> {
         let (index, features) = self.parse_char_features_with_buffer_str(sentence, buf)?;
         Ok((index, features))
     }
@@ -294,35 +380,7 @@ impl Definition for CWSDefinition {
 
         lines
             .par_iter()
-            .map(|sentence| {
-                self.parse_char_features(sentence).map(|(_, features)| {
-                    let mut labels = Vec::with_capacity(features.len());
-                    // 构建标签序列
-
-                    let mut last_char = ' ';
-                    let mut chars = sentence.chars().peekable();
-                    while let Some(cur_char) = chars.next() {
-                        if cur_char == ' ' {
-                            last_char = cur_char;
-                            continue;
-                        }
-                        if let Some(next_char) = chars.peek() {
-                            match (last_char, next_char) {
-                                (' ', ' ') => labels.push(self.label_to("S")),
-                                (' ', _nc) => labels.push(self.label_to("B")),
-                                (_lc, ' ') => labels.push(self.label_to("E")),
-                                (_lc, _nc) => labels.push(self.label_to("M")),
-                            }
-                        } else if last_char == ' ' {
-                            labels.push(self.label_to("S"));
-                        } else {
-                            labels.push(self.label_to("E"));
-                        }
-                        last_char = cur_char;
-                    }
-                    (features, labels)
-                })
-            })
+            .map(|sentence| self.gold_sample(sentence))
             .collect()
     }
 
@@ -333,34 +391,7 @@ impl Definition for CWSDefinition {
 
         lines
             .iter()
-            .map(|sentence| {
-                self.parse_char_features(sentence).map(|(_, features)| {
-                    let mut labels = Vec::with_capacity(features.len());
-                    // 构建标签序列
-                    let mut last_char = ' ';
-                    let mut chars = sentence.chars().peekable();
-                    while let Some(cur_char) = chars.next() {
-                        if cur_char == ' ' {
-                            last_char = cur_char;
-                            continue;
-                        }
-                        if let Some(next_char) = chars.peek() {
-                            match (last_char, next_char) {
-                                (' ', ' ') => labels.push(self.label_to("S")),
-                                (' ', _nc) => labels.push(self.label_to("B")),
-                                (_lc, ' ') => labels.push(self.label_to("E")),
-                                (_lc, _nc) => labels.push(self.label_to("M")),
-                            }
-                        } else if last_char == ' ' {
-                            labels.push(self.label_to("S"));
-                        } else {
-                            labels.push(self.label_to("E"));
-                        }
-                        last_char = cur_char;
-                    }
-                    (features, labels)
-                })
-            })
+            .map(|sentence| self.gold_sample(sentence))
             .collect()
     }
 
@@ -388,7 +419,7 @@ mod tests {
         let (_, no_buffer) = define.parse_char_features(sentence)?;
         let (_, with_buffer) = define.parse_char_features_with_buffer_str(sentence, &mut buffer)?;
 
-        for (a, b) in zip(no_buffer, with_buffer) {
+        for (a, b) in zip(&no_buffer, &with_buffer) {
             for (c, d) in zip(a, b) {
                 assert_eq!(c, d);
             }
@@ -404,6 +435,34 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_reuse_buffer() -> Result<()> {
+        let mut buffer = Vec::new();
+        let define = Define::default();
+        define.parse_char_features_with_buffer_str("桂林警备区", &mut buffer)?;
+        let (_, reused) = define.parse_char_features_with_buffer_str("水电站", &mut buffer)?;
+        let (_, fresh) = define.parse_char_features("水电站")?;
+        assert_eq!(reused.to_nested(), fresh.to_nested());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_token_join() -> Result<()> {
+        use crate::perceptron::{Definition, JoinMode, TokenJoin};
+
+        let define = Define::new().join(TokenJoin::new(JoinMode::Run));
+        let (index, features) = define.parse_char_features("买 iPhone15手机")?;
+        assert_eq!(features.len(), 4);
+        assert_eq!(features.get(1).unwrap()[0], "2iPhone15");
+        assert_eq!(define.word("买 iPhone15手机", &index, 1, 1), "iPhone15");
+
+        let sample = define.gold_sample("买 iPhone15 手机")?;
+        let labels: Vec<&str> = sample.labels.iter().map(|&l| define.to_label(l)).collect();
+        assert_eq!(labels, ["S", "S", "B", "E"]);
+        Ok(())
+    }
+
     #[test]
     fn test_features() -> Result<()> {
         let define = Define::default();
@@ -0,0 +1,172 @@
+//! 字级处理中连续的拉丁字母与数字的切分方式：默认每个字符单独作为一个单元，
+//! 也可以把一串字母数字合为一个单元，使 `iPhone15` 这样的词不会被切开
+use crate::perceptron::definition::is_skipped_char;
+use crate::script::Script;
+#[cfg(feature = "serialization")]
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "grapheme")]
+use unicode_segmentation::UnicodeSegmentation;
+
+#[cfg_attr(feature = "serialization", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serialization", serde(rename_all = "kebab-case"))]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum JoinMode {
+    /// 每个字符单独作为一个单元
+    #[default]
+    Char,
+    /// 连续的字母与数字作为一个单元
+    Run,
+    /// 同 `Run`，但在大小写变化处切开，如 `iPhone` → `i` `Phone`、`HTTPServer` → `HTTP` `Server`
+    CaseChange,
+}
+
+/// 分词定义使用的单元切分方式，随模型的元信息保存，加载模型时恢复，
+/// 保证训练与预测的切分一致
+#[cfg_attr(feature = "serialization", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serialization", serde(default))]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct TokenJoin {
+    pub mode: JoinMode,
+    /// 两侧都是字母或数字的连字符留在单元内，如 `COVID-19`，`Char` 时不起作用
+    pub keep_hyphens: bool,
+    /// 以扩展字位簇而不是字符为最小单位，需要启用 `grapheme` 特性
+    pub graphemes: bool,
+}
+
+fn is_latin(ch: char) -> bool {
+    ch.is_ascii_digit() || ('０'..='９').contains(&ch) || Script::of(ch) == Some(Script::Latin)
+}
+
+fn is_case_change(prev: char, ch: char, next: Option<char>) -> bool {
+    (prev.is_lowercase() && ch.is_uppercase())
+        || (prev.is_uppercase() && ch.is_uppercase() && next.is_some_and(char::is_lowercase))
+}
+
+impl TokenJoin {
+    pub fn new(mode: JoinMode) -> Self {
+        Self {
+            mode,
+            keep_hyphens: false,
+            graphemes: false,
+        }
+    }
+
+    pub fn keep_hyphens(mut self, keep_hyphens: bool) -> Self {
+        self.keep_hyphens = keep_hyphens;
+        self
+    }
+
+    /// 使 emoji 的 ZWJ 序列、组合附加符号等留在同一个单元内
+    #[cfg(feature = "grapheme")]
+    pub fn graphemes(mut self, graphemes: bool) -> Self {
+        self.graphemes = graphemes;
+        self
+    }
+
+    /// `text` 开头的字符或字位簇的字节长度
+    fn atom_len(&self, text: &str) -> usize {
+        #[cfg(feature = "grapheme")]
+        if self.graphemes {
+            return text.graphemes(true).next().map_or(0, str::len);
+        }
+        text.chars().next().map_or(0, char::len_utf8)
+    }
+
+    /// `text` 开头的单元的字节长度，`text` 为空时为 0；字位簇的类型取其首字
+    pub fn unit_len(&self, text: &str) -> usize {
+        let Some(first) = text.chars().next() else {
+            return 0;
+        };
+        let mut end = self.atom_len(text);
+        if self.mode == JoinMode::Char || !is_latin(first) {
+            return end;
+        }
+        let mut prev = first;
+        while let Some(ch) = text[end..].chars().next() {
+            let len = self.atom_len(&text[end..]);
+            let next = text[end + len..].chars().next();
+            let joined = match is_latin(ch) {
+                true => !(self.mode == JoinMode::CaseChange && is_case_change(prev, ch, next)),
+                false => {
+                    self.keep_hyphens && ch == '-' && is_latin(prev) && next.is_some_and(is_latin)
+                }
+            };
+            if !joined {
+                break;
+            }
+            (end, prev) = (end + len, ch);
+        }
+        end
+    }
+
+    /// 各单元的字节位置与文本，跳过空白与控制字符
+    pub fn units<'a>(&self, text: &'a str) -> Vec<(usize, &'a str)> {
+        let mut units = Vec::with_capacity(text.len());
+        let mut start = 0;
+        while let Some(ch) = text[start..].chars().next() {
+            if is_skipped_char(ch) {
+                start += self.atom_len(&text[start..]);
+                continue;
+            }
+            let len = self.unit_len(&text[start..]);
+            units.push((start, &text[start..start + len]));
+            start += len;
+        }
+        units
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_token_join() {
+        let text = "买 iPhone15 和 HTTPServer，COVID-19";
+        let units = |join: TokenJoin| -> Vec<&str> {
+            join.units(text).into_iter().map(|(_, unit)| unit).collect()
+        };
+        assert_eq!(units(TokenJoin::default()).len(), text.chars().count() - 3);
+        assert_eq!(
+            units(TokenJoin::new(JoinMode::Run)),
+            [
+                "买",
+                "iPhone15",
+                "和",
+                "HTTPServer",
+                "，",
+                "COVID",
+                "-",
+                "19"
+            ]
+        );
+        assert_eq!(
+            units(TokenJoin::new(JoinMode::CaseChange).keep_hyphens(true)),
+            ["买", "i", "Phone15", "和", "HTTP", "Server", "，", "COVID-19"]
+        );
+        assert_eq!(TokenJoin::new(JoinMode::Run).unit_len("ab-"), 2);
+    }
+
+    #[cfg(feature = "grapheme")]
+    #[test]
+    fn test_graphemes() {
+        let text = "家庭👨\u{200d}👩\u{200d}👧 cafe\u{301}";
+        let join = TokenJoin::default().graphemes(true);
+        let units: Vec<&str> = join.units(text).into_iter().map(|(_, unit)| unit).collect();
+        assert_eq!(
+            units,
+            [
+                "家",
+                "庭",
+                "👨\u{200d}👩\u{200d}👧",
+                "c",
+                "a",
+                "f",
+                "e\u{301}"
+            ]
+        );
+        let run = TokenJoin::new(JoinMode::Run).graphemes(true);
+        assert_eq!(run.unit_len("cafe\u{301}好"), "cafe\u{301}".len());
+        assert_eq!(TokenJoin::default().units(text).len(), 12);
+    }
+}
@@ -1,18 +1,170 @@
+#[cfg(feature = "cws")]
 mod cws;
+mod join;
+#[cfg(feature = "ner")]
 mod ner;
+#[cfg(feature = "pos")]
 mod pos;
+mod sequence;
+mod template;
 
 use anyhow::Result;
-use std::collections::HashSet;
+use std::borrow::Cow;
+use std::collections::{HashMap, HashSet};
 use std::fmt::Debug;
 use std::io::Read;
+#[cfg(any(feature = "pos", feature = "ner"))]
+use std::io::{BufRead, BufReader};
+use std::iter::zip;
 
+use crate::error::LtpError;
 use crate::get_entities;
-use crate::perceptron::Sample;
+use crate::perceptron::{EvalReport, FeatureArena, Normalizers, Sample, TagDictionary};
+#[cfg(feature = "cws")]
 pub use cws::CWSDefinition;
+pub use join::{JoinMode, TokenJoin};
+#[cfg(feature = "ner")]
 pub use ner::NERDefinition;
+#[cfg(feature = "pos")]
 pub use pos::POSDefinition;
+pub use sequence::{
+    CharIndex, CorpusFormat, SequenceDefinition, SequenceDefinitionBuilder, Template, TemplateItem,
+    TemplateValue,
+};
+#[cfg(feature = "serialization")]
+use serde::{Deserialize, Serialize};
+pub use template::TemplateSet;
 
+/// 标注不确定的词，如 `word/_`，训练时不产生更新
+pub const UNCERTAIN: usize = usize::MAX;
+pub const UNCERTAIN_TAG: &str = "_";
+
+/// 全部由空白或控制字符组成的词在特征中替换为该占位符
+pub const BLANK_TOKEN: &str = "\u{2423}";
+
+/// 分词时跳过的字符：不产生特征，也不会出现在词的首尾
+pub fn is_skipped_char(ch: char) -> bool {
+    ch.is_whitespace() || ch.is_control()
+}
+
+/// 按词输入的任务（词性标注、命名实体识别）对词的约定：空词返回 [`LtpError::EmptyToken`]，
+/// 全部由空白或控制字符组成的词替换为 [`BLANK_TOKEN`]，其余的词保持不变
+pub(crate) fn normalize_tokens<'a, 'b>(words: &'b [&'a str]) -> Result<Cow<'b, [&'a str]>> {
+    if let Some(idx) = words.iter().position(|word| word.is_empty()) {
+        return Err(LtpError::EmptyToken(idx).into());
+    }
+    let blank = |word: &str| word.chars().all(is_skipped_char);
+    if !words.iter().any(|word| blank(word)) {
+        return Ok(Cow::Borrowed(words));
+    }
+    Ok(Cow::Owned(
+        words
+            .iter()
+            .map(|&word| if blank(word) { BLANK_TOKEN } else { word })
+            .collect(),
+    ))
+}
+
+/// 训练语料中出现标签表以外的标签时的处理方式，构造定义时选择，不随模型保存
+#[cfg_attr(feature = "serialization", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serialization", serde(rename_all = "kebab-case"))]
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub enum UnknownLabelPolicy {
+    /// 返回 [`LtpError::UnknownLabel`]，读取语料时附带所在的行
+    #[default]
+    Error,
+    /// 视为给定的标签，如 `O` 或 `x`，该标签须在标签表中
+    Map(String),
+    /// 训练器读取语料前把其中的新标签追加到标签表末尾，见 [`Definition::unseen_labels`]
+    Extend,
+}
+
+impl UnknownLabelPolicy {
+    pub(crate) fn resolve(&self, label: &str, labels_to: &HashMap<String, usize>) -> Result<usize> {
+        let label = match (labels_to.get(label), self) {
+            (Some(&index), _) => return Ok(index),
+            (None, UnknownLabelPolicy::Map(unknown)) => unknown.as_str(),
+            (None, _) => label,
+        };
+        labels_to
+            .get(label)
+            .copied()
+            .ok_or_else(|| LtpError::UnknownLabel(label.to_string()).into())
+    }
+}
+
+/// 依次追加不在标签表中的标签
+pub(crate) fn extend_label_table(
+    to_labels: &mut Vec<String>,
+    labels_to: &mut HashMap<String, usize>,
+    labels: &[String],
+) {
+    for label in labels {
+        if label != UNCERTAIN_TAG && !labels_to.contains_key(label) {
+            labels_to.insert(label.clone(), to_labels.len());
+            to_labels.push(label.clone());
+        }
+    }
+}
+
+/// 每行一句、标签在每个词最后一个 `separator` 之后的语料中不在标签表中的标签，按首次出现的顺序
+#[cfg(any(feature = "pos", feature = "ner"))]
+pub(crate) fn unseen_inline_labels<R: Read>(
+    reader: R,
+    separator: char,
+    labels_to: &HashMap<String, usize>,
+) -> Result<Vec<String>> {
+    let mut seen = HashSet::new();
+    let mut labels = Vec::new();
+    for line in BufReader::new(reader).lines() {
+        let line = line?;
+        for token in line.split_whitespace() {
+            let Some((_, label)) = token.rsplit_once(separator) else {
+                continue;
+            };
+            if label != UNCERTAIN_TAG
+                && !labels_to.contains_key(label)
+                && seen.insert(label.to_string())
+            {
+                labels.push(label.to_string());
+            }
+        }
+    }
+    Ok(labels)
+}
+
+/// 统计 `词/标签` 格式的语料中各词出现过的标签，不确定的标签不计入
+#[cfg(feature = "pos")]
+pub(crate) fn collect_inline_tags<R: Read>(
+    reader: R,
+    separator: char,
+    tags: &mut TagDictionary,
+) -> Result<()> {
+    for line in BufReader::new(reader).lines() {
+        let line = line?;
+        let pairs = line
+            .split_whitespace()
+            .filter_map(|token| token.rsplit_once(separator))
+            .filter(|&(_, tag)| tag != UNCERTAIN_TAG);
+        tags.extend(pairs);
+    }
+    Ok(())
+}
+
+/// 把不确定的标签替换为预测结果，使这些位置不产生更新
+pub(crate) fn resolve_uncertain<'a>(labels: &'a [usize], preds: &[usize]) -> Cow<'a, [usize]> {
+    if !labels.contains(&UNCERTAIN) {
+        return Cow::Borrowed(labels);
+    }
+    Cow::Owned(
+        zip(labels, preds)
+            .map(|(&gold, &pred)| if gold == UNCERTAIN { pred } else { gold })
+            .collect(),
+    )
+}
+
+// 仅供内部实现使用，不属于公开接口
+#[doc(hidden)]
 #[macro_export]
 macro_rules! buf_feature {
     ($dst:expr, $feat:tt, $($arg:tt)*) => {
@@ -21,12 +173,34 @@ macro_rules! buf_feature {
     };
 }
 
+/// 按 `ends` 中记录的结束位置切分缓冲区，缓冲区须从 0 开始写入
+pub(crate) fn split_buffer<'a>(
+    buffer: &'a [u8],
+    ends: &FeatureArena<usize>,
+) -> FeatureArena<&'a str> {
+    let mut start = 0usize;
+    ends.map(|&end| {
+        // Safety : all write are valid utf8
+        let feature = unsafe { std::str::from_utf8_unchecked(&buffer[start..end]) };
+        start = end;
+        feature
+    })
+}
+
+pub(crate) fn to_owned_features(features: FeatureArena<&str>) -> FeatureArena<String> {
+    features.map(|&feature| feature.to_string())
+}
+
 pub trait CommonDefinePredict {}
 
+#[cfg(feature = "pos")]
 impl CommonDefinePredict for POSDefinition {}
 
+#[cfg(feature = "ner")]
 impl CommonDefinePredict for NERDefinition {}
 
+impl CommonDefinePredict for SequenceDefinition {}
+
 pub trait GenericItem<'a> {
     type Item;
 }
@@ -44,22 +218,66 @@ pub trait Definition: Default + Debug + Clone {
 
     fn label_num(&self) -> usize;
 
-    fn label_to(&self, label: &str) -> usize;
+    /// 标签不在标签表中时按 [`UnknownLabelPolicy`] 处理，默认返回
+    /// [`LtpError::UnknownLabel`](crate::LtpError::UnknownLabel)
+    fn label_to(&self, label: &str) -> Result<usize>;
 
     fn to_label(&self, index: usize) -> &str;
 
+    fn unknown_label_policy(&self) -> UnknownLabelPolicy {
+        UnknownLabelPolicy::Error
+    }
+
+    /// 训练语料中不在标签表中的标签，按首次出现的顺序；分词等标签固定的任务返回空
+    fn unseen_labels<R: Read>(&self, _reader: R) -> Result<Vec<String>> {
+        Ok(Vec::new())
+    }
+
+    /// 在标签表末尾追加标签，已有标签的下标不变
+    fn extend_labels(&mut self, _labels: &[String]) {}
+
+    /// 统计训练语料中各词出现过的标签，见 [`TagDictionary`]；不按词标注的任务不统计
+    fn collect_tags<R: Read>(&self, _reader: R, _tags: &mut TagDictionary) -> Result<()> {
+        Ok(())
+    }
+
+    /// 字级处理的单元切分方式，按词输入的任务不使用
+    fn token_join(&self) -> TokenJoin {
+        TokenJoin::default()
+    }
+
+    /// 由模型的元信息恢复训练时的切分方式
+    fn set_token_join(&mut self, _join: TokenJoin) {}
+
+    /// 特征抽取前对字或词的规范化
+    fn normalizers(&self) -> Normalizers {
+        Normalizers::default()
+    }
+
+    /// 由模型的元信息恢复训练时的规范化
+    fn set_normalizers(&mut self, _normalizers: Normalizers) {}
+
+    /// 特征缓冲区的预估大小，复用同一个缓冲区时稳定后不再重新分配
+    fn buffer_capacity(&self, _raw: &<Self::RawFeature as GenericItem>::Item) -> usize {
+        0
+    }
+
     #[allow(clippy::type_complexity)]
     fn parse_features(
         &self,
         raw: &<Self::RawFeature as GenericItem>::Item,
-    ) -> Result<(<Self::Fragment as GenericItem>::Item, Vec<Vec<String>>)>;
+    ) -> Result<(<Self::Fragment as GenericItem>::Item, FeatureArena<String>)> {
+        let mut buffer = Vec::with_capacity(self.buffer_capacity(raw));
+        let (fragment, features) = self.parse_features_with_buffer(raw, &mut buffer)?;
+        Ok((fragment, to_owned_features(features)))
+    }
 
     #[allow(clippy::type_complexity)]
     fn parse_features_with_buffer<'a>(
         &self,
         raw: &<Self::RawFeature as GenericItem>::Item,
         buf: &'a mut Vec<u8>,
-    ) -> Result<(<Self::Fragment as GenericItem>::Item, Vec<Vec<&'a str>>)>;
+    ) -> Result<(<Self::Fragment as GenericItem>::Item, FeatureArena<&'a str>)>;
 
     fn parse_gold_features<R: Read>(&self, reader: R) -> Result<Vec<Sample>>;
 
@@ -98,4 +316,58 @@ pub trait Definition: Default + Debug + Clone {
         let correct = predicts.intersection(&labels).count();
         (correct, predicts.len(), labels.len())
     }
+
+    /// 在 `evaluate` 的基础上统计准确率与每个标签的指标，累加到 `report`
+    fn evaluate_report(&self, predicts: &[usize], labels: &[usize], report: &mut EvalReport) {
+        self.evaluate_tags_report(predicts, labels, report)
+    }
+
+    /// 每个标签的指标
+    fn evaluate_tags_report(&self, predicts: &[usize], labels: &[usize], report: &mut EvalReport) {
+        add_overall(report, self.evaluate(predicts, labels), predicts, labels);
+        for (&p, &l) in zip(predicts, labels) {
+            if p == l {
+                report.label(self.to_label(l)).correct += 1;
+            }
+            report.label(self.to_label(p)).predicted += 1;
+            report.label(self.to_label(l)).gold += 1;
+        }
+    }
+
+    /// 每个实体类型的指标
+    fn evaluate_entities_report(
+        &self,
+        predicts: &[usize],
+        labels: &[usize],
+        report: &mut EvalReport,
+    ) {
+        add_overall(report, self.evaluate(predicts, labels), predicts, labels);
+        let predicts = self.to_labels(predicts);
+        let labels = self.to_labels(labels);
+
+        let predicts: HashSet<_> = get_entities(&predicts).into_iter().collect();
+        let labels: HashSet<_> = get_entities(&labels).into_iter().collect();
+        for entity in &predicts {
+            report.label(entity.0).predicted += 1;
+            if labels.contains(entity) {
+                report.label(entity.0).correct += 1;
+            }
+        }
+        for entity in &labels {
+            report.label(entity.0).gold += 1;
+        }
+    }
+}
+
+fn add_overall(
+    report: &mut EvalReport,
+    (correct, predicted, gold): (usize, usize, usize),
+    predicts: &[usize],
+    labels: &[usize],
+) {
+    report.overall.correct += correct;
+    report.overall.predicted += predicted;
+    report.overall.gold += gold;
+    report.tokens += labels.len();
+    report.tokens_correct += zip(predicts, labels).filter(|(p, l)| p == l).count();
 }
@@ -1,7 +1,12 @@
-use crate::perceptron::definition::GenericItem;
-use crate::perceptron::{Definition, Sample};
+use crate::error::LtpError;
+use crate::perceptron::definition::{
+    extend_label_table, normalize_tokens, split_buffer, to_owned_features, unseen_inline_labels,
+    GenericItem, UnknownLabelPolicy, UNCERTAIN, UNCERTAIN_TAG,
+};
+use crate::perceptron::{EmbeddingFeatures, FeatureArena, Normalizers};
+use crate::perceptron::{Definition, EvalReport, Sample};
 use crate::buf_feature;
-use anyhow::Result;
+use anyhow::{Context, Result};
 use itertools::Itertools;
 #[cfg(feature = "parallel")]
 use rayon::prelude::*;
@@ -9,13 +14,27 @@ use rayon::prelude::*;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::io::{BufRead, BufReader, Read, Write};
+use std::sync::Arc;
 
 
 #[cfg_attr(feature = "serialization", derive(Serialize, Deserialize))]
 #[derive(Default, Debug, Clone, PartialEq, Eq)]
 pub struct NERDefinition {
     to_labels: Vec<String>,
+    #[cfg_attr(
+        feature = "serialization",
+        serde(serialize_with = "crate::perceptron::serialize_sorted")
+    )]
     labels_to: HashMap<String, usize>,
+    /// 离散化的预训练词向量特征，不随模型保存
+    #[cfg_attr(feature = "serialization", serde(skip))]
+    embeddings: Option<Arc<EmbeddingFeatures>>,
+    /// 特征抽取前的规范化，不随定义保存，由模型的元信息恢复
+    #[cfg_attr(feature = "serialization", serde(skip))]
+    normalizers: Normalizers,
+    /// 训练语料中未知标签的处理方式，只在训练时使用
+    #[cfg_attr(feature = "serialization", serde(skip))]
+    unknown_labels: UnknownLabelPolicy,
 }
 
 impl NERDefinition {
@@ -28,9 +47,74 @@ impl NERDefinition {
         NERDefinition {
             labels_to,
             to_labels,
+            embeddings: None,
+            normalizers: Normalizers::default(),
+            unknown_labels: UnknownLabelPolicy::default(),
         }
     }
 
+    pub fn with_unknown_labels(mut self, policy: UnknownLabelPolicy) -> Self {
+        self.unknown_labels = policy;
+        self
+    }
+
+    /// 抽取特征前规范化各词，训练与预测一致
+    pub fn with_normalizers(mut self, normalizers: Normalizers) -> Self {
+        self.normalizers = normalizers;
+        self
+    }
+
+    /// 抽取特征时加入离散化的预训练词向量特征
+    pub fn embeddings(mut self, embeddings: Arc<EmbeddingFeatures>) -> Self {
+        self.embeddings = Some(embeddings);
+        self
+    }
+
+    /// 加载模型后重新设置训练时使用的词向量特征
+    pub fn set_embeddings(&mut self, embeddings: Option<Arc<EmbeddingFeatures>>) {
+        self.embeddings = embeddings;
+    }
+
+    /// 标签的下标，不在标签表中时返回 `None`
+    pub fn get_label(&self, label: &str) -> Option<usize> {
+        self.labels_to.get(label).copied()
+    }
+
+    /// 抽取特征前预先算出缓冲区的字节数与特征数，与
+    /// [`parse_words_features_with_buffer`](Self::parse_words_features_with_buffer) 写入的完全一致，
+    /// 不含词向量特征；词与词性长度不同时只计算较短的部分
+    pub fn feature_size(&self, words: &[&str], poses: &[&str]) -> (usize, usize) {
+        let len = words.len().min(poses.len());
+        let (words, poses) = (&words[..len], &poses[..len]);
+        let (mut bytes, mut count) = (0, 0);
+        for (idx, (&cur, &pos)) in words.iter().zip(poses).enumerate() {
+            let last = words.len() - idx - 1;
+            bytes += 2 + cur.len() + pos.len();
+            count += 2;
+            if idx > 0 {
+                let (pre, pre_pos) = (words[idx - 1], poses[idx - 1]);
+                bytes += 4 + 2 * pre.len() + cur.len() + 2 * pre_pos.len() + pos.len();
+                count += 4;
+                if idx > 1 {
+                    let pre2 = words[idx - 2];
+                    bytes += 4 + 3 * pre2.len() + pre.len() + cur.len() + poses[idx - 2].len();
+                    count += 4;
+                }
+            }
+            if last > 0 {
+                let (next, next_pos) = (words[idx + 1], poses[idx + 1]);
+                bytes += 4 + 2 * next.len() + cur.len() + 2 * next_pos.len() + pos.len();
+                count += 4;
+                if last > 1 {
+                    let next2 = words[idx + 2];
+                    bytes += 4 + 3 * next2.len() + next.len() + cur.len() + poses[idx + 2].len();
+                    count += 4;
+                }
+            }
+        }
+        (bytes, count)
+    }
+
     /// +----------------+-----------------------------------------------------------+
     // | 类别           | 特征                                                        |
     // +================+============================================================+
@@ -42,10 +126,24 @@ impl NERDefinition {
     // +----------------+------------------------------------------------------------+
     // | postag-bigram  | p[-1]p[0],p[0]p[1]                                         |
     // +----------------+------------------------------------------------------------+
-    pub fn parse_words_features_with_buffer<'a>(&self, words: &[&str], poses: &[&str], buffer: &'a mut Vec<u8>) -> Result<Vec<Vec<usize>>> {
+    pub fn parse_words_features_with_buffer<'a>(
+        &self,
+        words: &[&str],
+        poses: &[&str],
+        buffer: &'a mut Vec<u8>,
+    ) -> Result<FeatureArena<usize>> {
+        if words.len() != poses.len() {
+            return Err(LtpError::LengthMismatch(words.len(), poses.len()).into());
+        }
+        let words = normalize_tokens(words)?;
+        let normalized = self.normalizers.apply_all(&words)?;
+        let words: Vec<&str> = normalized.iter().map(AsRef::as_ref).collect();
+        buffer.clear();
         let word_null = "";
         let words_len = words.len();
-        let mut features = Vec::with_capacity(words_len);
+        let (bytes, count) = self.feature_size(&words, poses);
+        buffer.reserve_exact(bytes);
+        let mut features = FeatureArena::with_capacity(words_len, count);
 
         for (idx, &cur_word) in words.iter().enumerate() {
             // 剩余字符数
@@ -55,92 +153,100 @@ impl NERDefinition {
             let next_word = if last > 0 { words[idx + 1] } else { word_null };
             let next2_word = if last > 1 { words[idx + 2] } else { word_null };
 
-            // todo: 优化容量设置
-            let mut feature = Vec::with_capacity(18);
-
             // w[0]
-            buf_feature!(buffer, feature, "2{}", words[idx]);
+            buf_feature!(buffer, features, "2{}", words[idx]);
 
             // p[0]
-            buf_feature!(buffer, feature, "d{}", poses[idx]);
+            buf_feature!(buffer, features, "d{}", poses[idx]);
 
             if idx > 0 {
-                buf_feature!(buffer, feature, "1{}", pre_word);
+                buf_feature!(buffer, features, "1{}", pre_word);
                 // w[-1]
-                buf_feature!(buffer, feature, "6{}{}", pre_word, cur_word);
+                buf_feature!(buffer, features, "6{}{}", pre_word, cur_word);
                 // w[-1]w[0]
-                buf_feature!(buffer, feature, "c{}", poses[idx - 1]);
+                buf_feature!(buffer, features, "c{}", poses[idx - 1]);
                 // p[-1]
-                buf_feature!(buffer, feature, "g{}{}", poses[idx - 1], poses[idx]); // p[-1]p[0]
+                buf_feature!(buffer, features, "g{}{}", poses[idx - 1], poses[idx]); // p[-1]p[0]
                 if idx > 1 {
-                    buf_feature!(buffer, feature, "0{}", pre2_word);
+                    buf_feature!(buffer, features, "0{}", pre2_word);
                     // w[-2]
-                    buf_feature!(buffer, feature, "5{}{}", pre2_word, pre_word);
+                    buf_feature!(buffer, features, "5{}{}", pre2_word, pre_word);
                     // w[-2]w[-1]
-                    buf_feature!(buffer, feature, "9{}{}", pre2_word, cur_word); // w[-2]w[0]
+                    buf_feature!(buffer, features, "9{}{}", pre2_word, cur_word); // w[-2]w[0]
 
-                    buf_feature!(buffer, feature, "b{}", poses[idx - 2]); // p[-2]
+                    buf_feature!(buffer, features, "b{}", poses[idx - 2]); // p[-2]
                 }
             }
 
             if last > 0 {
-                buf_feature!(buffer, feature, "3{}", next_word);
+                buf_feature!(buffer, features, "3{}", next_word);
                 // w[+1]
-                buf_feature!(buffer, feature, "7{}{}", cur_word, next_word);
+                buf_feature!(buffer, features, "7{}{}", cur_word, next_word);
                 // w[0]w[+1]
-                buf_feature!(buffer, feature, "e{}", poses[idx + 1]);
+                buf_feature!(buffer, features, "e{}", poses[idx + 1]);
                 // p[+1]
-                buf_feature!(buffer, feature, "h{}{}", poses[idx], poses[idx + 1]); // p[0]p[+1]
+                buf_feature!(buffer, features, "h{}{}", poses[idx], poses[idx + 1]); // p[0]p[+1]
                 if last > 1 {
-                    buf_feature!(buffer, feature, "4{}", next2_word);
+                    buf_feature!(buffer, features, "4{}", next2_word);
                     // w[+2]
-                    buf_feature!(buffer, feature, "8{}{}", next_word, next2_word);
+                    buf_feature!(buffer, features, "8{}{}", next_word, next2_word);
                     // w[+1]w[+2]
-                    buf_feature!(buffer, feature, "a{}{}", cur_word, next2_word);
+                    buf_feature!(buffer, features, "a{}{}", cur_word, next2_word);
                     // w[0]w[+2]
-                    buf_feature!(buffer, feature, "f{}", poses[idx + 2]); // p[+2]
+                    buf_feature!(buffer, features, "f{}", poses[idx + 2]); // p[+2]
                 }
             }
 
-            features.push(feature);
+            if let Some(embeddings) = &self.embeddings {
+                embeddings.write(buffer, &mut features, &words, idx)?;
+            }
+            features.end_token();
         }
-
+        debug_assert!(self.embeddings.is_some() || buffer.len() == bytes);
         Ok(features)
     }
 
-    pub fn parse_words_features(&self, words: &[&str], poses: &[&str]) -> Result<Vec<Vec<String>>> {
-        let mut buffer = Vec::with_capacity(words.len() * 150);
-        let features = self.parse_words_features_with_buffer(words, poses, &mut buffer)?;
-
-        let mut start = 0usize;
-        let mut result = Vec::with_capacity(features.len());
-        for feature_end in features {
-            let mut feature = Vec::with_capacity(feature_end.len());
-            for end in feature_end {
-                // Safety : all write are valid utf8
-                feature.push(String::from_utf8_lossy(&buffer[start..end]).to_string());
-                start = end;
-            }
-            result.push(feature);
-        }
-        Ok(result)
+    pub fn parse_words_features(
+        &self,
+        words: &[&str],
+        poses: &[&str],
+    ) -> Result<FeatureArena<String>> {
+        let mut buffer = Vec::with_capacity(self.buffer_capacity(&(words, poses)));
+        let features = self.parse_words_features_with_buffer_str(words, poses, &mut buffer)?;
+        Ok(to_owned_features(features))
     }
 
-    pub fn parse_words_features_with_buffer_str<'a>(&self, words: &[&str], poses: &[&str], buffer: &'a mut Vec<u8>) -> Result<Vec<Vec<&'a str>>> {
+    pub fn parse_words_features_with_buffer_str<'a>(
+        &self,
+        words: &[&str],
+        poses: &[&str],
+        buffer: &'a mut Vec<u8>,
+    ) -> Result<FeatureArena<&'a str>> {
         let features = self.parse_words_features_with_buffer(words, poses, buffer)?;
+        Ok(split_buffer(buffer, &features))
+    }
 
-        let mut start = 0usize;
-        let mut result = Vec::with_capacity(features.len());
-        for feature_end in features {
-            let mut feature = Vec::with_capacity(feature_end.len());
-            for end in feature_end {
-                // Safety : all write are valid utf8
-                feature.push(unsafe { std::str::from_utf8_unchecked(&buffer[start..end]) });
-                start = end;
-            }
-            result.push(feature);
+    /// 训练语料中的一行，每个词为 `词/词性/实体`
+    fn gold_sample(&self, sentence: &str) -> Result<Sample> {
+        let words_tags = sentence.split_whitespace().collect_vec();
+
+        let mut words = Vec::with_capacity(words_tags.len());
+        let mut poses = Vec::with_capacity(words_tags.len());
+        let mut labels = Vec::with_capacity(words_tags.len());
+        for word_tag in words_tags {
+            let result = word_tag.rsplitn(3, '/');
+            let (label, pos, word) = result
+                .collect_tuple()
+                .ok_or_else(|| LtpError::MalformedToken(word_tag.to_string(), "word/pos/label"))?;
+            words.push(word);
+            poses.push(pos);
+            labels.push(match label {
+                UNCERTAIN_TAG => UNCERTAIN,
+                label => self.label_to(label)?,
+            });
         }
-        Ok(result)
+        self.parse_words_features(&words, &poses)
+            .map(|features| Sample::new(features, labels))
     }
 }
 
@@ -162,29 +268,44 @@ impl Definition for NERDefinition {
         self.to_labels.len()
     }
 
-    fn label_to(&self, label: &str) -> usize {
-        self.labels_to[label]
+    fn label_to(&self, label: &str) -> Result<usize> {
+        self.unknown_labels.resolve(label, &self.labels_to)
     }
 
     fn to_label(&self, index: usize) -> &str {
         &self.to_labels[index]
     }
 
-    fn parse_features(
-        &self,
-        line: &<Self::RawFeature as GenericItem>::Item,
-    ) -> Result<((), Vec<Vec<String>>)> {
-        let (words, features) = line;
-        let features = self.parse_words_features(words, features)?;
+    fn unknown_label_policy(&self) -> UnknownLabelPolicy {
+        self.unknown_labels.clone()
+    }
 
-        Ok(((), features))
+    fn unseen_labels<R: Read>(&self, reader: R) -> Result<Vec<String>> {
+        unseen_inline_labels(reader, '/', &self.labels_to)
+    }
+
+    fn extend_labels(&mut self, labels: &[String]) {
+        extend_label_table(&mut self.to_labels, &mut self.labels_to, labels);
+    }
+
+    fn normalizers(&self) -> Normalizers {
+        self.normalizers.clone()
+    }
+
+    fn set_normalizers(&mut self, normalizers: Normalizers) {
+        self.normalizers = normalizers;
+    }
+
+    /// 规范化不改变词长时是准确的
+    fn buffer_capacity(&self, line: &<Self::RawFeature as GenericItem>::Item) -> usize {
+        self.feature_size(line.0, line.1).0
     }
 
     fn parse_features_with_buffer<'a>(
         &self,
         line: &<Self::RawFeature as GenericItem>::Item,
         buf: &'a mut Vec<u8>,
-    ) -> Result<((), Vec<Vec<&'a str>>)> {
+    ) -> Result<((), FeatureArena<&'a str>)> {
         let (words, features) = line;
         let features = self.parse_words_features_with_buffer_str(words, features, buf)?;
         Ok(((), features))
@@ -193,24 +314,17 @@ impl Definition for NERDefinition {
     #[cfg(feature = "parallel")]
     fn parse_gold_features<R: Read>(&self, reader: R) -> Result<Vec<Sample>> {
         let lines = BufReader::new(reader).lines();
-        let lines = lines.flatten().filter(|s| !s.is_empty()).collect_vec();
+        let lines = lines
+            .flatten()
+            .enumerate()
+            .filter(|(_, s)| !s.is_empty())
+            .collect_vec();
 
         lines
             .par_iter()
-            .map(|sentence| {
-                let words_tags = sentence.split_whitespace().collect_vec();
-
-                let mut words = Vec::with_capacity(words_tags.len());
-                let mut poses = Vec::with_capacity(words_tags.len());
-                let mut labels = Vec::with_capacity(words_tags.len());
-                for word_tag in words_tags {
-                    let result = word_tag.rsplitn(3, '/');
-                    let (label, pos, word) = result.collect_tuple().expect("tag not found");
-                    words.push(word);
-                    poses.push(pos);
-                    labels.push(self.label_to(label));
-                }
-                self.parse_words_features(&words, &poses).map(|features| { (features, labels) })
+            .map(|(index, sentence)| {
+                self.gold_sample(sentence)
+                    .with_context(|| format!("line {}", index + 1))
             })
             .collect()
     }
@@ -218,24 +332,17 @@ impl Definition for NERDefinition {
     #[cfg(not(feature = "parallel"))]
     fn parse_gold_features<R: Read>(&self, reader: R) -> Result<Vec<Sample>> {
         let lines = BufReader::new(reader).lines();
-        let lines = lines.flatten().filter(|s| !s.is_empty()).collect_vec();
+        let lines = lines
+            .flatten()
+            .enumerate()
+            .filter(|(_, s)| !s.is_empty())
+            .collect_vec();
 
         lines
             .iter()
-            .map(|sentence| {
-                let words_tags = sentence.split_whitespace().collect_vec();
-
-                let mut words = Vec::with_capacity(words_tags.len());
-                let mut poses = Vec::with_capacity(words_tags.len());
-                let mut labels = Vec::with_capacity(words_tags.len());
-                for word_tag in words_tags {
-                    let result = word_tag.rsplitn(3, '/');
-                    let (label, pos, word) = result.collect_tuple().expect("tag not found");
-                    words.push(word);
-                    poses.push(pos);
-                    labels.push(self.label_to(label));
-                }
-                self.parse_words_features(&words, &poses).map(|features| { (features, labels) })
+            .map(|(index, sentence)| {
+                self.gold_sample(sentence)
+                    .with_context(|| format!("line {}", index + 1))
             })
             .collect()
     }
@@ -252,6 +359,10 @@ impl Definition for NERDefinition {
     fn evaluate(&self, predicts: &[usize], labels: &[usize]) -> (usize, usize, usize) {
         self.evaluate_entities(predicts, labels)
     }
+
+    fn evaluate_report(&self, predicts: &[usize], labels: &[usize], report: &mut EvalReport) {
+        self.evaluate_entities_report(predicts, labels, report)
+    }
 }
 
 
@@ -270,7 +381,7 @@ mod tests {
         let no_buffer = define.parse_words_features(&sentence, &pos)?;
         let with_buffer = define.parse_words_features_with_buffer_str(&sentence, &pos, &mut buffer)?;
 
-        for (a, b) in zip(no_buffer, with_buffer) {
+        for (a, b) in zip(&no_buffer, &with_buffer) {
             for (c, d) in zip(a, b) {
                 assert_eq!(c, d);
             }
@@ -1,7 +1,12 @@
-use crate::perceptron::definition::GenericItem;
-use crate::perceptron::{Definition, Sample};
+use crate::error::LtpError;
+use crate::perceptron::definition::{
+    collect_inline_tags, extend_label_table, normalize_tokens, split_buffer, to_owned_features,
+    unseen_inline_labels, GenericItem, UnknownLabelPolicy, UNCERTAIN, UNCERTAIN_TAG,
+};
+use crate::perceptron::{EmbeddingFeatures, FeatureArena, Normalizers};
+use crate::perceptron::{Definition, Sample, TagDictionary};
 use crate::buf_feature;
-use anyhow::Result;
+use anyhow::{Context, Result};
 use itertools::Itertools;
 #[cfg(feature = "parallel")]
 use rayon::prelude::*;
@@ -10,12 +15,81 @@ use serde::{Deserialize, Serialize};
 use smallvec::SmallVec;
 use std::collections::HashMap;
 use std::io::{BufRead, BufReader, Read, Write};
+use std::sync::Arc;
 
 #[cfg_attr(feature = "serialization", derive(Serialize, Deserialize))]
 #[derive(Default, Debug, Clone, PartialEq, Eq)]
 pub struct POSDefinition {
     to_labels: Vec<String>,
+    #[cfg_attr(
+        feature = "serialization",
+        serde(serialize_with = "crate::perceptron::serialize_sorted")
+    )]
     labels_to: HashMap<String, usize>,
+    /// 离散化的预训练词向量特征，不随模型保存
+    #[cfg_attr(feature = "serialization", serde(skip))]
+    embeddings: Option<Arc<EmbeddingFeatures>>,
+    /// 特征抽取前的规范化，不随定义保存，由模型的元信息恢复
+    #[cfg_attr(feature = "serialization", serde(skip))]
+    normalizers: Normalizers,
+    /// 训练语料中未知标签的处理方式，只在训练时使用
+    #[cfg_attr(feature = "serialization", serde(skip))]
+    unknown_labels: UnknownLabelPolicy,
+}
+
+/// 首字的字节数
+fn first_len(word: &str) -> usize {
+    word.chars().next().map_or(0, char::len_utf8)
+}
+
+/// 末字的字节数
+fn last_len(word: &str) -> usize {
+    word.chars().next_back().map_or(0, char::len_utf8)
+}
+
+/// 前 `n` 个字的字节数
+fn prefix_len(word: &str, n: usize) -> usize {
+    word.char_indices()
+        .nth(n)
+        .map_or(word.len(), |(end, _)| end)
+}
+
+/// 后 `n` 个字的字节数
+fn suffix_len(word: &str, n: usize) -> usize {
+    let start = word.char_indices().rev().nth(n - 1);
+    word.len() - start.map_or(0, |(start, _)| start)
+}
+
+/// 十进制表示的位数
+fn digits(n: usize) -> usize {
+    n.checked_ilog10().map_or(1, |d| d as usize + 1)
+}
+
+/// 只与当前词有关、不随上下文变化的特征：w[0]、ch[0,0]ch[0,n]、长度、前缀与后缀
+fn write_word_features(
+    buffer: &mut Vec<u8>,
+    features: &mut FeatureArena<usize>,
+    word: &str,
+    chars: &[char],
+) -> Result<()> {
+    // w[0]
+    buf_feature!(buffer, features, "2{}", word);
+    // ch[0,0]ch[0,n]
+    buf_feature!(buffer, features, "c{}{}", chars[0], chars[chars.len() - 1]);
+    // length
+    buf_feature!(buffer, features, "f{}", chars.len());
+    // prefix => ch[0,0]ch[0,0:1]ch[0,0:2]
+
+    let prefix_id = &['c', 'd', 'e'];
+    for (bias, prefix) in chars.iter().take(3).enumerate() {
+        buf_feature!(buffer, features, "{}{}", prefix_id[bias], prefix);
+    }
+    // suffix => ch[0,n-2:n],ch[0,n-1:n],ch[0,n]
+    let suffix_id = &['f', 'g', 'h'];
+    for (bias, suffix) in chars.iter().rev().take(3).enumerate() {
+        buf_feature!(buffer, features, "{}{}", suffix_id[bias], suffix);
+    }
+    Ok(())
 }
 
 impl POSDefinition {
@@ -28,7 +102,78 @@ impl POSDefinition {
         POSDefinition {
             labels_to,
             to_labels,
+            embeddings: None,
+            normalizers: Normalizers::default(),
+            unknown_labels: UnknownLabelPolicy::default(),
+        }
+    }
+
+    pub fn with_unknown_labels(mut self, policy: UnknownLabelPolicy) -> Self {
+        self.unknown_labels = policy;
+        self
+    }
+
+    /// 抽取特征前规范化各词，训练与预测一致
+    pub fn with_normalizers(mut self, normalizers: Normalizers) -> Self {
+        self.normalizers = normalizers;
+        self
+    }
+
+    /// 抽取特征时加入离散化的预训练词向量特征
+    pub fn embeddings(mut self, embeddings: Arc<EmbeddingFeatures>) -> Self {
+        self.embeddings = Some(embeddings);
+        self
+    }
+
+    /// 加载模型后重新设置训练时使用的词向量特征
+    pub fn set_embeddings(&mut self, embeddings: Option<Arc<EmbeddingFeatures>>) {
+        self.embeddings = embeddings;
+    }
+
+    /// 标签的下标，不在标签表中时返回 `None`
+    pub fn get_label(&self, label: &str) -> Option<usize> {
+        self.labels_to.get(label).copied()
+    }
+
+    /// 抽取 `words` 的特征前预先算出缓冲区的字节数与特征数，与
+    /// [`parse_words_features_with_buffer`](Self::parse_words_features_with_buffer) 写入的完全一致，
+    /// 不含词向量特征
+    pub fn feature_size(&self, words: &[&str]) -> (usize, usize) {
+        let (mut bytes, mut count) = (0, 0);
+        for (idx, &cur) in words.iter().enumerate() {
+            let last = words.len() - idx - 1;
+            let chars = cur.chars().count();
+            let affixes = chars.min(3);
+            // w[0]、ch[0,0]ch[0,n]、length、prefix、suffix
+            bytes += 3 + cur.len() + first_len(cur) + last_len(cur) + digits(chars);
+            bytes += 2 * affixes + prefix_len(cur, 3) + suffix_len(cur, 3);
+            count += 3 + 2 * affixes;
+            if idx > 0 {
+                let pre = words[idx - 1];
+                bytes += 3 + 2 * pre.len() + cur.len() + last_len(pre) + first_len(cur);
+                count += 3;
+                if idx > 1 {
+                    let pre2 = words[idx - 2];
+                    bytes += 3 + 3 * pre2.len() + pre.len() + cur.len();
+                    count += 3;
+                }
+            }
+            if last > 0 {
+                let next = words[idx + 1];
+                bytes += 3 + 2 * next.len() + cur.len() + last_len(cur) + first_len(next);
+                count += 3;
+                if last > 1 {
+                    let next2 = words[idx + 2];
+                    bytes += 3 + 3 * next2.len() + next.len() + cur.len();
+                    count += 3;
+                }
+            }
+            if idx > 0 && last > 0 {
+                bytes += 1 + words[idx - 1].len() + cur.len() + words[idx + 1].len();
+                count += 1;
+            }
         }
+        (bytes, count)
     }
 
     /// +----------------------+----------------------------------------------------------+
@@ -48,10 +193,33 @@ impl POSDefinition {
     // +----------------------+-----------------------------------------------------------+
     // | suffix               | ch[0,n-2:n],ch[0,n-1:n],ch[0,n]                           |
     // +----------------------+-----------------------------------------------------------+
-    pub fn parse_words_features_with_buffer<'a>(&self, words: &[&str], buffer: &'a mut Vec<u8>) -> Result<Vec<Vec<usize>>> {
+    pub fn parse_words_features_with_buffer<'a>(
+        &self,
+        words: &[&str],
+        buffer: &'a mut Vec<u8>,
+    ) -> Result<FeatureArena<usize>> {
+        self.parse_context_features_with_buffer(words, buffer, |_| false)
+    }
+
+    /// 同 [`parse_words_features_with_buffer`](Self::parse_words_features_with_buffer)，
+    /// 但 `cached` 返回 `true` 的词（规范化后）不写入只与词本身有关的特征，由调用方从缓存中补上，
+    /// 见 [`WordFeatureCache`](crate::perceptron::WordFeatureCache)
+    pub fn parse_context_features_with_buffer<F: FnMut(&str) -> bool>(
+        &self,
+        words: &[&str],
+        buffer: &mut Vec<u8>,
+        mut cached: F,
+    ) -> Result<FeatureArena<usize>> {
+        let words = normalize_tokens(words)?;
+        let normalized = self.normalizers.apply_all(&words)?;
+        let words: Vec<&str> = normalized.iter().map(AsRef::as_ref).collect();
+        buffer.clear();
         let word_null = "";
         let words_len = words.len();
-        let mut features = Vec::with_capacity(words_len);
+        let (bytes, count) = self.feature_size(&words);
+        buffer.reserve_exact(bytes);
+        let mut features = FeatureArena::with_capacity(words_len, count);
+        let mut skipped = false;
 
         let chars = words
             .iter()
@@ -66,114 +234,122 @@ impl POSDefinition {
             let next_word = if last > 0 { words[idx + 1] } else { word_null };
             let next2_word = if last > 1 { words[idx + 2] } else { word_null };
 
-            // todo: 优化容量设置
-            let mut feature = Vec::with_capacity(22);
-
-            // w[0]
-            buf_feature!(buffer, feature, "2{}", words[idx]);
-            // ch[0,0]ch[0,n]
-            buf_feature!(buffer, feature, "c{}{}", chars[idx][0], chars[idx][chars[idx].len() - 1]);
-            // length
-            buf_feature!(buffer, feature, "f{}", chars[idx].len());
-            // prefix => ch[0,0]ch[0,0:1]ch[0,0:2]
-
-            let prefix_id = &['c', 'd', 'e'];
-            for (bias, prefix) in chars[idx]
-                .iter()
-                .take(3)
-                .enumerate()
-            {
-                buf_feature!(buffer, feature, "{}{}", prefix_id[bias], prefix);
-            };
-            // suffix => ch[0,n-2:n],ch[0,n-1:n],ch[0,n]
-            let suffix_id = &['f', 'g', 'h'];
-            for (bias, suffix) in chars[idx]
-                .iter()
-                .rev()
-                .take(3)
-                .enumerate()
-            {
-                buf_feature!(buffer, feature, "{}{}", suffix_id[bias], suffix);
-            };
+            if cached(cur_word) {
+                skipped = true;
+            } else {
+                write_word_features(buffer, &mut features, cur_word, &chars[idx])?;
+            }
 
             if idx > 0 {
                 // w[-1]
-                buf_feature!(buffer, feature, "1{}", pre_word);
+                buf_feature!(buffer, features, "1{}", pre_word);
                 // w[-1]w[0]
-                buf_feature!(buffer, feature, "6{}{}", pre_word, cur_word);
+                buf_feature!(buffer, features, "6{}{}", pre_word, cur_word);
                 // ch[-1,n]ch[0,0]
-                buf_feature!(buffer, feature, "d{}{}", chars[idx - 1][chars[idx - 1].len() - 1], chars[idx][0]);
+                buf_feature!(
+                    buffer,
+                    features,
+                    "d{}{}",
+                    chars[idx - 1][chars[idx - 1].len() - 1],
+                    chars[idx][0]
+                );
 
                 if idx > 1 {
                     // w[-2]
-                    buf_feature!(buffer, feature, "0{}", pre2_word);
+                    buf_feature!(buffer, features, "0{}", pre2_word);
                     // w[-2]w[-1]
-                    buf_feature!(buffer, feature, "5{}{}", pre2_word, pre_word);
+                    buf_feature!(buffer, features, "5{}{}", pre2_word, pre_word);
                     // w[-2]w[0]
-                    buf_feature!(buffer, feature, "9{}{}", pre2_word, cur_word);
+                    buf_feature!(buffer, features, "9{}{}", pre2_word, cur_word);
                 }
             }
 
             if last > 0 {
                 // w[+1]
-                buf_feature!(buffer, feature, "3{}", next_word);
+                buf_feature!(buffer, features, "3{}", next_word);
                 // w[0]w[+1]
-                buf_feature!(buffer, feature, "7{}{}", cur_word, next_word);
+                buf_feature!(buffer, features, "7{}{}", cur_word, next_word);
                 // ch[0,-1]ch[1,0]
-                buf_feature!(buffer, feature, "e{}{}", chars[idx][chars[idx].len() - 1], chars[idx + 1][0]);
+                buf_feature!(
+                    buffer,
+                    features,
+                    "e{}{}",
+                    chars[idx][chars[idx].len() - 1],
+                    chars[idx + 1][0]
+                );
 
                 if last > 1 {
                     // w[+2]
-                    buf_feature!(buffer, feature, "4{}", next2_word);
+                    buf_feature!(buffer, features, "4{}", next2_word);
                     // w[+1]w[+2]
-                    buf_feature!(buffer, feature, "8{}{}", next_word, next2_word);
+                    buf_feature!(buffer, features, "8{}{}", next_word, next2_word);
                     // w[0]w[+2]
-                    buf_feature!(buffer, feature, "a{}{}", cur_word, next2_word);
+                    buf_feature!(buffer, features, "a{}{}", cur_word, next2_word);
                 }
             }
 
             if idx > 0 && last > 0 {
                 // w[-1]w[0]w[+1]
-                buf_feature!(buffer, feature, "b{}{}{}", pre_word, cur_word, next_word);
+                buf_feature!(buffer, features, "b{}{}{}", pre_word, cur_word, next_word);
+            }
+            if let Some(embeddings) = &self.embeddings {
+                embeddings.write(buffer, &mut features, &words, idx)?;
             }
-            features.push(feature);
+            features.end_token();
         }
+        debug_assert!(self.embeddings.is_some() || skipped || buffer.len() == bytes);
         Ok(features)
     }
 
-    pub fn parse_words_features(&self, words: &[&str]) -> Result<Vec<Vec<String>>> {
-        let mut buffer = Vec::with_capacity(words.len() * 180);
-        let features = self.parse_words_features_with_buffer(words, &mut buffer)?;
+    /// 规范化后的词与只与词本身有关的特征，用于预先填充 [`WordFeatureCache`](crate::perceptron::WordFeatureCache)
+    pub fn word_features(&self, word: &str) -> Result<(String, Vec<String>)> {
+        let words = [word];
+        let words = normalize_tokens(&words)?;
+        let word = self.normalizers.apply(words[0])?;
+        let chars = word.chars().collect_vec();
+        let mut buffer = Vec::new();
+        let mut features = FeatureArena::new();
+        write_word_features(&mut buffer, &mut features, &word, &chars)?;
+        features.end_token();
+        let features = split_buffer(&buffer, &features);
+        let features = features.iter().flatten().map(|s| s.to_string()).collect();
+        Ok((word.into_owned(), features))
+    }
 
-        let mut start = 0usize;
-        let mut result = Vec::with_capacity(features.len());
-        for feature_end in features {
-            let mut feature = Vec::with_capacity(feature_end.len());
-            for end in feature_end {
-                // Safety : all write are valid utf8
-                feature.push(String::from_utf8_lossy(&buffer[start..end]).to_string());
-                start = end;
-            }
-            result.push(feature);
-        }
-        Ok(result)
+    pub fn parse_words_features(&self, words: &[&str]) -> Result<FeatureArena<String>> {
+        let mut buffer = Vec::with_capacity(self.buffer_capacity(&words));
+        let features = self.parse_words_features_with_buffer_str(words, &mut buffer)?;
+        Ok(to_owned_features(features))
     }
 
-    pub fn parse_words_features_with_buffer_str<'a>(&self, words: &[&str], buffer: &'a mut Vec<u8>) -> Result<Vec<Vec<&'a str>>> {
+    pub fn parse_words_features_with_buffer_str<'a>(
+        &self,
+        words: &[&str],
+        buffer: &'a mut Vec<u8>,
+    ) -> Result<FeatureArena<&'a str>> {
         let features = self.parse_words_features_with_buffer(words, buffer)?;
+        Ok(split_buffer(buffer, &features))
+    }
 
-        let mut start = 0usize;
-        let mut result = Vec::with_capacity(features.len());
-        for feature_end in features {
-            let mut feature = Vec::with_capacity(feature_end.len());
-            for end in feature_end {
-                // Safety : all write are valid utf8
-                feature.push(unsafe { std::str::from_utf8_unchecked(&buffer[start..end]) });
-                start = end;
-            }
-            result.push(feature);
+    /// 训练语料中的一行，每个词为 `词/词性`
+    fn gold_sample(&self, sentence: &str) -> Result<Sample> {
+        let words_tags = sentence.split_whitespace().collect_vec();
+
+        let mut words = Vec::with_capacity(words_tags.len());
+        let mut labels = Vec::with_capacity(words_tags.len());
+        for word_tag in words_tags {
+            let result = word_tag.rsplitn(2, '/');
+            let (label, word) = result
+                .collect_tuple()
+                .ok_or_else(|| LtpError::MalformedToken(word_tag.to_string(), "word/pos"))?;
+            words.push(word);
+            labels.push(match label {
+                UNCERTAIN_TAG => UNCERTAIN,
+                label => self.label_to(label)?,
+            });
         }
-        Ok(result)
+        self.parse_words_features(&words)
+            .map(|features| Sample::new(features, labels))
     }
 }
 
@@ -190,24 +366,48 @@ impl Definition for POSDefinition {
         self.to_labels.len()
     }
 
-    fn label_to(&self, label: &str) -> usize {
-        self.labels_to[label]
+    fn label_to(&self, label: &str) -> Result<usize> {
+        self.unknown_labels.resolve(label, &self.labels_to)
     }
 
     fn to_label(&self, index: usize) -> &str {
         &self.to_labels[index]
     }
 
-    fn parse_features(&self, words: &&[&str]) -> Result<((), Vec<Vec<String>>)> {
-        let features = self.parse_words_features(words)?;
-        Ok(((), features))
+    fn unknown_label_policy(&self) -> UnknownLabelPolicy {
+        self.unknown_labels.clone()
+    }
+
+    fn unseen_labels<R: Read>(&self, reader: R) -> Result<Vec<String>> {
+        unseen_inline_labels(reader, '/', &self.labels_to)
+    }
+
+    fn extend_labels(&mut self, labels: &[String]) {
+        extend_label_table(&mut self.to_labels, &mut self.labels_to, labels);
+    }
+
+    fn collect_tags<R: Read>(&self, reader: R, tags: &mut TagDictionary) -> Result<()> {
+        collect_inline_tags(reader, '/', tags)
+    }
+
+    fn normalizers(&self) -> Normalizers {
+        self.normalizers.clone()
+    }
+
+    fn set_normalizers(&mut self, normalizers: Normalizers) {
+        self.normalizers = normalizers;
+    }
+
+    /// 规范化不改变词长时是准确的
+    fn buffer_capacity(&self, words: &&[&str]) -> usize {
+        self.feature_size(words).0
     }
 
     fn parse_features_with_buffer<'a>(
         &self,
         words: &&[&str],
         buf: &'a mut Vec<u8>,
-    ) -> Result<((), Vec<Vec<&'a str>>)> {
+    ) -> Result<((), FeatureArena<&'a str>)> {
         let features = self.parse_words_features_with_buffer_str(words, buf)?;
         Ok(((), features))
     }
@@ -215,22 +415,17 @@ impl Definition for POSDefinition {
     #[cfg(feature = "parallel")]
     fn parse_gold_features<R: Read>(&self, reader: R) -> Result<Vec<Sample>> {
         let lines = BufReader::new(reader).lines();
-        let lines = lines.flatten().filter(|s| !s.is_empty()).collect_vec();
+        let lines = lines
+            .flatten()
+            .enumerate()
+            .filter(|(_, s)| !s.is_empty())
+            .collect_vec();
 
         lines
             .par_iter()
-            .map(|sentence| {
-                let words_tags = sentence.split_whitespace().collect_vec();
-
-                let mut words = Vec::with_capacity(words_tags.len());
-                let mut labels = Vec::with_capacity(words_tags.len());
-                for word_tag in words_tags {
-                    let result = word_tag.rsplitn(2, '/');
-                    let (label, word) = result.collect_tuple().expect("tag not found");
-                    words.push(word);
-                    labels.push(self.label_to(label));
-                }
-                self.parse_words_features(&words).map(|features| (features, labels))
+            .map(|(index, sentence)| {
+                self.gold_sample(sentence)
+                    .with_context(|| format!("line {}", index + 1))
             })
             .collect()
     }
@@ -238,22 +433,17 @@ impl Definition for POSDefinition {
     #[cfg(not(feature = "parallel"))]
     fn parse_gold_features<R: Read>(&self, reader: R) -> Result<Vec<Sample>> {
         let lines = BufReader::new(reader).lines();
-        let lines = lines.flatten().filter(|s| !s.is_empty()).collect_vec();
+        let lines = lines
+            .flatten()
+            .enumerate()
+            .filter(|(_, s)| !s.is_empty())
+            .collect_vec();
 
         lines
             .iter()
-            .map(|sentence| {
-                let words_tags = sentence.split_whitespace().collect_vec();
-
-                let mut words = Vec::with_capacity(words_tags.len());
-                let mut labels = Vec::with_capacity(words_tags.len());
-                for word_tag in words_tags {
-                    let result = word_tag.rsplitn(2, '/');
-                    let (label, word) = result.collect_tuple().expect("tag not found");
-                    words.push(word);
-                    labels.push(self.label_to(label));
-                }
-                self.parse_words_features(&words).map(|features| (features, labels))
+            .map(|(index, sentence)| {
+                self.gold_sample(sentence)
+                    .with_context(|| format!("line {}", index + 1))
             })
             .collect()
     }
@@ -277,6 +467,8 @@ impl Definition for POSDefinition {
 mod tests {
     use std::iter::zip;
     use super::POSDefinition as Define;
+    use crate::error::LtpError;
+    use crate::perceptron::{Definition, UnknownLabelPolicy};
     use anyhow::Result;
 
     #[test]
@@ -288,7 +480,7 @@ mod tests {
         let no_buffer = define.parse_words_features(&sentence)?;
         let with_buffer = define.parse_words_features_with_buffer_str(&sentence, &mut buffer)?;
 
-        for (a, b) in zip(no_buffer, with_buffer) {
+        for (a, b) in zip(&no_buffer, &with_buffer) {
             for (c, d) in zip(a, b) {
                 assert_eq!(c, d);
             }
@@ -298,4 +490,47 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn test_unknown_labels() -> Result<()> {
+        let corpus = "他/r 来/v\n\n我/r 去/v 北京/ns\n";
+        let labels = vec!["r".to_string(), "v".to_string(), "x".to_string()];
+
+        let err = Define::new(labels.clone())
+            .parse_gold_features(corpus.as_bytes())
+            .unwrap_err();
+        assert_eq!(err.to_string(), "line 3");
+        assert_eq!(
+            err.downcast_ref::<LtpError>(),
+            Some(&LtpError::UnknownLabel("ns".into()))
+        );
+
+        let define =
+            Define::new(labels.clone()).with_unknown_labels(UnknownLabelPolicy::Map("x".into()));
+        let samples = define.parse_gold_features(corpus.as_bytes())?;
+        assert_eq!(samples[1].labels, [0, 1, 2]);
+
+        let mut define = Define::new(labels).with_unknown_labels(UnknownLabelPolicy::Extend);
+        let unseen = define.unseen_labels(corpus.as_bytes())?;
+        assert_eq!(unseen, ["ns"]);
+        define.extend_labels(&unseen);
+        let samples = define.parse_gold_features(corpus.as_bytes())?;
+        assert_eq!(samples[1].labels, [0, 1, 3]);
+        assert_eq!(define.label_num(), 4);
+        Ok(())
+    }
+
+    #[test]
+    fn test_feature_size() -> Result<()> {
+        let words = ["我", "在", "中华人民共和国国务院", "a", "iPhone", "。"];
+        let define = Define::default();
+        let (bytes, count) = define.feature_size(&words);
+
+        let mut buffer = Vec::new();
+        let features = define.parse_words_features_with_buffer(&words, &mut buffer)?;
+        assert_eq!(buffer.len(), bytes);
+        assert_eq!(buffer.capacity(), bytes);
+        assert_eq!(features.feature_len(), count);
+        Ok(())
+    }
 }
@@ -0,0 +1,578 @@
+use crate::corpus::{ConllFormat, ConllReader};
+use crate::error::LtpError;
+use crate::perceptron::definition::{
+    extend_label_table, normalize_tokens, split_buffer, to_owned_features, GenericItem,
+    UnknownLabelPolicy, UNCERTAIN, UNCERTAIN_TAG,
+};
+use crate::perceptron::{
+    Definition, EmbeddingFeatures, EvalReport, FeatureArena, Normalizers, Sample, TagDictionary,
+};
+use anyhow::{anyhow, Context, Result};
+use itertools::Itertools;
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
+#[cfg(feature = "serialization")]
+use serde::{Deserialize, Serialize};
+use smallvec::SmallVec;
+use std::collections::HashMap;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::sync::Arc;
+
+/// 词内字符的位置，`End(k)` 为倒数第 `k + 1` 个字符，即 `n-k`
+#[cfg_attr(feature = "serialization", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CharIndex {
+    Start(usize),
+    End(usize),
+}
+
+/// 特征模板中的一项，位置均相对于当前词
+#[cfg_attr(feature = "serialization", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TemplateItem {
+    /// `w[i]`
+    Word(isize),
+    /// `ch[i,j]`
+    Char(isize, CharIndex),
+    /// `len(w[i])`，按字符计
+    Len(isize),
+}
+
+/// 特征模板中一项在句中的取值
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TemplateValue<'a> {
+    Word(&'a str),
+    Char(char),
+    Len(usize),
+}
+
+impl TemplateValue<'_> {
+    pub fn write(&self, buffer: &mut Vec<u8>) -> std::io::Result<()> {
+        match *self {
+            TemplateValue::Word(word) => buffer.extend_from_slice(word.as_bytes()),
+            TemplateValue::Char(ch) => {
+                buffer.extend_from_slice(ch.encode_utf8(&mut [0; 4]).as_bytes())
+            }
+            TemplateValue::Len(len) => write!(buffer, "{}", len)?,
+        }
+        Ok(())
+    }
+}
+
+impl TemplateItem {
+    /// 第 `idx` 个词处的取值，引用的位置越界时为 `None`
+    #[inline]
+    pub fn value<'a, C: AsRef<[char]>>(
+        self,
+        words: &[&'a str],
+        chars: &[C],
+        idx: usize,
+    ) -> Option<TemplateValue<'a>> {
+        let word = |position: isize| {
+            idx.checked_add_signed(position)
+                .filter(|&i| i < words.len())
+        };
+        match self {
+            TemplateItem::Word(i) => word(i).map(|w| TemplateValue::Word(words[w])),
+            TemplateItem::Len(i) => word(i).map(|w| TemplateValue::Len(chars[w].as_ref().len())),
+            TemplateItem::Char(i, j) => {
+                let chars = chars[word(i)?].as_ref();
+                let ch = match j {
+                    CharIndex::Start(j) => chars.get(j).copied(),
+                    CharIndex::End(k) => chars.len().checked_sub(k + 1).map(|j| chars[j]),
+                };
+                ch.map(TemplateValue::Char)
+            }
+        }
+    }
+}
+
+/// 特征模板，特征字符串为 `prefix` 与各项取值直接拼接，引用的位置越界时不产生该特征；
+/// 表达式的写法与可移植模型格式中的特征模板相同，
+/// 如 `w[-1]w[0]`、`ch[0,0]ch[0,n]`、`ch[0,n-1]`、`len(w[0])`
+#[cfg_attr(feature = "serialization", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Template {
+    pub prefix: String,
+    pub items: Vec<TemplateItem>,
+}
+
+fn parse_position(expr: &str) -> Option<isize> {
+    expr.trim().parse().ok()
+}
+
+fn parse_char_index(expr: &str) -> Option<CharIndex> {
+    match expr.trim() {
+        "n" => Some(CharIndex::End(0)),
+        expr => match expr.strip_prefix("n-") {
+            Some(k) => k.parse().ok().map(CharIndex::End),
+            None => expr.parse().ok().map(CharIndex::Start),
+        },
+    }
+}
+
+impl Template {
+    pub fn parse(prefix: &str, expr: &str) -> Result<Self> {
+        let invalid = || anyhow!("invalid feature template `{}`", expr);
+        let mut items = Vec::new();
+        let mut rest = expr.trim();
+        while !rest.is_empty() {
+            let (item, tail) = if let Some(tail) = rest.strip_prefix("len(w[") {
+                let (position, tail) = tail.split_once("])").ok_or_else(invalid)?;
+                (
+                    TemplateItem::Len(parse_position(position).ok_or_else(invalid)?),
+                    tail,
+                )
+            } else if let Some(tail) = rest.strip_prefix("w[") {
+                let (position, tail) = tail.split_once(']').ok_or_else(invalid)?;
+                (
+                    TemplateItem::Word(parse_position(position).ok_or_else(invalid)?),
+                    tail,
+                )
+            } else if let Some(tail) = rest.strip_prefix("ch[") {
+                let (inner, tail) = tail.split_once(']').ok_or_else(invalid)?;
+                let (position, index) = inner.split_once(',').ok_or_else(invalid)?;
+                let position = parse_position(position).ok_or_else(invalid)?;
+                let index = parse_char_index(index).ok_or_else(invalid)?;
+                (TemplateItem::Char(position, index), tail)
+            } else {
+                return Err(invalid());
+            };
+            items.push(item);
+            rest = tail.trim_start();
+        }
+        if items.is_empty() {
+            return Err(invalid());
+        }
+        Ok(Template {
+            prefix: prefix.to_string(),
+            items,
+        })
+    }
+
+    /// 模板引用的位置都存在时写入特征并返回 `true`
+    fn write(
+        &self,
+        buffer: &mut Vec<u8>,
+        idx: usize,
+        words: &[&str],
+        chars: &[SmallVec<[char; 4]>],
+    ) -> Result<bool> {
+        let values: SmallVec<[_; 4]> = self
+            .items
+            .iter()
+            .map(|item| item.value(words, chars, idx))
+            .collect();
+        if values.iter().any(Option::is_none) {
+            return Ok(false);
+        }
+        buffer.extend_from_slice(self.prefix.as_bytes());
+        for value in values.iter().flatten() {
+            value.write(buffer)?;
+        }
+        Ok(true)
+    }
+}
+
+/// 标注语料的格式
+#[cfg_attr(feature = "serialization", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CorpusFormat {
+    /// 每行一句，词与标签以该字符分隔，如 `他/r 叫/v`
+    Inline(char),
+    /// CoNLL 列格式，每行一个词，空行分隔句子
+    Columns { word: usize, label: usize },
+}
+
+impl Default for CorpusFormat {
+    fn default() -> Self {
+        CorpusFormat::Inline('/')
+    }
+}
+
+/// 由特征模板与语料格式描述的按词序列标注任务，通过 [`SequenceDefinitionBuilder`] 构造，
+/// 不需要为新任务实现 [`Definition`]
+#[cfg_attr(feature = "serialization", derive(Serialize, Deserialize))]
+#[derive(Default, Debug, Clone, PartialEq, Eq)]
+pub struct SequenceDefinition {
+    to_labels: Vec<String>,
+    labels_to: HashMap<String, usize>,
+    templates: Vec<Template>,
+    viterbi: bool,
+    entities: bool,
+    format: CorpusFormat,
+    /// 离散化的预训练词向量特征，不随模型保存
+    #[cfg_attr(feature = "serialization", serde(skip))]
+    embeddings: Option<Arc<EmbeddingFeatures>>,
+    /// 特征抽取前的规范化，不随定义保存，由模型的元信息恢复
+    #[cfg_attr(feature = "serialization", serde(skip))]
+    normalizers: Normalizers,
+    /// 训练语料中未知标签的处理方式，只在训练时使用
+    #[cfg_attr(feature = "serialization", serde(skip))]
+    unknown_labels: UnknownLabelPolicy,
+}
+
+macro_rules! impl_set_param {
+    ($name:ident, $type:ty) => {
+        pub fn $name(mut self, $name: $type) -> Self {
+            self.$name = $name;
+            self
+        }
+    };
+}
+
+#[derive(Default, Debug, Clone)]
+pub struct SequenceDefinitionBuilder {
+    labels: Vec<String>,
+    templates: Vec<(String, String)>,
+    viterbi: bool,
+    entities: bool,
+    format: CorpusFormat,
+    unknown_labels: UnknownLabelPolicy,
+}
+
+impl SequenceDefinitionBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    impl_set_param!(labels, Vec<String>);
+    // 使用维特比解码，学习标签间的转移
+    impl_set_param!(viterbi, bool);
+    // 标签为 BIOES 等实体标签，按实体评测
+    impl_set_param!(entities, bool);
+    impl_set_param!(format, CorpusFormat);
+    impl_set_param!(unknown_labels, UnknownLabelPolicy);
+
+    /// 追加一个特征模板，表达式在 [`build`](Self::build) 时解析
+    pub fn template(mut self, prefix: &str, expr: &str) -> Self {
+        self.templates.push((prefix.to_string(), expr.to_string()));
+        self
+    }
+
+    pub fn build(self) -> Result<SequenceDefinition> {
+        if self.labels.is_empty() {
+            return Err(anyhow!("a sequence definition requires labels"));
+        }
+        if self.templates.is_empty() {
+            return Err(anyhow!("a sequence definition requires feature templates"));
+        }
+        let templates = self
+            .templates
+            .iter()
+            .map(|(prefix, expr)| Template::parse(prefix, expr))
+            .collect::<Result<_>>()?;
+        let labels_to = self
+            .labels
+            .iter()
+            .enumerate()
+            .map(|(i, label)| (label.clone(), i))
+            .collect();
+        Ok(SequenceDefinition {
+            to_labels: self.labels,
+            labels_to,
+            templates,
+            viterbi: self.viterbi,
+            entities: self.entities,
+            format: self.format,
+            embeddings: None,
+            normalizers: Normalizers::default(),
+            unknown_labels: self.unknown_labels,
+        })
+    }
+}
+
+impl SequenceDefinition {
+    pub fn builder() -> SequenceDefinitionBuilder {
+        SequenceDefinitionBuilder::new()
+    }
+
+    pub fn templates(&self) -> &[Template] {
+        &self.templates
+    }
+
+    pub fn format(&self) -> CorpusFormat {
+        self.format
+    }
+
+    /// 抽取特征前规范化各词，训练与预测一致
+    pub fn with_normalizers(mut self, normalizers: Normalizers) -> Self {
+        self.normalizers = normalizers;
+        self
+    }
+
+    /// 抽取特征时加入离散化的预训练词向量特征
+    pub fn embeddings(mut self, embeddings: Arc<EmbeddingFeatures>) -> Self {
+        self.embeddings = Some(embeddings);
+        self
+    }
+
+    /// 加载模型后重新设置训练时使用的词向量特征
+    pub fn set_embeddings(&mut self, embeddings: Option<Arc<EmbeddingFeatures>>) {
+        self.embeddings = embeddings;
+    }
+
+    pub fn parse_words_features_with_buffer(
+        &self,
+        words: &[&str],
+        buffer: &mut Vec<u8>,
+    ) -> Result<FeatureArena<usize>> {
+        let words = normalize_tokens(words)?;
+        let normalized = self.normalizers.apply_all(&words)?;
+        let words: Vec<&str> = normalized.iter().map(AsRef::as_ref).collect();
+        buffer.clear();
+        let mut features =
+            FeatureArena::with_capacity(words.len(), words.len() * self.templates.len());
+        let chars = words
+            .iter()
+            .map(|w| SmallVec::<[char; 4]>::from_iter(w.chars()))
+            .collect_vec();
+        for idx in 0..words.len() {
+            for template in &self.templates {
+                if template.write(buffer, idx, &words, &chars)? {
+                    features.push(buffer.len());
+                }
+            }
+            if let Some(embeddings) = &self.embeddings {
+                embeddings.write(buffer, &mut features, &words, idx)?;
+            }
+            features.end_token();
+        }
+        Ok(features)
+    }
+
+    pub fn parse_words_features(&self, words: &[&str]) -> Result<FeatureArena<String>> {
+        let mut buffer = Vec::with_capacity(self.buffer_capacity(&words));
+        let features = self.parse_words_features_with_buffer_str(words, &mut buffer)?;
+        Ok(to_owned_features(features))
+    }
+
+    pub fn parse_words_features_with_buffer_str<'a>(
+        &self,
+        words: &[&str],
+        buffer: &'a mut Vec<u8>,
+    ) -> Result<FeatureArena<&'a str>> {
+        let features = self.parse_words_features_with_buffer(words, buffer)?;
+        Ok(split_buffer(buffer, &features))
+    }
+
+    fn label(&self, label: &str) -> Result<usize> {
+        match label {
+            UNCERTAIN_TAG => Ok(UNCERTAIN),
+            label => self.label_to(label),
+        }
+    }
+
+    /// 按语料格式切分出各句的词与标签
+    fn read_sentences<R: Read>(&self, reader: R) -> Result<Vec<(Vec<String>, Vec<String>)>> {
+        match self.format {
+            CorpusFormat::Inline(separator) => {
+                let mut sentences = Vec::new();
+                for line in BufReader::new(reader).lines() {
+                    let line = line?;
+                    if line.trim().is_empty() {
+                        continue;
+                    }
+                    let mut sentence = (Vec::new(), Vec::new());
+                    for token in line.split_whitespace() {
+                        let (word, label) = token.rsplit_once(separator).ok_or_else(|| {
+                            LtpError::MalformedToken(token.to_string(), "word/label")
+                        })?;
+                        sentence.0.push(word.to_string());
+                        sentence.1.push(label.to_string());
+                    }
+                    sentences.push(sentence);
+                }
+                Ok(sentences)
+            }
+            CorpusFormat::Columns { word, label } => {
+                let format = ConllFormat::new(word).label(label);
+                ConllReader::new(reader, format)
+                    .map_ok(|sentence| (sentence.words, sentence.labels.unwrap_or_default()))
+                    .collect()
+            }
+        }
+    }
+
+    fn sample(&self, words: &[String], labels: &[String]) -> Result<Sample> {
+        let words = words.iter().map(String::as_str).collect_vec();
+        let labels = labels
+            .iter()
+            .map(|l| self.label(l))
+            .collect::<Result<_>>()?;
+        Ok(Sample::new(self.parse_words_features(&words)?, labels))
+    }
+}
+
+impl Definition for SequenceDefinition {
+    type Fragment = dyn for<'any> GenericItem<'any, Item = ()>;
+    type Prediction = dyn for<'any> GenericItem<'any, Item = Vec<&'any str>>;
+    type RawFeature = dyn for<'any> GenericItem<'any, Item = &'any [&'any str]>;
+
+    fn use_viterbi(&self) -> bool {
+        self.viterbi
+    }
+
+    fn labels(&self) -> Vec<String> {
+        self.to_labels.clone()
+    }
+
+    fn label_num(&self) -> usize {
+        self.to_labels.len()
+    }
+
+    fn label_to(&self, label: &str) -> Result<usize> {
+        self.unknown_labels.resolve(label, &self.labels_to)
+    }
+
+    fn to_label(&self, index: usize) -> &str {
+        &self.to_labels[index]
+    }
+
+    fn unknown_label_policy(&self) -> UnknownLabelPolicy {
+        self.unknown_labels.clone()
+    }
+
+    fn unseen_labels<R: Read>(&self, reader: R) -> Result<Vec<String>> {
+        let labels = self
+            .read_sentences(reader)?
+            .into_iter()
+            .flat_map(|(_, labels)| labels);
+        Ok(labels
+            .filter(|label| label != UNCERTAIN_TAG && !self.labels_to.contains_key(label))
+            .unique()
+            .collect())
+    }
+
+    fn extend_labels(&mut self, labels: &[String]) {
+        extend_label_table(&mut self.to_labels, &mut self.labels_to, labels);
+    }
+
+    fn collect_tags<R: Read>(&self, reader: R, tags: &mut TagDictionary) -> Result<()> {
+        for (words, labels) in self.read_sentences(reader)? {
+            let pairs = words
+                .into_iter()
+                .zip(labels)
+                .filter(|(_, label)| label != UNCERTAIN_TAG);
+            tags.extend(pairs);
+        }
+        Ok(())
+    }
+
+    fn normalizers(&self) -> Normalizers {
+        self.normalizers.clone()
+    }
+
+    fn set_normalizers(&mut self, normalizers: Normalizers) {
+        self.normalizers = normalizers;
+    }
+
+    fn buffer_capacity(&self, words: &&[&str]) -> usize {
+        words.len() * self.templates.len() * 8
+    }
+
+    fn parse_features_with_buffer<'a>(
+        &self,
+        words: &&[&str],
+        buf: &'a mut Vec<u8>,
+    ) -> Result<((), FeatureArena<&'a str>)> {
+        let features = self.parse_words_features_with_buffer_str(words, buf)?;
+        Ok(((), features))
+    }
+
+    fn parse_gold_features<R: Read>(&self, reader: R) -> Result<Vec<Sample>> {
+        let sentences = self.read_sentences(reader)?;
+        #[cfg(feature = "parallel")]
+        let iter = sentences.par_iter();
+        #[cfg(not(feature = "parallel"))]
+        let iter = sentences.iter();
+        iter.enumerate()
+            .map(|(index, (words, labels))| {
+                self.sample(words, labels)
+                    .with_context(|| format!("sentence {}", index + 1))
+            })
+            .collect()
+    }
+
+    fn predict(
+        &self,
+        _: &<Self::RawFeature as GenericItem>::Item,
+        _: &<Self::Fragment as GenericItem>::Item,
+        predicts: &[usize],
+    ) -> Vec<&str> {
+        self.to_labels(predicts)
+    }
+
+    fn evaluate(&self, predicts: &[usize], labels: &[usize]) -> (usize, usize, usize) {
+        if self.entities {
+            self.evaluate_entities(predicts, labels)
+        } else {
+            self.evaluate_tags(predicts, labels)
+        }
+    }
+
+    fn evaluate_report(&self, predicts: &[usize], labels: &[usize], report: &mut EvalReport) {
+        if self.entities {
+            self.evaluate_entities_report(predicts, labels, report)
+        } else {
+            self.evaluate_tags_report(predicts, labels, report)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Perceptron, Trainer};
+
+    #[test]
+    fn test_sequence_definition() -> Result<()> {
+        let define = SequenceDefinition::builder()
+            .labels(vec!["r".into(), "v".into(), "nh".into()])
+            .template("2", "w[0]")
+            .template("1", "w[-1]")
+            .template("c", "ch[0,0]ch[0,n]")
+            .template("f", "len(w[0])")
+            .template("h", "ch[0,n-1]")
+            .build()?;
+        let features = define.parse_words_features(&["他", "叫", "汤姆"])?;
+        assert_eq!(features.get(0).unwrap(), ["2他", "c他他", "f1"]);
+        assert_eq!(
+            features.get(2).unwrap(),
+            ["2汤姆", "1叫", "c汤姆", "f2", "h汤"]
+        );
+
+        let samples = define.parse_gold_features("他/r 叫/v 汤姆/nh\n他/_".as_bytes())?;
+        assert_eq!(samples[0].labels, [0, 1, 2]);
+        assert_eq!(samples[1].labels, [UNCERTAIN]);
+        assert!(define.parse_gold_features("他/n".as_bytes()).is_err());
+
+        let mut trainer = Trainer::new_with_define(define)
+            .epoch(3)
+            .compress(false)
+            .verbose(false);
+        trainer.eval_set = Some(samples[..1].to_vec());
+        trainer.train_set = Some(samples[..1].to_vec());
+        let model: Perceptron<_, HashMap<String, usize>, Vec<f64>, f64> = trainer.build()?;
+        assert_eq!(model.predict(&["他", "叫", "汤姆"])?, ["r", "v", "nh"]);
+
+        let define = SequenceDefinition::builder()
+            .labels(vec!["O".into(), "S-Nh".into()])
+            .template("2", "w[0]")
+            .format(CorpusFormat::Columns { word: 0, label: 2 })
+            .entities(true)
+            .build()?;
+        let samples = define.parse_gold_features("汤姆 nh S-Nh\n来 v O\n\n他 r O\n".as_bytes())?;
+        assert_eq!(samples.len(), 2);
+        assert_eq!(samples[0].labels, [1, 0]);
+        assert_eq!(define.evaluate(&[1, 0], &[1, 1]), (1, 1, 2));
+
+        assert!(Template::parse("x", "w[0").is_err());
+        assert!(Template::parse("x", "p[0]").is_err());
+        assert!(SequenceDefinition::builder()
+            .template("2", "w[0]")
+            .build()
+            .is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,215 @@
+//! 编译期展开的特征模板：由 [`feature_template!`](crate::feature_template) 的声明生成逐项写入缓冲区的代码，
+//! 增删模板时不需要同时手写缓冲区与 `String` 两个版本
+use crate::perceptron::definition::{normalize_tokens, split_buffer, to_owned_features};
+use crate::perceptron::{FeatureArena, Template, TemplateItem};
+use anyhow::Result;
+use smallvec::SmallVec;
+
+/// 由 [`feature_template!`](crate::feature_template) 生成的一组特征模板
+pub trait TemplateSet {
+    /// 各模板的前缀与各项，按声明的顺序
+    const TEMPLATES: &'static [(&'static str, &'static [TemplateItem])];
+
+    /// 写入第 `idx` 个词的特征，引用的位置越界的模板不产生特征
+    fn write_token<C: AsRef<[char]>>(
+        buffer: &mut Vec<u8>,
+        features: &mut FeatureArena<usize>,
+        words: &[&str],
+        chars: &[C],
+        idx: usize,
+    ) -> std::io::Result<()>;
+
+    /// 与 [`SequenceDefinition`](crate::SequenceDefinition) 使用的运行时模板相同
+    fn templates() -> Vec<Template> {
+        Self::TEMPLATES
+            .iter()
+            .map(|&(prefix, items)| Template {
+                prefix: prefix.to_string(),
+                items: items.to_vec(),
+            })
+            .collect()
+    }
+
+    fn extract_with_buffer(words: &[&str], buffer: &mut Vec<u8>) -> Result<FeatureArena<usize>> {
+        let words = normalize_tokens(words)?;
+        buffer.clear();
+        let chars: Vec<SmallVec<[char; 4]>> = words.iter().map(|w| w.chars().collect()).collect();
+        let mut features =
+            FeatureArena::with_capacity(words.len(), words.len() * Self::TEMPLATES.len());
+        for idx in 0..words.len() {
+            Self::write_token(buffer, &mut features, &words, &chars, idx)?;
+            features.end_token();
+        }
+        Ok(features)
+    }
+
+    fn extract_with_buffer_str<'a>(
+        words: &[&str],
+        buffer: &'a mut Vec<u8>,
+    ) -> Result<FeatureArena<&'a str>> {
+        let features = Self::extract_with_buffer(words, buffer)?;
+        Ok(split_buffer(buffer, &features))
+    }
+
+    fn extract(words: &[&str]) -> Result<FeatureArena<String>> {
+        let mut buffer = Vec::new();
+        Ok(to_owned_features(Self::extract_with_buffer_str(
+            words,
+            &mut buffer,
+        )?))
+    }
+}
+
+/// 由模板的声明生成实现 [`TemplateSet`](crate::perceptron::TemplateSet) 的类型，
+/// 每个模板为前缀与若干项，项的写法与 [`Template::parse`](crate::perceptron::Template::parse)
+/// 相同，只是词的长度写作 `len[i]`：
+///
+/// ```
+/// use ltp::feature_template;
+/// use ltp::perceptron::TemplateSet;
+///
+/// feature_template! {
+///     /// 词与首尾字
+///     pub struct WordTemplates {
+///         "2" => w[0];
+///         "6" => w[-1] w[0];
+///         "c" => ch[0, 0] ch[0, n];
+///         "g" => ch[0, n - 1];
+///         "f" => len[0];
+///     }
+/// }
+///
+/// let features = WordTemplates::extract(&["他", "喜欢"]).unwrap();
+/// assert_eq!(features.get(1).unwrap(), ["2喜欢", "6他喜欢", "c喜欢", "g喜", "f2"]);
+/// ```
+#[macro_export]
+macro_rules! feature_template {
+    (@item w [$i:literal]) => {
+        $crate::perceptron::TemplateItem::Word($i)
+    };
+    (@item len [$i:literal]) => {
+        $crate::perceptron::TemplateItem::Len($i)
+    };
+    (@item ch [$i:literal, n]) => {
+        $crate::perceptron::TemplateItem::Char($i, $crate::perceptron::CharIndex::End(0))
+    };
+    (@item ch [$i:literal, n - $k:literal]) => {
+        $crate::perceptron::TemplateItem::Char($i, $crate::perceptron::CharIndex::End($k))
+    };
+    (@item ch [$i:literal, $j:literal]) => {
+        $crate::perceptron::TemplateItem::Char($i, $crate::perceptron::CharIndex::Start($j))
+    };
+    (
+        $(#[$meta:meta])*
+        $vis:vis struct $name:ident {
+            $($prefix:literal => $($kind:ident [$($arg:tt)*])+;)*
+        }
+    ) => {
+        $(#[$meta])*
+        #[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+        $vis struct $name;
+
+        impl $crate::perceptron::TemplateSet for $name {
+            const TEMPLATES: &'static [(
+                &'static str,
+                &'static [$crate::perceptron::TemplateItem],
+            )] = &[
+                $(($prefix, &[$($crate::feature_template!(@item $kind [$($arg)*])),+])),*
+            ];
+
+            #[inline]
+            fn write_token<C: AsRef<[char]>>(
+                buffer: &mut Vec<u8>,
+                features: &mut $crate::perceptron::FeatureArena<usize>,
+                words: &[&str],
+                chars: &[C],
+                idx: usize,
+            ) -> ::std::io::Result<()> {
+                $(
+                    let values = [$(
+                        $crate::feature_template!(@item $kind [$($arg)*]).value(words, chars, idx)
+                    ),+];
+                    if values.iter().all(Option::is_some) {
+                        buffer.extend_from_slice($prefix.as_bytes());
+                        for value in values.iter().flatten() {
+                            value.write(buffer)?;
+                        }
+                        features.push(buffer.len());
+                    }
+                )*
+                Ok(())
+            }
+        }
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::perceptron::{Definition, SequenceDefinition};
+
+    crate::feature_template! {
+        struct PosLike {
+            "2" => w[0];
+            "1" => w[-1];
+            "0" => w[-2];
+            "6" => w[-1] w[0];
+            "b" => w[-1] w[0] w[1];
+            "c" => ch[0, 0] ch[0, n];
+            "d" => ch[-1, n] ch[0, 0];
+            "f" => len[0];
+            "h" => ch[0, n - 2];
+        }
+    }
+
+    #[test]
+    fn test_feature_template() -> Result<()> {
+        let exprs = [
+            "w[0]",
+            "w[-1]",
+            "w[-2]",
+            "w[-1]w[0]",
+            "w[-1]w[0]w[1]",
+            "ch[0,0]ch[0,n]",
+            "ch[-1,n]ch[0,0]",
+            "len(w[0])",
+            "ch[0,n-2]",
+        ];
+        let parsed = PosLike::TEMPLATES
+            .iter()
+            .zip(exprs)
+            .map(|(&(prefix, _), expr)| Template::parse(prefix, expr))
+            .collect::<Result<Vec<_>>>()?;
+        assert_eq!(PosLike::templates(), parsed);
+
+        // 与运行时解析的模板抽取的特征相同
+        let define = PosLike::TEMPLATES
+            .iter()
+            .zip(exprs)
+            .fold(
+                SequenceDefinition::builder(),
+                |builder, (&(prefix, _), expr)| builder.template(prefix, expr),
+            )
+            .labels(vec!["x".into()])
+            .build()?;
+        let words = ["我们", "去", "中国科学院", "。"];
+        let expected = define.parse_features(&&words[..])?.1;
+        assert_eq!(PosLike::extract(&words)?, expected);
+        assert_eq!(
+            PosLike::extract(&words)?.get(2).unwrap(),
+            [
+                "2中国科学院",
+                "1去",
+                "0我们",
+                "6去中国科学院",
+                "b去中国科学院。",
+                "c中院",
+                "d去中",
+                "f5",
+                "h科"
+            ]
+        );
+        assert!(PosLike::extract(&["他", ""]).is_err());
+        Ok(())
+    }
+}
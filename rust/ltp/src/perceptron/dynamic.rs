@@ -0,0 +1,252 @@
+//! 不区分任务的模型：`Definition` 的关联类型使不同任务的模型无法放进同一个集合，
+//! [`DynModel`] 以枚举分发输入与输出，可以按文件头加载任意任务的模型
+use crate::perceptron::{Compression, Definition, ModelHeader, ModelTask, Reader, Schema};
+#[cfg(feature = "cws")]
+use crate::CWSModel;
+#[cfg(feature = "ner")]
+use crate::NERModel;
+#[cfg(feature = "pos")]
+use crate::POSModel;
+use crate::{Format, ModelSerde};
+use anyhow::{anyhow, Result};
+#[cfg(feature = "fs")]
+use std::fs::File;
+use std::io::{BufRead, BufReader, Read};
+#[cfg(feature = "fs")]
+use std::path::{Path, PathBuf};
+
+/// 各任务模型的输入
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DynInput<'a> {
+    /// 分词的输入：一个句子
+    Text(&'a str),
+    /// 词性标注的输入：词
+    Words(&'a [&'a str]),
+    /// 命名实体识别的输入：词与词性
+    Tagged(&'a [&'a str], &'a [&'a str]),
+}
+
+/// 各任务模型的输出
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DynOutput<'a> {
+    /// 分词的结果
+    Words(Vec<&'a str>),
+    /// 词性或实体标签，与输入的词一一对应
+    Tags(Vec<&'a str>),
+}
+
+impl<'a> DynOutput<'a> {
+    pub fn into_inner(self) -> Vec<&'a str> {
+        match self {
+            DynOutput::Words(words) => words,
+            DynOutput::Tags(tags) => tags,
+        }
+    }
+}
+
+/// 按文件头（或旧模型的 avro schema）识别任务类型的模型
+#[derive(Debug, Clone)]
+pub enum DynModel {
+    #[cfg(feature = "cws")]
+    Cws(CWSModel),
+    #[cfg(feature = "pos")]
+    Pos(POSModel),
+    #[cfg(feature = "ner")]
+    Ner(NERModel),
+}
+
+#[cfg(feature = "cws")]
+impl From<CWSModel> for DynModel {
+    fn from(model: CWSModel) -> Self {
+        DynModel::Cws(model)
+    }
+}
+
+#[cfg(feature = "pos")]
+impl From<POSModel> for DynModel {
+    fn from(model: POSModel) -> Self {
+        DynModel::Pos(model)
+    }
+}
+
+#[cfg(feature = "ner")]
+impl From<NERModel> for DynModel {
+    fn from(model: NERModel) -> Self {
+        DynModel::Ner(model)
+    }
+}
+
+impl DynModel {
+    pub fn task(&self) -> ModelTask {
+        match self {
+            #[cfg(feature = "cws")]
+            DynModel::Cws(_) => ModelTask::CWS,
+            #[cfg(feature = "pos")]
+            DynModel::Pos(_) => ModelTask::POS,
+            #[cfg(feature = "ner")]
+            DynModel::Ner(_) => ModelTask::NER,
+        }
+    }
+
+    pub fn labels(&self) -> Vec<String> {
+        match self {
+            #[cfg(feature = "cws")]
+            DynModel::Cws(model) => model.definition.labels(),
+            #[cfg(feature = "pos")]
+            DynModel::Pos(model) => model.definition.labels(),
+            #[cfg(feature = "ner")]
+            DynModel::Ner(model) => model.definition.labels(),
+        }
+    }
+
+    /// 输入与模型的任务不符时返回错误
+    pub fn predict<'a>(&'a self, input: DynInput<'a>) -> Result<DynOutput<'a>> {
+        Ok(match (self, input) {
+            #[cfg(feature = "cws")]
+            (DynModel::Cws(model), DynInput::Text(text)) => DynOutput::Words(model.predict(text)?),
+            #[cfg(feature = "pos")]
+            (DynModel::Pos(model), DynInput::Words(words)) => {
+                DynOutput::Tags(model.predict(words)?)
+            }
+            #[cfg(feature = "ner")]
+            (DynModel::Ner(model), DynInput::Tagged(words, pos)) => {
+                DynOutput::Tags(model.predict((words, pos))?)
+            }
+            (model, input) => {
+                return Err(anyhow!(
+                    "a {} model can not predict {:?}",
+                    model.task(),
+                    input
+                ))
+            }
+        })
+    }
+
+    /// 不是模型文件（既没有文件头，也不是 avro 文件）时返回 `None`
+    fn read<R: BufRead>(mut reader: R) -> Result<Option<Self>> {
+        if let Some(header) = ModelHeader::peek(&mut reader)? {
+            let format = Format::from(header.payload);
+            return Ok(Some(match header.task {
+                #[cfg(feature = "cws")]
+                ModelTask::CWS => DynModel::Cws(ModelSerde::load(reader, format)?),
+                #[cfg(feature = "pos")]
+                ModelTask::POS => DynModel::Pos(ModelSerde::load(reader, format)?),
+                #[cfg(feature = "ner")]
+                ModelTask::NER => DynModel::Ner(ModelSerde::load(reader, format)?),
+                #[allow(unreachable_patterns)]
+                task => return Err(anyhow!("{} support is not compiled in", task)),
+            }));
+        }
+
+        if !reader.fill_buf()?.starts_with(b"Obj\x01") {
+            return Ok(None);
+        }
+        let reader = Reader::new(reader)?;
+        let name = match reader.writer_schema() {
+            Schema::Record { name, .. } => name.name.clone(),
+            _ => return Err(anyhow!("unknown model schema")),
+        };
+        Ok(Some(match name.as_str() {
+            #[cfg(feature = "cws")]
+            "cws" => DynModel::Cws(ModelSerde::load_avro(reader)?),
+            #[cfg(feature = "pos")]
+            "pos" => DynModel::Pos(ModelSerde::load_avro(reader)?),
+            #[cfg(feature = "ner")]
+            "ner" => DynModel::Ner(ModelSerde::load_avro(reader)?),
+            name => return Err(anyhow!("unknown model schema `{}`", name)),
+        }))
+    }
+
+    pub fn load<R: Read>(reader: R, compression: Compression) -> Result<Self> {
+        let reader = BufReader::new(compression.decoder(reader)?);
+        Self::read(reader)?.ok_or_else(|| {
+            anyhow!("unknown model format, only models with a header or legacy avro models are supported")
+        })
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        Self::load(bytes, Compression::None)
+    }
+
+    /// 根据扩展名（`.gz`、`.zst`）自动解压
+    #[cfg(feature = "fs")]
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(skip_all, fields(path = %path.as_ref().display()))
+    )]
+    pub fn load_file<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        Self::load(File::open(path)?, Compression::from_path(path))
+            .map_err(|err| anyhow!("{}: {}", path.display(), err))
+    }
+
+    #[cfg(feature = "fs")]
+    pub fn save_file<P: AsRef<Path>>(&self, path: P, format: Format) -> Result<()> {
+        match self {
+            #[cfg(feature = "cws")]
+            DynModel::Cws(model) => model.save_file(path, format),
+            #[cfg(feature = "pos")]
+            DynModel::Pos(model) => model.save_file(path, format),
+            #[cfg(feature = "ner")]
+            DynModel::Ner(model) => model.save_file(path, format),
+        }
+    }
+
+    /// 加载目录下（不递归）所有的模型，跳过不是模型的文件，按文件名排序
+    #[cfg(feature = "fs")]
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(skip_all, fields(dir = %dir.as_ref().display()))
+    )]
+    pub fn load_dir<P: AsRef<Path>>(dir: P) -> Result<Vec<(PathBuf, Self)>> {
+        let mut paths = Vec::new();
+        for entry in std::fs::read_dir(dir)? {
+            let entry = entry?;
+            if entry.file_type()?.is_file() {
+                paths.push(entry.path());
+            }
+        }
+        paths.sort();
+
+        let mut models = Vec::new();
+        for path in paths {
+            let reader = Compression::from_path(&path).decoder(File::open(&path)?)?;
+            let model = Self::read(BufReader::new(reader))
+                .map_err(|err| anyhow!("{}: {}", path.display(), err))?;
+            if let Some(model) = model {
+                models.push((path, model));
+            }
+        }
+        Ok(models)
+    }
+}
+
+#[cfg(all(test, feature = "cws", feature = "pos"))]
+mod tests {
+    use super::*;
+    use crate::perceptron::cws_test_model;
+    use crate::{POSDefinition, Perceptron};
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_dyn_model() -> Result<()> {
+        let features = HashMap::from([("2他".to_string(), 0)]);
+        let cws: CWSModel = cws_test_model(&["2他"], |_| 0.0);
+        let definition = POSDefinition::new(vec!["n".into(), "r".into()]);
+        let pos: POSModel = Perceptron::new_with_parameters(definition, features, vec![0.0, 1.0]);
+
+        let mut bytes = Vec::new();
+        pos.save(&mut bytes, Format::JSON)?;
+        let models = [DynModel::from(cws), DynModel::from_bytes(&bytes)?];
+        assert_eq!(models[1].task(), ModelTask::POS);
+        assert_eq!(models[1].labels(), ["n", "r"]);
+
+        let words = models[0].predict(DynInput::Text("他"))?.into_inner();
+        assert_eq!(words, ["他"]);
+        let tags = models[1].predict(DynInput::Words(&words))?;
+        assert_eq!(tags, DynOutput::Tags(vec!["r"]));
+        assert!(models[1].predict(DynInput::Text("他")).is_err());
+        assert!(DynModel::from_bytes(b"not a model").is_err());
+        Ok(())
+    }
+}
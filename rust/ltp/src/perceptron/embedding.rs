@@ -0,0 +1,270 @@
+//! 预训练词向量特征：读取 word2vec/fastText 的文本或二进制词向量，
+//! 把每一维的取值按区间离散化，在抽取特征时作为 `{prefix}{offset}:{dim}:{bucket}` 输出
+#[cfg(feature = "fs")]
+use crate::perceptron::CompressedReader;
+use crate::perceptron::FeatureArena;
+use anyhow::{anyhow, Context, Result};
+use std::collections::HashMap;
+use std::io::{BufRead, BufReader, Read, Write};
+#[cfg(feature = "fs")]
+use std::path::Path;
+
+/// 词向量文件的格式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EmbeddingFormat {
+    /// 每行一个词与各维取值，以空白分隔，首行可以是 `词数 维数`，如 fastText 的 `.vec`
+    Text,
+    /// word2vec 的二进制格式：首行为 `词数 维数`，之后每个词后跟一个空格与小端的 `f32`
+    Binary,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Embeddings {
+    dim: usize,
+    vectors: HashMap<String, Vec<f32>>,
+}
+
+impl Embeddings {
+    pub fn read<R: Read>(reader: R, format: EmbeddingFormat) -> Result<Self> {
+        let reader = BufReader::new(reader);
+        match format {
+            EmbeddingFormat::Text => Self::read_text(reader),
+            EmbeddingFormat::Binary => Self::read_binary(reader),
+        }
+    }
+
+    /// 文件可以是 gzip、zstd 压缩的
+    #[cfg(feature = "fs")]
+    pub fn open<P: AsRef<Path>>(path: P, format: EmbeddingFormat) -> Result<Self> {
+        Self::read(CompressedReader::open(path)?, format)
+    }
+
+    fn insert(&mut self, word: String, vector: Vec<f32>) -> Result<()> {
+        if self.dim == 0 {
+            self.dim = vector.len();
+        }
+        if vector.len() != self.dim {
+            return Err(anyhow!(
+                "embedding of `{}` has {} dimensions, expected {}",
+                word,
+                vector.len(),
+                self.dim
+            ));
+        }
+        self.vectors.insert(word, vector);
+        Ok(())
+    }
+
+    fn read_text<R: BufRead>(reader: R) -> Result<Self> {
+        let mut embeddings = Self::default();
+        for (index, line) in reader.lines().enumerate() {
+            let line = line?;
+            let mut fields = line.split_whitespace();
+            let Some(word) = fields.next() else {
+                continue;
+            };
+            let values: Vec<&str> = fields.collect();
+            // 首行的 `词数 维数`
+            if index == 0 && values.len() == 1 && word.parse::<usize>().is_ok() {
+                continue;
+            }
+            let vector = values
+                .iter()
+                .map(|value| value.parse::<f32>())
+                .collect::<Result<Vec<_>, _>>()
+                .with_context(|| format!("invalid embedding at line {}", index + 1))?;
+            embeddings.insert(word.to_string(), vector)?;
+        }
+        Ok(embeddings)
+    }
+
+    fn read_binary<R: BufRead>(mut reader: R) -> Result<Self> {
+        let mut header = String::new();
+        reader.read_line(&mut header)?;
+        let (count, dim) = header
+            .split_whitespace()
+            .map(str::parse::<usize>)
+            .collect::<Result<Vec<_>, _>>()
+            .ok()
+            .and_then(|header| match header[..] {
+                [count, dim] => Some((count, dim)),
+                _ => None,
+            })
+            .ok_or_else(|| anyhow!("invalid word2vec header `{}`", header.trim()))?;
+
+        let mut embeddings = Self {
+            dim,
+            vectors: HashMap::with_capacity(count),
+        };
+        let mut word = Vec::new();
+        let mut vector = vec![0u8; dim * 4];
+        for _ in 0..count {
+            word.clear();
+            reader.read_until(b' ', &mut word)?;
+            word.pop();
+            reader.read_exact(&mut vector)?;
+            let word = String::from_utf8_lossy(&word).trim_start().to_string();
+            let values = vector
+                .chunks_exact(4)
+                .map(|bytes| f32::from_le_bytes(bytes.try_into().unwrap()));
+            embeddings.insert(word, values.collect())?;
+        }
+        Ok(embeddings)
+    }
+
+    pub fn dim(&self) -> usize {
+        self.dim
+    }
+
+    pub fn len(&self) -> usize {
+        self.vectors.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.vectors.is_empty()
+    }
+
+    pub fn get(&self, word: &str) -> Option<&[f32]> {
+        self.vectors.get(word).map(Vec::as_slice)
+    }
+
+    /// 每一维按该维的最小值到最大值等分为 `buckets` 个区间，最多 256 个
+    pub fn discretize(&self, buckets: usize) -> EmbeddingFeatures {
+        let buckets = buckets.clamp(1, 256);
+        let mut bounds = vec![(f32::INFINITY, f32::NEG_INFINITY); self.dim];
+        for vector in self.vectors.values() {
+            for (bound, &value) in bounds.iter_mut().zip(vector) {
+                *bound = (bound.0.min(value), bound.1.max(value));
+            }
+        }
+        let bucket = |(min, max): (f32, f32), value: f32| {
+            let scaled = (value - min) / (max - min) * buckets as f32;
+            match scaled.is_finite() {
+                true => (scaled as usize).min(buckets - 1) as u8,
+                false => 0,
+            }
+        };
+        let table = self
+            .vectors
+            .iter()
+            .map(|(word, vector)| {
+                let values = bounds.iter().zip(vector).map(|(&b, &v)| bucket(b, v));
+                (word.clone(), values.collect())
+            })
+            .collect();
+        EmbeddingFeatures {
+            prefix: "E".to_string(),
+            window: vec![0],
+            table,
+        }
+    }
+}
+
+/// 离散化后的词向量，通过各定义的 `embeddings` 加入特征抽取；
+/// 不随模型保存，加载模型后需要重新设置
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EmbeddingFeatures {
+    prefix: String,
+    window: Vec<isize>,
+    table: HashMap<String, Vec<u8>>,
+}
+
+impl EmbeddingFeatures {
+    /// 特征前缀，默认为 `E`，不应与定义中已有的特征前缀冲突
+    pub fn prefix(mut self, prefix: &str) -> Self {
+        self.prefix = prefix.to_string();
+        self
+    }
+
+    /// 查找词向量的相对位置，默认只用当前的字或词
+    pub fn window(mut self, window: Vec<isize>) -> Self {
+        self.window = window;
+        self
+    }
+
+    pub fn len(&self) -> usize {
+        self.table.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.table.is_empty()
+    }
+
+    /// 第 `idx` 个字或词的特征，没有词向量的字或词不产生特征
+    pub fn write(
+        &self,
+        buffer: &mut Vec<u8>,
+        features: &mut FeatureArena<usize>,
+        tokens: &[&str],
+        idx: usize,
+    ) -> Result<()> {
+        for &offset in &self.window {
+            let Some(token) = idx
+                .checked_add_signed(offset)
+                .and_then(|position| tokens.get(position))
+            else {
+                continue;
+            };
+            let Some(buckets) = self.table.get(*token) else {
+                continue;
+            };
+            for (dim, bucket) in buckets.iter().enumerate() {
+                write!(buffer, "{}{}:{}:{}", self.prefix, offset, dim, bucket)?;
+                features.push(buffer.len());
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::perceptron::definition::split_buffer;
+    use crate::SequenceDefinition;
+    use std::sync::Arc;
+
+    #[test]
+    fn test_embeddings() -> Result<()> {
+        let text = "3 2\n北京 0.0 1.0\n天安门 0.5 -1.0\n爱 1.0 0.0\n";
+        let embeddings = Embeddings::read(text.as_bytes(), EmbeddingFormat::Text)?;
+        assert_eq!((embeddings.len(), embeddings.dim()), (3, 2));
+        assert!(Embeddings::read("a 1 2\nb 1\n".as_bytes(), EmbeddingFormat::Text).is_err());
+
+        let mut binary = b"2 2\n".to_vec();
+        for (word, vector) in [("北京", [0.0f32, 1.0]), ("爱", [1.0, 0.0])] {
+            binary.extend_from_slice(word.as_bytes());
+            binary.push(b' ');
+            vector.iter().for_each(|v| binary.extend(v.to_le_bytes()));
+            binary.push(b'\n');
+        }
+        let binary = Embeddings::read(binary.as_slice(), EmbeddingFormat::Binary)?;
+        assert_eq!(binary.get("爱"), embeddings.get("爱"));
+
+        let features = embeddings.discretize(4).window(vec![0, 1]);
+        let mut buffer = Vec::new();
+        let mut arena = FeatureArena::new();
+        let tokens = ["我", "爱", "北京"];
+        for idx in 0..tokens.len() {
+            features.write(&mut buffer, &mut arena, &tokens, idx)?;
+            arena.end_token();
+        }
+        let arena = split_buffer(&buffer, &arena);
+        assert_eq!(arena.get(0).unwrap(), ["E1:0:3", "E1:1:2"]);
+        assert_eq!(
+            arena.get(1).unwrap(),
+            ["E0:0:3", "E0:1:2", "E1:0:0", "E1:1:3"]
+        );
+        assert_eq!(arena.get(2).unwrap(), ["E0:0:0", "E0:1:3"]);
+
+        let define = SequenceDefinition::builder()
+            .labels(vec!["v".into(), "ns".into()])
+            .template("2", "w[0]")
+            .build()?
+            .embeddings(Arc::new(features.window(vec![0])));
+        let arena = define.parse_words_features(&["爱", "汤姆"])?;
+        assert_eq!(arena.get(0).unwrap(), ["2爱", "E0:0:3", "E0:1:2"]);
+        assert_eq!(arena.get(1).unwrap(), ["2汤姆"]);
+        Ok(())
+    }
+}
@@ -0,0 +1,351 @@
+//! 模型集成：参数平均与打分层面的加权投票
+#[cfg(feature = "cws")]
+use crate::get_entities;
+use crate::perceptron::definition::CommonDefinePredict;
+#[cfg(feature = "cws")]
+use crate::perceptron::CWSDefinition;
+use crate::perceptron::{
+    Definition, FeatureArena, GenericItem, Perceptron, TraitFeature, TraitFeatureCompressUtils,
+    TraitFeaturesTrainUtils, TraitParameter, TraitParameterStorage,
+    TraitParameterStorageCompressUtils,
+};
+use anyhow::{anyhow, Result};
+use std::collections::BTreeMap;
+
+fn normalize_weights<Param: TraitParameter>(models: usize, weights: &[f64]) -> Result<Vec<Param>> {
+    if models == 0 {
+        return Err(anyhow!("at least one model is required"));
+    }
+    if weights.is_empty() {
+        return Ok(vec![Param::from(1.0 / models as f64).unwrap(); models]);
+    }
+    if weights.len() != models {
+        return Err(anyhow!(
+            "got {} weights for {} models",
+            weights.len(),
+            models
+        ));
+    }
+    let total: f64 = weights.iter().sum();
+    if total <= 0.0 || weights.iter().any(|w| !w.is_finite() || *w < 0.0) {
+        return Err(anyhow!(
+            "weights must be finite, non-negative and not all zero"
+        ));
+    }
+    Ok(weights
+        .iter()
+        .map(|w| Param::from(w / total).unwrap())
+        .collect())
+}
+
+fn check_labels<Define: Definition>(definitions: &[&Define]) -> Result<()> {
+    let labels = definitions[0].labels();
+    for definition in &definitions[1..] {
+        if definition.labels() != labels {
+            return Err(anyhow!("models to combine must share the same labels"));
+        }
+    }
+    Ok(())
+}
+
+impl<Define, Feature, ParamStorage, Param> Perceptron<Define, Feature, ParamStorage, Param>
+where
+    Feature: TraitFeature + TraitFeaturesTrainUtils + TraitFeatureCompressUtils,
+    Param: TraitParameter,
+    ParamStorage: TraitParameterStorage<Param> + TraitParameterStorageCompressUtils<Param>,
+    Define: Definition,
+{
+    /// 按权重平均多个模型的参数（权重为空时等权），特征取并集，模型中缺失的特征视为 0。
+    /// 适用于在不同数据分片上训练的同一任务的模型。
+    pub fn merge(models: &[Self], weights: &[f64]) -> Result<Self>
+    where
+        for<'a> &'a Feature: IntoIterator<Item = (&'a String, &'a usize)>,
+    {
+        let weights: Vec<Param> = normalize_weights(models.len(), weights)?;
+        check_labels(&models.iter().map(|m| &m.definition).collect::<Vec<_>>())?;
+
+        let first = &models[0];
+        let label_num = first.definition.label_num();
+        let bias = if first.definition.use_viterbi() {
+            label_num
+        } else {
+            0
+        };
+
+        // 特征按字典序排列，保证合并结果与模型顺序无关
+        let mut merged: BTreeMap<&str, Vec<(usize, usize)>> = BTreeMap::new();
+        for (model_idx, model) in models.iter().enumerate() {
+            for (feature, &idx) in &model.features {
+                merged.entry(feature).or_default().push((model_idx, idx));
+            }
+        }
+
+        let mut parameters = ParamStorage::with_capacity((bias + merged.len()) * label_num);
+        for i in 0..bias * label_num {
+            let mut value = Param::zero();
+            for (model, &weight) in models.iter().zip(&weights) {
+                value += weight * model.parameters.get_param(i);
+            }
+            parameters.push(value);
+        }
+
+        let mut features = Feature::default();
+        for (idx, (feature, sources)) in merged.into_iter().enumerate() {
+            features.insert_feature(feature.to_string(), idx + bias);
+            for label in 0..label_num {
+                let mut value = Param::zero();
+                for &(model_idx, old_idx) in &sources {
+                    value += weights[model_idx]
+                        * models[model_idx]
+                            .parameters
+                            .get_param(old_idx * label_num + label);
+                }
+                parameters.push(value);
+            }
+        }
+
+        let mut metadata = first.metadata.clone();
+        metadata
+            .training
+            .insert("merged_models".to_string(), models.len().to_string());
+        Ok(
+            Perceptron::new_with_parameters(first.definition.clone(), features, parameters)
+                .with_metadata(metadata),
+        )
+    }
+}
+
+/// 对多个模型的打分加权求和后统一解码
+pub struct Ensemble<'a, Define, Feature, ParamStorage, Param>
+where
+    Feature: TraitFeature,
+    Param: TraitParameter,
+    ParamStorage: TraitParameterStorage<Param>,
+    Define: Definition,
+{
+    models: Vec<&'a Perceptron<Define, Feature, ParamStorage, Param>>,
+    weights: Vec<Param>,
+}
+
+impl<'a, Define, Feature, ParamStorage, Param> Ensemble<'a, Define, Feature, ParamStorage, Param>
+where
+    Feature: TraitFeature,
+    Param: TraitParameter,
+    ParamStorage: TraitParameterStorage<Param>,
+    Define: Definition,
+{
+    pub fn new(models: Vec<&'a Perceptron<Define, Feature, ParamStorage, Param>>) -> Result<Self> {
+        Self::with_weights(models, &[])
+    }
+
+    pub fn with_weights(
+        models: Vec<&'a Perceptron<Define, Feature, ParamStorage, Param>>,
+        weights: &[f64],
+    ) -> Result<Self> {
+        let weights = normalize_weights(models.len(), weights)?;
+        check_labels(&models.iter().map(|m| &m.definition).collect::<Vec<_>>())?;
+        Ok(Ensemble { models, weights })
+    }
+
+    pub fn definition(&self) -> &Define {
+        &self.models[0].definition
+    }
+
+    pub fn len(&self) -> usize {
+        self.models.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.models.is_empty()
+    }
+
+    /// 每个位置、每个标签的加权得分，按 `[position * label_num + label]` 排列
+    pub fn emission_scores(&self, features: &FeatureArena<&str>) -> Vec<Param> {
+        let label_num = self.definition().label_num();
+        let mut scores = vec![Param::zero(); features.len() * label_num];
+        for (model, &weight) in self.models.iter().zip(&self.weights) {
+            let indices = model.features.get_arena(features);
+            for (position, feature) in indices.iter().enumerate() {
+                let base = position * label_num;
+                for &idx in feature {
+                    for label in 0..label_num {
+                        scores[base + label] +=
+                            weight * model.parameters.get_param(idx * label_num + label);
+                    }
+                }
+            }
+        }
+        scores
+    }
+
+    fn transition_scores(&self) -> Vec<Param> {
+        let label_num = self.definition().label_num();
+        let mut scores = vec![Param::zero(); label_num * label_num];
+        for (model, &weight) in self.models.iter().zip(&self.weights) {
+            for (i, score) in scores.iter_mut().enumerate() {
+                *score += weight * model.parameters.get_param(i);
+            }
+        }
+        scores
+    }
+
+    pub fn decode(&self, features: &FeatureArena<&str>) -> Vec<usize> {
+        let label_num = self.definition().label_num();
+        let length = features.len();
+        if length == 0 {
+            return vec![];
+        }
+        let emission = self.emission_scores(features);
+
+        if !self.definition().use_viterbi() {
+            return emission
+                .chunks(label_num)
+                .map(|scores| argmax(scores))
+                .collect();
+        }
+
+        let transition = self.transition_scores();
+        let mut backtrace = vec![0usize; length * label_num];
+        let mut last = emission[..label_num].to_vec();
+        let mut now = vec![Param::zero(); label_num];
+        for position in 1..length {
+            let base = position * label_num;
+            for label in 0..label_num {
+                let mut best = Param::min_value();
+                for (pre, &score) in last.iter().enumerate() {
+                    let score = score + transition[pre * label_num + label];
+                    if score > best {
+                        best = score;
+                        backtrace[base + label] = pre;
+                    }
+                }
+                now[label] = best + emission[base + label];
+            }
+            std::mem::swap(&mut last, &mut now);
+        }
+
+        let mut res = vec![0; length];
+        let mut label = argmax(&last);
+        for position in (0..length).rev() {
+            res[position] = label;
+            label = backtrace[position * label_num + label];
+        }
+        res
+    }
+
+    pub fn predict_indices_with_buffer(
+        &self,
+        sentence: &<Define::RawFeature as GenericItem>::Item,
+        buffer: &mut Vec<u8>,
+    ) -> Result<Vec<usize>> {
+        let (_, features) = self
+            .definition()
+            .parse_features_with_buffer(sentence, buffer)?;
+        Ok(self.decode(&features))
+    }
+}
+
+fn argmax<Param: TraitParameter>(scores: &[Param]) -> usize {
+    let mut best = 0;
+    for (idx, score) in scores.iter().enumerate() {
+        if *score > scores[best] {
+            best = idx;
+        }
+    }
+    best
+}
+
+impl<'a, Define, Feature, ParamStorage, Param> Ensemble<'a, Define, Feature, ParamStorage, Param>
+where
+    Feature: TraitFeature,
+    Param: TraitParameter,
+    ParamStorage: TraitParameterStorage<Param>,
+    Define: Definition + CommonDefinePredict,
+{
+    pub fn predict_with_buffer(
+        &self,
+        sentence: <Define::RawFeature as GenericItem>::Item,
+        buffer: &mut Vec<u8>,
+    ) -> Result<<Define::Prediction as GenericItem<'_>>::Item> {
+        let (fragment, features) = self
+            .definition()
+            .parse_features_with_buffer(&sentence, buffer)?;
+        let preds = self.decode(&features);
+        Ok(self.definition().predict(&sentence, &fragment, &preds))
+    }
+}
+
+#[cfg(feature = "cws")]
+impl<'a, Feature, ParamStorage, Param> Ensemble<'a, CWSDefinition, Feature, ParamStorage, Param>
+where
+    Feature: TraitFeature,
+    Param: TraitParameter,
+    ParamStorage: TraitParameterStorage<Param>,
+{
+    pub fn predict<'s>(&self, sentence: &'s str) -> Result<Vec<&'s str>> {
+        let mut buffer = Vec::with_capacity(self.definition().buffer_capacity(&sentence));
+        let (fragments, features) = self
+            .definition()
+            .parse_features_with_buffer(&sentence, &mut buffer)?;
+        let preds = self.decode(&features);
+        let preds = self.definition().to_labels(&preds);
+        Ok(get_entities(&preds)
+            .into_iter()
+            .map(|(_, start, end)| self.definition().word(sentence, &fragments, start, end))
+            .collect())
+    }
+}
+
+#[cfg(all(test, feature = "cws"))]
+mod tests {
+    use super::*;
+    use crate::perceptron::cws_seeded_model;
+    use std::collections::HashMap;
+
+    type Model = Perceptron<CWSDefinition, HashMap<String, usize>, Vec<f64>, f64>;
+
+    #[test]
+    fn test_merge() -> Result<()> {
+        let a = cws_seeded_model(&["2我", "2们"], 1);
+        let b = cws_seeded_model(&["2们", "2你"], 3);
+        let merged = Model::merge(&[a.clone(), b.clone()], &[1.0, 3.0])?;
+
+        assert_eq!(merged.features.len(), 3);
+        assert_eq!(merged.parameters.len(), 16 + 3 * 4);
+        assert_eq!(
+            merged.parameters[0],
+            0.25 * a.parameters[0] + 0.75 * b.parameters[0]
+        );
+
+        let idx = merged.features["2我"];
+        assert_eq!(
+            merged.parameters[idx * 4 + 1],
+            0.25 * a.parameters[4 * 4 + 1]
+        );
+        let idx = merged.features["2们"];
+        assert_eq!(
+            merged.parameters[idx * 4 + 2],
+            0.25 * a.parameters[5 * 4 + 2] + 0.75 * b.parameters[4 * 4 + 2]
+        );
+
+        assert!(Model::merge(&[a.clone(), b.clone()], &[1.0, f64::NAN]).is_err());
+        assert!(Model::merge(&[a, b], &[1.0]).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_ensemble() -> Result<()> {
+        let a = cws_seeded_model(&["2我", "2们"], 1);
+        let b = cws_seeded_model(&["2们", "2你"], 3);
+
+        // 单个模型的集成与模型本身一致
+        let single = Ensemble::new(vec![&a])?;
+        assert_eq!(single.predict("我们你们")?, a.predict("我们你们")?);
+
+        // 打分是线性的，集成结果与合并后的模型一致
+        let ensemble = Ensemble::with_weights(vec![&a, &b], &[1.0, 3.0])?;
+        let merged = Model::merge(&[a.clone(), b.clone()], &[1.0, 3.0])?;
+        assert_eq!(ensemble.predict("我们你们")?, merged.predict("我们你们")?);
+        Ok(())
+    }
+}
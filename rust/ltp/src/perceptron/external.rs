@@ -0,0 +1,212 @@
+//! 外部排序构建特征字典
+//!
+//! 特征的种类多到内存放不下时，[`FeatureSorter`] 在内存中去重到一定字节数后排序写入临时文件，
+//! 最后多路归并这些有序的文件，按字典序依次给出所有不同的特征；内存中只保留一块特征与各文件的读缓冲，
+//! 最终的特征字典由调用方逐个插入，见 [`Trainer::external_features`]。
+//!
+//! [`Trainer::external_features`]: crate::perceptron::Trainer::external_features
+use anyhow::{Context, Result};
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashSet};
+use std::fs::File;
+use std::io::{BufReader, BufWriter, ErrorKind, Read, Write};
+use std::mem::size_of;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+static SORTER_ID: AtomicUsize = AtomicUsize::new(0);
+
+/// 每条记录为 4 字节小端长度与特征的 UTF-8 字节
+fn write_record<W: Write>(writer: &mut W, feature: &str) -> Result<()> {
+    writer.write_all(&(feature.len() as u32).to_le_bytes())?;
+    writer.write_all(feature.as_bytes())?;
+    Ok(())
+}
+
+fn read_record<R: Read>(reader: &mut R) -> Result<Option<String>> {
+    let mut len = [0u8; 4];
+    match reader.read_exact(&mut len) {
+        Ok(()) => {}
+        Err(err) if err.kind() == ErrorKind::UnexpectedEof => return Ok(None),
+        Err(err) => return Err(err.into()),
+    }
+    let mut bytes = vec![0u8; u32::from_le_bytes(len) as usize];
+    reader.read_exact(&mut bytes)?;
+    Ok(Some(String::from_utf8(bytes)?))
+}
+
+#[derive(Debug)]
+pub struct FeatureSorter {
+    /// 本次排序独占的临时目录，结束后删除
+    dir: PathBuf,
+    chunk_bytes: usize,
+    chunk: HashSet<String>,
+    bytes: usize,
+    runs: Vec<PathBuf>,
+}
+
+impl FeatureSorter {
+    /// 在 `dir` 下新建临时目录，内存中的特征超过 `chunk_bytes` 字节时写出一个有序文件
+    pub fn new<P: AsRef<Path>>(dir: P, chunk_bytes: usize) -> Result<Self> {
+        let id = SORTER_ID.fetch_add(1, Ordering::Relaxed);
+        let name = format!("ltp-features-{}-{}", std::process::id(), id);
+        let dir = dir.as_ref().join(name);
+        std::fs::create_dir_all(&dir)
+            .with_context(|| format!("failed to create {}", dir.display()))?;
+        Ok(FeatureSorter {
+            dir,
+            chunk_bytes,
+            chunk: HashSet::new(),
+            bytes: 0,
+            runs: Vec::new(),
+        })
+    }
+
+    pub fn push(&mut self, feature: &str) -> Result<()> {
+        if !self.chunk.contains(feature) {
+            self.bytes += feature.len() + size_of::<String>();
+            self.chunk.insert(feature.to_string());
+            if self.bytes >= self.chunk_bytes {
+                self.flush()?;
+            }
+        }
+        Ok(())
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        if self.chunk.is_empty() {
+            return Ok(());
+        }
+        let mut chunk: Vec<String> = self.chunk.drain().collect();
+        chunk.sort_unstable();
+        let path = self.dir.join(format!("run-{}.bin", self.runs.len()));
+        let mut writer = BufWriter::new(File::create(&path)?);
+        for feature in &chunk {
+            write_record(&mut writer, feature)?;
+        }
+        writer.flush()?;
+        self.runs.push(path);
+        self.bytes = 0;
+        Ok(())
+    }
+
+    /// 已写出的有序文件个数
+    pub fn runs(&self) -> usize {
+        self.runs.len()
+    }
+
+    /// 写出剩余的特征，归并所有有序文件
+    pub fn finish(mut self) -> Result<SortedFeatures> {
+        self.flush()?;
+        let mut sorted = SortedFeatures {
+            dir: std::mem::take(&mut self.dir),
+            readers: Vec::with_capacity(self.runs.len()),
+            heap: BinaryHeap::with_capacity(self.runs.len()),
+            last: None,
+        };
+        for path in &self.runs {
+            sorted.readers.push(BufReader::new(File::open(path)?));
+        }
+        for run in 0..sorted.readers.len() {
+            sorted.refill(run)?;
+        }
+        Ok(sorted)
+    }
+}
+
+impl Drop for FeatureSorter {
+    fn drop(&mut self) {
+        if !self.dir.as_os_str().is_empty() {
+            let _ = std::fs::remove_dir_all(&self.dir);
+        }
+    }
+}
+
+/// 按字典序依次给出的不同特征，读完或丢弃后删除临时文件
+#[derive(Debug)]
+pub struct SortedFeatures {
+    dir: PathBuf,
+    readers: Vec<BufReader<File>>,
+    heap: BinaryHeap<Reverse<(String, usize)>>,
+    last: Option<String>,
+}
+
+impl SortedFeatures {
+    fn refill(&mut self, run: usize) -> Result<()> {
+        if let Some(feature) = read_record(&mut self.readers[run])? {
+            self.heap.push(Reverse((feature, run)));
+        }
+        Ok(())
+    }
+}
+
+impl Iterator for SortedFeatures {
+    type Item = Result<String>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while let Some(Reverse((feature, run))) = self.heap.pop() {
+            if let Err(err) = self.refill(run) {
+                return Some(Err(err));
+            }
+            // 同一个特征可能出现在多个文件中，归并后相邻
+            if self.last.as_deref() != Some(feature.as_str()) {
+                self.last = Some(feature.clone());
+                return Some(Ok(feature));
+            }
+        }
+        None
+    }
+}
+
+impl Drop for SortedFeatures {
+    fn drop(&mut self) {
+        self.readers.clear();
+        let _ = std::fs::remove_dir_all(&self.dir);
+    }
+}
+
+#[cfg(all(test, feature = "cws"))]
+mod tests {
+    use super::*;
+    use crate::perceptron::{Definition, Trainer};
+    use crate::CWSDefinition;
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_feature_sorter() -> Result<()> {
+        let dir = std::env::temp_dir();
+        let mut sorter = FeatureSorter::new(&dir, 3 * size_of::<String>())?;
+        for feature in ["c", "a", "b", "a", "d", "c", "长特征", "b", "a"] {
+            sorter.push(feature)?;
+        }
+        assert!(sorter.runs() >= 2);
+        let run_dir = sorter.dir.clone();
+        let features = sorter.finish()?.collect::<Result<Vec<_>>>()?;
+        assert_eq!(features, vec!["a", "b", "c", "d", "长特征"]);
+        assert!(!run_dir.exists());
+
+        let define = CWSDefinition::default();
+        let corpus = "他 叫 汤姆 去 拿 外衣 。\n我们 去 拿 外衣\n";
+        let mut trainer: Trainer<CWSDefinition> = Trainer::new()
+            .epoch(3)
+            .shuffle(false)
+            .verbose(false)
+            .compress(false);
+        trainer.train_set = Some(define.parse_gold_features(corpus.as_bytes())?);
+        let model = trainer.build::<HashMap<String, usize>, Vec<f64>>()?;
+        let external = trainer
+            .external_features(256)
+            .build::<HashMap<String, usize>, Vec<f64>>()?;
+
+        // 特征相同，编号按字典序
+        assert_eq!(external.features.len(), model.features.len());
+        let mut keys: Vec<_> = external.features.iter().collect();
+        keys.sort_unstable_by_key(|(_, &id)| id);
+        assert!(keys.windows(2).all(|pair| pair[0].0 < pair[1].0));
+        assert_eq!(
+            external.predict("我们去拿外衣")?,
+            model.predict("我们去拿外衣")?
+        );
+        Ok(())
+    }
+}
@@ -1,4 +1,8 @@
+use crate::perceptron::{
+    Definition, FeatureArena, Perceptron, TraitParameter, TraitParameterStorage,
+};
 use std::collections::HashMap;
+use std::hash::BuildHasher;
 use std::ops::Deref;
 
 pub trait TraitFeature {
@@ -17,6 +21,10 @@ pub trait TraitFeature {
             .flatten()
             .collect()
     }
+    /// 逐词查找特征，不在字典中的特征被丢弃
+    fn get_arena<K: AsRef<str>>(&self, keys: &FeatureArena<K>) -> FeatureArena<usize> {
+        keys.filter_map(|k| self.get_with_key(k.as_ref()))
+    }
 }
 pub trait TraitFeatureCompressUtils: Default + IntoIterator<Item = (String, usize)> {
     fn features(self) -> Vec<(String, usize)>;
@@ -73,15 +81,15 @@ where
     }
 }
 
-// HashMap
+// HashMap，哈希函数可替换，见 `ahash`、`fxhash` 特性
 
-impl TraitFeature for HashMap<String, usize> {
+impl<S: BuildHasher> TraitFeature for HashMap<String, usize, S> {
     fn get_with_key(&self, key: &str) -> Option<usize> {
         self.get(key).copied()
     }
 }
 
-impl TraitFeaturesTrainUtils for HashMap<String, usize> {
+impl<S: BuildHasher + Clone> TraitFeaturesTrainUtils for HashMap<String, usize, S> {
     fn feature_num(&self) -> usize {
         self.len()
     }
@@ -102,3 +110,28 @@ impl TraitFeaturesTrainUtils for HashMap<String, usize> {
         self.remove(key)
     }
 }
+
+#[cfg(feature = "ahash")]
+pub type AHashFeatures = HashMap<String, usize, ahash::RandomState>;
+
+#[cfg(feature = "fxhash")]
+pub type FxFeatures = HashMap<String, usize, rustc_hash::FxBuildHasher>;
+
+impl<Define, S, ParamStorage, Param>
+    Perceptron<Define, HashMap<String, usize, S>, ParamStorage, Param>
+where
+    S: BuildHasher,
+    Param: TraitParameter,
+    ParamStorage: TraitParameterStorage<Param>,
+    Define: Definition,
+{
+    /// 更换特征表的哈希函数，例如 `model.with_hasher::<rustc_hash::FxBuildHasher>()`
+    pub fn with_hasher<T: BuildHasher + Default>(
+        self,
+    ) -> Perceptron<Define, HashMap<String, usize, T>, ParamStorage, Param> {
+        let mut features = HashMap::with_capacity_and_hasher(self.features.len(), T::default());
+        features.extend(self.features);
+        Perceptron::new_with_parameters(self.definition, features, self.parameters)
+            .with_metadata(self.metadata)
+    }
+}
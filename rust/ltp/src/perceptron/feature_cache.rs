@@ -0,0 +1,356 @@
+//! 预处理后的特征缓存：抽取一次语料的特征，写成紧凑的二进制文件，之后不同超参数的多次训练直接读取，
+//! 跳过特征抽取
+//!
+//! 语料按批切分为分片（见 [`Trainer`](crate::perceptron::Trainer) 的流式读取），启用 `parallel`
+//! 时各分片并行抽取与编码，再按原来的顺序写出。
+//!
+//! 缓存格式（小端）：魔数 `LTPF`、格式版本 u16、标签表与规范化器的名称（各以 u32 的个数开头），
+//! 之后为若干分片直到文件结束。每个分片以 u32 的句子数开头，之后为分片内不同特征的字符串表，
+//! 再依次为各句：u32 的词数，每个词为 u32 的标签（不确定的标签为 `u32::MAX`）、u32 的特征数
+//! 与各特征在字符串表中的 u32 下标；字符串为 u32 长度与 UTF-8 字节。
+use crate::perceptron::trainer::Batches;
+use crate::perceptron::{
+    CompressedReader, Compression, Definition, FeatureArena, Sample, UNCERTAIN,
+};
+use anyhow::{anyhow, Context, Result};
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
+use std::collections::HashMap;
+use std::fmt::{Display, Formatter};
+use std::fs::File;
+use std::io::{BufRead, ErrorKind, Read, Write};
+use std::path::Path;
+
+pub const CACHE_MAGIC: [u8; 4] = *b"LTPF";
+pub const CACHE_VERSION: u16 = 1;
+
+fn write_u32<W: Write>(writer: &mut W, value: usize) -> Result<()> {
+    let value = u32::try_from(value).map_err(|_| anyhow!("{} is too large for a cache", value))?;
+    writer.write_all(&value.to_le_bytes())?;
+    Ok(())
+}
+
+fn write_str<W: Write>(writer: &mut W, value: &str) -> Result<()> {
+    write_u32(writer, value.len())?;
+    writer.write_all(value.as_bytes())?;
+    Ok(())
+}
+
+fn read_array<R: Read, const N: usize>(reader: &mut R) -> Result<[u8; N]> {
+    let mut bytes = [0u8; N];
+    reader.read_exact(&mut bytes)?;
+    Ok(bytes)
+}
+
+fn read_u32<R: Read>(reader: &mut R) -> Result<usize> {
+    Ok(u32::from_le_bytes(read_array(reader)?) as usize)
+}
+
+fn read_str<R: Read>(reader: &mut R) -> Result<String> {
+    let mut bytes = vec![0u8; read_u32(reader)?];
+    reader.read_exact(&mut bytes)?;
+    Ok(String::from_utf8(bytes)?)
+}
+
+fn read_strings<R: Read>(reader: &mut R) -> Result<Vec<String>> {
+    (0..read_u32(reader)?).map(|_| read_str(reader)).collect()
+}
+
+fn write_strings<W: Write, S: AsRef<str>>(writer: &mut W, values: &[S]) -> Result<()> {
+    write_u32(writer, values.len())?;
+    for value in values {
+        write_str(writer, value.as_ref())?;
+    }
+    Ok(())
+}
+
+/// 缓存对应的标签表与规范化器，读取时须与定义一致
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CacheHeader {
+    pub labels: Vec<String>,
+    pub normalizers: Vec<String>,
+}
+
+impl CacheHeader {
+    pub fn new<D: Definition>(define: &D) -> Self {
+        CacheHeader {
+            labels: define.labels(),
+            normalizers: define.normalizers().names(),
+        }
+    }
+
+    pub fn write<W: Write>(&self, writer: &mut W) -> Result<()> {
+        writer.write_all(&CACHE_MAGIC)?;
+        writer.write_all(&CACHE_VERSION.to_le_bytes())?;
+        write_strings(writer, &self.labels)?;
+        write_strings(writer, &self.normalizers)
+    }
+
+    pub fn read<R: Read>(reader: &mut R) -> Result<Self> {
+        if read_array::<_, 4>(reader)? != CACHE_MAGIC {
+            return Err(anyhow!("not a feature cache"));
+        }
+        let version = u16::from_le_bytes(read_array(reader)?);
+        if version > CACHE_VERSION {
+            return Err(anyhow!(
+                "feature cache version {} is newer than supported {}",
+                version,
+                CACHE_VERSION
+            ));
+        }
+        Ok(CacheHeader {
+            labels: read_strings(reader)?,
+            normalizers: read_strings(reader)?,
+        })
+    }
+
+    /// 读取文件的头部，不是特征缓存时为 `None`
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Option<Self>> {
+        let mut reader = CompressedReader::open(path)?;
+        let mut magic = [0u8; 4];
+        match reader.read_exact(&mut magic) {
+            Ok(()) if magic == CACHE_MAGIC => {}
+            Ok(()) => return Ok(None),
+            Err(err) if err.kind() == ErrorKind::UnexpectedEof => return Ok(None),
+            Err(err) => return Err(err.into()),
+        }
+        Self::read(&mut (&magic[..]).chain(reader)).map(Some)
+    }
+
+    fn check<D: Definition>(&self, define: &D) -> Result<()> {
+        let expected = CacheHeader::new(define);
+        if self.labels != expected.labels {
+            return Err(anyhow!(
+                "feature cache labels {:?} differ from the definition {:?}",
+                self.labels,
+                expected.labels
+            ));
+        }
+        if self.normalizers != expected.normalizers {
+            return Err(anyhow!(
+                "feature cache normalizers {:?} differ from the definition {:?}",
+                self.normalizers,
+                expected.normalizers
+            ));
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CacheStats {
+    pub shards: usize,
+    pub sentences: usize,
+    pub tokens: usize,
+}
+
+impl Display for CacheStats {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "shards: {}, sentences: {}, tokens: {}",
+            self.shards, self.sentences, self.tokens
+        )
+    }
+}
+
+/// 编码一个分片，特征字符串在分片内只保存一次
+fn encode_shard(samples: &[Sample]) -> Result<Vec<u8>> {
+    let mut ids: HashMap<&str, usize> = HashMap::new();
+    let mut table: Vec<&str> = Vec::new();
+    for feature in samples.iter().flat_map(|sample| sample.features.as_flat()) {
+        ids.entry(feature).or_insert_with(|| {
+            table.push(feature);
+            table.len() - 1
+        });
+    }
+
+    let mut bytes = Vec::new();
+    write_u32(&mut bytes, samples.len())?;
+    write_strings(&mut bytes, &table)?;
+    for sample in samples {
+        write_u32(&mut bytes, sample.labels.len())?;
+        for (features, &label) in sample.features.iter().zip(&sample.labels) {
+            match label {
+                UNCERTAIN => bytes.extend_from_slice(&u32::MAX.to_le_bytes()),
+                label => write_u32(&mut bytes, label)?,
+            }
+            write_u32(&mut bytes, features.len())?;
+            for feature in features {
+                write_u32(&mut bytes, ids[feature.as_str()])?;
+            }
+        }
+    }
+    Ok(bytes)
+}
+
+/// 读取一个分片，文件结束时为 `None`
+fn decode_shard<R: Read>(reader: &mut R) -> Result<Option<Vec<Sample>>> {
+    let mut len = [0u8; 4];
+    match reader.read_exact(&mut len) {
+        Ok(()) => {}
+        Err(err) if err.kind() == ErrorKind::UnexpectedEof => return Ok(None),
+        Err(err) => return Err(err.into()),
+    }
+    let sentences = u32::from_le_bytes(len) as usize;
+    let table = read_strings(reader)?;
+    let mut samples = Vec::with_capacity(sentences);
+    for _ in 0..sentences {
+        let tokens = read_u32(reader)?;
+        let mut features = FeatureArena::new();
+        let mut labels = Vec::with_capacity(tokens);
+        for _ in 0..tokens {
+            labels.push(match read_u32(reader)? {
+                label if label == u32::MAX as usize => UNCERTAIN,
+                label => label,
+            });
+            for _ in 0..read_u32(reader)? {
+                let id = read_u32(reader)?;
+                let feature = table
+                    .get(id)
+                    .ok_or_else(|| anyhow!("feature id {} out of range", id))?;
+                features.push(feature.clone());
+            }
+            features.end_token();
+        }
+        samples.push(Sample::try_new(features, labels)?);
+    }
+    Ok(Some(samples))
+}
+
+/// 分片的编码与句子数、词数
+type ShardBytes = (Vec<u8>, usize, usize);
+
+fn preprocess_shard<D: Definition>(define: &D, shard: &(usize, String)) -> Result<ShardBytes> {
+    let (start, text) = shard;
+    let samples = define
+        .parse_gold_features(text.as_bytes())
+        .with_context(|| format!("shard from line {}", start))?;
+    let tokens = samples.iter().map(|sample| sample.labels.len()).sum();
+    Ok((encode_shard(&samples)?, samples.len(), tokens))
+}
+
+/// 每次同时处理的分片数
+fn round_size() -> usize {
+    #[cfg(feature = "parallel")]
+    return rayon::current_num_threads().max(1);
+    #[cfg(not(feature = "parallel"))]
+    return 1;
+}
+
+/// 抽取语料的特征，写入缓存
+pub fn write_cache<D, R, W>(define: &D, reader: R, writer: &mut W) -> Result<CacheStats>
+where
+    D: Definition + Sync,
+    R: BufRead,
+    W: Write,
+{
+    CacheHeader::new(define).write(writer)?;
+    let mut stats = CacheStats::default();
+    let mut batches = Batches::new(reader);
+    loop {
+        let round = batches
+            .by_ref()
+            .take(round_size())
+            .collect::<Result<Vec<_>>>()?;
+        if round.is_empty() {
+            break;
+        }
+        #[cfg(feature = "parallel")]
+        let shards = round.par_iter();
+        #[cfg(not(feature = "parallel"))]
+        let shards = round.iter();
+        let shards: Vec<Result<ShardBytes>> = shards
+            .map(|shard| preprocess_shard(define, shard))
+            .collect();
+        for shard in shards {
+            let (bytes, sentences, tokens) = shard?;
+            writer.write_all(&bytes)?;
+            stats.shards += 1;
+            stats.sentences += sentences;
+            stats.tokens += tokens;
+        }
+    }
+    Ok(stats)
+}
+
+/// 读取缓存中的全部样本，标签表或规范化器与定义不一致时报错
+pub fn read_cache<D: Definition, R: Read>(define: &D, reader: &mut R) -> Result<Vec<Sample>> {
+    CacheHeader::read(reader)?.check(define)?;
+    let mut samples = Vec::new();
+    while let Some(shard) = decode_shard(reader)? {
+        samples.extend(shard);
+    }
+    Ok(samples)
+}
+
+/// 抽取语料文件的特征写入缓存文件，根据扩展名（`.gz`、`.zst`）自动选择压缩方式
+pub fn preprocess<D, P, Q>(define: &D, corpus: P, cache: Q) -> Result<CacheStats>
+where
+    D: Definition + Sync,
+    P: AsRef<Path>,
+    Q: AsRef<Path>,
+{
+    let reader = CompressedReader::open(corpus)?;
+    let compression = Compression::from_path(&cache);
+    let mut writer = compression.encoder(File::create(cache)?)?;
+    let stats = write_cache(define, reader, &mut writer)?;
+    writer.finish()?;
+    Ok(stats)
+}
+
+pub fn load_cache<D: Definition, P: AsRef<Path>>(define: &D, path: P) -> Result<Vec<Sample>> {
+    read_cache(define, &mut CompressedReader::open(path)?)
+}
+
+#[cfg(all(test, feature = "pos"))]
+mod tests {
+    use super::*;
+    use crate::perceptron::Trainer;
+    use crate::POSDefinition;
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_feature_cache() -> Result<()> {
+        let define = POSDefinition::new(vec!["n".into(), "v".into(), "r".into()]);
+        let corpus = "他/r 喜欢/v 花/n\n她/r 花/_ 钱/n\n花/n 开/v\n";
+        let mut bytes = Vec::new();
+        let stats = write_cache(&define, corpus.as_bytes(), &mut bytes)?;
+        assert_eq!(stats.sentences, 3);
+        assert_eq!(stats.tokens, 8);
+
+        let expected = define.parse_gold_features(corpus.as_bytes())?;
+        assert_eq!(read_cache(&define, &mut bytes.as_slice())?, expected);
+        let other = POSDefinition::new(vec!["n".into(), "v".into()]);
+        assert!(read_cache(&other, &mut bytes.as_slice()).is_err());
+
+        let dir = std::env::temp_dir();
+        let corpus_path = dir.join(format!("ltp-cache-corpus-{}.txt", std::process::id()));
+        let cache_path = dir.join(format!("ltp-cache-{}.bin", std::process::id()));
+        std::fs::write(&corpus_path, corpus)?;
+        preprocess(&define, &corpus_path, &cache_path)?;
+        let header = CacheHeader::from_file(&cache_path)?;
+        assert_eq!(header.map(|header| header.labels), Some(define.labels()));
+        assert_eq!(CacheHeader::from_file(&corpus_path)?, None);
+
+        let trainer: Trainer<POSDefinition> = Trainer::new_with_define(define.clone())
+            .epoch(3)
+            .shuffle(false)
+            .verbose(false)
+            .compress(false);
+        let cached = trainer.clone().train_cache(&cache_path)?;
+        assert_eq!(cached.train_set.as_ref(), Some(&expected));
+        let model = cached.build::<HashMap<String, usize>, Vec<f64>>()?;
+        let direct = trainer
+            .train_file(&corpus_path)?
+            .build::<HashMap<String, usize>, Vec<f64>>()?;
+        assert_eq!(
+            model.predict(&["她", "花", "钱"])?,
+            direct.predict(&["她", "花", "钱"])?
+        );
+
+        std::fs::remove_file(corpus_path)?;
+        std::fs::remove_file(cache_path)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,148 @@
+//! 基于 FST 的特征索引
+//!
+//! 特征字符串按字典序压缩进有限状态转换器，查找只需按字节走一遍状态，
+//! 不需要计算哈希，也不需要分配内存，体积通常明显小于 `HashMap<String, usize>`。
+use crate::perceptron::{
+    Definition, Perceptron, TraitFeature, TraitParameter, TraitParameterStorage,
+};
+use anyhow::Result;
+use fst::{Map, MapBuilder, Streamer};
+#[cfg(feature = "serialization")]
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::collections::HashMap;
+use std::fmt::{Debug, Formatter};
+
+#[derive(Clone, Default)]
+pub struct FstFeatures {
+    map: Map<Vec<u8>>,
+}
+
+impl Debug for FstFeatures {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("FstFeatures")
+            .field("len", &self.map.len())
+            .field("bytes", &self.map.as_fst().size())
+            .finish()
+    }
+}
+
+impl FstFeatures {
+    /// 特征与编号不必有序，构建前先按字典序排序
+    pub fn from_entries<I, K>(features: I) -> Result<Self>
+    where
+        I: IntoIterator<Item = (K, usize)>,
+        K: AsRef<[u8]> + Ord,
+    {
+        let mut features: Vec<_> = features.into_iter().collect();
+        features.sort_unstable_by(|a, b| a.0.cmp(&b.0));
+        let mut builder = MapBuilder::memory();
+        for (key, value) in features {
+            builder.insert(key, value as u64)?;
+        }
+        Ok(FstFeatures {
+            map: Map::new(builder.into_inner()?)?,
+        })
+    }
+
+    pub fn from_bytes(bytes: Vec<u8>) -> Result<Self> {
+        Ok(FstFeatures {
+            map: Map::new(bytes)?,
+        })
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        self.map.as_fst().as_bytes()
+    }
+
+    pub fn len(&self) -> usize {
+        self.map.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.map.is_empty()
+    }
+
+    pub fn to_hash_map(&self) -> HashMap<String, usize> {
+        let mut features = HashMap::with_capacity(self.len());
+        let mut stream = self.map.stream();
+        while let Some((key, value)) = stream.next() {
+            features.insert(String::from_utf8_lossy(key).into_owned(), value as usize);
+        }
+        features
+    }
+}
+
+impl TraitFeature for FstFeatures {
+    #[inline]
+    fn get_with_key(&self, key: &str) -> Option<usize> {
+        self.map.get(key).map(|value| value as usize)
+    }
+}
+
+#[cfg(feature = "serialization")]
+impl Serialize for FstFeatures {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_bytes(self.as_bytes())
+    }
+}
+
+#[cfg(feature = "serialization")]
+impl<'de> Deserialize<'de> for FstFeatures {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let bytes = Vec::<u8>::deserialize(deserializer)?;
+        FstFeatures::from_bytes(bytes).map_err(serde::de::Error::custom)
+    }
+}
+
+pub type FstModel<Define> = Perceptron<Define, FstFeatures, Vec<f64>, f64>;
+
+impl<Define, ParamStorage, Param> Perceptron<Define, HashMap<String, usize>, ParamStorage, Param>
+where
+    Param: TraitParameter,
+    ParamStorage: TraitParameterStorage<Param>,
+    Define: Definition,
+{
+    /// 将特征表转换为 FST，参数保持不变
+    pub fn into_fst(self) -> Result<Perceptron<Define, FstFeatures, ParamStorage, Param>> {
+        let features = FstFeatures::from_entries(self.features)?;
+        Ok(
+            Perceptron::new_with_parameters(self.definition, features, self.parameters)
+                .with_metadata(self.metadata),
+        )
+    }
+}
+
+impl<Define, ParamStorage, Param> Perceptron<Define, FstFeatures, ParamStorage, Param>
+where
+    Param: TraitParameter,
+    ParamStorage: TraitParameterStorage<Param>,
+    Define: Definition,
+{
+    pub fn into_hash_map(self) -> Perceptron<Define, HashMap<String, usize>, ParamStorage, Param> {
+        let features = self.features.to_hash_map();
+        Perceptron::new_with_parameters(self.definition, features, self.parameters)
+            .with_metadata(self.metadata)
+    }
+}
+
+#[cfg(all(test, feature = "cws"))]
+mod tests {
+    use super::*;
+    use crate::perceptron::cws_test_model;
+
+    #[test]
+    fn test_fst_features() -> Result<()> {
+        let model = cws_test_model(&["2我", "2们", "6我们"], |i| (i % 5) as f64 - 2.0);
+        let features = model.features.clone();
+
+        let fst_model = model.clone().into_fst()?;
+        assert_eq!(fst_model.features.len(), 3);
+        assert_eq!(fst_model.features.get_with_key("6我们"), Some(6));
+        assert_eq!(fst_model.features.get_with_key("6我"), None);
+        assert_eq!(fst_model.predict("我们我")?, model.predict("我们我")?);
+
+        let bytes = fst_model.features.as_bytes().to_vec();
+        assert_eq!(FstFeatures::from_bytes(bytes)?.to_hash_map(), features);
+        Ok(())
+    }
+}
@@ -0,0 +1,101 @@
+//! 特征哈希：特征字符串直接哈希到固定大小的参数表，不再保存特征字典
+use crate::perceptron::{Perceptron, TraitFeature, TraitFeaturesTrainUtils};
+#[cfg(feature = "serialization")]
+use serde::{Deserialize, Serialize};
+
+#[cfg_attr(feature = "serialization", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HashedFeatures {
+    /// 参数表共有 `2^bits` 个特征槽
+    pub bits: u8,
+    /// 特征槽之前预留的位置，使用 viterbi 时为转移矩阵占用的部分
+    pub offset: usize,
+}
+
+impl Default for HashedFeatures {
+    fn default() -> Self {
+        HashedFeatures::new(20, 0)
+    }
+}
+
+impl HashedFeatures {
+    pub fn new(bits: u8, offset: usize) -> Self {
+        assert!(bits > 0 && bits < 32, "bits must be in 1..32");
+        HashedFeatures { bits, offset }
+    }
+
+    pub fn buckets(&self) -> usize {
+        1 << self.bits
+    }
+
+    /// FNV-1a，保证不同平台、不同版本得到相同的结果
+    #[inline]
+    pub fn bucket(&self, key: &str) -> usize {
+        let mut hash: u64 = 0xcbf29ce484222325;
+        for &byte in key.as_bytes() {
+            hash ^= byte as u64;
+            hash = hash.wrapping_mul(0x100000001b3);
+        }
+        (hash & (self.buckets() as u64 - 1)) as usize
+    }
+}
+
+impl TraitFeature for HashedFeatures {
+    #[inline]
+    fn get_with_key(&self, key: &str) -> Option<usize> {
+        Some(self.offset + self.bucket(key))
+    }
+}
+
+// 训练时参数表大小固定，插入与删除均无需记录
+impl TraitFeaturesTrainUtils for HashedFeatures {
+    fn feature_num(&self) -> usize {
+        self.buckets()
+    }
+
+    fn insert_feature(&mut self, _key: String, _value: usize) {}
+
+    fn remove_feature(&mut self, _key: &str) -> Option<usize> {
+        None
+    }
+
+    fn put_feature(&mut self, _key: String, _value: usize) {}
+
+    fn del_feature(&mut self, _key: &str) -> Option<usize> {
+        None
+    }
+}
+
+pub type HashedModel<Define> = Perceptron<Define, HashedFeatures, Vec<f64>, f64>;
+
+#[cfg(all(test, feature = "cws"))]
+mod tests {
+    use super::*;
+    use crate::perceptron::{Definition, Trainer};
+    use crate::CWSDefinition;
+    use anyhow::Result;
+
+    #[test]
+    fn test_hashed_training() -> Result<()> {
+        let corpus = "我们 是 中国 人\n中国 人民 很 勤劳\n我们 热爱 和平\n";
+        let definition = CWSDefinition::new();
+        let dataset = definition.parse_gold_features(corpus.as_bytes())?;
+
+        let mut trainer: Trainer<CWSDefinition> =
+            Trainer::new_with_define(definition).epoch(5).verbose(false);
+        trainer.eval_set = Some(dataset.clone());
+        trainer.train_set = Some(dataset);
+
+        let model: HashedModel<CWSDefinition> = trainer.build_hashed(12)?;
+        assert_eq!(model.parameters.len(), 4 * 4 + (1 << 12) * 4);
+        assert_eq!(
+            model.features.get_with_key("2我"),
+            Some(4 + model.features.bucket("2我"))
+        );
+        assert_eq!(
+            model.predict("我们是中国人")?,
+            vec!["我们", "是", "中国", "人"]
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,272 @@
+//! 模型文件头
+//!
+//! | 字节  | 含义                         |
+//! | ----- | ---------------------------- |
+//! | 0..4  | 魔数 `LTPM`                  |
+//! | 4..6  | 格式版本（小端）             |
+//! | 6     | 任务类型                     |
+//! | 7     | 负载格式                     |
+//! | 8..16 | 标签表的 FNV-1a 哈希（小端） |
+//!
+//! 版本 2 起，文件头之后紧跟元数据块：4 字节长度（小端）与 JSON 编码的
+//! [`ModelMetadata`](crate::perceptron::ModelMetadata)。
+//!
+//! 旧版本保存的模型没有文件头，读取时仍然兼容。
+use crate::perceptron::legacy::LegacyModelKind;
+use crate::perceptron::ENCRYPTED_MAGIC;
+use crate::perceptron::{Definition, TraitFeature, TraitParameter, TraitParameterStorage};
+#[cfg(feature = "cws")]
+use crate::CWSDefinition;
+#[cfg(feature = "ner")]
+use crate::NERDefinition;
+#[cfg(feature = "pos")]
+use crate::POSDefinition;
+use crate::Perceptron;
+use anyhow::{anyhow, Result};
+#[cfg(feature = "serialization")]
+use serde::{Deserialize, Serialize};
+use std::fmt::{Display, Formatter};
+use std::io::{BufRead, Write};
+
+pub const MAGIC: [u8; 4] = *b"LTPM";
+pub const FORMAT_VERSION: u16 = 2;
+pub const HEADER_SIZE: usize = 16;
+
+#[cfg_attr(feature = "serialization", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serialization", serde(rename_all = "lowercase"))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[repr(u8)]
+pub enum ModelTask {
+    CWS = 1,
+    POS = 2,
+    NER = 3,
+}
+
+impl Display for ModelTask {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            ModelTask::CWS => "CWS",
+            ModelTask::POS => "POS",
+            ModelTask::NER => "NER",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl TryFrom<u8> for ModelTask {
+    type Error = anyhow::Error;
+
+    fn try_from(value: u8) -> Result<Self> {
+        match value {
+            1 => Ok(ModelTask::CWS),
+            2 => Ok(ModelTask::POS),
+            3 => Ok(ModelTask::NER),
+            _ => Err(anyhow!("unknown model task id {}", value)),
+        }
+    }
+}
+
+/// 标注任务与其定义的对应关系
+pub trait TaskDefinition: Definition {
+    const TASK: ModelTask;
+}
+
+#[cfg(feature = "cws")]
+impl TaskDefinition for CWSDefinition {
+    const TASK: ModelTask = ModelTask::CWS;
+}
+
+#[cfg(feature = "pos")]
+impl TaskDefinition for POSDefinition {
+    const TASK: ModelTask = ModelTask::POS;
+}
+
+#[cfg(feature = "ner")]
+impl TaskDefinition for NERDefinition {
+    const TASK: ModelTask = ModelTask::NER;
+}
+
+impl<Define, Feature, ParamStorage, Param> Perceptron<Define, Feature, ParamStorage, Param>
+where
+    Feature: TraitFeature,
+    Param: TraitParameter,
+    ParamStorage: TraitParameterStorage<Param>,
+    Define: TaskDefinition,
+{
+    pub const TASK: ModelTask = Define::TASK;
+
+    pub fn header(&self, payload: PayloadFormat) -> ModelHeader {
+        ModelHeader::new(Define::TASK, payload, &self.definition.labels())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum PayloadFormat {
+    Json = 1,
+    Avro = 2,
+}
+
+impl TryFrom<u8> for PayloadFormat {
+    type Error = anyhow::Error;
+
+    fn try_from(value: u8) -> Result<Self> {
+        match value {
+            1 => Ok(PayloadFormat::Json),
+            2 => Ok(PayloadFormat::Avro),
+            _ => Err(anyhow!("unknown model payload format id {}", value)),
+        }
+    }
+}
+
+/// 标签表的稳定哈希，用于判断两个模型的标签是否一致
+pub fn label_hash<S: AsRef<str>>(labels: &[S]) -> u64 {
+    const OFFSET: u64 = 0xcbf29ce484222325;
+    const PRIME: u64 = 0x100000001b3;
+    let mut hash = OFFSET;
+    for label in labels {
+        for &byte in label.as_ref().as_bytes().iter().chain(&[0u8]) {
+            hash ^= byte as u64;
+            hash = hash.wrapping_mul(PRIME);
+        }
+    }
+    hash
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ModelHeader {
+    pub version: u16,
+    pub task: ModelTask,
+    pub payload: PayloadFormat,
+    pub label_hash: u64,
+}
+
+impl ModelHeader {
+    pub fn new<S: AsRef<str>>(task: ModelTask, payload: PayloadFormat, labels: &[S]) -> Self {
+        ModelHeader {
+            version: FORMAT_VERSION,
+            task,
+            payload,
+            label_hash: label_hash(labels),
+        }
+    }
+
+    pub fn to_bytes(&self) -> [u8; HEADER_SIZE] {
+        let mut bytes = [0u8; HEADER_SIZE];
+        bytes[0..4].copy_from_slice(&MAGIC);
+        bytes[4..6].copy_from_slice(&self.version.to_le_bytes());
+        bytes[6] = self.task as u8;
+        bytes[7] = self.payload as u8;
+        bytes[8..16].copy_from_slice(&self.label_hash.to_le_bytes());
+        bytes
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        if bytes.len() < HEADER_SIZE || bytes[0..4] != MAGIC {
+            return Err(anyhow!("not an ltp model file"));
+        }
+        let version = u16::from_le_bytes([bytes[4], bytes[5]]);
+        if version > FORMAT_VERSION {
+            return Err(anyhow!(
+                "model built with newer format (version {}, this build supports up to {}), \
+                 please upgrade ltp",
+                version,
+                FORMAT_VERSION
+            ));
+        }
+        let mut label_hash = [0u8; 8];
+        label_hash.copy_from_slice(&bytes[8..16]);
+        Ok(ModelHeader {
+            version,
+            task: ModelTask::try_from(bytes[6])?,
+            payload: PayloadFormat::try_from(bytes[7])?,
+            label_hash: u64::from_le_bytes(label_hash),
+        })
+    }
+
+    pub fn write<W: Write>(&self, writer: &mut W) -> Result<()> {
+        writer.write_all(&self.to_bytes())?;
+        Ok(())
+    }
+
+    /// 查看文件头但不消耗；没有文件头的旧模型返回 `None`
+    pub fn peek<R: BufRead>(reader: &mut R) -> Result<Option<Self>> {
+        let buf = reader.fill_buf()?;
+        if !buf.starts_with(&MAGIC) {
+            if buf.starts_with(&ENCRYPTED_MAGIC) {
+                return Err(anyhow!("this model is encrypted, load it with its cipher"));
+            }
+            if let Some(kind) = LegacyModelKind::detect(buf) {
+                return Err(anyhow!(
+                    "this is a legacy C++ LTP {} model, convert it with `legacy::load_legacy`",
+                    kind
+                ));
+            }
+            return Ok(None);
+        }
+        Self::from_bytes(buf).map(Some)
+    }
+
+    /// 读取文件头；没有文件头的旧模型返回 `None` 且不消耗任何字节
+    pub fn read<R: BufRead>(reader: &mut R) -> Result<Option<Self>> {
+        let header = Self::peek(reader)?;
+        if header.is_some() {
+            reader.consume(HEADER_SIZE);
+        }
+        Ok(header)
+    }
+
+    /// 文件头之后是否有元数据块
+    pub fn has_metadata(&self) -> bool {
+        self.version >= 2
+    }
+
+    pub fn expect_task(&self, task: ModelTask) -> Result<()> {
+        if self.task != task {
+            return Err(anyhow!("this is a {} model, expected {}", self.task, task));
+        }
+        Ok(())
+    }
+
+    pub fn expect_labels<S: AsRef<str>>(&self, labels: &[S]) -> Result<()> {
+        if self.label_hash != label_hash(labels) {
+            return Err(anyhow!(
+                "the labels of this {} model do not match its header, the file may be corrupted",
+                self.task
+            ));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_header() -> Result<()> {
+        let labels = ["B", "M", "E", "S"];
+        let header = ModelHeader::new(ModelTask::CWS, PayloadFormat::Avro, &labels);
+        let mut bytes = header.to_bytes().to_vec();
+        bytes.extend_from_slice(b"payload");
+
+        let mut reader = bytes.as_slice();
+        assert_eq!(ModelHeader::read(&mut reader)?, Some(header));
+        assert_eq!(reader, b"payload");
+
+        assert!(header.expect_labels(&labels).is_ok());
+        assert!(header.expect_labels(&["B", "M", "E"]).is_err());
+        let err = header.expect_task(ModelTask::POS).unwrap_err();
+        assert_eq!(err.to_string(), "this is a CWS model, expected POS");
+
+        let mut newer = header.to_bytes();
+        newer[4] = 0xff;
+        let err = ModelHeader::from_bytes(&newer).unwrap_err();
+        assert!(err.to_string().contains("newer format"));
+
+        let mut reader: &[u8] = b"{\"definition\":{}}";
+        assert_eq!(ModelHeader::read(&mut reader)?, None);
+        assert_eq!(reader.len(), 17);
+        Ok(())
+    }
+}
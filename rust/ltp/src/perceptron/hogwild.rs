@@ -0,0 +1,211 @@
+//! Hogwild 风格的并行训练
+//!
+//! 各线程共享同一份参数，更新时不加锁，只对单个参数做原子的读-改-写。线程之间看到的参数可能
+//! 略有滞后，训练结果与线程调度有关，不保证可复现，换来的是不需要每轮合并多份参数。
+use crate::perceptron::definition::resolve_uncertain;
+use crate::perceptron::{
+    Definition, FeatureArena, Perceptron, Sample, Trainer, TraitFeature, TraitFeaturesTrainUtils,
+    TraitParameter, TraitParameterStorage, TraitParameterStorageTrainUtils,
+    TraitParameterStorageUtils,
+};
+use anyhow::Result;
+use num_traits::{Float, NumCast};
+use std::fmt::Display;
+use std::iter::zip;
+use std::marker::PhantomData;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::thread;
+
+/// 以 `f64` 位模式保存在原子变量中的共享参数，克隆只增加引用计数
+#[derive(Debug, Clone)]
+pub struct HogwildParameters<Param> {
+    data: Arc<[AtomicU64]>,
+    __phantom: PhantomData<Param>,
+}
+
+impl<Param: TraitParameter> HogwildParameters<Param> {
+    pub fn new(size: usize) -> Self {
+        let data: Vec<AtomicU64> = (0..size).map(|_| AtomicU64::new(0f64.to_bits())).collect();
+        HogwildParameters {
+            data: data.into(),
+            __phantom: PhantomData,
+        }
+    }
+
+    pub fn add(&self, index: usize, value: Param) {
+        let value: f64 = NumCast::from(value).unwrap_or(0.0);
+        let _ = self.data[index].fetch_update(Ordering::Relaxed, Ordering::Relaxed, |bits| {
+            Some((f64::from_bits(bits) + value).to_bits())
+        });
+    }
+
+    /// 复制出当前参数的一份快照
+    pub fn snapshot<ParamStorage>(&self) -> ParamStorage
+    where
+        ParamStorage: TraitParameterStorageTrainUtils<Param>,
+    {
+        let mut parameters = ParamStorage::init(Param::zero(), self.data.len());
+        for i in 0..self.data.len() {
+            parameters[i] = self.get_param(i);
+        }
+        parameters
+    }
+}
+
+impl<Param> TraitParameterStorageUtils for HogwildParameters<Param> {
+    fn len(&self) -> usize {
+        self.data.len()
+    }
+}
+
+impl<Param: TraitParameter> TraitParameterStorage<Param> for HogwildParameters<Param> {
+    #[inline]
+    fn get_param(&self, index: usize) -> Param {
+        let value = f64::from_bits(self.data[index].load(Ordering::Relaxed));
+        Param::from(value).unwrap_or_else(Param::zero)
+    }
+}
+
+impl<Define, Feature, Param> Perceptron<Define, Feature, HogwildParameters<Param>, Param>
+where
+    Feature: TraitFeature,
+    Param: TraitParameter,
+    Define: Definition,
+{
+    pub fn hogwild_train_iter(&self, inputs: &FeatureArena<String>, labels: &[usize]) {
+        let label_num = self.definition.label_num();
+        let features = self.features.get_arena(inputs);
+        let preds = self.decode(&features);
+        let labels = &*resolve_uncertain(labels, &preds);
+
+        if labels.ne(&preds) {
+            for (idx, (&gold, &pred)) in zip(labels, &preds)
+                .enumerate()
+                .filter(|&(_, (gold, pred))| gold != pred)
+            {
+                for &feat in &features[idx] {
+                    self.parameters.add(feat * label_num + gold, Param::one());
+                    self.parameters.add(feat * label_num + pred, -Param::one());
+                }
+
+                if self.definition.use_viterbi() && idx > 0 {
+                    // transition
+                    self.parameters
+                        .add(labels[idx - 1] * label_num + gold, Param::one());
+                    self.parameters
+                        .add(preds[idx - 1] * label_num + pred, -Param::one());
+                }
+            }
+        }
+    }
+}
+
+impl<Define, Param> Trainer<Define, Param>
+where
+    Param: TraitParameter + Display + Sync + Send + 'static,
+    Define: Definition + Sync + Send + 'static,
+{
+    pub fn build_hogwild<Feature, ParamStorage>(
+        &self,
+        features: Feature,
+        threads: usize,
+    ) -> Result<Perceptron<Define, Feature, ParamStorage, Param>>
+    where
+        ParamStorage: TraitParameterStorage<Param> + TraitParameterStorageTrainUtils<Param>,
+        Feature: TraitFeature + TraitFeaturesTrainUtils + Sync,
+    {
+        let label_num = self.definition.label_num();
+        let bias = if self.definition.use_viterbi() {
+            // transition part of viterbi
+            label_num * label_num
+        } else {
+            0
+        };
+        let parameters_len = bias + features.feature_num() * label_num;
+        let shared = HogwildParameters::<Param>::new(parameters_len);
+        let threads = threads.max(1);
+
+        let mut best_f1 = f64::neg_infinity();
+        let mut best_parameters = ParamStorage::default();
+
+        if let Some(train_set) = &self.train_set {
+            let mut rng = rand::thread_rng();
+            for epoch in 0..self.epoch {
+                let order = self.epoch_order(&mut rng);
+                let chunk_size = order.len() / threads + 1;
+                thread::scope(|scope| {
+                    for chunk in order.chunks(chunk_size) {
+                        let perceptron = Perceptron::new_with_parameters(
+                            self.definition.clone(),
+                            &features,
+                            shared.clone(),
+                        );
+                        scope.spawn(move || {
+                            for &index in chunk {
+                                let Sample {
+                                    features: feature,
+                                    labels,
+                                } = &train_set[index];
+                                perceptron.hogwild_train_iter(feature, labels);
+                            }
+                        });
+                    }
+                });
+
+                let perceptron = Perceptron::new_with_parameters(
+                    self.definition.clone(),
+                    &features,
+                    shared.snapshot::<ParamStorage>(),
+                );
+                let (p, r, f1) = self.evaluate(&perceptron)?;
+                println!(
+                    "epoch: {}, precision: {}, recall: {}, f1: {}",
+                    epoch, p, r, f1
+                );
+                #[cfg(feature = "tracing")]
+                tracing::info!(epoch, precision = p, recall = r, f1, "epoch finished");
+                if f1 > best_f1 {
+                    best_f1 = f1;
+                    best_parameters = perceptron.parameters;
+                }
+            }
+        }
+
+        Ok(Perceptron::new_with_parameters(
+            self.definition.clone(),
+            features,
+            best_parameters,
+        ))
+    }
+}
+
+#[cfg(all(test, feature = "cws"))]
+mod tests {
+    use super::*;
+    use crate::CWSDefinition;
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_hogwild() -> Result<()> {
+        let define = CWSDefinition::default();
+        let dataset =
+            define.parse_gold_features("他 叫 汤姆 去 拿 外衣 。\n我们 去 拿 外衣\n".as_bytes())?;
+        let mut trainer: Trainer<CWSDefinition> = Trainer::new()
+            .algorithm(crate::Algorithm::Hogwild(2))
+            .epoch(5)
+            .verbose(false)
+            .compress(false);
+        trainer.eval_set = Some(dataset.clone());
+        trainer.train_set = Some(dataset);
+
+        let model = trainer.build::<HashMap<String, usize>, Vec<f64>>()?;
+        let (_, _, f1) = trainer.evaluate(&model)?;
+        assert!(f1 > 0.9);
+        assert_eq!(
+            model.predict("我们去拿外衣")?,
+            vec!["我们", "去", "拿", "外衣"]
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,186 @@
+//! 参数矩阵的存储顺序
+//!
+//! 逻辑上参数总是按 `feature * label_num + label` 访问；`FeatureMajor` 即按此顺序存放，
+//! 解码时逐特征读取全部标签的参数，内存是顺序访问的；`LabelMajor` 则把同一标签的参数连续存放，
+//! 适合逐标签扫描全部特征的场景（如按标签裁剪、统计）。
+//!
+//! 选用的存储顺序记录在模型元数据中随模型保存，参数本身总是按 `FeatureMajor` 写入文件，
+//! 加载后用 [`Perceptron::with_saved_layout`] 恢复。
+use crate::perceptron::{
+    Definition, Perceptron, TraitFeature, TraitParameter, TraitParameterStorage,
+    TraitParameterStorageUtils,
+};
+use anyhow::{anyhow, Result};
+#[cfg(feature = "serialization")]
+use serde::{Deserialize, Serialize};
+
+#[cfg_attr(feature = "serialization", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Layout {
+    #[default]
+    FeatureMajor,
+    LabelMajor,
+}
+
+impl Layout {
+    pub fn is_feature_major(&self) -> bool {
+        *self == Layout::FeatureMajor
+    }
+}
+
+#[cfg_attr(feature = "serialization", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct LayoutParameters<Param> {
+    layout: Layout,
+    label_num: usize,
+    rows: usize,
+    data: Vec<Param>,
+}
+
+impl<Param: TraitParameter> LayoutParameters<Param> {
+    pub fn new<ParamStorage>(
+        parameters: &ParamStorage,
+        label_num: usize,
+        layout: Layout,
+    ) -> Result<Self>
+    where
+        ParamStorage: TraitParameterStorage<Param>,
+    {
+        if label_num == 0 || !parameters.len().is_multiple_of(label_num) {
+            return Err(anyhow!(
+                "{} parameters can not be split into rows of {} labels",
+                parameters.len(),
+                label_num
+            ));
+        }
+        let rows = parameters.len() / label_num;
+        let mut data = vec![Param::zero(); parameters.len()];
+        for i in 0..parameters.len() {
+            data[Self::physical(layout, label_num, rows, i)] = parameters.get_param(i);
+        }
+        Ok(LayoutParameters {
+            layout,
+            label_num,
+            rows,
+            data,
+        })
+    }
+
+    #[inline]
+    fn physical(layout: Layout, label_num: usize, rows: usize, index: usize) -> usize {
+        match layout {
+            Layout::FeatureMajor => index,
+            Layout::LabelMajor => (index % label_num) * rows + index / label_num,
+        }
+    }
+
+    pub fn layout(&self) -> Layout {
+        self.layout
+    }
+
+    /// 还原为按特征存放的普通参数
+    pub fn to_vec(&self) -> Vec<Param> {
+        (0..self.data.len()).map(|i| self.get_param(i)).collect()
+    }
+}
+
+impl<Param> TraitParameterStorageUtils for LayoutParameters<Param> {
+    fn len(&self) -> usize {
+        self.data.len()
+    }
+}
+
+impl<Param: TraitParameter> TraitParameterStorage<Param> for LayoutParameters<Param> {
+    #[inline]
+    fn get_param(&self, index: usize) -> Param {
+        self.data[Self::physical(self.layout, self.label_num, self.rows, index)]
+    }
+}
+
+impl<Define, Feature, ParamStorage, Param> Perceptron<Define, Feature, ParamStorage, Param>
+where
+    Feature: TraitFeature,
+    Param: TraitParameter,
+    ParamStorage: TraitParameterStorage<Param>,
+    Define: Definition,
+{
+    /// 转换参数的存储顺序，并记录在元数据中
+    pub fn with_layout(
+        mut self,
+        layout: Layout,
+    ) -> Result<Perceptron<Define, Feature, LayoutParameters<Param>, Param>> {
+        let parameters =
+            LayoutParameters::new(&self.parameters, self.definition.label_num(), layout)?;
+        self.metadata.layout = layout;
+        Ok(
+            Perceptron::new_with_parameters(self.definition, self.features, parameters)
+                .with_metadata(self.metadata),
+        )
+    }
+
+    /// 按模型元数据中记录的存储顺序转换，用于恢复保存前选用的顺序
+    pub fn with_saved_layout(
+        self,
+    ) -> Result<Perceptron<Define, Feature, LayoutParameters<Param>, Param>> {
+        let layout = self.metadata.layout;
+        self.with_layout(layout)
+    }
+}
+
+impl<Define, Feature, Param> Perceptron<Define, Feature, LayoutParameters<Param>, Param>
+where
+    Feature: TraitFeature,
+    Param: TraitParameter,
+    Define: Definition,
+{
+    /// 还原为按特征存放的参数以便保存，元数据中仍记录原来的存储顺序
+    pub fn into_feature_major(self) -> Perceptron<Define, Feature, Vec<Param>, Param> {
+        let parameters = self.parameters.to_vec();
+        Perceptron::new_with_parameters(self.definition, self.features, parameters)
+            .with_metadata(self.metadata)
+    }
+}
+
+#[cfg(all(test, feature = "pos"))]
+mod tests {
+    use super::*;
+    use crate::POSDefinition;
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_layout() -> Result<()> {
+        let labels: Vec<String> = (0..20).map(|i| format!("t{}", i)).collect();
+        let mut features = HashMap::new();
+        features.insert("2中国".to_string(), 0);
+        features.insert("2人".to_string(), 1);
+        let parameters: Vec<f64> = (0..2 * 20).map(|i| ((i * 13) % 7) as f64).collect();
+        let model = Perceptron::new_with_parameters(
+            POSDefinition::new(labels),
+            features,
+            parameters.clone(),
+        );
+        let words = ["中国", "人"];
+        let expected: Vec<String> = model
+            .predict(&words)?
+            .into_iter()
+            .map(String::from)
+            .collect();
+
+        let converted = model.with_layout(Layout::LabelMajor)?;
+        assert_eq!(converted.parameters.layout(), Layout::LabelMajor);
+        assert_eq!(converted.metadata.layout, Layout::LabelMajor);
+        assert_eq!(converted.parameters.get_param(21), parameters[21]);
+        assert_eq!(converted.predict(&words)?, expected);
+
+        // 保存用的参数按特征存放，元数据仍记录选用的存储顺序
+        let restored = converted.into_feature_major();
+        assert_eq!(restored.parameters, parameters);
+        assert_eq!(restored.metadata.layout, Layout::LabelMajor);
+        let reloaded = restored.with_saved_layout()?;
+        assert_eq!(reloaded.parameters.layout(), Layout::LabelMajor);
+
+        assert!(LayoutParameters::new(&vec![0.0; 5], 2, Layout::LabelMajor).is_err());
+        assert!(LayoutParameters::new(&vec![0.0; 4], 0, Layout::FeatureMajor).is_err());
+        Ok(())
+    }
+}
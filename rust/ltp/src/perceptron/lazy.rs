@@ -0,0 +1,393 @@
+//! 按需加载的参数
+//!
+//! 参数矩阵分为两段：热段（转移矩阵和权重最大的若干特征）常驻内存，随模型一起序列化；
+//! 冷段是完整的参数矩阵，以 `f64` 小端序逐行写入单独的文件，未命中热段时按行读取，
+//! 并在不超过驻留预算的前提下缓存最近用到的行。
+use crate::perceptron::{
+    Definition, Perceptron, TraitFeature, TraitParameter, TraitParameterStorage,
+    TraitParameterStorageUtils,
+};
+use anyhow::{anyhow, Result};
+use num_traits::NumCast;
+#[cfg(feature = "serialization")]
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::{Debug, Formatter};
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::Path;
+use std::sync::{Arc, Mutex, OnceLock};
+
+pub type LazyModel<Define, Feature = HashMap<String, usize>, Param = f64> =
+    Perceptron<Define, Feature, LazyParameters<Param>, Param>;
+
+const CELL: usize = std::mem::size_of::<f64>();
+
+#[cfg_attr(feature = "serialization", derive(Serialize, Deserialize))]
+#[derive(Debug, Default)]
+pub struct LazyParameters<Param> {
+    label_num: usize,
+    rows: usize,
+    // 热段中的行号 -> 在 `hot` 中的位置
+    slots: HashMap<usize, usize>,
+    hot: Vec<Param>,
+    #[cfg_attr(feature = "serialization", serde(skip))]
+    cold: Option<ColdSection>,
+}
+
+struct Residency {
+    // 行号 -> (整行参数, 最近一次用到的时刻)
+    rows: HashMap<usize, (Arc<[f64]>, u64)>,
+    // 时刻 -> 行号，最早的一项即最久未使用的行
+    recency: BTreeMap<u64, usize>,
+    clock: u64,
+}
+
+pub struct ColdSection {
+    file: Mutex<File>,
+    residency: Mutex<Residency>,
+    budget: usize,
+    // 第一次读取失败的原因，解码后由 `LazyParameters::check` 报告
+    error: OnceLock<String>,
+}
+
+impl Debug for ColdSection {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ColdSection")
+            .field("budget", &self.budget)
+            .finish()
+    }
+}
+
+impl ColdSection {
+    /// `budget` 为缓存冷段行的最大字节数，为 0 时每次未命中都直接读文件
+    pub fn open<P: AsRef<Path>>(path: P, budget: usize) -> Result<Self> {
+        Ok(ColdSection {
+            file: Mutex::new(File::open(path)?),
+            residency: Mutex::new(Residency {
+                rows: HashMap::new(),
+                recency: BTreeMap::new(),
+                clock: 0,
+            }),
+            budget,
+            error: OnceLock::new(),
+        })
+    }
+
+    fn read_row(&self, row: usize, label_num: usize) -> Result<Arc<[f64]>> {
+        let mut bytes = vec![0u8; label_num * CELL];
+        let mut file = self
+            .file
+            .lock()
+            .map_err(|_| anyhow!("cold section poisoned"))?;
+        file.seek(SeekFrom::Start((row * label_num * CELL) as u64))?;
+        file.read_exact(&mut bytes)?;
+        Ok(bytes
+            .chunks_exact(CELL)
+            .map(|b| f64::from_le_bytes(b.try_into().unwrap()))
+            .collect())
+    }
+
+    /// 一个特征的整行参数，命中缓存时不读文件
+    fn row(&self, row: usize, label_num: usize) -> Result<Arc<[f64]>> {
+        let capacity = self.budget / (label_num * CELL);
+        let mut residency = self
+            .residency
+            .lock()
+            .map_err(|_| anyhow!("cold section poisoned"))?;
+        let Residency {
+            rows,
+            recency,
+            clock,
+        } = &mut *residency;
+        *clock += 1;
+        if let Some((values, used)) = rows.get_mut(&row) {
+            recency.remove(used);
+            recency.insert(*clock, row);
+            *used = *clock;
+            return Ok(values.clone());
+        }
+
+        let values = self.read_row(row, label_num)?;
+        if capacity > 0 {
+            if rows.len() >= capacity {
+                if let Some((_, oldest)) = recency.pop_first() {
+                    rows.remove(&oldest);
+                }
+            }
+            recency.insert(*clock, row);
+            rows.insert(row, (values.clone(), *clock));
+        }
+        Ok(values)
+    }
+
+    /// 当前缓存的冷段字节数
+    pub fn resident_bytes(&self) -> usize {
+        self.residency
+            .lock()
+            .map(|r| r.rows.values().map(|(v, _)| v.len() * CELL).sum())
+            .unwrap_or(0)
+    }
+}
+
+impl<Param: TraitParameter> LazyParameters<Param> {
+    /// 保留 `hot_rows` 个权重（L1 范数）最大的特征行，`pinned` 行总是保留在热段，
+    /// 完整的参数矩阵写入 `cold`
+    pub fn split<ParamStorage, W>(
+        parameters: &ParamStorage,
+        label_num: usize,
+        pinned: usize,
+        hot_rows: usize,
+        mut cold: W,
+    ) -> Result<Self>
+    where
+        ParamStorage: TraitParameterStorage<Param>,
+        W: Write,
+    {
+        if label_num == 0 || !parameters.len().is_multiple_of(label_num) {
+            return Err(anyhow!(
+                "{} parameters can not be split into rows of {} labels",
+                parameters.len(),
+                label_num
+            ));
+        }
+        let rows = parameters.len() / label_num;
+        let row = |r: usize| (0..label_num).map(move |l| parameters.get_param(r * label_num + l));
+
+        for r in 0..rows {
+            for value in row(r) {
+                let value: f64 = NumCast::from(value).unwrap_or(0.0);
+                cold.write_all(&value.to_le_bytes())?;
+            }
+        }
+        cold.flush()?;
+
+        let mut ranked: Vec<(usize, Param)> = (pinned.min(rows)..rows)
+            .map(|r| (r, row(r).fold(Param::zero(), |acc, v| acc + v.abs())))
+            .filter(|(_, norm)| *norm > Param::zero())
+            .collect();
+        ranked.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(std::cmp::Ordering::Equal));
+
+        let mut slots = HashMap::new();
+        let mut hot = Vec::new();
+        let selected =
+            (0..pinned.min(rows)).chain(ranked.into_iter().take(hot_rows).map(|(r, _)| r));
+        for (slot, r) in selected.enumerate() {
+            slots.insert(r, slot);
+            hot.extend(row(r));
+        }
+
+        Ok(LazyParameters {
+            label_num,
+            rows,
+            slots,
+            hot,
+            cold: None,
+        })
+    }
+
+    /// 挂载冷段，文件的大小须与参数矩阵一致，并且可以读取
+    pub fn attach(&mut self, cold: ColdSection) -> Result<()> {
+        let expected = (self.len() * CELL) as u64;
+        let actual = cold
+            .file
+            .lock()
+            .map_err(|_| anyhow!("cold section poisoned"))?
+            .metadata()?
+            .len();
+        if actual != expected {
+            return Err(anyhow!(
+                "cold section has {} bytes, expected {} for this model",
+                actual,
+                expected
+            ));
+        }
+        // 先读一行，确认文件可读
+        if let Some(last) = self.rows.checked_sub(1) {
+            cold.read_row(last, self.label_num)?;
+        }
+        self.cold = Some(cold);
+        Ok(())
+    }
+
+    pub fn cold(&self) -> Option<&ColdSection> {
+        self.cold.as_ref()
+    }
+
+    /// 热段的行数
+    pub fn hot_rows(&self) -> usize {
+        self.slots.len()
+    }
+
+    /// 冷段中的一整行，读取失败时记下错误，由 [`check`](TraitParameterStorage::check) 报告
+    fn cold_row(&self, row: usize) -> Option<Arc<[f64]>> {
+        let cold = self.cold.as_ref()?;
+        match cold.row(row, self.label_num) {
+            Ok(values) => Some(values),
+            Err(err) => {
+                let _ = cold.error.set(err.to_string());
+                None
+            }
+        }
+    }
+}
+
+impl<Param> TraitParameterStorageUtils for LazyParameters<Param> {
+    fn len(&self) -> usize {
+        self.rows * self.label_num
+    }
+}
+
+impl<Param: TraitParameter> TraitParameterStorage<Param> for LazyParameters<Param> {
+    /// 冷段未挂载或读取失败时返回 0，解码后由 [`check`](Self::check) 报告错误
+    fn get_param(&self, index: usize) -> Param {
+        let (row, label) = (index / self.label_num, index % self.label_num);
+        if let Some(&slot) = self.slots.get(&row) {
+            return self.hot[slot * self.label_num + label];
+        }
+        self.cold_row(row)
+            .and_then(|values| Param::from(values[label]))
+            .unwrap_or_else(Param::zero)
+    }
+
+    fn add_row(&self, start: usize, scores: &mut [Param]) {
+        let row = start / self.label_num;
+        if !start.is_multiple_of(self.label_num) || scores.len() != self.label_num {
+            for (offset, score) in scores.iter_mut().enumerate() {
+                *score += self.get_param(start + offset);
+            }
+        } else if let Some(&slot) = self.slots.get(&row) {
+            let values = &self.hot[slot * self.label_num..(slot + 1) * self.label_num];
+            for (score, &value) in scores.iter_mut().zip(values) {
+                *score += value;
+            }
+        } else if let Some(values) = self.cold_row(row) {
+            for (score, &value) in scores.iter_mut().zip(values.iter()) {
+                *score += Param::from(value).unwrap_or_else(Param::zero);
+            }
+        }
+    }
+
+    fn check(&self) -> Result<()> {
+        match &self.cold {
+            Some(cold) => match cold.error.get() {
+                Some(err) => Err(anyhow!("failed to read cold section: {}", err)),
+                None => Ok(()),
+            },
+            None if self.slots.len() < self.rows => Err(anyhow!(
+                "cold section is not attached, see LazyModel::with_cold_section"
+            )),
+            None => Ok(()),
+        }
+    }
+}
+
+impl<Define, Feature, ParamStorage, Param> Perceptron<Define, Feature, ParamStorage, Param>
+where
+    Feature: TraitFeature,
+    Param: TraitParameter,
+    ParamStorage: TraitParameterStorage<Param>,
+    Define: Definition,
+{
+    /// 拆分出热段与冷段，冷段写入 `cold`，返回只含热段的模型
+    pub fn split_sections<W: Write>(
+        self,
+        hot_rows: usize,
+        cold: W,
+    ) -> Result<LazyModel<Define, Feature, Param>> {
+        let label_num = self.definition.label_num();
+        // 转移矩阵占据前 label_num 行
+        let pinned = if self.definition.use_viterbi() {
+            label_num
+        } else {
+            0
+        };
+        let parameters =
+            LazyParameters::split(&self.parameters, label_num, pinned, hot_rows, cold)?;
+        Ok(
+            Perceptron::new_with_parameters(self.definition, self.features, parameters)
+                .with_metadata(self.metadata),
+        )
+    }
+}
+
+impl<Define, Feature, Param> LazyModel<Define, Feature, Param>
+where
+    Feature: TraitFeature,
+    Param: TraitParameter,
+    Define: Definition,
+{
+    /// 挂载冷段文件，`budget` 为冷段缓存的字节上限
+    pub fn with_cold_section<P: AsRef<Path>>(mut self, path: P, budget: usize) -> Result<Self> {
+        let cold = ColdSection::open(path, budget)?;
+        self.parameters.attach(cold)?;
+        Ok(self)
+    }
+}
+
+#[cfg(all(test, feature = "cws"))]
+mod tests {
+    use super::*;
+    use crate::perceptron::cws_test_model;
+    use crate::CWSDefinition;
+
+    #[test]
+    fn test_lazy_sections() -> Result<()> {
+        let features: HashMap<String, usize> = (4..10).map(|i| (format!("f{}", i), i)).collect();
+        let parameters: Vec<f64> = (0..40).map(|i| ((i * 7) % 5) as f64 - 2.0).collect();
+        let model: Perceptron<CWSDefinition, _, _, f64> =
+            Perceptron::new_with_parameters(CWSDefinition::default(), features, parameters.clone());
+
+        let path = std::env::temp_dir().join(format!("ltp-cold-{}.bin", std::process::id()));
+        let lazy = model.split_sections(2, File::create(&path)?)?;
+        assert_eq!(lazy.parameters.hot_rows(), 4 + 2);
+
+        let lazy = lazy.with_cold_section(&path, 4 * CELL)?;
+        for (i, &expected) in parameters.iter().enumerate() {
+            assert_eq!(lazy.parameters.get_param(i), expected);
+        }
+        for row in 0..10 {
+            let mut scores = vec![0.0; 4];
+            lazy.parameters.add_row(row * 4, &mut scores);
+            assert_eq!(scores, parameters[row * 4..row * 4 + 4]);
+        }
+        assert!(lazy.parameters.cold().unwrap().resident_bytes() <= 4 * CELL);
+        lazy.parameters.check()?;
+
+        std::fs::remove_file(path)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_lazy_errors() -> Result<()> {
+        let model = cws_test_model(&["2我", "2们", "6我们"], |i| (i % 7) as f64 - 3.0);
+        let expected: Vec<String> = model
+            .predict("我们我们")?
+            .into_iter()
+            .map(String::from)
+            .collect();
+
+        let dir = std::env::temp_dir();
+        let path = dir.join(format!("ltp-cold-err-{}.bin", std::process::id()));
+        let mut lazy = model.split_sections(0, File::create(&path)?)?;
+        // 只有转移矩阵在热段，特征都在冷段，未挂载冷段时不能预测
+        assert!(lazy.predict("我们我们").is_err());
+
+        // 挂载时检查冷段的大小
+        let short = dir.join(format!("ltp-cold-short-{}.bin", std::process::id()));
+        std::fs::write(&short, &std::fs::read(&path)?[..CELL])?;
+        let cold = ColdSection::open(&short, 0)?;
+        assert!(lazy.parameters.attach(cold).is_err());
+        std::fs::remove_file(short)?;
+
+        let lazy = lazy.with_cold_section(&path, 0)?;
+        assert_eq!(lazy.predict("我们我们")?, expected);
+
+        // 挂载后冷段读取失败时预测返回错误而不是 panic
+        File::options().write(true).open(&path)?.set_len(0)?;
+        assert!(lazy.predict("我们我们").is_err());
+
+        std::fs::remove_file(path)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,586 @@
+//! 原 C++ 版 LTP（3.x）感知机模型文件的识别与转换
+//!
+//! C++ 版模型以 128 字节、以 `\0` 结尾的任务名（`otcws`、`otpos`、`otner`）开头，之后是各块的偏移
+//! （分词模型依次为标签表、内部词典、特征空间、参数，词性标注与命名实体识别模型没有内部词典），
+//! 所有整数与浮点数均为小端：
+//!
+//! ```text
+//! smartmap     : char[16] "smartmap" | u32 桶数 | u32 条目数 | u32 键缓冲区字节数
+//!                | i32 桶[桶数] | {u32 键偏移, u32 值下标, u32 哈希值, i32 后继}[条目数]
+//!                | 以 \0 结尾的键[键缓冲区字节数] | T 值[条目数]
+//! featurespace : char[16] "featurespace" | i32 特征数 | u32 模板数 | smartmap<i32>[模板数]
+//! param        : char[16] "param" | u8 是否完整 | i32 维数 | f64 权重[维数]
+//!                | 完整模型另有 f64 累加权重[维数]、i32 更新时间[维数] 与 i32 时间戳
+//! ```
+//!
+//! 标签表是值为标签下标的 `smartmap<i32>`；特征空间的每个模板一张 `smartmap<i32>`，键为 `模板编号=字段`，
+//! 多个字段以 `-` 连接，值为全局的特征编号；参数按 `特征编号 * 标签数 + 标签` 排列，
+//! 之后是 `前一标签 * 标签数 + 标签` 排列的转移权重。
+//!
+//! 转换时按 [`LegacyDefinition::TEMPLATES`] 把与本实现相同的模板改写为这里的特征前缀，
+//! 其余模板（字符类别、词典、句首句尾占位等）的特征没有对应，直接丢弃；
+//! 参数改为本实现的 `feature * label_num + label` 排列，转移权重放在最前面。
+use crate::perceptron::{Definition, Perceptron};
+#[cfg(feature = "cws")]
+use crate::CWSDefinition;
+#[cfg(feature = "ner")]
+use crate::NERDefinition;
+#[cfg(feature = "pos")]
+use crate::POSDefinition;
+use anyhow::{anyhow, Result};
+use std::collections::HashMap;
+use std::fmt::{Display, Formatter};
+use std::io::Read;
+
+pub const LEGACY_HEADER_SIZE: usize = 128;
+
+/// C++ 版以这些占位符表示超出句子范围的上下文，本实现不产生这类特征
+const BOUNDARIES: [&str; 4] = ["_bos_", "_eos_", "__bos__", "__eos__"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LegacyModelKind {
+    CWS,
+    POS,
+    NER,
+}
+
+impl Display for LegacyModelKind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            LegacyModelKind::CWS => "cws",
+            LegacyModelKind::POS => "pos",
+            LegacyModelKind::NER => "ner",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl LegacyModelKind {
+    /// 根据文件开头判断是否为 C++ 版模型
+    pub fn detect(header: &[u8]) -> Option<Self> {
+        let header = &header[..header.len().min(LEGACY_HEADER_SIZE)];
+        let end = header.iter().position(|&b| b == 0)?;
+        match &header[..end] {
+            b"otcws" => Some(LegacyModelKind::CWS),
+            b"otpos" => Some(LegacyModelKind::POS),
+            b"otner" => Some(LegacyModelKind::NER),
+            _ => None,
+        }
+    }
+
+    pub fn detect_reader<R: Read>(reader: R) -> Result<Option<Self>> {
+        let mut header = Vec::with_capacity(LEGACY_HEADER_SIZE);
+        reader
+            .take(LEGACY_HEADER_SIZE as u64)
+            .read_to_end(&mut header)?;
+        Ok(Self::detect(&header))
+    }
+}
+
+/// 按偏移读取小端数据
+struct Bytes<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Bytes<'a> {
+    fn at(bytes: &'a [u8], pos: usize) -> Result<Self> {
+        if pos > bytes.len() {
+            return Err(anyhow!("offset {} is beyond the end of the model", pos));
+        }
+        Ok(Bytes { bytes, pos })
+    }
+
+    fn take(&mut self, len: usize) -> Result<&'a [u8]> {
+        let end = self
+            .pos
+            .checked_add(len)
+            .filter(|&end| end <= self.bytes.len())
+            .ok_or_else(|| anyhow!("truncated legacy model at offset {}", self.pos))?;
+        let bytes = &self.bytes[self.pos..end];
+        self.pos = end;
+        Ok(bytes)
+    }
+
+    fn array<const N: usize>(&mut self) -> Result<[u8; N]> {
+        Ok(self.take(N)?.try_into()?)
+    }
+
+    fn u32(&mut self) -> Result<usize> {
+        Ok(u32::from_le_bytes(self.array()?) as usize)
+    }
+
+    fn i32(&mut self) -> Result<i32> {
+        Ok(i32::from_le_bytes(self.array()?))
+    }
+
+    fn f64(&mut self) -> Result<f64> {
+        Ok(f64::from_le_bytes(self.array()?))
+    }
+
+    fn len(&mut self, what: &str) -> Result<usize> {
+        usize::try_from(self.i32()?).map_err(|_| anyhow!("negative {} in legacy model", what))
+    }
+
+    /// 16 字节、以 `\0` 结尾的块名
+    fn chunk(&mut self, name: &str) -> Result<()> {
+        let chunk = self.take(16)?;
+        let end = chunk.iter().position(|&b| b == 0).unwrap_or(chunk.len());
+        if &chunk[..end] != name.as_bytes() {
+            return Err(anyhow!(
+                "expected a `{}` block at offset {}",
+                name,
+                self.pos - 16
+            ));
+        }
+        Ok(())
+    }
+
+    /// 值为 `i32` 的 smartmap，按条目顺序给出键与值
+    fn smartmap(&mut self) -> Result<Vec<(String, i32)>> {
+        self.chunk("smartmap")?;
+        let buckets = self.u32()?;
+        let entries = self.u32()?;
+        let key_len = self.u32()?;
+        self.take(buckets.saturating_mul(4))?;
+        let mut nodes = Vec::with_capacity(entries.min(self.bytes.len() / 16));
+        for _ in 0..entries {
+            let node = self.take(16)?;
+            let key = u32::from_le_bytes(node[0..4].try_into()?) as usize;
+            let value = u32::from_le_bytes(node[4..8].try_into()?) as usize;
+            nodes.push((key, value));
+        }
+        let keys = self.take(key_len)?;
+        let values = self.take(entries.saturating_mul(4))?;
+        nodes
+            .into_iter()
+            .map(|(key, value)| {
+                let key = keys
+                    .get(key..)
+                    .and_then(|rest| rest.iter().position(|&b| b == 0).map(|end| &rest[..end]))
+                    .ok_or_else(|| anyhow!("smartmap key offset {} out of range", key))?;
+                let value = values
+                    .get(value * 4..value * 4 + 4)
+                    .ok_or_else(|| anyhow!("smartmap value index {} out of range", value))?;
+                Ok((
+                    String::from_utf8(key.to_vec())?,
+                    i32::from_le_bytes(value.try_into()?),
+                ))
+            })
+            .collect()
+    }
+}
+
+/// 转换得到的模型
+pub type LegacyPerceptron<Define> = Perceptron<Define, HashMap<String, usize>, Vec<f64>, f64>;
+
+/// 读出的 C++ 版模型，特征与参数保持原样
+#[derive(Debug, Clone, PartialEq)]
+pub struct LegacyModel {
+    pub kind: LegacyModelKind,
+    /// 按下标排列的标签
+    pub labels: Vec<String>,
+    /// 每个模板的特征：`模板编号=字段` 与特征编号
+    pub templates: Vec<Vec<(String, usize)>>,
+    pub feature_num: usize,
+    /// 完整模型只取第一组权重
+    pub parameters: Vec<f64>,
+}
+
+impl LegacyModel {
+    pub fn read<R: Read>(mut reader: R) -> Result<Self> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes)?;
+        Self::from_bytes(&bytes)
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        let kind =
+            LegacyModelKind::detect(bytes).ok_or_else(|| anyhow!("not a legacy C++ LTP model"))?;
+        let mut header = Bytes::at(bytes, LEGACY_HEADER_SIZE)?;
+        let labels_at = header.u32()?;
+        if kind == LegacyModelKind::CWS {
+            // 内部词典不参与转换
+            header.u32()?;
+        }
+        let features_at = header.u32()?;
+        let parameters_at = header.u32()?;
+
+        let entries = Bytes::at(bytes, labels_at)?.smartmap()?;
+        let mut labels = vec![None; entries.len()];
+        for (label, index) in entries {
+            match usize::try_from(index).ok().and_then(|i| labels.get_mut(i)) {
+                Some(slot) => *slot = Some(label),
+                None => return Err(anyhow!("label index {} out of range", index)),
+            }
+        }
+        let labels = labels
+            .into_iter()
+            .enumerate()
+            .map(|(index, label)| label.ok_or_else(|| anyhow!("missing label {}", index)))
+            .collect::<Result<Vec<_>>>()?;
+
+        let mut space = Bytes::at(bytes, features_at)?;
+        space.chunk("featurespace")?;
+        let feature_num = space.len("feature number")?;
+        let template_num = space.u32()?;
+        let mut templates = Vec::with_capacity(template_num.min(bytes.len()));
+        for _ in 0..template_num {
+            let features = space
+                .smartmap()?
+                .into_iter()
+                .map(|(key, index)| match usize::try_from(index) {
+                    Ok(index) if index < feature_num => Ok((key, index)),
+                    _ => Err(anyhow!("feature index {} out of range", index)),
+                })
+                .collect::<Result<Vec<_>>>()?;
+            templates.push(features);
+        }
+
+        let mut param = Bytes::at(bytes, parameters_at)?;
+        param.chunk("param")?;
+        param.take(1)?;
+        let dim = param.len("parameter dimension")?;
+        let label_num = labels.len();
+        if dim != (feature_num + label_num) * label_num {
+            return Err(anyhow!(
+                "{} parameters for {} features and {} labels",
+                dim,
+                feature_num,
+                label_num
+            ));
+        }
+        let parameters = (0..dim).map(|_| param.f64()).collect::<Result<Vec<_>>>()?;
+
+        Ok(LegacyModel {
+            kind,
+            labels,
+            templates,
+            feature_num,
+            parameters,
+        })
+    }
+
+    /// 转换为本实现的模型，同时返回丢弃的特征数
+    pub fn convert<Define: LegacyDefinition>(&self) -> Result<(LegacyPerceptron<Define>, usize)> {
+        if self.kind != Define::KIND {
+            return Err(anyhow!(
+                "this is a legacy {} model, expected {}",
+                self.kind,
+                Define::KIND
+            ));
+        }
+        let labels: Vec<String> = self.labels.iter().map(|l| Define::label(l)).collect();
+        let definition = Define::from_labels(labels.clone())?;
+        let label_num = definition.label_num();
+        // 本实现的标签下标到 C++ 版标签下标
+        let mapping = (0..label_num)
+            .map(|label| {
+                let name = definition.to_label(label);
+                labels
+                    .iter()
+                    .position(|l| l == name)
+                    .ok_or_else(|| anyhow!("label {} is missing in the legacy model", name))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        let legacy_num = self.labels.len();
+
+        // 按 C++ 版的特征编号排列，转换后的编号与之顺序相同
+        let mut converted = Vec::new();
+        let mut skipped = 0;
+        for (key, index) in self.templates.iter().flatten() {
+            match Define::feature(key) {
+                Some(feature) => converted.push((*index, feature)),
+                None => skipped += 1,
+            }
+        }
+        converted.sort_unstable();
+
+        let bias = if definition.use_viterbi() {
+            label_num
+        } else {
+            0
+        };
+        let mut features = HashMap::with_capacity(converted.len());
+        let mut parameters = vec![0.0; bias * label_num];
+        if definition.use_viterbi() {
+            let transitions = &self.parameters[self.feature_num * legacy_num..];
+            for (from, &legacy_from) in mapping.iter().enumerate() {
+                for (to, &legacy_to) in mapping.iter().enumerate() {
+                    parameters[from * label_num + to] =
+                        transitions[legacy_from * legacy_num + legacy_to];
+                }
+            }
+        }
+        for (index, feature) in converted {
+            let row = &self.parameters[index * legacy_num..(index + 1) * legacy_num];
+            // 字段去掉连接符后可能与同模板的另一个特征相同，两者同时出现，权重相加
+            match features.get(&feature) {
+                Some(&existing) => {
+                    for (label, &legacy) in mapping.iter().enumerate() {
+                        parameters[existing * label_num + label] += row[legacy];
+                    }
+                }
+                None => {
+                    features.insert(feature, parameters.len() / label_num);
+                    parameters.extend(mapping.iter().map(|&legacy| row[legacy]));
+                }
+            }
+        }
+        Ok((
+            Perceptron::new_with_parameters(definition, features, parameters),
+            skipped,
+        ))
+    }
+}
+
+/// 可以由 C++ 版模型转换的任务定义
+pub trait LegacyDefinition: Definition + Sized {
+    const KIND: LegacyModelKind;
+
+    /// C++ 版特征模板编号与本实现特征前缀的对应
+    const TEMPLATES: &'static [(&'static str, &'static str)];
+
+    fn from_labels(labels: Vec<String>) -> Result<Self>;
+
+    /// C++ 版标签在本实现中的写法
+    fn label(label: &str) -> String {
+        label.to_string()
+    }
+
+    /// 把 `模板编号=字段` 形式的 C++ 版特征改写为本实现的特征，没有对应时返回 `None`
+    fn feature(key: &str) -> Option<String> {
+        let (template, value) = key.split_once('=')?;
+        let (_, prefix) = Self::TEMPLATES.iter().find(|(id, _)| *id == template)?;
+        let mut feature = prefix.to_string();
+        for field in value.split('-') {
+            if field.is_empty() || BOUNDARIES.contains(&field.to_lowercase().as_str()) {
+                return None;
+            }
+            feature.push_str(field);
+        }
+        Some(feature)
+    }
+}
+
+#[cfg(feature = "cws")]
+impl LegacyDefinition for CWSDefinition {
+    const KIND: LegacyModelKind = LegacyModelKind::CWS;
+    // c[-2], c[-1], c[0], c[+1], c[+2], c[-2]c[-1], c[-1]c[0], c[0]c[+1], c[+1]c[+2]
+    const TEMPLATES: &'static [(&'static str, &'static str)] = &[
+        ("1", "0"),
+        ("2", "1"),
+        ("3", "2"),
+        ("4", "3"),
+        ("5", "4"),
+        ("6", "5"),
+        ("7", "6"),
+        ("8", "7"),
+        ("9", "8"),
+    ];
+
+    fn from_labels(_labels: Vec<String>) -> Result<Self> {
+        Ok(CWSDefinition::new())
+    }
+
+    /// C++ 版的分词标签为小写的 `b`、`i`、`e`、`s`
+    fn label(label: &str) -> String {
+        match label.to_uppercase().as_str() {
+            "I" => "M".to_string(),
+            other => other.to_string(),
+        }
+    }
+}
+
+#[cfg(feature = "pos")]
+impl LegacyDefinition for POSDefinition {
+    const KIND: LegacyModelKind = LegacyModelKind::POS;
+    // w[-2], w[-1], w[0], w[+1], w[+2], w[-1]w[0], w[0]w[+1]
+    const TEMPLATES: &'static [(&'static str, &'static str)] = &[
+        ("1", "0"),
+        ("2", "1"),
+        ("3", "2"),
+        ("4", "3"),
+        ("5", "4"),
+        ("6", "6"),
+        ("7", "7"),
+    ];
+
+    fn from_labels(labels: Vec<String>) -> Result<Self> {
+        Ok(POSDefinition::new(labels))
+    }
+}
+
+#[cfg(feature = "ner")]
+impl LegacyDefinition for NERDefinition {
+    const KIND: LegacyModelKind = LegacyModelKind::NER;
+    // w[-2..+2], w[-2]w[-1], w[-1]w[0], w[0]w[+1], w[+1]w[+2], p[-2..+2], p[-1]p[0], p[0]p[+1]
+    const TEMPLATES: &'static [(&'static str, &'static str)] = &[
+        ("1", "0"),
+        ("2", "1"),
+        ("3", "2"),
+        ("4", "3"),
+        ("5", "4"),
+        ("6", "5"),
+        ("7", "6"),
+        ("8", "7"),
+        ("9", "8"),
+        ("10", "b"),
+        ("11", "c"),
+        ("12", "d"),
+        ("13", "e"),
+        ("14", "f"),
+        ("16", "g"),
+        ("17", "h"),
+    ];
+
+    fn from_labels(labels: Vec<String>) -> Result<Self> {
+        Ok(NERDefinition::new(labels))
+    }
+}
+
+/// 读取并转换 C++ 版模型，丢弃没有对应模板的特征
+pub fn load_legacy<Define: LegacyDefinition, R: Read>(
+    reader: R,
+) -> Result<LegacyPerceptron<Define>> {
+    let (model, _) = LegacyModel::read(reader)?.convert()?;
+    Ok(model)
+}
+
+#[cfg(all(test, feature = "cws", feature = "pos"))]
+mod tests {
+    use super::*;
+    use crate::perceptron::cws_test_model;
+
+    fn smartmap(out: &mut Vec<u8>, entries: &[(&str, i32)]) {
+        let mut chunk = [0u8; 16];
+        chunk[..8].copy_from_slice(b"smartmap");
+        out.extend_from_slice(&chunk);
+        let keys: Vec<u8> = entries
+            .iter()
+            .flat_map(|(key, _)| key.bytes().chain([0]))
+            .collect();
+        for value in [1, entries.len(), keys.len()] {
+            out.extend_from_slice(&(value as u32).to_le_bytes());
+        }
+        out.extend_from_slice(&(-1i32).to_le_bytes());
+        let mut offset = 0;
+        for (i, (key, _)) in entries.iter().enumerate() {
+            for value in [offset, i as u32, 0] {
+                out.extend_from_slice(&value.to_le_bytes());
+            }
+            let next = if i + 1 < entries.len() {
+                i as i32 + 1
+            } else {
+                -1
+            };
+            out.extend_from_slice(&next.to_le_bytes());
+            offset += key.len() as u32 + 1;
+        }
+        out.extend_from_slice(&keys);
+        for (_, value) in entries {
+            out.extend_from_slice(&value.to_le_bytes());
+        }
+    }
+
+    /// 按 C++ 版的布局写出分词模型
+    fn legacy_cws(labels: &[&str], templates: &[&[(&str, i32)]], parameters: &[f64]) -> Vec<u8> {
+        let mut out = vec![0u8; LEGACY_HEADER_SIZE + 16];
+        out[..5].copy_from_slice(b"otcws");
+        let mut offsets = Vec::new();
+
+        offsets.push(out.len());
+        let labels: Vec<_> = labels.iter().zip(0..).map(|(l, i)| (*l, i)).collect();
+        smartmap(&mut out, &labels);
+        offsets.push(out.len());
+        smartmap(&mut out, &[("我们", 1)]);
+
+        offsets.push(out.len());
+        let mut chunk = [0u8; 16];
+        chunk[..12].copy_from_slice(b"featurespace");
+        out.extend_from_slice(&chunk);
+        let feature_num: usize = templates.iter().map(|t| t.len()).sum();
+        out.extend_from_slice(&(feature_num as i32).to_le_bytes());
+        out.extend_from_slice(&(templates.len() as u32).to_le_bytes());
+        for template in templates {
+            smartmap(&mut out, template);
+        }
+
+        offsets.push(out.len());
+        let mut chunk = [0u8; 16];
+        chunk[..5].copy_from_slice(b"param");
+        out.extend_from_slice(&chunk);
+        out.push(0);
+        out.extend_from_slice(&(parameters.len() as i32).to_le_bytes());
+        for param in parameters {
+            out.extend_from_slice(&param.to_le_bytes());
+        }
+
+        for (i, offset) in offsets.into_iter().enumerate() {
+            let at = LEGACY_HEADER_SIZE + i * 4;
+            out[at..at + 4].copy_from_slice(&(offset as u32).to_le_bytes());
+        }
+        out
+    }
+
+    #[test]
+    fn test_convert_cws() -> Result<()> {
+        let model = cws_test_model(&["2我", "2们", "6我们"], |i| {
+            ((i * 7 + 3) % 11) as f64 - 5.0
+        });
+
+        // C++ 版的标签顺序为 b、i、e、s，本实现为 S、B、M、E
+        let legacy_labels = ["b", "i", "e", "s"];
+        let to_legacy = [3, 0, 1, 2];
+        let templates: &[&[(&str, i32)]] = &[
+            &[("1=__bos__", 0)],
+            &[("3=我", 1), ("3=们", 2)],
+            &[("7=我-们", 3)],
+            &[("15=2", 4)],
+        ];
+        let feature_num = 5;
+        let mut parameters = vec![0.0; (feature_num + 4) * 4];
+        for (legacy, feature) in [(1, 4), (2, 5), (3, 6)] {
+            for label in 0..4 {
+                parameters[legacy * 4 + to_legacy[label]] = model.parameters[feature * 4 + label];
+            }
+        }
+        for (from, to) in (0..4).flat_map(|from| (0..4).map(move |to| (from, to))) {
+            let legacy = feature_num * 4 + to_legacy[from] * 4 + to_legacy[to];
+            parameters[legacy] = model.parameters[from * 4 + to];
+        }
+        let bytes = legacy_cws(&legacy_labels, templates, &parameters);
+
+        let legacy = LegacyModel::read(bytes.as_slice())?;
+        assert_eq!(legacy.labels, legacy_labels);
+        assert_eq!(legacy.feature_num, feature_num);
+        let (converted, skipped) = legacy.convert::<CWSDefinition>()?;
+        assert_eq!(skipped, 2);
+        assert_eq!(converted.features, model.features);
+        assert_eq!(converted.parameters, model.parameters);
+        for sentence in ["我们我", "他们我们"] {
+            assert_eq!(converted.predict(sentence)?, model.predict(sentence)?);
+        }
+
+        assert!(legacy.convert::<POSDefinition>().is_err());
+        let mut truncated = bytes.clone();
+        truncated.truncate(bytes.len() - 8);
+        assert!(load_legacy::<CWSDefinition, _>(truncated.as_slice()).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_detect() -> Result<()> {
+        let mut header = vec![0u8; LEGACY_HEADER_SIZE + 16];
+        header[..5].copy_from_slice(b"otpos");
+        assert_eq!(
+            LegacyModelKind::detect_reader(header.as_slice())?,
+            Some(LegacyModelKind::POS)
+        );
+        assert!(load_legacy::<POSDefinition, _>(header.as_slice()).is_err());
+
+        assert_eq!(LegacyModelKind::detect(b"{\"definition\":"), None);
+        assert_eq!(LegacyModelKind::detect(b"otcwsx\0"), None);
+        Ok(())
+    }
+}
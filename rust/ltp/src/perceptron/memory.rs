@@ -0,0 +1,91 @@
+//! 训练时的内存估算：样本、特征字典与参数各占多少字节
+//!
+//! 只统计主要的堆内存，哈希表按满载时的槽位估算，实际占用会略多一些。设置了
+//! [`Trainer::memory_budget`](crate::perceptron::Trainer::memory_budget) 时，
+//! 训练语料超出预算的部分改为每轮从磁盘流式读取，特征字典与参数超出预算时在分配参数前报错。
+use crate::perceptron::Sample;
+use std::fmt::{Display, Formatter};
+use std::mem::size_of;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct MemoryEstimate {
+    /// 内存中的训练样本，流式读取时为一批样本
+    pub samples: usize,
+    /// 特征字典
+    pub features: usize,
+    /// 参数，以及平均感知机的累加值、时间戳与最优参数的副本
+    pub parameters: usize,
+}
+
+impl MemoryEstimate {
+    pub fn total(&self) -> usize {
+        self.samples + self.features + self.parameters
+    }
+}
+
+fn mib(bytes: usize) -> f64 {
+    bytes as f64 / (1024.0 * 1024.0)
+}
+
+impl Display for MemoryEstimate {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "samples: {:.1} MiB, features: {:.1} MiB, parameters: {:.1} MiB, total: {:.1} MiB",
+            mib(self.samples),
+            mib(self.features),
+            mib(self.parameters),
+            mib(self.total())
+        )
+    }
+}
+
+/// 一个样本的特征字符串、词的划分与标签
+pub fn sample_bytes(sample: &Sample) -> usize {
+    let flat = sample.features.as_flat();
+    let strings: usize = flat.iter().map(String::capacity).sum();
+    size_of::<Sample>()
+        + std::mem::size_of_val(flat)
+        + strings
+        + sample.features.len() * size_of::<(usize, usize)>()
+        + sample.labels.capacity() * size_of::<usize>()
+}
+
+/// `HashMap<String, usize>` 形式的特征字典，`key_bytes` 为所有特征字符串的字节数之和
+pub fn dictionary_bytes(features: usize, key_bytes: usize) -> usize {
+    // 负载因子 7/8，每个槽位一个控制字节
+    let slots = features * 8 / 7 + 1;
+    slots * (size_of::<(String, usize)>() + 1) + key_bytes
+}
+
+/// 有 `cells` 个参数的平均感知机训练：参数、累加值、时间戳、每轮的备份与最优参数
+pub fn parameter_bytes<Param>(cells: usize) -> usize {
+    cells * (4 * size_of::<Param>() + size_of::<usize>())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::perceptron::FeatureArena;
+
+    #[test]
+    fn test_memory_estimate() {
+        let features = FeatureArena::from(vec![vec!["2我们".to_string()], vec!["2去".to_string()]]);
+        let sample = Sample::new(features, vec![0, 1]);
+        let strings: usize = sample.features.as_flat().iter().map(String::capacity).sum();
+        assert!(sample_bytes(&sample) > strings + 2 * size_of::<String>());
+        assert!(dictionary_bytes(7, 20) >= 8 * size_of::<(String, usize)>() + 20);
+        assert_eq!(parameter_bytes::<f64>(10), 10 * 40);
+
+        let estimate = MemoryEstimate {
+            samples: 1 << 20,
+            features: 1 << 19,
+            parameters: 1 << 21,
+        };
+        assert_eq!(estimate.total(), 7 << 19);
+        assert_eq!(
+            estimate.to_string(),
+            "samples: 1.0 MiB, features: 0.5 MiB, parameters: 2.0 MiB, total: 3.5 MiB"
+        );
+    }
+}
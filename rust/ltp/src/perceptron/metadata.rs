@@ -0,0 +1,105 @@
+//! 模型的训练来源信息，随模型文件一同保存
+use crate::perceptron::{Layout, TagDictionary, TokenJoin};
+#[cfg(feature = "serialization")]
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// 训练语料的规模
+#[cfg_attr(feature = "serialization", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serialization", serde(default))]
+pub struct CorpusStats {
+    pub train_sentences: usize,
+    pub train_tokens: usize,
+    pub eval_sentences: usize,
+    pub eval_tokens: usize,
+    pub features: usize,
+}
+
+#[cfg_attr(feature = "serialization", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serialization", serde(default))]
+pub struct ModelMetadata {
+    /// 生成模型的 ltp 版本
+    pub crate_version: String,
+    pub labels: Vec<String>,
+    /// 训练参数，如 epoch、algorithm、compress
+    pub training: BTreeMap<String, String>,
+    pub corpus: CorpusStats,
+    /// 影响特征抽取的编译选项（char-type、cross-char、near-char-type）
+    pub normalization: Vec<String>,
+    /// 分词的单元切分方式，加载模型时设置到定义上
+    pub token_join: TokenJoin,
+    /// 特征抽取前的规范化方式（[`Normalizer::name`](crate::perceptron::Normalizer::name)），
+    /// 加载模型时按名字恢复
+    pub normalizers: Vec<String>,
+    /// 用户自定义的键值对
+    pub tags: BTreeMap<String, String>,
+    /// 训练语料中各词出现过的标签，训练时启用 [`Trainer::tag_dictionary`] 才会统计
+    ///
+    /// [`Trainer::tag_dictionary`]: crate::perceptron::Trainer::tag_dictionary
+    #[cfg_attr(
+        feature = "serialization",
+        serde(skip_serializing_if = "TagDictionary::is_empty")
+    )]
+    pub tag_dictionary: TagDictionary,
+    /// 参数的存储顺序，文件中的参数总是按特征存放，加载后可按此恢复
+    #[cfg_attr(
+        feature = "serialization",
+        serde(skip_serializing_if = "Layout::is_feature_major")
+    )]
+    pub layout: Layout,
+}
+
+impl Default for ModelMetadata {
+    fn default() -> Self {
+        ModelMetadata {
+            crate_version: env!("CARGO_PKG_VERSION").to_string(),
+            labels: Vec::new(),
+            training: BTreeMap::new(),
+            corpus: CorpusStats::default(),
+            normalization: Self::normalization_features(),
+            token_join: TokenJoin::default(),
+            normalizers: Vec::new(),
+            tags: BTreeMap::new(),
+            tag_dictionary: TagDictionary::new(),
+            layout: Layout::default(),
+        }
+    }
+}
+
+impl ModelMetadata {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 当前编译时启用的、会影响特征抽取的选项
+    pub fn normalization_features() -> Vec<String> {
+        let mut features = Vec::new();
+        if cfg!(feature = "char-type") {
+            features.push("char-type".to_string());
+        }
+        if cfg!(feature = "cross-char") {
+            features.push("cross-char".to_string());
+        }
+        if cfg!(feature = "near-char-type") {
+            features.push("near-char-type".to_string());
+        }
+        features
+    }
+
+    pub fn with_tag<K: Into<String>, V: Into<String>>(mut self, key: K, value: V) -> Self {
+        self.tags.insert(key.into(), value.into());
+        self
+    }
+
+    pub fn tag(&self, key: &str) -> Option<&str> {
+        self.tags.get(key).map(|v| v.as_str())
+    }
+
+    /// 模型与当前编译选项的特征抽取方式是否一致，按字位簇切分的模型需要 `grapheme` 特性
+    pub fn is_normalization_compatible(&self) -> bool {
+        self.normalization == Self::normalization_features()
+            && (!self.token_join.graphemes || cfg!(feature = "grapheme"))
+    }
+}
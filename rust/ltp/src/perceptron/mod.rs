@@ -1,22 +1,223 @@
+mod arena;
+mod cipher;
+#[cfg(feature = "compact")]
+mod compact;
+mod compression;
+#[cfg(feature = "config")]
+mod config;
 mod definition;
+#[cfg(all(
+    feature = "serialization",
+    any(feature = "cws", feature = "pos", feature = "ner")
+))]
+mod dynamic;
+mod embedding;
+mod ensemble;
+#[cfg(feature = "fs")]
+mod external;
 mod feature;
+#[cfg(feature = "fs")]
+mod feature_cache;
+#[cfg(feature = "fst")]
+mod fst_feature;
+mod hashed;
+mod header;
+mod hogwild;
+mod layout;
+#[cfg(feature = "fs")]
+mod lazy;
+pub mod legacy;
+mod memory;
+mod metadata;
 mod model;
+mod normalizer;
 mod parameter;
+mod patch;
+#[cfg(feature = "serialization")]
+mod portable;
+mod predictions;
+#[cfg(feature = "quantization")]
+mod quantize;
+mod report;
 #[cfg(feature = "serialization")]
 mod serialization;
+mod spans;
+mod tag_dict;
 mod trainer;
+mod word_cache;
+#[cfg(feature = "zero-copy")]
+mod zero_copy;
+
+use crate::error::LtpError;
 
-pub use definition::{CWSDefinition, Definition, GenericItem, NERDefinition, POSDefinition};
+pub use arena::{FeatureArena, Tokens};
+pub use cipher::{decrypt_model, encrypt_model, ModelCipher, XorCipher, ENCRYPTED_MAGIC};
+#[cfg(feature = "compact")]
+pub use compact::{CompactFeatures, CompactModel};
+pub use compression::{CompressedReader, CompressedWriter, Compression};
+#[cfg(feature = "config")]
+pub use config::{AlgorithmName, ConfigDefinition, TrainConfig};
+#[cfg(feature = "cws")]
+pub use definition::CWSDefinition;
+#[cfg(feature = "ner")]
+pub use definition::NERDefinition;
+#[cfg(feature = "pos")]
+pub use definition::POSDefinition;
+pub use definition::{
+    is_skipped_char, CharIndex, CorpusFormat, Definition, GenericItem, JoinMode,
+    SequenceDefinition, SequenceDefinitionBuilder, Template, TemplateItem, TemplateSet,
+    TemplateValue, TokenJoin, UnknownLabelPolicy, BLANK_TOKEN, UNCERTAIN, UNCERTAIN_TAG,
+};
+#[cfg(all(
+    feature = "serialization",
+    any(feature = "cws", feature = "pos", feature = "ner")
+))]
+pub use dynamic::{DynInput, DynModel, DynOutput};
+pub use embedding::{EmbeddingFeatures, EmbeddingFormat, Embeddings};
+pub use ensemble::Ensemble;
+#[cfg(feature = "fs")]
+pub use external::{FeatureSorter, SortedFeatures};
+#[cfg(feature = "ahash")]
+pub use feature::AHashFeatures;
+#[cfg(feature = "fxhash")]
+pub use feature::FxFeatures;
 pub use feature::{TraitFeature, TraitFeatureCompressUtils, TraitFeaturesTrainUtils};
-pub use model::{PaMode, Perceptron};
+#[cfg(feature = "fs")]
+pub use feature_cache::{
+    load_cache, preprocess, read_cache, write_cache, CacheHeader, CacheStats, CACHE_MAGIC,
+    CACHE_VERSION,
+};
+#[cfg(feature = "fst")]
+pub use fst_feature::{FstFeatures, FstModel};
+pub use hashed::{HashedFeatures, HashedModel};
+pub use header::{
+    label_hash, ModelHeader, ModelTask, PayloadFormat, TaskDefinition, FORMAT_VERSION, MAGIC,
+};
+pub use hogwild::HogwildParameters;
+pub use layout::{Layout, LayoutParameters};
+#[cfg(feature = "fs")]
+pub use lazy::{ColdSection, LazyModel, LazyParameters};
+pub use memory::MemoryEstimate;
+pub use metadata::{CorpusStats, ModelMetadata};
+pub use model::{Chunking, PaMode, Perceptron};
+pub use normalizer::{DigitFold, LowercaseLatin, Normalizer, Normalizers, WidthFold};
 pub use parameter::{
     TraitParameter, TraitParameterStorage, TraitParameterStorageCompressUtils,
     TraitParameterStorageTrainUtils, TraitParameterStorageUtils,
 };
+pub use patch::{ModelPatch, PATCH_MAGIC, PATCH_VERSION};
+#[cfg(feature = "serialization")]
+pub use portable::{Decoder, FeatureTemplate, PortableModel, PORTABLE_FORMAT, PORTABLE_VERSION};
+pub use predictions::Predictions;
+#[cfg(feature = "quantization")]
+pub use quantize::{Quantization, QuantizedModel, QuantizedParameters};
+pub use report::{
+    char_tags, write_curve, write_errors, CalibrationBin, CalibrationReport, ConfusionMatrix,
+    CurvePoint, EvalReport, LabelScore, LengthBucket, LengthReport, OovReport, SentenceErrors,
+};
+#[cfg(all(feature = "serialization", feature = "cws"))]
+pub use serialization::SerdeCWSModel;
+#[cfg(all(feature = "serialization", feature = "ner"))]
+pub use serialization::SerdeNERModel;
+#[cfg(all(feature = "serialization", feature = "pos"))]
+pub use serialization::SerdePOSModel;
 #[cfg(feature = "serialization")]
 pub use serialization::{
-    schema, Codec, Format, ModelSerde, Reader, Schema, SerdeCWSModel, SerdeModel, SerdeNERModel,
-    SerdePOSModel,
+    format_to_payload, payload_to_format, read_metadata, read_model_metadata, schema,
+    serialize_sorted, write_avro_stable, write_metadata, Codec, Format, ModelSerde, Reader, Schema,
+    SerdeModel, StableModel,
 };
-pub use trainer::{Algorithm, Trainer};
-pub type Sample = (Vec<Vec<String>>, Vec<usize>);
+pub use spans::{Span, SpanMatch, SpanReport, SpanScore};
+pub use tag_dict::TagDictionary;
+pub use trainer::{Algorithm, Domain, Trainer};
+pub use word_cache::WordFeatureCache;
+#[cfg(feature = "zero-copy")]
+pub use zero_copy::{
+    load_zero_copy, read_aligned, to_zero_copy_bytes, AlignedVec, ArchivedFeatures,
+    ArchivedParameters, FeatureEntry, ZeroCopyDefinition, ZeroCopyModel, ZeroCopyPerceptron,
+};
+
+/// 一个训练样本：逐字（分词）或逐词的特征与标签
+#[cfg_attr(
+    feature = "serialization",
+    derive(serde::Serialize, serde::Deserialize)
+)]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Sample {
+    pub features: FeatureArena<String>,
+    pub labels: Vec<usize>,
+}
+
+impl Sample {
+    pub fn new(features: FeatureArena<String>, labels: Vec<usize>) -> Self {
+        debug_assert_eq!(features.len(), labels.len());
+        Self { features, labels }
+    }
+
+    /// 特征与标签的长度必须相同
+    pub fn try_new(features: FeatureArena<String>, labels: Vec<usize>) -> anyhow::Result<Self> {
+        if features.len() != labels.len() {
+            return Err(LtpError::LengthMismatch(features.len(), labels.len()).into());
+        }
+        Ok(Self { features, labels })
+    }
+
+    /// 字或词的个数
+    pub fn len(&self) -> usize {
+        self.labels.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.labels.is_empty()
+    }
+
+    /// 逐个字或词的特征与标签
+    pub fn iter(&self) -> impl Iterator<Item = (&[String], usize)> + '_ {
+        self.features.iter().zip(self.labels.iter().copied())
+    }
+
+    /// 检查特征与标签长度相同，且标签为 [`UNCERTAIN`] 或小于 `label_num`
+    pub fn check(&self, label_num: usize) -> anyhow::Result<()> {
+        if self.features.len() != self.labels.len() {
+            return Err(LtpError::LengthMismatch(self.features.len(), self.labels.len()).into());
+        }
+        match self
+            .labels
+            .iter()
+            .find(|&&label| label != UNCERTAIN && label >= label_num)
+        {
+            Some(&label) => Err(LtpError::LabelOutOfRange(label, label_num).into()),
+            None => Ok(()),
+        }
+    }
+}
+
+impl From<(FeatureArena<String>, Vec<usize>)> for Sample {
+    fn from((features, labels): (FeatureArena<String>, Vec<usize>)) -> Self {
+        Self::new(features, labels)
+    }
+}
+
+/// 测试用的小分词模型：4 个偏置特征之后依次是 `features`，参数由 `param` 按下标生成
+#[cfg(all(test, feature = "cws"))]
+pub(crate) fn cws_test_model(
+    features: &[&str],
+    param: impl Fn(usize) -> f64,
+) -> Perceptron<CWSDefinition, std::collections::HashMap<String, usize>, Vec<f64>, f64> {
+    let features: std::collections::HashMap<String, usize> = features
+        .iter()
+        .enumerate()
+        .map(|(i, feature)| (feature.to_string(), 4 + i))
+        .collect();
+    let parameters = (0..(16 + features.len() * 4)).map(param).collect();
+    Perceptron::new_with_parameters(CWSDefinition::new(), features, parameters)
+}
+
+/// 参数由 `seed` 决定的小分词模型，用于比较合并、差分等多个模型之间的操作
+#[cfg(all(test, feature = "cws"))]
+pub(crate) fn cws_seeded_model(
+    features: &[&str],
+    seed: usize,
+) -> Perceptron<CWSDefinition, std::collections::HashMap<String, usize>, Vec<f64>, f64> {
+    cws_test_model(features, |i| ((i * 7 + seed) % 5) as f64 - 2.0)
+}
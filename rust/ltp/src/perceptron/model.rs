@@ -1,10 +1,28 @@
-use crate::perceptron::definition::CommonDefinePredict;
-use crate::perceptron::GenericItem;
+use crate::error::LtpError;
+use crate::get_entities;
+#[cfg(any(feature = "pos", feature = "ner"))]
+use crate::label::InternedLabels;
+use crate::label::LabelSet;
+#[cfg(feature = "pos")]
+use crate::perceptron::definition::split_buffer;
+use crate::perceptron::definition::{resolve_uncertain, CommonDefinePredict};
+#[cfg(feature = "pos")]
+use crate::perceptron::WordFeatureCache;
+use crate::perceptron::{
+    CalibrationReport, ConfusionMatrix, EvalReport, FeatureArena, GenericItem, ModelMetadata,
+    SequenceDefinition, SpanReport, UNCERTAIN,
+};
 use crate::perceptron::{
     Definition, TraitFeature, TraitFeatureCompressUtils, TraitFeaturesTrainUtils, TraitParameter,
     TraitParameterStorage, TraitParameterStorageCompressUtils, TraitParameterStorageTrainUtils,
 };
-use anyhow::Result;
+#[cfg(feature = "cws")]
+use crate::CWSDefinition;
+#[cfg(feature = "ner")]
+use crate::NERDefinition;
+#[cfg(feature = "pos")]
+use crate::POSDefinition;
+use anyhow::{anyhow, Result};
 use binary_heap_plus::BinaryHeap;
 use itertools::Itertools;
 use num_traits::NumCast;
@@ -15,6 +33,16 @@ use std::collections::HashSet;
 use std::fmt::{Debug, Display, Formatter};
 use std::iter::zip;
 use std::mem::swap;
+use std::ops::Range;
+
+// 提前多少个特征预取参数行
+const PREFETCH_DISTANCE: usize = 4;
+
+fn log_sum_exp<I: Iterator<Item = f64>>(scores: I) -> f64 {
+    let scores: Vec<f64> = scores.collect();
+    let max = scores.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+    max + scores.iter().map(|s| (s - max).exp()).sum::<f64>().ln()
+}
 
 #[cfg_attr(feature = "serialization", derive(Serialize, Deserialize))]
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -33,6 +61,56 @@ impl<Param: TraitParameter> Default for PaMode<Param> {
     }
 }
 
+/// 超长句子的分段维特比解码：每段不超过 `max_length` 个位置，相邻两段重叠 `overlap` 个位置，
+/// 重叠部分前后两段各取一半，拼接后的结果长度与句子相同
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Chunking {
+    max_length: usize,
+    overlap: usize,
+}
+
+impl Chunking {
+    pub fn new(max_length: usize, overlap: usize) -> Result<Self> {
+        if overlap >= max_length {
+            return Err(anyhow!(
+                "chunk overlap {} must be less than max length {}",
+                overlap,
+                max_length
+            ));
+        }
+        Ok(Self {
+            max_length,
+            overlap,
+        })
+    }
+
+    pub fn max_length(&self) -> usize {
+        self.max_length
+    }
+
+    pub fn overlap(&self) -> usize {
+        self.overlap
+    }
+
+    /// 长度为 `length` 的句子的各段，及其中保留到结果里的位置
+    pub fn chunks(&self, length: usize) -> Vec<(Range<usize>, Range<usize>)> {
+        let step = self.max_length - self.overlap;
+        let (head, tail) = (self.overlap / 2, self.overlap - self.overlap / 2);
+        let mut chunks = Vec::with_capacity(length / step + 1);
+        let mut start = 0;
+        loop {
+            let end = (start + self.max_length).min(length);
+            let keep_start = if start == 0 { 0 } else { start + head };
+            let keep_end = if end == length { length } else { end - tail };
+            chunks.push((start..end, keep_start..keep_end));
+            if end == length {
+                return chunks;
+            }
+            start += step;
+        }
+    }
+}
+
 #[cfg_attr(feature = "serialization", derive(Serialize, Deserialize))]
 #[derive(Default, Debug, Clone)]
 pub struct Perceptron<Define, Feature, ParamStorage, Param>
@@ -45,6 +123,12 @@ where
     pub definition: Define,
     pub features: Feature,
     pub parameters: ParamStorage,
+    // 单独保存在模型文件的元数据块中
+    #[cfg_attr(feature = "serialization", serde(skip))]
+    pub metadata: ModelMetadata,
+    // 运行时设置，不随模型保存
+    #[cfg_attr(feature = "serialization", serde(skip))]
+    chunking: Option<Chunking>,
     #[cfg_attr(feature = "serialization", serde(skip_serializing))]
     __phantom: Option<Param>,
 }
@@ -103,54 +187,168 @@ where
             features,
             parameters,
             definition,
+            metadata: Default::default(),
+            chunking: None,
             __phantom: Default::default(),
         }
     }
 
+    pub fn metadata(&self) -> &ModelMetadata {
+        &self.metadata
+    }
+
+    pub fn with_metadata(mut self, metadata: ModelMetadata) -> Self {
+        self.metadata = metadata;
+        self
+    }
+
     pub fn display(&self) -> String {
         format!("{}", self)
     }
 
+    /// 超过 `chunking.max_length` 的句子分段做维特比解码，维特比的回溯表只按段长分配；
+    /// 不使用维特比解码的模型不受影响
+    pub fn with_chunking(mut self, chunking: Chunking) -> Self {
+        self.chunking = Some(chunking);
+        self
+    }
+
+    pub fn set_chunking(&mut self, chunking: Option<Chunking>) {
+        self.chunking = chunking;
+    }
+
+    pub fn chunking(&self) -> Option<Chunking> {
+        self.chunking
+    }
+
     // 通用部分
-    fn score_base(&self, features: &Vec<usize>, label: usize) -> Param {
+    /// 两遍打分的第二遍：特征编号已经整句查好（见 [`TraitFeature::get_arena`]），这里顺序扫描
+    /// 扁平的编号数组，逐行累加参数，结果按 `[position * label_num + label]` 排列
+    pub fn emission_scores(&self, features: &FeatureArena<usize>) -> Vec<Param> {
+        self.emission_range(features, 0..features.len())
+    }
+
+    /// 第 `positions.start` 到 `positions.end`（不含）个位置的发射分数，
+    /// 见 [`emission_scores`](Self::emission_scores)
+    pub fn emission_range(
+        &self,
+        features: &FeatureArena<usize>,
+        positions: Range<usize>,
+    ) -> Vec<Param> {
         let label_num = self.definition.label_num();
-        let mut score = Param::zero();
-        for &feature in features {
-            score += self.parameters[feature * label_num + label];
+        let flat = features.as_flat();
+        let mut scores = vec![Param::zero(); positions.len() * label_num];
+        for (row, position) in scores.chunks_exact_mut(label_num).zip(positions) {
+            let span = features.span(position);
+            for cursor in span {
+                if let Some(&next) = flat.get(cursor + PREFETCH_DISTANCE) {
+                    self.parameters.prefetch(next * label_num);
+                }
+                self.parameters.add_row(flat[cursor] * label_num, row);
+            }
         }
-        score
+        scores
     }
 
-    fn viterbi_decode(&self, features: &[Vec<usize>]) -> Vec<usize> {
+    /// 一组特征在各标签上的分数之和，按标签下标排列，不在特征表中的特征被忽略，不含转移分数；
+    /// 特征为定义抽取的特征字符串，如 [`CWSDefinition::parse_char_features`] 给出的某个位置的特征，
+    /// 可以在学到的权重上实现自定义的解码或重排序
+    ///
+    /// [`CWSDefinition::parse_char_features`]: crate::CWSDefinition::parse_char_features
+    pub fn score_all<S: AsRef<str>>(&self, features: &[S]) -> Vec<f64> {
         let label_num = self.definition.label_num();
-        let mut pre_matrix = vec![0usize; features.len() * label_num];
-        let mut score_last = vec![Param::zero(); label_num];
+        let mut scores = vec![0.0; label_num];
+        for feature in features {
+            let Some(index) = self.features.get_with_key(feature.as_ref()) else {
+                continue;
+            };
+            for (label, score) in scores.iter_mut().enumerate() {
+                let param = self.parameters.get_param(index * label_num + label);
+                *score += param.to_f64().unwrap_or_default();
+            }
+        }
+        scores
+    }
+
+    /// 一组特征在标签 `label` 上的分数，见 [`score_all`](Self::score_all)；
+    /// 标签不在标签表中时返回 [`LtpError::UnknownLabel`]
+    pub fn score<S: AsRef<str>>(&self, features: &[S], label: &str) -> Result<f64> {
+        let label = (0..self.definition.label_num())
+            .find(|&index| self.definition.to_label(index) == label)
+            .ok_or_else(|| LtpError::UnknownLabel(label.to_string()))?;
+        Ok(self.score_all(features)[label])
+    }
+
+    /// 从标签 `from` 转移到标签 `to` 的分数，按 `[from * label_num + to]` 排列；
+    /// 不使用维特比解码的模型没有转移分数，返回空
+    pub fn transition_scores(&self) -> Vec<f64> {
+        if !self.definition.use_viterbi() {
+            return Vec::new();
+        }
+        let label_num = self.definition.label_num();
+        (0..label_num * label_num)
+            .map(|index| self.parameters.get_param(index))
+            .map(|param| param.to_f64().unwrap_or_default())
+            .collect()
+    }
+
+    fn viterbi_decode(&self, features: &FeatureArena<usize>) -> Vec<usize> {
+        self.viterbi_chunked(features.len(), |positions| {
+            self.emission_range(features, positions)
+        })
+    }
+
+    /// 句子超过分段长度时逐段取发射分数解码后拼接，否则整句解码
+    fn viterbi_chunked<F>(&self, length: usize, emission: F) -> Vec<usize>
+    where
+        F: Fn(Range<usize>) -> Vec<Param>,
+    {
+        if length == 0 {
+            return Vec::new();
+        }
+        let chunking = match self.chunking {
+            Some(chunking) if length > chunking.max_length => chunking,
+            _ => return self.viterbi_emission(&emission(0..length), length),
+        };
+        let mut res = Vec::with_capacity(length);
+        for (chunk, keep) in chunking.chunks(length) {
+            let preds = self.viterbi_emission(&emission(chunk.clone()), chunk.len());
+            res.extend_from_slice(&preds[keep.start - chunk.start..keep.end - chunk.start]);
+        }
+        res
+    }
+
+    /// 在发射分数上做维特比解码，分数为负无穷的标签不会被选中
+    fn viterbi_emission(&self, emission: &[Param], length: usize) -> Vec<usize> {
+        let label_num = self.definition.label_num();
+        let mut pre_matrix = vec![0usize; length * label_num];
+        let mut score_last = emission[..label_num].to_vec();
         let mut score_now = vec![Param::zero(); label_num];
 
-        let first_feature = &features[0];
-        for label_idx in 0..label_num {
-            pre_matrix[label_idx] = label_idx;
-            score_last[label_idx] = self.score_base(first_feature, label_idx);
+        for (label_idx, pre) in pre_matrix[..label_num].iter_mut().enumerate() {
+            *pre = label_idx;
         }
 
-        for (i, feature) in features.iter().enumerate().skip(1) {
+        for i in 1..length {
             let base = i * label_num;
 
             for label_idx in 0..label_num {
-                let mut max_score = Param::min_value();
-                let score_base = self.score_base(feature, label_idx);
+                let mut max_score = Param::neg_infinity();
+                let score_base = emission[base + label_idx];
 
                 for (pre_label_idx, &last_score) in score_last.iter().enumerate() {
                     // transition
-                    let transition_score = self.parameters[pre_label_idx * label_num + label_idx];
+                    let transition_score = self
+                        .parameters
+                        .get_param(pre_label_idx * label_num + label_idx);
                     // let transition_score = Param::zero();
                     let score = last_score + score_base + transition_score;
                     if score > max_score {
                         max_score = score;
                         pre_matrix[base + label_idx] = pre_label_idx;
-                        score_now[label_idx] = max_score;
                     }
                 }
+                score_now[label_idx] = max_score;
             }
             swap(&mut score_last, &mut score_now);
         }
@@ -163,9 +361,9 @@ where
             })
             .unwrap();
 
-        let mut res = vec![0; features.len()];
+        let mut res = vec![0; length];
 
-        for i in (0..features.len()).rev() {
+        for i in (0..length).rev() {
             let label_idx = max_score_idx;
             res[i] = label_idx;
             max_score_idx = pre_matrix[i * label_num + label_idx];
@@ -174,14 +372,14 @@ where
         res
     }
 
-    fn simple_decode(&self, features: &[Vec<usize>]) -> Vec<usize> {
+    fn simple_decode(&self, features: &FeatureArena<usize>) -> Vec<usize> {
         let label_num = self.definition.label_num();
+        let emission = self.emission_scores(features);
         let mut res = vec![0; features.len()];
 
-        for (i, feature) in features.iter().enumerate() {
+        for (i, scores) in emission.chunks_exact(label_num).enumerate() {
             let mut max_score = Param::min_value();
-            for label_idx in 0..label_num {
-                let score = self.score_base(feature, label_idx);
+            for (label_idx, &score) in scores.iter().enumerate() {
                 if score > max_score {
                     max_score = score;
                     res[i] = label_idx;
@@ -193,7 +391,7 @@ where
     }
 
     // viterbi decode
-    pub fn decode(&self, features: &[Vec<usize>]) -> Vec<usize> {
+    pub fn decode(&self, features: &FeatureArena<usize>) -> Vec<usize> {
         if self.definition.use_viterbi() {
             self.viterbi_decode(features)
         } else {
@@ -201,13 +399,262 @@ where
         }
     }
 
-    pub fn evaluate(&self, inputs: &[Vec<String>], labels: &[usize]) -> (usize, usize, usize) {
-        let features: Vec<_> = inputs
+    /// 只在 `allowed` 给出的标签中解码，`allowed[i]` 为 `None` 的位置不受限制，如
+    /// [`TagDictionary::allowed`] 给出的高频词出现过的标签；不使用维特比解码时，
+    /// 受限的位置只累加这些标签的参数；空的标签集合同样不受限制，
+    /// 标签下标超出标签数时返回 [`LtpError::LabelOutOfRange`]
+    ///
+    /// [`TagDictionary::allowed`]: crate::perceptron::TagDictionary::allowed
+    pub fn decode_restricted(
+        &self,
+        features: &FeatureArena<usize>,
+        allowed: &[Option<Vec<usize>>],
+    ) -> Result<Vec<usize>> {
+        let label_num = self.definition.label_num();
+        let mut ids = allowed.iter().flatten().flatten();
+        if let Some(&label) = ids.find(|&&label| label >= label_num) {
+            return Err(LtpError::LabelOutOfRange(label, label_num).into());
+        }
+        if features.is_empty() {
+            return Ok(Vec::new());
+        }
+        let labels = |position: usize| {
+            let labels = allowed.get(position).and_then(Option::as_deref);
+            labels.filter(|labels| !labels.is_empty())
+        };
+        if self.definition.use_viterbi() {
+            return Ok(self.viterbi_chunked(features.len(), |positions| {
+                let mut emission = self.emission_range(features, positions.clone());
+                for (row, position) in emission.chunks_exact_mut(label_num).zip(positions) {
+                    let Some(labels) = labels(position) else {
+                        continue;
+                    };
+                    for (label, score) in row.iter_mut().enumerate() {
+                        if !labels.contains(&label) {
+                            *score = Param::neg_infinity();
+                        }
+                    }
+                }
+                emission
+            }));
+        }
+
+        let all = (0..label_num).collect::<Vec<_>>();
+        let mut scores = vec![Param::zero(); label_num];
+        let preds = (0..features.len())
+            .map(|position| {
+                let labels = labels(position).unwrap_or(&all);
+                scores.fill(Param::zero());
+                for &feature in features.get(position).unwrap_or_default() {
+                    let base = feature * label_num;
+                    for &label in labels {
+                        scores[label] += self.parameters.get_param(base + label);
+                    }
+                }
+                let mut best = (labels[0], Param::neg_infinity());
+                for &label in labels {
+                    if scores[label] > best.1 {
+                        best = (label, scores[label]);
+                    }
+                }
+                best.0
+            })
+            .collect();
+        Ok(preds)
+    }
+
+    /// 每个位置上各标签的后验概率，按 `[position * label_num + label]` 排列；
+    /// 使用维特比解码的模型按前向后向算法计算边缘概率，其余对每个位置的分数做 softmax
+    pub fn marginals(&self, features: &FeatureArena<usize>) -> Vec<f64> {
+        let label_num = self.definition.label_num();
+        let emission: Vec<f64> = self
+            .emission_scores(features)
+            .into_iter()
+            .map(|score| score.to_f64().unwrap_or_default())
+            .collect();
+        let length = features.len();
+        let (mut alpha, mut beta) = (emission.clone(), vec![0.0; emission.len()]);
+        if self.definition.use_viterbi() {
+            let transition = |from: usize, to: usize| {
+                let score = self.parameters.get_param(from * label_num + to);
+                score.to_f64().unwrap_or_default()
+            };
+            for i in 1..length {
+                for label in 0..label_num {
+                    let previous = &alpha[(i - 1) * label_num..i * label_num];
+                    let scores = (0..label_num).map(|p| previous[p] + transition(p, label));
+                    alpha[i * label_num + label] += log_sum_exp(scores);
+                }
+            }
+            for i in (0..length.saturating_sub(1)).rev() {
+                for label in 0..label_num {
+                    let next = (i + 1) * label_num;
+                    let scores = (0..label_num)
+                        .map(|n| transition(label, n) + emission[next + n] + beta[next + n]);
+                    beta[i * label_num + label] = log_sum_exp(scores);
+                }
+            }
+        }
+        let mut marginals = vec![0.0; emission.len()];
+        for (i, row) in marginals.chunks_exact_mut(label_num).enumerate() {
+            let base = i * label_num;
+            let scores: Vec<f64> = (0..label_num)
+                .map(|label| alpha[base + label] + beta[base + label])
+                .collect();
+            // 每个位置上 alpha + beta 的总和都等于配分函数
+            let z = log_sum_exp(scores.iter().copied());
+            for (marginal, score) in row.iter_mut().zip(scores) {
+                *marginal = (score - z).exp();
+            }
+        }
+        marginals
+    }
+
+    /// 解码结果与每个位置上预测标签的后验概率
+    pub fn confidence(&self, features: &FeatureArena<usize>) -> (Vec<usize>, Vec<f64>) {
+        let label_num = self.definition.label_num();
+        let preds = self.decode(features);
+        let marginals = self.marginals(features);
+        let confidence = preds
             .iter()
-            .map(|f| self.features.get_vector_string(f))
+            .enumerate()
+            .map(|(i, &pred)| marginals[i * label_num + pred])
             .collect();
+        (preds, confidence)
+    }
+
+    /// 把一个句子逐词的预测置信度与是否正确计入校准报告，不确定的词不计入
+    pub fn calibration(
+        &self,
+        inputs: &FeatureArena<String>,
+        labels: &[usize],
+        report: &mut CalibrationReport,
+    ) {
+        let features = self.features.get_arena(inputs);
+        let (preds, confidence) = self.confidence(&features);
+        for ((&gold, &pred), confidence) in zip(zip(labels, &preds), confidence) {
+            if gold != UNCERTAIN {
+                report.add(confidence, gold == pred);
+            }
+        }
+    }
+
+    pub fn evaluate(
+        &self,
+        inputs: &FeatureArena<String>,
+        labels: &[usize],
+    ) -> (usize, usize, usize) {
+        let features = self.features.get_arena(inputs);
+        let preds = self.decode(&features);
+        // 不确定的词视为预测正确
+        self.definition
+            .evaluate(&preds, &resolve_uncertain(labels, &preds))
+    }
+
+    pub fn evaluate_report(&self, inputs: &FeatureArena<String>, labels: &[usize]) -> EvalReport {
+        let features = self.features.get_arena(inputs);
+        let preds = self.decode(&features);
+        let mut report = EvalReport::default();
+        self.definition
+            .evaluate_report(&preds, &resolve_uncertain(labels, &preds), &mut report);
+        report
+    }
+
+    /// 把一个句子的预测结果按实体计入片段级评测，不确定的词视为预测正确
+    pub fn span_report(
+        &self,
+        inputs: &FeatureArena<String>,
+        labels: &[usize],
+        report: &mut SpanReport,
+    ) {
+        let features = self.features.get_arena(inputs);
+        let preds = self.decode(&features);
+        let labels = resolve_uncertain(labels, &preds);
+        let predicted = self.definition.to_labels(&preds);
+        let gold = self.definition.to_labels(&labels);
+        report.add(&get_entities(&gold), &get_entities(&predicted));
+    }
+
+    /// 把一个句子逐词的预测结果计入混淆矩阵，不确定的词不计入
+    pub fn confusion(
+        &self,
+        inputs: &FeatureArena<String>,
+        labels: &[usize],
+        matrix: &mut ConfusionMatrix,
+    ) {
+        let features = self.features.get_arena(inputs);
+        let preds = self.decode(&features);
+        for (&gold, &pred) in zip(labels, &preds) {
+            if gold != UNCERTAIN {
+                matrix.add(gold, pred);
+            }
+        }
+    }
+
+    pub fn label_set(&self) -> LabelSet {
+        LabelSet::new(self.definition.labels())
+    }
+
+    #[cfg(any(feature = "pos", feature = "ner"))]
+    fn check_label_set(&self, labels: &LabelSet) -> Result<()> {
+        let label_num = self.definition.label_num();
+        let same = labels.len() == label_num
+            && labels
+                .iter()
+                .enumerate()
+                .all(|(index, label)| self.definition.to_label(index) == label);
+        match same {
+            true => Ok(()),
+            false => Err(LtpError::LabelSetMismatch.into()),
+        }
+    }
+
+    pub fn predict_indices_with_buffer(
+        &self,
+        sentence: &<Define::RawFeature as GenericItem>::Item,
+        buffer: &mut Vec<u8>,
+    ) -> Result<Vec<usize>> {
+        let (_, features) = self
+            .definition
+            .parse_features_with_buffer(sentence, buffer)?;
+        let features = self.features.get_arena(&features);
         let preds = self.decode(&features);
-        self.definition.evaluate(&preds, labels)
+        self.parameters.check()?;
+        Ok(preds)
+    }
+
+    /// 解码结果与每个位置上预测标签的后验概率，见 [`Perceptron::confidence`]
+    pub fn predict_confidence_with_buffer(
+        &self,
+        sentence: &<Define::RawFeature as GenericItem>::Item,
+        buffer: &mut Vec<u8>,
+    ) -> Result<(Vec<usize>, Vec<f64>)> {
+        let (_, features) = self
+            .definition
+            .parse_features_with_buffer(sentence, buffer)?;
+        let features = self.features.get_arena(&features);
+        let confidence = self.confidence(&features);
+        self.parameters.check()?;
+        Ok(confidence)
+    }
+
+    /// 按模型元数据中的标签字典限制各词可选的标签后解码，`words` 为句子中的词
+    fn restricted_indices(
+        &self,
+        sentence: &<Define::RawFeature as GenericItem>::Item,
+        words: &[&str],
+        min_count: usize,
+    ) -> Result<Vec<usize>> {
+        let mut buffer = Vec::with_capacity(self.definition.buffer_capacity(sentence));
+        let (_, features) = self
+            .definition
+            .parse_features_with_buffer(sentence, &mut buffer)?;
+        let features = self.features.get_arena(&features);
+        let tags = &self.metadata.tag_dictionary;
+        let allowed = tags.allowed(&self.definition, words, min_count);
+        let preds = self.decode_restricted(&features, &allowed)?;
+        self.parameters.check()?;
+        Ok(preds)
     }
 }
 
@@ -226,16 +673,15 @@ where
         let (fragment, features) = self
             .definition
             .parse_features_with_buffer(&sentence, buffer)?;
-        let features: Vec<_> = features
-            .iter()
-            .map(|f| self.features.get_vector_str(f))
-            .collect();
+        let features = self.features.get_arena(&features);
         let preds = self.decode(&features);
+        self.parameters.check()?;
 
         Ok(self.definition.predict(&sentence, &fragment, &preds))
     }
 }
 
+#[cfg(feature = "pos")]
 impl<Feature, ParamStorage, Param> Perceptron<POSDefinition, Feature, ParamStorage, Param>
 where
     Feature: TraitFeature,
@@ -243,11 +689,76 @@ where
     ParamStorage: TraitParameterStorage<Param>,
 {
     pub fn predict(&self, sentence: &[&str]) -> Result<Vec<&str>> {
-        let mut buffer = Vec::with_capacity(sentence.len() * 180);
+        let mut buffer = Vec::with_capacity(self.definition.buffer_capacity(&sentence));
         self.predict_with_buffer(sentence, &mut buffer)
     }
+
+    /// 训练语料中出现不少于 `min_count` 次的词只在其出现过的标签中选择，
+    /// 标签字典为模型元数据中的 [`ModelMetadata::tag_dictionary`]
+    pub fn predict_restricted(&self, sentence: &[&str], min_count: usize) -> Result<Vec<&str>> {
+        let preds = self.restricted_indices(&sentence, sentence, min_count)?;
+        Ok(self.definition.to_labels(&preds))
+    }
+
+    /// 预先查好 `words` 的词内特征的编号，一般在加载模型后对高频词调用一次，如
+    /// `model.word_cache(model.metadata.tag_dictionary.most_frequent(10000))`
+    pub fn word_cache<I, S>(&self, words: I) -> Result<WordFeatureCache>
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        let mut cache = WordFeatureCache::new();
+        for word in words {
+            let (word, features) = self.definition.word_features(word.as_ref())?;
+            let features = features
+                .iter()
+                .filter_map(|feature| self.features.get_with_key(feature))
+                .collect();
+            cache.insert(word, features);
+        }
+        Ok(cache)
+    }
+
+    /// 同 [`predict`](Self::predict)，缓存中的词只抽取上下文特征，词内特征取缓存中的编号
+    pub fn predict_cached(&self, sentence: &[&str], cache: &WordFeatureCache) -> Result<Vec<&str>> {
+        let mut buffer = Vec::with_capacity(self.definition.buffer_capacity(&sentence));
+        let mut hits = Vec::with_capacity(sentence.len());
+        let cached = |word: &str| {
+            let hit = cache.get(word);
+            hits.push(hit);
+            hit.is_some()
+        };
+        let define = &self.definition;
+        let ends = define.parse_context_features_with_buffer(sentence, &mut buffer, cached)?;
+        let strings = split_buffer(&buffer, &ends);
+        let mut features = FeatureArena::with_capacity(strings.len(), ends.feature_len());
+        for (token, hit) in strings.iter().zip(hits) {
+            for &feature in hit.unwrap_or_default() {
+                features.push(feature);
+            }
+            for feature in token {
+                if let Some(feature) = self.features.get_with_key(feature) {
+                    features.push(feature);
+                }
+            }
+            features.end_token();
+        }
+        let preds = self.decode(&features);
+        self.parameters.check()?;
+        Ok(self.definition.to_labels(&preds))
+    }
+
+    /// 返回不依赖模型生命周期的标签，`labels` 应来自 [`Perceptron::label_set`]，
+    /// 与模型的标签表不同时返回 [`LtpError::LabelSetMismatch`]
+    pub fn predict_interned(&self, sentence: &[&str], labels: &LabelSet) -> Result<InternedLabels> {
+        self.check_label_set(labels)?;
+        let mut buffer = Vec::with_capacity(self.definition.buffer_capacity(&sentence));
+        let preds = self.predict_indices_with_buffer(&sentence, &mut buffer)?;
+        Ok(InternedLabels::from_indices(labels.clone(), &preds)?)
+    }
 }
 
+#[cfg(feature = "ner")]
 impl<Feature, ParamStorage, Param> Perceptron<NERDefinition, Feature, ParamStorage, Param>
 where
     Feature: TraitFeature,
@@ -255,13 +766,44 @@ where
     ParamStorage: TraitParameterStorage<Param>,
 {
     pub fn predict(&self, sentence: (&[&str], &[&str])) -> Result<Vec<&str>> {
-        let mut buffer = Vec::with_capacity(sentence.0.len() * 150);
+        let mut buffer = Vec::with_capacity(self.definition.buffer_capacity(&sentence));
         self.predict_with_buffer(sentence, &mut buffer)
     }
+
+    /// 返回不依赖模型生命周期的标签，`labels` 应来自 [`Perceptron::label_set`]，
+    /// 与模型的标签表不同时返回 [`LtpError::LabelSetMismatch`]
+    pub fn predict_interned(
+        &self,
+        sentence: (&[&str], &[&str]),
+        labels: &LabelSet,
+    ) -> Result<InternedLabels> {
+        self.check_label_set(labels)?;
+        let mut buffer = Vec::with_capacity(self.definition.buffer_capacity(&sentence));
+        let preds = self.predict_indices_with_buffer(&sentence, &mut buffer)?;
+        Ok(InternedLabels::from_indices(labels.clone(), &preds)?)
+    }
 }
 
-use crate::{get_entities, CWSDefinition, NERDefinition, POSDefinition};
+impl<Feature, ParamStorage, Param> Perceptron<SequenceDefinition, Feature, ParamStorage, Param>
+where
+    Feature: TraitFeature,
+    Param: TraitParameter,
+    ParamStorage: TraitParameterStorage<Param>,
+{
+    pub fn predict(&self, sentence: &[&str]) -> Result<Vec<&str>> {
+        let mut buffer = Vec::with_capacity(self.definition.buffer_capacity(&sentence));
+        self.predict_with_buffer(sentence, &mut buffer)
+    }
+
+    /// 训练语料中出现不少于 `min_count` 次的词只在其出现过的标签中选择，
+    /// 标签字典为模型元数据中的 [`ModelMetadata::tag_dictionary`]
+    pub fn predict_restricted(&self, sentence: &[&str], min_count: usize) -> Result<Vec<&str>> {
+        let preds = self.restricted_indices(&sentence, sentence, min_count)?;
+        Ok(self.definition.to_labels(&preds))
+    }
+}
 
+#[cfg(feature = "cws")]
 impl<Feature, ParamStorage, Param> Perceptron<CWSDefinition, Feature, ParamStorage, Param>
 where
     Feature: TraitFeature,
@@ -273,7 +815,7 @@ where
     }
 
     pub fn predict<'a>(&self, sentence: &'a str) -> Result<Vec<&'a str>> {
-        let mut buffer = Vec::with_capacity(sentence.len() * 20);
+        let mut buffer = Vec::with_capacity(self.definition.buffer_capacity(&sentence));
         self.predict_with_buffer(sentence, &mut buffer)
     }
 
@@ -285,25 +827,20 @@ where
         let (fragments, features) = self
             .definition
             .parse_features_with_buffer(&sentence, buffer)?;
-        let features: Vec<_> = features
-            .iter()
-            .map(|f| self.features.get_vector_str(f))
-            .collect();
+        let features = self.features.get_arena(&features);
         let preds = self.decode(&features);
+        self.parameters.check()?;
 
         let preds = self.definition.to_labels(&preds);
         let preds = get_entities(&preds);
         Ok(preds
             .into_iter()
-            .map(|(_, start, end)| {
-                let start = fragments[start];
-                let end = fragments[end + 1];
-                &sentence[start..end]
-            })
+            .map(|(_, start, end)| self.definition.word(sentence, &fragments, start, end))
             .collect::<Vec<_>>())
     }
 }
 
+#[cfg(feature = "cws")]
 impl<Feature, ParamStorage, Param> Perceptron<CWSDefinition, Feature, ParamStorage, Param>
 where
     Feature: TraitFeature + TraitFeaturesTrainUtils,
@@ -345,7 +882,7 @@ where
     // 被动攻击算法
     pub fn pa_train_iter(
         &mut self,
-        inputs: &[Vec<String>],
+        inputs: &FeatureArena<String>,
         labels: &[usize],
         total: &mut [Param],
         timestamp: &mut [usize],
@@ -353,11 +890,9 @@ where
         mode: &PaMode<Param>,
     ) {
         let label_num = self.definition.label_num();
-        let features: Vec<_> = inputs
-            .iter()
-            .map(|f| self.features.get_vector_string(f))
-            .collect();
+        let features = self.features.get_arena(inputs);
         let preds = self.decode(&features);
+        let labels = &*resolve_uncertain(labels, &preds);
 
         if labels.ne(&preds) {
             let errors = zip(labels, &preds)
@@ -458,18 +993,16 @@ where
 
     pub fn ap_train_iter(
         &mut self,
-        inputs: &[Vec<String>],
+        inputs: &FeatureArena<String>,
         labels: &[usize],
         total: &mut [Param],
         timestamp: &mut [usize],
         current: usize,
     ) {
         let label_num = self.definition.label_num();
-        let features: Vec<_> = inputs
-            .iter()
-            .map(|f| self.features.get_vector_string(f))
-            .collect();
+        let features = self.features.get_arena(inputs);
         let preds = self.decode(&features);
+        let labels = &*resolve_uncertain(labels, &preds);
 
         if labels.ne(&preds) {
             for (idx, (&gold, &pred)) in zip(labels, &preds)
@@ -517,13 +1050,11 @@ where
     }
 
     // 并行 averaged perceptron 算法
-    pub fn ap_train_parallel_iter(&mut self, inputs: &[Vec<String>], labels: &[usize]) {
+    pub fn ap_train_parallel_iter(&mut self, inputs: &FeatureArena<String>, labels: &[usize]) {
         let label_num = self.definition.label_num();
-        let features: Vec<_> = inputs
-            .iter()
-            .map(|f| self.features.get_vector_string(f))
-            .collect();
+        let features = self.features.get_arena(inputs);
         let preds = self.decode(&features);
+        let labels = &*resolve_uncertain(labels, &preds);
 
         if labels.ne(&preds) {
             for (idx, (&gold, &pred)) in zip(labels, &preds)
@@ -560,7 +1091,7 @@ where
         let start = feature * label_num;
         let end = start + label_num;
         for i in start..end {
-            score += parameters[i].abs();
+            score += parameters.get_param(i).abs();
         }
         score
     }
@@ -605,7 +1136,7 @@ where
             );
 
             for i in 0..trans_len {
-                new_parameters.push(old_parameters[i]);
+                new_parameters.push(old_parameters.get_param(i));
             }
 
             (new_parameters, label_num)
@@ -625,10 +1156,152 @@ where
             new_features.insert_feature(feature, idx + bias);
             let param_start = old_idx * label_num;
             for label_idx in 0..label_num {
-                new_parameters.push(old_parameters[param_start + label_idx]);
+                new_parameters.push(old_parameters.get_param(param_start + label_idx));
             }
         }
 
         Self::new_with_parameters(self.definition, new_features, new_parameters)
+            .with_metadata(self.metadata)
+    }
+}
+
+#[cfg(all(test, feature = "cws"))]
+mod tests {
+    use super::*;
+    use crate::perceptron::{Trainer, TraitFeature};
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_score() -> Result<()> {
+        let define = CWSDefinition::default();
+        let dataset =
+            define.parse_gold_features("他 叫 汤姆 去 拿 外衣 。\n我们 去 拿 外衣\n".as_bytes())?;
+        let mut trainer: Trainer<CWSDefinition> =
+            Trainer::new().epoch(5).verbose(false).compress(false);
+        trainer.train_set = Some(dataset);
+        let model = trainer.build::<HashMap<String, usize>, Vec<f64>>()?;
+
+        let (_, features) = model.definition.parse_char_features("我们去拿外衣")?;
+        let emission = model.emission_scores(&model.features.get_arena(&features));
+        let label_num = model.definition.label_num();
+        for (position, row) in emission.chunks_exact(label_num).enumerate() {
+            let scores = model.score_all(features.get(position).unwrap());
+            assert_eq!(scores, row);
+            let label = model.definition.to_label(1);
+            assert_eq!(model.score(features.get(position).unwrap(), label)?, row[1]);
+        }
+        assert_eq!(model.score_all(&["不存在的特征"]), vec![0.0; label_num]);
+        assert_eq!(model.transition_scores().len(), label_num * label_num);
+        let error = model.score(&["2我"], "X").unwrap_err();
+        assert_eq!(
+            error.downcast_ref::<LtpError>(),
+            Some(&LtpError::UnknownLabel("X".into()))
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_chunking() -> Result<()> {
+        let chunking = Chunking::new(5, 2)?;
+        assert_eq!(chunking.chunks(4), vec![(0..4, 0..4)]);
+        assert_eq!(
+            chunking.chunks(11),
+            vec![(0..5, 0..4), (3..8, 4..7), (6..11, 7..11)]
+        );
+        assert!(Chunking::new(3, 3).is_err());
+
+        let define = CWSDefinition::default();
+        let dataset =
+            define.parse_gold_features("他 叫 汤姆 去 拿 外衣 。\n我们 去 拿 外衣\n".as_bytes())?;
+        let mut trainer: Trainer<CWSDefinition> =
+            Trainer::new().epoch(5).verbose(false).compress(false);
+        trainer.train_set = Some(dataset);
+        let model = trainer.build::<HashMap<String, usize>, Vec<f64>>()?;
+
+        let sentence = "我们去拿外衣。".repeat(40);
+        let expected = model.predict(&sentence)?;
+        let model = model.with_chunking(Chunking::new(16, 6)?);
+        let words = model.predict(&sentence)?;
+        assert_eq!(words.concat(), sentence);
+        assert_eq!(words, expected);
+        Ok(())
+    }
+
+    #[test]
+    fn test_decode_restricted() -> Result<()> {
+        use crate::perceptron::cws_seeded_model;
+
+        let model = cws_seeded_model(&["2我", "2们", "2去"], 1);
+        let (_, features) = model.definition.parse_char_features("我们去")?;
+        let features = model.features.get_arena(&features);
+        let preds = model.decode(&features);
+
+        // 空的标签集合与 None 一样不受限制
+        let allowed = [Some(vec![]), None, Some(vec![])];
+        assert_eq!(model.decode_restricted(&features, &allowed)?, preds);
+        assert_eq!(
+            model.decode_restricted(&features, &vec![Some(vec![3]); 3])?,
+            vec![3; 3]
+        );
+
+        let error = model
+            .decode_restricted(&features, &[Some(vec![0, 4])])
+            .unwrap_err();
+        assert_eq!(
+            error.downcast_ref::<LtpError>(),
+            Some(&LtpError::LabelOutOfRange(4, 4))
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_uncertain_updates() -> Result<()> {
+        use crate::perceptron::{cws_test_model, HogwildParameters};
+
+        let define = CWSDefinition::default();
+        let mut samples = define.parse_gold_features("我们 去\n".as_bytes())?;
+        let sample = samples.remove(0);
+        let model = cws_test_model(&["2我", "2们", "2去"], |_| 0.0);
+        let preds = model.decode(&model.features.get_arena(&sample.features));
+        let (len, label_num) = (model.parameters.len(), define.label_num());
+
+        // 每种算法更新一次后的参数
+        let updated = |labels: &[usize]| {
+            let (mut total, mut timestamp) = (vec![0.0; len], vec![0; len]);
+            let mut ap = model.clone();
+            ap.ap_train_iter(&sample.features, labels, &mut total, &mut timestamp, 1);
+            let mut pa = model.clone();
+            let (total, timestamp) = (&mut total, &mut timestamp);
+            pa.pa_train_iter(&sample.features, labels, total, timestamp, 1, &PaMode::Pa);
+            let mut parallel = model.clone();
+            parallel.ap_train_parallel_iter(&sample.features, labels);
+            let hogwild = Perceptron::new_with_parameters(
+                model.definition.clone(),
+                model.features.clone(),
+                HogwildParameters::<f64>::new(len),
+            );
+            hogwild.hogwild_train_iter(&sample.features, labels);
+            [
+                ap.parameters,
+                pa.parameters,
+                parallel.parameters,
+                hogwild.parameters.snapshot(),
+            ]
+        };
+
+        // 只在不确定的词上与预测不同，参数不变
+        let mut uncertain = preds.clone();
+        uncertain[1..].fill(UNCERTAIN);
+        for parameters in updated(&uncertain) {
+            assert_eq!(parameters, model.parameters);
+        }
+
+        // 确定的词预测错误时仍然更新
+        let mut wrong = uncertain.clone();
+        wrong[0] = (preds[0] + 1) % label_num;
+        for parameters in updated(&wrong) {
+            assert_ne!(parameters, model.parameters);
+        }
+        Ok(())
     }
 }
@@ -0,0 +1,224 @@
+//! 特征抽取前对字或词的规范化：训练时由定义记录在模型的元信息中，加载模型后自动恢复，
+//! 预测时以相同的方式规范化；只影响特征，分词等结果仍是原文的切片
+use anyhow::{anyhow, Result};
+use std::borrow::Cow;
+use std::fmt::Debug;
+use std::sync::Arc;
+
+pub trait Normalizer: Debug + Send + Sync {
+    /// 保存在模型元信息中的名字，不同的规范化方式须有不同的名字
+    fn name(&self) -> &str;
+
+    fn normalize<'a>(&self, token: &'a str) -> Cow<'a, str>;
+}
+
+/// 逐字替换，没有字被替换时不分配
+fn map_chars(token: &str, map: impl Fn(char) -> char) -> Cow<'_, str> {
+    match token.chars().any(|ch| map(ch) != ch) {
+        true => Cow::Owned(token.chars().map(map).collect()),
+        false => Cow::Borrowed(token),
+    }
+}
+
+/// 拉丁字母转为小写，如 `iPhone` → `iphone`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct LowercaseLatin;
+
+impl Normalizer for LowercaseLatin {
+    fn name(&self) -> &str {
+        "lowercase-latin"
+    }
+
+    fn normalize<'a>(&self, token: &'a str) -> Cow<'a, str> {
+        map_chars(token, |ch| match ch {
+            'A'..='Z' | 'Ａ'..='Ｚ' => char::from_u32(ch as u32 + 0x20).unwrap_or(ch),
+            ch => ch,
+        })
+    }
+}
+
+/// 全角字母、数字、标点与空格转为半角
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct WidthFold;
+
+impl Normalizer for WidthFold {
+    fn name(&self) -> &str {
+        "width-fold"
+    }
+
+    fn normalize<'a>(&self, token: &'a str) -> Cow<'a, str> {
+        map_chars(token, |ch| match ch {
+            '\u{3000}' => ' ',
+            '\u{FF01}'..='\u{FF5E}' => char::from_u32(ch as u32 - 0xFEE0).unwrap_or(ch),
+            ch => ch,
+        })
+    }
+}
+
+/// 阿拉伯数字（含全角）都替换为 `0`，使特征不依赖具体的数值
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct DigitFold;
+
+impl Normalizer for DigitFold {
+    fn name(&self) -> &str {
+        "digit-fold"
+    }
+
+    fn normalize<'a>(&self, token: &'a str) -> Cow<'a, str> {
+        map_chars(token, |ch| match ch {
+            '0'..='9' | '０'..='９' => '0',
+            ch => ch,
+        })
+    }
+}
+
+#[derive(Debug, Clone)]
+enum Slot {
+    Ready(Arc<dyn Normalizer>),
+    /// 加载模型时没有对应实现的规范化方式，预测前须用 [`Normalizers::resolve`] 提供
+    Unresolved(String),
+}
+
+impl Slot {
+    fn name(&self) -> &str {
+        match self {
+            Slot::Ready(normalizer) => normalizer.name(),
+            Slot::Unresolved(name) => name,
+        }
+    }
+}
+
+/// 依次应用的规范化方式，按名字比较
+#[derive(Debug, Clone, Default)]
+pub struct Normalizers(Vec<Slot>);
+
+impl PartialEq for Normalizers {
+    fn eq(&self, other: &Self) -> bool {
+        self.names() == other.names()
+    }
+}
+
+impl Eq for Normalizers {}
+
+impl Normalizers {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 内置的规范化方式：`lowercase-latin`、`width-fold`、`digit-fold`
+    pub fn builtin(name: &str) -> Option<Arc<dyn Normalizer>> {
+        match name {
+            "lowercase-latin" => Some(Arc::new(LowercaseLatin)),
+            "width-fold" => Some(Arc::new(WidthFold)),
+            "digit-fold" => Some(Arc::new(DigitFold)),
+            _ => None,
+        }
+    }
+
+    /// 由模型元信息中的名字恢复，非内置的规范化方式须再用 [`Normalizers::resolve`] 提供
+    pub fn from_names<S: AsRef<str>>(names: &[S]) -> Self {
+        let slot = |name: &str| match Self::builtin(name) {
+            Some(normalizer) => Slot::Ready(normalizer),
+            None => Slot::Unresolved(name.to_string()),
+        };
+        Self(names.iter().map(|name| slot(name.as_ref())).collect())
+    }
+
+    pub fn with<N: Normalizer + 'static>(mut self, normalizer: N) -> Self {
+        self.0.push(Slot::Ready(Arc::new(normalizer)));
+        self
+    }
+
+    /// 提供同名的未恢复的规范化方式
+    pub fn resolve(&mut self, normalizer: Arc<dyn Normalizer>) {
+        for slot in &mut self.0 {
+            if matches!(slot, Slot::Unresolved(name) if name == normalizer.name()) {
+                *slot = Slot::Ready(normalizer.clone());
+            }
+        }
+    }
+
+    /// 未恢复的规范化方式的名字
+    pub fn unresolved(&self) -> Vec<&str> {
+        self.0
+            .iter()
+            .filter(|slot| matches!(slot, Slot::Unresolved(_)))
+            .map(Slot::name)
+            .collect()
+    }
+
+    pub fn names(&self) -> Vec<String> {
+        self.0.iter().map(|slot| slot.name().to_string()).collect()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// 有未恢复的规范化方式时返回错误
+    pub fn apply<'a>(&self, token: &'a str) -> Result<Cow<'a, str>> {
+        let mut token = Cow::Borrowed(token);
+        for slot in &self.0 {
+            let normalizer = match slot {
+                Slot::Ready(normalizer) => normalizer,
+                Slot::Unresolved(name) => {
+                    return Err(anyhow!("normalizer `{}` is not resolved", name));
+                }
+            };
+            if let Cow::Owned(normalized) = normalizer.normalize(&token) {
+                token = Cow::Owned(normalized);
+            }
+        }
+        Ok(token)
+    }
+
+    pub fn apply_all<'a>(&self, tokens: &[&'a str]) -> Result<Vec<Cow<'a, str>>> {
+        tokens.iter().map(|token| self.apply(token)).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalizers() -> Result<()> {
+        let normalizers = Normalizers::new()
+            .with(WidthFold)
+            .with(LowercaseLatin)
+            .with(DigitFold);
+        assert_eq!(normalizers.apply("ＩＰｈｏｎｅ１５")?, "iphone00");
+        assert!(matches!(normalizers.apply("北京")?, Cow::Borrowed(_)));
+        assert_eq!(
+            normalizers.names(),
+            ["width-fold", "lowercase-latin", "digit-fold"]
+        );
+        assert_eq!(Normalizers::from_names(&normalizers.names()), normalizers);
+
+        #[derive(Debug)]
+        struct Strip;
+        impl Normalizer for Strip {
+            fn name(&self) -> &str {
+                "strip"
+            }
+            fn normalize<'a>(&self, token: &'a str) -> Cow<'a, str> {
+                Cow::Owned(token.replace('·', ""))
+            }
+        }
+        let mut loaded = Normalizers::from_names(&["digit-fold", "strip"]);
+        assert_eq!(loaded.unresolved(), ["strip"]);
+        assert!(loaded.apply("1").is_err());
+        loaded.resolve(Arc::new(Strip));
+        assert_eq!(loaded.apply("维·1")?, "维0");
+
+        // 只有特征被规范化，词仍是原文
+        #[cfg(feature = "cws")]
+        {
+            let define = crate::CWSDefinition::new().with_normalizers(loaded);
+            let (index, features) = define.parse_char_features("买5")?;
+            assert_eq!(features.get(1).unwrap()[0], "20");
+            assert_eq!(define.word("买5", &index, 1, 1), "5");
+        }
+        Ok(())
+    }
+}
@@ -1,3 +1,4 @@
+use anyhow::Result;
 use num_traits::{Float, Num, NumAssignOps};
 use std::ops::{Deref, Index, IndexMut};
 
@@ -24,11 +25,30 @@ where
     }
 }
 
-pub trait TraitParameterStorage<Param>:
-    Index<usize, Output = Param> + TraitParameterStorageUtils
+// 推理只需要按值读取参数，量化存储可以在读取时反量化
+pub trait TraitParameterStorage<Param>: TraitParameterStorageUtils
 where
     Param: TraitParameter,
 {
+    fn get_param(&self, index: usize) -> Param;
+
+    /// 提示即将读取 `index` 处的参数，不支持的存储忽略即可
+    #[inline]
+    fn prefetch(&self, _index: usize) {}
+
+    /// 把从 `start` 开始的 `scores.len()` 个参数累加到 `scores` 上，按行读取的存储可以一次读出整行
+    #[inline]
+    fn add_row(&self, start: usize, scores: &mut [Param]) {
+        for (offset, score) in scores.iter_mut().enumerate() {
+            *score += self.get_param(start + offset);
+        }
+    }
+
+    /// 读取参数的过程中是否出错，如按需加载的冷段读取失败；解码后检查，出错时解码结果不可信
+    #[inline]
+    fn check(&self) -> Result<()> {
+        Ok(())
+    }
 }
 
 impl<T, Param> TraitParameterStorage<Param> for T
@@ -36,6 +56,29 @@ where
     T: Index<usize, Output = Param> + TraitParameterStorageUtils,
     Param: TraitParameter,
 {
+    #[inline]
+    fn get_param(&self, index: usize) -> Param {
+        self[index]
+    }
+
+    #[inline]
+    fn prefetch(&self, index: usize) {
+        if index < self.len() {
+            prefetch_read(&self[index]);
+        }
+    }
+}
+
+#[inline(always)]
+#[allow(unused_variables)]
+fn prefetch_read<T>(value: &T) {
+    #[cfg(target_arch = "x86_64")]
+    #[allow(unused_unsafe)]
+    // Safety : prefetch 只是提示，不会访问内存
+    unsafe {
+        use std::arch::x86_64::{_mm_prefetch, _MM_HINT_T0};
+        _mm_prefetch::<_MM_HINT_T0>(value as *const T as *const i8);
+    }
 }
 
 impl<T> TraitParameterStorageUtils for Vec<T> {
@@ -0,0 +1,368 @@
+//! 模型的稀疏差分：增量训练后只传输变化的转移参数与新增、删除或权重变化的特征，
+//! 设备上在旧模型上应用补丁即可得到新模型
+//!
+//! 补丁格式（小端）：魔数 `LTPD`、格式版本 u16、标签表哈希 u64、基础模型与目标模型的指纹 u64、
+//! 标签数 u32，之后依次为变化的转移参数（下标 u32 与值）、删除的特征与新增或变化的特征，
+//! 各部分以 u32 的个数开头；字符串为 u32 长度与 UTF-8 字节，参数均为 f64。
+use crate::perceptron::{
+    label_hash, Definition, Perceptron, TraitFeature, TraitFeatureCompressUtils,
+    TraitFeaturesTrainUtils, TraitParameter, TraitParameterStorage,
+    TraitParameterStorageCompressUtils,
+};
+#[cfg(feature = "fs")]
+use crate::perceptron::{CompressedReader, Compression};
+use anyhow::{anyhow, Result};
+use std::collections::BTreeMap;
+#[cfg(feature = "fs")]
+use std::fs::File;
+use std::io::{Read, Write};
+#[cfg(feature = "fs")]
+use std::path::Path;
+
+pub const PATCH_MAGIC: [u8; 4] = *b"LTPD";
+pub const PATCH_VERSION: u16 = 1;
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ModelPatch {
+    pub label_hash: u64,
+    /// 只能应用在指纹相同的模型上，见 [`Perceptron::fingerprint`]
+    pub base: u64,
+    /// 应用补丁后的模型的指纹
+    pub target: u64,
+    pub label_num: usize,
+    /// 变化的转移参数的下标与新值
+    pub transitions: Vec<(usize, f64)>,
+    pub removed: Vec<String>,
+    /// 新增或权重变化的特征，及其在各标签上的参数
+    pub upserted: Vec<(String, Vec<f64>)>,
+}
+
+const FNV_OFFSET: u64 = 0xcbf29ce484222325;
+const FNV_PRIME: u64 = 0x100000001b3;
+
+fn fnv(hash: &mut u64, bytes: &[u8]) {
+    for &byte in bytes {
+        *hash ^= byte as u64;
+        *hash = hash.wrapping_mul(FNV_PRIME);
+    }
+}
+
+fn to_f64<Param: TraitParameter>(value: Param) -> f64 {
+    value.to_f64().unwrap_or(f64::NAN)
+}
+
+fn write_u32<W: Write>(writer: &mut W, value: usize) -> Result<()> {
+    let value = u32::try_from(value).map_err(|_| anyhow!("{} is too large for a patch", value))?;
+    writer.write_all(&value.to_le_bytes())?;
+    Ok(())
+}
+
+fn write_str<W: Write>(writer: &mut W, value: &str) -> Result<()> {
+    write_u32(writer, value.len())?;
+    writer.write_all(value.as_bytes())?;
+    Ok(())
+}
+
+fn read_array<R: Read, const N: usize>(reader: &mut R) -> Result<[u8; N]> {
+    let mut bytes = [0u8; N];
+    reader.read_exact(&mut bytes)?;
+    Ok(bytes)
+}
+
+fn read_u32<R: Read>(reader: &mut R) -> Result<usize> {
+    Ok(u32::from_le_bytes(read_array(reader)?) as usize)
+}
+
+fn read_f64<R: Read>(reader: &mut R) -> Result<f64> {
+    Ok(f64::from_le_bytes(read_array(reader)?))
+}
+
+fn read_str<R: Read>(reader: &mut R) -> Result<String> {
+    let mut bytes = vec![0u8; read_u32(reader)?];
+    reader.read_exact(&mut bytes)?;
+    Ok(String::from_utf8(bytes)?)
+}
+
+impl ModelPatch {
+    /// 新增、删除与变化的特征数
+    pub fn len(&self) -> usize {
+        self.removed.len() + self.upserted.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.transitions.is_empty() && self.removed.is_empty() && self.upserted.is_empty()
+    }
+
+    pub fn write<W: Write>(&self, writer: &mut W) -> Result<()> {
+        writer.write_all(&PATCH_MAGIC)?;
+        writer.write_all(&PATCH_VERSION.to_le_bytes())?;
+        for value in [self.label_hash, self.base, self.target] {
+            writer.write_all(&value.to_le_bytes())?;
+        }
+        write_u32(writer, self.label_num)?;
+        write_u32(writer, self.transitions.len())?;
+        for &(index, value) in &self.transitions {
+            write_u32(writer, index)?;
+            writer.write_all(&value.to_le_bytes())?;
+        }
+        write_u32(writer, self.removed.len())?;
+        for feature in &self.removed {
+            write_str(writer, feature)?;
+        }
+        write_u32(writer, self.upserted.len())?;
+        for (feature, row) in &self.upserted {
+            write_str(writer, feature)?;
+            for value in row {
+                writer.write_all(&value.to_le_bytes())?;
+            }
+        }
+        Ok(())
+    }
+
+    pub fn read<R: Read>(reader: &mut R) -> Result<Self> {
+        if read_array::<_, 4>(reader)? != PATCH_MAGIC {
+            return Err(anyhow!("not an ltp model patch"));
+        }
+        let version = u16::from_le_bytes(read_array(reader)?);
+        if version > PATCH_VERSION {
+            return Err(anyhow!(
+                "patch built with newer format (version {}), please upgrade ltp",
+                version
+            ));
+        }
+        let label_hash = u64::from_le_bytes(read_array(reader)?);
+        let base = u64::from_le_bytes(read_array(reader)?);
+        let target = u64::from_le_bytes(read_array(reader)?);
+        let label_num = read_u32(reader)?;
+        let transitions = (0..read_u32(reader)?)
+            .map(|_| Ok((read_u32(reader)?, read_f64(reader)?)))
+            .collect::<Result<_>>()?;
+        let removed = (0..read_u32(reader)?)
+            .map(|_| read_str(reader))
+            .collect::<Result<_>>()?;
+        let upserted = (0..read_u32(reader)?)
+            .map(|_| {
+                let feature = read_str(reader)?;
+                let row = (0..label_num)
+                    .map(|_| read_f64(reader))
+                    .collect::<Result<_>>()?;
+                Ok((feature, row))
+            })
+            .collect::<Result<_>>()?;
+        Ok(ModelPatch {
+            label_hash,
+            base,
+            target,
+            label_num,
+            transitions,
+            removed,
+            upserted,
+        })
+    }
+
+    /// 根据扩展名（`.gz`、`.zst`）自动选择压缩方式
+    #[cfg(feature = "fs")]
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        let compression = Compression::from_path(&path);
+        let mut writer = compression.encoder(File::create(path)?)?;
+        self.write(&mut writer)?;
+        writer.finish()?;
+        Ok(())
+    }
+
+    #[cfg(feature = "fs")]
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self> {
+        Self::read(&mut CompressedReader::open(path)?)
+    }
+}
+
+impl<Define, Feature, ParamStorage, Param> Perceptron<Define, Feature, ParamStorage, Param>
+where
+    Feature: TraitFeature + TraitFeaturesTrainUtils + TraitFeatureCompressUtils,
+    Param: TraitParameter,
+    ParamStorage: TraitParameterStorage<Param> + TraitParameterStorageCompressUtils<Param>,
+    Define: Definition,
+{
+    fn transition_num(&self) -> usize {
+        match self.definition.use_viterbi() {
+            true => self.definition.label_num() * self.definition.label_num(),
+            false => 0,
+        }
+    }
+
+    /// 按特征排序的参数行的起点
+    fn feature_rows(&self) -> BTreeMap<String, usize> {
+        let label_num = self.definition.label_num();
+        let features = self.features.clone().features().into_iter();
+        features
+            .map(|(feature, idx)| (feature, idx * label_num))
+            .collect()
+    }
+
+    fn row(&self, start: usize) -> impl Iterator<Item = f64> + '_ {
+        let label_num = self.definition.label_num();
+        (start..start + label_num).map(|i| to_f64(self.parameters.get_param(i)))
+    }
+
+    /// 标签、转移参数与各特征参数的哈希，与特征的下标无关
+    pub fn fingerprint(&self) -> u64 {
+        let mut hash = FNV_OFFSET;
+        let labels = label_hash(&self.definition.labels());
+        fnv(&mut hash, &labels.to_le_bytes());
+        for i in 0..self.transition_num() {
+            let value = to_f64(self.parameters.get_param(i));
+            fnv(&mut hash, &value.to_le_bytes());
+        }
+        for (feature, start) in self.feature_rows() {
+            fnv(&mut hash, feature.as_bytes());
+            fnv(&mut hash, &[0]);
+            for value in self.row(start) {
+                fnv(&mut hash, &value.to_le_bytes());
+            }
+        }
+        hash
+    }
+
+    /// 由旧模型到新模型的补丁，两者的标签须一致
+    pub fn diff(&self, target: &Self) -> Result<ModelPatch> {
+        let labels = self.definition.labels();
+        if labels != target.definition.labels()
+            || self.definition.use_viterbi() != target.definition.use_viterbi()
+        {
+            return Err(anyhow!("models to diff must share the same labels"));
+        }
+        let transitions = (0..self.transition_num())
+            .map(|i| (i, to_f64(target.parameters.get_param(i))))
+            .filter(|&(i, value)| to_f64(self.parameters.get_param(i)) != value)
+            .collect();
+
+        let base_rows = self.feature_rows();
+        let target_rows = target.feature_rows();
+        let removed = base_rows
+            .keys()
+            .filter(|feature| !target_rows.contains_key(*feature))
+            .cloned()
+            .collect();
+        let upserted = target_rows
+            .into_iter()
+            .filter_map(|(feature, start)| {
+                let row: Vec<f64> = target.row(start).collect();
+                match base_rows.get(&feature) {
+                    Some(&base) if self.row(base).eq(row.iter().copied()) => None,
+                    _ => Some((feature, row)),
+                }
+            })
+            .collect();
+        Ok(ModelPatch {
+            label_hash: label_hash(&labels),
+            base: self.fingerprint(),
+            target: target.fingerprint(),
+            label_num: labels.len(),
+            transitions,
+            removed,
+            upserted,
+        })
+    }
+
+    /// 应用补丁，模型与补丁的基础模型不一致或结果与目标模型不一致时返回错误
+    pub fn apply_patch(&self, patch: &ModelPatch) -> Result<Self> {
+        let label_num = self.definition.label_num();
+        if patch.label_hash != label_hash(&self.definition.labels()) || patch.label_num != label_num
+        {
+            return Err(anyhow!("the labels of the patch do not match the model"));
+        }
+        if patch.base != self.fingerprint() {
+            return Err(anyhow!("the patch was made for a different base model"));
+        }
+        let param = |value: f64| {
+            Param::from(value).ok_or_else(|| anyhow!("parameter {} is out of range", value))
+        };
+
+        enum Row<'a> {
+            Base(usize),
+            Patched(&'a [f64]),
+        }
+        let mut rows: BTreeMap<&str, Row> = BTreeMap::new();
+        let base_rows = self.feature_rows();
+        for (feature, &start) in &base_rows {
+            rows.insert(feature, Row::Base(start));
+        }
+        for feature in &patch.removed {
+            rows.remove(feature.as_str());
+        }
+        for (feature, row) in &patch.upserted {
+            rows.insert(feature, Row::Patched(row));
+        }
+
+        let bias = self.transition_num() / label_num.max(1);
+        let mut parameters = ParamStorage::with_capacity((bias + rows.len()) * label_num);
+        let mut transitions = patch.transitions.iter().peekable();
+        for i in 0..bias * label_num {
+            match transitions.next_if(|&&(index, _)| index == i) {
+                Some(&(_, value)) => parameters.push(param(value)?),
+                None => parameters.push(self.parameters.get_param(i)),
+            }
+        }
+        let mut features = Feature::default();
+        for (idx, (feature, row)) in rows.into_iter().enumerate() {
+            features.insert_feature(feature.to_string(), idx + bias);
+            match row {
+                Row::Base(start) => {
+                    for i in start..start + label_num {
+                        parameters.push(self.parameters.get_param(i));
+                    }
+                }
+                Row::Patched(values) => {
+                    for &value in values {
+                        parameters.push(param(value)?);
+                    }
+                }
+            }
+        }
+
+        let mut metadata = self.metadata.clone();
+        metadata
+            .training
+            .insert("patch".to_string(), format!("{:016x}", patch.target));
+        let patched =
+            Perceptron::new_with_parameters(self.definition.clone(), features, parameters)
+                .with_metadata(metadata);
+        if patched.fingerprint() != patch.target {
+            return Err(anyhow!("the patched model does not match the target model"));
+        }
+        Ok(patched)
+    }
+}
+
+#[cfg(all(test, feature = "cws"))]
+mod tests {
+    use super::*;
+    use crate::perceptron::cws_seeded_model;
+
+    #[test]
+    fn test_patch() -> Result<()> {
+        let base = cws_seeded_model(&["2我", "2们", "2你"], 1);
+        let mut target = cws_seeded_model(&["2你", "2们", "2他"], 1);
+        let you = base.features["2你"] * 4;
+        target.parameters[16..20].copy_from_slice(&base.parameters[you..you + 4]);
+        target.parameters[3] = 9.0;
+
+        let patch = base.diff(&target)?;
+        assert_eq!(patch.transitions, [(3, 9.0)]);
+        assert_eq!(patch.removed, ["2我"]);
+        assert_eq!(patch.len(), 2);
+        assert_eq!(patch.upserted[0].0, "2他");
+
+        let mut bytes = Vec::new();
+        patch.write(&mut bytes)?;
+        let patch = ModelPatch::read(&mut bytes.as_slice())?;
+        let patched = base.apply_patch(&patch)?;
+        assert_eq!(patched.fingerprint(), target.fingerprint());
+        assert_eq!(patched.predict("他们你们")?, target.predict("他们你们")?);
+
+        // 只能应用在对应的基础模型上
+        assert!(target.apply_patch(&patch).is_err());
+        assert!(base.diff(&base)?.is_empty());
+        Ok(())
+    }
+}
@@ -0,0 +1,353 @@
+//! 可移植的线性模型格式，其它语言的运行时不依赖本库即可推理
+//!
+//! 导出为一个 JSON 对象：
+//!
+//! - `format` / `version`：固定为 `"ltp-linear"` 与 [`PORTABLE_VERSION`]
+//! - `task`：`cws`、`pos` 或 `ner`；`decoder`：`viterbi` 或 `argmax`
+//! - `labels`：标签表，`L = labels.len()`
+//! - `templates`：特征模板，特征字符串为 `prefix` 与表达式取值直接拼接，
+//!   模板引用的位置越界时不产生该特征；CWS 先去掉空白字符再按字符抽取
+//! - `features`：特征字符串到参数行号的映射，不在字典中的特征忽略
+//! - `weights`：按行展开的参数表，第 `r` 行第 `l` 列为 `weights[r * L + l]`；
+//!   使用 viterbi 时前 `L` 行为转移矩阵，`weights[prev * L + cur]`
+//!
+//! 每个位置的发射分数为其全部特征所在行之和；viterbi 的转移分数加在相邻位置之间，
+//! 得分相同时回溯起点取最后一个最大值，其余取第一个，参见 [`PortableModel::decode`]。
+use crate::perceptron::{
+    ModelTask, Perceptron, TaskDefinition, TraitFeature, TraitParameter, TraitParameterStorage,
+};
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::io::{Read, Write};
+
+pub const PORTABLE_FORMAT: &str = "ltp-linear";
+pub const PORTABLE_VERSION: u32 = 1;
+
+/// 特征模板，`expr` 中 `ch[i]` 为相对位置的字符，`w[i]` 为词，`p[i]` 为词性，
+/// `ch[i,j]` 为第 `i` 个词的第 `j` 个字符（`n` 表示最后一个）
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FeatureTemplate {
+    pub prefix: String,
+    pub expr: String,
+}
+
+// (prefix, expr, 需要启用的编译选项)
+type TemplateSpec = (&'static str, &'static str, Option<&'static str>);
+
+const CWS_TEMPLATES: &[TemplateSpec] = &[
+    ("2", "ch[0]", None),
+    ("b", "type(ch[0])", Some("char-type")),
+    ("1", "ch[-1]", None),
+    ("6", "ch[-1]ch[0]", None),
+    ("c", "type(ch[-1])", Some("char-type")),
+    ("d", "type(ch[-1])type(ch[0])", Some("near-char-type")),
+    ("0", "ch[-2]", None),
+    ("5", "ch[-2]ch[-1]", None),
+    ("9", "ch[-2]ch[0]", Some("cross-char")),
+    ("c", "ch[-2]=ch[0]", None),
+    ("3", "ch[1]", None),
+    ("7", "ch[0]ch[1]", None),
+    ("d", "type(ch[1])", Some("char-type")),
+    ("4", "ch[2]", None),
+    ("8", "ch[1]ch[2]", None),
+    ("a", "ch[0]ch[2]", Some("cross-char")),
+];
+
+const POS_TEMPLATES: &[TemplateSpec] = &[
+    ("2", "w[0]", None),
+    ("c", "ch[0,0]ch[0,n]", None),
+    ("f", "len(w[0])", None),
+    ("c", "ch[0,0]", None),
+    ("d", "ch[0,1]", None),
+    ("e", "ch[0,2]", None),
+    ("f", "ch[0,n]", None),
+    ("g", "ch[0,n-1]", None),
+    ("h", "ch[0,n-2]", None),
+    ("1", "w[-1]", None),
+    ("6", "w[-1]w[0]", None),
+    ("d", "ch[-1,n]ch[0,0]", None),
+    ("0", "w[-2]", None),
+    ("5", "w[-2]w[-1]", None),
+    ("9", "w[-2]w[0]", None),
+    ("3", "w[1]", None),
+    ("7", "w[0]w[1]", None),
+    ("e", "ch[0,n]ch[1,0]", None),
+    ("4", "w[2]", None),
+    ("8", "w[1]w[2]", None),
+    ("a", "w[0]w[2]", None),
+    ("b", "w[-1]w[0]w[1]", None),
+];
+
+const NER_TEMPLATES: &[TemplateSpec] = &[
+    ("2", "w[0]", None),
+    ("d", "p[0]", None),
+    ("1", "w[-1]", None),
+    ("6", "w[-1]w[0]", None),
+    ("c", "p[-1]", None),
+    ("g", "p[-1]p[0]", None),
+    ("0", "w[-2]", None),
+    ("5", "w[-2]w[-1]", None),
+    ("9", "w[-2]w[0]", None),
+    ("b", "p[-2]", None),
+    ("3", "w[1]", None),
+    ("7", "w[0]w[1]", None),
+    ("e", "p[1]", None),
+    ("h", "p[0]p[1]", None),
+    ("4", "w[2]", None),
+    ("8", "w[1]w[2]", None),
+    ("a", "w[0]w[2]", None),
+    ("f", "p[2]", None),
+];
+
+fn enabled(feature: &str) -> bool {
+    matches!(feature, "char-type" if cfg!(feature = "char-type"))
+        || matches!(feature, "cross-char" if cfg!(feature = "cross-char"))
+        || matches!(feature, "near-char-type" if cfg!(feature = "near-char-type"))
+}
+
+impl FeatureTemplate {
+    /// 当前编译选项下该任务实际使用的特征模板，按抽取顺序排列
+    pub fn for_task(task: ModelTask) -> Vec<FeatureTemplate> {
+        let specs = match task {
+            ModelTask::CWS => CWS_TEMPLATES,
+            ModelTask::POS => POS_TEMPLATES,
+            ModelTask::NER => NER_TEMPLATES,
+        };
+        specs
+            .iter()
+            .filter(|(_, _, feature)| feature.is_none_or(enabled))
+            .map(|&(prefix, expr, _)| FeatureTemplate {
+                prefix: prefix.to_string(),
+                expr: expr.to_string(),
+            })
+            .collect()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Decoder {
+    Viterbi,
+    Argmax,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PortableModel {
+    pub format: String,
+    pub version: u32,
+    pub task: String,
+    pub decoder: Decoder,
+    pub labels: Vec<String>,
+    pub templates: Vec<FeatureTemplate>,
+    /// 影响特征抽取的编译选项，见 [`crate::perceptron::ModelMetadata::normalization`]
+    pub normalization: Vec<String>,
+    pub features: BTreeMap<String, usize>,
+    pub weights: Vec<f64>,
+}
+
+impl PortableModel {
+    pub fn validate(&self) -> Result<()> {
+        if self.format != PORTABLE_FORMAT || self.version != PORTABLE_VERSION {
+            return Err(anyhow!(
+                "unsupported portable format {} v{}",
+                self.format,
+                self.version
+            ));
+        }
+        let label_num = self.labels.len();
+        if label_num == 0 || !self.weights.len().is_multiple_of(label_num) {
+            return Err(anyhow!(
+                "weights ({}) is not a multiple of label count ({})",
+                self.weights.len(),
+                label_num
+            ));
+        }
+        let rows = self.weights.len() / label_num;
+        if self.decoder == Decoder::Viterbi && rows < label_num {
+            return Err(anyhow!("missing transition matrix"));
+        }
+        if let Some((key, row)) = self.features.iter().find(|(_, &row)| row >= rows) {
+            return Err(anyhow!("feature {} points to row {} of {}", key, row, rows));
+        }
+        Ok(())
+    }
+
+    pub fn write<W: Write>(&self, writer: W) -> Result<()> {
+        serde_json::to_writer(writer, self)?;
+        Ok(())
+    }
+
+    pub fn read<R: Read>(reader: R) -> Result<Self> {
+        let model: Self = serde_json::from_reader(reader)?;
+        model.validate()?;
+        Ok(model)
+    }
+
+    /// 导出为 `ltp-core` 的二进制格式，只支持默认特征模板的分词模型，参数转为 f32
+    #[cfg(feature = "embedded")]
+    pub fn to_embedded(&self) -> Result<Vec<u8>> {
+        if self.task != "cws" {
+            return Err(anyhow!(
+                "ltp-core only supports cws models, found {}",
+                self.task
+            ));
+        }
+        if !self.normalization.is_empty() {
+            return Err(anyhow!(
+                "ltp-core does not support feature options {:?}",
+                self.normalization
+            ));
+        }
+        let decoder = match self.decoder {
+            Decoder::Viterbi => ltp_core::Decoder::Viterbi,
+            Decoder::Argmax => ltp_core::Decoder::Argmax,
+        };
+        let labels: Vec<&str> = self.labels.iter().map(String::as_str).collect();
+        let features: Vec<(&str, usize)> = self
+            .features
+            .iter()
+            .map(|(key, &row)| (key.as_str(), row))
+            .collect();
+        Ok(ltp_core::StaticModel::encode(
+            decoder,
+            &labels,
+            &features,
+            &self.weights,
+        ))
+    }
+
+    /// 参考实现：输入每个位置的特征字符串，返回标签下标
+    pub fn decode<S: AsRef<str>>(&self, tokens: &[Vec<S>]) -> Vec<usize> {
+        let label_num = self.labels.len();
+        let emission: Vec<Vec<f64>> = tokens
+            .iter()
+            .map(|features| {
+                let mut scores = vec![0.0; label_num];
+                for row in features
+                    .iter()
+                    .filter_map(|f| self.features.get(f.as_ref()))
+                {
+                    for (label, score) in scores.iter_mut().enumerate() {
+                        *score += self.weights[row * label_num + label];
+                    }
+                }
+                scores
+            })
+            .collect();
+
+        let argmax = |scores: &[f64]| {
+            (0..label_num).fold(0, |best, l| if scores[l] > scores[best] { l } else { best })
+        };
+        if self.decoder == Decoder::Argmax || emission.is_empty() {
+            return emission.iter().map(|scores| argmax(scores)).collect();
+        }
+
+        let mut backptr = vec![vec![0usize; label_num]; emission.len()];
+        let mut last = emission[0].clone();
+        for (i, scores) in emission.iter().enumerate().skip(1) {
+            let mut now = vec![f64::MIN; label_num];
+            for cur in 0..label_num {
+                for (prev, &score) in last.iter().enumerate() {
+                    let score = score + scores[cur] + self.weights[prev * label_num + cur];
+                    if score > now[cur] {
+                        now[cur] = score;
+                        backptr[i][cur] = prev;
+                    }
+                }
+            }
+            last = now;
+        }
+
+        let mut label =
+            (0..label_num).fold(0, |best, l| if last[l] >= last[best] { l } else { best });
+        let mut path = vec![0; emission.len()];
+        for i in (0..emission.len()).rev() {
+            path[i] = label;
+            label = backptr[i][label];
+        }
+        path
+    }
+}
+
+impl<Define, Feature, ParamStorage, Param> Perceptron<Define, Feature, ParamStorage, Param>
+where
+    Define: TaskDefinition,
+    Feature: TraitFeature,
+    for<'a> &'a Feature: IntoIterator<Item = (&'a String, &'a usize)>,
+    Param: TraitParameter,
+    ParamStorage: TraitParameterStorage<Param>,
+{
+    /// 导出为可移植格式，参数统一转为 f64
+    pub fn to_portable(&self) -> PortableModel {
+        let task = Define::TASK;
+        PortableModel {
+            format: PORTABLE_FORMAT.to_string(),
+            version: PORTABLE_VERSION,
+            task: task.to_string().to_lowercase(),
+            decoder: if self.definition.use_viterbi() {
+                Decoder::Viterbi
+            } else {
+                Decoder::Argmax
+            },
+            labels: self.definition.labels(),
+            templates: FeatureTemplate::for_task(task),
+            normalization: self.metadata.normalization.clone(),
+            features: (&self.features)
+                .into_iter()
+                .map(|(key, &row)| (key.clone(), row))
+                .collect(),
+            weights: (0..self.parameters.len())
+                .map(|i| self.parameters.get_param(i).to_f64().unwrap_or_default())
+                .collect(),
+        }
+    }
+}
+
+#[cfg(all(test, feature = "cws"))]
+mod tests {
+    use super::*;
+    use crate::perceptron::cws_test_model;
+    use crate::perceptron::Definition;
+
+    #[test]
+    fn test_portable_decode() -> Result<()> {
+        let model = cws_test_model(
+            &["2我", "2们", "7我们", "6是中", "2国", "3人"],
+            |i| ((i * 7 + 3) % 11) as f64 - 5.0,
+        );
+
+        let mut buffer = Vec::new();
+        model.to_portable().write(&mut buffer)?;
+        let portable = PortableModel::read(buffer.as_slice())?;
+        assert_eq!(portable.task, "cws");
+        assert_eq!(portable.decoder, Decoder::Viterbi);
+
+        let sentence = "我们是中国人";
+        let (_, features) = model.definition.parse_features(&sentence)?;
+        let expected = model.decode(&model.features.get_arena(&features));
+        assert_eq!(portable.decode(&features.to_nested()), expected);
+        Ok(())
+    }
+
+    #[test]
+    #[cfg(all(
+        feature = "embedded",
+        not(any(
+            feature = "char-type",
+            feature = "cross-char",
+            feature = "near-char-type"
+        ))
+    ))]
+    fn test_embedded() -> Result<()> {
+        let features = ["2我", "2们", "7我们", "6是中", "2国", "3人", "c"];
+        let model = cws_test_model(&features, |i| ((i * 5 + 1) % 9) as f64 - 4.0);
+
+        let bytes = model.to_portable().to_embedded()?;
+        let embedded = ltp_core::StaticModel::from_bytes(&bytes).map_err(|e| anyhow!("{}", e))?;
+        for sentence in ["我们是中国人", "人人 我们\t国国", "", "中"] {
+            assert_eq!(embedded.segment(sentence), model.predict(sentence)?);
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,179 @@
+//! 保存的预测结果：验证集解码一次后记下各句预测的标签下标，之后逐标签、片段级、混淆矩阵等各种评测
+//! 直接在其上计算，不必重复解码；也可以从 `ltp predict` 写出的标注文件读取
+use crate::get_entities;
+use crate::perceptron::definition::resolve_uncertain;
+use crate::perceptron::{ConfusionMatrix, Definition, EvalReport, Sample, SpanReport, UNCERTAIN};
+use anyhow::{anyhow, Result};
+#[cfg(feature = "serialization")]
+use serde::{Deserialize, Serialize};
+use std::io::Read;
+use std::iter::zip;
+#[cfg(feature = "fs")]
+use std::path::Path;
+
+#[cfg_attr(feature = "serialization", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serialization", serde(transparent))]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Predictions {
+    /// 每句逐字（分词）或逐词预测的标签下标，与验证集的样本一一对应
+    labels: Vec<Vec<usize>>,
+}
+
+impl Predictions {
+    pub fn new(labels: Vec<Vec<usize>>) -> Self {
+        Predictions { labels }
+    }
+
+    /// 读取与训练语料格式相同的标注结果，如 `ltp predict` 对验证集原文的输出
+    pub fn from_reader<D: Definition, R: Read>(define: &D, reader: R) -> Result<Self> {
+        let samples = define.parse_gold_features(reader)?;
+        Ok(Predictions::new(
+            samples.into_iter().map(|sample| sample.labels).collect(),
+        ))
+    }
+
+    #[cfg(feature = "fs")]
+    pub fn from_file<D: Definition, P: AsRef<Path>>(define: &D, path: P) -> Result<Self> {
+        Self::from_reader(define, crate::perceptron::CompressedReader::open(path)?)
+    }
+
+    pub fn len(&self) -> usize {
+        self.labels.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.labels.is_empty()
+    }
+
+    pub fn get(&self, index: usize) -> Option<&[usize]> {
+        self.labels.get(index).map(Vec::as_slice)
+    }
+
+    /// 句子数与各句的长度须与样本相同，预测中不能有不确定的标签
+    pub fn check(&self, samples: &[Sample]) -> Result<()> {
+        if self.labels.len() != samples.len() {
+            return Err(anyhow!(
+                "{} predicted sentences for {} samples",
+                self.labels.len(),
+                samples.len()
+            ));
+        }
+        for (index, (preds, sample)) in zip(&self.labels, samples).enumerate() {
+            if preds.len() != sample.labels.len() {
+                return Err(anyhow!(
+                    "sentence {}: {} predicted labels for {} gold labels",
+                    index,
+                    preds.len(),
+                    sample.labels.len()
+                ));
+            }
+            if preds.contains(&UNCERTAIN) {
+                return Err(anyhow!("sentence {}: uncertain predicted label", index));
+            }
+        }
+        Ok(())
+    }
+
+    fn pairs<'a>(
+        &'a self,
+        samples: &'a [Sample],
+    ) -> Result<impl Iterator<Item = (&'a [usize], &'a [usize])>> {
+        self.check(samples)?;
+        let pairs = zip(&self.labels, samples);
+        Ok(pairs.map(|(preds, sample)| (preds.as_slice(), sample.labels.as_slice())))
+    }
+
+    /// 与 [`Trainer::evaluate_report`](crate::perceptron::Trainer::evaluate_report) 相同，
+    /// 不确定的词视为预测正确
+    pub fn evaluate_report<D: Definition>(
+        &self,
+        define: &D,
+        samples: &[Sample],
+    ) -> Result<EvalReport> {
+        let mut report = EvalReport::default();
+        for (preds, labels) in self.pairs(samples)? {
+            let labels = resolve_uncertain(labels, preds);
+            define.evaluate_report(preds, &labels, &mut report);
+        }
+        Ok(report)
+    }
+
+    /// 按实体做片段级评测，不确定的词视为预测正确
+    pub fn span_report<D: Definition>(&self, define: &D, samples: &[Sample]) -> Result<SpanReport> {
+        let mut report = SpanReport::default();
+        for (preds, labels) in self.pairs(samples)? {
+            let labels = resolve_uncertain(labels, preds);
+            let predicted = define.to_labels(preds);
+            let gold = define.to_labels(&labels);
+            report.add(&get_entities(&gold), &get_entities(&predicted));
+        }
+        Ok(report)
+    }
+
+    /// 逐词的混淆矩阵，不确定的词不计入
+    pub fn confusion_matrix<D: Definition>(
+        &self,
+        define: &D,
+        samples: &[Sample],
+    ) -> Result<ConfusionMatrix> {
+        let mut matrix = ConfusionMatrix::new(define.labels());
+        for (preds, labels) in self.pairs(samples)? {
+            for (&gold, &pred) in zip(labels, preds) {
+                if gold != UNCERTAIN {
+                    matrix.add(gold, pred);
+                }
+            }
+        }
+        Ok(matrix)
+    }
+}
+
+#[cfg(all(test, feature = "pos"))]
+mod tests {
+    use super::*;
+    use crate::perceptron::Trainer;
+    use crate::POSDefinition;
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_predictions() -> Result<()> {
+        let define = POSDefinition::new(vec!["n".into(), "v".into(), "r".into()]);
+        let corpus = "他/r 喜欢/v 花/n\n她/r 花/_ 钱/n\n花/n 开/v\n";
+        let mut trainer: Trainer<POSDefinition> = Trainer::new_with_define(define.clone())
+            .epoch(3)
+            .verbose(false)
+            .compress(false);
+        trainer.train_set = Some(define.parse_gold_features(corpus.as_bytes())?);
+        trainer.eval_set = trainer.train_set.clone();
+        let model = trainer.build::<HashMap<String, usize>, Vec<f64>>()?;
+        let samples = trainer.eval_set.as_deref().unwrap();
+
+        // 与直接用模型评测的结果相同
+        let predictions = trainer.predictions(&model)?;
+        assert_eq!(predictions.len(), 3);
+        assert_eq!(
+            predictions.evaluate_report(&define, samples)?,
+            trainer.evaluate_report(&model)?
+        );
+        assert_eq!(
+            predictions.confusion_matrix(&define, samples)?,
+            trainer.confusion_matrix(&model)?
+        );
+        assert_eq!(
+            trainer.evaluate_predictions(&predictions)?,
+            trainer.evaluate_report(&model)?
+        );
+
+        // 从标注文件读取：第一句的“喜欢”预测错误，第二句不确定的“花”视为正确
+        let predicted = "他/r 喜欢/n 花/n\n她/r 花/v 钱/n\n花/n 开/v\n";
+        let predictions = Predictions::from_reader(&define, predicted.as_bytes())?;
+        assert_eq!(predictions.get(0), Some(&[2, 0, 0][..]));
+        let report = predictions.evaluate_report(&define, samples)?;
+        assert_eq!(report.accuracy(), 7.0 / 8.0);
+        assert_eq!(predictions.confusion_matrix(&define, samples)?.get(1, 0), 1);
+
+        let short = Predictions::from_reader(&define, "他/r 喜欢/v\n".as_bytes())?;
+        assert!(short.evaluate_report(&define, samples).is_err());
+        Ok(())
+    }
+}
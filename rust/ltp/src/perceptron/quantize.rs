@@ -0,0 +1,185 @@
+//! 参数量化，打分时再反量化
+//!
+//! `F16` 直接降低精度；`I8` 每 `block` 个参数共享一个缩放系数，
+//! 以标签数作为块大小时即为每个特征一个缩放系数。
+use crate::perceptron::{
+    Definition, Perceptron, TraitFeature, TraitParameter, TraitParameterStorage,
+    TraitParameterStorageUtils,
+};
+use half::f16;
+use num_traits::NumCast;
+#[cfg(feature = "serialization")]
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+#[cfg_attr(feature = "serialization", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Quantization {
+    #[default]
+    F16,
+    I8,
+}
+
+#[cfg_attr(feature = "serialization", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, PartialEq)]
+pub enum QuantizedParameters {
+    F16(Vec<f16>),
+    I8 {
+        block: usize,
+        scales: Vec<f32>,
+        values: Vec<i8>,
+    },
+}
+
+impl Default for QuantizedParameters {
+    fn default() -> Self {
+        QuantizedParameters::F16(Vec::new())
+    }
+}
+
+impl QuantizedParameters {
+    pub fn quantize<Param, ParamStorage>(
+        parameters: &ParamStorage,
+        mode: Quantization,
+        block: usize,
+    ) -> Self
+    where
+        Param: TraitParameter,
+        ParamStorage: TraitParameterStorage<Param>,
+    {
+        assert!(block > 0, "block size must be positive");
+        let len = parameters.len();
+        let value = |i: usize| -> f32 { parameters.get_param(i).to_f32().unwrap_or_default() };
+
+        match mode {
+            Quantization::F16 => {
+                QuantizedParameters::F16((0..len).map(|i| f16::from_f32(value(i))).collect())
+            }
+            Quantization::I8 => {
+                let mut scales = Vec::with_capacity(len.div_ceil(block));
+                let mut values = Vec::with_capacity(len);
+                for start in (0..len).step_by(block) {
+                    let end = (start + block).min(len);
+                    let max = (start..end).map(|i| value(i).abs()).fold(0f32, f32::max);
+                    let scale = if max > 0.0 { max / i8::MAX as f32 } else { 1.0 };
+                    scales.push(scale);
+                    values.extend((start..end).map(|i| (value(i) / scale).round() as i8));
+                }
+                QuantizedParameters::I8 {
+                    block,
+                    scales,
+                    values,
+                }
+            }
+        }
+    }
+
+    pub fn mode(&self) -> Quantization {
+        match self {
+            QuantizedParameters::F16(_) => Quantization::F16,
+            QuantizedParameters::I8 { .. } => Quantization::I8,
+        }
+    }
+
+    #[inline]
+    pub fn dequantize(&self, index: usize) -> f32 {
+        match self {
+            QuantizedParameters::F16(values) => values[index].to_f32(),
+            QuantizedParameters::I8 {
+                block,
+                scales,
+                values,
+            } => values[index] as f32 * scales[index / block],
+        }
+    }
+
+    pub fn to_vec<Param: TraitParameter>(&self) -> Vec<Param> {
+        (0..self.len()).map(|i| self.get_param(i)).collect()
+    }
+}
+
+impl TraitParameterStorageUtils for QuantizedParameters {
+    fn len(&self) -> usize {
+        match self {
+            QuantizedParameters::F16(values) => values.len(),
+            QuantizedParameters::I8 { values, .. } => values.len(),
+        }
+    }
+}
+
+impl<Param: TraitParameter> TraitParameterStorage<Param> for QuantizedParameters {
+    #[inline]
+    fn get_param(&self, index: usize) -> Param {
+        <Param as NumCast>::from(self.dequantize(index)).unwrap()
+    }
+}
+
+pub type QuantizedModel<Define> =
+    Perceptron<Define, HashMap<String, usize>, QuantizedParameters, f64>;
+
+impl<Define, Feature, ParamStorage, Param> Perceptron<Define, Feature, ParamStorage, Param>
+where
+    Feature: TraitFeature,
+    Param: TraitParameter,
+    ParamStorage: TraitParameterStorage<Param>,
+    Define: Definition,
+{
+    /// 按特征（每个特征占标签数个参数）分块量化
+    pub fn quantize(
+        self,
+        mode: Quantization,
+    ) -> Perceptron<Define, Feature, QuantizedParameters, Param> {
+        let block = self.definition.label_num();
+        let parameters = QuantizedParameters::quantize(&self.parameters, mode, block);
+        Perceptron::new_with_parameters(self.definition, self.features, parameters)
+            .with_metadata(self.metadata)
+    }
+}
+
+impl<Define, Feature, Param> Perceptron<Define, Feature, QuantizedParameters, Param>
+where
+    Feature: TraitFeature,
+    Param: TraitParameter,
+    Define: Definition,
+{
+    pub fn dequantize(self) -> Perceptron<Define, Feature, Vec<Param>, Param> {
+        let parameters = self.parameters.to_vec();
+        Perceptron::new_with_parameters(self.definition, self.features, parameters)
+            .with_metadata(self.metadata)
+    }
+}
+
+#[cfg(all(test, feature = "cws"))]
+mod tests {
+    use super::*;
+    use crate::perceptron::cws_test_model;
+    use crate::CWSDefinition;
+
+    fn model() -> Perceptron<CWSDefinition, HashMap<String, usize>, Vec<f64>, f64> {
+        cws_test_model(&["2我", "2们", "6我们"], |i| {
+            ((i * 37) % 11) as f64 * 0.31 - 1.5
+        })
+    }
+
+    #[test]
+    fn test_quantize() {
+        for mode in [Quantization::F16, Quantization::I8] {
+            let origin = model();
+            let quantized = model().quantize(mode);
+            assert_eq!(quantized.parameters.mode(), mode);
+
+            for i in 0..origin.parameters.len() {
+                let value: f64 = quantized.parameters.get_param(i);
+                let error = (origin.parameters[i] - value).abs();
+                assert!(error < 0.02, "{:?} {} {}", mode, i, error);
+            }
+            assert_eq!(
+                origin.predict("我们我们").unwrap(),
+                quantized.predict("我们我们").unwrap()
+            );
+
+            let restored = quantized.dequantize();
+            assert_eq!(restored.parameters.len(), origin.parameters.len());
+        }
+    }
+}
@@ -0,0 +1,833 @@
+//! 评测报告：整体的精确率、召回率、F1 与准确率，以及每个标签的指标
+use crate::perceptron::UNCERTAIN_TAG;
+#[cfg(feature = "serialization")]
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashSet};
+use std::fmt::{Display, Formatter};
+use std::io::Write;
+use std::iter::zip;
+
+fn prf(correct: usize, predicted: usize, gold: usize) -> (f64, f64, f64) {
+    let ratio = |a: usize, b: usize| if b == 0 { 0.0 } else { a as f64 / b as f64 };
+    let precision = ratio(correct, predicted);
+    let recall = ratio(correct, gold);
+    let f1 = if precision + recall == 0.0 {
+        0.0
+    } else {
+        2.0 * precision * recall / (precision + recall)
+    };
+    (precision, recall, f1)
+}
+
+/// 一个标签的计数，`gold` 即 support
+#[cfg_attr(feature = "serialization", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct LabelScore {
+    pub correct: usize,
+    pub predicted: usize,
+    pub gold: usize,
+}
+
+impl LabelScore {
+    pub fn precision(&self) -> f64 {
+        prf(self.correct, self.predicted, self.gold).0
+    }
+
+    pub fn recall(&self) -> f64 {
+        prf(self.correct, self.predicted, self.gold).1
+    }
+
+    pub fn f1(&self) -> f64 {
+        prf(self.correct, self.predicted, self.gold).2
+    }
+
+    fn merge(&mut self, other: &LabelScore) {
+        self.correct += other.correct;
+        self.predicted += other.predicted;
+        self.gold += other.gold;
+    }
+}
+
+/// 分词按词、词性标注按词、实体识别按实体统计整体指标；
+/// 每个标签的指标对实体识别按实体类型统计，其余按标签逐词统计
+#[cfg_attr(feature = "serialization", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct EvalReport {
+    pub overall: LabelScore,
+    pub labels: BTreeMap<String, LabelScore>,
+    pub tokens: usize,
+    /// 标签预测正确的词数
+    pub tokens_correct: usize,
+}
+
+impl EvalReport {
+    pub fn precision(&self) -> f64 {
+        self.overall.precision()
+    }
+
+    pub fn recall(&self) -> f64 {
+        self.overall.recall()
+    }
+
+    pub fn f1(&self) -> f64 {
+        self.overall.f1()
+    }
+
+    pub fn accuracy(&self) -> f64 {
+        prf(self.tokens_correct, self.tokens, self.tokens).0
+    }
+
+    /// 各标签计数求和后计算
+    pub fn micro(&self) -> (f64, f64, f64) {
+        let mut total = LabelScore::default();
+        self.labels.values().for_each(|score| total.merge(score));
+        prf(total.correct, total.predicted, total.gold)
+    }
+
+    /// 各标签指标的算术平均，只计入在标注中出现过的标签
+    pub fn macro_avg(&self) -> (f64, f64, f64) {
+        let scores: Vec<_> = self.labels.values().filter(|s| s.gold > 0).collect();
+        if scores.is_empty() {
+            return (0.0, 0.0, 0.0);
+        }
+        let n = scores.len() as f64;
+        let sum = |f: fn(&LabelScore) -> f64| scores.iter().map(|s| f(s)).sum::<f64>() / n;
+        (
+            sum(LabelScore::precision),
+            sum(LabelScore::recall),
+            sum(LabelScore::f1),
+        )
+    }
+
+    pub fn label(&mut self, label: &str) -> &mut LabelScore {
+        if !self.labels.contains_key(label) {
+            self.labels.insert(label.to_string(), LabelScore::default());
+        }
+        self.labels.get_mut(label).unwrap()
+    }
+
+    pub fn merge(&mut self, other: &EvalReport) {
+        self.overall.merge(&other.overall);
+        for (label, score) in &other.labels {
+            self.label(label).merge(score);
+        }
+        self.tokens += other.tokens;
+        self.tokens_correct += other.tokens_correct;
+    }
+}
+
+impl Display for EvalReport {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let width = self
+            .labels
+            .keys()
+            .map(|label| label.chars().count())
+            .chain([12])
+            .max()
+            .unwrap_or(12);
+        writeln!(
+            f,
+            "{:<width$} {:>9} {:>9} {:>9} {:>9}",
+            "label", "precision", "recall", "f1", "support"
+        )?;
+        let row = |f: &mut Formatter<'_>, name: &str, scores: (f64, f64, f64), n: usize| {
+            let (p, r, f1) = scores;
+            writeln!(f, "{name:<width$} {p:>9.4} {r:>9.4} {f1:>9.4} {n:>9}")
+        };
+        for (label, score) in &self.labels {
+            let scores = (score.precision(), score.recall(), score.f1());
+            row(f, label, scores, score.gold)?;
+        }
+        writeln!(f)?;
+        let support = self.labels.values().map(|s| s.gold).sum();
+        row(f, "micro avg", self.micro(), support)?;
+        row(f, "macro avg", self.macro_avg(), support)?;
+        let overall = (self.precision(), self.recall(), self.f1());
+        row(f, "overall", overall, self.overall.gold)?;
+        write!(f, "{:<width$} {:>39.4}", "accuracy", self.accuracy())
+    }
+}
+
+/// 逐词的混淆矩阵，行为标注的标签，列为预测的标签
+#[cfg_attr(feature = "serialization", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ConfusionMatrix {
+    pub labels: Vec<String>,
+    pub matrix: Vec<Vec<usize>>,
+}
+
+impl ConfusionMatrix {
+    pub fn new(labels: Vec<String>) -> Self {
+        let matrix = vec![vec![0; labels.len()]; labels.len()];
+        ConfusionMatrix { labels, matrix }
+    }
+
+    pub fn add(&mut self, gold: usize, predicted: usize) {
+        self.matrix[gold][predicted] += 1;
+    }
+
+    pub fn get(&self, gold: usize, predicted: usize) -> usize {
+        self.matrix[gold][predicted]
+    }
+
+    /// 两个矩阵的标签表须相同
+    pub fn merge(&mut self, other: &ConfusionMatrix) {
+        for (row, other) in self.matrix.iter_mut().zip(&other.matrix) {
+            for (count, other) in row.iter_mut().zip(other) {
+                *count += other;
+            }
+        }
+    }
+
+    /// 被混淆的标签对 `(标注, 预测, 次数)`，按次数从多到少
+    pub fn confusions(&self) -> Vec<(&str, &str, usize)> {
+        let mut confusions = Vec::new();
+        for (gold, row) in self.matrix.iter().enumerate() {
+            for (predicted, &count) in row.iter().enumerate() {
+                if gold != predicted && count > 0 {
+                    let pair = (self.labels[gold].as_str(), self.labels[predicted].as_str());
+                    confusions.push((pair.0, pair.1, count));
+                }
+            }
+        }
+        confusions.sort_by(|a, b| b.2.cmp(&a.2).then_with(|| (a.0, a.1).cmp(&(b.0, b.1))));
+        confusions
+    }
+
+    /// 第一行为预测的标签，第一列为标注的标签
+    pub fn write_csv<W: Write>(&self, mut writer: W) -> std::io::Result<()> {
+        let escape = |label: &str| match label.contains([',', '"', '\n']) {
+            true => format!("\"{}\"", label.replace('"', "\"\"")),
+            false => label.to_string(),
+        };
+        let header: Vec<String> = self.labels.iter().map(|l| escape(l)).collect();
+        writeln!(writer, "gold\\predicted,{}", header.join(","))?;
+        for (label, row) in header.iter().zip(&self.matrix) {
+            let row: Vec<String> = row.iter().map(usize::to_string).collect();
+            writeln!(writer, "{},{}", label, row.join(","))?;
+        }
+        Ok(())
+    }
+}
+
+/// 一个含错误的句子，标注与预测逐词对齐；分词按字对齐为 B/M/E/S 标签
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SentenceErrors {
+    /// 在语料中的下标
+    pub index: usize,
+    pub tokens: Vec<String>,
+    pub gold: Vec<String>,
+    pub predicted: Vec<String>,
+    /// 标注与预测不同的词数，不确定的词不计入
+    pub errors: usize,
+}
+
+/// 把分词结果转换为逐字的 B/M/E/S 标签
+pub fn char_tags(words: &[&str]) -> Vec<String> {
+    let mut tags = Vec::new();
+    for word in words {
+        let n = word.chars().count();
+        tags.extend((0..n).map(|i| match (i, n) {
+            (_, 1) => "S",
+            (0, _) => "B",
+            (i, n) if i == n - 1 => "E",
+            _ => "M",
+        }));
+    }
+    tags.into_iter().map(String::from).collect()
+}
+
+impl SentenceErrors {
+    /// 没有错误时返回 `None`
+    pub fn new<S: AsRef<str>>(
+        index: usize,
+        tokens: &[S],
+        gold: &[S],
+        predicted: &[S],
+    ) -> Option<Self> {
+        let to_vec = |v: &[S]| v.iter().map(|s| s.as_ref().to_string()).collect();
+        let errors = zip(gold, predicted)
+            .filter(|(g, p)| g.as_ref() != p.as_ref() && g.as_ref() != UNCERTAIN_TAG)
+            .count();
+        (errors > 0).then(|| SentenceErrors {
+            index,
+            tokens: to_vec(tokens),
+            gold: to_vec(gold),
+            predicted: to_vec(predicted),
+            errors,
+        })
+    }
+
+    /// 分词的标注与预测按字对齐
+    pub fn from_words(index: usize, gold: &[&str], predicted: &[&str]) -> Option<Self> {
+        let chars: Vec<String> = gold
+            .iter()
+            .flat_map(|w| w.chars())
+            .map(String::from)
+            .collect();
+        Self::new(index, &chars, &char_tags(gold), &char_tags(predicted))
+    }
+
+    fn is_error(&self, i: usize) -> bool {
+        self.gold[i] != self.predicted[i] && self.gold[i] != UNCERTAIN_TAG
+    }
+}
+
+/// 第一行为句子，错误的连续片段用 `[[ ]]` 标出，之后每行为 `词 标注 预测`，错误的行以 `*` 结尾
+impl Display for SentenceErrors {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "# sentence {}: {} errors", self.index, self.errors)?;
+        let mut text = String::new();
+        for (i, token) in self.tokens.iter().enumerate() {
+            let error = self.is_error(i);
+            if error && (i == 0 || !self.is_error(i - 1)) {
+                text.push_str("[[");
+            }
+            text.push_str(token);
+            if error && (i + 1 == self.tokens.len() || !self.is_error(i + 1)) {
+                text.push_str("]]");
+            }
+        }
+        writeln!(f, "{}", text)?;
+        for (i, token) in self.tokens.iter().enumerate() {
+            let mark = if self.is_error(i) { "\t*" } else { "" };
+            writeln!(
+                f,
+                "{}\t{}\t{}{}",
+                token, self.gold[i], self.predicted[i], mark
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// 按错误数从多到少写出，错误数相同时按句子在语料中的顺序
+pub fn write_errors<W: Write>(mut writer: W, errors: &mut [SentenceErrors]) -> std::io::Result<()> {
+    errors.sort_by(|a, b| b.errors.cmp(&a.errors).then(a.index.cmp(&b.index)));
+    for sentence in errors.iter() {
+        writeln!(writer, "{}", sentence)?;
+    }
+    Ok(())
+}
+
+/// 按词是否在训练词表中分开统计：词性标注与实体识别按词统计标签准确率，
+/// 分词按词统计精确率与召回率（预测的词按预测的词是否登录计入 `predicted`）
+#[cfg_attr(feature = "serialization", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct OovReport {
+    pub iv: LabelScore,
+    pub oov: LabelScore,
+}
+
+impl OovReport {
+    fn score(&mut self, known: bool) -> &mut LabelScore {
+        match known {
+            true => &mut self.iv,
+            false => &mut self.oov,
+        }
+    }
+
+    /// 逐词的标签，不确定的词不计入
+    pub fn add_tokens<S: AsRef<str>>(
+        &mut self,
+        words: &[S],
+        gold: &[S],
+        predicted: &[S],
+        known: &dyn Fn(&str) -> bool,
+    ) {
+        for ((word, gold), predicted) in zip(zip(words, gold), predicted) {
+            let gold = gold.as_ref();
+            if gold == UNCERTAIN_TAG {
+                continue;
+            }
+            let score = self.score(known(word.as_ref()));
+            score.gold += 1;
+            score.predicted += 1;
+            if gold == predicted.as_ref() {
+                score.correct += 1;
+            }
+        }
+    }
+
+    /// 分词结果，起止位置都相同的词算作正确
+    pub fn add_words<S: AsRef<str>>(
+        &mut self,
+        gold: &[S],
+        predicted: &[S],
+        known: &dyn Fn(&str) -> bool,
+    ) {
+        let spans = |words: &[S]| {
+            let mut start = 0;
+            let mut spans = HashSet::new();
+            for word in words {
+                let end = start + word.as_ref().len();
+                spans.insert((start, end));
+                start = end;
+            }
+            spans
+        };
+        let predicted_spans = spans(predicted);
+        let mut start = 0;
+        for word in gold {
+            let word = word.as_ref();
+            let score = self.score(known(word));
+            score.gold += 1;
+            if predicted_spans.contains(&(start, start + word.len())) {
+                score.correct += 1;
+            }
+            start += word.len();
+        }
+        for word in predicted {
+            self.score(known(word.as_ref())).predicted += 1;
+        }
+    }
+
+    pub fn merge(&mut self, other: &OovReport) {
+        self.iv.merge(&other.iv);
+        self.oov.merge(&other.oov);
+    }
+
+    pub fn oov_rate(&self) -> f64 {
+        match self.iv.gold + self.oov.gold {
+            0 => 0.0,
+            total => self.oov.gold as f64 / total as f64,
+        }
+    }
+}
+
+impl Display for OovReport {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        writeln!(
+            f,
+            "{:<5} {:>9} {:>9} {:>9} {:>9}",
+            "words", "precision", "recall", "f1", "count"
+        )?;
+        for (name, s) in [("iv", &self.iv), ("oov", &self.oov)] {
+            let (p, r, f1) = (s.precision(), s.recall(), s.f1());
+            writeln!(f, "{name:<5} {p:>9.4} {r:>9.4} {f1:>9.4} {:>9}", s.gold)?;
+        }
+        write!(f, "oov rate: {:.2}%", self.oov_rate() * 100.0)
+    }
+}
+
+/// 一个置信度区间内的词
+#[cfg_attr(feature = "serialization", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct CalibrationBin {
+    pub count: usize,
+    pub correct: usize,
+    pub confidence: f64,
+}
+
+impl CalibrationBin {
+    pub fn accuracy(&self) -> f64 {
+        match self.count {
+            0 => 0.0,
+            n => self.correct as f64 / n as f64,
+        }
+    }
+
+    pub fn mean_confidence(&self) -> f64 {
+        match self.count {
+            0 => 0.0,
+            n => self.confidence / n as f64,
+        }
+    }
+}
+
+/// 按预测置信度等宽分箱的准确率，用于检验置信度能否作为人工复核的阈值
+#[cfg_attr(feature = "serialization", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, PartialEq)]
+pub struct CalibrationReport {
+    pub bins: Vec<CalibrationBin>,
+}
+
+impl Default for CalibrationReport {
+    fn default() -> Self {
+        Self::new(10)
+    }
+}
+
+impl CalibrationReport {
+    pub fn new(bins: usize) -> Self {
+        CalibrationReport {
+            bins: vec![CalibrationBin::default(); bins.max(1)],
+        }
+    }
+
+    pub fn add(&mut self, confidence: f64, correct: bool) {
+        let n = self.bins.len();
+        let index = ((confidence * n as f64) as usize).min(n - 1);
+        let bin = &mut self.bins[index];
+        bin.count += 1;
+        bin.correct += correct as usize;
+        bin.confidence += confidence;
+    }
+
+    pub fn merge(&mut self, other: &CalibrationReport) {
+        for (bin, other) in self.bins.iter_mut().zip(&other.bins) {
+            bin.count += other.count;
+            bin.correct += other.correct;
+            bin.confidence += other.confidence;
+        }
+    }
+
+    pub fn count(&self) -> usize {
+        self.bins.iter().map(|bin| bin.count).sum()
+    }
+
+    /// 期望校准误差：各箱平均置信度与准确率之差按词数加权平均
+    pub fn ece(&self) -> f64 {
+        let total = self.count().max(1) as f64;
+        self.bins
+            .iter()
+            .map(|bin| bin.count as f64 / total * (bin.mean_confidence() - bin.accuracy()).abs())
+            .sum()
+    }
+
+    /// 最大校准误差，不计空箱
+    pub fn mce(&self) -> f64 {
+        self.bins
+            .iter()
+            .filter(|bin| bin.count > 0)
+            .map(|bin| (bin.mean_confidence() - bin.accuracy()).abs())
+            .fold(0.0, f64::max)
+    }
+}
+
+impl Display for CalibrationReport {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        writeln!(
+            f,
+            "{:<11} {:>9} {:>10} {:>9} {:>9}",
+            "confidence", "count", "mean conf", "accuracy", "gap"
+        )?;
+        let width = 1.0 / self.bins.len() as f64;
+        for (i, bin) in self.bins.iter().enumerate() {
+            let range = format!("{:.2}-{:.2}", i as f64 * width, (i + 1) as f64 * width);
+            let (confidence, accuracy) = (bin.mean_confidence(), bin.accuracy());
+            writeln!(
+                f,
+                "{:<11} {:>9} {:>10.4} {:>9.4} {:>9.4}",
+                range,
+                bin.count,
+                confidence,
+                accuracy,
+                confidence - accuracy
+            )?;
+        }
+        write!(f, "ece: {:.4}, mce: {:.4}", self.ece(), self.mce())
+    }
+}
+
+/// 一个长度区间内的句子：指标与逐句的解码耗时（微秒）
+#[cfg_attr(feature = "serialization", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LengthBucket {
+    pub score: LabelScore,
+    /// 长度为句子的词数，分词为字数
+    pub tokens: usize,
+    pub latencies: Vec<u64>,
+}
+
+impl LengthBucket {
+    pub fn sentences(&self) -> usize {
+        self.latencies.len()
+    }
+
+    pub fn mean_latency(&self) -> f64 {
+        match self.latencies.len() {
+            0 => 0.0,
+            n => self.latencies.iter().sum::<u64>() as f64 / n as f64,
+        }
+    }
+
+    /// 最近秩法的分位数，`q` 取 0 到 1
+    pub fn percentile(&self, q: f64) -> u64 {
+        let mut latencies = self.latencies.clone();
+        latencies.sort_unstable();
+        match latencies.len() {
+            0 => 0,
+            n => latencies[((q * n as f64).ceil() as usize).clamp(1, n) - 1],
+        }
+    }
+
+    /// 平均每个词的解码耗时，用于观察解码代价随句长的增长
+    pub fn latency_per_token(&self) -> f64 {
+        match self.tokens {
+            0 => 0.0,
+            n => self.latencies.iter().sum::<u64>() as f64 / n as f64,
+        }
+    }
+}
+
+/// 按句长分桶的指标与耗时，桶以上界（含）为键，超出最大上界的句子归入 `usize::MAX`
+#[cfg_attr(feature = "serialization", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LengthReport {
+    pub bounds: Vec<usize>,
+    pub buckets: BTreeMap<usize, LengthBucket>,
+}
+
+impl Default for LengthReport {
+    fn default() -> Self {
+        Self::new(vec![10, 20, 40, 80, 160])
+    }
+}
+
+impl LengthReport {
+    pub fn new(mut bounds: Vec<usize>) -> Self {
+        bounds.sort_unstable();
+        bounds.dedup();
+        LengthReport {
+            bounds,
+            buckets: BTreeMap::new(),
+        }
+    }
+
+    pub fn bucket(&self, length: usize) -> usize {
+        let index = self.bounds.partition_point(|&bound| bound < length);
+        self.bounds.get(index).copied().unwrap_or(usize::MAX)
+    }
+
+    pub fn add(&mut self, length: usize, score: LabelScore, latency: u64) {
+        let bucket = self.buckets.entry(self.bucket(length)).or_default();
+        bucket.score.merge(&score);
+        bucket.tokens += length;
+        bucket.latencies.push(latency);
+    }
+
+    pub fn merge(&mut self, other: &LengthReport) {
+        for (bound, other) in &other.buckets {
+            let bucket = self.buckets.entry(*bound).or_default();
+            bucket.score.merge(&other.score);
+            bucket.tokens += other.tokens;
+            bucket.latencies.extend(&other.latencies);
+        }
+    }
+}
+
+impl Display for LengthReport {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        writeln!(
+            f,
+            "{:<9} {:>9} {:>9} {:>9} {:>9} {:>9} {:>9} {:>9}",
+            "length", "sentences", "f1", "mean(us)", "p50(us)", "p95(us)", "max(us)", "us/token"
+        )?;
+        for (bound, b) in &self.buckets {
+            let lower = self
+                .bounds
+                .iter()
+                .rev()
+                .find(|&x| x < bound)
+                .map_or(1, |x| x + 1);
+            let range = match *bound {
+                usize::MAX => format!("{lower}+"),
+                bound => format!("{lower}-{bound}"),
+            };
+            writeln!(
+                f,
+                "{:<9} {:>9} {:>9.4} {:>9.1} {:>9} {:>9} {:>9} {:>9.2}",
+                range,
+                b.sentences(),
+                b.score.f1(),
+                b.mean_latency(),
+                b.percentile(0.5),
+                b.percentile(0.95),
+                b.percentile(1.0),
+                b.latency_per_token()
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// 学习曲线上的一点：用训练集的一部分训练，在验证集上评测
+#[cfg_attr(feature = "serialization", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct CurvePoint {
+    pub fraction: f64,
+    /// 实际使用的训练句子数
+    pub sentences: usize,
+    pub features: usize,
+    pub precision: f64,
+    pub recall: f64,
+    pub f1: f64,
+}
+
+impl Display for CurvePoint {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let bar = "#".repeat((self.f1 * 50.0).round() as usize);
+        write!(
+            f,
+            "{:>5.1}% {:>8} {:>9.4} {}",
+            self.fraction * 100.0,
+            self.sentences,
+            self.f1,
+            bar
+        )
+    }
+}
+
+/// 以 CSV 写出学习曲线，可直接用于作图
+pub fn write_curve<W: Write>(mut writer: W, points: &[CurvePoint]) -> std::io::Result<()> {
+    writeln!(writer, "fraction,sentences,features,precision,recall,f1")?;
+    for p in points {
+        writeln!(
+            writer,
+            "{},{},{},{},{},{}",
+            p.fraction, p.sentences, p.features, p.precision, p.recall, p.f1
+        )?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_report() {
+        let mut report = EvalReport::default();
+        *report.label("n") = LabelScore {
+            correct: 3,
+            predicted: 4,
+            gold: 3,
+        };
+        *report.label("v") = LabelScore {
+            correct: 1,
+            predicted: 1,
+            gold: 2,
+        };
+        let mut other = report.clone();
+        other.merge(&report);
+        assert_eq!(other.labels["n"].gold, 6);
+
+        let (p, r, _) = report.micro();
+        assert_eq!((p, r), (0.8, 0.8));
+        let (p, r, _) = report.macro_avg();
+        assert_eq!((p, r), (0.875, 0.75));
+        assert!(report.to_string().contains("macro avg"));
+    }
+
+    #[test]
+    fn test_confusion_matrix() -> std::io::Result<()> {
+        let mut matrix = ConfusionMatrix::new(vec!["n".into(), "v".into(), "a,b".into()]);
+        for (gold, predicted) in [(0, 0), (0, 1), (0, 1), (1, 0), (2, 2)] {
+            matrix.add(gold, predicted);
+        }
+        matrix.merge(&matrix.clone());
+        assert_eq!(matrix.get(0, 1), 4);
+        assert_eq!(matrix.confusions(), vec![("n", "v", 4), ("v", "n", 2)]);
+
+        let mut csv = Vec::new();
+        matrix.write_csv(&mut csv)?;
+        let csv = String::from_utf8(csv).unwrap();
+        assert_eq!(csv.lines().next(), Some("gold\\predicted,n,v,\"a,b\""));
+        assert_eq!(csv.lines().nth(1), Some("n,2,4,0"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_sentence_errors() {
+        assert!(SentenceErrors::from_words(0, &["他", "叫"], &["他", "叫"]).is_none());
+        let errors = SentenceErrors::from_words(3, &["他", "叫", "汤姆"], &["他", "叫汤", "姆"]);
+        let errors = errors.unwrap();
+        assert_eq!(errors.gold, ["S", "S", "B", "E"]);
+        assert_eq!(errors.predicted, ["S", "B", "E", "S"]);
+        assert_eq!(errors.errors, 3);
+        let text = errors.to_string();
+        assert_eq!(text.lines().nth(1), Some("他[[叫汤姆]]"));
+        assert_eq!(text.lines().nth(3), Some("叫\tS\tB\t*"));
+
+        let mut errors = vec![
+            errors,
+            SentenceErrors::new(1, &["他"], &["r"], &["v"]).unwrap(),
+        ];
+        let mut out = Vec::new();
+        write_errors(&mut out, &mut errors).unwrap();
+        assert_eq!(errors[1].index, 1);
+    }
+
+    #[test]
+    fn test_oov_report() {
+        let known = |word: &str| word != "汤姆";
+        let mut report = OovReport::default();
+        report.add_words(&["他", "叫", "汤姆"], &["他", "叫汤", "姆"], &known);
+        assert_eq!(
+            report.iv,
+            LabelScore {
+                correct: 1,
+                predicted: 3,
+                gold: 2
+            }
+        );
+        assert_eq!(
+            report.oov,
+            LabelScore {
+                correct: 0,
+                predicted: 0,
+                gold: 1
+            }
+        );
+
+        let mut report = OovReport::default();
+        let words = ["他", "叫", "汤姆", "去"];
+        report.add_tokens(
+            &words,
+            &["r", "v", "nh", "_"],
+            &["r", "n", "nh", "v"],
+            &known,
+        );
+        assert_eq!((report.iv.correct, report.iv.gold), (1, 2));
+        assert_eq!((report.oov.correct, report.oov.gold), (1, 1));
+        assert_eq!(report.oov_rate(), 1.0 / 3.0);
+    }
+
+    #[test]
+    fn test_length_report() {
+        let score = |correct| LabelScore {
+            correct,
+            predicted: 2,
+            gold: 2,
+        };
+        let mut report = LengthReport::new(vec![20, 10]);
+        report.add(3, score(2), 30);
+        report.add(10, score(1), 50);
+        report.add(25, score(0), 400);
+        assert_eq!(report.bucket(11), 20);
+
+        let mut other = LengthReport::new(vec![10, 20]);
+        other.add(4, score(2), 10);
+        report.merge(&other);
+        assert_eq!(
+            report.buckets.keys().collect::<Vec<_>>(),
+            [&10, &usize::MAX]
+        );
+
+        let short = &report.buckets[&10];
+        assert_eq!(short.sentences(), 3);
+        assert_eq!(short.score.correct, 5);
+        assert_eq!(short.percentile(0.5), 30);
+        assert_eq!(short.latency_per_token(), 90.0 / 17.0);
+        assert!(report.to_string().contains("21+"));
+    }
+
+    #[test]
+    fn test_calibration() {
+        let mut report = CalibrationReport::new(4);
+        report.add(0.9, true);
+        report.add(0.8, false);
+        report.add(1.0, true);
+        let mut other = CalibrationReport::new(4);
+        other.add(0.3, false);
+        report.merge(&other);
+
+        assert_eq!(report.count(), 4);
+        assert_eq!(report.bins[3].count, 3);
+        assert!((report.bins[3].mean_confidence() - 0.9).abs() < 1e-9);
+        // (3 * |0.9 - 2/3| + 1 * |0.3 - 0|) / 4
+        assert!((report.ece() - 0.25).abs() < 1e-9);
+        assert!((report.mce() - 0.3).abs() < 1e-9);
+    }
+}
@@ -1,8 +1,27 @@
-use crate::{CWSDefinition, NERDefinition, POSDefinition, Perceptron};
-use anyhow::Result;
+use crate::perceptron::{
+    decrypt_model, encrypt_model, Compression, ModelCipher, ModelHeader, ModelMetadata,
+    PayloadFormat,
+};
+#[cfg(feature = "cws")]
+use crate::CWSDefinition;
+#[cfg(feature = "ner")]
+use crate::NERDefinition;
+#[cfg(feature = "pos")]
+use crate::POSDefinition;
+use crate::Perceptron;
+use anyhow::{anyhow, Result};
+use apache_avro::types::Value;
 pub use apache_avro::{schema, Codec, Reader, Schema};
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Serialize, Serializer};
 use std::collections::HashMap;
+#[cfg(feature = "fs")]
+use std::fs::File;
+use std::hash::BuildHasher;
+#[cfg(feature = "fs")]
+use std::io::BufWriter;
+use std::io::{BufReader, Read, Write};
+#[cfg(feature = "fs")]
+use std::path::Path;
 
 #[derive(Copy, Clone, Debug)]
 pub enum Format {
@@ -14,19 +33,303 @@ pub trait ModelSerde<'de>: Serialize + Deserialize<'de> {
     fn load<R: std::io::Read>(reader: R, format: Format) -> Result<Self>;
     fn load_avro<R: std::io::Read>(reader: Reader<R>) -> Result<Self>;
     fn save<W: std::io::Write>(&self, writer: W, format: Format) -> Result<()>;
+
+    fn load_compressed<R: std::io::Read>(
+        reader: R,
+        format: Format,
+        compression: Compression,
+    ) -> Result<Self> {
+        Self::load(compression.decoder(reader)?, format)
+    }
+
+    fn save_compressed<W: std::io::Write>(
+        &self,
+        writer: W,
+        format: Format,
+        compression: Compression,
+    ) -> Result<()> {
+        let mut writer = compression.encoder(writer)?;
+        self.save(&mut writer, format)?;
+        writer.finish()?.flush()?;
+        Ok(())
+    }
+
+    /// 解密后按文件头识别压缩方式，见 [`ModelCipher`]
+    fn load_encrypted<R: std::io::Read>(
+        mut reader: R,
+        format: Format,
+        cipher: &dyn ModelCipher,
+    ) -> Result<Self> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes)?;
+        let plain = decrypt_model(cipher, &bytes)?;
+        Self::load_compressed(plain.as_slice(), format, Compression::from_magic(&plain))
+    }
+
+    /// 先压缩再加密
+    fn save_encrypted<W: std::io::Write>(
+        &self,
+        mut writer: W,
+        format: Format,
+        compression: Compression,
+        cipher: &dyn ModelCipher,
+    ) -> Result<()> {
+        let mut plain = Vec::new();
+        self.save_compressed(&mut plain, format, compression)?;
+        writer.write_all(&encrypt_model(cipher, &plain)?)?;
+        Ok(())
+    }
+
+    /// 根据扩展名（`.gz`、`.zst`）自动选择压缩方式
+    #[cfg(feature = "fs")]
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(skip_all, fields(path = %path.as_ref().display()))
+    )]
+    fn load_file<P: AsRef<Path>>(path: P, format: Format) -> Result<Self> {
+        let compression = Compression::from_path(&path);
+        Self::load_compressed(File::open(path)?, format, compression)
+    }
+
+    #[cfg(feature = "fs")]
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(skip_all, fields(path = %path.as_ref().display()))
+    )]
+    fn save_file<P: AsRef<Path>>(&self, path: P, format: Format) -> Result<()> {
+        let compression = Compression::from_path(&path);
+        let writer = BufWriter::new(File::create(path)?);
+        self.save_compressed(writer, format, compression)
+    }
+
+    #[cfg(feature = "fs")]
+    fn load_file_encrypted<P: AsRef<Path>>(
+        path: P,
+        format: Format,
+        cipher: &dyn ModelCipher,
+    ) -> Result<Self> {
+        Self::load_encrypted(File::open(path)?, format, cipher)
+    }
+
+    /// 加密后的文件不再按扩展名选择压缩方式，由 `compression` 给出
+    #[cfg(feature = "fs")]
+    fn save_file_encrypted<P: AsRef<Path>>(
+        &self,
+        path: P,
+        format: Format,
+        compression: Compression,
+        cipher: &dyn ModelCipher,
+    ) -> Result<()> {
+        self.save_encrypted(File::create(path)?, format, compression, cipher)
+    }
+
+    /// 从内存中的模型文件加载，不依赖文件系统，可用于 wasm32 等平台；
+    /// 没有文件头的旧模型按 avro 魔数区分格式，不支持压缩
+    fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        let format = if bytes.starts_with(b"Obj\x01") {
+            Format::AVRO(Codec::Null)
+        } else {
+            Format::JSON
+        };
+        Self::load(bytes, format)
+    }
+}
+
+pub fn format_to_payload(format: Format) -> PayloadFormat {
+    match format {
+        Format::JSON => PayloadFormat::Json,
+        Format::AVRO(_) => PayloadFormat::Avro,
+    }
+}
+
+/// 文件头记录的格式，avro 的压缩方式由 avro 文件自身记录，读取时不需要
+impl From<PayloadFormat> for Format {
+    fn from(payload: PayloadFormat) -> Self {
+        match payload {
+            PayloadFormat::Json => Format::JSON,
+            PayloadFormat::Avro => Format::AVRO(Codec::Null),
+        }
+    }
+}
+
+/// 以文件头记录的格式为准，压缩方式由 avro 文件自身记录
+pub fn payload_to_format(payload: PayloadFormat, requested: Format) -> Format {
+    match (payload, requested) {
+        (PayloadFormat::Json, _) => Format::JSON,
+        (PayloadFormat::Avro, Format::AVRO(codec)) => Format::AVRO(codec),
+        (PayloadFormat::Avro, Format::JSON) => Format::AVRO(Codec::Null),
+    }
+}
+
+pub fn write_metadata<W: Write>(writer: &mut W, metadata: &ModelMetadata) -> Result<()> {
+    let bytes = serde_json::to_vec(metadata)?;
+    let len = u32::try_from(bytes.len()).map_err(|_| anyhow!("model metadata is too large"))?;
+    writer.write_all(&len.to_le_bytes())?;
+    writer.write_all(&bytes)?;
+    Ok(())
+}
+
+pub fn read_metadata<R: Read>(reader: &mut R) -> Result<ModelMetadata> {
+    let mut len = [0u8; 4];
+    reader.read_exact(&mut len)?;
+    let mut bytes = vec![0u8; u32::from_le_bytes(len) as usize];
+    reader.read_exact(&mut bytes)?;
+    Ok(serde_json::from_slice(&bytes)?)
+}
+
+/// 只读取模型文件中的元数据，不加载参数；旧格式的模型返回 `None`
+pub fn read_model_metadata<R: Read>(reader: R) -> Result<Option<ModelMetadata>> {
+    let mut reader = BufReader::new(reader);
+    match ModelHeader::read(&mut reader)? {
+        Some(header) if header.has_metadata() => read_metadata(&mut reader).map(Some),
+        _ => Ok(None),
+    }
+}
+
+/// 按键排序后输出，使相同的 `HashMap` 总是序列化为相同的字节
+pub fn serialize_sorted<K, V, H, S>(
+    map: &HashMap<K, V, H>,
+    serializer: S,
+) -> std::result::Result<S::Ok, S::Error>
+where
+    K: Ord + Serialize,
+    V: Serialize,
+    H: BuildHasher,
+    S: Serializer,
+{
+    let mut entries: Vec<_> = map.iter().collect();
+    entries.sort_unstable_by(|a, b| a.0.cmp(b.0));
+    serializer.collect_map(entries)
+}
+
+/// 与 [`SerdeModel`] 的 JSON 序列化结果相同，但特征按字典序输出
+#[doc(hidden)]
+#[derive(Serialize)]
+pub struct StableModel<'a, T, V> {
+    definition: &'a T,
+    #[serde(serialize_with = "serialize_sorted")]
+    features: &'a HashMap<String, usize>,
+    parameters: &'a [V],
+}
+
+impl<'a, T: crate::perceptron::Definition, V: crate::perceptron::TraitParameter>
+    From<&'a SerdeModel<T, V>> for StableModel<'a, T, V>
+{
+    fn from(model: &'a SerdeModel<T, V>) -> Self {
+        Self {
+            definition: &model.definition,
+            features: &model.features,
+            parameters: &model.parameters,
+        }
+    }
+}
+
+/// 固定的 avro 同步标记，`apache_avro::Writer` 每次随机生成
+const AVRO_SYNC_MARKER: &[u8; 16] = b"ltp-model-marker";
+
+/// 按 avro 的 object container 格式写出单个值，同步标记固定，文件头与值中的 map 按键排序，
+/// 相同的模型总是得到相同的字节
+pub fn write_avro_stable<W: Write>(
+    mut writer: W,
+    schema: &Schema,
+    codec: Codec,
+    value: Value,
+) -> Result<()> {
+    let mut header = b"Obj\x01".to_vec();
+    let metadata = [
+        ("avro.codec", Value::from(codec)),
+        ("avro.schema", Value::Bytes(serde_json::to_vec(schema)?)),
+    ];
+    encode_long(metadata.len(), &mut header)?;
+    for (key, value) in metadata {
+        header.extend(apache_avro::to_avro_datum(&Schema::String, key)?);
+        header.extend(apache_avro::to_avro_datum(&Schema::Bytes, value)?);
+    }
+    header.push(0);
+    header.extend_from_slice(AVRO_SYNC_MARKER);
+
+    let mut block = Vec::new();
+    encode_sorted(value, schema, &mut block)?;
+    codec.compress(&mut block)?;
+    encode_long(1, &mut header)?;
+    encode_long(block.len(), &mut header)?;
+    writer.write_all(&header)?;
+    writer.write_all(&block)?;
+    writer.write_all(AVRO_SYNC_MARKER)?;
+    Ok(())
+}
+
+fn encode_long(value: usize, buffer: &mut Vec<u8>) -> Result<()> {
+    buffer.extend(apache_avro::to_avro_datum(&Schema::Long, value as i64)?);
+    Ok(())
+}
+
+/// 除 map 按键排序外与 `apache_avro` 的编码相同，记录与 map 之外的值直接交给 `apache_avro`
+fn encode_sorted(value: Value, schema: &Schema, buffer: &mut Vec<u8>) -> Result<()> {
+    match (value, schema) {
+        (
+            Value::Record(fields),
+            Schema::Record {
+                fields: schemas,
+                lookup,
+                ..
+            },
+        ) => {
+            for (name, value) in fields {
+                let index = lookup
+                    .get(&name)
+                    .ok_or_else(|| anyhow!("field {} is not in the avro schema", name))?;
+                encode_sorted(value, &schemas[*index].schema, buffer)?;
+            }
+        }
+        (Value::Map(items), Schema::Map(inner)) => {
+            let mut items: Vec<_> = items.into_iter().collect();
+            items.sort_unstable_by(|a, b| a.0.cmp(&b.0));
+            if !items.is_empty() {
+                encode_long(items.len(), buffer)?;
+            }
+            for (key, value) in items {
+                buffer.extend(apache_avro::to_avro_datum(&Schema::String, key)?);
+                encode_sorted(value, inner, buffer)?;
+            }
+            buffer.push(0);
+        }
+        (value, schema) => buffer.extend(apache_avro::to_avro_datum(schema, value)?),
+    }
+    Ok(())
 }
 
 pub type SerdeModel<T, V> = Perceptron<T, HashMap<String, usize>, Vec<V>, V>;
+#[cfg(feature = "cws")]
 pub type SerdeCWSModel = SerdeModel<CWSDefinition, f64>;
+#[cfg(feature = "pos")]
 pub type SerdePOSModel = SerdeModel<POSDefinition, f64>;
+#[cfg(feature = "ner")]
 pub type SerdeNERModel = SerdeModel<NERDefinition, f64>;
 
+// 仅供内部实现使用，不属于公开接口
+#[doc(hidden)]
 #[macro_export]
 macro_rules! impl_model_serialization {
     ($name:tt, $raw_schema:ident) => {
         impl<'de> ModelSerde<'de> for $name {
             fn load<R: std::io::Read>(reader: R, format: Format) -> Result<Self> {
-                let model = match format {
+                let mut reader = std::io::BufReader::new(reader);
+                let header = $crate::perceptron::ModelHeader::read(&mut reader)?;
+                let format = match header {
+                    Some(header) => {
+                        header.expect_task(<$name>::TASK)?;
+                        $crate::perceptron::payload_to_format(header.payload, format)
+                    }
+                    None => format,
+                };
+                let metadata = match header {
+                    Some(header) if header.has_metadata() => {
+                        $crate::perceptron::read_metadata(&mut reader)?
+                    }
+                    _ => Default::default(),
+                };
+                let mut model: Self = match format {
                     Format::JSON => serde_json::from_reader(reader)?,
                     Format::AVRO(_) => {
                         let schema = apache_avro::Schema::parse_str($raw_schema)?;
@@ -34,35 +337,49 @@ macro_rules! impl_model_serialization {
 
                         let mut model = None;
                         for value in reader {
-                            model = Some(apache_avro::from_value::<Self>(&value.unwrap())?);
+                            model = Some(apache_avro::from_value::<Self>(&value?)?);
                         }
-                        model.unwrap()
+                        model.ok_or_else(|| anyhow::anyhow!("the avro model file has no record"))?
                     }
                 };
+                $crate::perceptron::Definition::set_token_join(
+                    &mut model.definition,
+                    metadata.token_join,
+                );
+                $crate::perceptron::Definition::set_normalizers(
+                    &mut model.definition,
+                    $crate::perceptron::Normalizers::from_names(&metadata.normalizers),
+                );
+                model.metadata = metadata;
+                if let Some(header) = header {
+                    header.expect_labels(&$crate::perceptron::Definition::labels(
+                        &model.definition,
+                    ))?;
+                }
                 Ok(model)
             }
 
             fn load_avro<R: std::io::Read>(reader: apache_avro::Reader<R>) -> Result<Self> {
-                let model = {
-                    let mut model = None;
-                    for value in reader {
-                        model = Some(apache_avro::from_value::<Self>(&value.unwrap())?);
-                    }
-                    model.unwrap()
-                };
-                Ok(model)
+                let mut model = None;
+                for value in reader {
+                    model = Some(apache_avro::from_value::<Self>(&value?)?);
+                }
+                model.ok_or_else(|| anyhow::anyhow!("the avro model file has no record"))
             }
 
-            fn save<W: std::io::Write>(&self, writer: W, format: Format) -> Result<()> {
+            fn save<W: std::io::Write>(&self, mut writer: W, format: Format) -> Result<()> {
+                self.header($crate::perceptron::format_to_payload(format))
+                    .write(&mut writer)?;
+                $crate::perceptron::write_metadata(&mut writer, &self.metadata)?;
                 match format {
                     Format::JSON => {
-                        serde_json::to_writer(writer, self)?;
+                        let model = $crate::perceptron::StableModel::from(self);
+                        serde_json::to_writer(writer, &model)?;
                     }
                     Format::AVRO(codec) => {
                         let schema = apache_avro::Schema::parse_str($raw_schema)?;
-                        let mut writer = apache_avro::Writer::with_codec(&schema, writer, codec);
-                        writer.append_ser(self)?;
-                        writer.flush()?;
+                        let value = apache_avro::to_value(self)?;
+                        $crate::perceptron::write_avro_stable(writer, &schema, codec, value)?;
                     }
                 }
                 Ok(())
@@ -72,19 +389,161 @@ macro_rules! impl_model_serialization {
     () => {};
 }
 
+#[cfg(feature = "cws")]
 static CWS_RAW_SCHEMA: &str = include_str!(concat!(
     env!("CARGO_MANIFEST_DIR"),
     "/vendor/schema/cws.avsc"
 ));
+#[cfg(feature = "pos")]
 static POS_RAW_SCHEMA: &str = include_str!(concat!(
     env!("CARGO_MANIFEST_DIR"),
     "/vendor/schema/pos.avsc"
 ));
+#[cfg(feature = "ner")]
 static NER_RAW_SCHEMA: &str = include_str!(concat!(
     env!("CARGO_MANIFEST_DIR"),
     "/vendor/schema/ner.avsc"
 ));
 
+#[cfg(feature = "cws")]
 impl_model_serialization!(SerdeCWSModel, CWS_RAW_SCHEMA);
+#[cfg(feature = "pos")]
 impl_model_serialization!(SerdePOSModel, POS_RAW_SCHEMA);
+#[cfg(feature = "ner")]
 impl_model_serialization!(SerdeNERModel, NER_RAW_SCHEMA);
+
+#[cfg(all(test, feature = "cws", feature = "pos"))]
+mod tests {
+    use super::*;
+    use crate::perceptron::cws_test_model;
+
+    fn cws_model() -> SerdeCWSModel {
+        cws_test_model(&["2我"], |i| i as f64 * 0.5)
+            .with_metadata(ModelMetadata::new().with_tag("corpus", "pku"))
+    }
+
+    #[test]
+    fn test_header_round_trip() -> Result<()> {
+        let model = cws_model();
+        for format in [Format::JSON, Format::AVRO(Codec::Deflate)] {
+            let mut bytes = Vec::new();
+            model.save(&mut bytes, format)?;
+            assert!(bytes.starts_with(&crate::perceptron::MAGIC));
+
+            // 文件头中的格式优先于调用方给出的格式
+            let loaded = SerdeCWSModel::load(bytes.as_slice(), Format::JSON)?;
+            assert_eq!(loaded.parameters, model.parameters);
+            assert_eq!(loaded.metadata(), model.metadata());
+            let loaded = SerdeCWSModel::from_bytes(&bytes)?;
+            assert_eq!(loaded.parameters, model.parameters);
+            assert_eq!(
+                read_model_metadata(bytes.as_slice())?.as_ref(),
+                Some(model.metadata())
+            );
+
+            let err = SerdePOSModel::load(bytes.as_slice(), format).unwrap_err();
+            assert_eq!(err.to_string(), "this is a CWS model, expected POS");
+        }
+
+        // 没有文件头的旧模型
+        let legacy = serde_json::to_vec(&model)?;
+        let loaded = SerdeCWSModel::load(legacy.as_slice(), Format::JSON)?;
+        assert_eq!(loaded.features, model.features);
+        let loaded = SerdeCWSModel::from_bytes(&legacy)?;
+        assert_eq!(loaded.features, model.features);
+
+        // 只有文件头、没有记录的 avro 文件返回错误
+        let metadata = Value::Map(HashMap::from([
+            ("avro.schema".into(), Value::Bytes(CWS_RAW_SCHEMA.into())),
+            ("avro.codec".into(), Value::Bytes(b"null".to_vec())),
+        ]));
+        let map = apache_avro::Schema::Map(Box::new(apache_avro::Schema::Bytes));
+        let mut empty = b"Obj\x01".to_vec();
+        empty.extend(apache_avro::to_avro_datum(&map, metadata)?);
+        empty.extend([0u8; 16]);
+        let format = Format::AVRO(Codec::Null);
+        let err = SerdeCWSModel::load(empty.as_slice(), format).unwrap_err();
+        assert_eq!(err.to_string(), "the avro model file has no record");
+        let reader = apache_avro::Reader::new(empty.as_slice())?;
+        assert!(SerdeCWSModel::load_avro(reader).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_definition_from_metadata() -> Result<()> {
+        use crate::perceptron::{Definition, DigitFold, JoinMode, Normalizers, TokenJoin};
+
+        let join = TokenJoin::new(JoinMode::Run);
+        let normalizers = Normalizers::new().with(DigitFold);
+        let mut metadata = ModelMetadata::new();
+        metadata.token_join = join;
+        metadata.normalizers = normalizers.names();
+        let definition = CWSDefinition::new()
+            .join(join)
+            .with_normalizers(normalizers.clone());
+        let parameters = vec![0.0; 8];
+        let model: SerdeCWSModel =
+            Perceptron::new_with_parameters(definition, HashMap::new(), parameters)
+                .with_metadata(metadata);
+
+        let mut bytes = Vec::new();
+        model.save(&mut bytes, Format::AVRO(Codec::Deflate))?;
+        let loaded = SerdeCWSModel::load(bytes.as_slice(), Format::JSON)?;
+        assert_eq!(loaded.definition.token_join(), join);
+        assert_eq!(loaded.definition.normalizers(), normalizers);
+        assert_eq!(loaded.definition, model.definition);
+        Ok(())
+    }
+
+    #[test]
+    fn test_encrypted() -> Result<()> {
+        use crate::perceptron::XorCipher;
+
+        let model = cws_model();
+        let cipher = XorCipher::new("app-secret");
+        let mut bytes = Vec::new();
+        model.save_encrypted(&mut bytes, Format::JSON, Compression::None, &cipher)?;
+        let loaded = SerdeCWSModel::load_encrypted(bytes.as_slice(), Format::JSON, &cipher)?;
+        assert_eq!(loaded.parameters, model.parameters);
+
+        let err = SerdeCWSModel::load(bytes.as_slice(), Format::JSON).unwrap_err();
+        assert!(err.to_string().contains("encrypted"));
+        let wrong = XorCipher::new("guess");
+        assert!(SerdeCWSModel::load_encrypted(bytes.as_slice(), Format::JSON, &wrong).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_stable_save() -> Result<()> {
+        let labels: Vec<String> = ["n", "v", "r", "p", "u"].map(String::from).into();
+        let save = |format| -> Result<Vec<u8>> {
+            // 每个 HashMap 的哈希种子不同，插入顺序也不同
+            let mut features = HashMap::new();
+            for i in 0..64 {
+                features.insert(format!("2{}", i), i);
+            }
+            let parameters = (0..65 * 5).map(|i| i as f64).collect();
+            let model: SerdePOSModel = Perceptron::new_with_parameters(
+                POSDefinition::new(labels.clone()),
+                features,
+                parameters,
+            );
+            let mut bytes = Vec::new();
+            model.save(&mut bytes, format)?;
+            Ok(bytes)
+        };
+        for format in [
+            Format::JSON,
+            Format::AVRO(Codec::Null),
+            Format::AVRO(Codec::Deflate),
+        ] {
+            let bytes = save(format)?;
+            assert_eq!(bytes, save(format)?);
+            let loaded = SerdePOSModel::load(bytes.as_slice(), format)?;
+            assert_eq!(loaded.features.len(), 64);
+            assert_eq!(loaded.features["27"], 7);
+            assert_eq!(loaded.definition, POSDefinition::new(labels.clone()));
+        }
+        Ok(())
+    }
+}
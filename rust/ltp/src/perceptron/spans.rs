@@ -0,0 +1,191 @@
+//! 实体的片段级评测，各匹配方式参照 MUC 与 SemEval-2013 的约定
+#[cfg(feature = "serialization")]
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt::{Display, Formatter};
+
+/// 实体 `(类型, 起始, 结束)`，结束位置包含在内，与 [`crate::get_entities`] 一致
+pub type Span<'a> = (&'a str, usize, usize);
+
+/// 预测的实体与标注的实体如何算作匹配
+#[cfg_attr(feature = "serialization", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serialization", serde(rename_all = "kebab-case"))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum SpanMatch {
+    /// 边界与类型都相同
+    Exact,
+    /// 类型与起始位置相同
+    LeftBoundary,
+    /// 类型与结束位置相同
+    RightBoundary,
+    /// 不论类型，边界相同得 1 分，有重叠得 0.5 分
+    Partial,
+    /// 类型相同且有重叠
+    TypeOnly,
+}
+
+impl SpanMatch {
+    pub const ALL: [SpanMatch; 5] = [
+        SpanMatch::Exact,
+        SpanMatch::LeftBoundary,
+        SpanMatch::RightBoundary,
+        SpanMatch::Partial,
+        SpanMatch::TypeOnly,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            SpanMatch::Exact => "exact",
+            SpanMatch::LeftBoundary => "left-boundary",
+            SpanMatch::RightBoundary => "right-boundary",
+            SpanMatch::Partial => "partial",
+            SpanMatch::TypeOnly => "type-only",
+        }
+    }
+
+    /// 一对实体的得分
+    pub fn credit(&self, gold: &Span, predicted: &Span) -> f64 {
+        let overlap = predicted.1 <= gold.2 && gold.1 <= predicted.2;
+        let same_type = gold.0 == predicted.0;
+        let matched = match self {
+            SpanMatch::Exact => gold == predicted,
+            SpanMatch::LeftBoundary => same_type && gold.1 == predicted.1,
+            SpanMatch::RightBoundary => same_type && gold.2 == predicted.2,
+            SpanMatch::Partial if (gold.1, gold.2) == (predicted.1, predicted.2) => true,
+            SpanMatch::Partial => return if overlap { 0.5 } else { 0.0 },
+            SpanMatch::TypeOnly => same_type && overlap,
+        };
+        if matched {
+            1.0
+        } else {
+            0.0
+        }
+    }
+}
+
+#[cfg_attr(feature = "serialization", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct SpanScore {
+    /// 部分匹配时可以是小数
+    pub correct: f64,
+    pub predicted: usize,
+    pub gold: usize,
+}
+
+impl SpanScore {
+    pub fn precision(&self) -> f64 {
+        match self.predicted {
+            0 => 0.0,
+            n => self.correct / n as f64,
+        }
+    }
+
+    pub fn recall(&self) -> f64 {
+        match self.gold {
+            0 => 0.0,
+            n => self.correct / n as f64,
+        }
+    }
+
+    pub fn f1(&self) -> f64 {
+        let (p, r) = (self.precision(), self.recall());
+        if p + r == 0.0 {
+            0.0
+        } else {
+            2.0 * p * r / (p + r)
+        }
+    }
+}
+
+#[cfg_attr(feature = "serialization", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SpanReport {
+    pub scores: BTreeMap<SpanMatch, SpanScore>,
+}
+
+impl SpanReport {
+    /// 累加一个句子的实体，每个预测的实体至多与一个标注的实体匹配，优先取得分高的
+    pub fn add(&mut self, gold: &[Span], predicted: &[Span]) {
+        for mode in SpanMatch::ALL {
+            let mut used = vec![false; predicted.len()];
+            let mut correct = 0.0;
+            for g in gold {
+                let best = predicted
+                    .iter()
+                    .enumerate()
+                    .filter(|&(i, _)| !used[i])
+                    .map(|(i, p)| (i, mode.credit(g, p)))
+                    .filter(|&(_, credit)| credit > 0.0)
+                    .max_by(|a, b| a.1.total_cmp(&b.1).then(b.0.cmp(&a.0)));
+                if let Some((i, credit)) = best {
+                    used[i] = true;
+                    correct += credit;
+                }
+            }
+            let score = self.scores.entry(mode).or_default();
+            score.correct += correct;
+            score.predicted += predicted.len();
+            score.gold += gold.len();
+        }
+    }
+
+    pub fn merge(&mut self, other: &SpanReport) {
+        for (mode, other) in &other.scores {
+            let score = self.scores.entry(*mode).or_default();
+            score.correct += other.correct;
+            score.predicted += other.predicted;
+            score.gold += other.gold;
+        }
+    }
+
+    pub fn get(&self, mode: SpanMatch) -> SpanScore {
+        self.scores.get(&mode).copied().unwrap_or_default()
+    }
+}
+
+impl Display for SpanReport {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        writeln!(
+            f,
+            "{:<14} {:>9} {:>9} {:>9} {:>9} {:>9} {:>9}",
+            "match", "precision", "recall", "f1", "correct", "predicted", "gold"
+        )?;
+        for (mode, s) in &self.scores {
+            writeln!(
+                f,
+                "{:<14} {:>9.4} {:>9.4} {:>9.4} {:>9} {:>9} {:>9}",
+                mode.name(),
+                s.precision(),
+                s.recall(),
+                s.f1(),
+                s.correct,
+                s.predicted,
+                s.gold
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::get_entities;
+
+    #[test]
+    fn test_span_report() {
+        let gold = vec!["B-Ni", "I-Ni", "E-Ni", "O", "S-Nh", "B-Ns", "E-Ns"];
+        let predicted = vec!["B-Ni", "E-Ni", "O", "O", "S-Ns", "B-Ns", "E-Ns"];
+        let mut report = SpanReport::default();
+        report.add(&get_entities(&gold), &get_entities(&predicted));
+
+        let correct = |mode| report.get(mode).correct;
+        assert_eq!(correct(SpanMatch::Exact), 1.0);
+        assert_eq!(correct(SpanMatch::LeftBoundary), 2.0);
+        assert_eq!(correct(SpanMatch::RightBoundary), 1.0);
+        // Ns 与 Nh 边界相同，Ni 部分重叠
+        assert_eq!(correct(SpanMatch::Partial), 2.5);
+        assert_eq!(correct(SpanMatch::TypeOnly), 2.0);
+        assert_eq!(report.get(SpanMatch::Exact).precision(), 1.0 / 3.0);
+    }
+}
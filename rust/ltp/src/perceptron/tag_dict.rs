@@ -0,0 +1,154 @@
+//! 从训练语料中统计的标签字典：每个词出现过的标签及其次数，随模型元数据保存；
+//! 解码时高频词可以只在出现过的标签中选择（见 [`Perceptron::decode_restricted`]）
+//!
+//! [`Perceptron::decode_restricted`]: crate::perceptron::Perceptron::decode_restricted
+use crate::perceptron::Definition;
+#[cfg(feature = "serialization")]
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+#[cfg_attr(feature = "serialization", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serialization", serde(transparent))]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TagDictionary {
+    words: BTreeMap<String, BTreeMap<String, usize>>,
+}
+
+impl TagDictionary {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add(&mut self, word: &str, tag: &str) {
+        let tags = self.words.entry(word.to_string()).or_default();
+        *tags.entry(tag.to_string()).or_default() += 1;
+    }
+
+    pub fn merge(&mut self, other: &TagDictionary) {
+        for (word, tags) in &other.words {
+            let entry = self.words.entry(word.clone()).or_default();
+            for (tag, count) in tags {
+                *entry.entry(tag.clone()).or_default() += count;
+            }
+        }
+    }
+
+    /// 词的个数
+    pub fn len(&self) -> usize {
+        self.words.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.words.is_empty()
+    }
+
+    /// 词出现过的标签及其次数
+    pub fn tags(&self, word: &str) -> Option<&BTreeMap<String, usize>> {
+        self.words.get(word)
+    }
+
+    /// 词在语料中出现的次数
+    pub fn count(&self, word: &str) -> usize {
+        self.tags(word).map_or(0, |tags| tags.values().sum())
+    }
+
+    /// 出现次数最多的 `n` 个词，次数相同时按词排序
+    pub fn most_frequent(&self, n: usize) -> Vec<&str> {
+        let mut words = self
+            .words
+            .iter()
+            .map(|(word, tags)| (tags.values().sum::<usize>(), word.as_str()))
+            .collect::<Vec<_>>();
+        words.sort_unstable_by(|a, b| b.0.cmp(&a.0).then(a.1.cmp(b.1)));
+        words.into_iter().take(n).map(|(_, word)| word).collect()
+    }
+
+    /// 出现不少于 `min_count` 次的词出现过的标签的下标（升序），其余词为 `None`；
+    /// 标签都不在定义的标签表中时也为 `None`
+    pub fn allowed<D: Definition>(
+        &self,
+        define: &D,
+        words: &[&str],
+        min_count: usize,
+    ) -> Vec<Option<Vec<usize>>> {
+        words
+            .iter()
+            .map(|word| {
+                let tags = self.tags(word)?;
+                if tags.values().sum::<usize>() < min_count {
+                    return None;
+                }
+                let mut labels = tags
+                    .keys()
+                    .filter_map(|tag| define.label_to(tag).ok())
+                    .collect::<Vec<_>>();
+                labels.sort_unstable();
+                labels.dedup();
+                (!labels.is_empty()).then_some(labels)
+            })
+            .collect()
+    }
+}
+
+impl<W: AsRef<str>, T: AsRef<str>> Extend<(W, T)> for TagDictionary {
+    fn extend<I: IntoIterator<Item = (W, T)>>(&mut self, iter: I) {
+        for (word, tag) in iter {
+            self.add(word.as_ref(), tag.as_ref());
+        }
+    }
+}
+
+#[cfg(all(test, feature = "pos"))]
+mod tests {
+    use super::*;
+    use crate::perceptron::{Trainer, TraitFeature};
+    use crate::POSDefinition;
+    use anyhow::Result;
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_tag_dictionary() -> Result<()> {
+        let define = POSDefinition::new(vec!["n".into(), "v".into(), "r".into()]);
+        let corpus = "他/r 喜欢/v 花/n\n她/r 花/v 钱/n\n花/n 开/v\n";
+        let mut tags = TagDictionary::new();
+        define.collect_tags(corpus.as_bytes(), &mut tags)?;
+        assert_eq!(tags.count("花"), 3);
+        assert_eq!(tags.tags("花").unwrap()["n"], 2);
+        assert_eq!(
+            tags.allowed(&define, &["他", "花", "钱"], 2),
+            vec![None, Some(vec![0, 1]), None]
+        );
+        assert_eq!(tags.allowed(&define, &["钱"], 1), vec![Some(vec![0])]);
+
+        let mut trainer: Trainer<POSDefinition> = Trainer::new_with_define(define.clone())
+            .epoch(3)
+            .verbose(false)
+            .compress(false)
+            .tag_dictionary(true);
+        trainer.train_set = Some(define.parse_gold_features(corpus.as_bytes())?);
+        trainer.collect_tags(corpus.as_bytes())?;
+        let model = trainer.build::<HashMap<String, usize>, Vec<f64>>()?;
+        assert_eq!(model.metadata.tag_dictionary, tags);
+
+        // 高频词只在出现过的标签中选择，其余词不受限制
+        let words = ["钱", "他"];
+        let preds = model.predict_restricted(&words, 1)?;
+        assert_eq!(preds[0], "n");
+        assert_eq!(preds[1], "r");
+        assert_eq!(
+            model.predict_restricted(&words, 100)?,
+            model.predict(&words)?
+        );
+
+        // 不使用维特比解码时，空的标签集合同样不受限制
+        let features = define.parse_words_features(&words)?;
+        let features = model.features.get_arena(&features);
+        let preds = model.decode(&features);
+        let allowed = [Some(vec![]), Some(vec![2])];
+        assert_eq!(
+            model.decode_restricted(&features, &allowed)?,
+            vec![preds[0], 2]
+        );
+        Ok(())
+    }
+}
@@ -1,29 +1,44 @@
+use crate::perceptron::memory::{dictionary_bytes, parameter_bytes, sample_bytes};
 use crate::perceptron::model::PaMode;
+#[cfg(feature = "fs")]
+use crate::perceptron::{load_cache, CompressedReader, FeatureSorter, UnknownLabelPolicy};
 use crate::perceptron::{
-    Definition, Perceptron, Sample, TraitFeature, TraitFeatureCompressUtils,
+    CalibrationReport, ConfusionMatrix, CorpusStats, CurvePoint, Definition, EvalReport,
+    HashedFeatures, LabelScore, LengthReport, MemoryEstimate, ModelMetadata, Perceptron,
+    Predictions, Sample, SpanReport, TagDictionary, TraitFeature, TraitFeatureCompressUtils,
     TraitFeaturesTrainUtils, TraitParameter, TraitParameterStorage,
     TraitParameterStorageCompressUtils, TraitParameterStorageTrainUtils,
 };
-use anyhow::Result;
+#[cfg(feature = "fs")]
+use anyhow::Context;
+use anyhow::{anyhow, Result};
 use num_traits::Float;
 use rand::prelude::SliceRandom;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
 #[cfg(feature = "parallel")]
 use rayon::prelude::*;
 #[cfg(feature = "serialization")]
 use serde::{Deserialize, Serialize};
-use std::collections::HashSet;
+use std::collections::{BTreeMap, HashSet};
 use std::fmt::{Debug, Display, Formatter};
-use std::fs::File;
+#[cfg(feature = "fs")]
+use std::io::BufRead;
+use std::io::Read;
 use std::ops::Deref;
-use std::path::Path;
-use std::sync::{Arc, RwLock};
+#[cfg(feature = "fs")]
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
 use std::thread;
+use std::time::Instant;
 
 #[cfg_attr(feature = "serialization", derive(Serialize, Deserialize))]
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum Algorithm<Param: TraitParameter> {
     AP(usize),
     PA(PaMode<Param>),
+    /// 无锁共享参数的并行感知机，参数为线程数，结果不可复现
+    Hogwild(usize),
 }
 
 impl<Param: TraitParameter + Display> Display for Algorithm<Param> {
@@ -41,6 +56,9 @@ impl<Param: TraitParameter + Display> Display for Algorithm<Param> {
             Algorithm::PA(PaMode::PaII(c)) => {
                 write!(f, "algorithm: PaII(c={})", c)
             }
+            Algorithm::Hogwild(threads) => {
+                write!(f, "algorithm: Hogwild (threads={})", threads)
+            }
         }
     }
 }
@@ -63,6 +81,10 @@ where
     pub shuffle: bool,
     pub verbose: bool,
     pub eval_threads: usize,
+    /// 调用方提供的 rayon 线程池，设置后评测不再按 `eval_threads` 新建线程池
+    #[cfg(feature = "parallel")]
+    #[cfg_attr(feature = "serialization", serde(skip))]
+    pub thread_pool: Option<Arc<rayon::ThreadPool>>,
 
     // 训练算法
     pub algorithm: Algorithm<Param>,
@@ -74,6 +96,83 @@ where
 
     pub train_set: Option<Vec<Sample>>,
     pub eval_set: Option<Vec<Sample>>,
+    /// 训练集由多个语料依次拼接而成时，各语料的大小与采样权重
+    pub domains: Vec<Domain>,
+
+    // 写入模型元数据的自定义标签
+    pub tags: BTreeMap<String, String>,
+    /// 训练语料中各词出现过的标签，为 `None` 时不统计
+    pub tag_dictionary: Option<TagDictionary>,
+    /// 训练时的内存预算（字节），见 [`MemoryEstimate`]
+    pub memory_budget: Option<usize>,
+    /// 训练语料超出内存预算时不读入 `train_set`，每轮从该文件分批读取
+    #[cfg(feature = "fs")]
+    pub stream_path: Option<PathBuf>,
+    /// 构建特征字典时内存中最多保留的特征字节数，超出后排序写入临时文件，见 [`FeatureSorter`]
+    #[cfg(feature = "fs")]
+    pub external_features: Option<usize>,
+    /// 外部排序的临时文件所在的目录，默认为系统的临时目录
+    #[cfg(feature = "fs")]
+    pub spill_dir: Option<PathBuf>,
+}
+
+/// 流式读取训练语料时每批至少的行数
+#[cfg(feature = "fs")]
+const STREAM_BATCH_LINES: usize = 10000;
+
+/// 语料文件按批切分后的文本与每批的起始行号：按行的语料满 [`STREAM_BATCH_LINES`] 行即切分，
+/// 以空行分隔句子的语料在其后的第一个空行处切分，最多不超过四倍的行数
+#[cfg(feature = "fs")]
+pub(crate) struct Batches<R: BufRead> {
+    lines: std::io::Lines<R>,
+    line: usize,
+}
+
+#[cfg(feature = "fs")]
+impl<R: BufRead> Batches<R> {
+    pub(crate) fn new(reader: R) -> Self {
+        Batches {
+            lines: reader.lines(),
+            line: 0,
+        }
+    }
+}
+
+#[cfg(feature = "fs")]
+impl<R: BufRead> Iterator for Batches<R> {
+    type Item = Result<(usize, String)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let start = self.line + 1;
+        let mut batch = String::new();
+        let (mut lines, mut blank) = (0, false);
+        for line in self.lines.by_ref() {
+            let line = match line {
+                Ok(line) => line,
+                Err(err) => return Some(Err(err.into())),
+            };
+            self.line += 1;
+            let empty = line.trim().is_empty();
+            batch.push_str(&line);
+            batch.push('\n');
+            lines += 1;
+            if lines >= STREAM_BATCH_LINES && (empty || !blank || lines >= 4 * STREAM_BATCH_LINES) {
+                return Some(Ok((start, batch)));
+            }
+            blank |= empty;
+        }
+        (!batch.is_empty()).then_some(Ok((start, batch)))
+    }
+}
+
+/// 多语料训练中的一个语料，每轮按 `weight` 采样：整数部分为完整遍历的次数，
+/// 小数部分为每轮额外随机抽取的比例，如 `3.0` 为每轮遍历三次，`0.5` 为每轮抽取一半
+#[cfg_attr(feature = "serialization", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, PartialEq)]
+pub struct Domain {
+    pub name: String,
+    pub size: usize,
+    pub weight: f64,
 }
 
 macro_rules! impl_set_param {
@@ -127,24 +226,452 @@ where
     impl_set_param!(threshold, Param);
     impl_set_param!(algorithm, Algorithm<Param>);
 
+    /// 在调用方的线程池中评测，与应用的其它部分共用线程
+    #[cfg(feature = "parallel")]
+    pub fn thread_pool(mut self, pool: Arc<rayon::ThreadPool>) -> Self {
+        self.thread_pool = Some(pool);
+        self
+    }
+
+    #[cfg(feature = "parallel")]
+    fn eval_pool(&self) -> Result<Arc<rayon::ThreadPool>> {
+        match &self.thread_pool {
+            Some(pool) => Ok(Arc::clone(pool)),
+            None => Ok(Arc::new(
+                rayon::ThreadPoolBuilder::new()
+                    .num_threads(self.eval_threads)
+                    .build()?,
+            )),
+        }
+    }
+
+    pub fn tag<K: Into<String>, V: Into<String>>(mut self, key: K, value: V) -> Self {
+        self.tags.insert(key.into(), value.into());
+        self
+    }
+
+    /// 训练时的内存预算（字节）：[`train_file`](Self::train_file) 估算训练样本超过预算的一半时，
+    /// 改为每轮从文件分批流式读取；[`build`](Self::build) 估算的总内存超出预算时在分配参数前报错
+    pub fn memory_budget(mut self, bytes: usize) -> Self {
+        self.memory_budget = Some(bytes);
+        self
+    }
+
+    /// 以外部排序构建特征字典：内存中的特征超过 `chunk_bytes` 字节时排序写入临时文件，
+    /// 最后归并并按字典序编号，适合特征种类多到内存放不下的语料
+    #[cfg(feature = "fs")]
+    pub fn external_features(mut self, chunk_bytes: usize) -> Self {
+        self.external_features = Some(chunk_bytes);
+        self
+    }
+
+    /// 外部排序的临时文件所在的目录
+    #[cfg(feature = "fs")]
+    pub fn spill_dir<P: AsRef<Path>>(mut self, dir: P) -> Self {
+        self.spill_dir = Some(dir.as_ref().to_path_buf());
+        self
+    }
+
+    /// 读取训练语料时统计各词出现过的标签，写入模型元数据，见 [`TagDictionary`]
+    pub fn tag_dictionary(mut self, enable: bool) -> Self {
+        self.tag_dictionary = enable.then(TagDictionary::new);
+        self
+    }
+
+    /// 启用了标签字典时，统计语料中各词出现过的标签；[`train_file`](Self::train_file) 等会自动调用
+    pub fn collect_tags<R: Read>(&mut self, reader: R) -> Result<()> {
+        if let Some(tags) = &mut self.tag_dictionary {
+            self.definition.collect_tags(reader, tags)?;
+        }
+        Ok(())
+    }
+
+    /// 记录训练参数与语料规模
+    pub fn metadata(&self, features: usize) -> ModelMetadata {
+        let mut metadata = ModelMetadata::new();
+        metadata.labels = self.definition.labels();
+        metadata.token_join = self.definition.token_join();
+        metadata.normalizers = self.definition.normalizers().names();
+
+        let training = &mut metadata.training;
+        training.insert("epoch".to_string(), self.epoch.to_string());
+        training.insert("shuffle".to_string(), self.shuffle.to_string());
+        let algorithm = self.algorithm.to_string();
+        let algorithm = algorithm.trim_start_matches("algorithm: ");
+        training.insert("algorithm".to_string(), algorithm.to_string());
+        training.insert("compress".to_string(), self.compress.to_string());
+        #[cfg(feature = "fs")]
+        if self.stream_path.is_some() {
+            training.insert("streaming".to_string(), true.to_string());
+        }
+        if !self.domains.is_empty() {
+            let domains = self
+                .domains
+                .iter()
+                .map(|domain| format!("{}:{}", domain.name, domain.weight))
+                .collect::<Vec<_>>();
+            training.insert("domains".to_string(), domains.join(","));
+        }
+        if self.compress {
+            training.insert("ratio".to_string(), self.ratio.to_string());
+            training.insert("threshold".to_string(), self.threshold.to_string());
+        }
+
+        let count = |dataset: &Option<Vec<Sample>>| match dataset {
+            Some(dataset) => (
+                dataset.len(),
+                dataset.iter().map(|sample| sample.labels.len()).sum(),
+            ),
+            None => (0, 0),
+        };
+        let (train_sentences, train_tokens) = count(&self.train_set);
+        let (eval_sentences, eval_tokens) = count(&self.eval_set);
+        metadata.corpus = CorpusStats {
+            train_sentences,
+            train_tokens,
+            eval_sentences,
+            eval_tokens,
+            features,
+        };
+        metadata.tags = self.tags.clone();
+        metadata.tag_dictionary = self.tag_dictionary.clone().unwrap_or_default();
+        metadata
+    }
+
+    #[cfg(feature = "fs")]
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(skip_all, fields(path = %path.as_ref().display()))
+    )]
     pub fn load_dataset<P: AsRef<Path>>(&self, path: P) -> Result<Vec<Sample>> {
-        let file = File::open(path)?;
+        let file = CompressedReader::open(path)?;
         let dataset = self.definition.parse_gold_features(file)?;
+        #[cfg(feature = "tracing")]
+        tracing::info!(sentences = dataset.len(), "dataset loaded");
         Ok(dataset)
     }
 
+    /// 定义的未知标签策略为 [`UnknownLabelPolicy::Extend`] 时，把语料中的新标签追加到标签表
+    #[cfg(feature = "fs")]
+    pub fn extend_labels_from<P: AsRef<Path>>(&mut self, path: P) -> Result<()> {
+        if self.definition.unknown_label_policy() != UnknownLabelPolicy::Extend {
+            return Ok(());
+        }
+        let labels = self
+            .definition
+            .unseen_labels(CompressedReader::open(path)?)?;
+        #[cfg(feature = "tracing")]
+        if !labels.is_empty() {
+            tracing::info!(labels = ?labels, "label set extended");
+        }
+        self.definition.extend_labels(&labels);
+        Ok(())
+    }
+
+    #[cfg(feature = "fs")]
     pub fn train_file<P: AsRef<Path>>(mut self, path: P) -> Result<Self> {
+        self.extend_labels_from(&path)?;
+        if self.tag_dictionary.is_some() {
+            self.tag_dictionary = Some(TagDictionary::new());
+            self.collect_tags(CompressedReader::open(&path)?)?;
+        }
+        self.domains.clear();
+        self.stream_path = None;
+        if let Some(budget) = self.memory_budget {
+            let samples = self.estimate_samples(&path)?;
+            if samples > budget / 2 {
+                if self.verbose {
+                    let mib = samples as f64 / (1024.0 * 1024.0);
+                    println!("train samples need about {:.1} MiB, streaming", mib);
+                }
+                #[cfg(feature = "tracing")]
+                tracing::info!(
+                    samples,
+                    budget,
+                    "train set exceeds memory budget, streaming"
+                );
+                self.train_set = None;
+                self.stream_path = Some(path.as_ref().to_path_buf());
+                return Ok(self);
+            }
+        }
         let dataset = self.load_dataset(path)?;
         self.train_set = Some(dataset);
         Ok(self)
     }
 
+    /// 估算语料文件全部读入内存后训练样本所占的字节数，按第一批样本每字节语料的占用外推
+    #[cfg(feature = "fs")]
+    pub fn estimate_samples<P: AsRef<Path>>(&self, path: P) -> Result<usize> {
+        let mut reader = CompressedReader::open(&path)?;
+        let total = std::io::copy(&mut reader, &mut std::io::sink())?;
+        let mut batches = Batches::new(CompressedReader::open(&path)?);
+        let Some(batch) = batches.next() else {
+            return Ok(0);
+        };
+        let (_, text) = batch?;
+        let samples = self.definition.parse_gold_features(text.as_bytes())?;
+        let bytes: usize = samples.iter().map(sample_bytes).sum();
+        Ok((bytes as f64 * total as f64 / text.len().max(1) as f64) as usize)
+    }
+
+    /// 按批读取语料文件并解析，见 [`Batches`]
+    #[cfg(feature = "fs")]
+    fn read_batches<F>(&self, path: &Path, mut f: F) -> Result<()>
+    where
+        F: FnMut(Vec<Sample>) -> Result<()>,
+    {
+        let batches = Batches::new(CompressedReader::open(path)?);
+        for batch in batches {
+            let (start, text) = batch?;
+            let samples = self
+                .definition
+                .parse_gold_features(text.as_bytes())
+                .with_context(|| format!("batch from line {}", start))?;
+            f(samples)?;
+        }
+        Ok(())
+    }
+
+    /// 流式读取的训练语料中所有不同的特征
+    #[cfg(feature = "fs")]
+    fn streamed_features(&self) -> Result<HashSet<String>> {
+        let mut features = HashSet::new();
+        if let Some(path) = &self.stream_path {
+            self.read_batches(path, |samples| {
+                for feature in samples.iter().flat_map(|sample| sample.features.as_flat()) {
+                    if !features.contains(feature) {
+                        features.insert(feature.clone());
+                    }
+                }
+                Ok(())
+            })?;
+        }
+        Ok(features)
+    }
+
+    /// 以外部排序收集训练集与流式读取的语料中所有不同的特征，按字典序编号插入特征字典，
+    /// 同时返回特征字符串的字节数之和
+    #[cfg(feature = "fs")]
+    fn external_dictionary<Feature>(
+        &self,
+        chunk_bytes: usize,
+        bias: usize,
+    ) -> Result<(Feature, usize)>
+    where
+        Feature: TraitFeaturesTrainUtils + Default,
+    {
+        let dir = self.spill_dir.clone().unwrap_or_else(std::env::temp_dir);
+        let mut sorter = FeatureSorter::new(dir, chunk_bytes)?;
+        if let Some(path) = &self.stream_path {
+            self.read_batches(path, |samples| {
+                for feature in samples.iter().flat_map(|sample| sample.features.as_flat()) {
+                    sorter.push(feature)?;
+                }
+                Ok(())
+            })?;
+        }
+        for sample in self.train_set.iter().flatten() {
+            for feature in sample.features.as_flat() {
+                sorter.push(feature)?;
+            }
+        }
+        if self.verbose {
+            println!("features: {} sorted runs", sorter.runs());
+        }
+
+        let mut features = Feature::default();
+        let mut key_bytes = 0;
+        for (idx, feature) in sorter.finish()?.enumerate() {
+            let feature = feature?;
+            key_bytes += feature.len();
+            features.insert_feature(feature, idx + bias);
+        }
+        Ok((features, key_bytes))
+    }
+
+    /// 一轮训练中依次给出的样本：内存中的训练集按 [`epoch_order`](Self::epoch_order)，
+    /// 流式读取时逐批读入，`shuffle` 时在批内打乱
+    fn for_each_sample<R: Rng, F: FnMut(&Sample)>(&self, rng: &mut R, mut f: F) -> Result<()> {
+        #[cfg(feature = "fs")]
+        if let Some(path) = &self.stream_path {
+            return self.read_batches(path, |mut samples| {
+                if self.shuffle {
+                    samples.shuffle(rng);
+                }
+                samples.iter().for_each(&mut f);
+                Ok(())
+            });
+        }
+        if let Some(train_set) = &self.train_set {
+            for index in self.epoch_order(rng) {
+                f(&train_set[index]);
+            }
+        }
+        Ok(())
+    }
+
+    fn is_streaming(&self) -> bool {
+        #[cfg(feature = "fs")]
+        return self.stream_path.is_some();
+        #[cfg(not(feature = "fs"))]
+        return false;
+    }
+
+    /// 内存中的训练样本与验证样本、特征字典与参数的估算，特征字典按训练集中不同的特征计算；
+    /// 流式读取的训练语料不计入
+    pub fn memory_estimate(&self) -> MemoryEstimate {
+        let mut features = HashSet::new();
+        for sample in self.train_set.iter().flatten() {
+            features.extend(sample.features.as_flat().iter().map(String::as_str));
+        }
+        let key_bytes = features.iter().map(|feature| feature.len()).sum();
+        self.estimate(features.len(), key_bytes)
+    }
+
+    fn estimate(&self, features: usize, key_bytes: usize) -> MemoryEstimate {
+        let samples = [&self.train_set, &self.eval_set]
+            .into_iter()
+            .flatten()
+            .flatten()
+            .map(sample_bytes)
+            .sum();
+        let label_num = self.definition.label_num();
+        let bias = if self.definition.use_viterbi() {
+            label_num * label_num
+        } else {
+            0
+        };
+        MemoryEstimate {
+            samples,
+            features: dictionary_bytes(features, key_bytes),
+            parameters: parameter_bytes::<Param>(bias + features * label_num),
+        }
+    }
+
+    /// 追加一个带采样权重的语料，各语料在每轮中按权重采样后混合打乱
+    pub fn train_domain<N: Into<String>>(
+        mut self,
+        name: N,
+        samples: Vec<Sample>,
+        weight: f64,
+    ) -> Result<Self> {
+        if self.is_streaming() {
+            return Err(anyhow!("cannot add a domain to a streamed train file"));
+        }
+        if !(weight.is_finite() && weight >= 0.0) {
+            return Err(anyhow!(
+                "domain weight must be non-negative, got {}",
+                weight
+            ));
+        }
+        let label_num = self.definition.label_num();
+        for sample in &samples {
+            sample.check(label_num)?;
+        }
+        let train_set = self.train_set.get_or_insert_with(Vec::new);
+        // 之前没有分语料加入的训练集视为权重为 1 的语料
+        let known: usize = self.domains.iter().map(|domain| domain.size).sum();
+        if train_set.len() > known {
+            self.domains.push(Domain {
+                name: "train".to_string(),
+                size: train_set.len() - known,
+                weight: 1.0,
+            });
+        }
+        self.domains.push(Domain {
+            name: name.into(),
+            size: samples.len(),
+            weight,
+        });
+        train_set.extend(samples);
+        Ok(self)
+    }
+
+    #[cfg(feature = "fs")]
+    pub fn train_file_weighted<P: AsRef<Path>>(mut self, path: P, weight: f64) -> Result<Self> {
+        self.extend_labels_from(&path)?;
+        if self.tag_dictionary.is_some() {
+            self.collect_tags(CompressedReader::open(&path)?)?;
+        }
+        let name = path.as_ref().display().to_string();
+        let dataset = self.load_dataset(path)?;
+        self.train_domain(name, dataset, weight)
+    }
+
+    /// 检查训练集与验证集中的每个样本，见 [`Sample::check`]
+    pub fn check_samples(&self) -> Result<()> {
+        let label_num = self.definition.label_num();
+        for (name, samples) in [("train", &self.train_set), ("eval", &self.eval_set)] {
+            for (index, sample) in samples.iter().flatten().enumerate() {
+                sample
+                    .check(label_num)
+                    .map_err(|err| err.context(format!("invalid {} sample {}", name, index)))?;
+            }
+        }
+        Ok(())
+    }
+
+    /// 流式读取训练语料时只能单线程训练
+    fn check_streaming(&self) -> Result<()> {
+        let parallel = match self.algorithm {
+            Algorithm::AP(threads) => threads > 1,
+            Algorithm::PA(_) => false,
+            Algorithm::Hogwild(_) => true,
+        };
+        if self.is_streaming() && parallel {
+            return Err(anyhow!(
+                "streamed training supports only single-threaded AP and PA"
+            ));
+        }
+        Ok(())
+    }
+
+    /// 一轮训练中样本的下标，按语料权重采样，`shuffle` 时打乱
+    pub fn epoch_order<R: Rng>(&self, rng: &mut R) -> Vec<usize> {
+        let total = self.train_set.as_ref().map_or(0, Vec::len);
+        let mut order = Vec::with_capacity(total);
+        let mut start = 0;
+        for domain in &self.domains {
+            let indices: Vec<usize> = (start..start + domain.size).collect();
+            for _ in 0..domain.weight.trunc() as usize {
+                order.extend_from_slice(&indices);
+            }
+            let extra = (domain.weight.fract() * domain.size as f64).round() as usize;
+            order.extend(indices.choose_multiple(rng, extra));
+            start += domain.size;
+        }
+        order.extend(start..total);
+        if self.shuffle {
+            order.shuffle(rng);
+        }
+        order
+    }
+
+    #[cfg(feature = "fs")]
     pub fn eval_file<P: AsRef<Path>>(mut self, path: P) -> Result<Self> {
+        self.extend_labels_from(&path)?;
         let dataset = self.load_dataset(path)?;
         self.eval_set = Some(dataset);
         Ok(self)
     }
 
+    /// 以 [`preprocess`](crate::perceptron::preprocess) 写出的特征缓存为训练集，跳过特征抽取；
+    /// 缓存中没有原始语料，不统计标签字典
+    #[cfg(feature = "fs")]
+    pub fn train_cache<P: AsRef<Path>>(mut self, path: P) -> Result<Self> {
+        self.domains.clear();
+        self.stream_path = None;
+        self.train_set = Some(load_cache(&self.definition, path)?);
+        Ok(self)
+    }
+
+    #[cfg(feature = "fs")]
+    pub fn eval_cache<P: AsRef<Path>>(mut self, path: P) -> Result<Self> {
+        self.eval_set = Some(load_cache(&self.definition, path)?);
+        Ok(self)
+    }
+
     pub fn display(self) -> Self {
         println!("{}", self);
         self
@@ -160,52 +687,215 @@ where
         ParamStorage: TraitParameterStorage<Param> + TraitParameterStorageTrainUtils<Param>,
         Define: Definition,
     {
-        if let Some(eval_set) = &self.eval_set {
-            #[cfg(feature = "parallel")]
-            let result = {
-                let pool = rayon::ThreadPoolBuilder::new()
-                    .num_threads(self.eval_threads)
-                    .build()
-                    .unwrap();
-                pool.install(|| {
-                    eval_set
-                        .par_iter()
-                        .map(|(feature, labels)| model.evaluate(feature, labels))
-                        .reduce_with(
-                            |(a_correct, a_preds, a_labels), (b_correct, b_preds, b_labels)| {
-                                (
-                                    a_correct + b_correct,
-                                    a_preds + b_preds,
-                                    a_labels + b_labels,
-                                )
-                            },
-                        )
-                })
+        let report = self.evaluate_report(model)?;
+        Ok((report.precision(), report.recall(), report.f1()))
+    }
+
+    /// 在验证集上评测，包含准确率与每个标签的指标，没有验证集时为空报告
+    pub fn evaluate_report<Feature, ParamStorage>(
+        &self,
+        model: &Perceptron<Define, Feature, ParamStorage, Param>,
+    ) -> Result<EvalReport>
+    where
+        Feature: TraitFeature,
+        Param: TraitParameter,
+        ParamStorage: TraitParameterStorage<Param> + TraitParameterStorageTrainUtils<Param>,
+        Define: Definition,
+    {
+        let Some(eval_set) = &self.eval_set else {
+            return Ok(EvalReport::default());
+        };
+        let merge = |mut a: EvalReport, b: EvalReport| {
+            a.merge(&b);
+            a
+        };
+        #[cfg(feature = "parallel")]
+        let report = {
+            let pool = self.eval_pool()?;
+            pool.install(|| {
+                eval_set
+                    .par_iter()
+                    .map(|sample| model.evaluate_report(&sample.features, &sample.labels))
+                    .reduce(EvalReport::default, merge)
+            })
+        };
+        #[cfg(not(feature = "parallel"))]
+        let report = eval_set
+            .iter()
+            .map(|sample| model.evaluate_report(&sample.features, &sample.labels))
+            .fold(EvalReport::default(), merge);
+        Ok(report)
+    }
+
+    /// 解码一遍验证集，保存各句的预测结果，见 [`Predictions`]
+    pub fn predictions<Feature, ParamStorage>(
+        &self,
+        model: &Perceptron<Define, Feature, ParamStorage, Param>,
+    ) -> Result<Predictions>
+    where
+        Feature: TraitFeature,
+        Param: TraitParameter,
+        ParamStorage: TraitParameterStorage<Param> + TraitParameterStorageTrainUtils<Param>,
+        Define: Definition,
+    {
+        let Some(eval_set) = &self.eval_set else {
+            return Ok(Predictions::default());
+        };
+        let decode = |sample: &Sample| model.decode(&model.features.get_arena(&sample.features));
+        #[cfg(feature = "parallel")]
+        let labels = {
+            let pool = self.eval_pool()?;
+            pool.install(|| eval_set.par_iter().map(decode).collect())
+        };
+        #[cfg(not(feature = "parallel"))]
+        let labels = eval_set.iter().map(decode).collect();
+        Ok(Predictions::new(labels))
+    }
+
+    /// 在保存的预测结果上评测验证集，与 [`evaluate_report`](Self::evaluate_report) 相同而不必重新解码
+    pub fn evaluate_predictions(&self, predictions: &Predictions) -> Result<EvalReport> {
+        let eval_set = self.eval_set.as_deref().unwrap_or_default();
+        predictions.evaluate_report(&self.definition, eval_set)
+    }
+
+    /// 在验证集上按实体做片段级评测，适用于实体识别等 BIO/BIESO 标注
+    pub fn evaluate_spans<Feature, ParamStorage>(
+        &self,
+        model: &Perceptron<Define, Feature, ParamStorage, Param>,
+    ) -> Result<SpanReport>
+    where
+        Feature: TraitFeature,
+        Param: TraitParameter,
+        ParamStorage: TraitParameterStorage<Param> + TraitParameterStorageTrainUtils<Param>,
+        Define: Definition,
+    {
+        let Some(eval_set) = &self.eval_set else {
+            return Ok(SpanReport::default());
+        };
+        let add = |mut report: SpanReport, sample: &Sample| {
+            model.span_report(&sample.features, &sample.labels, &mut report);
+            report
+        };
+        #[cfg(feature = "parallel")]
+        let report = {
+            let merge = |mut a: SpanReport, b: SpanReport| {
+                a.merge(&b);
+                a
             };
-            #[cfg(not(feature = "parallel"))]
-            let result = eval_set
-                .iter()
-                .map(|(feature, labels)| model.evaluate(feature, labels))
-                .reduce(
-                    |(a_correct, a_preds, a_labels), (b_correct, b_preds, b_labels)| {
-                        (
-                            a_correct + b_correct,
-                            a_preds + b_preds,
-                            a_labels + b_labels,
-                        )
-                    },
-                );
+            let pool = self.eval_pool()?;
+            pool.install(|| {
+                eval_set
+                    .par_iter()
+                    .fold(SpanReport::default, add)
+                    .reduce(SpanReport::default, merge)
+            })
+        };
+        #[cfg(not(feature = "parallel"))]
+        let report = eval_set.iter().fold(SpanReport::default(), add);
+        Ok(report)
+    }
 
-            if let Some((correct, preds_total, labels_total)) = result {
-                let precision = correct as f64 / preds_total as f64;
-                let recall = correct as f64 / labels_total as f64;
-                let f1 = 2.0 * precision * recall / (precision + recall);
-                return Ok((precision, recall, f1));
-            }
+    /// 在验证集上按句长分桶统计指标与逐句的解码耗时，为使耗时可比，逐句单线程解码
+    pub fn evaluate_lengths<Feature, ParamStorage>(
+        &self,
+        model: &Perceptron<Define, Feature, ParamStorage, Param>,
+        mut report: LengthReport,
+    ) -> Result<LengthReport>
+    where
+        Feature: TraitFeature,
+        Param: TraitParameter,
+        ParamStorage: TraitParameterStorage<Param> + TraitParameterStorageTrainUtils<Param>,
+        Define: Definition,
+    {
+        for Sample {
+            features: feature,
+            labels,
+        } in self.eval_set.iter().flatten()
+        {
+            let start = Instant::now();
+            let (correct, predicted, gold) = model.evaluate(feature, labels);
+            let latency = start.elapsed().as_micros() as u64;
+            let score = LabelScore {
+                correct,
+                predicted,
+                gold,
+            };
+            report.add(labels.len(), score, latency);
         }
-        Ok((0.0, 0.0, 0.0))
+        Ok(report)
+    }
+
+    /// 在验证集上按预测置信度分箱统计逐词的准确率
+    pub fn calibration<Feature, ParamStorage>(
+        &self,
+        model: &Perceptron<Define, Feature, ParamStorage, Param>,
+        bins: usize,
+    ) -> Result<CalibrationReport>
+    where
+        Feature: TraitFeature,
+        Param: TraitParameter,
+        ParamStorage: TraitParameterStorage<Param> + TraitParameterStorageTrainUtils<Param>,
+        Define: Definition,
+    {
+        let empty = || CalibrationReport::new(bins);
+        let Some(eval_set) = &self.eval_set else {
+            return Ok(empty());
+        };
+        let add = |mut report: CalibrationReport, sample: &Sample| {
+            model.calibration(&sample.features, &sample.labels, &mut report);
+            report
+        };
+        #[cfg(feature = "parallel")]
+        let report = {
+            let merge = |mut a: CalibrationReport, b: CalibrationReport| {
+                a.merge(&b);
+                a
+            };
+            let pool = self.eval_pool()?;
+            pool.install(|| eval_set.par_iter().fold(empty, add).reduce(empty, merge))
+        };
+        #[cfg(not(feature = "parallel"))]
+        let report = eval_set.iter().fold(empty(), add);
+        Ok(report)
+    }
+
+    /// 在验证集上统计逐词的混淆矩阵
+    pub fn confusion_matrix<Feature, ParamStorage>(
+        &self,
+        model: &Perceptron<Define, Feature, ParamStorage, Param>,
+    ) -> Result<ConfusionMatrix>
+    where
+        Feature: TraitFeature,
+        Param: TraitParameter,
+        ParamStorage: TraitParameterStorage<Param> + TraitParameterStorageTrainUtils<Param>,
+        Define: Definition,
+    {
+        let empty = || ConfusionMatrix::new(model.definition.labels());
+        let Some(eval_set) = &self.eval_set else {
+            return Ok(empty());
+        };
+        let add = |mut matrix: ConfusionMatrix, sample: &Sample| {
+            model.confusion(&sample.features, &sample.labels, &mut matrix);
+            matrix
+        };
+        #[cfg(feature = "parallel")]
+        let matrix = {
+            let merge = |mut a: ConfusionMatrix, b: ConfusionMatrix| {
+                a.merge(&b);
+                a
+            };
+            let pool = self.eval_pool()?;
+            pool.install(|| eval_set.par_iter().fold(empty, add).reduce(empty, merge))
+        };
+        #[cfg(not(feature = "parallel"))]
+        let matrix = eval_set.iter().fold(empty(), add);
+        Ok(matrix)
     }
 
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(skip_all, fields(algorithm = %self.algorithm, epochs = self.epoch))
+    )]
     pub fn build<Feature, ParamStorage>(
         &self,
     ) -> Result<Perceptron<Define, Feature, ParamStorage, Param>>
@@ -224,31 +914,82 @@ where
             + Sync
             + 'static,
     {
-        let mut features_set = HashSet::new();
-        if let Some(train_set) = &self.train_set {
-            for (sentence_features, _sentence_labels) in train_set {
-                for word_features in sentence_features {
-                    for features in word_features {
-                        features_set.insert(features.to_owned());
-                    }
-                }
-            }
-        }
-
+        self.check_samples()?;
+        self.check_streaming()?;
         let bias = if self.definition.use_viterbi() {
             // transition part of viterbi
             self.definition.label_num()
         } else {
             0
         };
+        #[cfg(feature = "fs")]
+        if let Some(chunk_bytes) = self.external_features {
+            let (features, key_bytes) = self.external_dictionary::<Feature>(chunk_bytes, bias)?;
+            return self.build_with_features(features, key_bytes);
+        }
 
+        #[cfg(feature = "fs")]
+        let streamed = self.streamed_features()?;
+        #[cfg(not(feature = "fs"))]
+        let streamed = HashSet::<String>::new();
+        let mut features_set: HashSet<&str> = streamed.iter().map(String::as_str).collect();
+        if let Some(train_set) = &self.train_set {
+            for Sample {
+                features: sentence_features,
+                ..
+            } in train_set
+            {
+                for features in sentence_features.as_flat() {
+                    features_set.insert(features.as_str());
+                }
+            }
+        }
+
+        let key_bytes = features_set.iter().map(|feature| feature.len()).sum();
         let mut features = Feature::default();
         for (feature, id) in features_set
             .into_iter()
             .enumerate()
             .map(|(idx, feature)| (feature, idx + bias))
         {
-            features.insert_feature(feature, id);
+            features.insert_feature(feature.to_string(), id);
+        }
+        self.build_with_features(features, key_bytes)
+    }
+
+    /// 在构建好的特征字典上训练，`key_bytes` 为特征字符串的字节数之和，用于检查内存预算
+    fn build_with_features<Feature, ParamStorage>(
+        &self,
+        features: Feature,
+        key_bytes: usize,
+    ) -> Result<Perceptron<Define, Feature, ParamStorage, Param>>
+    where
+        ParamStorage: TraitParameterStorage<Param>
+            + TraitParameterStorageTrainUtils<Param>
+            + TraitParameterStorageCompressUtils<Param>
+            + Send
+            + Sync
+            + 'static,
+        Feature: TraitFeature
+            + TraitFeaturesTrainUtils
+            + TraitFeatureCompressUtils
+            + ToOwned<Owned = Feature>
+            + Send
+            + Sync
+            + 'static,
+    {
+        if let Some(budget) = self.memory_budget {
+            let estimate = self.estimate(features.feature_num(), key_bytes);
+            if self.verbose {
+                println!("memory: {}", estimate);
+            }
+            if estimate.total() > budget {
+                return Err(anyhow!(
+                    "estimated memory ({}) exceeds the budget of {} bytes",
+                    estimate,
+                    budget
+                ));
+            }
         }
 
         let model = match &self.algorithm {
@@ -261,6 +1002,7 @@ where
                 }
             }
             Algorithm::PA(mode) => self.build_pa(features, mode)?,
+            Algorithm::Hogwild(threads) => self.build_hogwild(features, *threads)?,
         };
 
         let model = if self.compress {
@@ -268,12 +1010,110 @@ where
             let (p, r, f1) = self.evaluate(&model)?;
 
             println!("Compressed: precision: {}, recall: {}, f1: {}", p, r, f1);
+            #[cfg(feature = "tracing")]
+            tracing::info!(
+                features = model.features.feature_num(),
+                precision = p,
+                recall = r,
+                f1,
+                "model compressed"
+            );
             model
         } else {
             model
         };
 
-        Ok(model)
+        let metadata = self.metadata(model.features.feature_num());
+        Ok(model.with_metadata(metadata))
+    }
+
+    /// 按 `fractions` 依次取训练集的一部分训练并在验证集上评测，各部分取自同一个随机排列的前缀，
+    /// 因而较小的部分总包含于较大的部分；训练时忽略多语料的采样权重
+    pub fn learning_curve<Feature, ParamStorage>(
+        &mut self,
+        fractions: &[f64],
+        seed: u64,
+    ) -> Result<Vec<CurvePoint>>
+    where
+        ParamStorage: TraitParameterStorage<Param>
+            + TraitParameterStorageTrainUtils<Param>
+            + TraitParameterStorageCompressUtils<Param>
+            + Send
+            + Sync
+            + 'static,
+        Feature: TraitFeature
+            + TraitFeaturesTrainUtils
+            + TraitFeatureCompressUtils
+            + ToOwned<Owned = Feature>
+            + Send
+            + Sync
+            + 'static,
+    {
+        if self.eval_set.is_none() {
+            return Err(anyhow!("learning curve requires an eval set"));
+        }
+        if let Some(fraction) = fractions.iter().find(|f| !(**f > 0.0 && **f <= 1.0)) {
+            return Err(anyhow!("fraction {} is not in (0, 1]", fraction));
+        }
+        let train_set = self.train_set.take().unwrap_or_default();
+        let domains = std::mem::take(&mut self.domains);
+        let subsets = curve_subsets(train_set.len(), fractions, seed);
+
+        let mut curve = || -> Result<Vec<CurvePoint>> {
+            let mut points = Vec::with_capacity(fractions.len());
+            for (&fraction, subset) in fractions.iter().zip(&subsets) {
+                let subset = subset.iter().map(|&i| train_set[i].clone());
+                self.train_set = Some(subset.collect());
+                let model = self.build::<Feature, ParamStorage>()?;
+                let (precision, recall, f1) = self.evaluate(&model)?;
+                points.push(CurvePoint {
+                    fraction,
+                    sentences: self.train_set.as_ref().map_or(0, Vec::len),
+                    features: model.features.feature_num(),
+                    precision,
+                    recall,
+                    f1,
+                });
+            }
+            Ok(points)
+        };
+        let points = curve();
+        self.train_set = Some(train_set);
+        self.domains = domains;
+        points
+    }
+
+    /// 使用特征哈希训练，参数表有 `2^bits` 个特征槽，不保存特征字典，也不做模型压缩
+    pub fn build_hashed<ParamStorage>(
+        &self,
+        bits: u8,
+    ) -> Result<Perceptron<Define, HashedFeatures, ParamStorage, Param>>
+    where
+        ParamStorage: TraitParameterStorage<Param>
+            + TraitParameterStorageTrainUtils<Param>
+            + Send
+            + Sync
+            + 'static,
+    {
+        self.check_samples()?;
+        self.check_streaming()?;
+        let offset = if self.definition.use_viterbi() {
+            // transition part of viterbi
+            self.definition.label_num()
+        } else {
+            0
+        };
+        let features = HashedFeatures::new(bits, offset);
+
+        let model = match &self.algorithm {
+            Algorithm::AP(threads) if *threads > 1 => self.build_ap_parallel(features, *threads)?,
+            Algorithm::AP(_) => self.build_ap(features)?,
+            Algorithm::PA(mode) => self.build_pa(features, mode)?,
+            Algorithm::Hogwild(threads) => self.build_hogwild(features, *threads)?,
+        };
+
+        let metadata = self.metadata(features.feature_num());
+        Ok(model.with_metadata(metadata))
     }
 
     pub fn build_ap<Feature, ParamStorage>(
@@ -302,20 +1142,17 @@ where
         let mut best_f1 = f64::neg_infinity();
         let mut best_parameters = ParamStorage::default();
 
-        if let Some(train_set) = &self.train_set {
+        if self.train_set.is_some() || self.is_streaming() {
             let mut rng = rand::thread_rng();
             let mut current = 0;
             let mut total = vec![Param::zero(); parameters_len];
             let mut timestamp = vec![0; parameters_len];
-            let mut train_set = train_set.clone();
             for epoch in 0..self.epoch {
-                if self.shuffle {
-                    train_set.shuffle(&mut rng);
-                }
-                for (feature, labels) in train_set.iter() {
+                self.for_each_sample(&mut rng, |sample| {
                     current += 1;
+                    let (feature, labels) = (&sample.features, &sample.labels);
                     perceptron.ap_train_iter(feature, labels, &mut total, &mut timestamp, current);
-                }
+                })?;
 
                 let backup = perceptron.parameters.clone();
                 perceptron.average(&total, &timestamp, current);
@@ -326,6 +1163,8 @@ where
                     "epoch: {}, precision: {}, recall: {}, f1: {}",
                     epoch, p, r, f1
                 );
+                #[cfg(feature = "tracing")]
+                tracing::info!(epoch, precision = p, recall = r, f1, "epoch finished");
 
                 if f1 > best_f1 {
                     best_f1 = f1;
@@ -364,27 +1203,23 @@ where
         let mut best_f1 = f64::neg_infinity();
         let mut best_parameters = ParamStorage::default();
 
-        if let Some(train_set) = &self.train_set {
+        if self.train_set.is_some() || self.is_streaming() {
             let mut rng = rand::thread_rng();
             let mut current = 0;
             let mut total = vec![Param::zero(); parameters_len];
             let mut timestamp = vec![0; parameters_len];
-            let mut train_set = train_set.clone();
             for epoch in 0..self.epoch {
-                if self.shuffle {
-                    train_set.shuffle(&mut rng);
-                }
-                for (feature, labels) in train_set.iter() {
+                self.for_each_sample(&mut rng, |sample| {
                     current += 1;
                     perceptron.pa_train_iter(
-                        feature,
-                        labels,
+                        &sample.features,
+                        &sample.labels,
                         &mut total,
                         &mut timestamp,
                         current,
                         pa_mode,
                     );
-                }
+                })?;
 
                 let backup = perceptron.parameters.clone();
                 perceptron.average(&total, &timestamp, current);
@@ -395,6 +1230,8 @@ where
                     "epoch: {}, precision: {}, recall: {}, f1: {}",
                     epoch, p, r, f1
                 );
+                #[cfg(feature = "tracing")]
+                tracing::info!(epoch, precision = p, recall = r, f1, "epoch finished");
 
                 if f1 > best_f1 {
                     best_f1 = f1;
@@ -420,7 +1257,6 @@ where
             + 'static,
         Feature: TraitFeature
             + TraitFeaturesTrainUtils
-            + TraitFeatureCompressUtils
             + ToOwned<Owned = Feature>
             + Send
             + Sync
@@ -443,18 +1279,17 @@ where
         let mut parameters = vec![ParamStorage::init(Param::zero(), parameters_len); threads];
 
         if let Some(train_set) = &self.train_set {
-            let chunk_size = (train_set.len() as f64 / threads as f64) as usize + 1;
-            let train_set = Arc::new(RwLock::new(train_set.clone()));
+            let mut rng = rand::thread_rng();
+            let train_set = Arc::new(train_set.clone());
 
             for epoch in 0..self.epoch {
-                if self.shuffle {
-                    let mut rng = rand::thread_rng();
-                    train_set.write().unwrap().shuffle(&mut rng);
-                }
+                let order = Arc::new(self.epoch_order(&mut rng));
+                let chunk_size = (order.len() as f64 / threads as f64) as usize + 1;
                 let mut children = vec![];
                 for thread in 0..threads {
                     let clone_feature = Arc::clone(&features);
                     let train_set_clone = Arc::clone(&train_set);
+                    let order = Arc::clone(&order);
                     let definition = self.definition.clone();
                     let parameters = parameters.pop().unwrap();
                     children.push(thread::spawn(move || -> ParamStorage {
@@ -463,9 +1298,12 @@ where
                             clone_feature.deref(),
                             parameters,
                         );
-                        let shared = train_set_clone.read().unwrap();
-                        if let Some(chunk) = shared.chunks(chunk_size).nth(thread) {
-                            for (feature, labels) in chunk {
+                        if let Some(chunk) = order.chunks(chunk_size).nth(thread) {
+                            for &index in chunk {
+                                let Sample {
+                                    features: feature,
+                                    labels,
+                                } = &train_set_clone[index];
                                 perceptron.ap_train_parallel_iter(feature, labels);
                             }
                         };
@@ -501,6 +1339,8 @@ where
                     "epoch: {}, precision: {}, recall: {}, f1: {}",
                     epoch, p, r, f1
                 );
+                #[cfg(feature = "tracing")]
+                tracing::info!(epoch, precision = p, recall = r, f1, "epoch finished");
                 if f1 > best_f1 {
                     best_parameters = perceptron.parameters;
                     best_f1 = f1;
@@ -541,6 +1381,10 @@ where
         if let Some(train_set) = &self.train_set {
             writeln!(f, "  train_set: {}", train_set.len())?;
         }
+        #[cfg(feature = "fs")]
+        if let Some(path) = &self.stream_path {
+            writeln!(f, "  stream: {}", path.display())?;
+        }
         if let Some(eval_set) = &self.eval_set {
             writeln!(f, "  eval_set: {}", eval_set.len())?;
         }
@@ -548,3 +1392,187 @@ where
         Ok(())
     }
 }
+
+/// 学习曲线各点使用的训练句子下标，均取自同一个随机排列的前缀，每点至少一句
+fn curve_subsets(len: usize, fractions: &[f64], seed: u64) -> Vec<Vec<usize>> {
+    let mut order: Vec<usize> = (0..len).collect();
+    order.shuffle(&mut StdRng::seed_from_u64(seed));
+    fractions
+        .iter()
+        .map(|fraction| {
+            let size = ((len as f64 * fraction).round() as usize).max(1);
+            order.iter().take(size).copied().collect()
+        })
+        .collect()
+}
+
+#[cfg(all(test, feature = "pos"))]
+mod tests {
+    use super::*;
+    use crate::error::LtpError;
+    use crate::perceptron::{write_curve, FeatureArena, POSDefinition, UNCERTAIN};
+
+    fn samples(n: usize) -> Vec<Sample> {
+        (0..n)
+            .map(|i| Sample::new(FeatureArena::from(vec![vec![]]), vec![i % 2]))
+            .collect()
+    }
+
+    #[test]
+    fn test_epoch_order() -> Result<()> {
+        let define = POSDefinition::new(vec!["r".into(), "v".into()]);
+        let trainer: Trainer<POSDefinition> = Trainer::new_with_define(define)
+            .shuffle(false)
+            .train_domain("news", samples(4), 1.0)?
+            .train_domain("social", samples(2), 2.5)?;
+        let order = trainer.epoch_order(&mut StdRng::seed_from_u64(0));
+        // 新闻 4 条，社交媒体遍历两次再抽一条
+        assert_eq!(order.len(), 4 + 2 * 2 + 1);
+        assert_eq!(&order[..8], &[0, 1, 2, 3, 4, 5, 4, 5]);
+        assert!(order[8] >= 4);
+        assert!(trainer.metadata(0).training["domains"].contains("social:2.5"));
+
+        let trainer = trainer.train_domain("bad", samples(1), -1.0);
+        assert!(trainer.is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_check_samples() {
+        let define = POSDefinition::new(vec!["r".into(), "v".into()]);
+        let trainer: Trainer<POSDefinition> = Trainer::new_with_define(define);
+        let mismatch = Sample {
+            features: FeatureArena::from(vec![vec!["2他".to_string()]]),
+            labels: vec![0, 1],
+        };
+        let error = trainer
+            .clone()
+            .train_domain("mismatch", vec![mismatch], 1.0)
+            .unwrap_err();
+        assert_eq!(
+            error.downcast_ref::<LtpError>(),
+            Some(&LtpError::LengthMismatch(1, 2))
+        );
+
+        let mut trainer = trainer.train_domain("ok", samples(2), 1.0).unwrap();
+        trainer.eval_set = Some(vec![Sample::new(
+            FeatureArena::from(vec![vec![], vec![]]),
+            vec![UNCERTAIN, 2],
+        )]);
+        let error = trainer.check_samples().unwrap_err();
+        assert!(error.to_string().contains("invalid eval sample 0"));
+        assert_eq!(
+            error.downcast_ref::<LtpError>(),
+            Some(&LtpError::LabelOutOfRange(2, 2))
+        );
+        assert!(Sample::try_new(FeatureArena::default(), vec![0]).is_err());
+    }
+
+    #[test]
+    fn test_curve_subsets() {
+        let subsets = curve_subsets(10, &[0.25, 0.5, 1.0], 7);
+        let sizes: Vec<usize> = subsets.iter().map(Vec::len).collect();
+        assert_eq!(sizes, [3, 5, 10]);
+        // 较小的部分是较大部分的前缀，且不依赖 fractions 的顺序
+        assert_eq!(subsets[0], subsets[1][..3]);
+        assert_eq!(subsets[1], subsets[2][..5]);
+        assert_eq!(curve_subsets(10, &[1.0, 0.25], 7)[1], subsets[0]);
+        assert_eq!(curve_subsets(1, &[0.1], 7), [vec![0]]);
+    }
+
+    #[test]
+    fn test_learning_curve() -> Result<()> {
+        use std::collections::HashMap;
+
+        let define = POSDefinition::new(vec!["r".into(), "v".into()]);
+        let samples = |n: usize| -> Vec<Sample> {
+            (0..n)
+                .map(|i| {
+                    let features = vec![vec![format!("2{}", i)]];
+                    Sample::new(FeatureArena::from(features), vec![i % 2])
+                })
+                .collect()
+        };
+        let mut trainer: Trainer<POSDefinition> = Trainer::new_with_define(define)
+            .epoch(1)
+            .verbose(false)
+            .compress(false)
+            .train_domain("news", samples(8), 1.0)?;
+        let fractions = [0.25, 0.5, 1.0];
+        assert!(trainer
+            .learning_curve::<HashMap<String, usize>, Vec<f64>>(&fractions, 0)
+            .is_err());
+
+        trainer.eval_set = Some(samples(4));
+        let points = trainer.learning_curve::<HashMap<String, usize>, Vec<f64>>(&fractions, 0)?;
+        let sentences: Vec<usize> = points.iter().map(|p| p.sentences).collect();
+        assert_eq!(sentences, [2, 4, 8]);
+        // 每句一个独有的特征
+        let features: Vec<usize> = points.iter().map(|p| p.features).collect();
+        assert_eq!(features, [2, 4, 8]);
+        assert_eq!(trainer.train_set.as_ref().map(Vec::len), Some(8));
+        assert!(trainer
+            .learning_curve::<HashMap<String, usize>, Vec<f64>>(&[0.0], 0)
+            .is_err());
+
+        let mut csv = Vec::new();
+        write_curve(&mut csv, &points)?;
+        let csv = String::from_utf8(csv)?;
+        let mut lines = csv.lines();
+        assert_eq!(
+            lines.next(),
+            Some("fraction,sentences,features,precision,recall,f1")
+        );
+        let row: Vec<&str> = lines.next().unwrap().split(',').collect();
+        assert_eq!(&row[..3], ["0.25", "2", "2"]);
+        assert_eq!(row.len(), 6);
+        assert_eq!(lines.count(), 2);
+        Ok(())
+    }
+
+    #[cfg(feature = "fs")]
+    #[test]
+    fn test_memory_budget() -> Result<()> {
+        use std::collections::HashMap;
+
+        let path = std::env::temp_dir().join(format!("ltp-stream-{}.txt", std::process::id()));
+        let corpus = "他/r 喜欢/v 花/n\n她/r 花/v 钱/n\n花/n 开/v\n".repeat(200);
+        std::fs::write(&path, corpus)?;
+        let define = POSDefinition::new(vec!["n".into(), "v".into(), "r".into()]);
+        let trainer: Trainer<POSDefinition> = Trainer::new_with_define(define)
+            .epoch(2)
+            .shuffle(false)
+            .verbose(false)
+            .compress(false);
+
+        let memory = trainer.clone().train_file(&path)?;
+        let estimate = memory.memory_estimate();
+        let budget = estimate.features + estimate.parameters;
+        assert!(memory.estimate_samples(&path)? > budget / 2);
+        assert!(estimate.samples > 0);
+
+        let streamed = trainer.clone().memory_budget(budget).train_file(&path)?;
+        assert!(streamed.train_set.is_none());
+        assert_eq!(streamed.stream_path.as_deref(), Some(path.as_path()));
+        let expected = memory.build::<HashMap<String, usize>, Vec<f64>>()?;
+        let model = streamed.build::<HashMap<String, usize>, Vec<f64>>()?;
+        assert_eq!(model.metadata.training["streaming"], "true");
+        assert_eq!(model.features.len(), expected.features.len());
+        for words in [&["她", "花", "钱"][..], &["花", "开"]] {
+            assert_eq!(model.predict(words)?, expected.predict(words)?);
+        }
+
+        let parallel = streamed.clone().algorithm(Algorithm::Hogwild(2));
+        assert!(parallel
+            .build::<HashMap<String, usize>, Vec<f64>>()
+            .is_err());
+        let error = trainer
+            .memory_budget(1)
+            .train_file(&path)?
+            .build::<HashMap<String, usize>, Vec<f64>>()
+            .unwrap_err();
+        assert!(error.to_string().contains("exceeds the budget"));
+        std::fs::remove_file(&path)?;
+        Ok(())
+    }
+}
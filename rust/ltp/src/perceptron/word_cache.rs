@@ -0,0 +1,79 @@
+//! 高频词的词内特征缓存
+//!
+//! 词性标注的特征中，w[0]、首尾字、长度、前缀与后缀只与词本身有关，对同一个词总是相同的。
+//! [`WordFeatureCache`] 在加载模型后为出现次数最多的若干个词预先查好这些特征的编号，
+//! 预测时这些词只抽取上下文特征，词内特征直接取缓存中的编号；真实文本中高频词占了大部分，
+//! 可以省去相当一部分格式化与查表的开销。
+use std::collections::HashMap;
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct WordFeatureCache {
+    /// 规范化后的词到其词内特征的编号，不在特征表中的特征已被丢弃
+    words: HashMap<String, Vec<usize>>,
+}
+
+impl WordFeatureCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn insert(&mut self, word: String, features: Vec<usize>) {
+        self.words.insert(word, features);
+    }
+
+    pub fn get(&self, word: &str) -> Option<&[usize]> {
+        self.words.get(word).map(Vec::as_slice)
+    }
+
+    pub fn contains(&self, word: &str) -> bool {
+        self.words.contains_key(word)
+    }
+
+    pub fn len(&self) -> usize {
+        self.words.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.words.is_empty()
+    }
+}
+
+#[cfg(all(test, feature = "pos"))]
+mod tests {
+    use super::*;
+    use crate::perceptron::{Definition, DigitFold, Normalizers, TagDictionary, Trainer};
+    use crate::POSDefinition;
+    use anyhow::Result;
+
+    #[test]
+    fn test_word_feature_cache() -> Result<()> {
+        let labels = vec!["n".into(), "v".into(), "r".into(), "m".into()];
+        let define =
+            POSDefinition::new(labels).with_normalizers(Normalizers::new().with(DigitFold));
+        let corpus = "他/r 喜欢/v 花/n\n她/r 花/v 钱/n 3/m\n花/n 开/v\n";
+        let mut trainer: Trainer<POSDefinition> = Trainer::new_with_define(define.clone())
+            .epoch(3)
+            .verbose(false)
+            .compress(false)
+            .tag_dictionary(true);
+        trainer.train_set = Some(define.parse_gold_features(corpus.as_bytes())?);
+        trainer.collect_tags(corpus.as_bytes())?;
+        let model = trainer.build::<HashMap<String, usize>, Vec<f64>>()?;
+
+        let tags: &TagDictionary = &model.metadata.tag_dictionary;
+        assert_eq!(tags.most_frequent(2), vec!["花", "3"]);
+        let cache = model.word_cache(tags.most_frequent(10).into_iter().chain(["7"]))?;
+        // 键为规范化后的词
+        assert!(cache.contains("0"));
+        assert_eq!(cache.get("花").map(<[usize]>::len), Some(5));
+
+        for words in [&["她", "花", "钱"][..], &["花", "新词", "7", "开"], &["钱"]] {
+            assert_eq!(model.predict_cached(words, &cache)?, model.predict(words)?);
+        }
+        assert_eq!(
+            model.predict_cached(&["花"], &WordFeatureCache::new())?,
+            model.predict(&["花"])?
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,257 @@
+//! 无需反序列化即可直接使用的模型格式
+//!
+//! 模型以 rkyv 归档保存，加载时只校验字节，特征表与参数直接引用底层缓冲区。
+use crate::perceptron::{Definition, Perceptron, TraitFeature, TraitParameterStorageUtils};
+#[cfg(feature = "cws")]
+use crate::CWSDefinition;
+#[cfg(feature = "ner")]
+use crate::NERDefinition;
+#[cfg(feature = "pos")]
+use crate::POSDefinition;
+use anyhow::{anyhow, Result};
+pub use rkyv::AlignedVec;
+use rkyv::{Archive, Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt::{Debug, Formatter};
+use std::io::{Read, Write};
+use std::ops::Index;
+
+#[derive(Archive, Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[archive(check_bytes)]
+pub struct FeatureEntry {
+    pub key: String,
+    pub index: u32,
+}
+
+/// 扁平化的模型，特征按 key 排序以便在归档上二分查找
+#[derive(Archive, Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[archive(check_bytes)]
+pub struct ZeroCopyModel {
+    pub task: String,
+    pub labels: Vec<String>,
+    pub features: Vec<FeatureEntry>,
+    pub parameters: Vec<f64>,
+}
+
+#[derive(Clone, Copy)]
+pub struct ArchivedFeatures<'a> {
+    entries: &'a [ArchivedFeatureEntry],
+}
+
+impl Debug for ArchivedFeatures<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ArchivedFeatures")
+            .field("len", &self.entries.len())
+            .finish()
+    }
+}
+
+impl TraitFeature for ArchivedFeatures<'_> {
+    fn get_with_key(&self, key: &str) -> Option<usize> {
+        self.entries
+            .binary_search_by(|entry| entry.key.as_str().cmp(key))
+            .ok()
+            .map(|idx| self.entries[idx].index as usize)
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct ArchivedParameters<'a> {
+    parameters: &'a [f64],
+}
+
+impl Index<usize> for ArchivedParameters<'_> {
+    type Output = f64;
+
+    fn index(&self, index: usize) -> &Self::Output {
+        &self.parameters[index]
+    }
+}
+
+impl TraitParameterStorageUtils for ArchivedParameters<'_> {
+    fn len(&self) -> usize {
+        self.parameters.len()
+    }
+}
+
+pub type ZeroCopyPerceptron<'a, Define> =
+    Perceptron<Define, ArchivedFeatures<'a>, ArchivedParameters<'a>, f64>;
+
+/// 可以从标签列表重建的任务定义
+pub trait ZeroCopyDefinition: Definition {
+    const TASK: &'static str;
+
+    fn from_labels(labels: Vec<String>) -> Self;
+}
+
+#[cfg(feature = "cws")]
+impl ZeroCopyDefinition for CWSDefinition {
+    const TASK: &'static str = "cws";
+
+    fn from_labels(_labels: Vec<String>) -> Self {
+        CWSDefinition::new()
+    }
+}
+
+#[cfg(feature = "pos")]
+impl ZeroCopyDefinition for POSDefinition {
+    const TASK: &'static str = "pos";
+
+    fn from_labels(labels: Vec<String>) -> Self {
+        POSDefinition::new(labels)
+    }
+}
+
+#[cfg(feature = "ner")]
+impl ZeroCopyDefinition for NERDefinition {
+    const TASK: &'static str = "ner";
+
+    fn from_labels(labels: Vec<String>) -> Self {
+        NERDefinition::new(labels)
+    }
+}
+
+impl<Define> Perceptron<Define, HashMap<String, usize>, Vec<f64>, f64>
+where
+    Define: ZeroCopyDefinition,
+{
+    /// 特征下标须能用 `u32` 表示
+    pub fn to_zero_copy(&self) -> Result<ZeroCopyModel> {
+        let mut features = self
+            .features
+            .iter()
+            .map(|(key, &index)| {
+                let index = u32::try_from(index)
+                    .map_err(|_| anyhow!("feature index {} overflows u32", index))?;
+                Ok(FeatureEntry {
+                    key: key.clone(),
+                    index,
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+        features.sort_unstable_by(|a, b| a.key.cmp(&b.key));
+
+        Ok(ZeroCopyModel {
+            task: Define::TASK.to_string(),
+            labels: self.definition.labels(),
+            features,
+            parameters: self.parameters.clone(),
+        })
+    }
+
+    pub fn save_zero_copy<W: Write>(&self, mut writer: W) -> Result<()> {
+        let bytes = to_zero_copy_bytes(&self.to_zero_copy()?)?;
+        writer.write_all(&bytes)?;
+        Ok(())
+    }
+}
+
+pub fn to_zero_copy_bytes(model: &ZeroCopyModel) -> Result<AlignedVec> {
+    rkyv::to_bytes::<_, 4096>(model).map_err(|e| anyhow!("{}", e))
+}
+
+/// 读取到对齐的缓冲区，归档中的 `f64` 要求 8 字节对齐
+pub fn read_aligned<R: Read>(mut reader: R) -> Result<AlignedVec> {
+    let mut bytes = AlignedVec::new();
+    bytes.extend_from_reader(&mut reader)?;
+    Ok(bytes)
+}
+
+/// 校验 `bytes` 并返回借用该缓冲区的模型，不复制特征与参数
+pub fn load_zero_copy<Define: ZeroCopyDefinition>(
+    bytes: &[u8],
+) -> Result<ZeroCopyPerceptron<'_, Define>> {
+    let archived = rkyv::check_archived_root::<ZeroCopyModel>(bytes)
+        .map_err(|e| anyhow!("invalid zero-copy model: {}", e))?;
+
+    if archived.task.as_str() != Define::TASK {
+        return Err(anyhow!(
+            "this is a {} model, expected {}",
+            archived.task.as_str(),
+            Define::TASK
+        ));
+    }
+
+    let labels = archived.labels.iter().map(|l| l.to_string()).collect();
+    Ok(Perceptron::new_with_parameters(
+        Define::from_labels(labels),
+        ArchivedFeatures {
+            entries: archived.features.as_slice(),
+        },
+        ArchivedParameters {
+            parameters: archived.parameters.as_slice(),
+        },
+    ))
+}
+
+impl<Define: ZeroCopyDefinition> ZeroCopyPerceptron<'static, Define> {
+    /// 从程序内嵌的字节加载模型，通常配合 [`include_model!`](crate::include_model) 使用。
+    ///
+    /// 已对齐时不复制；未对齐（例如直接使用 `include_bytes!`）时复制一次到对齐的缓冲区，
+    /// 该缓冲区在程序退出前不会释放。
+    pub fn from_static(bytes: &'static [u8]) -> Result<Self> {
+        if (bytes.as_ptr() as usize).is_multiple_of(AlignedVec::ALIGNMENT) {
+            return load_zero_copy(bytes);
+        }
+        let mut aligned = AlignedVec::with_capacity(bytes.len());
+        aligned.extend_from_slice(bytes);
+        let aligned: &'static AlignedVec = Box::leak(Box::new(aligned));
+        load_zero_copy(aligned.as_slice())
+    }
+}
+
+/// 以 16 字节对齐的方式内嵌模型文件，得到 `&'static [u8]`
+///
+/// ```ignore
+/// let model: ZeroCopyPerceptron<CWSDefinition> =
+///     ZeroCopyPerceptron::from_static(ltp::include_model!("cws.rkyv"))?;
+/// ```
+#[macro_export]
+macro_rules! include_model {
+    ($path:expr) => {{
+        #[repr(C, align(16))]
+        struct __Aligned<T: ?Sized>(T);
+        static __ALIGNED: &__Aligned<[u8]> = &__Aligned(*include_bytes!($path));
+        &__ALIGNED.0
+    }};
+}
+
+#[cfg(all(test, feature = "cws", feature = "pos"))]
+mod tests {
+    use super::*;
+    use crate::perceptron::cws_test_model;
+
+    #[test]
+    fn test_round_trip() -> Result<()> {
+        let model = cws_test_model(&["2我", "2们", "6我们"], |i| (i % 7) as f64 - 3.0);
+
+        let mut bytes = Vec::new();
+        model.save_zero_copy(&mut bytes)?;
+        let bytes = read_aligned(bytes.as_slice())?;
+
+        let view: ZeroCopyPerceptron<CWSDefinition> = load_zero_copy(&bytes)?;
+        assert_eq!(view.check_feature("6我们"), Some(6));
+        assert_eq!(view.predict("我们我")?, model.predict("我们我")?);
+
+        assert!(load_zero_copy::<POSDefinition>(&bytes).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_from_static() -> Result<()> {
+        let model = cws_test_model(&["2我"], |i| i as f64);
+        let bytes = to_zero_copy_bytes(&model.to_zero_copy()?)?;
+
+        // 故意错开一个字节，模拟 include_bytes! 得到的未对齐数据
+        let mut shifted = vec![0u8; bytes.len() + 1];
+        shifted[1..].copy_from_slice(&bytes);
+        let shifted: &'static [u8] = &Box::leak(shifted.into_boxed_slice())[1..];
+
+        let view = ZeroCopyPerceptron::<CWSDefinition>::from_static(shifted)?;
+        assert_eq!(view.predict("我我")?, model.predict("我我")?);
+
+        let embedded: &'static [u8] = crate::include_model!("mod.rs");
+        assert!((embedded.as_ptr() as usize).is_multiple_of(AlignedVec::ALIGNMENT));
+        Ok(())
+    }
+}
@@ -0,0 +1,121 @@
+//! Arrow 列式批量接口，可以直接对接 Polars、DataFusion 等
+use crate::pipeline::LtpPipeline;
+use anyhow::Result;
+use arrow_array::builder::{ListBuilder, StringBuilder};
+use arrow_array::{Array, ArrayRef, StringArray, StructArray};
+use arrow_schema::Field;
+use std::sync::Arc;
+
+struct LabelColumn {
+    name: &'static str,
+    builder: ListBuilder<StringBuilder>,
+}
+
+impl LabelColumn {
+    fn new(name: &'static str) -> Self {
+        Self {
+            name,
+            builder: ListBuilder::new(StringBuilder::new()),
+        }
+    }
+
+    fn append<'a>(&mut self, labels: impl Iterator<Item = &'a str>) {
+        self.builder.append_value(labels.map(Some));
+    }
+
+    fn append_null(&mut self) {
+        self.builder.append_null();
+    }
+
+    fn finish(mut self) -> (Arc<Field>, ArrayRef) {
+        let array = self.builder.finish();
+        let field = Field::new(self.name, array.data_type().clone(), true);
+        (Arc::new(field), Arc::new(array))
+    }
+}
+
+impl LtpPipeline {
+    /// 批量处理 Arrow 文本列，输入直接借用 Arrow 的缓冲区，不复制文本。
+    ///
+    /// 返回与输入等长的 `Struct<words: List<Utf8>, pos: List<Utf8>, ner: List<Utf8>>` 列，
+    /// 输入为空值的行输出空值，未运行的阶段对应的列为空值。
+    pub fn process_arrow(&self, texts: &StringArray) -> Result<StructArray> {
+        let valid: Vec<&str> = texts.iter().flatten().collect();
+        let mut sentences = self.process_batch(&valid)?.into_iter();
+
+        let mut words = LabelColumn::new("words");
+        let mut pos = LabelColumn::new("pos");
+        let mut ner = LabelColumn::new("ner");
+        for i in 0..texts.len() {
+            let sentence = if texts.is_null(i) {
+                None
+            } else {
+                sentences.next()
+            };
+            let Some(sentence) = sentence else {
+                words.append_null();
+                pos.append_null();
+                ner.append_null();
+                continue;
+            };
+            words.append(sentence.words.iter().map(|w| w.as_str()));
+            match &sentence.pos {
+                Some(labels) => pos.append(labels.iter()),
+                None => pos.append_null(),
+            }
+            match &sentence.ner {
+                Some(labels) => ner.append(labels.iter()),
+                None => ner.append_null(),
+            }
+        }
+
+        let (fields, arrays): (Vec<_>, Vec<_>) = [words, pos, ner]
+            .into_iter()
+            .map(LabelColumn::finish)
+            .unzip();
+        Ok(StructArray::try_new(
+            fields.into(),
+            arrays,
+            texts.nulls().cloned(),
+        )?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pipeline::{Annotation, PipelineStage, Sentence, StageMetadata};
+    use arrow_array::cast::AsArray;
+
+    struct Whitespace;
+
+    impl PipelineStage for Whitespace {
+        fn metadata(&self) -> StageMetadata {
+            StageMetadata::new("whitespace")
+                .inputs(&[Annotation::Text])
+                .outputs(&[Annotation::Words])
+        }
+
+        fn process(&self, sentence: &mut Sentence) -> Result<()> {
+            sentence.words = sentence.text.split_whitespace().map(String::from).collect();
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_process_arrow() -> Result<()> {
+        let pipeline = LtpPipeline::new().add_stage(Whitespace);
+        let texts = StringArray::from(vec![Some("a b"), None, Some("c")]);
+        let result = pipeline.process_arrow(&texts)?;
+
+        assert_eq!(result.len(), 3);
+        assert!(result.is_null(1));
+        let words = result.column_by_name("words").unwrap().as_list::<i32>();
+        let first = words.value(0);
+        let first: Vec<_> = first.as_string::<i32>().iter().flatten().collect();
+        assert_eq!(first, vec!["a", "b"]);
+        assert_eq!(words.value(2).len(), 1);
+        assert!(result.column_by_name("pos").unwrap().is_null(0));
+        Ok(())
+    }
+}
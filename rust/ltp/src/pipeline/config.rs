@@ -0,0 +1,247 @@
+//! 配置文件驱动的流水线，部署时调整模型、词典与各项参数不需要改代码
+//!
+//! ```toml
+//! cws = "cws_model.bin"
+//! pos = "pos_model.bin"
+//! ner = "ner_model.bin"
+//! dictionaries = ["user.dict"]
+//! threads = 4
+//! non_chinese = "whitespace"
+//!
+//! [clean]
+//! zero_width = true
+//! html_entities = true
+//!
+//! [normalization]
+//! trim = true
+//! fullwidth = true
+//!
+//! [split]
+//! use_en = false
+//!
+//! [thresholds]
+//! ner = 0.6
+//! ```
+//!
+//! 相对路径均相对于配置文件所在目录。
+use crate::clean::TextCleaner;
+use crate::dict::Dictionary;
+use crate::perceptron::ModelTask;
+#[cfg(feature = "ner")]
+use crate::pipeline::NERStage;
+#[cfg(feature = "pos")]
+use crate::pipeline::PosLexiconStage;
+use crate::pipeline::{LtpPipeline, Normalization, UserDictStage};
+use crate::script::{NonChinese, ScriptGuard};
+use crate::{DynModel, SplitOptions};
+use anyhow::{anyhow, Context, Result};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+/// 各阶段的阈值
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Thresholds {
+    /// 实体的平均后验概率低于阈值时丢弃，见 [`NERStage::min_confidence`]
+    pub ner: Option<f64>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PipelineConfig {
+    #[serde(default)]
+    pub cws: Option<PathBuf>,
+    #[serde(default)]
+    pub pos: Option<PathBuf>,
+    /// 依赖词性标注模型
+    #[serde(default)]
+    pub ner: Option<PathBuf>,
+    /// 用户词典，分词后按词典合并，带词性的词条同时用于修正词性
+    #[serde(default)]
+    pub dictionaries: Vec<PathBuf>,
+    /// 批量处理的线程数，缺省使用 rayon 的全局线程池
+    #[serde(default)]
+    pub threads: Option<usize>,
+    #[serde(default)]
+    pub metrics: bool,
+    /// 设置时检测句子的文字，非中文的句子按该方式处理，见 [`ScriptGuard`]
+    #[serde(default)]
+    pub non_chinese: Option<NonChinese>,
+
+    /// 文本清洗，结果可以映射回原文，见 [`TextCleaner`]
+    #[serde(default)]
+    pub clean: TextCleaner,
+    #[serde(default)]
+    pub normalization: Normalization,
+    /// [`LtpPipeline::process_document`] 的分句选项
+    #[serde(default)]
+    pub split: SplitOptions,
+    #[serde(default)]
+    pub thresholds: Thresholds,
+
+    #[serde(skip)]
+    base: PathBuf,
+}
+
+impl PipelineConfig {
+    pub fn from_toml(content: &str) -> Result<Self> {
+        Ok(toml::from_str(content)?)
+    }
+
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        let content = std::fs::read_to_string(path)
+            .with_context(|| format!("failed to read config {}", path.display()))?;
+        let mut config = Self::from_toml(&content)?;
+        config.base = path.parent().map(Path::to_path_buf).unwrap_or_default();
+        Ok(config)
+    }
+
+    /// 相对于配置文件所在目录解析路径
+    pub fn resolve<P: AsRef<Path>>(&self, path: P) -> PathBuf {
+        self.base.join(path)
+    }
+
+    /// 合并所有的用户词典
+    pub fn dictionary(&self) -> Result<Dictionary> {
+        let mut dictionary = Dictionary::default();
+        for path in &self.dictionaries {
+            let path = self.resolve(path);
+            let entries = Dictionary::from_file(&path)
+                .with_context(|| format!("failed to read dictionary {}", path.display()))?;
+            dictionary.entries.extend(entries.entries);
+        }
+        Ok(dictionary)
+    }
+
+    /// 加载模型与词典，按 清洗、规范化、分词、用户词典、词性标注、词性词典、实体识别 的顺序组装流水线
+    pub fn pipeline(&self) -> Result<LtpPipeline> {
+        let mut pipeline = LtpPipeline::new()
+            .enable_metrics(self.metrics)
+            .split_options(self.split.clone());
+        if let Some(threads) = self.threads {
+            #[cfg(feature = "parallel")]
+            {
+                pipeline = pipeline.threads(threads)?;
+            }
+            #[cfg(not(feature = "parallel"))]
+            return Err(anyhow!(
+                "threads = {} requires the `parallel` feature",
+                threads
+            ));
+        }
+        if let Some(policy) = self.non_chinese {
+            pipeline = pipeline.script_guard(ScriptGuard::new().policy(policy));
+        }
+        if self.clean.is_enabled() {
+            pipeline = pipeline.add_stage(self.clean);
+        }
+        if self.normalization.is_enabled() {
+            pipeline = pipeline.add_stage(self.normalization);
+        }
+
+        let dictionary = self.dictionary()?;
+        let models = [
+            (&self.cws, ModelTask::CWS),
+            (&self.pos, ModelTask::POS),
+            (&self.ner, ModelTask::NER),
+        ];
+        for (path, task) in models {
+            let Some(path) = path else { continue };
+            let model = DynModel::load_file(self.resolve(path))?;
+            if model.task() != task {
+                return Err(anyhow!(
+                    "{}: expected a {} model, found a {} model",
+                    path.display(),
+                    task,
+                    model.task()
+                ));
+            }
+            pipeline = match model {
+                #[cfg(feature = "cws")]
+                DynModel::Cws(model) => {
+                    let pipeline = pipeline.with_cws(model);
+                    match dictionary.entries.is_empty() {
+                        true => pipeline,
+                        false => pipeline.add_stage(UserDictStage::new(dictionary.hook())),
+                    }
+                }
+                #[cfg(feature = "pos")]
+                DynModel::Pos(model) => {
+                    let pipeline = pipeline.with_pos(model);
+                    let lexicon = dictionary.lexicon();
+                    match lexicon.is_empty() {
+                        true => pipeline,
+                        false => pipeline.add_stage(PosLexiconStage::new(lexicon)),
+                    }
+                }
+                #[cfg(feature = "ner")]
+                DynModel::Ner(model) => {
+                    let stage = NERStage::new(model);
+                    pipeline.add_stage(match self.thresholds.ner {
+                        Some(threshold) => stage.min_confidence(threshold),
+                        None => stage,
+                    })
+                }
+            };
+        }
+        pipeline.validate()?;
+        Ok(pipeline)
+    }
+}
+
+impl LtpPipeline {
+    /// 从 TOML 配置文件构造流水线，格式见 [`PipelineConfig`]
+    pub fn from_config<P: AsRef<Path>>(path: P) -> Result<Self> {
+        PipelineConfig::from_file(path)?.pipeline()
+    }
+}
+
+#[cfg(all(test, feature = "cws", feature = "pos"))]
+mod tests {
+    use super::*;
+    use crate::perceptron::cws_test_model;
+    use crate::{CWSModel, Format, ModelSerde, POSDefinition, POSModel, Perceptron};
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_pipeline_config() -> Result<()> {
+        let dir = std::env::temp_dir().join("ltp-pipeline-config");
+        std::fs::create_dir_all(&dir)?;
+        let features = HashMap::from([("2他".to_string(), 0)]);
+        let cws: CWSModel = cws_test_model(&["2他"], |_| 0.0);
+        cws.save_file(dir.join("cws.bin"), Format::JSON)?;
+        let definition = POSDefinition::new(vec!["n".into(), "nh".into()]);
+        let pos: POSModel = Perceptron::new_with_parameters(definition, features, vec![1.0, 0.0]);
+        pos.save_file(dir.join("pos.bin"), Format::JSON)?;
+        std::fs::write(dir.join("user.dict"), "汤姆 nh 10\n")?;
+        std::fs::write(
+            dir.join("ltp.toml"),
+            "cws = \"cws.bin\"\npos = \"pos.bin\"\ndictionaries = [\"user.dict\"]\n\n[clean]\nzero_width = true\n\n[normalization]\ntrim = true\nfullwidth = true\n",
+        )?;
+
+        let pipeline = LtpPipeline::from_config(dir.join("ltp.toml"))?;
+        let names: Vec<_> = pipeline.stages().into_iter().map(|s| s.name).collect();
+        assert_eq!(
+            names,
+            [
+                "clean",
+                "normalize",
+                "cws",
+                "user-dict",
+                "pos",
+                "pos-lexicon"
+            ]
+        );
+        let text = " 汤\u{200B}姆ＬＴＰ ";
+        let sentence = pipeline.process(text)?;
+        assert_eq!(sentence.text, "汤姆LTP");
+        // 清洗与规范化后的位置仍能映射回原文
+        assert_eq!(&text[sentence.original_span(6..9)], "ＬＴＰ");
+        let index = sentence.words.iter().position(|w| w == "汤姆").unwrap();
+        assert_eq!(sentence.pos.unwrap().get(index), Some("nh"));
+
+        assert!(PipelineConfig::from_toml("cws = \"a\"\nthread = 1").is_err());
+        Ok(())
+    }
+}
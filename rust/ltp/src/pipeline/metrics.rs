@@ -0,0 +1,146 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+
+/// 延迟直方图的桶上界（微秒），最后一个桶收纳所有更慢的调用
+pub const LATENCY_BUCKETS_US: [u64; 11] = [
+    10, 50, 100, 500, 1_000, 5_000, 10_000, 50_000, 100_000, 500_000, 1_000_000,
+];
+
+#[derive(Debug, Default)]
+pub(crate) struct StageCounters {
+    calls: AtomicU64,
+    errors: AtomicU64,
+    tokens: AtomicU64,
+    nanos: AtomicU64,
+    buckets: [AtomicU64; LATENCY_BUCKETS_US.len() + 1],
+}
+
+impl StageCounters {
+    pub(crate) fn record(&self, elapsed: Duration, tokens: usize, ok: bool) {
+        self.calls.fetch_add(1, Ordering::Relaxed);
+        if !ok {
+            self.errors.fetch_add(1, Ordering::Relaxed);
+        }
+        self.tokens.fetch_add(tokens as u64, Ordering::Relaxed);
+        self.nanos
+            .fetch_add(elapsed.as_nanos() as u64, Ordering::Relaxed);
+
+        let micros = elapsed.as_micros() as u64;
+        let bucket = LATENCY_BUCKETS_US
+            .iter()
+            .position(|&bound| micros <= bound)
+            .unwrap_or(LATENCY_BUCKETS_US.len());
+        self.buckets[bucket].fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn reset(&self) {
+        self.calls.store(0, Ordering::Relaxed);
+        self.errors.store(0, Ordering::Relaxed);
+        self.tokens.store(0, Ordering::Relaxed);
+        self.nanos.store(0, Ordering::Relaxed);
+        for bucket in &self.buckets {
+            bucket.store(0, Ordering::Relaxed);
+        }
+    }
+
+    pub(crate) fn snapshot(&self, name: &str) -> StageMetrics {
+        StageMetrics {
+            name: name.to_string(),
+            calls: self.calls.load(Ordering::Relaxed),
+            errors: self.errors.load(Ordering::Relaxed),
+            tokens: self.tokens.load(Ordering::Relaxed),
+            total_time: Duration::from_nanos(self.nanos.load(Ordering::Relaxed)),
+            latency_histogram: self
+                .buckets
+                .iter()
+                .map(|b| b.load(Ordering::Relaxed))
+                .collect(),
+        }
+    }
+}
+
+/// 单个阶段的统计快照
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct StageMetrics {
+    pub name: String,
+    pub calls: u64,
+    pub errors: u64,
+    /// 该阶段处理过的词数
+    pub tokens: u64,
+    pub total_time: Duration,
+    /// 与 [`LATENCY_BUCKETS_US`] 对应的非累积计数，多出的最后一项为 `+Inf` 桶
+    pub latency_histogram: Vec<u64>,
+}
+
+impl StageMetrics {
+    pub fn mean_latency(&self) -> Duration {
+        if self.calls == 0 {
+            Duration::ZERO
+        } else {
+            Duration::from_nanos((self.total_time.as_nanos() / self.calls as u128) as u64)
+        }
+    }
+
+    pub fn tokens_per_sec(&self) -> f64 {
+        let secs = self.total_time.as_secs_f64();
+        if secs > 0.0 {
+            self.tokens as f64 / secs
+        } else {
+            0.0
+        }
+    }
+
+    /// Prometheus 风格的累积直方图：`(上界秒数, 累积计数)`，上界为 `None` 表示 `+Inf`
+    pub fn cumulative_histogram(&self) -> Vec<(Option<f64>, u64)> {
+        let mut total = 0;
+        self.latency_histogram
+            .iter()
+            .enumerate()
+            .map(|(i, &count)| {
+                total += count;
+                let bound = LATENCY_BUCKETS_US.get(i).map(|&us| us as f64 / 1e6);
+                (bound, total)
+            })
+            .collect()
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PipelineMetrics {
+    pub stages: Vec<StageMetrics>,
+}
+
+impl PipelineMetrics {
+    pub fn stage(&self, name: &str) -> Option<&StageMetrics> {
+        self.stages.iter().find(|s| s.name == name)
+    }
+
+    pub fn total_time(&self) -> Duration {
+        self.stages.iter().map(|s| s.total_time).sum()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_counters() {
+        let counters = StageCounters::default();
+        counters.record(Duration::from_micros(5), 3, true);
+        counters.record(Duration::from_millis(2), 7, false);
+        counters.record(Duration::from_secs(2), 0, true);
+
+        let metrics = counters.snapshot("cws");
+        assert_eq!(metrics.calls, 3);
+        assert_eq!(metrics.errors, 1);
+        assert_eq!(metrics.tokens, 10);
+        assert_eq!(metrics.latency_histogram[0], 1);
+        assert_eq!(metrics.latency_histogram[5], 1);
+        assert_eq!(metrics.latency_histogram[LATENCY_BUCKETS_US.len()], 1);
+        assert_eq!(metrics.cumulative_histogram().last(), Some(&(None, 3)));
+
+        counters.reset();
+        assert_eq!(counters.snapshot("cws").calls, 0);
+    }
+}
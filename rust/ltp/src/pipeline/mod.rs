@@ -0,0 +1,842 @@
+#[cfg(feature = "arrow")]
+mod arrow;
+mod cancel;
+#[cfg(all(
+    feature = "config",
+    any(feature = "cws", feature = "pos", feature = "ner")
+))]
+mod config;
+mod metrics;
+mod stage;
+
+use crate::clean::OffsetMap;
+use crate::error::LtpError;
+use crate::label::InternedLabels;
+use crate::quantity::Quantity;
+#[cfg(any(feature = "cws", feature = "pos", feature = "ner"))]
+use crate::perceptron::{Perceptron, TraitFeature, TraitParameter, TraitParameterStorage};
+#[cfg(feature = "cws")]
+use crate::CWSDefinition;
+#[cfg(feature = "ner")]
+use crate::NERDefinition;
+#[cfg(feature = "pos")]
+use crate::POSDefinition;
+use crate::script::{whitespace_tokenize, NonChinese, Script, ScriptGuard, ScriptStats};
+use crate::{paragraph_spans, stn_split_spans, SplitOptions};
+use anyhow::{anyhow, Result};
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
+use std::collections::{HashMap, HashSet};
+use std::fmt::{Debug, Formatter};
+use std::ops::Range;
+#[cfg(feature = "parallel")]
+use std::sync::Arc;
+use std::time::Instant;
+
+pub use cancel::{CancellationToken, Cancelled};
+#[cfg(all(
+    feature = "config",
+    any(feature = "cws", feature = "pos", feature = "ner")
+))]
+pub use config::{PipelineConfig, Thresholds};
+use metrics::StageCounters;
+pub use metrics::{PipelineMetrics, StageMetrics, LATENCY_BUCKETS_US};
+
+#[cfg(feature = "cws")]
+pub use stage::CWSStage;
+#[cfg(feature = "ner")]
+pub use stage::GazetteerStage;
+#[cfg(feature = "ner")]
+pub use stage::NERStage;
+#[cfg(feature = "pos")]
+pub use stage::POSStage;
+#[cfg(feature = "pos")]
+pub use stage::PosLexiconStage;
+pub use stage::{Annotation, Normalization, PipelineStage, StageMetadata, UserDictStage};
+
+/// 一个句子在流水线中的全部标注结果，不借用模型或输入文本
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Sentence {
+    pub text: String,
+    /// 由 [`LtpPipeline::process_document`] 分句时，句子在文档中的字节位置
+    pub offset: usize,
+    pub words: Vec<String>,
+    pub pos: Option<InternedLabels>,
+    pub ner: Option<InternedLabels>,
+    /// 自定义阶段的输出，键为 [`Annotation::Custom`] 的名字
+    pub custom: HashMap<String, Vec<String>>,
+    /// 经 [`TextCleaner`](crate::clean::TextCleaner) 或 [`Normalization`] 改写时，
+    /// 最初的文本与 `text` 到它的位置映射
+    pub original: Option<(String, OffsetMap)>,
+    /// 设置了 [`ScriptGuard`] 时，句子中字符最多的文字
+    pub script: Option<Script>,
+    /// 由 [`QuantityRecognizer`](crate::quantity::QuantityRecognizer) 识别的数值与日期
+    pub quantities: Vec<Quantity>,
+    /// 由 [`LtpPipeline::process_tokens`] 处理时，调用方给出的各词在其原文中的位置
+    pub token_spans: Option<Vec<Range<usize>>>,
+}
+
+impl Sentence {
+    pub fn new(text: impl Into<String>) -> Self {
+        Self {
+            text: text.into(),
+            ..Default::default()
+        }
+    }
+
+    pub fn words(&self) -> Vec<&str> {
+        self.words.iter().map(|w| w.as_str()).collect()
+    }
+
+    /// `text` 中的字节区间在最初文本中的位置，文本未被改写时原样返回
+    pub fn original_span(&self, span: Range<usize>) -> Range<usize> {
+        match &self.original {
+            Some((_, map)) => map.to_original(span),
+            None => span,
+        }
+    }
+
+    fn has(&self, annotation: &Annotation) -> bool {
+        match annotation {
+            Annotation::Text | Annotation::Words => !self.words.is_empty(),
+            Annotation::Pos => self.pos.is_some(),
+            Annotation::Ner => self.ner.is_some(),
+            Annotation::Quantities => !self.quantities.is_empty(),
+            Annotation::Custom(name) => self.custom.contains_key(*name),
+        }
+    }
+
+    /// 各词在最初文本中对应的部分，如简繁转换前的字形；词须依次出现在 `text` 中，找不到的词为空
+    pub fn original_words(&self) -> Vec<&str> {
+        let original = self.original.as_ref().map_or(&self.text, |(text, _)| text);
+        let mut cursor = 0;
+        self.words
+            .iter()
+            .map(|word| match self.text[cursor..].find(word.as_str()) {
+                Some(start) => {
+                    let span = cursor + start..cursor + start + word.len();
+                    cursor = span.end;
+                    &original[self.original_span(span)]
+                }
+                None => "",
+            })
+            .collect()
+    }
+}
+
+/// 文档中的一个段落，`span` 为段落在文档中的字节区间，相邻区间之间为原文中的空白行等分隔
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Paragraph {
+    pub span: Range<usize>,
+    pub sentences: Vec<Sentence>,
+}
+
+struct StageEntry {
+    stage: Box<dyn PipelineStage>,
+    counters: StageCounters,
+}
+
+impl StageEntry {
+    fn new(stage: Box<dyn PipelineStage>) -> Self {
+        Self {
+            stage,
+            counters: Default::default(),
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct LtpPipeline {
+    stages: Vec<StageEntry>,
+    metrics: bool,
+    split: SplitOptions,
+    guard: Option<ScriptGuard>,
+    #[cfg(feature = "parallel")]
+    pool: Option<Arc<rayon::ThreadPool>>,
+}
+
+impl Debug for LtpPipeline {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_list()
+            .entries(self.stages.iter().map(|s| s.stage.metadata().name))
+            .finish()
+    }
+}
+
+impl LtpPipeline {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    #[cfg(feature = "cws")]
+    pub fn with_cws<Feature, ParamStorage, Param>(
+        self,
+        model: Perceptron<CWSDefinition, Feature, ParamStorage, Param>,
+    ) -> Self
+    where
+        Feature: TraitFeature + Send + Sync + 'static,
+        ParamStorage: TraitParameterStorage<Param> + Send + Sync + 'static,
+        Param: TraitParameter + Send + Sync + 'static,
+    {
+        self.add_stage(CWSStage::new(model))
+    }
+
+    #[cfg(feature = "pos")]
+    pub fn with_pos<Feature, ParamStorage, Param>(
+        self,
+        model: Perceptron<POSDefinition, Feature, ParamStorage, Param>,
+    ) -> Self
+    where
+        Feature: TraitFeature + Send + Sync + 'static,
+        ParamStorage: TraitParameterStorage<Param> + Send + Sync + 'static,
+        Param: TraitParameter + Send + Sync + 'static,
+    {
+        self.add_stage(POSStage::new(model))
+    }
+
+    #[cfg(feature = "ner")]
+    pub fn with_ner<Feature, ParamStorage, Param>(
+        self,
+        model: Perceptron<NERDefinition, Feature, ParamStorage, Param>,
+    ) -> Self
+    where
+        Feature: TraitFeature + Send + Sync + 'static,
+        ParamStorage: TraitParameterStorage<Param> + Send + Sync + 'static,
+        Param: TraitParameter + Send + Sync + 'static,
+    {
+        self.add_stage(NERStage::new(model))
+    }
+
+    /// [`LtpPipeline::process_document`] 分句时使用的选项
+    pub fn split_options(mut self, options: SplitOptions) -> Self {
+        self.split = options;
+        self
+    }
+
+    /// 运行各阶段前检测句子的文字，非中文的句子按 [`ScriptGuard::policy`] 处理
+    pub fn script_guard(mut self, guard: ScriptGuard) -> Self {
+        self.guard = Some(guard);
+        self
+    }
+
+    /// 批量处理时使用独立的线程池，缺省使用 rayon 的全局线程池
+    #[cfg(feature = "parallel")]
+    pub fn threads(mut self, threads: usize) -> Result<Self> {
+        let pool = rayon::ThreadPoolBuilder::new()
+            .num_threads(threads)
+            .build()?;
+        self.pool = Some(Arc::new(pool));
+        Ok(self)
+    }
+
+    /// 批量处理时使用调用方提供的线程池
+    #[cfg(feature = "parallel")]
+    pub fn thread_pool(mut self, pool: Arc<rayon::ThreadPool>) -> Self {
+        self.pool = Some(pool);
+        self
+    }
+
+    #[cfg(feature = "parallel")]
+    fn install<T: Send>(&self, op: impl FnOnce() -> T + Send) -> T {
+        match &self.pool {
+            Some(pool) => pool.install(op),
+            None => op(),
+        }
+    }
+
+    /// 在流水线末尾追加一个阶段
+    pub fn add_stage<S: PipelineStage + 'static>(mut self, stage: S) -> Self {
+        self.stages.push(StageEntry::new(Box::new(stage)));
+        self
+    }
+
+    /// 在名为 `name` 的阶段之前插入一个阶段
+    pub fn insert_before<S: PipelineStage + 'static>(
+        mut self,
+        name: &str,
+        stage: S,
+    ) -> Result<Self> {
+        let idx = self.position(name)?;
+        self.stages.insert(idx, StageEntry::new(Box::new(stage)));
+        Ok(self)
+    }
+
+    /// 在名为 `name` 的阶段之后插入一个阶段
+    pub fn insert_after<S: PipelineStage + 'static>(
+        mut self,
+        name: &str,
+        stage: S,
+    ) -> Result<Self> {
+        let idx = self.position(name)?;
+        self.stages
+            .insert(idx + 1, StageEntry::new(Box::new(stage)));
+        Ok(self)
+    }
+
+    pub fn remove_stage(&mut self, name: &str) -> Option<Box<dyn PipelineStage>> {
+        let idx = self.position(name).ok()?;
+        Some(self.stages.remove(idx).stage)
+    }
+
+    fn position(&self, name: &str) -> Result<usize> {
+        self.stages
+            .iter()
+            .position(|s| s.stage.metadata().name == name)
+            .ok_or_else(|| anyhow!("stage `{}` not found in pipeline", name))
+    }
+
+    pub fn stages(&self) -> Vec<StageMetadata> {
+        self.stages.iter().map(|s| s.stage.metadata()).collect()
+    }
+
+    /// 开启各阶段的耗时与吞吐统计，默认关闭，关闭时不产生额外开销
+    pub fn enable_metrics(mut self, enable: bool) -> Self {
+        self.metrics = enable;
+        self
+    }
+
+    pub fn metrics(&self) -> PipelineMetrics {
+        PipelineMetrics {
+            stages: self
+                .stages
+                .iter()
+                .map(|s| s.counters.snapshot(&s.stage.metadata().name))
+                .collect(),
+        }
+    }
+
+    pub fn reset_metrics(&self) {
+        for entry in &self.stages {
+            entry.counters.reset();
+        }
+    }
+
+    /// 检查每个阶段需要的标注层都已由之前的阶段产出
+    pub fn validate(&self) -> Result<()> {
+        let mut available = HashSet::from([Annotation::Text]);
+        for entry in &self.stages {
+            let metadata = entry.stage.metadata();
+            for input in &metadata.inputs {
+                if !available.contains(input) {
+                    return Err(anyhow!(
+                        "stage `{}` requires `{}`, which no earlier stage produces",
+                        metadata.name,
+                        input
+                    ));
+                }
+            }
+            available.extend(metadata.outputs);
+        }
+        Ok(())
+    }
+
+    pub fn process(&self, text: &str) -> Result<Sentence> {
+        let mut sentence = Sentence::new(text);
+        if let Some(guard) = &self.guard {
+            let stats = ScriptStats::new(text);
+            sentence.script = stats.dominant();
+            if !guard.is_chinese_with(text, &stats) {
+                match guard.policy {
+                    NonChinese::Process => {}
+                    NonChinese::Skip => return Ok(sentence),
+                    NonChinese::Whitespace => {
+                        sentence.words = whitespace_tokenize(text);
+                        return Ok(sentence);
+                    }
+                }
+            }
+        }
+        for entry in &self.stages {
+            self.run_stage(entry, &mut sentence)?;
+        }
+        Ok(sentence)
+    }
+
+    fn run_stage(&self, entry: &StageEntry, sentence: &mut Sentence) -> Result<()> {
+        if !self.metrics {
+            return entry.stage.process(sentence);
+        }
+        let start = Instant::now();
+        let result = entry.stage.process(sentence);
+        let tokens = sentence.words.len();
+        entry
+            .counters
+            .record(start.elapsed(), tokens, result.is_ok());
+        result
+    }
+
+    /// 处理调用方已经分好的词，`spans` 为各词在调用方原文中的位置，可由
+    /// [`Sentence::entity_spans`] 取回实体在原文中的位置。
+    ///
+    /// 句子的文本为各词的拼接；产出或改写 `text` 与 `words` 的阶段（分词、用户词典、清洗等）
+    /// 被跳过，保证词的边界不变。
+    pub fn process_tokens<S: AsRef<str>>(
+        &self,
+        tokens: &[S],
+        spans: &[Range<usize>],
+    ) -> Result<Sentence> {
+        if tokens.len() != spans.len() {
+            return Err(LtpError::LengthMismatch(tokens.len(), spans.len()).into());
+        }
+        let words: Vec<String> = tokens
+            .iter()
+            .map(|token| token.as_ref().to_string())
+            .collect();
+        let mut sentence = Sentence::new(words.concat());
+        sentence.words = words;
+        sentence.token_spans = Some(spans.to_vec());
+        for entry in &self.stages {
+            let outputs = entry.stage.metadata().outputs;
+            if outputs.contains(&Annotation::Text) || outputs.contains(&Annotation::Words) {
+                continue;
+            }
+            self.run_stage(entry, &mut sentence)?;
+        }
+        Ok(sentence)
+    }
+
+    /// 补全 `sentence` 中缺少的标注层，直到产出 `target` 为止，之后的阶段不再运行。
+    ///
+    /// 输出了调用方已给出的标注层的阶段被跳过；给出分词结果时文本也视为已给出，
+    /// 与 [`LtpPipeline::process_tokens`] 一样不改写词的边界。
+    pub fn annotate(&self, mut sentence: Sentence, target: Annotation) -> Result<Sentence> {
+        let last = self
+            .stages
+            .iter()
+            .rposition(|s| s.stage.metadata().outputs.contains(&target))
+            .ok_or_else(|| anyhow!("no stage in pipeline produces `{}`", target))?;
+        let skipped: Vec<bool> = self.stages[..=last]
+            .iter()
+            .map(|s| s.stage.metadata().outputs.iter().any(|a| sentence.has(a)))
+            .collect();
+        for (entry, skipped) in self.stages[..=last].iter().zip(skipped) {
+            if !skipped {
+                self.run_stage(entry, &mut sentence)?;
+            }
+        }
+        Ok(sentence)
+    }
+
+    #[cfg(feature = "parallel")]
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(skip_all, fields(sentences = texts.len()))
+    )]
+    pub fn process_batch(&self, texts: &[&str]) -> Result<Vec<Sentence>> {
+        self.install(|| texts.par_iter().map(|text| self.process(text)).collect())
+    }
+
+    #[cfg(not(feature = "parallel"))]
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(skip_all, fields(sentences = texts.len()))
+    )]
+    pub fn process_batch(&self, texts: &[&str]) -> Result<Vec<Sentence>> {
+        texts.iter().map(|text| self.process(text)).collect()
+    }
+
+    /// 与 [`LtpPipeline::process_batch`] 相同，但在每个句子开始前检查 `token`，
+    /// 取消后尽快返回 [`Cancelled`] 错误
+    #[cfg(feature = "parallel")]
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(skip_all, fields(sentences = texts.len()))
+    )]
+    pub fn process_batch_with_cancel(
+        &self,
+        texts: &[&str],
+        token: &CancellationToken,
+    ) -> Result<Vec<Sentence>> {
+        self.install(|| {
+            texts
+                .par_iter()
+                .map(|text| {
+                    token.check()?;
+                    self.process(text)
+                })
+                .collect()
+        })
+    }
+
+    /// 与 [`LtpPipeline::process_batch`] 相同，但在每个句子开始前检查 `token`，
+    /// 取消后尽快返回 [`Cancelled`] 错误
+    #[cfg(not(feature = "parallel"))]
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(skip_all, fields(sentences = texts.len()))
+    )]
+    pub fn process_batch_with_cancel(
+        &self,
+        texts: &[&str],
+        token: &CancellationToken,
+    ) -> Result<Vec<Sentence>> {
+        texts
+            .iter()
+            .map(|text| {
+                token.check()?;
+                self.process(text)
+            })
+            .collect()
+    }
+
+    /// 先分句，再逐句处理，句子记录其在文档中的位置
+    pub fn process_document(&self, text: &str) -> Result<Vec<Sentence>> {
+        let spans = stn_split_spans(text, &self.split);
+        let sentences: Vec<&str> = spans.iter().map(|span| &text[span.clone()]).collect();
+        let sentences = self.process_batch(&sentences)?;
+        Ok(with_offsets(sentences, &spans))
+    }
+
+    pub fn process_document_with_cancel(
+        &self,
+        text: &str,
+        token: &CancellationToken,
+    ) -> Result<Vec<Sentence>> {
+        token.check()?;
+        let spans = stn_split_spans(text, &self.split);
+        let sentences: Vec<&str> = spans.iter().map(|span| &text[span.clone()]).collect();
+        let sentences = self.process_batch_with_cancel(&sentences, token)?;
+        Ok(with_offsets(sentences, &spans))
+    }
+
+    /// 先按 [`SplitOptions::paragraph`] 分段，再在段内分句并逐句处理，
+    /// 句子的 `offset` 为其在整个文档中的位置
+    pub fn process_paragraphs(&self, text: &str) -> Result<Vec<Paragraph>> {
+        let paragraphs = paragraph_spans(text, self.split.paragraph);
+        let mut spans = vec![];
+        let mut counts = vec![];
+        for paragraph in &paragraphs {
+            let sentences = stn_split_spans(&text[paragraph.clone()], &self.split);
+            counts.push(sentences.len());
+            let start = paragraph.start;
+            spans.extend(
+                sentences
+                    .into_iter()
+                    .map(|span| span.start + start..span.end + start),
+            );
+        }
+        let sentences: Vec<&str> = spans.iter().map(|span| &text[span.clone()]).collect();
+        let sentences = with_offsets(self.process_batch(&sentences)?, &spans);
+        let mut sentences = sentences.into_iter();
+        Ok(paragraphs
+            .into_iter()
+            .zip(counts)
+            .map(|(span, count)| Paragraph {
+                span,
+                sentences: sentences.by_ref().take(count).collect(),
+            })
+            .collect())
+    }
+}
+
+fn with_offsets(mut sentences: Vec<Sentence>, spans: &[Range<usize>]) -> Vec<Sentence> {
+    for (sentence, span) in sentences.iter_mut().zip(spans) {
+        sentence.offset = span.start;
+    }
+    sentences
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ParagraphBreak;
+
+    struct Lowercase;
+
+    impl PipelineStage for Lowercase {
+        fn metadata(&self) -> StageMetadata {
+            StageMetadata::new("lowercase")
+                .inputs(&[Annotation::Text])
+                .outputs(&[Annotation::Text])
+        }
+
+        fn process(&self, sentence: &mut Sentence) -> Result<()> {
+            sentence.text = sentence.text.to_lowercase();
+            Ok(())
+        }
+    }
+
+    struct Whitespace;
+
+    impl PipelineStage for Whitespace {
+        fn metadata(&self) -> StageMetadata {
+            StageMetadata::new("whitespace")
+                .inputs(&[Annotation::Text])
+                .outputs(&[Annotation::Words])
+        }
+
+        fn process(&self, sentence: &mut Sentence) -> Result<()> {
+            sentence.words = sentence.text.split_whitespace().map(String::from).collect();
+            Ok(())
+        }
+    }
+
+    struct Length;
+
+    impl PipelineStage for Length {
+        fn metadata(&self) -> StageMetadata {
+            StageMetadata::new("length")
+                .inputs(&[Annotation::Words])
+                .outputs(&[Annotation::Custom("length")])
+        }
+
+        fn process(&self, sentence: &mut Sentence) -> Result<()> {
+            let lengths = sentence.words.iter().map(|w| w.chars().count().to_string());
+            sentence
+                .custom
+                .insert("length".to_string(), lengths.collect());
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_custom_stages() -> Result<()> {
+        let pipeline = LtpPipeline::new()
+            .add_stage(Whitespace)
+            .add_stage(Length)
+            .insert_before("whitespace", Lowercase)?;
+        pipeline.validate()?;
+
+        let names: Vec<_> = pipeline.stages().into_iter().map(|m| m.name).collect();
+        assert_eq!(names, vec!["lowercase", "whitespace", "length"]);
+
+        let sentence = pipeline.process("Hello LTP World")?;
+        assert_eq!(sentence.words, vec!["hello", "ltp", "world"]);
+        assert_eq!(sentence.custom["length"], vec!["5", "3", "5"]);
+
+        let text = "你好 世界。再 见。";
+        let sentences = pipeline.process_document(text)?;
+        assert_eq!(sentences[1].words, vec!["再", "见。"]);
+        assert_eq!(sentences[1].offset, 16);
+        assert!(text[sentences[1].offset..].starts_with(&sentences[1].text));
+        Ok(())
+    }
+
+    #[test]
+    fn test_annotate() -> Result<()> {
+        let pipeline = LtpPipeline::new()
+            .add_stage(Lowercase)
+            .add_stage(Whitespace)
+            .add_stage(Length)
+            .enable_metrics(true);
+
+        let sentence = pipeline.annotate(Sentence::new("Hello LTP"), Annotation::Words)?;
+        assert_eq!(sentence.words, vec!["hello", "ltp"]);
+        assert!(sentence.custom.is_empty());
+
+        // 已给出的词不再经过改写文本与分词的阶段
+        let mut sentence = Sentence::new("Hello LTP");
+        sentence.words = vec!["Hello".to_string(), "LTP".to_string()];
+        let sentence = pipeline.annotate(sentence, Annotation::Custom("length"))?;
+        assert_eq!(sentence.words, vec!["Hello", "LTP"]);
+        assert_eq!(sentence.custom["length"], vec!["5", "3"]);
+        assert_eq!(pipeline.metrics().stage("whitespace").unwrap().calls, 1);
+
+        assert!(pipeline
+            .annotate(Sentence::new("Hello"), Annotation::Pos)
+            .is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_metrics() -> Result<()> {
+        let pipeline = LtpPipeline::new()
+            .add_stage(Whitespace)
+            .add_stage(Length)
+            .enable_metrics(true);
+        pipeline.process_batch(&["a b c", "d e"])?;
+
+        let metrics = pipeline.metrics();
+        let whitespace = metrics.stage("whitespace").unwrap();
+        assert_eq!(whitespace.calls, 2);
+        assert_eq!(whitespace.tokens, 5);
+        assert_eq!(whitespace.latency_histogram.iter().sum::<u64>(), 2);
+
+        pipeline.reset_metrics();
+        assert_eq!(pipeline.metrics().stage("length").unwrap().calls, 0);
+        Ok(())
+    }
+
+    #[test]
+    fn test_cancel() -> Result<()> {
+        let pipeline = LtpPipeline::new().add_stage(Whitespace);
+        let token = CancellationToken::new();
+        assert_eq!(
+            pipeline.process_batch_with_cancel(&["a b"], &token)?.len(),
+            1
+        );
+
+        token.cancel();
+        let err = pipeline
+            .process_document_with_cancel("你好。再见。", &token)
+            .unwrap_err();
+        assert!(err.is::<Cancelled>());
+        Ok(())
+    }
+
+    #[cfg(feature = "parallel")]
+    #[test]
+    fn test_thread_pool() -> Result<()> {
+        struct Threads;
+
+        impl PipelineStage for Threads {
+            fn metadata(&self) -> StageMetadata {
+                StageMetadata::new("threads").outputs(&[Annotation::Custom("threads")])
+            }
+
+            fn process(&self, sentence: &mut Sentence) -> Result<()> {
+                let threads = rayon::current_num_threads().to_string();
+                sentence.custom.insert("threads".to_string(), vec![threads]);
+                Ok(())
+            }
+        }
+
+        let pool = Arc::new(rayon::ThreadPoolBuilder::new().num_threads(3).build()?);
+        let pipeline = LtpPipeline::new()
+            .add_stage(Threads)
+            .thread_pool(Arc::clone(&pool));
+        let sentences = pipeline.process_batch(&["a", "b"])?;
+        assert!(sentences.iter().all(|s| s.custom["threads"] == ["3"]));
+
+        let pipeline = LtpPipeline::new().add_stage(Threads).threads(2)?;
+        let sentences = pipeline.process_batch(&["a"])?;
+        assert_eq!(sentences[0].custom["threads"], ["2"]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_paragraphs() -> Result<()> {
+        let text = "一 二。三 四。\n五 六。\n\n七 八";
+        let pipeline = LtpPipeline::new().add_stage(Whitespace);
+        let paragraphs = pipeline.process_paragraphs(text)?;
+        assert_eq!(paragraphs.len(), 2);
+        assert_eq!(paragraphs[0].sentences.len(), 3);
+        assert_eq!(&text[paragraphs[1].span.clone()], "七 八");
+        let last = &paragraphs[1].sentences[0];
+        assert_eq!(
+            (last.offset, last.words()),
+            (paragraphs[1].span.start, vec!["七", "八"])
+        );
+
+        let pipeline = pipeline.split_options(SplitOptions {
+            paragraph: ParagraphBreak::Newline,
+            ..Default::default()
+        });
+        let paragraphs = pipeline.process_paragraphs(text)?;
+        let counts: Vec<_> = paragraphs.iter().map(|p| p.sentences.len()).collect();
+        assert_eq!(counts, vec![2, 1, 1]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_validate() {
+        let pipeline = LtpPipeline::new().add_stage(Length).add_stage(Whitespace);
+        assert!(pipeline.validate().is_err());
+        assert!(LtpPipeline::new().insert_after("cws", Length).is_err());
+    }
+
+    #[cfg(feature = "ner")]
+    #[test]
+    fn test_reload_dictionaries() -> Result<()> {
+        use crate::dict::{Gazetteer, Reloadable};
+        use crate::hook::Hook;
+        use crate::label::LabelSet;
+
+        struct Chars;
+
+        impl PipelineStage for Chars {
+            fn metadata(&self) -> StageMetadata {
+                StageMetadata::new("chars")
+                    .inputs(&[Annotation::Text])
+                    .outputs(&[Annotation::Words])
+            }
+
+            fn process(&self, sentence: &mut Sentence) -> Result<()> {
+                sentence.words = sentence.text.chars().map(String::from).collect();
+                Ok(())
+            }
+        }
+
+        struct Outside;
+
+        impl PipelineStage for Outside {
+            fn metadata(&self) -> StageMetadata {
+                StageMetadata::new("outside")
+                    .inputs(&[Annotation::Words])
+                    .outputs(&[Annotation::Ner])
+            }
+
+            fn process(&self, sentence: &mut Sentence) -> Result<()> {
+                let labels = LabelSet::new(["O", "S-Ns", "B-Ns", "E-Ns"].map(String::from).into());
+                let outside = vec![0; sentence.words.len()];
+                sentence.ner = Some(InternedLabels::from_indices(labels, &outside)?);
+                Ok(())
+            }
+        }
+
+        let hook = Reloadable::new(Hook::new());
+        let gazetteer = Reloadable::new(Gazetteer::new());
+        let pipeline = LtpPipeline::new()
+            .add_stage(Chars)
+            .add_stage(UserDictStage::reloadable(hook.clone()))
+            .add_stage(Outside)
+            .add_stage(GazetteerStage::reloadable(gazetteer.clone()));
+        pipeline.validate()?;
+        let sentence = pipeline.process("去北京")?;
+        assert_eq!(sentence.words, ["去", "北", "京"]);
+        assert_eq!(sentence.ner.unwrap().to_vec(), ["O", "O", "O"]);
+
+        // 运行中替换词典，不需要重建流水线
+        let mut words = Hook::new();
+        words.add_word("北京", Some(2));
+        hook.replace(words);
+        let mut places = Gazetteer::new();
+        places.insert("北京", "Ns");
+        gazetteer.replace(places);
+        let sentence = pipeline.process("去北京")?;
+        assert_eq!(sentence.words, ["去", "北京"]);
+        assert_eq!(sentence.ner.unwrap().to_vec(), ["O", "S-Ns"]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_process_tokens() -> Result<()> {
+        use crate::label::LabelSet;
+
+        struct Tags;
+
+        impl PipelineStage for Tags {
+            fn metadata(&self) -> StageMetadata {
+                StageMetadata::new("tags")
+                    .inputs(&[Annotation::Words])
+                    .outputs(&[Annotation::Ner])
+            }
+
+            fn process(&self, sentence: &mut Sentence) -> Result<()> {
+                let labels = LabelSet::new(["O", "B-Ns", "E-Ns"].map(String::from).into());
+                let ids: Vec<usize> = sentence
+                    .words
+                    .iter()
+                    .map(|word| match word.as_str() {
+                        "北" => 1,
+                        "京" => 2,
+                        _ => 0,
+                    })
+                    .collect();
+                sentence.ner = Some(InternedLabels::from_indices(labels, &ids)?);
+                Ok(())
+            }
+        }
+
+        let pipeline = LtpPipeline::new().add_stage(Whitespace).add_stage(Tags);
+        // 上游按字符计的位置，词之间有空格
+        let sentence = pipeline.process_tokens(&["去", "北", "京"], &[0..1, 2..3, 4..5])?;
+        assert_eq!(sentence.words, ["去", "北", "京"]);
+        assert_eq!(sentence.entity_spans()?, [("Ns", 2..5)]);
+        assert!(pipeline.process_tokens(&["去"], &[]).is_err());
+
+        let sentence = pipeline.process("去 北 京")?;
+        assert_eq!(sentence.entity_spans()?, [("Ns", 4..11)]);
+        Ok(())
+    }
+}
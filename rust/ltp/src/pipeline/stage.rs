@@ -0,0 +1,571 @@
+use crate::clean::{OffsetMap, TextCleaner};
+use crate::convert::Converter;
+#[cfg(feature = "ner")]
+use crate::dict::Gazetteer;
+#[cfg(feature = "pos")]
+use crate::dict::PosLexicon;
+use crate::dict::Reloadable;
+#[cfg(feature = "ner")]
+use crate::get_entities;
+use crate::hook::Hook;
+#[cfg(any(feature = "pos", feature = "ner"))]
+use crate::label::InternedLabels;
+#[cfg(feature = "ner")]
+use crate::label::LabelId;
+#[cfg(any(feature = "pos", feature = "ner"))]
+use crate::label::LabelSet;
+#[cfg(feature = "ner")]
+use crate::perceptron::Definition;
+#[cfg(any(feature = "cws", feature = "pos", feature = "ner"))]
+use crate::perceptron::{Perceptron, TraitFeature, TraitParameter, TraitParameterStorage};
+use crate::pipeline::Sentence;
+use crate::quantity::QuantityRecognizer;
+#[cfg(feature = "cws")]
+use crate::CWSDefinition;
+#[cfg(feature = "ner")]
+use crate::NERDefinition;
+#[cfg(feature = "pos")]
+use crate::POSDefinition;
+#[cfg(any(feature = "pos", feature = "ner"))]
+use anyhow::anyhow;
+use anyhow::Result;
+#[cfg(feature = "serialization")]
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+use std::fmt::{Display, Formatter};
+
+/// 流水线中各阶段读写的标注层
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Annotation {
+    Text,
+    Words,
+    Pos,
+    Ner,
+    /// 见 [`Sentence::quantities`]
+    Quantities,
+    /// 第三方阶段产出的自定义标注层
+    Custom(&'static str),
+}
+
+impl Display for Annotation {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Annotation::Text => write!(f, "text"),
+            Annotation::Words => write!(f, "words"),
+            Annotation::Pos => write!(f, "pos"),
+            Annotation::Ner => write!(f, "ner"),
+            Annotation::Quantities => write!(f, "quantities"),
+            Annotation::Custom(name) => write!(f, "{}", name),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StageMetadata {
+    pub name: Cow<'static, str>,
+    pub version: Cow<'static, str>,
+    /// 运行前必须已经存在的标注层
+    pub inputs: Vec<Annotation>,
+    /// 运行后写入（或改写）的标注层
+    pub outputs: Vec<Annotation>,
+}
+
+impl StageMetadata {
+    pub fn new(name: impl Into<Cow<'static, str>>) -> Self {
+        Self {
+            name: name.into(),
+            version: Cow::Borrowed("0"),
+            inputs: vec![],
+            outputs: vec![],
+        }
+    }
+
+    pub fn version(mut self, version: impl Into<Cow<'static, str>>) -> Self {
+        self.version = version.into();
+        self
+    }
+
+    pub fn inputs(mut self, inputs: &[Annotation]) -> Self {
+        self.inputs = inputs.to_vec();
+        self
+    }
+
+    pub fn outputs(mut self, outputs: &[Annotation]) -> Self {
+        self.outputs = outputs.to_vec();
+        self
+    }
+}
+
+/// 流水线阶段，第三方实现该 trait 即可插入到内置阶段之间
+///
+/// `process` 读取 `metadata().inputs` 中声明的标注层，并写入 `metadata().outputs` 中声明的标注层。
+pub trait PipelineStage: Send + Sync {
+    fn metadata(&self) -> StageMetadata;
+
+    fn process(&self, sentence: &mut Sentence) -> Result<()>;
+}
+
+#[cfg(feature = "cws")]
+pub struct CWSStage<Feature, ParamStorage, Param>
+where
+    Feature: TraitFeature,
+    ParamStorage: TraitParameterStorage<Param>,
+    Param: TraitParameter,
+{
+    model: Perceptron<CWSDefinition, Feature, ParamStorage, Param>,
+}
+
+#[cfg(feature = "cws")]
+impl<Feature, ParamStorage, Param> CWSStage<Feature, ParamStorage, Param>
+where
+    Feature: TraitFeature,
+    ParamStorage: TraitParameterStorage<Param>,
+    Param: TraitParameter,
+{
+    pub fn new(model: Perceptron<CWSDefinition, Feature, ParamStorage, Param>) -> Self {
+        Self { model }
+    }
+
+    pub fn model(&self) -> &Perceptron<CWSDefinition, Feature, ParamStorage, Param> {
+        &self.model
+    }
+}
+
+#[cfg(feature = "cws")]
+impl<Feature, ParamStorage, Param> PipelineStage for CWSStage<Feature, ParamStorage, Param>
+where
+    Feature: TraitFeature + Send + Sync,
+    ParamStorage: TraitParameterStorage<Param> + Send + Sync,
+    Param: TraitParameter + Send + Sync,
+{
+    fn metadata(&self) -> StageMetadata {
+        StageMetadata::new("cws")
+            .version(env!("CARGO_PKG_VERSION"))
+            .inputs(&[Annotation::Text])
+            .outputs(&[Annotation::Words])
+    }
+
+    fn process(&self, sentence: &mut Sentence) -> Result<()> {
+        let words = self.model.predict(&sentence.text)?;
+        sentence.words = words.into_iter().map(String::from).collect();
+        Ok(())
+    }
+}
+
+#[cfg(feature = "pos")]
+pub struct POSStage<Feature, ParamStorage, Param>
+where
+    Feature: TraitFeature,
+    ParamStorage: TraitParameterStorage<Param>,
+    Param: TraitParameter,
+{
+    model: Perceptron<POSDefinition, Feature, ParamStorage, Param>,
+    labels: LabelSet,
+}
+
+#[cfg(feature = "pos")]
+impl<Feature, ParamStorage, Param> POSStage<Feature, ParamStorage, Param>
+where
+    Feature: TraitFeature,
+    ParamStorage: TraitParameterStorage<Param>,
+    Param: TraitParameter,
+{
+    pub fn new(model: Perceptron<POSDefinition, Feature, ParamStorage, Param>) -> Self {
+        let labels = model.label_set();
+        Self { model, labels }
+    }
+
+    pub fn model(&self) -> &Perceptron<POSDefinition, Feature, ParamStorage, Param> {
+        &self.model
+    }
+}
+
+#[cfg(feature = "pos")]
+impl<Feature, ParamStorage, Param> PipelineStage for POSStage<Feature, ParamStorage, Param>
+where
+    Feature: TraitFeature + Send + Sync,
+    ParamStorage: TraitParameterStorage<Param> + Send + Sync,
+    Param: TraitParameter + Send + Sync,
+{
+    fn metadata(&self) -> StageMetadata {
+        StageMetadata::new("pos")
+            .version(env!("CARGO_PKG_VERSION"))
+            .inputs(&[Annotation::Words])
+            .outputs(&[Annotation::Pos])
+    }
+
+    fn process(&self, sentence: &mut Sentence) -> Result<()> {
+        let words = sentence.words();
+        let pos = self.model.predict_interned(&words, &self.labels)?;
+        sentence.pos = Some(pos);
+        Ok(())
+    }
+}
+
+#[cfg(feature = "ner")]
+pub struct NERStage<Feature, ParamStorage, Param>
+where
+    Feature: TraitFeature,
+    ParamStorage: TraitParameterStorage<Param>,
+    Param: TraitParameter,
+{
+    model: Perceptron<NERDefinition, Feature, ParamStorage, Param>,
+    labels: LabelSet,
+    min_confidence: Option<f64>,
+}
+
+#[cfg(feature = "ner")]
+impl<Feature, ParamStorage, Param> NERStage<Feature, ParamStorage, Param>
+where
+    Feature: TraitFeature,
+    ParamStorage: TraitParameterStorage<Param>,
+    Param: TraitParameter,
+{
+    pub fn new(model: Perceptron<NERDefinition, Feature, ParamStorage, Param>) -> Self {
+        let labels = model.label_set();
+        Self {
+            model,
+            labels,
+            min_confidence: None,
+        }
+    }
+
+    pub fn model(&self) -> &Perceptron<NERDefinition, Feature, ParamStorage, Param> {
+        &self.model
+    }
+
+    /// 实体中各词标签的平均后验概率低于阈值时，整个实体改为 `O`
+    pub fn min_confidence(mut self, threshold: f64) -> Self {
+        self.min_confidence = Some(threshold);
+        self
+    }
+
+    fn predict_filtered(
+        &self,
+        words: &[&str],
+        pos: &[&str],
+        threshold: f64,
+    ) -> Result<InternedLabels> {
+        let mut buffer = Vec::new();
+        let (mut preds, confidence) = self
+            .model
+            .predict_confidence_with_buffer(&(words, pos), &mut buffer)?;
+        if let Some(outside) = self.labels.id("O") {
+            let tags = self.model.definition.to_labels(&preds);
+            for (_, start, end) in get_entities(&tags) {
+                let mean = confidence[start..=end].iter().sum::<f64>() / (end - start + 1) as f64;
+                if mean < threshold {
+                    preds[start..=end].fill(outside.index());
+                }
+            }
+        }
+        Ok(InternedLabels::from_indices(self.labels.clone(), &preds)?)
+    }
+}
+
+#[cfg(feature = "ner")]
+impl<Feature, ParamStorage, Param> PipelineStage for NERStage<Feature, ParamStorage, Param>
+where
+    Feature: TraitFeature + Send + Sync,
+    ParamStorage: TraitParameterStorage<Param> + Send + Sync,
+    Param: TraitParameter + Send + Sync,
+{
+    fn metadata(&self) -> StageMetadata {
+        StageMetadata::new("ner")
+            .version(env!("CARGO_PKG_VERSION"))
+            .inputs(&[Annotation::Words, Annotation::Pos])
+            .outputs(&[Annotation::Ner])
+    }
+
+    fn process(&self, sentence: &mut Sentence) -> Result<()> {
+        let words = sentence.words();
+        let pos = sentence
+            .pos
+            .as_ref()
+            .ok_or_else(|| anyhow!("ner stage requires pos tags"))?
+            .to_vec();
+        let ner = match self.min_confidence {
+            Some(threshold) => self.predict_filtered(&words, &pos, threshold)?,
+            None => self.model.predict_interned((&words, &pos), &self.labels)?,
+        };
+        sentence.ner = Some(ner);
+        Ok(())
+    }
+}
+
+/// 分词前规范化输入文本，之后各阶段看到的 `text` 是规范化后的文本，
+/// 原文与位置映射保存在 [`Sentence::original`] 中
+#[cfg_attr(feature = "serialization", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serialization", serde(default, deny_unknown_fields))]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Normalization {
+    /// 去掉首尾的空白
+    pub trim: bool,
+    /// 全角字母、数字、标点与空格转为半角
+    pub fullwidth: bool,
+}
+
+impl Normalization {
+    pub fn is_enabled(&self) -> bool {
+        self.trim || self.fullwidth
+    }
+
+    pub fn normalize(&self, text: &str) -> String {
+        self.normalize_with_offsets(text).0
+    }
+
+    /// 同时返回规范化后的文本到原文的位置映射
+    pub fn normalize_with_offsets(&self, text: &str) -> (String, OffsetMap) {
+        let (start, trimmed) = match self.trim {
+            true => (text.len() - text.trim_start().len(), text.trim()),
+            false => (0, text),
+        };
+        let mut normalized = String::with_capacity(trimmed.len());
+        let mut map = OffsetMap::new(text.len());
+        for (index, ch) in trimmed.char_indices() {
+            let index = start + index;
+            map.push(normalized.len(), index..index + ch.len_utf8());
+            normalized.push(match ch {
+                '\u{3000}' if self.fullwidth => ' ',
+                '\u{FF01}'..='\u{FF5E}' if self.fullwidth => {
+                    char::from_u32(ch as u32 - 0xFEE0).unwrap_or(ch)
+                }
+                ch => ch,
+            });
+        }
+        (normalized, map)
+    }
+}
+
+impl PipelineStage for Normalization {
+    fn metadata(&self) -> StageMetadata {
+        StageMetadata::new("normalize")
+            .version(env!("CARGO_PKG_VERSION"))
+            .inputs(&[Annotation::Text])
+            .outputs(&[Annotation::Text])
+    }
+
+    fn process(&self, sentence: &mut Sentence) -> Result<()> {
+        let (text, map) = self.normalize_with_offsets(&sentence.text);
+        replace_text(sentence, text, map);
+        Ok(())
+    }
+}
+
+/// 分词前清洗文本，清洗前的文本与位置映射保存在 [`Sentence::original`] 中
+impl PipelineStage for TextCleaner {
+    fn metadata(&self) -> StageMetadata {
+        StageMetadata::new("clean")
+            .version(env!("CARGO_PKG_VERSION"))
+            .inputs(&[Annotation::Text])
+            .outputs(&[Annotation::Text])
+    }
+
+    fn process(&self, sentence: &mut Sentence) -> Result<()> {
+        let (text, map) = self.clean(&sentence.text);
+        replace_text(sentence, text, map);
+        Ok(())
+    }
+}
+
+/// 分词前做简繁转换，转换前的文本与位置映射保存在 [`Sentence::original`] 中，
+/// 可用 [`Sentence::original_words`] 取回原来字形的词
+impl PipelineStage for Converter {
+    fn metadata(&self) -> StageMetadata {
+        StageMetadata::new("convert")
+            .version(env!("CARGO_PKG_VERSION"))
+            .inputs(&[Annotation::Text])
+            .outputs(&[Annotation::Text])
+    }
+
+    fn process(&self, sentence: &mut Sentence) -> Result<()> {
+        let (text, map) = self.convert_with_offsets(&sentence.text);
+        replace_text(sentence, text, map);
+        Ok(())
+    }
+}
+
+/// 在分词结果上识别数值与日期，写入 [`Sentence::quantities`]
+impl PipelineStage for QuantityRecognizer {
+    fn metadata(&self) -> StageMetadata {
+        StageMetadata::new("quantity")
+            .version(env!("CARGO_PKG_VERSION"))
+            .inputs(&[Annotation::Words])
+            .outputs(&[Annotation::Quantities])
+    }
+
+    fn process(&self, sentence: &mut Sentence) -> Result<()> {
+        sentence.quantities = self.recognize(&sentence.words);
+        Ok(())
+    }
+}
+
+/// 替换句子的文本，并把位置映射与之前的映射合并，使其总是指向最初的输入
+fn replace_text(sentence: &mut Sentence, text: String, map: OffsetMap) {
+    let text = std::mem::replace(&mut sentence.text, text);
+    sentence.original = Some(match sentence.original.take() {
+        Some((original, previous)) => (original, previous.compose(&map)),
+        None => (text, map),
+    });
+}
+
+/// 按用户词典重新合并分词结果，见 [`Hook::hook`]
+pub struct UserDictStage {
+    hook: Reloadable<Hook>,
+}
+
+impl UserDictStage {
+    pub fn new(hook: Hook) -> Self {
+        Self::reloadable(Reloadable::new(hook))
+    }
+
+    pub fn reloadable(hook: Reloadable<Hook>) -> Self {
+        Self { hook }
+    }
+
+    /// 替换词典的句柄，阶段加入流水线后仍可通过它更新词典
+    pub fn handle(&self) -> Reloadable<Hook> {
+        self.hook.clone()
+    }
+}
+
+impl PipelineStage for UserDictStage {
+    fn metadata(&self) -> StageMetadata {
+        StageMetadata::new("user-dict")
+            .version(env!("CARGO_PKG_VERSION"))
+            .inputs(&[Annotation::Text, Annotation::Words])
+            .outputs(&[Annotation::Words])
+    }
+
+    fn process(&self, sentence: &mut Sentence) -> Result<()> {
+        let words = self.hook.get().hook(&sentence.text, &sentence.words());
+        sentence.words = words.into_iter().map(String::from).collect();
+        Ok(())
+    }
+}
+
+/// 词典中的词若预测的词性不在词典给出的词性中，改为词典中词频最高且模型支持的词性
+#[cfg(feature = "pos")]
+pub struct PosLexiconStage {
+    lexicon: Reloadable<PosLexicon>,
+}
+
+#[cfg(feature = "pos")]
+impl PosLexiconStage {
+    pub fn new(lexicon: PosLexicon) -> Self {
+        Self::reloadable(Reloadable::new(lexicon))
+    }
+
+    pub fn reloadable(lexicon: Reloadable<PosLexicon>) -> Self {
+        Self { lexicon }
+    }
+
+    pub fn handle(&self) -> Reloadable<PosLexicon> {
+        self.lexicon.clone()
+    }
+}
+
+#[cfg(feature = "pos")]
+impl PipelineStage for PosLexiconStage {
+    fn metadata(&self) -> StageMetadata {
+        StageMetadata::new("pos-lexicon")
+            .version(env!("CARGO_PKG_VERSION"))
+            .inputs(&[Annotation::Words, Annotation::Pos])
+            .outputs(&[Annotation::Pos])
+    }
+
+    fn process(&self, sentence: &mut Sentence) -> Result<()> {
+        let pos = sentence
+            .pos
+            .as_mut()
+            .ok_or_else(|| anyhow!("pos lexicon stage requires pos tags"))?;
+        let labels = pos.label_set().clone();
+        let mut ids = pos.ids().to_vec();
+        let lexicon = self.lexicon.get();
+        for (word, id) in sentence.words.iter().zip(ids.iter_mut()) {
+            let Some(tags) = lexicon.tags(word) else {
+                continue;
+            };
+            let tags: Vec<&str> = tags.collect();
+            if tags.contains(&&labels[*id]) {
+                continue;
+            }
+            if let Some(best) = tags.iter().find_map(|tag| labels.id(tag)) {
+                *id = best;
+            }
+        }
+        *pos = InternedLabels::new(labels, ids);
+        Ok(())
+    }
+}
+
+/// 按实体词典补充模型没有识别出的实体：词典中的实体只在其各词都被预测为 `O` 时写入，
+/// 模型的标签中没有对应类型的标签时忽略
+#[cfg(feature = "ner")]
+pub struct GazetteerStage {
+    gazetteer: Reloadable<Gazetteer>,
+}
+
+#[cfg(feature = "ner")]
+impl GazetteerStage {
+    pub fn new(gazetteer: Gazetteer) -> Self {
+        Self::reloadable(Reloadable::new(gazetteer))
+    }
+
+    pub fn reloadable(gazetteer: Reloadable<Gazetteer>) -> Self {
+        Self { gazetteer }
+    }
+
+    pub fn handle(&self) -> Reloadable<Gazetteer> {
+        self.gazetteer.clone()
+    }
+}
+
+/// 实体各词的标签，单个词为 `S-`，否则为 `B-`、`I-`（或 `M-`）与 `E-`
+#[cfg(feature = "ner")]
+fn entity_tags(labels: &LabelSet, kind: &str, len: usize) -> Option<Vec<LabelId>> {
+    let id = |prefix: &str| labels.id(&format!("{}-{}", prefix, kind));
+    if len == 1 {
+        return Some(vec![id("S")?]);
+    }
+    let middle = id("I").or_else(|| id("M"));
+    let mut tags = vec![id("B")?];
+    for _ in 2..len {
+        tags.push(middle?);
+    }
+    tags.push(id("E")?);
+    Some(tags)
+}
+
+#[cfg(feature = "ner")]
+impl PipelineStage for GazetteerStage {
+    fn metadata(&self) -> StageMetadata {
+        StageMetadata::new("gazetteer")
+            .version(env!("CARGO_PKG_VERSION"))
+            .inputs(&[Annotation::Words, Annotation::Ner])
+            .outputs(&[Annotation::Ner])
+    }
+
+    fn process(&self, sentence: &mut Sentence) -> Result<()> {
+        let ner = sentence
+            .ner
+            .as_mut()
+            .ok_or_else(|| anyhow!("gazetteer stage requires ner tags"))?;
+        let labels = ner.label_set().clone();
+        let Some(outside) = labels.id("O") else {
+            return Ok(());
+        };
+        let mut ids = ner.ids().to_vec();
+        let gazetteer = self.gazetteer.get();
+        for (span, kind) in gazetteer.find(&sentence.words) {
+            if ids[span.clone()].iter().any(|&id| id != outside) {
+                continue;
+            }
+            if let Some(tags) = entity_tags(&labels, kind, span.len()) {
+                ids[span].copy_from_slice(&tags);
+            }
+        }
+        *ner = InternedLabels::new(labels, ids);
+        Ok(())
+    }
+}
@@ -0,0 +1,40 @@
+//! 常用的公开接口：`use ltp::prelude::*;`
+//!
+//! 这里导出的类型遵循语义化版本，不兼容的修改只会出现在主版本号变化时；
+//! 其余模块中的类型（特征存储、参数存储、各种实验性的模型格式）可能在次版本中调整
+pub use crate::align::Alignment;
+pub use crate::error::LtpError;
+pub use crate::perceptron::{
+    Algorithm, Compression, Definition, EvalReport, PaMode, Perceptron, Sample, SequenceDefinition,
+    Trainer,
+};
+pub use crate::pipeline::{LtpPipeline, Paragraph, PipelineStage, Sentence, StageMetadata};
+pub use crate::script::{NonChinese, ScriptGuard};
+pub use crate::stnsplit::{
+    paragraph_spans, stn_split, stn_split_spans, stn_split_with_options, ParagraphBreak,
+    SplitOptions,
+};
+pub use crate::stopwords::Stopwords;
+pub use crate::tfidf::TermStats;
+pub use crate::{drop_get_entities, get_entities};
+
+#[cfg(feature = "cws")]
+pub use crate::CWSDefinition;
+#[cfg(feature = "ner")]
+pub use crate::NERDefinition;
+#[cfg(feature = "pos")]
+pub use crate::POSDefinition;
+
+#[cfg(feature = "serialization")]
+pub use crate::perceptron::{Format, ModelSerde};
+#[cfg(all(feature = "serialization", feature = "cws"))]
+pub use crate::CWSModel;
+#[cfg(all(feature = "serialization", feature = "ner"))]
+pub use crate::NERModel;
+#[cfg(all(feature = "serialization", feature = "pos"))]
+pub use crate::POSModel;
+#[cfg(all(
+    feature = "serialization",
+    any(feature = "cws", feature = "pos", feature = "ner")
+))]
+pub use crate::{DynInput, DynModel, DynOutput};
@@ -0,0 +1,335 @@
+//! 数值与日期：把分词结果中相邻的词组合为数、百分数与日期，并给出规范化的值，
+//! 如 `一千五百六十五` → 1565、`一九九○年` → 1990、`百分之五十` → 50%
+#[cfg(feature = "serialization")]
+use serde::{Deserialize, Serialize};
+use std::fmt::{Display, Formatter};
+use std::ops::Range;
+
+#[cfg_attr(feature = "serialization", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum QuantityValue {
+    Number(f64),
+    /// 百分数，`50%` 为 50
+    Percent(f64),
+    /// 至少有年或月
+    Date {
+        year: Option<i64>,
+        month: Option<u32>,
+        day: Option<u32>,
+    },
+}
+
+/// 解析得到的数不会是 NaN
+impl Eq for QuantityValue {}
+
+/// 按 ISO 8601 的写法输出日期，缺少年份时为 `--MM-DD`
+impl Display for QuantityValue {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match *self {
+            QuantityValue::Number(value) => write!(f, "{}", value),
+            QuantityValue::Percent(value) => write!(f, "{}%", value),
+            QuantityValue::Date { year, month, day } => {
+                match year {
+                    Some(year) => write!(f, "{:04}", year)?,
+                    None => write!(f, "-")?,
+                }
+                if let Some(month) = month {
+                    write!(f, "-{:02}", month)?;
+                }
+                if let Some(day) = day {
+                    write!(f, "-{:02}", day)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+/// 一个数值或日期，`words` 为其在 [`Sentence::words`](crate::Sentence::words) 中的下标区间
+#[cfg_attr(feature = "serialization", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Quantity {
+    pub words: Range<usize>,
+    pub value: QuantityValue,
+}
+
+fn digit(ch: char) -> Option<u32> {
+    let digit = match ch {
+        '0'..='9' => ch as u32 - '0' as u32,
+        '０'..='９' => ch as u32 - '０' as u32,
+        '零' | '〇' | '○' => 0,
+        '一' | '壹' => 1,
+        '二' | '两' | '贰' => 2,
+        '三' | '叁' => 3,
+        '四' | '肆' => 4,
+        '五' | '伍' => 5,
+        '六' | '陆' => 6,
+        '七' | '柒' => 7,
+        '八' | '捌' => 8,
+        '九' | '玖' => 9,
+        _ => return None,
+    };
+    Some(digit)
+}
+
+fn unit(ch: char) -> Option<f64> {
+    match ch {
+        '十' | '拾' => Some(10.0),
+        '百' | '佰' => Some(100.0),
+        '千' | '仟' => Some(1000.0),
+        '万' | '萬' => Some(1e4),
+        '亿' | '億' => Some(1e8),
+        _ => None,
+    }
+}
+
+/// 逐位读出的数，如 `一九九○`、`2024`，允许千分位的逗号
+fn parse_digits(text: &str) -> Option<f64> {
+    let mut value = 0.0;
+    let mut digits = 0;
+    for ch in text.chars() {
+        match ch {
+            ',' | '，' if digits > 0 => {}
+            ch => {
+                value = value * 10.0 + digit(ch)? as f64;
+                digits += 1;
+            }
+        }
+    }
+    (digits > 0).then_some(value)
+}
+
+/// 带单位的中文整数，如 `一千五百六十五`、`十五`、`三万`、`1.5亿`
+fn parse_integer(text: &str) -> Option<f64> {
+    if text.chars().all(|ch| unit(ch).is_none()) {
+        return parse_digits(text);
+    }
+    // 阿拉伯数字后接单位
+    let prefix = text
+        .find(|ch: char| unit(ch).is_some())
+        .unwrap_or(text.len());
+    if prefix > 0
+        && text[..prefix]
+            .chars()
+            .all(|ch| ch.is_ascii_digit() || ch == '.')
+    {
+        let value: f64 = text[..prefix].parse().ok()?;
+        return text[prefix..]
+            .chars()
+            .try_fold(value, |value, ch| Some(value * unit(ch)?));
+    }
+
+    let (mut total, mut wan, mut section, mut number) = (0.0, 0.0, 0.0, None);
+    let mut started = false;
+    for ch in text.chars() {
+        if let Some(d) = digit(ch) {
+            number = Some(d as f64);
+        } else {
+            let unit = unit(ch)?;
+            if unit < 1e4 {
+                section += match number {
+                    Some(n) => n * unit,
+                    // 开头的 `十` 即 `一十`，其余单位前必须有数
+                    None if unit == 10.0 && !started => unit,
+                    None => return None,
+                };
+            } else {
+                let group = section + number.unwrap_or_default();
+                // `万一`、`千万` 等不是数
+                if group == 0.0 && (unit == 1e4 || total + wan == 0.0) {
+                    return None;
+                }
+                if unit == 1e4 {
+                    wan += group * 1e4;
+                } else {
+                    total = (total + wan + group) * 1e8;
+                    wan = 0.0;
+                }
+                section = 0.0;
+            }
+            number = None;
+        }
+        started = true;
+    }
+    Some(total + wan + section + number.unwrap_or_default())
+}
+
+/// 解析中文或阿拉伯数字写的数，可带负号与小数
+pub fn parse_number(text: &str) -> Option<f64> {
+    let text = text.trim();
+    let (sign, text) = match text.strip_prefix(['负', '-', '－']) {
+        Some(rest) => (-1.0, rest),
+        None => (1.0, text),
+    };
+    let (integer, fraction) = match text.split_once(['点', '.', '．']) {
+        Some((integer, fraction)) if fraction.chars().all(|ch| unit(ch).is_none()) => {
+            (integer, Some(fraction))
+        }
+        // 如 `1.5亿`，小数点在单位之前
+        _ => (text, None),
+    };
+    let mut value = parse_integer(integer)?;
+    if let Some(fraction) = fraction {
+        let digits = fraction.chars().filter(|&ch| ch != ',').count() as i32;
+        value += parse_digits(fraction)? / 10f64.powi(digits);
+    }
+    Some(sign * value)
+}
+
+fn parse_percent(text: &str) -> Option<f64> {
+    if let Some(rest) = text.strip_prefix("百分之") {
+        return parse_number(rest);
+    }
+    parse_number(text.strip_suffix(['%', '％'])?)
+}
+
+fn parse_date(text: &str) -> Option<QuantityValue> {
+    let (year, rest) = match text.split_once('年') {
+        // `三年` 等是时长而不是年份
+        Some((year, _)) if year.chars().count() < 2 => return None,
+        Some((year, rest)) => (Some(parse_digits(year)? as i64), rest),
+        None => (None, text),
+    };
+    let (month, rest) = match rest.split_once('月') {
+        Some((month, rest)) => (Some(parse_integer(month)? as u32), rest),
+        None => (None, rest),
+    };
+    let day = match rest.strip_suffix(['日', '号']) {
+        Some(day) if month.is_some() => Some(parse_integer(day)? as u32),
+        None if rest.is_empty() => None,
+        _ => return None,
+    };
+    let valid = (year.is_some() || month.is_some())
+        && month.is_none_or(|m| (1..=12).contains(&m))
+        && day.is_none_or(|d| (1..=31).contains(&d));
+    valid.then_some(QuantityValue::Date { year, month, day })
+}
+
+/// 解析一段文本，整段都是数、百分数或日期时返回其值
+pub fn parse_quantity(text: &str) -> Option<QuantityValue> {
+    if text.is_empty() {
+        return None;
+    }
+    if let Some(date) = parse_date(text) {
+        return Some(date);
+    }
+    if let Some(percent) = parse_percent(text) {
+        return Some(QuantityValue::Percent(percent));
+    }
+    parse_number(text).map(QuantityValue::Number)
+}
+
+/// 在分词结果上从左到右取能组成数值或日期的最长的相邻词
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct QuantityRecognizer {
+    /// 一个数值或日期最多包含的词数
+    pub max_words: usize,
+}
+
+impl Default for QuantityRecognizer {
+    fn default() -> Self {
+        Self { max_words: 6 }
+    }
+}
+
+impl QuantityRecognizer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn max_words(mut self, max_words: usize) -> Self {
+        self.max_words = max_words.max(1);
+        self
+    }
+
+    pub fn recognize<S: AsRef<str>>(&self, words: &[S]) -> Vec<Quantity> {
+        let mut quantities = Vec::new();
+        let mut start = 0;
+        while start < words.len() {
+            let longest = (start + self.max_words).min(words.len());
+            let found = (start + 1..=longest).rev().find_map(|end| {
+                let text: String = words[start..end].iter().map(AsRef::as_ref).collect();
+                Some((end, parse_quantity(&text)?))
+            });
+            match found {
+                Some((end, value)) => {
+                    quantities.push(Quantity {
+                        words: start..end,
+                        value,
+                    });
+                    start = end;
+                }
+                None => start += 1,
+            }
+        }
+        quantities
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::LtpPipeline;
+    use anyhow::Result;
+
+    #[test]
+    fn test_quantity() -> Result<()> {
+        let number = |text| parse_number(text);
+        assert_eq!(number("一千五百六十五"), Some(1565.0));
+        assert_eq!(number("十五"), Some(15.0));
+        assert_eq!(number("一百零五"), Some(105.0));
+        assert_eq!(number("三万两千"), Some(32000.0));
+        assert_eq!(number("一亿两千万"), Some(1.2e8));
+        assert_eq!(number("1.5亿"), Some(1.5e8));
+        assert_eq!(number("负二点五"), Some(-2.5));
+        assert_eq!(number("１,５６５"), Some(1565.0));
+        assert_eq!(number("万一"), None);
+        assert_eq!(number("千万"), None);
+
+        let date = parse_quantity("一九九○年三月五日").unwrap();
+        assert_eq!(date.to_string(), "1990-03-05");
+        assert_eq!(parse_quantity("十二月").unwrap().to_string(), "--12");
+        assert_eq!(
+            parse_quantity("百分之五十"),
+            Some(QuantityValue::Percent(50.0))
+        );
+        assert_eq!(parse_quantity("十三月"), None);
+        assert_eq!(parse_quantity("三年"), None);
+
+        let words = [
+            "他",
+            "一九九○",
+            "年",
+            "出生",
+            "，",
+            "身高",
+            "一百八十",
+            "厘米",
+        ];
+        let quantities = QuantityRecognizer::new().recognize(&words);
+        assert_eq!(quantities.len(), 2);
+        assert_eq!(quantities[0].words, 1..3);
+        assert_eq!(quantities[0].value.to_string(), "1990");
+        assert_eq!(quantities[1].value, QuantityValue::Number(180.0));
+
+        struct Words;
+        impl crate::PipelineStage for Words {
+            fn metadata(&self) -> crate::StageMetadata {
+                let words = crate::pipeline::Annotation::Words;
+                crate::StageMetadata::new("words").outputs(&[words])
+            }
+            fn process(&self, sentence: &mut crate::Sentence) -> Result<()> {
+                sentence.words = sentence.text.split(' ').map(String::from).collect();
+                Ok(())
+            }
+        }
+        let pipeline = LtpPipeline::new()
+            .add_stage(Words)
+            .add_stage(QuantityRecognizer::new());
+        pipeline.validate()?;
+        let sentence = pipeline.process("涨 了 百分之 三点五")?;
+        assert_eq!(sentence.quantities[0].words, 2..4);
+        assert_eq!(sentence.quantities[0].value, QuantityValue::Percent(3.5));
+        Ok(())
+    }
+}
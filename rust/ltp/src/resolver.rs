@@ -0,0 +1,161 @@
+//! 模型获取：从 URL 或本地路径下载模型到缓存目录，并校验 SHA-256
+//!
+//! 缓存按内容寻址，文件名即为模型的 SHA-256，同一模型只会下载一次。
+//! 下载中断后保留 `.part` 文件，下次通过 HTTP Range 请求继续下载。
+use anyhow::{anyhow, Context, Result};
+use sha2::{Digest, Sha256};
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, Clone)]
+pub struct ModelResolver {
+    cache_dir: PathBuf,
+}
+
+impl Default for ModelResolver {
+    fn default() -> Self {
+        Self::new(Self::default_cache_dir())
+    }
+}
+
+impl ModelResolver {
+    pub fn new<P: Into<PathBuf>>(cache_dir: P) -> Self {
+        ModelResolver {
+            cache_dir: cache_dir.into(),
+        }
+    }
+
+    /// `$LTP_CACHE`，否则为 `~/.cache/ltp`
+    pub fn default_cache_dir() -> PathBuf {
+        if let Some(dir) = std::env::var_os("LTP_CACHE") {
+            return PathBuf::from(dir);
+        }
+        let home = std::env::var_os("HOME")
+            .or_else(|| std::env::var_os("USERPROFILE"))
+            .map(PathBuf::from)
+            .unwrap_or_else(std::env::temp_dir);
+        home.join(".cache").join("ltp")
+    }
+
+    pub fn cache_dir(&self) -> &Path {
+        &self.cache_dir
+    }
+
+    pub fn cached_path(&self, sha256: &str) -> PathBuf {
+        self.cache_dir.join(sha256.to_ascii_lowercase())
+    }
+
+    /// 返回校验通过的本地缓存路径，必要时先下载。
+    /// `source` 可以是 `http(s)://` URL、`file://` URL 或本地路径。
+    pub fn resolve(&self, source: &str, sha256: &str) -> Result<PathBuf> {
+        let sha256 = sha256.to_ascii_lowercase();
+        if sha256.len() != 64 || !sha256.bytes().all(|b| b.is_ascii_hexdigit()) {
+            return Err(anyhow!("invalid sha256 checksum: {}", sha256));
+        }
+
+        let target = self.cached_path(&sha256);
+        if target.exists() {
+            if sha256_file(&target)? == sha256 {
+                return Ok(target);
+            }
+            fs::remove_file(&target)?;
+        }
+
+        fs::create_dir_all(&self.cache_dir)
+            .with_context(|| format!("failed to create {}", self.cache_dir.display()))?;
+        let partial = self.cache_dir.join(format!("{}.part", sha256));
+
+        if source.starts_with("http://") || source.starts_with("https://") {
+            download(source, &partial)?;
+        } else {
+            let path = source.strip_prefix("file://").unwrap_or(source);
+            fs::copy(path, &partial).with_context(|| format!("failed to read {}", path))?;
+        }
+
+        let actual = sha256_file(&partial)?;
+        if actual != sha256 {
+            fs::remove_file(&partial)?;
+            return Err(anyhow!(
+                "checksum mismatch for {}: expected {}, got {}",
+                source,
+                sha256,
+                actual
+            ));
+        }
+        fs::rename(&partial, &target)?;
+        Ok(target)
+    }
+}
+
+fn download(url: &str, partial: &Path) -> Result<()> {
+    let downloaded = fs::metadata(partial).map(|m| m.len()).unwrap_or(0);
+    let mut request = ureq::get(url);
+    if downloaded > 0 {
+        request = request.set("Range", &format!("bytes={}-", downloaded));
+    }
+    let response = match request.call() {
+        Ok(response) => response,
+        // `.part` 已经完整，交给校验和判断
+        Err(ureq::Error::Status(416, _)) if downloaded > 0 => return Ok(()),
+        Err(err) => return Err(err).with_context(|| format!("failed to download {}", url)),
+    };
+
+    // 服务器不支持断点续传时重新下载
+    let mut file = if response.status() == 206 {
+        OpenOptions::new().append(true).open(partial)?
+    } else {
+        File::create(partial)?
+    };
+    io::copy(&mut response.into_reader(), &mut file)?;
+    file.flush()?;
+    Ok(())
+}
+
+pub fn sha256_file<P: AsRef<Path>>(path: P) -> Result<String> {
+    let mut file = File::open(path)?;
+    let mut hasher = Sha256::new();
+    let mut buffer = vec![0u8; 64 * 1024];
+    loop {
+        let n = file.read(&mut buffer)?;
+        if n == 0 {
+            break;
+        }
+        hasher.update(&buffer[..n]);
+    }
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resolve_local() -> Result<()> {
+        let dir = std::env::temp_dir().join(format!("ltp-resolver-{}", std::process::id()));
+        fs::create_dir_all(&dir)?;
+        let source = dir.join("model.bin");
+        fs::write(&source, b"ltp model")?;
+        let sha256 = sha256_file(&source)?;
+
+        let resolver = ModelResolver::new(dir.join("cache"));
+        let path = resolver.resolve(source.to_str().unwrap(), &sha256)?;
+        assert_eq!(path, resolver.cached_path(&sha256));
+        assert_eq!(fs::read(&path)?, b"ltp model");
+
+        // 已缓存时不再读取源文件
+        fs::remove_file(&source)?;
+        assert_eq!(resolver.resolve("missing.bin", &sha256)?, path);
+
+        fs::write(&source, b"tampered")?;
+        let wrong = "0".repeat(64);
+        let err = resolver
+            .resolve(&format!("file://{}", source.display()), &wrong)
+            .unwrap_err();
+        assert!(err.to_string().contains("checksum mismatch"));
+        assert!(!resolver.cached_path(&wrong).exists());
+
+        fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+}
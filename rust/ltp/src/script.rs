@@ -0,0 +1,302 @@
+//! 文字与语言检测：按 Unicode 区块统计各文字的字符数，可选字符 n-gram 模型区分同一文字的语言，
+//! 流水线据此跳过或另行处理非中文的句子，避免对英文段落做无意义的分词
+#[cfg(feature = "serialization")]
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+/// 字母所属的文字，数字、标点与空白不属于任何文字
+#[cfg_attr(feature = "serialization", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Script {
+    Han,
+    /// 平假名与片假名
+    Kana,
+    Hangul,
+    Latin,
+    Cyrillic,
+    Arabic,
+    Other,
+}
+
+impl Script {
+    pub const ALL: [Script; 7] = [
+        Script::Han,
+        Script::Kana,
+        Script::Hangul,
+        Script::Latin,
+        Script::Cyrillic,
+        Script::Arabic,
+        Script::Other,
+    ];
+
+    pub fn of(ch: char) -> Option<Script> {
+        let script = match u32::from(ch) {
+            0x2E80..=0x2FDF
+            | 0x3005
+            | 0x3007
+            | 0x3021..=0x3029
+            | 0x3400..=0x4DBF
+            | 0x4E00..=0x9FFF
+            | 0xF900..=0xFAFF
+            | 0x20000..=0x323AF => Script::Han,
+            0x3040..=0x30FF | 0x31F0..=0x31FF | 0xFF66..=0xFF9D => Script::Kana,
+            0x1100..=0x11FF | 0x3130..=0x318F | 0xAC00..=0xD7AF => Script::Hangul,
+            _ if !ch.is_alphabetic() => return None,
+            0x41..=0x5A | 0x61..=0x7A | 0xC0..=0x24F | 0x1E00..=0x1EFF => Script::Latin,
+            0xFF21..=0xFF3A | 0xFF41..=0xFF5A => Script::Latin,
+            0x400..=0x52F => Script::Cyrillic,
+            0x600..=0x6FF | 0x750..=0x77F => Script::Arabic,
+            _ => Script::Other,
+        };
+        Some(script)
+    }
+}
+
+/// 文本中各文字的字符数
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ScriptStats {
+    counts: [usize; Script::ALL.len()],
+}
+
+impl ScriptStats {
+    pub fn new(text: &str) -> Self {
+        let mut stats = Self::default();
+        for script in text.chars().filter_map(Script::of) {
+            stats.counts[script as usize] += 1;
+        }
+        stats
+    }
+
+    /// 属于某种文字的字符总数
+    pub fn letters(&self) -> usize {
+        self.counts.iter().sum()
+    }
+
+    pub fn count(&self, script: Script) -> usize {
+        self.counts[script as usize]
+    }
+
+    /// 占全部文字字符的比例，没有文字字符时为 0
+    pub fn ratio(&self, script: Script) -> f64 {
+        match self.letters() {
+            0 => 0.0,
+            letters => self.count(script) as f64 / letters as f64,
+        }
+    }
+
+    /// 字符最多的文字，个数相同时取 [`Script::ALL`] 中靠前的
+    pub fn dominant(&self) -> Option<Script> {
+        Script::ALL
+            .into_iter()
+            .rev()
+            .max_by_key(|&script| self.count(script))
+            .filter(|&script| self.count(script) > 0)
+    }
+}
+
+/// 字符 n-gram 的朴素贝叶斯语言模型，加一平滑
+#[cfg_attr(feature = "serialization", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NgramModel {
+    n: usize,
+    languages: BTreeMap<String, HashMap<String, u64>>,
+}
+
+impl NgramModel {
+    pub fn new(n: usize) -> Self {
+        Self {
+            n: n.max(1),
+            languages: BTreeMap::new(),
+        }
+    }
+
+    /// 小写并合并空白，首尾补空格，使 n-gram 包含词的边界
+    fn grams(&self, text: &str) -> Vec<String> {
+        let words: Vec<String> = text.split_whitespace().map(str::to_lowercase).collect();
+        let chars: Vec<char> = format!(" {} ", words.join(" ")).chars().collect();
+        chars
+            .windows(self.n)
+            .filter(|gram| gram.iter().any(|ch| !ch.is_whitespace()))
+            .map(String::from_iter)
+            .collect()
+    }
+
+    pub fn train(&mut self, language: &str, text: &str) {
+        let grams = self.grams(text);
+        let counts = self.languages.entry(language.to_string()).or_default();
+        for gram in grams {
+            *counts.entry(gram).or_default() += 1;
+        }
+    }
+
+    pub fn languages(&self) -> impl Iterator<Item = &str> {
+        self.languages.keys().map(String::as_str)
+    }
+
+    /// 对数似然最大的语言，模型为空或文本没有 n-gram 时返回 `None`
+    pub fn detect(&self, text: &str) -> Option<&str> {
+        let grams = self.grams(text);
+        if grams.is_empty() {
+            return None;
+        }
+        let vocabulary: HashSet<&String> = self.languages.values().flat_map(|c| c.keys()).collect();
+        let vocabulary = vocabulary.len() as f64 + 1.0;
+        let score = |counts: &HashMap<String, u64>| {
+            let total = counts.values().sum::<u64>() as f64 + vocabulary;
+            grams
+                .iter()
+                .map(|gram| {
+                    let count = counts.get(gram).copied().unwrap_or_default();
+                    ((count as f64 + 1.0) / total).ln()
+                })
+                .sum::<f64>()
+        };
+        self.languages
+            .iter()
+            .map(|(language, counts)| (language.as_str(), score(counts)))
+            .max_by(|a, b| a.1.total_cmp(&b.1))
+            .map(|(language, _)| language)
+    }
+}
+
+/// 非中文句子的处理方式
+#[cfg_attr(feature = "serialization", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serialization", serde(rename_all = "kebab-case"))]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum NonChinese {
+    /// 照常运行全部阶段，只记录检测到的文字
+    Process,
+    /// 不运行任何阶段
+    #[default]
+    Skip,
+    /// 按空白与标点切分为词，不运行任何阶段
+    Whitespace,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ScriptGuard {
+    /// 汉字占全部文字字符的比例不低于该值才视为中文
+    pub min_han_ratio: f64,
+    /// 假名与谚文的比例不低于该值时视为日文或韩文
+    pub max_kana_ratio: f64,
+    /// 按文字统计判断为中文后，再由模型确认
+    pub model: Option<NgramModel>,
+    /// 模型中中文的语言名
+    pub chinese: String,
+    pub policy: NonChinese,
+}
+
+impl Default for ScriptGuard {
+    fn default() -> Self {
+        Self {
+            min_han_ratio: 0.3,
+            max_kana_ratio: 0.1,
+            model: None,
+            chinese: "zh".to_string(),
+            policy: NonChinese::default(),
+        }
+    }
+}
+
+impl ScriptGuard {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn min_han_ratio(mut self, ratio: f64) -> Self {
+        self.min_han_ratio = ratio;
+        self
+    }
+
+    pub fn model(mut self, model: NgramModel, chinese: &str) -> Self {
+        self.model = Some(model);
+        self.chinese = chinese.to_string();
+        self
+    }
+
+    pub fn policy(mut self, policy: NonChinese) -> Self {
+        self.policy = policy;
+        self
+    }
+
+    /// 只有数字与标点的文本视为中文
+    pub fn is_chinese(&self, text: &str) -> bool {
+        self.is_chinese_with(text, &ScriptStats::new(text))
+    }
+
+    pub(crate) fn is_chinese_with(&self, text: &str, stats: &ScriptStats) -> bool {
+        if stats.letters() == 0 {
+            return true;
+        }
+        let kana = stats.ratio(Script::Kana) + stats.ratio(Script::Hangul);
+        if stats.ratio(Script::Han) < self.min_han_ratio || kana >= self.max_kana_ratio {
+            return false;
+        }
+        match &self.model {
+            Some(model) => model.detect(text).is_none_or(|lang| lang == self.chinese),
+            None => true,
+        }
+    }
+}
+
+/// 按空白切分，标点与符号单独成词
+pub fn whitespace_tokenize(text: &str) -> Vec<String> {
+    let mut words = Vec::new();
+    let mut word = String::new();
+    for ch in text.chars() {
+        if ch.is_alphanumeric() {
+            word.push(ch);
+            continue;
+        }
+        if !word.is_empty() {
+            words.push(std::mem::take(&mut word));
+        }
+        if !ch.is_whitespace() {
+            words.push(ch.to_string());
+        }
+    }
+    if !word.is_empty() {
+        words.push(word);
+    }
+    words
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::LtpPipeline;
+    use anyhow::Result;
+
+    #[test]
+    fn test_script_guard() -> Result<()> {
+        let stats = ScriptStats::new("iPhone 15 发布了！");
+        assert_eq!(
+            (stats.count(Script::Latin), stats.count(Script::Han)),
+            (6, 3)
+        );
+        assert_eq!(stats.dominant(), Some(Script::Latin));
+        assert_eq!(ScriptStats::new("2024。").dominant(), None);
+
+        let guard = ScriptGuard::new();
+        assert!(guard.is_chinese("iPhone 15 发布了！"));
+        assert!(guard.is_chinese("2024。"));
+        assert!(!guard.is_chinese("The quick brown fox jumps over the lazy dog."));
+        assert!(!guard.is_chinese("東京に行きます"));
+
+        let mut model = NgramModel::new(2);
+        model.train("en", "the cat sat on the mat and the dog ate the bone");
+        model.train("fr", "le chat est sur la table et le chien mange");
+        assert_eq!(model.detect("the dog and the cat"), Some("en"));
+        assert_eq!(model.detect("le chien et le chat"), Some("fr"));
+        assert_eq!(model.detect("  "), None);
+
+        let pipeline = LtpPipeline::new().script_guard(guard.policy(NonChinese::Whitespace));
+        let sentence = pipeline.process("Hello, world!")?;
+        assert_eq!(sentence.words, ["Hello", ",", "world", "!"]);
+        assert_eq!(sentence.script, Some(Script::Latin));
+        let sentence = pipeline.process("我爱北京")?;
+        assert!(sentence.words.is_empty());
+        assert_eq!(sentence.script, Some(Script::Han));
+        Ok(())
+    }
+}
@@ -1,8 +1,22 @@
 #[cfg(feature = "serialization")]
 use serde::{Deserialize, Serialize};
 use smallvec::SmallVec;
+use std::ops::Range;
 
+/// 段落的划分方式，空白行总是段落之间的分隔
 #[cfg_attr(feature = "serialization", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serialization", serde(rename_all = "kebab-case"))]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ParagraphBreak {
+    /// 以空白行分段，段内的单个换行只作为分句的边界
+    #[default]
+    BlankLine,
+    /// 每个非空行为一个段落
+    Newline,
+}
+
+#[cfg_attr(feature = "serialization", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serialization", serde(default))]
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub struct SplitOptions {
     pub use_zh: bool,
@@ -10,6 +24,14 @@ pub struct SplitOptions {
     pub bracket_as_entity: bool,
     pub zh_quote_as_entity: bool,
     pub en_quote_as_entity: bool,
+    /// 额外的句末标点，如 `；`、`;`
+    pub terminators: Vec<char>,
+    /// 省略号是否结束句子，否则省略号连同其后的内容留在当前句中
+    pub ellipsis_as_terminator: bool,
+    /// 超过该字符数的句子在其中最后一个逗号、分号或空白之后强制切分，找不到时直接截断
+    pub max_length: Option<usize>,
+    /// 段落的划分方式，见 [`paragraph_spans`]
+    pub paragraph: ParagraphBreak,
 }
 
 impl Default for SplitOptions {
@@ -20,22 +42,27 @@ impl Default for SplitOptions {
             bracket_as_entity: true,
             zh_quote_as_entity: true,
             en_quote_as_entity: true,
+            terminators: Vec::new(),
+            ellipsis_as_terminator: true,
+            max_length: None,
+            paragraph: ParagraphBreak::BlankLine,
         }
     }
 }
 
 pub fn stn_split(text: &str) -> Vec<&str> {
-    let option = SplitOptions {
-        use_zh: true,
-        use_en: true,
-        bracket_as_entity: true,
-        zh_quote_as_entity: true,
-        en_quote_as_entity: true,
-    };
-    stn_split_with_options(text, &option)
+    stn_split_with_options(text, &SplitOptions::default())
 }
 
 pub fn stn_split_with_options<'a, 'b>(text: &'a str, options: &'b SplitOptions) -> Vec<&'a str> {
+    stn_split_spans(text, options)
+        .into_iter()
+        .map(|span| &text[span])
+        .collect()
+}
+
+/// 分句，返回每个句子在 `text` 中的字节区间
+pub fn stn_split_spans(text: &str, options: &SplitOptions) -> Vec<Range<usize>> {
     let mut res = vec![];
     let mut char_indices = text.char_indices().peekable();
     let mut quotes = SmallVec::<[char; 4]>::new();
@@ -66,7 +93,7 @@ pub fn stn_split_with_options<'a, 'b>(text: &'a str, options: &'b SplitOptions)
                 '\r' | '\n' => {
                     // skip empty sentence
                     if idx > next_start {
-                        res.push(&text[next_start..idx]); // skip the '\n'
+                        res.push(next_start..idx); // skip the '\n'
                     }
                     next_start = idx + 1;
 
@@ -120,7 +147,7 @@ pub fn stn_split_with_options<'a, 'b>(text: &'a str, options: &'b SplitOptions)
                     // 省略号不能作为开头
                     // 对于省略号啥也不干
                     if options.use_zh || options.use_en {
-                        end_flag = true;
+                        end_flag = options.ellipsis_as_terminator;
                         as_normal = false;
                     }
                 }
@@ -178,12 +205,17 @@ pub fn stn_split_with_options<'a, 'b>(text: &'a str, options: &'b SplitOptions)
                         as_normal = false;
                     }
                 }
-                _ => {}
+                _ => {
+                    if options.terminators.contains(&ch) {
+                        end_flag = true;
+                        as_normal = false;
+                    }
+                }
             }
             if as_normal {
                 if end_flag && quotes.is_empty() {
                     if idx > next_start {
-                        res.push(&text[next_start..idx]);
+                        res.push(next_start..idx);
                     }
                     next_start = idx;
                 }
@@ -202,15 +234,66 @@ pub fn stn_split_with_options<'a, 'b>(text: &'a str, options: &'b SplitOptions)
             last_char = ch;
         } else {
             if next_start < text.len() {
-                res.push(&text[next_start..]);
+                res.push(next_start..text.len());
             }
             break;
         }
     }
 
+    match options.max_length {
+        Some(max_length) if max_length > 0 => res
+            .into_iter()
+            .flat_map(|span| force_split(text, span, max_length))
+            .collect(),
+        _ => res,
+    }
+}
+
+/// 分段，返回每个段落在 `text` 中的字节区间，不含首尾的空白行与行尾的换行符
+pub fn paragraph_spans(text: &str, policy: ParagraphBreak) -> Vec<Range<usize>> {
+    let mut res = vec![];
+    let mut current: Option<Range<usize>> = None;
+    let mut start = 0;
+    for line in text.split_inclusive('\n') {
+        let end = start + line.trim_end_matches(['\r', '\n']).len();
+        if text[start..end].trim().is_empty() {
+            res.extend(current.take());
+        } else {
+            match policy {
+                ParagraphBreak::BlankLine => {
+                    current = Some(current.map_or(start, |paragraph| paragraph.start)..end)
+                }
+                ParagraphBreak::Newline => res.push(start..end),
+            }
+        }
+        start += line.len();
+    }
+    res.extend(current);
     res
 }
 
+/// 把超过 `max_length` 个字符的句子切成若干段，优先在逗号、分号或空白之后切分
+fn force_split(text: &str, span: Range<usize>, max_length: usize) -> Vec<Range<usize>> {
+    let mut res = vec![];
+    let mut start = span.start;
+    loop {
+        let rest = &text[start..span.end];
+        let Some((limit, _)) = rest.char_indices().nth(max_length) else {
+            res.push(start..span.end);
+            return res;
+        };
+        let cut = rest[..limit]
+            .char_indices()
+            .rev()
+            .find(|&(_, ch)| {
+                matches!(ch, '，' | ',' | '；' | ';' | '、' | '：' | ':') || ch.is_whitespace()
+            })
+            .map_or(limit, |(i, ch)| i + ch.len_utf8());
+        res.push(start..start + cut);
+        start += cut;
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -325,6 +408,47 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_configurable() {
+        let text = "第一句；第二句……第三句。";
+        assert_eq!(stn_split(text), vec!["第一句；第二句……", "第三句。"]);
+
+        let options = SplitOptions {
+            terminators: vec!['；'],
+            ellipsis_as_terminator: false,
+            ..Default::default()
+        };
+        assert_eq!(
+            stn_split_with_options(text, &options),
+            vec!["第一句；", "第二句……第三句。"]
+        );
+        // 字节区间可以直接在原文中定位
+        let spans = stn_split_spans(text, &options);
+        assert_eq!(spans, vec![0..12, 12..text.len()]);
+
+        let options = SplitOptions {
+            max_length: Some(6),
+            ..Default::default()
+        };
+        assert_eq!(
+            stn_split_with_options("一二三，四五六七八九十。短句。", &options),
+            vec!["一二三，", "四五六七八九", "十。", "短句。"]
+        );
+    }
+
+    #[test]
+    fn test_paragraphs() {
+        let text = "\n第一段。\r\n还是第一段。\n \n\n第二段。\n";
+        let spans = paragraph_spans(text, ParagraphBreak::BlankLine);
+        let paragraphs: Vec<_> = spans.into_iter().map(|span| &text[span]).collect();
+        assert_eq!(paragraphs, vec!["第一段。\r\n还是第一段。", "第二段。"]);
+
+        let spans = paragraph_spans(text, ParagraphBreak::Newline);
+        let paragraphs: Vec<_> = spans.into_iter().map(|span| &text[span]).collect();
+        assert_eq!(paragraphs, vec!["第一段。", "还是第一段。", "第二段。"]);
+        assert!(paragraph_spans(" \n", ParagraphBreak::BlankLine).is_empty());
+    }
+
     #[test]
     fn test_mix() {
         // chinese & english & point number
@@ -0,0 +1,175 @@
+//! 停用词表与对分词结果的过滤
+//!
+//! 词表文件每行一个词，空行与 `#` 开头的注释忽略，可以是 gzip、zstd 压缩的。
+#[cfg(feature = "fs")]
+use crate::perceptron::CompressedReader;
+use crate::pipeline::Sentence;
+use crate::InternedLabels;
+use anyhow::Result;
+use std::collections::HashSet;
+use std::io::{BufRead, BufReader, Read};
+#[cfg(feature = "fs")]
+use std::path::Path;
+
+static CHINESE: &str = include_str!(concat!(
+    env!("CARGO_MANIFEST_DIR"),
+    "/vendor/stopwords/zh.txt"
+));
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Stopwords {
+    words: HashSet<String>,
+    /// 只由标点与符号组成的词也视为停用词
+    pub punctuation: bool,
+}
+
+impl Stopwords {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 内置的中文停用词表，包括常用的代词、助词、介词、连词与语气词，同时过滤标点
+    pub fn chinese() -> Self {
+        let mut stopwords = Self::new().punctuation(true);
+        stopwords.extend_lines(CHINESE);
+        stopwords
+    }
+
+    pub fn punctuation(mut self, punctuation: bool) -> Self {
+        self.punctuation = punctuation;
+        self
+    }
+
+    pub fn from_reader<R: Read>(reader: R) -> Result<Self> {
+        let mut stopwords = Self::new();
+        for line in BufReader::new(reader).lines() {
+            stopwords.extend_lines(&line?);
+        }
+        Ok(stopwords)
+    }
+
+    #[cfg(feature = "fs")]
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self> {
+        Self::from_reader(CompressedReader::open(path)?)
+    }
+
+    /// 加入用户词表中的词
+    #[cfg(feature = "fs")]
+    pub fn extend_file<P: AsRef<Path>>(&mut self, path: P) -> Result<()> {
+        self.words.extend(Self::from_file(path)?.words);
+        Ok(())
+    }
+
+    fn extend_lines(&mut self, text: &str) {
+        let words = text
+            .lines()
+            .map(|line| line.trim_start_matches('\u{feff}').trim())
+            .filter(|line| !line.is_empty() && !line.starts_with('#'));
+        self.words.extend(words.map(String::from));
+    }
+
+    pub fn insert<W: Into<String>>(&mut self, word: W) -> bool {
+        self.words.insert(word.into())
+    }
+
+    pub fn remove(&mut self, word: &str) -> bool {
+        self.words.remove(word)
+    }
+
+    pub fn len(&self) -> usize {
+        self.words.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.words.is_empty()
+    }
+
+    pub fn is_stopword(&self, word: &str) -> bool {
+        let word = word.trim();
+        word.is_empty()
+            || self.words.contains(word)
+            || (self.punctuation && word.chars().all(|ch| !ch.is_alphanumeric()))
+    }
+
+    /// 保留的词的下标，可用于对齐词性等逐词标注
+    pub fn positions<S: AsRef<str>>(&self, words: &[S]) -> Vec<usize> {
+        (0..words.len())
+            .filter(|&i| !self.is_stopword(words[i].as_ref()))
+            .collect()
+    }
+
+    pub fn filter<'a, S: AsRef<str>>(&self, words: &'a [S]) -> Vec<&'a str> {
+        let words = words.iter().map(AsRef::as_ref);
+        words.filter(|word| !self.is_stopword(word)).collect()
+    }
+
+    /// 去掉停用词，词性与实体标签随词一起去掉；实体由多个词组成时可能被截断，
+    /// 自定义阶段的输出中与词数相同的一并过滤
+    pub fn filter_sentence(&self, sentence: &Sentence) -> Sentence {
+        let positions = self.positions(&sentence.words);
+        let pick = |labels: &InternedLabels| {
+            let ids = positions.iter().map(|&i| labels.ids()[i]).collect();
+            InternedLabels::new(labels.label_set().clone(), ids)
+        };
+        let mut filtered = sentence.clone();
+        filtered.words = positions
+            .iter()
+            .map(|&i| sentence.words[i].clone())
+            .collect();
+        filtered.pos = sentence.pos.as_ref().map(pick);
+        filtered.ner = sentence.ner.as_ref().map(pick);
+        filtered.token_spans = sentence
+            .token_spans
+            .as_ref()
+            .map(|spans| positions.iter().map(|&i| spans[i].clone()).collect());
+        for values in filtered.custom.values_mut() {
+            if values.len() == sentence.words.len() {
+                *values = positions.iter().map(|&i| values[i].clone()).collect();
+            }
+        }
+        filtered
+    }
+}
+
+impl Sentence {
+    /// 去掉停用词后的词
+    pub fn content_words(&self, stopwords: &Stopwords) -> Vec<&str> {
+        stopwords.filter(&self.words)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::LabelSet;
+
+    #[test]
+    fn test_stopwords() -> Result<()> {
+        let mut stopwords = Stopwords::chinese();
+        assert!(stopwords.is_stopword("的") && stopwords.is_stopword("，"));
+        stopwords.extend_lines("# 用户词表\n汤姆\n");
+        stopwords.remove("我们");
+
+        let mut sentence = Sentence::new("我们和汤姆去了北京。");
+        sentence.words = ["我们", "和", "汤姆", "去", "了", "北京", "。"]
+            .map(String::from)
+            .into();
+        let labels = LabelSet::new(
+            ["r", "c", "nh", "v", "u", "ns", "wp"]
+                .map(String::from)
+                .into(),
+        );
+        let ids = [0, 1, 2, 3, 4, 5, 6];
+        sentence.pos = Some(InternedLabels::from_indices(labels, &ids)?);
+        assert_eq!(sentence.content_words(&stopwords), ["我们", "去", "北京"]);
+
+        let filtered = stopwords.filter_sentence(&sentence);
+        assert_eq!(filtered.words, ["我们", "去", "北京"]);
+        assert_eq!(filtered.pos.unwrap().to_vec(), ["r", "v", "ns"]);
+
+        let custom = Stopwords::from_reader("\u{feff}的\n\n".as_bytes())?;
+        assert_eq!(custom.len(), 1);
+        assert_eq!(custom.filter(&["好", "的", "。"]), ["好", "。"]);
+        Ok(())
+    }
+}
@@ -0,0 +1,284 @@
+//! 词频、文档频率与 TF-IDF，可以逐个文档增量统计流水线的输出
+//!
+//! 词表很大时可以用 count-min sketch 代替精确计数，内存占用固定，估计值只会偏大。
+use crate::pipeline::Sentence;
+use crate::stopwords::Stopwords;
+use anyhow::{anyhow, Result};
+#[cfg(feature = "serialization")]
+use serde::{Deserialize, Serialize};
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{HashMap, HashSet};
+use std::hash::{Hash, Hasher};
+
+/// `depth` 行、每行 `width` 个计数器，估计值取各行的最小值
+#[cfg_attr(feature = "serialization", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CountMinSketch {
+    width: usize,
+    depth: usize,
+    table: Vec<u64>,
+}
+
+impl CountMinSketch {
+    pub fn new(width: usize, depth: usize) -> Self {
+        let (width, depth) = (width.max(1), depth.max(1));
+        Self {
+            width,
+            depth,
+            table: vec![0; width * depth],
+        }
+    }
+
+    /// 以不低于 `1 - delta` 的概率，估计值的误差不超过总数的 `epsilon` 倍
+    pub fn with_error(epsilon: f64, delta: f64) -> Self {
+        let width = (std::f64::consts::E / epsilon).ceil() as usize;
+        let depth = (1.0 / delta).ln().ceil() as usize;
+        Self::new(width, depth)
+    }
+
+    fn cell(&self, row: usize, item: &str) -> usize {
+        // 固定种子，结果在不同进程间可以复现、合并
+        let mut hasher = DefaultHasher::new();
+        row.hash(&mut hasher);
+        item.hash(&mut hasher);
+        row * self.width + (hasher.finish() % self.width as u64) as usize
+    }
+
+    pub fn add(&mut self, item: &str, count: u64) {
+        for row in 0..self.depth {
+            let cell = self.cell(row, item);
+            self.table[cell] += count;
+        }
+    }
+
+    pub fn estimate(&self, item: &str) -> u64 {
+        (0..self.depth)
+            .map(|row| self.table[self.cell(row, item)])
+            .min()
+            .unwrap_or_default()
+    }
+
+    pub fn merge(&mut self, other: &CountMinSketch) -> Result<()> {
+        if (self.width, self.depth) != (other.width, other.depth) {
+            return Err(anyhow!(
+                "sketch size mismatch: {}x{} vs {}x{}",
+                self.depth,
+                self.width,
+                other.depth,
+                other.width
+            ));
+        }
+        for (a, b) in self.table.iter_mut().zip(&other.table) {
+            *a += b;
+        }
+        Ok(())
+    }
+}
+
+/// 精确计数或 count-min sketch
+#[cfg_attr(feature = "serialization", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TermCounts {
+    Exact(HashMap<String, u64>),
+    Sketch(CountMinSketch),
+}
+
+impl TermCounts {
+    pub fn add(&mut self, term: &str, count: u64) {
+        match self {
+            TermCounts::Exact(counts) => match counts.get_mut(term) {
+                Some(value) => *value += count,
+                None => {
+                    counts.insert(term.to_string(), count);
+                }
+            },
+            TermCounts::Sketch(sketch) => sketch.add(term, count),
+        }
+    }
+
+    pub fn get(&self, term: &str) -> u64 {
+        match self {
+            TermCounts::Exact(counts) => counts.get(term).copied().unwrap_or_default(),
+            TermCounts::Sketch(sketch) => sketch.estimate(term),
+        }
+    }
+
+    pub fn merge(&mut self, other: &TermCounts) -> Result<()> {
+        match (self, other) {
+            (TermCounts::Exact(counts), TermCounts::Exact(other)) => {
+                for (term, &count) in other {
+                    *counts.entry(term.clone()).or_default() += count;
+                }
+                Ok(())
+            }
+            (TermCounts::Sketch(sketch), TermCounts::Sketch(other)) => sketch.merge(other),
+            _ => Err(anyhow!("cannot merge exact counts with a sketch")),
+        }
+    }
+}
+
+/// 语料的词频与文档频率
+#[cfg_attr(feature = "serialization", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TermStats {
+    pub documents: u64,
+    pub tokens: u64,
+    pub term_frequency: TermCounts,
+    pub document_frequency: TermCounts,
+    #[cfg_attr(feature = "serialization", serde(skip))]
+    stopwords: Option<Stopwords>,
+}
+
+impl Default for TermStats {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl TermStats {
+    /// 精确计数
+    pub fn new() -> Self {
+        Self {
+            documents: 0,
+            tokens: 0,
+            term_frequency: TermCounts::Exact(HashMap::new()),
+            document_frequency: TermCounts::Exact(HashMap::new()),
+            stopwords: None,
+        }
+    }
+
+    /// 词频与文档频率各用一个 `depth` 行 `width` 列的 count-min sketch
+    pub fn count_min(width: usize, depth: usize) -> Self {
+        Self {
+            term_frequency: TermCounts::Sketch(CountMinSketch::new(width, depth)),
+            document_frequency: TermCounts::Sketch(CountMinSketch::new(width, depth)),
+            ..Self::new()
+        }
+    }
+
+    /// 统计时跳过停用词
+    pub fn stopwords(mut self, stopwords: Stopwords) -> Self {
+        self.stopwords = Some(stopwords);
+        self
+    }
+
+    fn is_term(&self, word: &str) -> bool {
+        match &self.stopwords {
+            Some(stopwords) => !stopwords.is_stopword(word),
+            None => !word.trim().is_empty(),
+        }
+    }
+
+    pub fn add_document<S: AsRef<str>>(&mut self, words: &[S]) {
+        let mut seen = HashSet::new();
+        for term in words.iter().map(AsRef::as_ref) {
+            if self.is_term(term) {
+                self.term_frequency.add(term, 1);
+                self.tokens += 1;
+                seen.insert(term);
+            }
+        }
+        for term in seen {
+            self.document_frequency.add(term, 1);
+        }
+        self.documents += 1;
+    }
+
+    /// 把多个句子作为一个文档，如 [`LtpPipeline::process_document`] 的输出
+    ///
+    /// [`LtpPipeline::process_document`]: crate::LtpPipeline::process_document
+    pub fn add_sentences<'a, I: IntoIterator<Item = &'a Sentence>>(&mut self, sentences: I) {
+        let words: Vec<&str> = sentences
+            .into_iter()
+            .flat_map(|sentence| sentence.words.iter().map(String::as_str))
+            .collect();
+        self.add_document(&words);
+    }
+
+    /// 合并另一部分语料的统计，计数方式与 sketch 的大小必须相同
+    pub fn merge(&mut self, other: &TermStats) -> Result<()> {
+        self.term_frequency.merge(&other.term_frequency)?;
+        self.document_frequency.merge(&other.document_frequency)?;
+        self.documents += other.documents;
+        self.tokens += other.tokens;
+        Ok(())
+    }
+
+    /// 平滑的逆文档频率 `ln((1 + N) / (1 + df)) + 1`
+    pub fn idf(&self, term: &str) -> f64 {
+        let df = self.document_frequency.get(term) as f64;
+        ((1.0 + self.documents as f64) / (1.0 + df)).ln() + 1.0
+    }
+
+    /// 文档的 TF-IDF 向量，词频按文档长度归一化，按权重从大到小排列
+    pub fn tfidf<S: AsRef<str>>(&self, words: &[S]) -> Vec<(String, f64)> {
+        let mut counts: HashMap<&str, usize> = HashMap::new();
+        let mut total = 0;
+        for term in words.iter().map(AsRef::as_ref) {
+            if !self.is_term(term) {
+                continue;
+            }
+            *counts.entry(term).or_default() += 1;
+            total += 1;
+        }
+        let mut vector: Vec<(String, f64)> = counts
+            .into_iter()
+            .map(|(term, count)| {
+                let weight = count as f64 / total as f64 * self.idf(term);
+                (term.to_string(), weight)
+            })
+            .collect();
+        vector.sort_by(|a, b| b.1.total_cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        vector
+    }
+
+    /// 词频最高的 `n` 个词，count-min sketch 不保存词表，返回 `None`
+    pub fn most_common(&self, n: usize) -> Option<Vec<(&str, u64)>> {
+        let TermCounts::Exact(counts) = &self.term_frequency else {
+            return None;
+        };
+        let mut terms: Vec<_> = counts.iter().map(|(t, &c)| (t.as_str(), c)).collect();
+        terms.sort_unstable_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(b.0)));
+        terms.truncate(n);
+        Some(terms)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_term_stats() -> Result<()> {
+        let documents = [
+            vec!["我", "爱", "北京", "天安门", "。"],
+            vec!["北京", "的", "秋天", "。"],
+            vec!["我", "爱", "秋天", "秋天"],
+        ];
+        let mut exact = TermStats::new().stopwords(Stopwords::chinese());
+        let mut sketch = TermStats::count_min(1024, 4).stopwords(Stopwords::chinese());
+        for words in &documents {
+            exact.add_document(words);
+            sketch.add_document(words);
+        }
+        assert_eq!((exact.documents, exact.tokens), (3, 8));
+        assert_eq!(exact.term_frequency.get("秋天"), 3);
+        assert_eq!(exact.document_frequency.get("秋天"), 2);
+        assert_eq!(exact.term_frequency.get("的"), 0);
+        assert_eq!(exact.most_common(1), Some(vec![("秋天", 3)]));
+        assert!(sketch.most_common(1).is_none());
+        // 估计值不会偏小
+        assert!(sketch.term_frequency.get("秋天") >= 3);
+
+        let vector = exact.tfidf(&documents[0]);
+        assert_eq!(vector[0].0, "天安门");
+        assert!(exact.idf("天安门") > exact.idf("北京"));
+
+        let mut other = TermStats::new();
+        other.add_document(&["北京"]);
+        exact.merge(&other)?;
+        assert_eq!(exact.document_frequency.get("北京"), 3);
+        assert!(sketch.merge(&other).is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,118 @@
+//! HuggingFace tokenizers 适配：用分词模型作为预分词器，并接受带偏移的预分词结果
+use crate::perceptron::{
+    CWSDefinition, Perceptron, TraitFeature, TraitParameter, TraitParameterStorage,
+};
+use anyhow::{anyhow, Result};
+use std::sync::Arc;
+use tokenizers::tokenizer::normalizer::Range;
+use tokenizers::{PreTokenizedString, PreTokenizer};
+
+/// 词在原句中的字节偏移，`words` 必须是 `text` 的切片（分词模型的输出即是如此）
+pub fn word_offsets(text: &str, words: &[&str]) -> Vec<(usize, usize)> {
+    let base = text.as_ptr() as usize;
+    words
+        .iter()
+        .map(|word| {
+            let start = word.as_ptr() as usize - base;
+            (start, start + word.len())
+        })
+        .collect()
+}
+
+/// 按字节偏移从原文切出词，用于把外部预分词结果交给词性标注、命名实体识别
+pub fn words_from_offsets<'a>(text: &'a str, offsets: &[(usize, usize)]) -> Result<Vec<&'a str>> {
+    let mut last = 0;
+    offsets
+        .iter()
+        .map(|&(start, end)| {
+            if start < last || start >= end {
+                return Err(anyhow!("invalid or overlapping offsets {}..{}", start, end));
+            }
+            last = end;
+            text.get(start..end).ok_or_else(|| {
+                anyhow!(
+                    "offsets {}..{} out of range or not on a char boundary",
+                    start,
+                    end
+                )
+            })
+        })
+        .collect()
+}
+
+/// 以分词模型为前端的预分词器，可通过 `Tokenizer::with_pre_tokenizer` 接入 BERT 等模型，
+/// 切分后的偏移仍然对应原始文本
+pub struct CWSPreTokenizer<Feature, ParamStorage, Param>
+where
+    Feature: TraitFeature,
+    Param: TraitParameter,
+    ParamStorage: TraitParameterStorage<Param>,
+{
+    model: Arc<Perceptron<CWSDefinition, Feature, ParamStorage, Param>>,
+}
+
+impl<Feature, ParamStorage, Param> CWSPreTokenizer<Feature, ParamStorage, Param>
+where
+    Feature: TraitFeature,
+    Param: TraitParameter,
+    ParamStorage: TraitParameterStorage<Param>,
+{
+    pub fn new(
+        model: impl Into<Arc<Perceptron<CWSDefinition, Feature, ParamStorage, Param>>>,
+    ) -> Self {
+        Self {
+            model: model.into(),
+        }
+    }
+}
+
+impl<Feature, ParamStorage, Param> PreTokenizer for CWSPreTokenizer<Feature, ParamStorage, Param>
+where
+    Feature: TraitFeature,
+    Param: TraitParameter,
+    ParamStorage: TraitParameterStorage<Param>,
+{
+    fn pre_tokenize(&self, pretokenized: &mut PreTokenizedString) -> tokenizers::Result<()> {
+        pretokenized.split(|_, normalized| {
+            let text = normalized.get();
+            if text.is_empty() {
+                return Ok(vec![]);
+            }
+            let words = self.model.predict(text)?;
+            let offsets = word_offsets(text, &words);
+            Ok(offsets
+                .into_iter()
+                .filter_map(|(start, end)| normalized.slice(Range::Normalized(start..end)))
+                .collect::<Vec<_>>())
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::perceptron::cws_test_model;
+    use tokenizers::{OffsetReferential, OffsetType};
+
+    #[test]
+    fn test_pre_tokenize() -> Result<()> {
+        let model = cws_test_model(&[], |i| (i % 5) as f64 - 2.0);
+        let pre_tokenizer = CWSPreTokenizer::new(model);
+
+        let text = "他叫汤姆去拿外衣。";
+        let mut pretokenized = PreTokenizedString::from(text);
+        pre_tokenizer
+            .pre_tokenize(&mut pretokenized)
+            .map_err(|e| anyhow!(e))?;
+        let splits = pretokenized.get_splits(OffsetReferential::Original, OffsetType::Byte);
+        let offsets: Vec<_> = splits.iter().map(|(_, offsets, _)| *offsets).collect();
+
+        let words = words_from_offsets(text, &offsets)?;
+        assert_eq!(words.concat(), text);
+        for ((word, _, _), expected) in splits.iter().zip(&words) {
+            assert_eq!(word, expected);
+        }
+        assert!(words_from_offsets(text, &[(0, 1)]).is_err());
+        Ok(())
+    }
+}